@@ -45,7 +45,7 @@ async fn main() -> Result<(), std::io::Error> {
 
     let app = Route::new().at("/", get(index)).at("/event", get(event));
 
-    Server::new(TcpListener::bind("0.0.0.0:3000"))
+    let report = Server::new(TcpListener::bind("0.0.0.0:3000"))
         .run_with_graceful_shutdown(
             app,
             async move {
@@ -53,5 +53,12 @@ async fn main() -> Result<(), std::io::Error> {
             },
             Some(Duration::from_secs(5)),
         )
-        .await
+        .await?;
+    println!(
+        "closed {} connections forcibly, aborted requests: {:?}, drained in {:?}",
+        report.forced_connections(),
+        report.aborted_requests(),
+        report.drain_duration()
+    );
+    Ok(())
 }
@@ -11,7 +11,7 @@ mod utils;
 
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
-use syn::{parse_macro_input, FnArg, GenericParam, ItemFn, Member, Result};
+use syn::{parse_macro_input, FnArg, GenericParam, ItemFn, LitStr, Member, Result};
 
 /// Wrap an asynchronous function as an `Endpoint`.
 ///
@@ -22,25 +22,36 @@ use syn::{parse_macro_input, FnArg, GenericParam, ItemFn, Member, Result};
 /// async fn example() {
 /// }
 /// ```
+///
+/// With the `auth` feature, `#[handler(permission = "users:write")]` rejects
+/// the requests which are not granted the permission by the `Authorization`
+/// middleware with `403 Forbidden`.
 #[proc_macro_attribute]
 pub fn handler(args: TokenStream, input: TokenStream) -> TokenStream {
     let mut internal = false;
+    let mut permission = None;
 
     let arg_parser = syn::meta::parser(|meta| {
         if meta.path.is_ident("internal") {
             internal = true;
+        } else if meta.path.is_ident("permission") {
+            permission = Some(meta.value()?.parse::<LitStr>()?);
         }
         Ok(())
     });
     parse_macro_input!(args with arg_parser);
 
-    match generate_handler(internal, input) {
+    match generate_handler(internal, permission, input) {
         Ok(stream) => stream,
         Err(err) => err.into_compile_error().into(),
     }
 }
 
-fn generate_handler(internal: bool, input: TokenStream) -> Result<TokenStream> {
+fn generate_handler(
+    internal: bool,
+    permission: Option<LitStr>,
+    input: TokenStream,
+) -> Result<TokenStream> {
     let crate_name = utils::get_crate_name(internal);
     let item_fn = syn::parse::<ItemFn>(input)?;
     let (impl_generics, type_generics, where_clause) = item_fn.sig.generics.split_for_impl();
@@ -95,6 +106,12 @@ fn generate_handler(internal: bool, input: TokenStream) -> Result<TokenStream> {
         quote! { #vis struct #ident; }
     };
 
+    let check_permission = permission.map(|permission| {
+        quote! {
+            #crate_name::auth::check_permission(&req, #permission).await?;
+        }
+    });
+
     let mut extractors = Vec::new();
     let mut args = Vec::new();
     for (idx, input) in item_fn.sig.inputs.clone().into_iter().enumerate() {
@@ -119,6 +136,7 @@ fn generate_handler(internal: bool, input: TokenStream) -> Result<TokenStream> {
             #[allow(unused_mut)]
             async fn call(&self, mut req: #crate_name::Request) -> #crate_name::Result<Self::Output> {
                 let (req, mut body) = req.split();
+                #check_permission
                 #(#extractors)*
                 #item_fn
                 let res = #ident(#(#args),*)#call_await;
@@ -4,7 +4,7 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::service_generator::PoemServiceGenerator;
+use crate::{service_generator::PoemServiceGenerator, transcoding::HttpRules};
 
 #[derive(Debug)]
 pub(crate) struct GrpcConfig {
@@ -15,6 +15,8 @@ pub(crate) struct GrpcConfig {
     pub(crate) build_server: bool,
     pub(crate) client_middlewares: Vec<String>,
     pub(crate) server_middlewares: Vec<String>,
+    pub(crate) json_transcoding: bool,
+    pub(crate) http_rules: Option<HttpRules>,
 }
 
 impl Default for GrpcConfig {
@@ -27,6 +29,8 @@ impl Default for GrpcConfig {
             build_server: true,
             client_middlewares: Vec::new(),
             server_middlewares: Vec::new(),
+            json_transcoding: false,
+            http_rules: None,
         }
     }
 }
@@ -36,6 +40,7 @@ impl Default for GrpcConfig {
 pub struct Config {
     prost_config: prost_build::Config,
     grpc_config: GrpcConfig,
+    file_descriptor_set_path: Option<PathBuf>,
 }
 
 impl Default for Config {
@@ -51,6 +56,7 @@ impl Config {
         Self {
             prost_config: prost_build::Config::default(),
             grpc_config: GrpcConfig::default(),
+            file_descriptor_set_path: None,
         }
     }
 
@@ -289,8 +295,9 @@ impl Config {
     /// When set, the `FileDescriptorSet` generated by `protoc` is written to
     /// the provided filesystem path.
     pub fn file_descriptor_set_path(mut self, path: impl AsRef<Path>) -> Self {
-        self.prost_config
-            .file_descriptor_set_path(PathBuf::from(std::env::var("OUT_DIR").unwrap()).join(path));
+        let path = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join(path);
+        self.prost_config.file_descriptor_set_path(&path);
+        self.file_descriptor_set_path = Some(path);
         self
     }
 
@@ -318,6 +325,31 @@ impl Config {
         self
     }
 
+    /// Generate the HTTP/JSON transcoding for the methods annotated with
+    /// `google.api.http`.
+    ///
+    /// The generated servers implement
+    /// `poem_grpc::transcoding::HttpTranscoding` and can be added with
+    /// `RouteGrpc::add_transcoded_service`, this requires the
+    /// `json-transcoding` feature of `poem-grpc`, and the messages must
+    /// implement `serde::Serialize` and `serde::Deserialize`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut config = poem_grpc_build::Config::new();
+    /// config
+    ///     .type_attribute(
+    ///         ".",
+    ///         "#[derive(serde::Serialize, serde::Deserialize)] #[serde(default)]",
+    ///     )
+    ///     .json_transcoding();
+    /// ```
+    pub fn json_transcoding(mut self) -> Self {
+        self.grpc_config.json_transcoding = true;
+        self
+    }
+
     /// Add an argument to the `protoc` protobuf compilation invocation.
     pub fn protoc_arg(mut self, arg: impl Into<OsString>) -> Self {
         self.prost_config.protoc_arg(arg.into());
@@ -331,10 +363,31 @@ impl Config {
         protos: &[impl AsRef<Path>],
         includes: &[impl AsRef<Path>],
     ) -> Result<()> {
+        if !self.grpc_config.json_transcoding {
+            return self
+                .prost_config
+                .service_generator(Box::new(PoemServiceGenerator {
+                    config: self.grpc_config,
+                }))
+                .compile_protos(protos, includes);
+        }
+
+        // the `google.api.http` options are only kept in the raw descriptors
+        let path = match self.file_descriptor_set_path {
+            Some(path) => path,
+            None => {
+                let path = PathBuf::from(std::env::var("OUT_DIR").unwrap())
+                    .join("poem-grpc-transcoding.bin");
+                self.prost_config.file_descriptor_set_path(&path);
+                path
+            }
+        };
+        let fds = self.prost_config.load_fds(protos, includes)?;
+        self.grpc_config.http_rules = Some(HttpRules::decode(&std::fs::read(path)?)?);
         self.prost_config
             .service_generator(Box::new(PoemServiceGenerator {
                 config: self.grpc_config,
             }))
-            .compile_protos(protos, includes)
+            .compile_fds(fds)
     }
 }
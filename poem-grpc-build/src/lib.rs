@@ -11,6 +11,7 @@ mod client;
 mod config;
 mod server;
 mod service_generator;
+mod transcoding;
 mod utils;
 
 use std::path::Path;
@@ -3,8 +3,9 @@ use prost_build::Service;
 use quote::{format_ident, quote};
 use syn::{Expr, Path, Type};
 
-use crate::{config::GrpcConfig, utils::get_crate_name};
+use crate::{config::GrpcConfig, transcoding::Binding, utils::get_crate_name};
 
+#[derive(Clone, Copy)]
 struct MethodInfo<'a> {
     path: &'a str,
     service_ident: &'a Ident,
@@ -20,6 +21,9 @@ pub(crate) fn generate(config: &GrpcConfig, service: &Service, buf: &mut String)
     let server_ident = format_ident!("{}Server", &service.name);
     let mut trait_methods = Vec::new();
     let mut endpoints = Vec::new();
+    let mut transcoded_proxies = Vec::new();
+    let mut transcoded_routes = Vec::<(String, Vec<TokenStream>)>::new();
+    let mut transcoded_bindings = Vec::<(String, String, &str)>::new();
     let codec_list = config
         .codec_list
         .iter()
@@ -41,6 +45,11 @@ pub(crate) fn generate(config: &GrpcConfig, service: &Service, buf: &mut String)
     } else {
         service.proto_name.clone()
     };
+    let service_full_name = if service.package.is_empty() {
+        format!(".{}", service.proto_name)
+    } else {
+        format!(".{}.{}", service.package, service.proto_name)
+    };
 
     for method in &service.methods {
         let method_ident = format_ident!("{}", &method.name);
@@ -57,6 +66,43 @@ pub(crate) fn generate(config: &GrpcConfig, service: &Service, buf: &mut String)
             output_type: &output_type,
             crate_name: &crate_name,
         };
+        if let Some(rules) = &config.http_rules {
+            let bindings = rules.bindings(&service_full_name, &method.proto_name);
+            if !bindings.is_empty() && (method.client_streaming || method.server_streaming) {
+                println!(
+                    "cargo:warning={service_full_name}/{}: only the unary methods can be transcoded",
+                    method.proto_name
+                );
+            } else if !bindings.is_empty() {
+                let fields = rules.fields(&crate_name, &method.input_proto_type);
+                transcoded_proxies.push(generate_unary_proxy(method_info));
+                for binding in bindings {
+                    if let Some((_, _, other)) =
+                        transcoded_bindings.iter().find(|(http_method, path, _)| {
+                            *http_method == binding.method && *path == binding.path
+                        })
+                    {
+                        panic!(
+                            "{service_full_name}/{}: the HTTP binding `{} {}` conflicts with {service_full_name}/{other}",
+                            method.proto_name, binding.method, binding.path
+                        );
+                    }
+                    transcoded_bindings.push((
+                        binding.method.clone(),
+                        binding.path.clone(),
+                        &method.proto_name,
+                    ));
+                    let endpoint = generate_transcoded_endpoint(method_info, &binding, &fields);
+                    match transcoded_routes
+                        .iter_mut()
+                        .find(|(path, _)| *path == binding.path)
+                    {
+                        Some((_, endpoints)) => endpoints.push(endpoint),
+                        None => transcoded_routes.push((binding.path, vec![endpoint])),
+                    }
+                }
+            }
+        }
 
         match (method.client_streaming, method.server_streaming) {
             (false, false) => {
@@ -92,6 +138,26 @@ pub(crate) fn generate(config: &GrpcConfig, service: &Service, buf: &mut String)
         }
     });
 
+    let http_transcoding = config.json_transcoding.then(|| {
+        let routes = transcoded_routes.iter().map(|(path, endpoints)| {
+            quote! {
+                let route = route.at(#path, ::poem::RouteMethod::new()#(#endpoints)*);
+            }
+        });
+
+        quote! {
+            impl<T: #service_ident> #crate_name::transcoding::HttpTranscoding for #server_ident<T> {
+                #[allow(clippy::redundant_clone)]
+                #[allow(clippy::let_and_return)]
+                fn http_routes(&self, route: ::poem::Route) -> ::poem::Route {
+                    #(#transcoded_proxies)*
+                    #(#routes)*
+                    route
+                }
+            }
+        }
+    });
+
     let token_stream = quote! {
         #[allow(unused_imports)]
         pub trait #service_ident: Send + Sync + 'static {
@@ -170,6 +236,8 @@ pub(crate) fn generate(config: &GrpcConfig, service: &Service, buf: &mut String)
                 ep.boxed()
             }
         }
+
+        #http_transcoding
     };
 
     buf.push_str(&prettyplease::unparse(&syn::parse2(token_stream).unwrap()));
@@ -211,25 +279,17 @@ fn generice_call_with_codec(
     }
 }
 
-fn generate_unary(codec_list: &[Path], method_info: MethodInfo) -> TokenStream {
+fn generate_unary_proxy(method_info: MethodInfo) -> TokenStream {
     let MethodInfo {
-        path,
         service_ident,
         proxy_service_ident,
         method_ident,
         input_type,
         output_type,
         crate_name,
+        ..
     } = method_info;
 
-    let call = generice_call_with_codec(
-        crate_name,
-        codec_list,
-        quote! {
-            #crate_name::server::GrpcServer::new(codec, server.send_compressd, &server.accept_compressed).unary(#proxy_service_ident(server.inner.clone()), req).await
-        },
-    );
-
     quote! {
         #[allow(non_camel_case_types)]
         struct #proxy_service_ident<T>(::std::sync::Arc<T>);
@@ -244,6 +304,73 @@ fn generate_unary(codec_list: &[Path], method_info: MethodInfo) -> TokenStream {
                 self.0.#method_ident(request).await
             }
         }
+    }
+}
+
+fn generate_transcoded_endpoint(
+    method_info: MethodInfo,
+    binding: &Binding,
+    fields: &[TokenStream],
+) -> TokenStream {
+    let MethodInfo {
+        proxy_service_ident,
+        crate_name,
+        ..
+    } = method_info;
+    let method = &binding.method;
+    let path_params = binding
+        .path_params
+        .iter()
+        .map(|(param, field)| quote!((#param, #field)));
+    let body = option_tokens(binding.body.as_deref());
+    let response_body = option_tokens(binding.response_body.as_deref());
+
+    quote! {
+        .method(::poem::http::Method::from_bytes(#method.as_bytes()).unwrap(), ::poem::endpoint::make({
+            let server = self.clone();
+            move |req| {
+                let server = server.clone();
+                async move {
+                    static BINDING: #crate_name::transcoding::Binding = #crate_name::transcoding::Binding {
+                        path_params: &[#(#path_params),*],
+                        body: #body,
+                        response_body: #response_body,
+                        fields: &[#(#fields),*],
+                    };
+                    #crate_name::transcoding::transcode(&BINDING, #proxy_service_ident(server.inner.clone()), req).await
+                }
+            }
+        }))
+    }
+}
+
+fn option_tokens(value: Option<&str>) -> TokenStream {
+    match value {
+        Some(value) => quote!(::std::option::Option::Some(#value)),
+        None => quote!(::std::option::Option::None),
+    }
+}
+
+fn generate_unary(codec_list: &[Path], method_info: MethodInfo) -> TokenStream {
+    let MethodInfo {
+        path,
+        proxy_service_ident,
+        crate_name,
+        ..
+    } = method_info;
+
+    let call = generice_call_with_codec(
+        crate_name,
+        codec_list,
+        quote! {
+            #crate_name::server::GrpcServer::new(codec, server.send_compressd, &server.accept_compressed).unary(#proxy_service_ident(server.inner.clone()), req).await
+        },
+    );
+
+    let proxy = generate_unary_proxy(method_info);
+
+    quote! {
+        #proxy
 
         route = route.at(#path, ::poem::endpoint::make({
             let server = self.clone();
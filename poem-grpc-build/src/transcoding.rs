@@ -0,0 +1,372 @@
+//! Reads the `google.api.http` annotations from the file descriptor set.
+//!
+//! `prost_types` drops the unknown extensions when decoding the descriptors,
+//! so the subset of the descriptors used by the transcoding is decoded again
+//! with the extension fields.
+
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+};
+
+use proc_macro2::TokenStream;
+use prost::Message;
+use quote::quote;
+
+#[derive(Clone, PartialEq, Message)]
+struct FileDescriptorSet {
+    #[prost(message, repeated, tag = "1")]
+    file: Vec<FileDescriptorProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct FileDescriptorProto {
+    #[prost(string, optional, tag = "2")]
+    package: Option<String>,
+    #[prost(message, repeated, tag = "4")]
+    message_type: Vec<DescriptorProto>,
+    #[prost(message, repeated, tag = "6")]
+    service: Vec<ServiceDescriptorProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct DescriptorProto {
+    #[prost(string, optional, tag = "1")]
+    name: Option<String>,
+    #[prost(message, repeated, tag = "2")]
+    field: Vec<FieldDescriptorProto>,
+    #[prost(message, repeated, tag = "3")]
+    nested_type: Vec<DescriptorProto>,
+    #[prost(message, optional, tag = "7")]
+    options: Option<MessageOptions>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct MessageOptions {
+    #[prost(bool, optional, tag = "7")]
+    map_entry: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct FieldDescriptorProto {
+    #[prost(string, optional, tag = "1")]
+    name: Option<String>,
+    #[prost(int32, optional, tag = "4")]
+    label: Option<i32>,
+    #[prost(int32, optional, tag = "5")]
+    r#type: Option<i32>,
+    #[prost(string, optional, tag = "6")]
+    type_name: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ServiceDescriptorProto {
+    #[prost(string, optional, tag = "1")]
+    name: Option<String>,
+    #[prost(message, repeated, tag = "2")]
+    method: Vec<MethodDescriptorProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct MethodDescriptorProto {
+    #[prost(string, optional, tag = "1")]
+    name: Option<String>,
+    #[prost(message, optional, tag = "4")]
+    options: Option<MethodOptions>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct MethodOptions {
+    #[prost(message, optional, tag = "72295728")]
+    http: Option<HttpRule>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct HttpRule {
+    #[prost(oneof = "Pattern", tags = "2, 3, 4, 5, 6, 8")]
+    pattern: Option<Pattern>,
+    #[prost(string, tag = "7")]
+    body: String,
+    #[prost(string, tag = "12")]
+    response_body: String,
+    #[prost(message, repeated, tag = "11")]
+    additional_bindings: Vec<HttpRule>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+enum Pattern {
+    #[prost(string, tag = "2")]
+    Get(String),
+    #[prost(string, tag = "3")]
+    Put(String),
+    #[prost(string, tag = "4")]
+    Post(String),
+    #[prost(string, tag = "5")]
+    Delete(String),
+    #[prost(string, tag = "6")]
+    Patch(String),
+    #[prost(message, tag = "8")]
+    Custom(CustomHttpPattern),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct CustomHttpPattern {
+    #[prost(string, tag = "1")]
+    kind: String,
+    #[prost(string, tag = "2")]
+    path: String,
+}
+
+const LABEL_REPEATED: i32 = 3;
+const TYPE_MESSAGE: i32 = 11;
+const MAX_FIELD_DEPTH: usize = 4;
+
+/// The `google.api.http` annotations of the methods and the message
+/// descriptors of a file descriptor set.
+#[derive(Debug, Default)]
+pub(crate) struct HttpRules {
+    methods: HashMap<String, HttpRule>,
+    messages: HashMap<String, DescriptorProto>,
+}
+
+fn collect_messages(
+    messages: &mut HashMap<String, DescriptorProto>,
+    prefix: &str,
+    descriptors: &[DescriptorProto],
+) {
+    for descriptor in descriptors {
+        let name = format!("{prefix}.{}", descriptor.name());
+        collect_messages(messages, &name, &descriptor.nested_type);
+        messages.insert(name, descriptor.clone());
+    }
+}
+
+/// An HTTP binding of a method.
+pub(crate) struct Binding {
+    /// The HTTP method.
+    pub(crate) method: String,
+    /// The path of the poem route.
+    pub(crate) path: String,
+    /// The route parameters and the field paths they are bound to.
+    pub(crate) path_params: Vec<(String, String)>,
+    pub(crate) body: Option<String>,
+    pub(crate) response_body: Option<String>,
+}
+
+/// Converts a path template to a poem route path, see
+/// <https://github.com/googleapis/googleapis/blob/master/google/api/http.proto>.
+fn convert_template(template: &str) -> std::result::Result<Binding, String> {
+    let unsupported = || format!("unsupported path template `{template}`");
+    let segments = template.strip_prefix('/').ok_or_else(unsupported)?;
+    if segments.contains(':') {
+        return Err(unsupported());
+    }
+
+    let mut path = String::new();
+    let mut path_params = Vec::new();
+    // every variable segment gets a distinct name, including the wildcards
+    // which are not bound to a field
+    let mut vars = 0;
+    let mut segments = segments.split('/').peekable();
+    while let Some(segment) = segments.next() {
+        let name = format!("p{vars}");
+        let is_last = segments.peek().is_none();
+        match segment {
+            "*" => {
+                path.push_str(&format!("/:{name}"));
+                vars += 1;
+            }
+            "**" if is_last => {
+                path.push_str(&format!("/*{name}"));
+                vars += 1;
+            }
+            _ if segment.starts_with('{') && segment.ends_with('}') => {
+                let variable = &segment[1..segment.len() - 1];
+                let (field, pattern) = variable.split_once('=').unwrap_or((variable, "*"));
+                match pattern {
+                    "*" => path.push_str(&format!("/:{name}")),
+                    "**" if is_last => path.push_str(&format!("/*{name}")),
+                    _ => return Err(unsupported()),
+                }
+                path_params.push((name, field.to_string()));
+                vars += 1;
+            }
+            _ if !segment.is_empty()
+                && !segment.contains(['{', '}', '*'])
+                && !segment.starts_with([':']) =>
+            {
+                path.push('/');
+                path.push_str(segment);
+            }
+            _ => return Err(unsupported()),
+        }
+    }
+
+    Ok(Binding {
+        method: String::new(),
+        path,
+        path_params,
+        body: None,
+        response_body: None,
+    })
+}
+
+impl HttpRules {
+    pub(crate) fn decode(data: &[u8]) -> Result<Self> {
+        let fds = FileDescriptorSet::decode(data).map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid FileDescriptorSet: {err}"),
+            )
+        })?;
+        let mut rules = HttpRules::default();
+
+        for file in fds.file {
+            let package = match file.package() {
+                "" => String::new(),
+                package => format!(".{package}"),
+            };
+            collect_messages(&mut rules.messages, &package, &file.message_type);
+            for service in &file.service {
+                for method in &service.method {
+                    if let Some(rule) = method.options.as_ref().and_then(|opts| opts.http.clone()) {
+                        rules.methods.insert(
+                            format!("{package}.{}/{}", service.name(), method.name()),
+                            rule,
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// Returns the HTTP bindings of the method, the unsupported bindings are
+    /// reported as cargo warnings and skipped.
+    ///
+    /// `service` is the full name of the service with a leading `.`.
+    pub(crate) fn bindings(&self, service: &str, method: &str) -> Vec<Binding> {
+        let Some(rule) = self.methods.get(&format!("{service}/{method}")) else {
+            return Vec::new();
+        };
+
+        std::iter::once(rule)
+            .chain(&rule.additional_bindings)
+            .filter_map(|rule| {
+                let (http_method, template) = match rule.pattern.as_ref()? {
+                    Pattern::Get(path) => ("GET", path.as_str()),
+                    Pattern::Put(path) => ("PUT", path.as_str()),
+                    Pattern::Post(path) => ("POST", path.as_str()),
+                    Pattern::Delete(path) => ("DELETE", path.as_str()),
+                    Pattern::Patch(path) => ("PATCH", path.as_str()),
+                    Pattern::Custom(custom) => (custom.kind.as_str(), custom.path.as_str()),
+                };
+                match convert_template(template) {
+                    Ok(binding) => Some(Binding {
+                        method: http_method.to_string(),
+                        body: Some(rule.body.clone()).filter(|body| !body.is_empty()),
+                        response_body: Some(rule.response_body.clone())
+                            .filter(|body| !body.is_empty()),
+                        ..binding
+                    }),
+                    Err(err) => {
+                        println!("cargo:warning={service}/{method}: {err}");
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Generates the scalar fields of the message, which can be bound to the
+    /// path and query parameters.
+    pub(crate) fn fields(&self, crate_name: &TokenStream, message: &str) -> Vec<TokenStream> {
+        let mut fields = Vec::new();
+        self.collect_fields(crate_name, message, "", 0, &mut fields);
+        fields
+    }
+
+    fn collect_fields(
+        &self,
+        crate_name: &TokenStream,
+        message: &str,
+        prefix: &str,
+        depth: usize,
+        fields: &mut Vec<TokenStream>,
+    ) {
+        let Some(descriptor) = self.messages.get(message) else {
+            return;
+        };
+        for field in &descriptor.field {
+            let path = format!("{prefix}{}", field.name());
+            let repeated = field.label() == LABEL_REPEATED;
+            let kind = match field.r#type() {
+                1 | 2 => quote!(Float),
+                3..=7 | 13..=18 => quote!(Integer),
+                8 => quote!(Bool),
+                9 => quote!(String),
+                TYPE_MESSAGE if !repeated && depth < MAX_FIELD_DEPTH => {
+                    let type_name = field.type_name();
+                    let is_map_entry = self
+                        .messages
+                        .get(type_name)
+                        .and_then(|message| message.options.as_ref())
+                        .is_some_and(|options| options.map_entry());
+                    if !is_map_entry {
+                        self.collect_fields(
+                            crate_name,
+                            type_name,
+                            &format!("{path}."),
+                            depth + 1,
+                            fields,
+                        );
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            fields.push(quote! {
+                #crate_name::transcoding::Field {
+                    path: #path,
+                    kind: #crate_name::transcoding::FieldKind::#kind,
+                    repeated: #repeated,
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn convert_template() {
+        let binding = super::convert_template("/v1/{parent}/books/{book.id=*}/**").unwrap();
+        assert_eq!(binding.path, "/v1/:p0/books/:p1/*p2");
+        assert_eq!(
+            binding.path_params,
+            vec![
+                ("p0".to_string(), "parent".to_string()),
+                ("p1".to_string(), "book.id".to_string()),
+            ]
+        );
+
+        let binding = super::convert_template("/v1/files/{name=**}").unwrap();
+        assert_eq!(binding.path, "/v1/files/*p0");
+
+        let binding = super::convert_template("/v1/*/books/{id}/*/{page}").unwrap();
+        assert_eq!(binding.path, "/v1/:p0/books/:p1/:p2/:p3");
+        assert_eq!(
+            binding.path_params,
+            vec![
+                ("p1".to_string(), "id".to_string()),
+                ("p3".to_string(), "page".to_string()),
+            ]
+        );
+
+        assert!(super::convert_template("/v1/{name=shelves/*}").is_err());
+        assert!(super::convert_template("/v1/{name}:cancel").is_err());
+        assert!(super::convert_template("/v1/**/a").is_err());
+        assert!(super::convert_template("v1").is_err());
+    }
+}
@@ -15,6 +15,17 @@ fn main() -> Result<()> {
         .internal()
         .compile(&["proto/test_harness.proto"], &["proto/"])?;
 
+    if std::env::var_os("CARGO_FEATURE_JSON_TRANSCODING").is_some() {
+        poem_grpc_build::Config::new()
+            .internal()
+            .type_attribute(
+                ".test_transcoding",
+                "#[derive(serde::Serialize, serde::Deserialize)] #[serde(default)]",
+            )
+            .json_transcoding()
+            .compile(&["proto/test_transcoding.proto"], &["proto/"])?;
+    }
+
     // example
     poem_grpc_build::Config::new()
         .internal()
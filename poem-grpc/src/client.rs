@@ -1,4 +1,4 @@
-use std::{io::Error as IoError, sync::Arc};
+use std::{io::Error as IoError, sync::Arc, time::Duration};
 
 use bytes::Bytes;
 use futures_util::TryStreamExt;
@@ -21,6 +21,7 @@ use crate::{
     compression::get_incoming_encodings,
     connector::HttpsConnector,
     encoding::{create_decode_response_body, create_encode_request_body},
+    resolver::{Resolver, Upstreams},
     Code, CompressionEncoding, Metadata, Request, Response, Status, Streaming,
 };
 
@@ -33,6 +34,8 @@ pub struct ClientConfig {
     origin: Option<Uri>,
     user_agent: Option<HeaderValue>,
     tls_config: Option<TlsClientConfig>,
+    resolver: Option<Arc<dyn Resolver>>,
+    resolve_interval: Option<Duration>,
 }
 
 impl ClientConfig {
@@ -146,6 +149,41 @@ impl ClientConfigBuilder {
         self
     }
 
+    /// Set a [`Resolver`] to resolve the uris of the GRPC endpoints
+    /// periodically, the uris added by [`ClientConfigBuilder::uri`] are used
+    /// until the first resolution completes.
+    ///
+    /// The uris are resolved in the background when they are older than the
+    /// interval, and the previous uris are kept if the resolution fails or
+    /// returns no uri.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use poem_grpc::{resolver::DnsResolver, ClientConfig};
+    /// let cfg = ClientConfig::builder()
+    ///     .resolver(DnsResolver::new("http://my-service:3000").unwrap())
+    ///     .resolve_interval(Duration::from_secs(10))
+    ///     .build();
+    /// ```
+    pub fn resolver(mut self, resolver: impl Resolver) -> Self {
+        if let Ok(config) = &mut self.config {
+            config.resolver = Some(Arc::new(resolver));
+        }
+        self
+    }
+
+    /// Set the interval to resolve the uris with the [`Resolver`].
+    ///
+    /// Default is `30s`.
+    pub fn resolve_interval(mut self, interval: Duration) -> Self {
+        if let Ok(config) = &mut self.config {
+            config.resolve_interval = Some(interval);
+        }
+        self
+    }
+
     /// Consumes this builder and returns the `ClientConfig`
     pub fn build(self) -> Result<ClientConfig, ClientBuilderError> {
         self.config
@@ -453,25 +491,41 @@ fn create_client_endpoint(
         .http2_only(true)
         .build(HttpsConnector::new(config.tls_config.take()));
 
+    let upstreams = Arc::new(Upstreams::new(
+        std::mem::take(&mut config.uris),
+        config.resolver.take().map(|resolver| {
+            (
+                resolver,
+                config.resolve_interval.unwrap_or(Duration::from_secs(30)),
+            )
+        }),
+    ));
     let config = Arc::new(config);
 
     Arc::new(ToDynEndpoint(poem::endpoint::make(move |request| {
         let config = config.clone();
+        let upstreams = upstreams.clone();
         let cli = cli.clone();
         async move {
             let mut request: hyper::Request<BoxBody> = request.into();
 
-            if config.uris.is_empty() {
+            let uris = upstreams.get().await.map_err(|err| {
+                poem::Error::from_string(
+                    format!("failed to resolve the uris: {err}"),
+                    StatusCode::SERVICE_UNAVAILABLE,
+                )
+            })?;
+            if uris.is_empty() {
                 return Err(poem::Error::from_string(
                     "uris is empty",
                     StatusCode::INTERNAL_SERVER_ERROR,
                 ));
             }
 
-            let base_uri = if config.uris.len() == 1 {
-                &config.uris[0]
+            let base_uri = if uris.len() == 1 {
+                &uris[0]
             } else {
-                &config.uris[fastrand::usize(0..config.uris.len())]
+                &uris[fastrand::usize(0..uris.len())]
             };
             *request.uri_mut() = make_uri(base_uri, request.uri());
 
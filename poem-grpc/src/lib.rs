@@ -19,6 +19,7 @@ pub mod service;
 
 pub mod codec;
 pub mod metadata;
+pub mod resolver;
 
 mod compression;
 mod connector;
@@ -34,6 +35,9 @@ mod status;
 mod streaming;
 #[cfg(test)]
 mod test_harness;
+#[cfg(feature = "json-transcoding")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json-transcoding")))]
+pub mod transcoding;
 
 pub use client::{ClientBuilderError, ClientConfig, ClientConfigBuilder};
 pub use compression::CompressionEncoding;
@@ -0,0 +1,290 @@
+//! Resolvers of the upstream servers of the GRPC clients.
+//!
+//! The resolvers are only used by the GRPC clients generated by
+//! `poem-grpc-build`, `poem` itself has no HTTP proxy whose upstreams could be
+//! resolved with them.
+
+use std::{
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use futures_util::{future::BoxFuture, FutureExt};
+use poem::http::{
+    uri::{InvalidUri, Scheme},
+    Uri,
+};
+
+/// Resolves the uris of the upstream servers of a GRPC client.
+///
+/// The resolver is called periodically by the client, see
+/// [`ClientConfigBuilder::resolver`](crate::ClientConfigBuilder::resolver).
+pub trait Resolver: Send + Sync + 'static {
+    /// Returns the uris of the upstream servers.
+    fn resolve(&self) -> BoxFuture<'_, IoResult<Vec<Uri>>>;
+}
+
+#[derive(Clone)]
+enum Lookup {
+    Host {
+        host: String,
+        port: u16,
+    },
+    #[cfg(feature = "dns-srv")]
+    Srv(String),
+}
+
+/// A [`Resolver`] which looks up the servers with DNS, for example of a
+/// Kubernetes headless service.
+///
+/// # Examples
+///
+/// ```rust
+/// use poem_grpc::{resolver::DnsResolver, ClientConfig};
+///
+/// let resolver = DnsResolver::new("http://my-service.default.svc.cluster.local:50051").unwrap();
+/// let cfg = ClientConfig::builder().resolver(resolver).build();
+/// ```
+#[derive(Clone)]
+pub struct DnsResolver {
+    scheme: Scheme,
+    lookup: Lookup,
+}
+
+impl DnsResolver {
+    /// Create a resolver which looks up the `A` and `AAAA` records of the
+    /// host of the uri, and returns an uri with the scheme and the port of
+    /// the uri for each address.
+    ///
+    /// The default port is `80` for `http` and `443` for `https`.
+    pub fn new(uri: impl TryInto<Uri, Error = InvalidUri>) -> Result<Self, InvalidUri> {
+        let uri = uri.try_into()?;
+        let scheme = uri.scheme().cloned().unwrap_or(Scheme::HTTP);
+        let port = uri
+            .port_u16()
+            .unwrap_or(if scheme == Scheme::HTTPS { 443 } else { 80 });
+        let host = uri.host().unwrap_or_default().to_string();
+        Ok(Self {
+            scheme,
+            lookup: Lookup::Host { host, port },
+        })
+    }
+
+    /// Create a resolver which looks up the `SRV` records of the name, for
+    /// example `_grpc._tcp.my-service.default.svc.cluster.local`, and
+    /// returns an uri with the scheme for each target.
+    #[cfg(feature = "dns-srv")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dns-srv")))]
+    pub fn srv(scheme: Scheme, name: impl Into<String>) -> Self {
+        Self {
+            scheme,
+            lookup: Lookup::Srv(name.into()),
+        }
+    }
+
+    fn make_uri(&self, host: &str, port: u16) -> IoResult<Uri> {
+        let authority = if host.contains(':') {
+            format!("[{host}]:{port}")
+        } else {
+            format!("{host}:{port}")
+        };
+        Uri::builder()
+            .scheme(self.scheme.clone())
+            .authority(authority)
+            .path_and_query("/")
+            .build()
+            .map_err(|err| IoError::new(ErrorKind::InvalidData, err))
+    }
+}
+
+impl Resolver for DnsResolver {
+    fn resolve(&self) -> BoxFuture<'_, IoResult<Vec<Uri>>> {
+        async move {
+            match &self.lookup {
+                Lookup::Host { host, port } => {
+                    let mut uris = Vec::new();
+                    for addr in tokio::net::lookup_host((host.as_str(), *port)).await? {
+                        let uri = self.make_uri(&addr.ip().to_string(), addr.port())?;
+                        if !uris.contains(&uri) {
+                            uris.push(uri);
+                        }
+                    }
+                    Ok(uris)
+                }
+                #[cfg(feature = "dns-srv")]
+                Lookup::Srv(name) => {
+                    let resolver = hickory_resolver::TokioAsyncResolver::tokio_from_system_conf()
+                        .map_err(IoError::other)?;
+                    let lookup = resolver
+                        .srv_lookup(name.as_str())
+                        .await
+                        .map_err(IoError::other)?;
+                    lookup
+                        .iter()
+                        .map(|srv| {
+                            let target = srv.target().to_utf8();
+                            self.make_uri(target.trim_end_matches('.'), srv.port())
+                        })
+                        .collect()
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+/// The upstream servers of a client, which are re-resolved periodically.
+pub(crate) struct Upstreams {
+    uris: RwLock<Arc<[Uri]>>,
+    resolver: Option<(Arc<dyn Resolver>, Duration)>,
+    resolved_at: Mutex<Option<Instant>>,
+    resolving: AtomicBool,
+}
+
+impl Upstreams {
+    pub(crate) fn new(uris: Vec<Uri>, resolver: Option<(Arc<dyn Resolver>, Duration)>) -> Self {
+        Self {
+            uris: RwLock::new(uris.into()),
+            resolver,
+            resolved_at: Mutex::new(None),
+            resolving: AtomicBool::new(false),
+        }
+    }
+
+    fn current(&self) -> Arc<[Uri]> {
+        self.uris
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    /// Resolves the servers, the current servers are kept if the resolution
+    /// fails or returns no server.
+    async fn refresh(&self, resolver: &dyn Resolver) -> IoResult<()> {
+        let res = match resolver.resolve().await {
+            Ok(uris) if uris.is_empty() => {
+                Err(IoError::new(ErrorKind::NotFound, "no upstream is resolved"))
+            }
+            Ok(uris) => {
+                *self.uris.write().unwrap_or_else(|err| err.into_inner()) = uris.into();
+                Ok(())
+            }
+            Err(err) => Err(err),
+        };
+        *self
+            .resolved_at
+            .lock()
+            .unwrap_or_else(|err| err.into_inner()) = Some(Instant::now());
+        self.resolving.store(false, Ordering::Release);
+        res
+    }
+
+    /// Returns the servers, the servers are resolved in the background if
+    /// they are older than the interval, or resolved before returning if
+    /// there is no server.
+    pub(crate) async fn get(self: &Arc<Self>) -> IoResult<Arc<[Uri]>> {
+        let Some((resolver, interval)) = &self.resolver else {
+            return Ok(self.current());
+        };
+
+        let uris = self.current();
+        if uris.is_empty() {
+            self.resolving.store(true, Ordering::Release);
+            self.refresh(&**resolver).await?;
+            return Ok(self.current());
+        }
+
+        let expired = self
+            .resolved_at
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .map_or(true, |resolved_at| resolved_at.elapsed() >= *interval);
+        if expired && !self.resolving.swap(true, Ordering::AcqRel) {
+            let upstreams = self.clone();
+            let resolver = resolver.clone();
+            tokio::spawn(async move {
+                let _ = upstreams.refresh(&*resolver).await;
+            });
+        }
+        Ok(uris)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    struct CountResolver(AtomicUsize);
+
+    impl Resolver for CountResolver {
+        fn resolve(&self) -> BoxFuture<'_, IoResult<Vec<Uri>>> {
+            async move {
+                match self.0.fetch_add(1, Ordering::Relaxed) {
+                    0 => Ok(vec![Uri::from_static("http://10.0.0.1:3000")]),
+                    1 => Err(IoError::other("failed")),
+                    2 => Ok(vec![]),
+                    _ => Ok(vec![
+                        Uri::from_static("http://10.0.0.2:3000"),
+                        Uri::from_static("http://10.0.0.3:3000"),
+                    ]),
+                }
+            }
+            .boxed()
+        }
+    }
+
+    async fn next_uris(upstreams: &Arc<Upstreams>) -> Arc<[Uri]> {
+        upstreams.get().await.unwrap();
+        while upstreams.resolving.load(Ordering::Acquire) {
+            tokio::task::yield_now().await;
+        }
+        upstreams.current()
+    }
+
+    #[tokio::test]
+    async fn refresh() {
+        let resolver = Arc::new(CountResolver(AtomicUsize::new(0)));
+        let upstreams = Arc::new(Upstreams::new(vec![], Some((resolver, Duration::ZERO))));
+
+        let uris = upstreams.get().await.unwrap();
+        assert_eq!(&*uris, [Uri::from_static("http://10.0.0.1:3000")]);
+
+        // the servers are kept on the errors and the empty results
+        let uris = next_uris(&upstreams).await;
+        assert_eq!(&*uris, [Uri::from_static("http://10.0.0.1:3000")]);
+        let uris = next_uris(&upstreams).await;
+        assert_eq!(&*uris, [Uri::from_static("http://10.0.0.1:3000")]);
+
+        let uris = next_uris(&upstreams).await;
+        assert_eq!(
+            &*uris,
+            [
+                Uri::from_static("http://10.0.0.2:3000"),
+                Uri::from_static("http://10.0.0.3:3000")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn dns_resolver() {
+        let resolver = DnsResolver::new("http://localhost:3000").unwrap();
+        let uris = resolver.resolve().await.unwrap();
+        assert!(!uris.is_empty());
+        for uri in uris {
+            assert_eq!(uri.scheme(), Some(&Scheme::HTTP));
+            assert_eq!(uri.port_u16(), Some(3000));
+        }
+
+        let resolver = DnsResolver::new("https://127.0.0.1").unwrap();
+        assert_eq!(
+            resolver.resolve().await.unwrap(),
+            vec![Uri::from_static("https://127.0.0.1:443/")]
+        );
+    }
+}
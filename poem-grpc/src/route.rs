@@ -22,6 +22,20 @@ impl RouteGrpc {
         self.route = self.route.nest(format!("/{}", S::NAME), service);
         self
     }
+
+    /// Add a GRPC service, and the HTTP routes of the methods with the
+    /// `google.api.http` annotations
+    #[cfg(feature = "json-transcoding")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json-transcoding")))]
+    pub fn add_transcoded_service<S>(mut self, service: S) -> Self
+    where
+        S: IntoEndpoint<Endpoint = BoxEndpoint<'static, Response>>
+            + Service
+            + crate::transcoding::HttpTranscoding,
+    {
+        self.route = service.http_routes(self.route);
+        self.add_service(service)
+    }
 }
 
 impl IntoEndpoint for RouteGrpc {
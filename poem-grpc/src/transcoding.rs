@@ -0,0 +1,440 @@
+//! HTTP/JSON to GRPC transcoding.
+//!
+//! Enable it with
+//! [`poem_grpc_build::Config::json_transcoding`](https://docs.rs/poem-grpc-build/latest/poem_grpc_build/struct.Config.html#method.json_transcoding),
+//! then the generated servers implement [`HttpTranscoding`], and the unary
+//! methods with the `google.api.http` annotations can also be called with
+//! HTTP/JSON requests:
+//!
+//! ```protobuf
+//! import "google/api/annotations.proto";
+//!
+//! service Greeter {
+//!   rpc SayHello(HelloRequest) returns (HelloReply) {
+//!     option (google.api.http) = {
+//!       post: "/v1/greeter/{name}"
+//!       body: "*"
+//!     };
+//!   }
+//! }
+//! ```
+//!
+//! ```ignore
+//! let route = RouteGrpc::new().add_transcoded_service(GreeterServer::new(GreeterService));
+//! ```
+//!
+//! The messages are converted with [`serde`] using the protobuf field names,
+//! so they must implement `serde::Serialize` and `serde::Deserialize`, and
+//! should be annotated with `#[serde(default)]` so that the fields missing in
+//! the request are set to their default values.
+
+use poem::{
+    http::{header, StatusCode},
+    Request, Response, Route,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    service::UnaryService, Code, Metadata, Request as GrpcRequest, Response as GrpcResponse, Status,
+};
+
+/// Represents a GRPC service that can be called with HTTP/JSON requests.
+pub trait HttpTranscoding {
+    /// Adds the HTTP routes of the methods to the route.
+    fn http_routes(&self, route: Route) -> Route;
+}
+
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FieldKind {
+    String,
+    Integer,
+    Float,
+    Bool,
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct Field {
+    pub path: &'static str,
+    pub kind: FieldKind,
+    pub repeated: bool,
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct Binding {
+    pub path_params: &'static [(&'static str, &'static str)],
+    pub body: Option<&'static str>,
+    pub response_body: Option<&'static str>,
+    pub fields: &'static [Field],
+}
+
+fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::Cancelled => StatusCode::from_u16(499).unwrap(),
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+            StatusCode::BAD_REQUEST
+        }
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::Unknown | Code::Internal | Code::DataLoss | Code::Other(_) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+fn json_response(status: StatusCode, value: &Value) -> Response {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(value.to_string())
+}
+
+fn status_response(status: Status) -> Response {
+    let mut resp = json_response(
+        http_status(status.code()),
+        &serde_json::json!({
+            "code": status.code().as_u16(),
+            "message": status.message().unwrap_or_default(),
+            "details": [],
+        }),
+    );
+    resp.headers_mut().extend(status.metadata().headers.clone());
+    resp
+}
+
+fn invalid_argument(message: impl std::fmt::Display) -> Response {
+    status_response(Status::new(Code::InvalidArgument).with_message(message))
+}
+
+fn convert_value(field: &Field, value: &str) -> Result<Value, String> {
+    let invalid = || format!("invalid value `{value}` for field `{}`", field.path);
+    Ok(match field.kind {
+        FieldKind::String => Value::String(value.to_string()),
+        FieldKind::Integer => value
+            .parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| value.parse::<u64>().map(Value::from))
+            .map_err(|_| invalid())?,
+        FieldKind::Float => value
+            .parse::<f64>()
+            .ok()
+            .and_then(|value| serde_json::Number::from_f64(value).map(Value::Number))
+            .ok_or_else(invalid)?,
+        FieldKind::Bool => Value::Bool(value.parse().map_err(|_| invalid())?),
+    })
+}
+
+fn set_field(message: &mut Value, path: &str, value: Value, append: bool) -> Result<(), String> {
+    let mut target = message;
+    for name in path.split('.') {
+        if target.is_null() {
+            *target = Value::Object(Map::new());
+        }
+        target = target
+            .as_object_mut()
+            .ok_or_else(|| format!("field `{path}` is not a message"))?
+            .entry(name)
+            .or_insert(Value::Null);
+    }
+    match target {
+        Value::Array(values) if append => values.push(value),
+        _ if append => *target = Value::Array(vec![value]),
+        _ => *target = value,
+    }
+    Ok(())
+}
+
+fn build_message<'a>(
+    binding: &Binding,
+    path_param: impl Fn(&str) -> Option<&'a str>,
+    query: Option<&str>,
+    body: &[u8],
+) -> Result<Value, String> {
+    let body = if body.is_empty() {
+        Value::Object(Map::new())
+    } else {
+        serde_json::from_slice(body).map_err(|err| err.to_string())?
+    };
+    let mut message = match binding.body {
+        Some("*") => body,
+        Some(path) => {
+            let mut message = Value::Object(Map::new());
+            set_field(&mut message, path, body, false)?;
+            message
+        }
+        None => Value::Object(Map::new()),
+    };
+
+    let find_field = |path: &str| binding.fields.iter().find(|field| field.path == path);
+    for (param, path) in binding.path_params {
+        let value = path_param(param).ok_or_else(|| format!("missing path parameter `{path}`"))?;
+        let value = match find_field(path) {
+            Some(field) => convert_value(field, value)?,
+            None => Value::String(value.to_string()),
+        };
+        set_field(&mut message, path, value, false)?;
+    }
+
+    if binding.body != Some("*") {
+        let query: Vec<(String, String)> = query
+            .map(serde_urlencoded::from_str)
+            .transpose()
+            .map_err(|err| err.to_string())?
+            .unwrap_or_default();
+        for (name, value) in query {
+            if let Some(field) = find_field(&name) {
+                let value = convert_value(field, &value)?;
+                set_field(&mut message, &name, value, field.repeated)?;
+            }
+        }
+    }
+
+    Ok(message)
+}
+
+#[doc(hidden)]
+pub async fn transcode<S, I>(binding: &Binding, service: S, req: Request) -> Response
+where
+    S: UnaryService<I>,
+    S::Response: Serialize,
+    I: DeserializeOwned,
+{
+    let (req, mut body) = req.split();
+    let body = match body.take() {
+        Ok(body) => match body.into_bytes().await {
+            Ok(body) => body,
+            Err(err) => return invalid_argument(err),
+        },
+        Err(err) => return invalid_argument(err),
+    };
+
+    let message = match build_message(
+        binding,
+        |name| req.raw_path_param(name),
+        req.uri().query(),
+        &body,
+    )
+    .and_then(|message| serde_json::from_value::<I>(message).map_err(|err| err.to_string()))
+    {
+        Ok(message) => message,
+        Err(err) => return invalid_argument(err),
+    };
+
+    let (parts, _) = req.into_parts();
+    let res = service
+        .call(GrpcRequest {
+            metadata: Metadata {
+                headers: parts.headers,
+            },
+            message,
+            extensions: parts.extensions,
+        })
+        .await;
+
+    match res {
+        Ok(GrpcResponse { metadata, message }) => {
+            let mut value = match serde_json::to_value(message) {
+                Ok(value) => value,
+                Err(err) => return status_response(Status::from_std_error(err)),
+            };
+            if let Some(path) = binding.response_body {
+                for name in path.split('.') {
+                    value = value.get_mut(name).map(Value::take).unwrap_or_default();
+                }
+            }
+            let mut resp = json_response(StatusCode::OK, &value);
+            resp.headers_mut().extend(metadata.headers);
+            resp
+        }
+        Err(status) => status_response(status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use poem::{http::Method, Endpoint, IntoEndpoint};
+    use serde_json::json;
+
+    use super::*;
+    use crate::RouteGrpc;
+
+    #[allow(unreachable_pub)]
+    mod proto {
+        include!(concat!(env!("OUT_DIR"), "/test_transcoding.rs"));
+    }
+
+    use proto::{
+        Book, CreateBookRequest, CreateBookResponse, GetBookRequest, Library, LibraryServer,
+    };
+
+    struct LibraryService;
+
+    impl Library for LibraryService {
+        async fn get_book(
+            &self,
+            req: GrpcRequest<GetBookRequest>,
+        ) -> Result<GrpcResponse<Book>, Status> {
+            match req.id {
+                1 => Ok(GrpcResponse::new(Book {
+                    id: 1,
+                    title: if req.verbose { "Poem (1st)" } else { "Poem" }.to_string(),
+                    tags: vec![],
+                })),
+                _ => Err(Status::new(Code::NotFound).with_message("book not found")),
+            }
+        }
+
+        async fn create_book(
+            &self,
+            req: GrpcRequest<CreateBookRequest>,
+        ) -> Result<GrpcResponse<CreateBookResponse>, Status> {
+            let req = req.into_inner();
+            Ok(GrpcResponse::new(CreateBookResponse {
+                book: req.book.map(|book| Book {
+                    title: format!("{}/{}", req.shelf, book.title),
+                    ..book
+                }),
+            }))
+        }
+    }
+
+    const FIELDS: &[Field] = &[
+        Field {
+            path: "id",
+            kind: FieldKind::Integer,
+            repeated: false,
+        },
+        Field {
+            path: "user.name",
+            kind: FieldKind::String,
+            repeated: false,
+        },
+        Field {
+            path: "tags",
+            kind: FieldKind::String,
+            repeated: true,
+        },
+        Field {
+            path: "active",
+            kind: FieldKind::Bool,
+            repeated: false,
+        },
+    ];
+
+    #[test]
+    fn build_message() {
+        let binding = Binding {
+            path_params: &[("p0", "id")],
+            body: Some("user"),
+            response_body: None,
+            fields: FIELDS,
+        };
+        let query = Some("tags=a&tags=b&active=true&unknown=1");
+        assert_eq!(
+            super::build_message(
+                &binding,
+                |name| (name == "p0").then_some("42"),
+                query,
+                br#"{"name": "a"}"#
+            )
+            .unwrap(),
+            json!({
+                "id": 42,
+                "user": { "name": "a" },
+                "tags": ["a", "b"],
+                "active": true,
+            })
+        );
+
+        assert_eq!(
+            super::build_message(&binding, |_| Some("x"), None, b"").unwrap_err(),
+            "invalid value `x` for field `id`"
+        );
+    }
+
+    #[test]
+    fn status() {
+        let resp = status_response(Status::new(Code::NotFound).with_message("not found"));
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(http_status(Code::Cancelled).as_u16(), 499);
+    }
+
+    #[tokio::test]
+    async fn transcode() {
+        let ep = RouteGrpc::new()
+            .add_transcoded_service(LibraryServer::new(LibraryService))
+            .into_endpoint();
+        let call = |req: Request| {
+            let ep = &ep;
+            async move {
+                let resp = ep.get_response(req).await;
+                let status = resp.status();
+                let body = resp.into_body().into_json::<Value>().await.unwrap();
+                (status, body)
+            }
+        };
+
+        assert_eq!(
+            call(
+                Request::builder()
+                    .uri_str("/v1/books/1?verbose=true")
+                    .finish()
+            )
+            .await,
+            (
+                StatusCode::OK,
+                json!({"id": 1, "title": "Poem (1st)", "tags": []})
+            )
+        );
+        assert_eq!(
+            call(Request::builder().uri_str("/v1/books/2").finish()).await,
+            (
+                StatusCode::NOT_FOUND,
+                json!({"code": 5, "message": "book not found", "details": []})
+            )
+        );
+        assert_eq!(
+            call(Request::builder().uri_str("/v1/books/abc").finish())
+                .await
+                .0,
+            StatusCode::BAD_REQUEST
+        );
+
+        assert_eq!(
+            call(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri_str("/v1/shelves/rust/books")
+                    .body(r#"{"id": 3, "title": "Poem", "tags": ["web"]}"#)
+            )
+            .await,
+            (
+                StatusCode::OK,
+                json!({"id": 3, "title": "rust/Poem", "tags": ["web"]})
+            )
+        );
+        assert_eq!(
+            call(
+                Request::builder()
+                    .method(Method::PUT)
+                    .uri_str("/v1/shelves/rust/books")
+                    .body(r#"{"title": "Poem"}"#)
+            )
+            .await,
+            (
+                StatusCode::OK,
+                json!({"book": {"id": 0, "title": "rust/Poem", "tags": []}})
+            )
+        );
+    }
+}
@@ -9,12 +9,13 @@ use syn::{
 use crate::{
     common_args::{
         APIMethod, CodeSample, DefaultValue, ExampleValue, ExternalDocument, ExtraHeader,
+        ParamStyle,
     },
     error::GeneratorResult,
     utils::{
         convert_oai_path, get_crate_name, get_description, get_summary_and_description,
         optional_literal, optional_literal_string, parse_oai_attrs, remove_description,
-        remove_oai_attrs, RemoveLifetime,
+        remove_oai_attrs, OaiPath, RemoveLifetime,
     },
     validators::Validators,
 };
@@ -58,6 +59,17 @@ struct APIOperation {
     code_samples: Vec<CodeSample>,
     #[darling(default)]
     hidden: bool,
+    #[darling(default)]
+    cache: Option<CacheArgs>,
+}
+
+#[derive(FromMeta)]
+struct CacheArgs {
+    max_age: u64,
+    #[darling(default)]
+    private: bool,
+    #[darling(default, multiple)]
+    vary: Vec<String>,
 }
 
 #[derive(FromMeta, Default)]
@@ -74,6 +86,8 @@ struct APIOperationParam {
     #[darling(default)]
     validator: Option<Validators>,
     #[darling(default)]
+    style: Option<ParamStyle>,
+    #[darling(default)]
     explode: Option<bool>,
 
     // for oauth
@@ -181,6 +195,7 @@ fn generate_operation(
         actual_type,
         code_samples,
         hidden,
+        cache,
     } = args;
     if methods.is_empty() {
         return Err(Error::new_spanned(
@@ -195,7 +210,12 @@ fn generate_operation(
     let description = optional_literal(&description);
     let tags = api_args.common_tags.iter().chain(&tags);
     let prefix_path = &api_args.prefix_path;
-    let (oai_path, new_path) = convert_oai_path(&path)?;
+    let OaiPath {
+        oai_path,
+        new_path,
+        constraints,
+        vars: path_vars,
+    } = convert_oai_path(&path)?;
     let oai_path = prefix_path
         .as_ref()
         .map(|prefix| quote! { #crate_name::__private::join_path(#prefix, #oai_path) })
@@ -282,6 +302,14 @@ fn generate_operation(
             .unwrap_or_else(|| arg_ident.unraw().to_string());
         use_args.push(pname.clone());
 
+        if operation_param.default.is_some() && path_vars.contains(&param_name) {
+            return Err(Error::new_spanned(
+                &arg_ident,
+                "Path parameters are always required, the `default` attribute cannot be used.",
+            )
+            .into());
+        }
+
         if !hidden {
             // register arg type
             ctx.register_items.push(quote! {
@@ -297,6 +325,9 @@ fn generate_operation(
             Some(DefaultValue::Function(func_name)) => {
                 quote!(::std::option::Option::Some(#func_name))
             }
+            Some(DefaultValue::Value(value)) => {
+                quote!(::std::option::Option::Some(|| -> <#arg_ty as #crate_name::ApiExtractor>::ParamType { #value }))
+            }
             None => quote!(::std::option::Option::None),
         };
         let has_default = operation_param.default.is_some();
@@ -307,6 +338,12 @@ fn generate_operation(
             Some(DefaultValue::Function(func_name)) => {
                 quote!(#crate_name::types::ToJSON::to_json(&#func_name()))
             }
+            Some(DefaultValue::Value(value)) => {
+                quote!(#crate_name::types::ToJSON::to_json(&{
+                    let value: <#arg_ty as #crate_name::ApiExtractor>::ParamType = #value;
+                    value
+                }))
+            }
             None => quote!(::std::option::Option::None),
         };
 
@@ -344,14 +381,40 @@ fn generate_operation(
         }).unwrap_or_default();
         let validators_update_meta = validator.create_update_meta(crate_name)?;
 
+        // pattern of the constraint in the path
+        let constraint_pattern = constraints
+            .iter()
+            .find(|(name, _)| *name == param_name)
+            .map(|(_, constraint)| {
+                quote! {
+                    if <#arg_ty as #crate_name::ApiExtractor>::param_in() == ::std::option::Option::Some(#crate_name::registry::MetaParamIn::Path) {
+                        schema.pattern = ::std::option::Option::Some(::std::format!(
+                            "^(?:{})$",
+                            #crate_name::__private::poem::Route::constraint_regex(#constraint),
+                        ));
+                    }
+                }
+            });
+
         // do extract
-        let explode = operation_param.explode.unwrap_or(true);
+        let style = operation_param.style;
+        let explode = operation_param
+            .explode
+            .unwrap_or_else(|| style.map_or(true, ParamStyle::default_explode));
+        let style = match style {
+            Some(style) => {
+                let style = style.to_meta(crate_name);
+                quote!(::std::option::Option::Some(#style))
+            }
+            None => quote!(::std::option::Option::None),
+        };
 
         parse_args.push(quote! {
             let mut param_opts = #crate_name::ExtractParamOptions {
                 name: #param_name,
                 default_value: #default_value,
                 example_value: #example_value,
+                style: #style,
                 explode: #explode,
             };
 
@@ -378,6 +441,7 @@ fn generate_operation(
                     let mut schema = #crate_name::registry::MetaSchema::ANY;
                     schema.default = #param_meta_default;
                     schema.example = #param_meta_example;
+                    #constraint_pattern
                     #validators_update_meta
                     schema
                 };
@@ -389,7 +453,9 @@ fn generate_operation(
                     description: #param_desc,
                     required: <#arg_ty as #crate_name::ApiExtractor>::PARAM_IS_REQUIRED && !#has_default,
                     deprecated: #deprecated,
+                    style: #style,
                     explode: #explode,
+                    content_type: <#arg_ty as #crate_name::ApiExtractor>::param_content_type(),
                 };
                 params.push(meta_param);
             }
@@ -438,6 +504,39 @@ fn generate_operation(
         ),
         None => quote!(),
     };
+    let cache_control = cache.as_ref().map(
+        |CacheArgs {
+             max_age,
+             private,
+             vary,
+         }| {
+            quote! {
+                #crate_name::registry::MetaCacheControl {
+                    max_age: #max_age,
+                    private: #private,
+                    vary: ::std::vec![#(#vary),*],
+                }
+            }
+        },
+    );
+    let cache_meta = match &cache_control {
+        Some(cache_control) => quote!(::std::option::Option::Some(#cache_control)),
+        None => quote!(::std::option::Option::None),
+    };
+    let (clone_cache, clone_cache_inner, apply_cache) = match &cache_control {
+        Some(cache_control) => (
+            quote! {
+                let cache = ::std::sync::Arc::new(#cache_control);
+            },
+            quote! {
+                let cache = ::std::clone::Clone::clone(&cache);
+            },
+            quote! {
+                let resp = #crate_name::__private::apply_cache_control(&request, resp, &cache).await;
+            },
+        ),
+        None => (quote!(), quote!(), quote!()),
+    };
 
     for method in &methods {
         let http_method = method.to_http_method();
@@ -459,8 +558,10 @@ fn generate_operation(
                 .or_default()
                 .insert(#crate_name::__private::poem::http::Method::#http_method, {
                     let api_obj = ::std::clone::Clone::clone(&api_obj);
+                    #clone_cache
                     let ep = #crate_name::__private::poem::endpoint::make(move |request| {
                         let api_obj = ::std::clone::Clone::clone(&api_obj);
+                        #clone_cache_inner
                         async move {
                             let (request, mut body) = request.split();
                             #(#parse_args)*
@@ -469,6 +570,7 @@ fn generate_operation(
                             match ::std::result::Result::map(res, #crate_name::__private::poem::IntoResponse::into_response) {
                                 ::std::result::Result::Ok(mut resp) => {
                                     #update_content_type
+                                    #apply_cache
                                     ::std::result::Result::Ok(resp)
                                 }
                                 ::std::result::Result::Err(err) => ::std::result::Result::Err(err),
@@ -516,7 +618,9 @@ fn generate_operation(
                 description: #description,
                 required: <#ty as #crate_name::types::Type>::IS_REQUIRED,
                 deprecated: #deprecated,
+                style: ::std::option::Option::None,
                 explode: true,
+                content_type: ::std::option::Option::None,
             });
         });
     }
@@ -608,6 +712,7 @@ fn generate_operation(
                     },
                     operation_id: #operation_id,
                     code_samples: ::std::vec![#(#code_samples),*],
+                    cache: #cache_meta,
                 }
             };
             ctx.operations.push((oai_path.clone(), meta_operation));
@@ -1,7 +1,7 @@
 use darling::{util::SpannedValue, FromMeta};
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{Lit, Path};
+use syn::{Expr, ExprLit, ExprUnary, Lit, Path, UnOp};
 
 #[derive(Debug, Copy, Clone, FromMeta)]
 #[allow(clippy::enum_variant_names)]
@@ -136,10 +136,53 @@ pub(crate) enum ParamIn {
     Cookie,
 }
 
+#[derive(Debug, Copy, Clone, FromMeta, Eq, PartialEq)]
+pub(crate) enum ParamStyle {
+    #[darling(rename = "matrix")]
+    Matrix,
+    #[darling(rename = "label")]
+    Label,
+    #[darling(rename = "form")]
+    Form,
+    #[darling(rename = "simple")]
+    Simple,
+    #[darling(rename = "spaceDelimited")]
+    SpaceDelimited,
+    #[darling(rename = "pipeDelimited")]
+    PipeDelimited,
+    #[darling(rename = "deepObject")]
+    DeepObject,
+}
+
+impl ParamStyle {
+    /// Returns the default value of `explode` for this style.
+    pub(crate) fn default_explode(self) -> bool {
+        matches!(self, ParamStyle::Form | ParamStyle::DeepObject)
+    }
+
+    pub(crate) fn to_meta(self, crate_name: &TokenStream) -> TokenStream {
+        match self {
+            ParamStyle::Matrix => quote!(#crate_name::registry::MetaParamStyle::Matrix),
+            ParamStyle::Label => quote!(#crate_name::registry::MetaParamStyle::Label),
+            ParamStyle::Form => quote!(#crate_name::registry::MetaParamStyle::Form),
+            ParamStyle::Simple => quote!(#crate_name::registry::MetaParamStyle::Simple),
+            ParamStyle::SpaceDelimited => {
+                quote!(#crate_name::registry::MetaParamStyle::SpaceDelimited)
+            }
+            ParamStyle::PipeDelimited => {
+                quote!(#crate_name::registry::MetaParamStyle::PipeDelimited)
+            }
+            ParamStyle::DeepObject => quote!(#crate_name::registry::MetaParamStyle::DeepObject),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) enum DefaultValue {
     Default,
     Function(Path),
+    /// A numeric or boolean literal, e.g. `default = 10`.
+    Value(Expr),
 }
 
 impl FromMeta for DefaultValue {
@@ -150,9 +193,38 @@ impl FromMeta for DefaultValue {
     fn from_value(value: &Lit) -> darling::Result<Self> {
         match value {
             Lit::Str(str) => Ok(DefaultValue::Function(syn::parse_str(&str.value())?)),
+            Lit::Int(_) | Lit::Float(_) | Lit::Bool(_) => {
+                Ok(DefaultValue::Value(Expr::Lit(ExprLit {
+                    attrs: Vec::new(),
+                    lit: value.clone(),
+                })))
+            }
             _ => Err(darling::Error::unexpected_lit_type(value).with_span(value)),
         }
     }
+
+    fn from_expr(expr: &Expr) -> darling::Result<Self> {
+        match expr {
+            Expr::Lit(lit) => Self::from_value(&lit.lit),
+            Expr::Group(group) => Self::from_expr(&group.expr),
+            // negative numbers, e.g. `default = -1`
+            Expr::Unary(ExprUnary {
+                op: UnOp::Neg(_),
+                expr: inner,
+                ..
+            }) if matches!(
+                &**inner,
+                Expr::Lit(ExprLit {
+                    lit: Lit::Int(_) | Lit::Float(_),
+                    ..
+                })
+            ) =>
+            {
+                Ok(DefaultValue::Value(expr.clone()))
+            }
+            _ => Err(darling::Error::unexpected_expr_type(expr).with_span(expr)),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -120,6 +120,10 @@ pub(crate) fn generate(args: DeriveInput) -> GeneratorResult<TokenStream> {
                         quote!(<#field_ty as ::std::default::Default>::default())
                     }
                     DefaultValue::Function(func_name) => quote!(#func_name()),
+                    DefaultValue::Value(value) => quote!({
+                        let value: #field_ty = #value;
+                        value
+                    }),
                 };
 
                 deserialize_none.push(quote! {
@@ -159,6 +163,12 @@ pub(crate) fn generate(args: DeriveInput) -> GeneratorResult<TokenStream> {
             Some(DefaultValue::Function(func_name)) => {
                 quote!(#crate_name::types::ToJSON::to_json(&#func_name()))
             }
+            Some(DefaultValue::Value(value)) => {
+                quote!(#crate_name::types::ToJSON::to_json(&{
+                    let value: #field_ty = #value;
+                    value
+                }))
+            }
             None => quote!(::std::option::Option::None),
         };
 
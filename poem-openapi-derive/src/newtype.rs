@@ -116,6 +116,8 @@ pub(crate) fn generate(args: DeriveInput) -> GeneratorResult<TokenStream> {
     let from_parameter = if args.from_parameter {
         Some(quote! {
             impl #impl_generics #crate_name::types::ParseFromParameter for #ident #ty_generics #where_clause {
+                const CONTENT_TYPE: ::std::option::Option<&'static str> = <#inner_ty as #crate_name::types::ParseFromParameter>::CONTENT_TYPE;
+
                 fn parse_from_parameter(value: &str) -> #crate_name::types::ParseResult<Self> {
                     let value = ::std::result::Result::map_err(<#inner_ty as #crate_name::types::ParseFromParameter>::parse_from_parameter(value), poem_openapi::types::ParseError::propagate)?;
                     ::std::result::Result::Ok(#ident(value))
@@ -127,6 +129,21 @@ pub(crate) fn generate(args: DeriveInput) -> GeneratorResult<TokenStream> {
                     let value = ::std::result::Result::map_err(<#inner_ty as #crate_name::types::ParseFromParameter>::parse_from_parameters(iter), poem_openapi::types::ParseError::propagate)?;
                     ::std::result::Result::Ok(#ident(value))
                 }
+
+                fn parse_from_delimited(value: &str, delimiter: char) -> #crate_name::types::ParseResult<Self> {
+                    let value = ::std::result::Result::map_err(<#inner_ty as #crate_name::types::ParseFromParameter>::parse_from_delimited(value, delimiter), poem_openapi::types::ParseError::propagate)?;
+                    ::std::result::Result::Ok(#ident(value))
+                }
+
+                fn parse_from_object_parameters<I, K, V>(iter: I) -> #crate_name::types::ParseResult<Self>
+                where
+                    I: ::std::iter::IntoIterator<Item = (K, V)>,
+                    K: ::std::convert::AsRef<str>,
+                    V: ::std::convert::AsRef<str>,
+                {
+                    let value = ::std::result::Result::map_err(<#inner_ty as #crate_name::types::ParseFromParameter>::parse_from_object_parameters(iter), poem_openapi::types::ParseError::propagate)?;
+                    ::std::result::Result::Ok(#ident(value))
+                }
             }
         })
     } else {
@@ -71,6 +71,8 @@ struct ObjectArgs {
     #[darling(default)]
     deny_unknown_fields: bool,
     #[darling(default)]
+    ignore_read_only_fields: bool,
+    #[darling(default)]
     example: bool,
     #[darling(default)]
     external_docs: Option<ExternalDocument>,
@@ -97,6 +99,14 @@ pub(crate) fn generate(args: DeriveInput) -> GeneratorResult<TokenStream> {
             );
         }
     };
+    if let Some(DefaultValue::Value(value)) = &args.default {
+        return Err(Error::new_spanned(
+            value,
+            "The default value of an object must be `default` or a function.",
+        )
+        .into());
+    }
+
     let oai_typename = args.rename.clone().unwrap_or_else(|| ident.to_string());
     let description = get_description(&args.attrs)?;
     let mut deserialize_fields = Vec::new();
@@ -122,8 +132,13 @@ pub(crate) fn generate(args: DeriveInput) -> GeneratorResult<TokenStream> {
         let skip_serializing_if = &field.skip_serializing_if;
 
         if field.skip {
+            let create_default_value = match &field.default {
+                Some(DefaultValue::Default) | None => quote!(::std::default::Default::default()),
+                Some(DefaultValue::Function(func_name)) => quote!(#func_name()),
+                Some(DefaultValue::Value(value)) => quote!(#value),
+            };
             deserialize_fields.push(quote! {
-                let #field_ident: #field_ty = ::std::default::Default::default();
+                let #field_ident: #field_ty = #create_default_value;
             });
             fields.push(field_ident);
             continue;
@@ -137,6 +152,14 @@ pub(crate) fn generate(args: DeriveInput) -> GeneratorResult<TokenStream> {
             .into());
         }
 
+        if *field.flatten && field.default.is_some() {
+            return Err(Error::new(
+                field.flatten.span(),
+                "The `default` attribute cannot be used with flattened fields, the defaults of the flattened object apply instead.",
+            )
+            .into());
+        }
+
         let field_name = field.rename.clone().unwrap_or_else(|| {
             apply_rename_rule_field(args.rename_all, field_ident.unraw().to_string())
         });
@@ -153,6 +176,10 @@ pub(crate) fn generate(args: DeriveInput) -> GeneratorResult<TokenStream> {
             (Some(default_value), _) => Some(match default_value {
                 DefaultValue::Default => quote!(<#field_ty as ::std::default::Default>::default()),
                 DefaultValue::Function(func_name) => quote!(#func_name()),
+                DefaultValue::Value(value) => quote!({
+                    let value: #field_ty = #value;
+                    value
+                }),
             }),
             // object default
             (_, Some(default_value)) => Some(match default_value {
@@ -163,6 +190,7 @@ pub(crate) fn generate(args: DeriveInput) -> GeneratorResult<TokenStream> {
                     let default_obj: Self = #func_name();
                     default_obj.#field_ident
                 }),
+                DefaultValue::Value(_) => unreachable!(),
             }),
             // no default
             _ => None,
@@ -172,12 +200,21 @@ pub(crate) fn generate(args: DeriveInput) -> GeneratorResult<TokenStream> {
             let create_default_value = create_default_value
                 .clone()
                 .unwrap_or_else(|| quote! { ::std::default::Default::default() });
-            deserialize_fields.push(quote! {
-                #[allow(non_snake_case)]
-                let #field_ident: #field_ty = {
+            let check_read_only = if args.ignore_read_only_fields {
+                quote! {
+                    obj.remove(#field_name);
+                }
+            } else {
+                quote! {
                     if obj.contains_key(#field_name) {
                         return Err(#crate_name::types::ParseError::custom(format!("properties `{}` is read only.", #field_name)));
                     }
+                }
+            };
+            deserialize_fields.push(quote! {
+                #[allow(non_snake_case)]
+                let #field_ident: #field_ty = {
+                    #check_read_only
                     #create_default_value
                 };
             });
@@ -300,7 +337,7 @@ pub(crate) fn generate(args: DeriveInput) -> GeneratorResult<TokenStream> {
                 fields.push((#field_name, original_schema.merge(patch_schema)));
             }});
 
-            let has_default = field.default.is_some();
+            let has_default = create_default_value.is_some();
             required_fields.push(quote! {
                 if <#field_ty>::IS_REQUIRED && !#has_default {
                     fields.push(#field_name);
@@ -109,13 +109,37 @@ pub(crate) fn parse_oai_attrs<T: FromMeta>(attrs: &[Attribute]) -> GeneratorResu
     Ok(None)
 }
 
-pub(crate) fn convert_oai_path(path: &SpannedValue<String>) -> Result<(String, String)> {
+/// The converted paths of an operation.
+pub(crate) struct OaiPath {
+    /// The path in the OpenAPI document.
+    pub(crate) oai_path: String,
+    /// The path of the route.
+    pub(crate) new_path: String,
+    /// The constraints of the `<name:constraint>` segments.
+    pub(crate) constraints: Vec<(String, String)>,
+    /// The names of the path variables.
+    pub(crate) vars: Vec<String>,
+}
+
+/// Splits the `<name:constraint>` segment.
+fn split_constraint(s: &str) -> Option<(&str, &str)> {
+    let (name, constraint) = s.strip_prefix('<')?.strip_suffix('>')?.split_once(':')?;
+    let is_ident = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    (is_ident && !constraint.is_empty()).then_some((name, constraint))
+}
+
+pub(crate) fn convert_oai_path(path: &SpannedValue<String>) -> Result<OaiPath> {
     if !path.starts_with('/') {
         return Err(Error::new(path.span(), "The path must start with '/'."));
     }
 
     let mut oai_path = String::new();
     let mut new_path = String::new();
+    let mut constraints = Vec::new();
     let mut vars = HashSet::new();
 
     for s in path.split('/') {
@@ -123,18 +147,31 @@ pub(crate) fn convert_oai_path(path: &SpannedValue<String>) -> Result<(String, S
             continue;
         }
 
-        if let Some(var) = s.strip_prefix(':') {
+        let var = match s.strip_prefix(':') {
+            Some(var) => Some(var),
+            None => split_constraint(s).map(|(name, constraint)| {
+                constraints.push((name.to_string(), constraint.to_string()));
+                name
+            }),
+        };
+
+        if let Some(var) = var {
             oai_path.push_str("/{");
             oai_path.push_str(var);
             oai_path.push('}');
 
-            new_path.push_str("/:");
-            new_path.push_str(var);
+            if s.starts_with(':') {
+                new_path.push_str("/:");
+                new_path.push_str(var);
+            } else {
+                new_path.push('/');
+                new_path.push_str(s);
+            }
 
             if !vars.insert(var) {
                 return Err(Error::new(
                     path.span(),
-                    format!("Repeated path variable `{}`.", &s[1..]),
+                    format!("Repeated path variable `{}`.", var),
                 ));
             }
         } else {
@@ -154,7 +191,13 @@ pub(crate) fn convert_oai_path(path: &SpannedValue<String>) -> Result<(String, S
         new_path += "/";
     }
 
-    Ok((oai_path, new_path))
+    let vars = vars.into_iter().map(ToString::to_string).collect();
+    Ok(OaiPath {
+        oai_path,
+        new_path,
+        constraints,
+        vars,
+    })
 }
 
 pub(crate) struct RemoveLifetime;
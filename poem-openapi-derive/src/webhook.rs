@@ -10,7 +10,7 @@ use syn::{
 };
 
 use crate::{
-    common_args::{APIMethod, DefaultValue, ExternalDocument},
+    common_args::{APIMethod, DefaultValue, ExternalDocument, ParamStyle},
     error::GeneratorResult,
     utils::{
         get_crate_name, get_description, get_summary_and_description, optional_literal,
@@ -54,6 +54,8 @@ struct WebHookOperationParam {
     #[darling(default)]
     validator: Option<Validators>,
     #[darling(default)]
+    style: Option<ParamStyle>,
+    #[darling(default)]
     explode: Option<bool>,
 }
 
@@ -199,6 +201,12 @@ fn generate_operation(
             Some(DefaultValue::Function(func_name)) => {
                 quote!(::std::option::Option::Some(#crate_name::types::ToJSON::to_json(&#func_name())))
             }
+            Some(DefaultValue::Value(value)) => {
+                quote!(::std::option::Option::Some(#crate_name::types::ToJSON::to_json(&{
+                    let value: <#arg_ty as #crate_name::ApiExtractor>::ParamType = #value;
+                    value
+                })))
+            }
             None => quote!(::std::option::Option::None),
         };
 
@@ -213,7 +221,17 @@ fn generate_operation(
             .unwrap_or_else(|| arg_ident.unraw().to_string());
         let param_desc = optional_literal_string(&param_description);
         let deprecated = operation_param.deprecated;
-        let explode = operation_param.explode.unwrap_or(true);
+        let style = operation_param.style;
+        let explode = operation_param
+            .explode
+            .unwrap_or_else(|| style.map_or(true, ParamStyle::default_explode));
+        let style = match style {
+            Some(style) => {
+                let style = style.to_meta(crate_name);
+                quote!(::std::option::Option::Some(#style))
+            }
+            None => quote!(::std::option::Option::None),
+        };
 
         params_meta.push(quote! {
             if <#arg_ty as #crate_name::ApiExtractor>::TYPES.contains(&#crate_name::ApiExtractorType::Parameter) {
@@ -233,7 +251,9 @@ fn generate_operation(
                     description: #param_desc,
                     required: <#arg_ty as #crate_name::ApiExtractor>::PARAM_IS_REQUIRED,
                     deprecated: #deprecated,
+                    style: #style,
                     explode: #explode,
+                    content_type: <#arg_ty as #crate_name::ApiExtractor>::param_content_type(),
                 };
                 params.push(meta_param);
             }
@@ -290,6 +310,7 @@ fn generate_operation(
                         security: ::std::vec![],
                         operation_id: #operation_id,
                         code_samples: ::std::vec![],
+                        cache: ::std::option::Option::None,
                     }
                 }
             },
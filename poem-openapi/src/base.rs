@@ -11,8 +11,8 @@ use poem::{endpoint::BoxEndpoint, http::Method, Error, FromRequest, Request, Req
 use crate::{
     payload::Payload,
     registry::{
-        MetaApi, MetaMediaType, MetaOAuthScope, MetaParamIn, MetaRequest, MetaResponse,
-        MetaResponses, MetaSchemaRef, MetaWebhook, Registry,
+        MetaApi, MetaMediaType, MetaOAuthScope, MetaParamIn, MetaParamStyle, MetaRequest,
+        MetaResponse, MetaResponses, MetaSchemaRef, MetaWebhook, Registry,
     },
 };
 
@@ -71,6 +71,10 @@ pub struct ExtractParamOptions<T> {
     /// The example value of this parameter.
     pub example_value: Option<fn() -> T>,
 
+    /// The serialization style of this parameter, `None` for the default style
+    /// of the parameter location.
+    pub style: Option<MetaParamStyle>,
+
     /// When this is `true`, parameter values of type array or object generate
     /// separate parameters for each value of the array or key-value pair of the
     /// map.
@@ -83,6 +87,7 @@ impl<T> Default for ExtractParamOptions<T> {
             name: "",
             default_value: None,
             example_value: None,
+            style: None,
             explode: true,
         }
     }
@@ -186,6 +191,12 @@ pub trait ApiExtractor<'a>: Sized {
         None
     }
 
+    /// Returns the media type of the parameter if this extractor is parameter
+    /// serialized as the `content` of the parameter.
+    fn param_content_type() -> Option<&'static str> {
+        None
+    }
+
     /// Returns `MetaRequest` if this extractor is request object.
     fn request_meta() -> Option<MetaRequest> {
         None
@@ -0,0 +1,55 @@
+use poem::{
+    http::{header, HeaderValue, Method},
+    web::{
+        etag_for,
+        headers::{ETag, HeaderMapExt},
+    },
+    Request, Response,
+};
+
+use crate::registry::MetaCacheControl;
+
+/// Adds the `Cache-Control`, `Vary` and `ETag` headers to the successful
+/// responses of the `GET` and `HEAD` requests.
+///
+/// The headers already set by the operation are kept, and the `If-None-Match`
+/// header is handled by the
+/// [`ConditionalRequest`](poem::middleware::ConditionalRequest) middleware.
+#[doc(hidden)]
+pub async fn apply_cache_control(
+    req: &Request,
+    mut resp: Response,
+    cache: &MetaCacheControl,
+) -> Response {
+    if !matches!(*req.method(), Method::GET | Method::HEAD) || !resp.status().is_success() {
+        return resp;
+    }
+
+    let headers = resp.headers_mut();
+    if !headers.contains_key(header::CACHE_CONTROL) {
+        let visibility = if cache.private { "private" } else { "public" };
+        if let Ok(value) =
+            HeaderValue::from_str(&format!("{visibility}, max-age={}", cache.max_age))
+        {
+            headers.insert(header::CACHE_CONTROL, value);
+        }
+    }
+    for name in &cache.vary {
+        if let Ok(value) = HeaderValue::from_str(name) {
+            headers.append(header::VARY, value);
+        }
+    }
+
+    if req.method() == Method::GET && resp.headers().typed_get::<ETag>().is_none() {
+        let body = resp.take_body();
+        match body.into_bytes().await {
+            Ok(data) => {
+                resp.headers_mut().typed_insert(etag_for(&data));
+                resp.set_body(data);
+            }
+            Err(err) => return poem::Error::from(err).into_response(),
+        }
+    }
+
+    resp
+}
@@ -112,6 +112,9 @@
 //! | prost-wkt-types  | Integrate with the [`prost-wkt-types` crate](https://crates.io/crates/prost-wkt-types) |
 //! | static-files     | Support for static file response                                                       |
 //! | websocket        | Support for websocket                                                                  |
+//! | pagination       | Support for the pagination sort parameter                                              |
+//! | test             | Test utilities to check the responses against the OpenAPI schema                       |
+//! | csv              | Support for the CSV payload                                                            |
 //! |sonic-rs          | Uses [`sonic-rs`](https://github.com/cloudwego/sonic-rs) instead of `serde_json`. Pls, checkout `sonic-rs` requirements to properly enable `sonic-rs` capabilities |
 
 #![doc(html_favicon_url = "https://raw.githubusercontent.com/poem-web/poem/master/favicon.ico")]
@@ -133,11 +136,15 @@ pub mod payload;
 #[doc(hidden)]
 pub mod registry;
 mod response;
+#[cfg(feature = "test")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test")))]
+pub mod test;
 pub mod types;
 #[doc(hidden)]
 pub mod validation;
 
 mod base;
+mod cache;
 mod openapi;
 mod path_util;
 #[cfg(any(
@@ -191,5 +198,7 @@ pub mod __private {
     pub use serde;
     pub use serde_json;
 
-    pub use crate::{auth::CheckerReturn, base::UrlQuery, path_util::join_path};
+    pub use crate::{
+        auth::CheckerReturn, base::UrlQuery, cache::apply_cache_control, path_util::join_path,
+    };
 }
@@ -526,7 +526,9 @@ impl<T, W> OpenApiService<T, W> {
                         description: header.description.clone(),
                         required: *is_required,
                         deprecated: header.deprecated,
+                        style: None,
                         explode: true,
+                        content_type: None,
                     },
                 );
             }
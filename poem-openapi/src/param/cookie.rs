@@ -45,6 +45,10 @@ impl<'a, T: ParseFromParameter> ApiExtractor<'a> for Cookie<T> {
         Some(T::schema_ref())
     }
 
+    fn param_content_type() -> Option<&'static str> {
+        T::CONTENT_TYPE
+    }
+
     fn param_raw_type(&self) -> Option<&Self::ParamRawType> {
         self.0.as_raw_value()
     }
@@ -113,6 +117,10 @@ impl<'a, T: ParseFromParameter> ApiExtractor<'a> for CookiePrivate<T> {
         Some(T::schema_ref())
     }
 
+    fn param_content_type() -> Option<&'static str> {
+        T::CONTENT_TYPE
+    }
+
     fn param_raw_type(&self) -> Option<&Self::ParamRawType> {
         self.0.as_raw_value()
     }
@@ -182,6 +190,10 @@ impl<'a, T: ParseFromParameter> ApiExtractor<'a> for CookieSigned<T> {
         Some(T::schema_ref())
     }
 
+    fn param_content_type() -> Option<&'static str> {
+        T::CONTENT_TYPE
+    }
+
     fn param_raw_type(&self) -> Option<&Self::ParamRawType> {
         self.0.as_raw_value()
     }
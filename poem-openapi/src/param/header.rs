@@ -4,12 +4,15 @@ use poem::{Request, RequestBody, Result};
 
 use crate::{
     error::ParseParamError,
-    registry::{MetaParamIn, MetaSchemaRef, Registry},
+    registry::{MetaParamIn, MetaParamStyle, MetaSchemaRef, Registry},
     types::ParseFromParameter,
     ApiExtractor, ApiExtractorType, ExtractParamOptions,
 };
 
 /// Represents the parameters passed by the request header.
+///
+/// With the `simple` style, the elements of an array or an object are
+/// separated by commas in a single header, other styles are ignored.
 pub struct Header<T>(pub T);
 
 impl<T> Deref for Header<T> {
@@ -45,6 +48,10 @@ impl<'a, T: ParseFromParameter> ApiExtractor<'a> for Header<T> {
         Some(T::schema_ref())
     }
 
+    fn param_content_type() -> Option<&'static str> {
+        T::CONTENT_TYPE
+    }
+
     fn param_raw_type(&self) -> Option<&Self::ParamRawType> {
         self.0.as_raw_value()
     }
@@ -68,14 +75,20 @@ impl<'a, T: ParseFromParameter> ApiExtractor<'a> for Header<T> {
             _ => {}
         }
 
-        ParseFromParameter::parse_from_parameters(values)
-            .map(Self)
-            .map_err(|err| {
-                ParseParamError {
-                    name: param_opts.name,
-                    reason: err.into_message(),
-                }
-                .into()
-            })
+        let res = match param_opts.style {
+            Some(MetaParamStyle::Simple) => match values.next() {
+                Some(value) => ParseFromParameter::parse_from_delimited(value, ','),
+                None => ParseFromParameter::parse_from_parameters(values),
+            },
+            _ => ParseFromParameter::parse_from_parameters(values),
+        };
+
+        res.map(Self).map_err(|err| {
+            ParseParamError {
+                name: param_opts.name,
+                reason: err.into_message(),
+            }
+            .into()
+        })
     }
 }
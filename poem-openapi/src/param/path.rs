@@ -4,12 +4,36 @@ use poem::{Request, RequestBody, Result};
 
 use crate::{
     error::ParsePathError,
-    registry::{MetaParamIn, MetaSchemaRef, Registry},
-    types::ParseFromParameter,
+    registry::{MetaParamIn, MetaParamStyle, MetaSchemaRef, Registry},
+    types::{ParseError, ParseFromParameter, ParseResult},
     ApiExtractor, ApiExtractorType, ExtractParamOptions,
 };
 
 /// Represents the parameters passed by the URI path.
+///
+/// The parameter is passed as is by default, and can be serialized in the
+/// `simple`, `label` and `matrix` styles, other styles are ignored.
+///
+/// # Example
+///
+/// ```
+/// use poem_openapi::{param::Path, OpenApi};
+///
+/// struct Api;
+///
+/// #[OpenApi]
+/// impl Api {
+///     /// `GET /points/1,2/.red.green/;size=3`
+///     #[oai(path = "/points/:point/:colors/:size", method = "get")]
+///     async fn points(
+///         &self,
+///         #[oai(style = "simple")] point: Path<Vec<i32>>,
+///         #[oai(style = "label", explode = true)] colors: Path<Vec<String>>,
+///         #[oai(style = "matrix")] size: Path<i32>,
+///     ) {
+///     }
+/// }
+/// ```
 pub struct Path<T>(pub T);
 
 impl<T> Deref for Path<T> {
@@ -45,6 +69,10 @@ impl<'a, T: ParseFromParameter> ApiExtractor<'a> for Path<T> {
         Some(T::schema_ref())
     }
 
+    fn param_content_type() -> Option<&'static str> {
+        T::CONTENT_TYPE
+    }
+
     fn param_raw_type(&self) -> Option<&Self::ParamRawType> {
         self.0.as_raw_value()
     }
@@ -63,14 +91,66 @@ impl<'a, T: ParseFromParameter> ApiExtractor<'a> for Path<T> {
             (None, _) => None,
         };
 
-        ParseFromParameter::parse_from_parameters(value)
-            .map(Self)
-            .map_err(|err| {
-                ParsePathError {
-                    name: param_opts.name,
-                    reason: err.into_message(),
-                }
-                .into()
-            })
+        let res = match (value, param_opts.style) {
+            (Some(value), Some(style)) => {
+                parse_styled(value, param_opts.name, style, param_opts.explode)
+            }
+            _ => ParseFromParameter::parse_from_parameters(value),
+        };
+
+        res.map(Self).map_err(|err| {
+            ParsePathError {
+                name: param_opts.name,
+                reason: err.into_message(),
+            }
+            .into()
+        })
+    }
+}
+
+fn parse_styled<T: ParseFromParameter>(
+    value: &str,
+    name: &str,
+    style: MetaParamStyle,
+    explode: bool,
+) -> ParseResult<T> {
+    match style {
+        MetaParamStyle::Simple => T::parse_from_delimited(value, ','),
+        MetaParamStyle::Label => {
+            let value = value
+                .strip_prefix('.')
+                .ok_or_else(|| ParseError::custom("expected a value starting with `.`"))?;
+            T::parse_from_delimited(value, if explode { '.' } else { ',' })
+        }
+        MetaParamStyle::Matrix => {
+            let value = value
+                .strip_prefix(';')
+                .ok_or_else(|| ParseError::custom("expected a value starting with `;`"))?;
+            if !explode {
+                let value = value
+                    .strip_prefix(name)
+                    .filter(|value| value.is_empty() || value.starts_with('='))
+                    .ok_or_else(|| {
+                        ParseError::custom(format!("expected a value starting with `;{name}`"))
+                    })?;
+                return T::parse_from_delimited(value.strip_prefix('=').unwrap_or_default(), ',');
+            }
+
+            // `;name=3;name=4` for an array, `;R=100;G=200` for an object
+            let items = value.split(';').collect::<Vec<_>>();
+            let values = items
+                .iter()
+                .map(|item| item.strip_prefix(name)?.strip_prefix('='))
+                .collect::<Option<Vec<_>>>();
+            match values {
+                Some(values) => T::parse_from_parameters(values),
+                None => T::parse_from_object_parameters(
+                    items
+                        .iter()
+                        .map(|item| item.split_once('=').unwrap_or((item, ""))),
+                ),
+            }
+        }
+        _ => T::parse_from_parameters(std::iter::once(value)),
     }
 }
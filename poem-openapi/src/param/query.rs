@@ -5,12 +5,38 @@ use poem::{Request, RequestBody, Result};
 use crate::{
     base::UrlQuery,
     error::ParseParamError,
-    registry::{MetaParamIn, MetaSchemaRef, Registry},
-    types::ParseFromParameter,
+    registry::{MetaParamIn, MetaParamStyle, MetaSchemaRef, Registry},
+    types::{ParseFromParameter, ParseResult},
     ApiExtractor, ApiExtractorType, ExtractParamOptions,
 };
 
 /// Represents the parameters passed by the query string.
+///
+/// The parameter can be serialized in the `form` (default),
+/// `spaceDelimited`, `pipeDelimited` and `deepObject` styles, other styles
+/// are parsed as `form`.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use poem_openapi::{param::Query, OpenApi};
+///
+/// struct Api;
+///
+/// #[OpenApi]
+/// impl Api {
+///     /// `GET /items?ids=1|2|3&filter[color]=red&filter[size]=L`
+///     #[oai(path = "/items", method = "get")]
+///     async fn items(
+///         &self,
+///         #[oai(style = "pipeDelimited")] ids: Query<Vec<i32>>,
+///         #[oai(style = "deepObject")] filter: Query<BTreeMap<String, String>>,
+///     ) {
+///     }
+/// }
+/// ```
 pub struct Query<T>(pub T);
 
 impl<T> Deref for Query<T> {
@@ -46,6 +72,10 @@ impl<'a, T: ParseFromParameter> ApiExtractor<'a> for Query<T> {
         Some(T::schema_ref())
     }
 
+    fn param_content_type() -> Option<&'static str> {
+        T::CONTENT_TYPE
+    }
+
     fn param_raw_type(&self) -> Option<&Self::ParamRawType> {
         self.0.as_raw_value()
     }
@@ -55,41 +85,70 @@ impl<'a, T: ParseFromParameter> ApiExtractor<'a> for Query<T> {
         _body: &mut RequestBody,
         param_opts: ExtractParamOptions<Self::ParamType>,
     ) -> Result<Self> {
-        let mut values = request
-            .extensions()
-            .get::<UrlQuery>()
-            .unwrap()
-            .get_all(param_opts.name)
-            .peekable();
-
-        match &param_opts.default_value {
-            Some(default_value) if values.peek().is_none() => {
-                return Ok(Self(default_value()));
-            }
-            _ => {}
-        }
-
-        if param_opts.explode {
-            ParseFromParameter::parse_from_parameters(values)
-                .map(Self)
-                .map_err(|err| {
-                    ParseParamError {
-                        name: param_opts.name,
-                        reason: err.into_message(),
-                    }
-                    .into()
-                })
+        let query = request.extensions().get::<UrlQuery>().unwrap();
+        let res = if param_opts.style == Some(MetaParamStyle::DeepObject) {
+            parse_deep_object(query, &param_opts)
         } else {
-            let values = values.next().unwrap().split(',').map(|v| v.trim());
-            ParseFromParameter::parse_from_parameters(values)
-                .map(Self)
-                .map_err(|err| {
-                    ParseParamError {
-                        name: param_opts.name,
-                        reason: err.into_message(),
-                    }
-                    .into()
-                })
-        }
+            parse_values(query, &param_opts)
+        };
+
+        res.map(Self).map_err(|err| {
+            ParseParamError {
+                name: param_opts.name,
+                reason: err.into_message(),
+            }
+            .into()
+        })
+    }
+}
+
+fn parse_values<T: ParseFromParameter>(
+    query: &UrlQuery,
+    param_opts: &ExtractParamOptions<T>,
+) -> ParseResult<T> {
+    let mut values = query.get_all(param_opts.name).peekable();
+
+    match &param_opts.default_value {
+        Some(default_value) if values.peek().is_none() => return Ok(default_value()),
+        _ => {}
+    }
+
+    if param_opts.explode {
+        return T::parse_from_parameters(values);
+    }
+
+    let delimiter = match param_opts.style {
+        Some(MetaParamStyle::SpaceDelimited) => ' ',
+        Some(MetaParamStyle::PipeDelimited) => '|',
+        _ => ',',
+    };
+    match values.next() {
+        Some(value) => T::parse_from_delimited(value, delimiter),
+        None => T::parse_from_parameters(values),
+    }
+}
+
+/// Parses the parameters like `name[key]=value`.
+fn parse_deep_object<T: ParseFromParameter>(
+    query: &UrlQuery,
+    param_opts: &ExtractParamOptions<T>,
+) -> ParseResult<T> {
+    let mut pairs = query
+        .iter()
+        .filter_map(|(key, value)| {
+            let key = key
+                .strip_prefix(param_opts.name)?
+                .strip_prefix('[')?
+                .strip_suffix(']')?;
+            Some((key, value))
+        })
+        .peekable();
+
+    if pairs.peek().is_none() {
+        return match &param_opts.default_value {
+            Some(default_value) => Ok(default_value()),
+            None => T::parse_from_parameters(std::iter::empty::<&str>()),
+        };
     }
+    T::parse_from_object_parameters(pairs)
 }
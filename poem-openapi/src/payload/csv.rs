@@ -0,0 +1,97 @@
+use std::ops::{Deref, DerefMut};
+
+use futures_util::TryStreamExt;
+use poem::{web::CsvStream, FromRequest, IntoResponse, Request, RequestBody, Response, Result};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    error::ParseRequestPayloadError,
+    payload::{ParsePayload, Payload},
+    registry::{MetaMediaType, MetaResponse, MetaResponses, MetaSchemaRef, Registry},
+    types::Type,
+    ApiResponse,
+};
+
+/// A CSV payload.
+///
+/// The records are serialized and deserialized with `serde`, the first record
+/// is the header with the names of the fields. The schema of the payload is an
+/// array of `T`.
+#[cfg_attr(docsrs, doc(cfg(feature = "csv")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Csv<T>(pub Vec<T>);
+
+impl<T> Deref for Csv<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Csv<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: Type> Payload for Csv<T> {
+    const CONTENT_TYPE: &'static str = "text/csv; charset=utf-8";
+
+    fn check_content_type(content_type: &str) -> bool {
+        matches!(content_type.parse::<mime::Mime>(), Ok(content_type) if content_type.type_() == "text"
+                && content_type.subtype() == "csv")
+    }
+
+    fn schema_ref() -> MetaSchemaRef {
+        <Vec<T>>::schema_ref()
+    }
+
+    #[allow(unused_variables)]
+    fn register(registry: &mut Registry) {
+        T::register(registry);
+    }
+}
+
+impl<T: Type + DeserializeOwned> ParsePayload for Csv<T> {
+    const IS_REQUIRED: bool = true;
+
+    async fn from_request(request: &Request, body: &mut RequestBody) -> Result<Self> {
+        let records = CsvStream::<T>::from_request(request, body).await?;
+        let records = records
+            .try_collect()
+            .await
+            .map_err(|err| ParseRequestPayloadError {
+                reason: err.to_string(),
+            })?;
+        Ok(Self(records))
+    }
+}
+
+impl<T: Serialize + Send + 'static> IntoResponse for Csv<T> {
+    fn into_response(self) -> Response {
+        poem::web::Csv(futures_util::stream::iter(self.0)).into_response()
+    }
+}
+
+impl<T: Type + Serialize> ApiResponse for Csv<T> {
+    fn meta() -> MetaResponses {
+        MetaResponses {
+            responses: vec![MetaResponse {
+                description: "",
+                status: Some(200),
+                content: vec![MetaMediaType {
+                    content_type: Self::CONTENT_TYPE,
+                    schema: Self::schema_ref(),
+                }],
+                headers: vec![],
+            }],
+        }
+    }
+
+    fn register(registry: &mut Registry) {
+        T::register(registry);
+    }
+}
+
+impl_apirequest_for_payload!(Csv<T>, T: Type + DeserializeOwned);
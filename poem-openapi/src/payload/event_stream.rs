@@ -8,7 +8,9 @@ use poem::{
 
 use crate::{
     payload::Payload,
-    registry::{MetaMediaType, MetaResponse, MetaResponses, MetaSchema, MetaSchemaRef, Registry},
+    registry::{
+        MetaEvent, MetaMediaType, MetaResponse, MetaResponses, MetaSchema, MetaSchemaRef, Registry,
+    },
     types::{ToJSON, Type},
     ApiResponse,
 };
@@ -17,7 +19,50 @@ type ToEventFn<T> = Box<dyn (FnMut(T) -> Event) + Send + 'static>;
 
 /// An event stream payload.
 ///
+/// The events are documented with the `x-events` extension of the schema. If
+/// the item type is a [`Union`](crate::Union) with a discriminator, each
+/// variant is an event whose type is the discriminator value, otherwise all
+/// the items are `message` events.
+///
 /// Reference: <https://github.com/OAI/OpenAPI-Specification/issues/396#issuecomment-894718960>
+///
+/// # Example
+///
+/// ```
+/// use futures_util::stream::BoxStream;
+/// use poem_openapi::{payload::EventStream, Object, OpenApi, Union};
+///
+/// #[derive(Object)]
+/// struct Created {
+///     id: i64,
+/// }
+///
+/// #[derive(Object)]
+/// struct Deleted {
+///     id: i64,
+/// }
+///
+/// #[derive(Union)]
+/// #[oai(discriminator_name = "type")]
+/// enum Change {
+///     Created(Created),
+///     Deleted(Deleted),
+/// }
+///
+/// struct Api;
+///
+/// #[OpenApi]
+/// impl Api {
+///     /// Sends `event: Created` and `event: Deleted` events.
+///     #[oai(path = "/changes", method = "get")]
+///     async fn changes(&self) -> EventStream<BoxStream<'static, Change>> {
+///         EventStream::new(Box::pin(futures_util::stream::iter(vec![
+///             Change::Created(Created { id: 1 }),
+///             Change::Deleted(Deleted { id: 1 }),
+///         ])))
+///     }
+/// }
+/// ```
 pub struct EventStream<T: Stream + Send + 'static> {
     stream: T,
     keep_alive: Option<Duration>,
@@ -45,6 +90,9 @@ impl<T: Stream + Send + 'static> EventStream<T> {
 
     /// Set a function used to convert the message to SSE event.
     ///
+    /// The event types set by this function are not reflected in the `x-events`
+    /// extension.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -79,8 +127,16 @@ impl<T: Stream<Item = E> + Send + 'static, E: Type + ToJSON> Payload for EventSt
     const CONTENT_TYPE: &'static str = "text/event-stream";
 
     fn schema_ref() -> MetaSchemaRef {
+        let events = match discriminated_events::<E>() {
+            Some((_, events)) => events,
+            None => vec![MetaEvent {
+                event: "message".to_string(),
+                data: E::schema_ref(),
+            }],
+        };
         MetaSchemaRef::Inline(Box::new(MetaSchema {
             items: Some(Box::new(E::schema_ref())),
+            events,
             ..MetaSchema::new_with_format("array", "event-stream")
         }))
     }
@@ -96,11 +152,20 @@ impl<T: Stream<Item = E> + Send + 'static, E: Type + ToJSON + 'static> IntoRespo
     fn into_response(self) -> Response {
         let mut sse = match self.to_event {
             Some(to_event) => SSE::new(self.stream.map(to_event)),
-            None => SSE::new(
-                self.stream
-                    .map(|message| message.to_json_string())
-                    .map(Event::message),
-            ),
+            None => {
+                let discriminator = discriminated_events::<E>().map(|(property, _)| property);
+                SSE::new(self.stream.map(move |message| {
+                    let value = message.to_json();
+                    let event_type = discriminator.and_then(|property| {
+                        Some(value.as_ref()?.get(property)?.as_str()?.to_string())
+                    });
+                    let event = Event::message(serde_json::to_string(&value).unwrap_or_default());
+                    match event_type {
+                        Some(event_type) => event.event_type(event_type),
+                        None => event,
+                    }
+                }))
+            }
         };
 
         if let Some(keep_alive) = self.keep_alive {
@@ -130,3 +195,28 @@ impl<T: Stream<Item = E> + Send + 'static, E: Type + ToJSON> ApiResponse for Eve
         E::register(registry);
     }
 }
+
+/// Returns the discriminator property and the events of the variants if `E`
+/// is a union with a discriminator.
+fn discriminated_events<E: Type>() -> Option<(&'static str, Vec<MetaEvent>)> {
+    let MetaSchemaRef::Reference(name) = E::schema_ref() else {
+        return None;
+    };
+    let mut registry = Registry::new();
+    E::register(&mut registry);
+    let discriminator = registry.schemas.remove(&name)?.discriminator?;
+    let events = discriminator
+        .mapping
+        .into_iter()
+        .map(|(event, schema)| MetaEvent {
+            event,
+            data: MetaSchemaRef::Reference(
+                schema
+                    .strip_prefix("#/components/schemas/")
+                    .unwrap_or(&schema)
+                    .to_string(),
+            ),
+        })
+        .collect();
+    Some((discriminator.property_name, events))
+}
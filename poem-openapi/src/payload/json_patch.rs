@@ -0,0 +1,153 @@
+use std::ops::{Deref, DerefMut};
+
+use poem::{FromRequest, Request, RequestBody, Result};
+use serde_json::Value;
+
+use crate::{
+    error::ParseRequestPayloadError,
+    payload::{ParsePayload, Payload},
+    registry::{MetaSchema, MetaSchemaRef, Registry},
+    types::{ParseFromJSON, ToJSON, Type},
+};
+
+/// A JSON Patch payload, see [RFC 6902](https://datatracker.ietf.org/doc/html/rfc6902).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsonPatch(pub poem::web::JsonPatch);
+
+impl Deref for JsonPatch {
+    type Target = poem::web::JsonPatch;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for JsonPatch {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Payload for JsonPatch {
+    const CONTENT_TYPE: &'static str = "application/json-patch+json";
+
+    fn check_content_type(content_type: &str) -> bool {
+        matches!(content_type.parse::<mime::Mime>(), Ok(content_type) if content_type.essence_str() == "application/json-patch+json")
+    }
+
+    fn schema_ref() -> MetaSchemaRef {
+        MetaSchemaRef::Inline(Box::new(MetaSchema {
+            items: Some(Box::new(MetaSchemaRef::Reference(
+                "JsonPatchOperation".to_string(),
+            ))),
+            ..MetaSchema::new("array")
+        }))
+    }
+
+    fn register(registry: &mut Registry) {
+        registry.create_schema::<poem::web::PatchOperation, _>(
+            "JsonPatchOperation".to_string(),
+            |_| {
+                let string = || MetaSchemaRef::Inline(Box::new(MetaSchema::new("string")));
+                MetaSchema {
+                    required: vec!["op", "path"],
+                    properties: vec![
+                        (
+                            "op",
+                            MetaSchemaRef::Inline(Box::new(MetaSchema {
+                                enum_items: ["add", "remove", "replace", "move", "copy", "test"]
+                                    .into_iter()
+                                    .map(Into::into)
+                                    .collect(),
+                                ..MetaSchema::new("string")
+                            })),
+                        ),
+                        ("path", string()),
+                        ("from", string()),
+                        ("value", MetaSchemaRef::Inline(Box::new(MetaSchema::ANY))),
+                    ],
+                    ..MetaSchema::new("object")
+                }
+            },
+        );
+    }
+}
+
+impl ParsePayload for JsonPatch {
+    const IS_REQUIRED: bool = true;
+
+    async fn from_request(request: &Request, body: &mut RequestBody) -> Result<Self> {
+        let data = Vec::<u8>::from_request(request, body).await?;
+        Ok(Self(serde_json::from_slice(&data).map_err(|err| {
+            ParseRequestPayloadError {
+                reason: err.to_string(),
+            }
+        })?))
+    }
+}
+
+impl_apirequest_for_payload!(JsonPatch);
+
+/// A JSON Merge Patch payload, see [RFC 7386](https://datatracker.ietf.org/doc/html/rfc7386).
+///
+/// The type parameter is the type of the resource the patch is applied to,
+/// and is used as the schema of the payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergePatch<T>(pub poem::web::MergePatch<T>);
+
+impl<T> Deref for MergePatch<T> {
+    type Target = poem::web::MergePatch<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for MergePatch<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: ToJSON + ParseFromJSON> MergePatch<T> {
+    /// Applies the patch to the JSON representation of the value, and returns
+    /// the patched value.
+    pub fn apply(&self, value: &T) -> Result<T, ParseRequestPayloadError> {
+        let mut doc = value.to_json().unwrap_or_default();
+        self.0.apply_to_value(&mut doc);
+        T::parse_from_json(Some(doc)).map_err(|err| ParseRequestPayloadError {
+            reason: err.into_message(),
+        })
+    }
+}
+
+impl<T: Type> Payload for MergePatch<T> {
+    const CONTENT_TYPE: &'static str = "application/merge-patch+json";
+
+    fn check_content_type(content_type: &str) -> bool {
+        matches!(content_type.parse::<mime::Mime>(), Ok(content_type) if content_type.essence_str() == "application/merge-patch+json")
+    }
+
+    fn schema_ref() -> MetaSchemaRef {
+        T::schema_ref()
+    }
+
+    fn register(registry: &mut Registry) {
+        T::register(registry);
+    }
+}
+
+impl<T: Type> ParsePayload for MergePatch<T> {
+    const IS_REQUIRED: bool = true;
+
+    async fn from_request(request: &Request, body: &mut RequestBody) -> Result<Self> {
+        let data = Vec::<u8>::from_request(request, body).await?;
+        let patch: Value =
+            serde_json::from_slice(&data).map_err(|err| ParseRequestPayloadError {
+                reason: err.to_string(),
+            })?;
+        Ok(Self(poem::web::MergePatch::new(patch)))
+    }
+}
+
+impl_apirequest_for_payload!(MergePatch<T>, T: Type);
@@ -3,10 +3,13 @@
 mod attachment;
 mod base64_payload;
 mod binary;
+#[cfg(feature = "csv")]
+mod csv;
 mod event_stream;
 mod form;
 mod html;
 mod json;
+mod json_patch;
 mod plain_text;
 mod response;
 mod xml;
@@ -16,6 +19,8 @@ use std::future::Future;
 
 use poem::{Request, RequestBody, Result};
 
+#[cfg(feature = "csv")]
+pub use self::csv::Csv;
 pub use self::{
     attachment::{Attachment, AttachmentType},
     base64_payload::Base64,
@@ -24,6 +29,7 @@ pub use self::{
     form::Form,
     html::Html,
     json::Json,
+    json_patch::{JsonPatch, MergePatch},
     plain_text::PlainText,
     response::Response,
     xml::Xml,
@@ -116,6 +116,18 @@ pub struct MetaSchema {
     pub max_properties: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub min_properties: Option<usize>,
+    #[serde(rename = "x-events", skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<MetaEvent>,
+}
+
+/// An event of an event stream, which is documented with the `x-events`
+/// extension.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetaEvent {
+    /// The event type, i.e. the `event` field of the server-sent event.
+    pub event: String,
+    /// The schema of the `data` field of the server-sent event.
+    pub data: MetaSchemaRef,
 }
 
 fn serialize_properties<S: Serializer>(
@@ -164,6 +176,7 @@ impl MetaSchema {
         unique_items: None,
         max_properties: None,
         min_properties: None,
+        events: vec![],
     };
 
     pub fn new(ty: &'static str) -> Self {
@@ -356,17 +369,31 @@ pub enum MetaParamIn {
     CookieSigned,
 }
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MetaParamStyle {
+    Matrix,
+    Label,
+    Form,
+    Simple,
+    SpaceDelimited,
+    PipeDelimited,
+    DeepObject,
+}
+
+#[derive(Debug, PartialEq)]
 pub struct MetaOperationParam {
     pub name: String,
     pub schema: MetaSchemaRef,
-    #[serde(rename = "in")]
     pub in_type: MetaParamIn,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub required: bool,
     pub deprecated: bool,
+    pub style: Option<MetaParamStyle>,
     pub explode: bool,
+    /// The media type of the parameter value if it is serialized as the
+    /// `content` of the parameter, such as `application/json`.
+    pub content_type: Option<&'static str>,
 }
 
 #[derive(Debug, PartialEq, Serialize)]
@@ -461,6 +488,18 @@ pub struct MetaCodeSample {
     pub source: &'static str,
 }
 
+/// The caching behavior of an operation, which is documented with the
+/// `x-cache` extension.
+#[derive(Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaCacheControl {
+    pub max_age: u64,
+    #[serde(skip_serializing_if = "is_false")]
+    pub private: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub vary: Vec<&'static str>,
+}
+
 #[derive(Debug, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MetaOperation {
@@ -487,6 +526,8 @@ pub struct MetaOperation {
     pub operation_id: Option<&'static str>,
     #[serde(rename = "x-code-samples", skip_serializing_if = "Vec::is_empty")]
     pub code_samples: Vec<MetaCodeSample>,
+    #[serde(rename = "x-cache", skip_serializing_if = "Option::is_none")]
+    pub cache: Option<MetaCacheControl>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -3,8 +3,8 @@ use std::collections::BTreeMap;
 use serde::{ser::SerializeMap, Serialize, Serializer};
 
 use crate::registry::{
-    MetaApi, MetaExternalDocument, MetaInfo, MetaPath, MetaResponses, MetaSchema, MetaSchemaRef,
-    MetaSecurityScheme, MetaServer, MetaWebhook, Registry,
+    MetaApi, MetaExternalDocument, MetaInfo, MetaOperationParam, MetaPath, MetaResponses,
+    MetaSchema, MetaSchemaRef, MetaSecurityScheme, MetaServer, MetaWebhook, Registry,
 };
 
 const OPENAPI_VERSION: &str = "3.0.0";
@@ -22,6 +22,47 @@ impl Serialize for MetaSchemaRef {
     }
 }
 
+impl Serialize for MetaOperationParam {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct MediaType<'a> {
+            schema: &'a MetaSchemaRef,
+        }
+
+        let mut s = serializer.serialize_map(None)?;
+        s.serialize_entry("name", &self.name)?;
+        if self.content_type.is_none() {
+            s.serialize_entry("schema", &self.schema)?;
+        }
+        s.serialize_entry("in", &self.in_type)?;
+        if let Some(description) = &self.description {
+            s.serialize_entry("description", description)?;
+        }
+        s.serialize_entry("required", &self.required)?;
+        s.serialize_entry("deprecated", &self.deprecated)?;
+        match self.content_type {
+            Some(content_type) => {
+                s.serialize_entry(
+                    "content",
+                    &BTreeMap::from([(
+                        content_type,
+                        MediaType {
+                            schema: &self.schema,
+                        },
+                    )]),
+                )?;
+            }
+            None => {
+                if let Some(style) = &self.style {
+                    s.serialize_entry("style", style)?;
+                }
+                s.serialize_entry("explode", &self.explode)?;
+            }
+        }
+        s.end()
+    }
+}
+
 struct PathMap<'a>(&'a [MetaApi], Option<&'a str>);
 
 impl<'a> Serialize for PathMap<'a> {
@@ -0,0 +1,337 @@
+//! Test utilities to check the responses against the OpenAPI schema.
+//!
+//! # Example
+//!
+//! ```
+//! use poem::{http::Method, test::TestClient};
+//! use poem_openapi::{payload::Json, test::TestResponseExt, Object, OpenApi, OpenApiService};
+//!
+//! #[derive(Object)]
+//! struct User {
+//!     id: i64,
+//!     name: String,
+//! }
+//!
+//! struct Api;
+//!
+//! #[OpenApi]
+//! impl Api {
+//!     #[oai(path = "/users/:id", method = "get")]
+//!     async fn get_user(&self, id: poem_openapi::param::Path<i64>) -> Json<User> {
+//!         Json(User {
+//!             id: id.0,
+//!             name: "sunli".to_string(),
+//!         })
+//!     }
+//! }
+//!
+//! let cli = TestClient::new(OpenApiService::new(Api, "demo", "1.0"));
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let resp = cli.get("/users/1").send().await;
+//! resp.assert_status_is_ok();
+//! resp.assert_matches_operation::<Api>(Method::GET, "/users/1")
+//!     .await;
+//!
+//! let resp = cli.get("/users/1").send().await;
+//! resp.assert_json_matches_schema::<User>().await;
+//! # });
+//! ```
+
+mod validate;
+
+use std::future::Future;
+
+use poem::{
+    http::{header, Method},
+    test::TestResponse,
+};
+use serde_json::Value;
+
+use crate::{
+    registry::{MetaOperation, MetaResponse, Registry},
+    types::Type,
+    OpenApi,
+};
+
+/// Extension trait for [`TestResponse`] to check the response against the
+/// OpenAPI schema.
+pub trait TestResponseExt {
+    /// Asserts that the response body is JSON and it matches the schema of
+    /// `T`.
+    fn assert_json_matches_schema<T: Type>(self) -> impl Future<Output = ()> + Send;
+
+    /// Asserts that the response matches one of the responses declared by
+    /// the operation of `T` with the specified method and path.
+    ///
+    /// The `path` can be either the path template of the operation (e.g.
+    /// `/users/{id}`) or the request path (e.g. `/users/1`).
+    ///
+    /// This checks the status code, the required headers, the content type
+    /// and, for JSON content, the body against the declared schema.
+    fn assert_matches_operation<T: OpenApi>(
+        self,
+        method: Method,
+        path: &str,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+impl TestResponseExt for TestResponse {
+    async fn assert_json_matches_schema<T: Type>(self) {
+        let mut registry = Registry::new();
+        T::register(&mut registry);
+
+        let value = read_json(self).await;
+        if value.is_null() && !T::IS_REQUIRED {
+            return;
+        }
+        if let Err(err) = validate::validate(&registry, &T::schema_ref(), &value) {
+            panic!(
+                "response does not match the schema of `{}`: {err}",
+                T::name()
+            );
+        }
+    }
+
+    async fn assert_matches_operation<T: OpenApi>(self, method: Method, path: &str) {
+        let mut registry = Registry::new();
+        T::register(&mut registry);
+        let apis = T::meta();
+
+        let operation = find_operation(&apis, &method, path)
+            .unwrap_or_else(|| panic!("operation `{method} {path}` is not declared"));
+        let status = self.0.status();
+        let response = find_response(operation, status.as_u16()).unwrap_or_else(|| {
+            panic!("status `{status}` is not declared by the operation `{method} {path}`")
+        });
+
+        for meta_header in &response.headers {
+            if meta_header.required {
+                self.assert_header_exist(meta_header.name.as_str());
+            }
+        }
+
+        if response.content.is_empty() {
+            return;
+        }
+
+        let content_type = self
+            .0
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<mime::Mime>().ok())
+            .expect("expect content type");
+        let media_type = response
+            .content
+            .iter()
+            .find(|media_type| {
+                media_type
+                    .content_type
+                    .parse::<mime::Mime>()
+                    .map(|declared| declared.essence_str() == content_type.essence_str())
+                    .unwrap_or_default()
+            })
+            .unwrap_or_else(|| {
+                panic!("content type `{content_type}` is not declared by the operation `{method} {path}`")
+            });
+
+        if content_type.subtype() == mime::JSON || content_type.suffix() == Some(mime::JSON) {
+            let value = read_json(self).await;
+            if let Err(err) = validate::validate(&registry, &media_type.schema, &value) {
+                panic!("response does not match the operation `{method} {path}`: {err}");
+            }
+        }
+    }
+}
+
+async fn read_json(resp: TestResponse) -> Value {
+    resp.0
+        .into_body()
+        .into_json::<Value>()
+        .await
+        .expect("expect json body")
+}
+
+fn find_operation<'a>(
+    apis: &'a [crate::registry::MetaApi],
+    method: &Method,
+    path: &str,
+) -> Option<&'a MetaOperation> {
+    apis.iter()
+        .flat_map(|api| &api.paths)
+        .filter(|meta_path| path_matches(&meta_path.path, path))
+        .flat_map(|meta_path| &meta_path.operations)
+        .find(|operation| operation.method == *method)
+}
+
+fn find_response(operation: &MetaOperation, status: u16) -> Option<&MetaResponse> {
+    let responses = &operation.responses.responses;
+    responses
+        .iter()
+        .find(|response| response.status == Some(status))
+        .or_else(|| responses.iter().find(|response| response.status.is_none()))
+}
+
+fn path_matches(template: &str, path: &str) -> bool {
+    let mut template = template.split('/').filter(|s| !s.is_empty());
+    let mut path = path.split('/').filter(|s| !s.is_empty());
+
+    loop {
+        match (template.next(), path.next()) {
+            (Some(a), Some(_)) if a.starts_with('{') && a.ends_with('}') => {}
+            (Some(a), Some(b)) if a == b => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use poem::{
+        http::{Method, StatusCode},
+        test::TestClient,
+        Response,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        param::Path, payload::Json, registry::MetaSchemaRef, ApiResponse, Object, OpenApiService,
+    };
+
+    #[derive(Object)]
+    #[oai(internal)]
+    struct Item {
+        #[oai(validator(min_length = 1))]
+        name: String,
+        tags: Vec<String>,
+        count: Option<i32>,
+    }
+
+    #[derive(ApiResponse)]
+    #[oai(internal)]
+    enum GetItemResponse {
+        #[oai(status = 200)]
+        Ok(Json<Item>),
+        #[oai(status = 404)]
+        NotFound,
+    }
+
+    struct Api;
+
+    #[crate::OpenApi(internal)]
+    impl Api {
+        #[oai(path = "/items/:id", method = "get")]
+        async fn get_item(&self, id: Path<i32>) -> GetItemResponse {
+            match id.0 {
+                1 => GetItemResponse::Ok(Json(Item {
+                    name: "a".to_string(),
+                    tags: vec!["b".to_string()],
+                    count: None,
+                })),
+                _ => GetItemResponse::NotFound,
+            }
+        }
+
+        #[oai(path = "/bad", method = "get")]
+        async fn bad(&self) -> Json<Item> {
+            unreachable!()
+        }
+    }
+
+    fn check<T: Type>(value: Value) -> Result<(), String> {
+        let mut registry = Registry::new();
+        T::register(&mut registry);
+        validate::validate(&registry, &T::schema_ref(), &value).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn validate_object() {
+        assert!(check::<Item>(json!({"name": "a", "tags": []})).is_ok());
+        assert!(check::<Item>(json!({"name": "a", "tags": [], "count": null})).is_ok());
+        assert_eq!(
+            check::<Item>(json!({"tags": []})).unwrap_err(),
+            "$: missing required property `name`"
+        );
+        assert_eq!(
+            check::<Item>(json!({"name": "", "tags": []})).unwrap_err(),
+            "$.name: the length is less than `1`"
+        );
+        assert_eq!(
+            check::<Item>(json!({"name": "a", "tags": [1]})).unwrap_err(),
+            "$.tags[0]: expect `string`, got `1`"
+        );
+        assert_eq!(
+            check::<Item>(json!({"name": "a", "tags": [], "count": 1.5})).unwrap_err(),
+            "$.count: expect `integer`, got `1.5`"
+        );
+    }
+
+    #[test]
+    fn validate_inline() {
+        let registry = Registry::new();
+        let schema = MetaSchemaRef::Inline(Box::new(crate::registry::MetaSchema {
+            maximum: Some(10.0),
+            ..crate::registry::MetaSchema::new("integer")
+        }));
+        assert!(validate::validate(&registry, &schema, &json!(10)).is_ok());
+        assert!(validate::validate(&registry, &schema, &json!(11)).is_err());
+    }
+
+    #[tokio::test]
+    async fn assert_matches_operation() {
+        let cli = TestClient::new(OpenApiService::new(Api, "test", "1.0"));
+
+        cli.get("/items/1")
+            .send()
+            .await
+            .assert_matches_operation::<Api>(Method::GET, "/items/1")
+            .await;
+        cli.get("/items/2")
+            .send()
+            .await
+            .assert_matches_operation::<Api>(Method::GET, "/items/{id}")
+            .await;
+        cli.get("/items/1")
+            .send()
+            .await
+            .assert_json_matches_schema::<Item>()
+            .await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "response does not match the operation `GET /bad`: $.tags")]
+    async fn assert_matches_operation_mismatch() {
+        let resp = TestResponse(
+            Response::builder()
+                .content_type("application/json; charset=utf-8")
+                .body(r#"{"name": "a", "tags": "b"}"#),
+        );
+        resp.assert_matches_operation::<Api>(Method::GET, "/bad")
+            .await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "status `500 Internal Server Error` is not declared")]
+    async fn assert_matches_operation_undeclared_status() {
+        let resp = TestResponse(
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .finish(),
+        );
+        resp.assert_matches_operation::<Api>(Method::GET, "/items/1")
+            .await;
+    }
+
+    #[test]
+    fn test_path_matches() {
+        assert!(path_matches("/a/{id}/b", "/a/1/b"));
+        assert!(path_matches("/a/{id}/b", "/a/{id}/b"));
+        assert!(path_matches("/", "/"));
+        assert!(!path_matches("/a/{id}", "/a"));
+        assert!(!path_matches("/a/{id}", "/a/1/b"));
+        assert!(!path_matches("/a/b", "/a/c"));
+    }
+}
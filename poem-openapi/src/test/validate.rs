@@ -0,0 +1,311 @@
+use std::fmt::{self, Display, Formatter};
+
+use serde_json::Value;
+
+use crate::registry::{MetaDiscriminatorObject, MetaSchema, MetaSchemaRef, Registry};
+
+/// An error that occurs when a JSON value does not match a schema.
+#[derive(Debug)]
+pub(crate) struct SchemaMismatch {
+    path: String,
+    reason: String,
+}
+
+impl Display for SchemaMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.reason)
+    }
+}
+
+type Result<T = ()> = std::result::Result<T, SchemaMismatch>;
+
+fn mismatch(path: &str, reason: impl Into<String>) -> SchemaMismatch {
+    SchemaMismatch {
+        path: path.to_string(),
+        reason: reason.into(),
+    }
+}
+
+/// Validates `value` against the schema, resolving references with the
+/// registry.
+pub(crate) fn validate(registry: &Registry, schema: &MetaSchemaRef, value: &Value) -> Result {
+    validate_ref(registry, schema, value, "$")
+}
+
+fn validate_ref(registry: &Registry, schema: &MetaSchemaRef, value: &Value, path: &str) -> Result {
+    match schema {
+        MetaSchemaRef::Inline(schema) => validate_schema(registry, schema, value, path),
+        MetaSchemaRef::Reference(name) => {
+            let schema = registry
+                .schemas
+                .get(name)
+                .unwrap_or_else(|| panic!("schema `{name}` is not registered"));
+            validate_schema(registry, schema, value, path)
+        }
+    }
+}
+
+fn resolve<'a>(registry: &'a Registry, schema: &'a MetaSchemaRef) -> &'a MetaSchema {
+    match schema {
+        MetaSchemaRef::Inline(schema) => schema,
+        MetaSchemaRef::Reference(name) => registry
+            .schemas
+            .get(name)
+            .unwrap_or_else(|| panic!("schema `{name}` is not registered")),
+    }
+}
+
+fn validate_schema(registry: &Registry, schema: &MetaSchema, value: &Value, path: &str) -> Result {
+    // Unions declare the `object` type, but their variants may have any type.
+    if schema.any_of.is_empty() && schema.one_of.is_empty() {
+        validate_type(registry, schema, value, path)?;
+    }
+
+    if !schema.enum_items.is_empty() && !schema.enum_items.contains(value) {
+        return Err(mismatch(
+            path,
+            format!("`{value}` is not one of the enum items"),
+        ));
+    }
+
+    for schema in &schema.all_of {
+        validate_ref(registry, schema, value, path)?;
+    }
+
+    if let Some(discriminator) = &schema.discriminator {
+        return validate_discriminator(registry, schema, discriminator, value, path);
+    }
+
+    if !schema.any_of.is_empty()
+        && !schema
+            .any_of
+            .iter()
+            .any(|schema| validate_ref(registry, schema, value, path).is_ok())
+    {
+        return Err(mismatch(path, "does not match any of the schemas"));
+    }
+
+    if !schema.one_of.is_empty() {
+        let count = schema
+            .one_of
+            .iter()
+            .filter(|schema| validate_ref(registry, schema, value, path).is_ok())
+            .count();
+        if count != 1 {
+            return Err(mismatch(
+                path,
+                format!("expect to match exactly one of the schemas, but matched {count}"),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_discriminator(
+    registry: &Registry,
+    schema: &MetaSchema,
+    discriminator: &MetaDiscriminatorObject,
+    value: &Value,
+    path: &str,
+) -> Result {
+    let name = value
+        .get(discriminator.property_name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| {
+            mismatch(
+                path,
+                format!(
+                    "missing discriminator property `{}`",
+                    discriminator.property_name
+                ),
+            )
+        })?;
+    let schema_name = discriminator
+        .mapping
+        .iter()
+        .find(|(mapping_name, _)| mapping_name == name)
+        .map(|(_, reference)| reference.trim_start_matches("#/components/schemas/"))
+        .unwrap_or(name);
+    let schema = schema
+        .one_of
+        .iter()
+        .chain(&schema.any_of)
+        .find(|schema| matches!(schema, MetaSchemaRef::Reference(name) if name == schema_name))
+        .ok_or_else(|| mismatch(path, format!("unknown discriminator value `{name}`")))?;
+    validate_ref(registry, schema, value, path)
+}
+
+fn validate_type(registry: &Registry, schema: &MetaSchema, value: &Value, path: &str) -> Result {
+    match (schema.ty, value) {
+        ("", _) => Ok(()),
+        ("boolean", Value::Bool(_)) => Ok(()),
+        ("integer", Value::Number(n)) if n.is_i64() || n.is_u64() => {
+            validate_number(schema, n.as_f64().unwrap_or_default(), path)
+        }
+        ("number", Value::Number(n)) => {
+            validate_number(schema, n.as_f64().unwrap_or_default(), path)
+        }
+        ("string", Value::String(s)) => validate_string(schema, s, path),
+        ("array", Value::Array(items)) => validate_array(registry, schema, items, path),
+        ("object", Value::Object(_)) => validate_object(registry, schema, value, path),
+        (ty, value) => Err(mismatch(path, format!("expect `{ty}`, got `{value}`"))),
+    }
+}
+
+fn validate_number(schema: &MetaSchema, n: f64, path: &str) -> Result {
+    if let Some(maximum) = schema.maximum {
+        let exclusive = schema.exclusive_maximum.unwrap_or_default();
+        if (exclusive && n >= maximum) || n > maximum {
+            return Err(mismatch(path, format!("`{n}` is greater than `{maximum}`")));
+        }
+    }
+
+    if let Some(minimum) = schema.minimum {
+        let exclusive = schema.exclusive_minimum.unwrap_or_default();
+        if (exclusive && n <= minimum) || n < minimum {
+            return Err(mismatch(path, format!("`{n}` is less than `{minimum}`")));
+        }
+    }
+
+    if let Some(multiple_of) = schema.multiple_of {
+        if (n / multiple_of).fract() != 0.0 {
+            return Err(mismatch(
+                path,
+                format!("`{n}` is not a multiple of `{multiple_of}`"),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_string(schema: &MetaSchema, s: &str, path: &str) -> Result {
+    let len = s.chars().count();
+
+    if let Some(max_length) = schema.max_length {
+        if len > max_length {
+            return Err(mismatch(
+                path,
+                format!("the length is greater than `{max_length}`"),
+            ));
+        }
+    }
+
+    if let Some(min_length) = schema.min_length {
+        if len < min_length {
+            return Err(mismatch(
+                path,
+                format!("the length is less than `{min_length}`"),
+            ));
+        }
+    }
+
+    if let Some(pattern) = &schema.pattern {
+        let re = regex::Regex::new(pattern).expect("valid pattern");
+        if !re.is_match(s) {
+            return Err(mismatch(
+                path,
+                format!("`{s}` does not match the pattern `{pattern}`"),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_array(registry: &Registry, schema: &MetaSchema, items: &[Value], path: &str) -> Result {
+    if let Some(max_items) = schema.max_items {
+        if items.len() > max_items {
+            return Err(mismatch(
+                path,
+                format!("the number of items is greater than `{max_items}`"),
+            ));
+        }
+    }
+
+    if let Some(min_items) = schema.min_items {
+        if items.len() < min_items {
+            return Err(mismatch(
+                path,
+                format!("the number of items is less than `{min_items}`"),
+            ));
+        }
+    }
+
+    if schema.unique_items == Some(true) {
+        for (idx, item) in items.iter().enumerate() {
+            if items[..idx].contains(item) {
+                return Err(mismatch(path, "the items are not unique"));
+            }
+        }
+    }
+
+    if let Some(items_schema) = &schema.items {
+        for (idx, item) in items.iter().enumerate() {
+            validate_ref(registry, items_schema, item, &format!("{path}[{idx}]"))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_object(registry: &Registry, schema: &MetaSchema, value: &Value, path: &str) -> Result {
+    let object = value.as_object().expect("object");
+
+    if let Some(max_properties) = schema.max_properties {
+        if object.len() > max_properties {
+            return Err(mismatch(
+                path,
+                format!("the number of properties is greater than `{max_properties}`"),
+            ));
+        }
+    }
+
+    if let Some(min_properties) = schema.min_properties {
+        if object.len() < min_properties {
+            return Err(mismatch(
+                path,
+                format!("the number of properties is less than `{min_properties}`"),
+            ));
+        }
+    }
+
+    for name in &schema.required {
+        if !object.contains_key(*name) {
+            return Err(mismatch(
+                path,
+                format!("missing required property `{name}`"),
+            ));
+        }
+    }
+
+    for (name, value) in object {
+        let property_path = format!("{path}.{name}");
+        match schema
+            .properties
+            .iter()
+            .find(|(property_name, _)| property_name == name)
+        {
+            Some((_, property_schema)) => {
+                if value.is_null() && !schema.required.contains(&name.as_str()) {
+                    continue;
+                }
+                if resolve(registry, property_schema).write_only {
+                    return Err(mismatch(
+                        &property_path,
+                        "write-only property in the response",
+                    ));
+                }
+                validate_ref(registry, property_schema, value, &property_path)?;
+            }
+            None => {
+                if let Some(additional_properties) = &schema.additional_properties {
+                    validate_ref(registry, additional_properties, value, &property_path)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -4,7 +4,9 @@ use serde_json::Value;
 
 use crate::{
     registry::{MetaSchema, MetaSchemaRef, Registry},
-    types::{ParseError, ParseFromJSON, ParseFromParameter, ParseResult, ToJSON, Type},
+    types::{
+        split_delimited, ParseError, ParseFromJSON, ParseFromParameter, ParseResult, ToJSON, Type,
+    },
 };
 
 impl<T: Type, const LEN: usize> Type for [T; LEN] {
@@ -90,6 +92,10 @@ impl<T: ParseFromParameter, const LEN: usize> ParseFromParameter for [T; LEN] {
 
         Ok(values.try_into().ok().unwrap())
     }
+
+    fn parse_from_delimited(value: &str, delimiter: char) -> ParseResult<Self> {
+        Self::parse_from_parameters(split_delimited(value, delimiter))
+    }
 }
 
 impl<T: ToJSON, const LEN: usize> ToJSON for [T; LEN] {
@@ -4,7 +4,10 @@ use serde_json::Value;
 
 use crate::{
     registry::{MetaSchema, MetaSchemaRef, Registry},
-    types::{ParseError, ParseFromJSON, ParseResult, ToJSON, Type},
+    types::{
+        parse_delimited_object, ParseError, ParseFromJSON, ParseFromParameter, ParseResult, ToJSON,
+        Type,
+    },
 };
 
 impl<K, V> Type for BTreeMap<K, V>
@@ -72,6 +75,40 @@ where
     }
 }
 
+impl<K, V> ParseFromParameter for BTreeMap<K, V>
+where
+    K: ToString + FromStr + Ord + Sync + Send,
+    K::Err: Display,
+    V: ParseFromParameter,
+{
+    fn parse_from_parameter(value: &str) -> ParseResult<Self> {
+        Self::parse_from_delimited(value, ',')
+    }
+
+    fn parse_from_delimited(value: &str, delimiter: char) -> ParseResult<Self> {
+        parse_delimited_object(value, delimiter)
+    }
+
+    fn parse_from_object_parameters<I, A, B>(iter: I) -> ParseResult<Self>
+    where
+        I: IntoIterator<Item = (A, B)>,
+        A: AsRef<str>,
+        B: AsRef<str>,
+    {
+        let mut obj = BTreeMap::new();
+        for (key, value) in iter {
+            let key = key
+                .as_ref()
+                .parse()
+                .map_err(|err| ParseError::custom(format!("object key: {err}")))?;
+            let value = V::parse_from_parameters(std::iter::once(value.as_ref()))
+                .map_err(ParseError::propagate)?;
+            obj.insert(key, value);
+        }
+        Ok(obj)
+    }
+}
+
 impl<K, V> ToJSON for BTreeMap<K, V>
 where
     K: ToString + FromStr + Ord + Sync + Send,
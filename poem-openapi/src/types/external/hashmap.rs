@@ -10,7 +10,10 @@ use serde_json::Value;
 
 use crate::{
     registry::{MetaSchema, MetaSchemaRef, Registry},
-    types::{ParseError, ParseFromJSON, ParseResult, ToJSON, Type},
+    types::{
+        parse_delimited_object, ParseError, ParseFromJSON, ParseFromParameter, ParseResult, ToJSON,
+        Type,
+    },
 };
 
 impl<K, V, R> Type for HashMap<K, V, R>
@@ -80,6 +83,41 @@ where
     }
 }
 
+impl<K, V, R> ParseFromParameter for HashMap<K, V, R>
+where
+    K: ToString + FromStr + Eq + Hash + Sync + Send,
+    K::Err: Display,
+    V: ParseFromParameter,
+    R: Sync + Send + Default + BuildHasher,
+{
+    fn parse_from_parameter(value: &str) -> ParseResult<Self> {
+        Self::parse_from_delimited(value, ',')
+    }
+
+    fn parse_from_delimited(value: &str, delimiter: char) -> ParseResult<Self> {
+        parse_delimited_object(value, delimiter)
+    }
+
+    fn parse_from_object_parameters<I, A, B>(iter: I) -> ParseResult<Self>
+    where
+        I: IntoIterator<Item = (A, B)>,
+        A: AsRef<str>,
+        B: AsRef<str>,
+    {
+        let mut obj = HashMap::with_hasher(R::default());
+        for (key, value) in iter {
+            let key = key
+                .as_ref()
+                .parse()
+                .map_err(|err| ParseError::custom(format!("object key: {err}")))?;
+            let value = V::parse_from_parameters(std::iter::once(value.as_ref()))
+                .map_err(ParseError::propagate)?;
+            obj.insert(key, value);
+        }
+        Ok(obj)
+    }
+}
+
 impl<K, V, R> ToJSON for HashMap<K, V, R>
 where
     K: ToString + FromStr + Eq + Hash + Sync + Send,
@@ -10,8 +10,8 @@ use serde_json::Value;
 use crate::{
     registry::{MetaSchema, MetaSchemaRef, Registry},
     types::{
-        ParseError, ParseFromJSON, ParseFromMultipartField, ParseFromParameter, ParseResult,
-        ToJSON, Type,
+        split_delimited, ParseError, ParseFromJSON, ParseFromMultipartField, ParseFromParameter,
+        ParseResult, ToJSON, Type,
     },
 };
 
@@ -89,6 +89,10 @@ impl<T: ParseFromParameter + Hash + Eq, R: Send + Sync + Default + BuildHasher>
         }
         Ok(values)
     }
+
+    fn parse_from_delimited(value: &str, delimiter: char) -> ParseResult<Self> {
+        Self::parse_from_parameters(split_delimited(value, delimiter))
+    }
 }
 
 impl<T, R> ParseFromMultipartField for HashSet<T, R>
@@ -21,6 +21,8 @@ mod humantime_wrapper;
 mod integers;
 mod ip;
 mod optional;
+#[cfg(feature = "pagination")]
+mod pagination;
 #[cfg(feature = "prost-wkt-types")]
 mod prost_wkt_types;
 mod regex;
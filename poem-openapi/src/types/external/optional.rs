@@ -64,6 +64,8 @@ impl<T: ParseFromJSON> ParseFromJSON for Option<T> {
 }
 
 impl<T: ParseFromParameter> ParseFromParameter for Option<T> {
+    const CONTENT_TYPE: Option<&'static str> = T::CONTENT_TYPE;
+
     fn parse_from_parameter(_value: &str) -> ParseResult<Self> {
         unreachable!()
     }
@@ -81,6 +83,23 @@ impl<T: ParseFromParameter> ParseFromParameter for Option<T> {
             .map_err(ParseError::propagate)
             .map(Some)
     }
+
+    fn parse_from_delimited(value: &str, delimiter: char) -> ParseResult<Self> {
+        T::parse_from_delimited(value, delimiter)
+            .map_err(ParseError::propagate)
+            .map(Some)
+    }
+
+    fn parse_from_object_parameters<I, K, V>(iter: I) -> ParseResult<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        T::parse_from_object_parameters(iter)
+            .map_err(ParseError::propagate)
+            .map(Some)
+    }
 }
 
 impl<T: ParseFromMultipartField> ParseFromMultipartField for Option<T> {
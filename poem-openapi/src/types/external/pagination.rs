@@ -0,0 +1,104 @@
+use std::borrow::Cow;
+
+use poem::web::{Sort, SortField};
+
+use crate::{
+    registry::{MetaSchema, MetaSchemaRef},
+    types::{ParseError, ParseFromParameter, ParseResult, Type},
+};
+
+impl<T: SortField + Sync> Type for Sort<T> {
+    const IS_REQUIRED: bool = false;
+
+    type RawValueType = Self;
+
+    type RawElementValueType = Self;
+
+    fn name() -> Cow<'static, str> {
+        "string(sort)".into()
+    }
+
+    fn schema_ref() -> MetaSchemaRef {
+        let field = format!("[+-]?({})", T::FIELDS.join("|"));
+        MetaSchemaRef::Inline(Box::new(MetaSchema {
+            pattern: Some(format!("^({field}(,{field})*)?$")),
+            ..MetaSchema::new_with_format("string", "sort")
+        }))
+    }
+
+    fn as_raw_value(&self) -> Option<&Self::RawValueType> {
+        Some(self)
+    }
+
+    fn raw_element_iter<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = &'a Self::RawElementValueType> + 'a> {
+        Box::new(self.as_raw_value().into_iter())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T: SortField + Sync> ParseFromParameter for Sort<T> {
+    fn parse_from_parameter(value: &str) -> ParseResult<Self> {
+        Sort::parse(value).map_err(ParseError::custom)
+    }
+
+    fn parse_from_parameters<I: IntoIterator<Item = A>, A: AsRef<str>>(
+        iter: I,
+    ) -> ParseResult<Self> {
+        let mut sort = Sort::default();
+        for value in iter {
+            sort.0.extend(Self::parse_from_parameter(value.as_ref())?.0);
+        }
+        Ok(sort)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use poem::web::SortOrder;
+
+    use super::*;
+
+    #[derive(Debug, Eq, PartialEq)]
+    enum Field {
+        Name,
+        Age,
+    }
+
+    impl SortField for Field {
+        const FIELDS: &'static [&'static str] = &["name", "age"];
+
+        fn from_name(name: &str) -> Option<Self> {
+            match name {
+                "name" => Some(Field::Name),
+                "age" => Some(Field::Age),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn parse_from_parameters() {
+        let sort = Sort::<Field>::parse_from_parameters(vec!["-age", "name"]).unwrap();
+        assert_eq!(
+            sort.0,
+            vec![(Field::Age, SortOrder::Desc), (Field::Name, SortOrder::Asc)]
+        );
+        assert!(Sort::<Field>::parse_from_parameters(Vec::<&str>::new())
+            .unwrap()
+            .is_empty());
+        assert!(Sort::<Field>::parse_from_parameter("email").is_err());
+
+        let MetaSchemaRef::Inline(schema) = Sort::<Field>::schema_ref() else {
+            unreachable!()
+        };
+        assert_eq!(
+            schema.pattern.as_deref(),
+            Some("^([+-]?(name|age)(,[+-]?(name|age))*)?$")
+        );
+    }
+}
@@ -6,8 +6,8 @@ use serde_json::Value;
 use crate::{
     registry::{MetaSchema, MetaSchemaRef, Registry},
     types::{
-        ParseError, ParseFromJSON, ParseFromMultipartField, ParseFromParameter, ParseResult,
-        ToJSON, Type,
+        split_delimited, ParseError, ParseFromJSON, ParseFromMultipartField, ParseFromParameter,
+        ParseResult, ToJSON, Type,
     },
 };
 
@@ -81,6 +81,10 @@ impl<T: ParseFromParameter> ParseFromParameter for Vec<T> {
         }
         Ok(values)
     }
+
+    fn parse_from_delimited(value: &str, delimiter: char) -> ParseResult<Self> {
+        Self::parse_from_parameters(split_delimited(value, delimiter))
+    }
 }
 
 impl<T: ParseFromMultipartField> ParseFromMultipartField for Vec<T> {
@@ -0,0 +1,109 @@
+use std::{
+    borrow::Cow,
+    ops::{Deref, DerefMut},
+};
+
+use serde_json::Value;
+
+use crate::{
+    registry::{MetaSchemaRef, Registry},
+    types::{ParseError, ParseFromJSON, ParseFromParameter, ParseResult, ToJSON, Type},
+};
+
+/// A parameter value serialized as JSON.
+///
+/// It allows the structured (object) parameters in the headers, cookies and
+/// query strings, the parameter is described with `content:
+/// application/json` in the spec.
+///
+/// # Example
+///
+/// ```
+/// use poem_openapi::{param::Header, types::JsonParam, Object, OpenApi};
+///
+/// #[derive(Object)]
+/// struct Tracing {
+///     id: String,
+///     sampled: bool,
+/// }
+///
+/// struct Api;
+///
+/// #[OpenApi]
+/// impl Api {
+///     /// `x-tracing: {"id": "abc", "sampled": true}`
+///     #[oai(path = "/", method = "get")]
+///     async fn index(&self, #[oai(name = "x-tracing")] tracing: Header<JsonParam<Tracing>>) {}
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct JsonParam<T>(pub T);
+
+impl<T> Deref for JsonParam<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for JsonParam<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: Type> Type for JsonParam<T> {
+    const IS_REQUIRED: bool = T::IS_REQUIRED;
+
+    type RawValueType = T::RawValueType;
+
+    type RawElementValueType = T::RawElementValueType;
+
+    fn name() -> Cow<'static, str> {
+        T::name()
+    }
+
+    fn schema_ref() -> MetaSchemaRef {
+        T::schema_ref()
+    }
+
+    fn register(registry: &mut Registry) {
+        T::register(registry);
+    }
+
+    fn as_raw_value(&self) -> Option<&Self::RawValueType> {
+        self.0.as_raw_value()
+    }
+
+    fn raw_element_iter<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = &'a Self::RawElementValueType> + 'a> {
+        self.0.raw_element_iter()
+    }
+}
+
+impl<T: ParseFromJSON> ParseFromJSON for JsonParam<T> {
+    fn parse_from_json(value: Option<Value>) -> ParseResult<Self> {
+        T::parse_from_json(value)
+            .map_err(ParseError::propagate)
+            .map(Self)
+    }
+}
+
+impl<T: ParseFromJSON> ParseFromParameter for JsonParam<T> {
+    const CONTENT_TYPE: Option<&'static str> = Some("application/json");
+
+    fn parse_from_parameter(value: &str) -> ParseResult<Self> {
+        let value = serde_json::from_str(value).map_err(ParseError::custom)?;
+        T::parse_from_json(Some(value))
+            .map_err(ParseError::propagate)
+            .map(Self)
+    }
+}
+
+impl<T: ToJSON> ToJSON for JsonParam<T> {
+    fn to_json(&self) -> Option<Value> {
+        self.0.to_json()
+    }
+}
@@ -365,6 +365,8 @@ impl<T: ParseFromJSON> ParseFromJSON for MaybeUndefined<T> {
 }
 
 impl<T: ParseFromParameter> ParseFromParameter for MaybeUndefined<T> {
+    const CONTENT_TYPE: Option<&'static str> = T::CONTENT_TYPE;
+
     fn parse_from_parameter(_value: &str) -> ParseResult<Self> {
         unreachable!()
     }
@@ -382,6 +384,23 @@ impl<T: ParseFromParameter> ParseFromParameter for MaybeUndefined<T> {
             .map_err(ParseError::propagate)
             .map(MaybeUndefined::Value)
     }
+
+    fn parse_from_delimited(value: &str, delimiter: char) -> ParseResult<Self> {
+        T::parse_from_delimited(value, delimiter)
+            .map_err(ParseError::propagate)
+            .map(MaybeUndefined::Value)
+    }
+
+    fn parse_from_object_parameters<I, K, V>(iter: I) -> ParseResult<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        T::parse_from_object_parameters(iter)
+            .map_err(ParseError::propagate)
+            .map(MaybeUndefined::Value)
+    }
 }
 
 impl<T: ParseFromMultipartField> ParseFromMultipartField for MaybeUndefined<T> {
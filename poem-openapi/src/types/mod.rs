@@ -5,6 +5,7 @@ mod base64_type;
 mod binary;
 mod error;
 mod external;
+mod json_param;
 mod maybe_undefined;
 mod string_types;
 
@@ -16,6 +17,7 @@ pub use any::Any;
 pub use base64_type::Base64;
 pub use binary::Binary;
 pub use error::{ParseError, ParseResult};
+pub use json_param::JsonParam;
 pub use maybe_undefined::MaybeUndefined;
 use poem::{http::HeaderValue, web::Field as PoemField};
 use serde_json::Value;
@@ -125,6 +127,10 @@ pub trait ParseFromYAML: Sized + Type {
 /// Represents a type that can parsing from parameter. (header, query, path,
 /// cookie)
 pub trait ParseFromParameter: Sized + Type {
+    /// The media type of the value if it is serialized as the `content` of
+    /// the parameter, such as `application/json`.
+    const CONTENT_TYPE: Option<&'static str> = None;
+
     /// Parse from parameter.
     fn parse_from_parameter(value: &str) -> ParseResult<Self>;
 
@@ -138,6 +144,55 @@ pub trait ParseFromParameter: Sized + Type {
             None => Err(ParseError::expected_input()),
         }
     }
+
+    /// Parse from a parameter whose elements are separated by the delimiter,
+    /// such as `3,4,5` for an array, or `R,100,G,200` and `R=100,G=200` for an
+    /// object.
+    ///
+    /// The whole value is parsed as a single value by default.
+    fn parse_from_delimited(value: &str, delimiter: char) -> ParseResult<Self> {
+        let _ = delimiter;
+        Self::parse_from_parameter(value)
+    }
+
+    /// Parse from the key-value pairs of an object parameter, such as the
+    /// parameters in the `deepObject` style.
+    fn parse_from_object_parameters<I, K, V>(iter: I) -> ParseResult<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let _ = iter;
+        Err(ParseError::custom("expected an object"))
+    }
+}
+
+/// Splits the elements of a delimited parameter, an empty value has no
+/// elements.
+pub(crate) fn split_delimited(value: &str, delimiter: char) -> impl Iterator<Item = &str> {
+    value
+        .split(delimiter)
+        .map(str::trim)
+        .filter(move |_| !value.is_empty())
+}
+
+/// Parse an object from the elements of a delimited parameter, which are
+/// either `key=value` pairs, or keys and values in turn.
+pub(crate) fn parse_delimited_object<T: ParseFromParameter>(
+    value: &str,
+    delimiter: char,
+) -> ParseResult<T> {
+    let items = split_delimited(value, delimiter).collect::<Vec<_>>();
+    if items.iter().all(|item| item.contains('=')) {
+        T::parse_from_object_parameters(items.iter().filter_map(|item| item.split_once('=')))
+    } else if items.len() % 2 == 0 {
+        T::parse_from_object_parameters(items.chunks(2).map(|pair| (pair[0], pair[1])))
+    } else {
+        Err(ParseError::custom(
+            "expected the key-value pairs of an object",
+        ))
+    }
 }
 
 /// Represents a type that can parsing from multipart.
@@ -296,6 +351,8 @@ impl<T: ParseFromXML> ParseFromXML for Arc<T> {
 }
 
 impl<T: ParseFromParameter> ParseFromParameter for Arc<T> {
+    const CONTENT_TYPE: Option<&'static str> = T::CONTENT_TYPE;
+
     fn parse_from_parameter(_value: &str) -> ParseResult<Self> {
         unreachable!()
     }
@@ -307,6 +364,23 @@ impl<T: ParseFromParameter> ParseFromParameter for Arc<T> {
             .map_err(ParseError::propagate)
             .map(Arc::new)
     }
+
+    fn parse_from_delimited(value: &str, delimiter: char) -> ParseResult<Self> {
+        T::parse_from_delimited(value, delimiter)
+            .map_err(ParseError::propagate)
+            .map(Arc::new)
+    }
+
+    fn parse_from_object_parameters<I, K, V>(iter: I) -> ParseResult<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        T::parse_from_object_parameters(iter)
+            .map_err(ParseError::propagate)
+            .map(Arc::new)
+    }
 }
 
 impl<T: ToJSON> ToJSON for Arc<T> {
@@ -374,6 +448,8 @@ impl<T: ParseFromXML> ParseFromXML for Box<T> {
 }
 
 impl<T: ParseFromParameter> ParseFromParameter for Box<T> {
+    const CONTENT_TYPE: Option<&'static str> = T::CONTENT_TYPE;
+
     fn parse_from_parameter(_value: &str) -> ParseResult<Self> {
         unreachable!()
     }
@@ -385,6 +461,23 @@ impl<T: ParseFromParameter> ParseFromParameter for Box<T> {
             .map_err(ParseError::propagate)
             .map(Box::new)
     }
+
+    fn parse_from_delimited(value: &str, delimiter: char) -> ParseResult<Self> {
+        T::parse_from_delimited(value, delimiter)
+            .map_err(ParseError::propagate)
+            .map(Box::new)
+    }
+
+    fn parse_from_object_parameters<I, K, V>(iter: I) -> ParseResult<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        T::parse_from_object_parameters(iter)
+            .map_err(ParseError::propagate)
+            .map(Box::new)
+    }
 }
 
 impl<T: ParseFromMultipartField> ParseFromMultipartField for Box<T> {
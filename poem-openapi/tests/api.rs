@@ -1,8 +1,9 @@
 use poem::{
     http::{Method, StatusCode},
+    middleware::{ConditionalRequest, ResourceVersion},
     test::TestClient,
-    web::Data,
-    Endpoint, EndpointExt, Error,
+    web::{headers::ETag, Data},
+    Endpoint, EndpointExt, Error, Request,
 };
 use poem_openapi::{
     param::{Path, Query},
@@ -31,6 +32,47 @@ async fn path_and_method() {
     cli.post("/abc").send().await.assert_status_is_ok();
 }
 
+#[tokio::test]
+async fn path_constraints() {
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/users/<id:uint>/files/<name:.*\\.png>", method = "get")]
+        async fn test(&self, id: Path<u64>, name: Path<String>) -> PlainText<String> {
+            PlainText(format!("{}:{}", id.0, name.0))
+        }
+    }
+
+    let meta: MetaApi = Api::meta().remove(0);
+    assert_eq!(meta.paths[0].path, "/users/{id}/files/{name}");
+    let params = &meta.paths[0].operations[0].params;
+    assert_eq!(params[0].name, "id");
+    assert_eq!(
+        params[0].schema.unwrap_inline().pattern.as_deref(),
+        Some("^(?:[0-9]+)$")
+    );
+    assert_eq!(
+        params[1].schema.unwrap_inline().pattern.as_deref(),
+        Some("^(?:.*\\.png)$")
+    );
+
+    let cli = TestClient::new(OpenApiService::new(Api, "test", "1.0"));
+    cli.get("/users/1/files/a.png")
+        .send()
+        .await
+        .assert_text("1:a.png")
+        .await;
+    cli.get("/users/a/files/a.png")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    cli.get("/users/1/files/a.jpg")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
 #[test]
 fn deprecated() {
     struct Api;
@@ -912,6 +954,87 @@ async fn code_samples() {
     assert_eq!(code_sample.source, "Google Go");
 }
 
+#[tokio::test]
+async fn cache_control() {
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/public", method = "get", cache(max_age = 60, vary = "Accept"))]
+        async fn public(&self) -> PlainText<&'static str> {
+            PlainText("hello")
+        }
+
+        #[oai(
+            path = "/private",
+            method = "get",
+            method = "post",
+            cache(max_age = 10, private, vary = "Accept", vary = "Cookie")
+        )]
+        async fn private(&self) -> PlainText<&'static str> {
+            PlainText("hello")
+        }
+
+        #[oai(path = "/error", method = "get", cache(max_age = 60))]
+        async fn error(&self) -> Result<PlainText<&'static str>, Error> {
+            Err(Error::from_status(StatusCode::NOT_FOUND))
+        }
+    }
+
+    let meta: MetaApi = Api::meta().remove(0);
+    let cache = meta.paths[0].operations[0].cache.as_ref().unwrap();
+    assert_eq!(cache.max_age, 60);
+    assert!(!cache.private);
+    assert_eq!(cache.vary, vec!["Accept"]);
+    let cache = meta.paths[1].operations[0].cache.as_ref().unwrap();
+    assert!(cache.private);
+    assert_eq!(cache.vary, vec!["Accept", "Cookie"]);
+
+    let service = OpenApiService::new(Api, "test", "1.0");
+    let spec: serde_json::Value = serde_json::from_str(&service.spec()).unwrap();
+    assert_eq!(
+        spec["paths"]["/public"]["get"]["x-cache"],
+        serde_json::json!({ "maxAge": 60, "vary": ["Accept"] })
+    );
+
+    struct NoVersion;
+
+    impl ResourceVersion for NoVersion {
+        async fn etag<'a>(&'a self, _req: &'a Request) -> poem::Result<Option<ETag>> {
+            Ok(None)
+        }
+    }
+
+    let ep = service.with(ConditionalRequest::new(NoVersion));
+    let cli = TestClient::new(ep);
+
+    let resp = cli.get("/public").send().await;
+    resp.assert_status_is_ok();
+    resp.assert_header("cache-control", "public, max-age=60");
+    resp.assert_header("vary", "Accept");
+    let etag = resp.0.headers().get("etag").unwrap().clone();
+    resp.assert_text("hello").await;
+
+    cli.get("/public")
+        .header("if-none-match", etag)
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_MODIFIED);
+
+    let resp = cli.get("/private").send().await;
+    resp.assert_header("cache-control", "private, max-age=10");
+    resp.assert_header_all("vary", ["Accept", "Cookie"]);
+
+    let resp = cli.post("/private").send().await;
+    resp.assert_status_is_ok();
+    resp.assert_header_is_not_exist("cache-control");
+    resp.assert_header_is_not_exist("etag");
+
+    let resp = cli.get("/error").send().await;
+    resp.assert_status(StatusCode::NOT_FOUND);
+    resp.assert_header_is_not_exist("cache-control");
+}
+
 #[tokio::test]
 async fn hidden() {
     #[derive(Debug, Object)]
@@ -154,6 +154,34 @@ fn write_only_all() {
     );
 }
 
+#[test]
+fn ignore_read_only_fields() {
+    #[derive(Debug, Object, PartialEq)]
+    #[oai(ignore_read_only_fields, deny_unknown_fields)]
+    struct Obj {
+        #[oai(read_only)]
+        id: i32,
+        value: i32,
+    }
+
+    assert_eq!(
+        Obj::parse_from_json(Some(serde_json::json!({
+            "id": 99,
+            "value": 100,
+        })))
+        .unwrap(),
+        Obj { id: 0, value: 100 }
+    );
+
+    assert_eq!(
+        Obj { id: 99, value: 100 }.to_json(),
+        Some(serde_json::json!({
+            "id": 99,
+            "value": 100,
+        }))
+    );
+}
+
 #[test]
 fn field_skip() {
     #[derive(Object, Debug, Eq, PartialEq)]
@@ -844,6 +872,63 @@ fn example_generics() {
     );
 }
 
+#[test]
+fn field_default_value() {
+    #[derive(Object, Debug, PartialEq)]
+    struct Obj {
+        #[oai(default = 10)]
+        a: i32,
+        #[oai(default = -1.5)]
+        b: f64,
+        #[oai(default = true)]
+        c: bool,
+        #[oai(skip, default = 7)]
+        d: u8,
+    }
+
+    let meta = get_meta::<Obj>();
+    assert_eq!(meta.properties.len(), 3);
+    assert_eq!(
+        meta.properties[0].1.unwrap_inline().default,
+        Some(json!(10))
+    );
+    assert_eq!(
+        meta.properties[1].1.unwrap_inline().default,
+        Some(json!(-1.5))
+    );
+    assert_eq!(
+        meta.properties[2].1.unwrap_inline().default,
+        Some(json!(true))
+    );
+    assert!(meta.required.is_empty());
+
+    assert_eq!(
+        Obj::parse_from_json(Some(json!({}))).unwrap(),
+        Obj {
+            a: 10,
+            b: -1.5,
+            c: true,
+            d: 7,
+        }
+    );
+
+    assert_eq!(
+        Obj::parse_from_json(Some(json!({
+            "a": 1,
+            "b": 2.5,
+            "c": false,
+            "d": 3,
+        })))
+        .unwrap(),
+        Obj {
+            a: 1,
+            b: 2.5,
+            c: false,
+            d: 7,
+        }
+    );
+}
+
 #[test]
 fn object_default() {
     #[derive(Object, Debug, Eq, PartialEq)]
@@ -869,6 +954,7 @@ fn object_default() {
 
     let field_meta = meta.properties[1].1.unwrap_inline();
     assert_eq!(field_meta.default, Some(json!("abc")));
+    assert!(meta.required.is_empty());
 
     assert_eq!(
         Obj::parse_from_json(Some(json!({}))).unwrap(),
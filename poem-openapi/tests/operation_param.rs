@@ -1,13 +1,16 @@
+use std::collections::{BTreeMap, HashMap};
+
 use poem::{
-    http::header,
+    http::{header, StatusCode},
     test::TestClient,
     web::cookie::{Cookie, CookieJar, CookieKey},
 };
 use poem_openapi::{
     param::{Cookie as ParamCookie, CookiePrivate, CookieSigned, Header, Path, Query},
-    registry::{MetaApi, MetaParamIn, MetaSchema, MetaSchemaRef},
-    types::Type,
-    OpenApi, OpenApiService,
+    payload::PlainText,
+    registry::{MetaApi, MetaParamIn, MetaParamStyle, MetaSchema, MetaSchemaRef},
+    types::{JsonParam, Type},
+    Object, OpenApi, OpenApiService,
 };
 use serde_json::json;
 
@@ -124,6 +127,89 @@ async fn query_multiple_values_no_explode() {
         .assert_status_is_ok();
 }
 
+#[tokio::test]
+async fn query_delimited_styles() {
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/", method = "get")]
+        async fn test(
+            &self,
+            #[oai(style = "spaceDelimited")] a: Query<Vec<i32>>,
+            #[oai(style = "pipeDelimited")] b: Query<Vec<i32>>,
+            #[oai(style = "pipeDelimited", explode = true)] c: Query<Vec<i32>>,
+            #[oai(style = "pipeDelimited")] d: Query<String>,
+        ) {
+            assert_eq!(a.0, vec![1, 2, 3]);
+            assert_eq!(b.0, vec![4, 5, 6]);
+            assert_eq!(c.0, vec![7, 8]);
+            assert_eq!(d.0, "x|y");
+        }
+    }
+
+    let meta: MetaApi = Api::meta().remove(0);
+    let params = &meta.paths[0].operations[0].params;
+    assert_eq!(params[0].style, Some(MetaParamStyle::SpaceDelimited));
+    assert!(!params[0].explode);
+    assert_eq!(params[1].style, Some(MetaParamStyle::PipeDelimited));
+    assert!(!params[1].explode);
+    assert!(params[2].explode);
+
+    let api = OpenApiService::new(Api, "test", "1.0");
+    TestClient::new(api)
+        .get("/")
+        .query("a", &"1 2 3")
+        .query("b", &"4|5|6")
+        .query("c", &7)
+        .query("c", &8)
+        .query("d", &"x|y")
+        .send()
+        .await
+        .assert_status_is_ok();
+}
+
+#[tokio::test]
+async fn query_deep_object() {
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/", method = "get")]
+        async fn test(
+            &self,
+            #[oai(style = "deepObject")] filter: Query<BTreeMap<String, i32>>,
+            #[oai(style = "deepObject")] other: Query<Option<HashMap<String, String>>>,
+        ) {
+            assert_eq!(
+                filter.0,
+                BTreeMap::from([("min".to_string(), 1), ("max".to_string(), 10)])
+            );
+            assert!(other.0.is_none());
+        }
+    }
+
+    let meta: MetaApi = Api::meta().remove(0);
+    let param = &meta.paths[0].operations[0].params[0];
+    assert_eq!(param.style, Some(MetaParamStyle::DeepObject));
+    assert!(param.explode);
+    assert_eq!(param.schema.unwrap_inline().ty, "object");
+
+    let api = OpenApiService::new(Api, "test", "1.0");
+    let cli = TestClient::new(api);
+    cli.get("/")
+        .query("filter[min]", &1)
+        .query("filter[max]", &10)
+        .send()
+        .await
+        .assert_status_is_ok();
+    cli.get("/")
+        .query("filter[min]", &"a")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
 #[tokio::test]
 async fn query_default() {
     struct Api;
@@ -177,6 +263,46 @@ async fn query_default() {
         .assert_status_is_ok();
 }
 
+#[tokio::test]
+async fn query_default_value() {
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/", method = "get")]
+        async fn test(
+            &self,
+            #[oai(default = 10)] limit: Query<u32>,
+            #[oai(default = -1)] offset: Query<i64>,
+            #[oai(default = false)] desc: Query<bool>,
+        ) -> PlainText<String> {
+            PlainText(format!("{} {} {}", limit.0, offset.0, desc.0))
+        }
+    }
+
+    let meta: MetaApi = Api::meta().remove(0);
+    let params = &meta.paths[0].operations[0].params;
+    assert!(params.iter().all(|param| !param.required));
+    assert_eq!(params[0].schema.unwrap_inline().default, Some(json!(10)));
+    assert_eq!(params[1].schema.unwrap_inline().default, Some(json!(-1)));
+    assert_eq!(params[2].schema.unwrap_inline().default, Some(json!(false)));
+
+    let cli = TestClient::new(OpenApiService::new(Api, "test", "1.0"));
+
+    let resp = cli.get("/").send().await;
+    resp.assert_status_is_ok();
+    resp.assert_text("10 -1 false").await;
+
+    let resp = cli
+        .get("/")
+        .query("limit", &20)
+        .query("desc", &true)
+        .send()
+        .await;
+    resp.assert_status_is_ok();
+    resp.assert_text("20 -1 true").await;
+}
+
 #[tokio::test]
 async fn header() {
     struct Api;
@@ -261,6 +387,175 @@ async fn path() {
         .assert_status_is_ok();
 }
 
+#[tokio::test]
+async fn path_styles() {
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/:a/:b/:c/:d/:e/:f", method = "get")]
+        async fn test(
+            &self,
+            #[oai(style = "simple")] a: Path<Vec<i32>>,
+            #[oai(style = "simple", explode = true)] b: Path<BTreeMap<String, i32>>,
+            #[oai(style = "label")] c: Path<Vec<i32>>,
+            #[oai(style = "label", explode = true)] d: Path<Vec<i32>>,
+            #[oai(style = "matrix")] e: Path<Vec<i32>>,
+            #[oai(style = "matrix", explode = true)] f: Path<BTreeMap<String, i32>>,
+        ) {
+            let map = BTreeMap::from([("R".to_string(), 100), ("G".to_string(), 200)]);
+            assert_eq!(a.0, vec![1, 2]);
+            assert_eq!(b.0, map);
+            assert_eq!(c.0, vec![3, 4]);
+            assert_eq!(d.0, vec![5, 6]);
+            assert_eq!(e.0, vec![7, 8]);
+            assert_eq!(f.0, map);
+        }
+    }
+
+    let meta: MetaApi = Api::meta().remove(0);
+    let params = &meta.paths[0].operations[0].params;
+    assert_eq!(params[0].style, Some(MetaParamStyle::Simple));
+    assert!(!params[0].explode);
+    assert_eq!(params[2].style, Some(MetaParamStyle::Label));
+    assert_eq!(params[4].style, Some(MetaParamStyle::Matrix));
+    assert!(!params[4].explode);
+
+    let api = OpenApiService::new(Api, "test", "1.0");
+    let cli = TestClient::new(api);
+    cli.get("/1,2/R=100,G=200/.3,4/.5.6/;e=7,8/;R=100;G=200")
+        .send()
+        .await
+        .assert_status_is_ok();
+    cli.get("/1,2/R=100,G=200/3,4/.5.6/;e=7,8/;R=100;G=200")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn path_matrix_explode_array() {
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/:v/:k", method = "get")]
+        async fn test(
+            &self,
+            #[oai(style = "matrix", explode = true)] v: Path<Vec<i32>>,
+            #[oai(style = "matrix")] k: Path<i32>,
+        ) {
+            assert_eq!(v.0, vec![1, 2, 3]);
+            assert_eq!(k.0, 4);
+        }
+    }
+
+    let api = OpenApiService::new(Api, "test", "1.0");
+    TestClient::new(api)
+        .get("/;v=1;v=2;v=3/;k=4")
+        .send()
+        .await
+        .assert_status_is_ok();
+}
+
+#[tokio::test]
+async fn header_simple_style() {
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/", method = "get")]
+        async fn test(&self, #[oai(style = "simple")] v: Header<Vec<i32>>) {
+            assert_eq!(v.0, vec![1, 2, 3]);
+        }
+    }
+
+    let api = OpenApiService::new(Api, "test", "1.0");
+    TestClient::new(api)
+        .get("/")
+        .header("v", "1, 2, 3")
+        .send()
+        .await
+        .assert_status_is_ok();
+}
+
+#[tokio::test]
+async fn json_params() {
+    #[derive(Debug, PartialEq, Object)]
+    struct Filter {
+        name: String,
+        limit: i32,
+    }
+
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/", method = "get")]
+        async fn test(
+            &self,
+            #[oai(name = "x-filter")] v1: Header<JsonParam<Filter>>,
+            v2: ParamCookie<JsonParam<Filter>>,
+        ) {
+            assert_eq!(
+                v1.0 .0,
+                Filter {
+                    name: "a".to_string(),
+                    limit: 10
+                }
+            );
+            assert_eq!(
+                v2.0 .0,
+                Filter {
+                    name: "b".to_string(),
+                    limit: 20
+                }
+            );
+        }
+    }
+
+    let meta: MetaApi = Api::meta().remove(0);
+    let params = &meta.paths[0].operations[0].params;
+    assert_eq!(params[0].content_type, Some("application/json"));
+    assert_eq!(params[1].content_type, Some("application/json"));
+    assert_eq!(
+        serde_json::to_value(&params[0]).unwrap(),
+        json!({
+            "name": "x-filter",
+            "in": "header",
+            "required": true,
+            "deprecated": false,
+            "content": {
+                "application/json": {
+                    "schema": {
+                        "$ref": "#/components/schemas/Filter",
+                    },
+                },
+            },
+        })
+    );
+
+    let api = OpenApiService::new(Api, "test", "1.0");
+    let cookie_jar = CookieJar::default();
+    cookie_jar.add(Cookie::new_with_str("v2", r#"{"name":"b","limit":20}"#));
+    let cookie = cookie_jar.get("v2").unwrap().to_string();
+    let cli = TestClient::new(api);
+
+    cli.get("/")
+        .header("x-filter", r#"{"name":"a","limit":10}"#)
+        .header(header::COOKIE, &cookie)
+        .send()
+        .await
+        .assert_status_is_ok();
+
+    cli.get("/")
+        .header("x-filter", "name=a")
+        .header(header::COOKIE, &cookie)
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
 #[tokio::test]
 async fn cookie() {
     struct Api;
@@ -51,3 +51,253 @@ async fn response_wrapper() {
     resp.assert_status(StatusCode::BAD_REQUEST);
     resp.assert_header("MY-HEADER1", "def");
 }
+
+#[tokio::test]
+async fn json_patch() {
+    use poem::http::header;
+    use poem_openapi::{
+        payload::{JsonPatch, MergePatch},
+        registry::{MetaApi, MetaSchemaRef},
+        Object,
+    };
+
+    #[derive(Object, Debug, Clone, Eq, PartialEq)]
+    struct Item {
+        name: String,
+        count: u32,
+    }
+
+    fn item() -> Item {
+        Item {
+            name: "a".to_string(),
+            count: 1,
+        }
+    }
+
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/json-patch", method = "patch")]
+        async fn json_patch(&self, patch: JsonPatch) -> poem::Result<Json<Item>> {
+            let mut doc = serde_json::json!({ "name": item().name, "count": item().count });
+            patch.apply(&mut doc)?;
+            Ok(Json(Item {
+                name: doc["name"].as_str().unwrap().to_string(),
+                count: doc["count"].as_u64().unwrap() as u32,
+            }))
+        }
+
+        #[oai(path = "/merge-patch", method = "patch")]
+        async fn merge_patch(&self, patch: MergePatch<Item>) -> poem::Result<Json<Item>> {
+            Ok(Json(patch.apply(&item())?))
+        }
+    }
+
+    let meta: MetaApi = Api::meta().remove(0);
+    let request = meta.paths[0].operations[0].request.as_ref().unwrap();
+    assert_eq!(
+        request.content[0].content_type,
+        "application/json-patch+json"
+    );
+    let request = meta.paths[1].operations[0].request.as_ref().unwrap();
+    assert_eq!(
+        request.content[0].content_type,
+        "application/merge-patch+json"
+    );
+    assert_eq!(
+        request.content[0].schema,
+        MetaSchemaRef::Reference("Item".to_string())
+    );
+
+    let cli = TestClient::new(OpenApiService::new(Api, "test", "1.0"));
+    let resp = cli
+        .patch("/json-patch")
+        .header(header::CONTENT_TYPE, "application/json-patch+json")
+        .body(r#"[{"op": "replace", "path": "/count", "value": 2}]"#)
+        .send()
+        .await;
+    resp.assert_status_is_ok();
+    resp.assert_json(&serde_json::json!({ "name": "a", "count": 2 }))
+        .await;
+
+    let resp = cli
+        .patch("/merge-patch")
+        .header(header::CONTENT_TYPE, "application/merge-patch+json")
+        .body(r#"{"name": "b"}"#)
+        .send()
+        .await;
+    resp.assert_status_is_ok();
+    resp.assert_json(&serde_json::json!({ "name": "b", "count": 1 }))
+        .await;
+
+    cli.patch("/merge-patch")
+        .header(header::CONTENT_TYPE, "application/merge-patch+json")
+        .body(r#"{"count": "x"}"#)
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    cli.patch("/merge-patch")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(r#"{"name": "b"}"#)
+        .send()
+        .await
+        .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[cfg(feature = "csv")]
+#[tokio::test]
+async fn csv() {
+    use poem::http::header;
+    use poem_openapi::{
+        payload::Csv,
+        registry::{MetaApi, MetaSchemaRef},
+        Object,
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Object, Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    struct Item {
+        name: String,
+        count: Option<u32>,
+    }
+
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/", method = "post")]
+        async fn items(&self, items: Csv<Item>) -> Csv<Item> {
+            Csv(items
+                .0
+                .into_iter()
+                .filter(|item| item.count.is_some())
+                .collect())
+        }
+    }
+
+    let meta: MetaApi = Api::meta().remove(0);
+    let operation = &meta.paths[0].operations[0];
+    let request = operation.request.as_ref().unwrap();
+    assert_eq!(request.content[0].content_type, "text/csv; charset=utf-8");
+    let MetaSchemaRef::Inline(schema) = &request.content[0].schema else {
+        panic!("expect an inline schema");
+    };
+    assert_eq!(schema.ty, "array");
+    assert_eq!(
+        schema.items.as_deref(),
+        Some(&MetaSchemaRef::Reference("Item".to_string()))
+    );
+    assert_eq!(
+        operation.responses.responses[0].content[0].content_type,
+        "text/csv; charset=utf-8"
+    );
+
+    let cli = TestClient::new(OpenApiService::new(Api, "test", "1.0"));
+    let resp = cli
+        .post("/")
+        .header(header::CONTENT_TYPE, "text/csv")
+        .body("count,name\r\n1,\"a, b\"\r\n,c\r\n")
+        .send()
+        .await;
+    resp.assert_status_is_ok();
+    resp.assert_text("name,count\r\n\"a, b\",1\r\n").await;
+
+    cli.post("/")
+        .header(header::CONTENT_TYPE, "text/csv")
+        .body("count,name\r\nx,a\r\n")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn event_stream() {
+    use futures_util::stream::BoxStream;
+    use poem_openapi::{payload::EventStream, registry::MetaApi, Object, Union};
+    use serde_json::json;
+
+    #[derive(Object)]
+    struct Message {
+        text: String,
+    }
+
+    #[derive(Object)]
+    struct Created {
+        id: i64,
+    }
+
+    #[derive(Object)]
+    struct Deleted {
+        id: i64,
+    }
+
+    #[derive(Union)]
+    #[oai(discriminator_name = "type")]
+    enum Change {
+        Created(Created),
+        Deleted(Deleted),
+    }
+
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/messages", method = "get")]
+        async fn messages(&self) -> EventStream<BoxStream<'static, Message>> {
+            EventStream::new(Box::pin(futures_util::stream::iter(vec![Message {
+                text: "hello".to_string(),
+            }])))
+        }
+
+        #[oai(path = "/changes", method = "get")]
+        async fn changes(&self) -> EventStream<BoxStream<'static, Change>> {
+            EventStream::new(Box::pin(futures_util::stream::iter(vec![
+                Change::Created(Created { id: 1 }),
+                Change::Deleted(Deleted { id: 1 }),
+            ])))
+        }
+    }
+
+    let meta: MetaApi = Api::meta().remove(0);
+    let schema = |idx: usize| {
+        serde_json::to_value(
+            &meta.paths[idx].operations[0].responses.responses[0].content[0].schema,
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        schema(0)["x-events"],
+        json!([{
+            "event": "message",
+            "data": { "$ref": "#/components/schemas/Message" },
+        }])
+    );
+    assert_eq!(
+        schema(1)["x-events"],
+        json!([
+            {
+                "event": "Created",
+                "data": { "$ref": "#/components/schemas/Change_Created" },
+            },
+            {
+                "event": "Deleted",
+                "data": { "$ref": "#/components/schemas/Change_Deleted" },
+            },
+        ])
+    );
+
+    let cli = TestClient::new(OpenApiService::new(Api, "test", "1.0"));
+
+    let resp = cli.get("/messages").send().await;
+    resp.assert_status_is_ok();
+    resp.assert_content_type("text/event-stream");
+    resp.assert_text("data: {\"text\":\"hello\"}\n\n").await;
+
+    let resp = cli.get("/changes").send().await;
+    resp.assert_status_is_ok();
+    resp.assert_text(
+        "event: Created\ndata: {\"id\":1,\"type\":\"Created\"}\n\nevent: Deleted\ndata: {\"id\":1,\"type\":\"Deleted\"}\n\n",
+    )
+    .await;
+}
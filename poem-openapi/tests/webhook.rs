@@ -125,7 +125,9 @@ async fn parameters() {
                 description: None,
                 required: true,
                 deprecated: false,
+                style: None,
                 explode: true,
+                content_type: None,
             },
             MetaOperationParam {
                 name: "b".to_string(),
@@ -134,7 +136,9 @@ async fn parameters() {
                 description: None,
                 required: true,
                 deprecated: false,
+                style: None,
                 explode: true,
+                content_type: None,
             }
         ]
     );
@@ -0,0 +1,55 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use poem::{
+    endpoint::make_sync,
+    handler,
+    middleware::Arena,
+    web::{bumpalo, RequestArena},
+    Endpoint, EndpointExt, Request,
+};
+
+/// The number of the small strings built per request.
+const STRINGS: usize = 32;
+
+#[handler]
+fn heap_strings() {
+    for i in 0..STRINGS {
+        black_box(format!("x-header-{}: value-{}", i, i));
+    }
+}
+
+#[handler]
+fn arena_strings(arena: &RequestArena) {
+    arena.with(|bump| {
+        for i in 0..STRINGS {
+            black_box(bumpalo::format!(in bump, "x-header-{}: value-{}", i, i));
+        }
+    });
+}
+
+fn bench(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("arena");
+
+    let baseline = make_sync(|_| ());
+    let heap = heap_strings;
+    let arena = arena_strings.with(Arena::new());
+    group.bench_function("baseline", |b| {
+        b.to_async(&rt)
+            .iter(|| async { baseline.call(Request::default()).await.unwrap() })
+    });
+    group.bench_function("heap", |b| {
+        b.to_async(&rt)
+            .iter(|| async { heap.call(Request::default()).await.unwrap() })
+    });
+    group.bench_function("arena", |b| {
+        b.to_async(&rt)
+            .iter(|| async { arena.call(Request::default()).await.unwrap() })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
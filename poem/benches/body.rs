@@ -0,0 +1,117 @@
+use std::{net::SocketAddr, sync::mpsc, thread};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use poem::{
+    get, handler,
+    http::header,
+    listener::{Acceptor, Listener, TcpListener},
+    Body, Response, Route, Server,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+const CONNECTIONS: usize = 16;
+const REQUESTS: usize = 16;
+const STREAM_SIZE: usize = 1024 * 1024;
+
+static DATA: [u8; STREAM_SIZE] = [b'x'; STREAM_SIZE];
+
+#[handler]
+fn small() -> &'static str {
+    "hello"
+}
+
+#[handler]
+fn stream() -> Response {
+    Response::builder()
+        .header(header::CONTENT_LENGTH, STREAM_SIZE)
+        .body(Body::from_async_read(&DATA[..]))
+}
+
+#[handler]
+async fn file() -> Response {
+    let file = tokio::fs::File::open(file_path()).await.unwrap();
+    Response::builder()
+        .header(header::CONTENT_LENGTH, STREAM_SIZE)
+        .body(Body::from_async_read(file))
+}
+
+fn file_path() -> std::path::PathBuf {
+    std::env::temp_dir().join("poem-bench-body")
+}
+
+/// Starts a server on a multi-thread runtime.
+fn start(writev: bool) -> SocketAddr {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let acceptor = TcpListener::bind("127.0.0.1:0")
+                .into_acceptor()
+                .await
+                .unwrap();
+            tx.send(*acceptor.local_addr()[0].as_socket_addr().unwrap())
+                .unwrap();
+            Server::new_with_acceptor(acceptor)
+                .http1_writev(writev)
+                .run(
+                    Route::new()
+                        .at("/small", get(small))
+                        .at("/stream", get(stream))
+                        .at("/file", get(file)),
+                )
+                .await
+        })
+    });
+    rx.recv().unwrap()
+}
+
+/// Sends the requests on a keep-alive connection.
+async fn send_requests(addr: SocketAddr, path: &'static str) {
+    let mut conn = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    let request = format!("GET {path} HTTP/1.1\r\nhost: localhost\r\n\r\n");
+    let mut line = String::new();
+    let mut body = Vec::new();
+    for _ in 0..REQUESTS {
+        conn.get_mut().write_all(request.as_bytes()).await.unwrap();
+        let mut content_length = 0;
+        loop {
+            line.clear();
+            conn.read_line(&mut line).await.unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(len) = line.strip_prefix("content-length: ") {
+                content_length = len.trim().parse().unwrap();
+            }
+        }
+        body.resize(content_length, 0);
+        conn.read_exact(&mut body).await.unwrap();
+    }
+}
+
+fn body(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    std::fs::write(file_path(), DATA).unwrap();
+    let mut group = c.benchmark_group("body");
+    group.throughput(Throughput::Elements((CONNECTIONS * REQUESTS) as u64));
+
+    for (name, addr) in [("writev", start(true)), ("flatten", start(false))] {
+        for path in ["/small", "/stream", "/file"] {
+            group.bench_with_input(BenchmarkId::new(name, path), &path, |b, path| {
+                b.to_async(&rt).iter(|| async {
+                    let clients = (0..CONNECTIONS).map(|_| tokio::spawn(send_requests(addr, path)));
+                    for client in clients.collect::<Vec<_>>() {
+                        client.await.unwrap();
+                    }
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, body);
+criterion_main!(benches);
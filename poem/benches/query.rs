@@ -0,0 +1,74 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use poem::{endpoint::make_sync, Endpoint, Request, Route};
+use serde::Deserialize;
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct Params {
+    q: String,
+    page: u32,
+    per_page: u32,
+    sort: String,
+    order: String,
+    fields: String,
+    filter: String,
+    lang: String,
+    region: String,
+    token: String,
+}
+
+fn query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("query");
+
+    for (name, query) in [
+        (
+            "plain",
+            "q=poem&page=2&per_page=50&sort=created_at&order=desc&fields=id,name,email,created_at\
+             &filter=status:active&lang=en&region=us-east-1&token=0123456789abcdef0123456789abcdef",
+        ),
+        (
+            "escaped",
+            "q=poem+web+framework&page=2&per_page=50&sort=created_at&order=desc\
+             &fields=id%2Cname%2Cemail%2Ccreated_at&filter=status%3Aactive+AND+age%3E18\
+             &lang=zh-CN&region=%E4%B8%AD%E5%9B%BD&token=0123456789abcdef0123456789abcdef",
+        ),
+    ] {
+        let req = Request::builder()
+            .uri_str(format!("/search?{query}"))
+            .finish();
+        group.bench_with_input(BenchmarkId::new("serde_urlencoded", name), query, |b, q| {
+            b.iter(|| serde_urlencoded::from_str::<Params>(q).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("params", name), &req, |b, req| {
+            b.iter(|| req.params::<Params>().unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn path(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("path");
+    let route = Route::new().at("/files/:dir/:name", make_sync(|_| ()));
+
+    for (name, path) in [
+        ("plain", "/files/documents/annual-report-2024.pdf"),
+        (
+            "escaped",
+            "/files/%E6%96%87%E6%A1%A3/annual%20report%202024%20%28final%29.pdf",
+        ),
+    ] {
+        group.bench_with_input(BenchmarkId::new("route", name), path, |b, path| {
+            b.to_async(&rt).iter(|| async {
+                let req = Request::builder().uri_str(path).finish();
+                route.call(req).await.unwrap();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, query, path);
+criterion_main!(benches);
@@ -0,0 +1,79 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use poem::Request;
+
+/// Counts the allocations to report the allocations per request copy.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn create_request() -> Request {
+    (0..20)
+        .fold(
+            Request::builder().uri_str("/api/v1/users/12345?fields=name,email"),
+            |builder, i| builder.header(format!("x-header-{i}"), "value"),
+        )
+        .finish()
+}
+
+/// Copies the request by rebuilding it from the components.
+fn rebuild(req: &Request) -> Request {
+    let mut builder = Request::builder()
+        .method(req.method().clone())
+        .uri(req.uri().clone())
+        .version(req.version());
+    for (name, value) in req.headers() {
+        builder = builder.header(name, value);
+    }
+    builder.finish()
+}
+
+fn allocations(f: impl Fn() -> Request) -> usize {
+    let start = ALLOCATIONS.load(Ordering::Relaxed);
+    drop(f());
+    ALLOCATIONS.load(Ordering::Relaxed) - start
+}
+
+fn request(c: &mut Criterion) {
+    let req = create_request();
+    println!(
+        "allocations per copy: rebuild {}, clone_without_body {}",
+        allocations(|| rebuild(&req)),
+        allocations(|| req.clone_without_body()),
+    );
+
+    let mut group = c.benchmark_group("request");
+    group.bench_function("rebuild", |b| b.iter(|| rebuild(&req)));
+    group.bench_function("clone_without_body", |b| {
+        b.iter(|| req.clone_without_body())
+    });
+    group.bench_function("clone_and_modify_headers", |b| {
+        b.iter(|| {
+            let mut req = req.clone_without_body();
+            req.headers_mut().remove("x-header-0");
+            req
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, request);
+criterion_main!(benches);
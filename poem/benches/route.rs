@@ -0,0 +1,44 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use poem::{endpoint::make_sync, Endpoint, Request, Route};
+
+/// Creates a route with `n` groups of static, parameter and regex routes.
+fn create_route(n: usize) -> Route {
+    (0..n).fold(Route::new(), |route, i| {
+        route
+            .at(format!("/static{i}/users/list"), make_sync(|_| ()))
+            .at(
+                format!("/params{i}/:user_id/posts/:post_id"),
+                make_sync(|_| ()),
+            )
+            .at(format!("/regex{i}/<id:int>/files/*path"), make_sync(|_| ()))
+    })
+}
+
+fn route(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("route");
+
+    for n in [10, 100, 500] {
+        let route = create_route(n);
+        let last = n - 1;
+
+        for (name, path) in [
+            ("static", format!("/static{last}/users/list")),
+            ("params", format!("/params{last}/12/posts/34")),
+            ("regex", format!("/regex{last}/56/files/a/b.png")),
+            ("not_found", format!("/missing{last}/users/list")),
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, n), &path, |b, path| {
+                b.to_async(&rt).iter(|| async {
+                    let req = Request::builder().uri_str(path).finish();
+                    let _ = route.call(req).await;
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, route);
+criterion_main!(benches);
@@ -0,0 +1,102 @@
+use std::{net::SocketAddr, sync::mpsc, thread};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use poem::{
+    handler,
+    listener::{Acceptor, Listener, TcpListener},
+    Server, ShardedServer,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+const CONNECTIONS: usize = 64;
+const REQUESTS: usize = 16;
+
+#[handler]
+fn hello() -> &'static str {
+    "hello"
+}
+
+/// Starts a server on a multi-thread runtime.
+fn start_default() -> SocketAddr {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let acceptor = TcpListener::bind("127.0.0.1:0")
+                .into_acceptor()
+                .await
+                .unwrap();
+            tx.send(*acceptor.local_addr()[0].as_socket_addr().unwrap())
+                .unwrap();
+            Server::new_with_acceptor(acceptor).run(hello).await
+        })
+    });
+    rx.recv().unwrap()
+}
+
+/// Starts a server with a shard per core.
+fn start_sharded() -> SocketAddr {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        ShardedServer::new(TcpListener::bind("127.0.0.1:0"))
+            .configure(move |server| {
+                let tx = tx.clone();
+                server.on_start(move |info| async move {
+                    let _ = tx.send(*info.local_addrs()[0].as_socket_addr().unwrap());
+                    Ok(())
+                })
+            })
+            .run(|| hello)
+    });
+    rx.recv().unwrap()
+}
+
+/// Sends the requests on a keep-alive connection.
+async fn send_requests(addr: SocketAddr) {
+    let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    let mut line = String::new();
+    for _ in 0..REQUESTS {
+        stream
+            .get_mut()
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut content_length = 0;
+        loop {
+            line.clear();
+            stream.read_line(&mut line).await.unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(len) = line.strip_prefix("content-length: ") {
+                content_length = len.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; content_length];
+        stream.read_exact(&mut body).await.unwrap();
+    }
+}
+
+fn server(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("server");
+    group.throughput(Throughput::Elements((CONNECTIONS * REQUESTS) as u64));
+
+    for (name, addr) in [("default", start_default()), ("sharded", start_sharded())] {
+        group.bench_with_input(BenchmarkId::new(name, CONNECTIONS), &addr, |b, addr| {
+            b.to_async(&rt).iter(|| async {
+                let clients = (0..CONNECTIONS).map(|_| tokio::spawn(send_requests(*addr)));
+                for client in clients.collect::<Vec<_>>() {
+                    client.await.unwrap();
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, server);
+criterion_main!(benches);
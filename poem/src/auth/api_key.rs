@@ -0,0 +1,717 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::RwLock;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    clock::{Clock, SharedClock},
+    error::ApiKeyError,
+    http::{header, HeaderName, Method, StatusCode},
+    web::Json,
+    Endpoint, FromRequest, IntoResponse, Middleware, Request, RequestBody, Response, Result,
+};
+
+/// An API key stored in an [`ApiKeyStore`].
+///
+/// Only the SHA-256 hash of the secret is stored, the plaintext key is
+/// returned once when it is issued.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ApiKey {
+    /// The identifier of the key.
+    pub id: String,
+    /// The name of the key.
+    pub name: String,
+    /// The hex encoded SHA-256 hash of the secret.
+    pub hash: String,
+    /// The scopes granted to the key.
+    pub scopes: BTreeSet<String>,
+    /// The time when the key was issued.
+    pub created_at: SystemTime,
+    /// The time when the key expires.
+    pub expires_at: Option<SystemTime>,
+}
+
+impl ApiKey {
+    /// Returns `true` if the key has expired.
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(SystemTime::now())
+    }
+
+    /// Returns `true` if the key has expired at `now`.
+    pub fn is_expired_at(&self, now: SystemTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Returns `true` if the key is granted the scope.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.contains(scope)
+    }
+}
+
+impl<'a> FromRequest<'a> for &'a ApiKey {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<ApiKey>()
+            .expect("To use the `ApiKey` extractor, the `ApiKeyAuth` middleware is required."))
+    }
+}
+
+/// Represents a back-end storage of the API keys.
+pub trait ApiKeyStore: Send + Sync + 'static {
+    /// Load an API key by id.
+    fn get<'a>(&'a self, id: &'a str) -> impl Future<Output = Result<Option<ApiKey>>> + Send + 'a;
+
+    /// Insert an API key.
+    fn insert<'a>(&'a self, key: &'a ApiKey) -> impl Future<Output = Result<()>> + Send + 'a;
+
+    /// Remove an API key by id, returns `false` if it does not exist.
+    fn remove<'a>(&'a self, id: &'a str) -> impl Future<Output = Result<bool>> + Send + 'a;
+
+    /// Returns all the API keys.
+    fn list(&self) -> impl Future<Output = Result<Vec<ApiKey>>> + Send + '_;
+}
+
+/// An API key storage using memory.
+#[derive(Default)]
+pub struct MemoryApiKeyStore {
+    keys: RwLock<BTreeMap<String, ApiKey>>,
+}
+
+impl MemoryApiKeyStore {
+    /// Create a `MemoryApiKeyStore`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl ApiKeyStore for MemoryApiKeyStore {
+    async fn get<'a>(&'a self, id: &'a str) -> Result<Option<ApiKey>> {
+        Ok(self.keys.read().get(id).cloned())
+    }
+
+    async fn insert<'a>(&'a self, key: &'a ApiKey) -> Result<()> {
+        self.keys.write().insert(key.id.clone(), key.clone());
+        Ok(())
+    }
+
+    async fn remove<'a>(&'a self, id: &'a str) -> Result<bool> {
+        Ok(self.keys.write().remove(id).is_some())
+    }
+
+    async fn list(&self) -> Result<Vec<ApiKey>> {
+        Ok(self.keys.read().values().cloned().collect())
+    }
+}
+
+/// A newly issued API key.
+#[derive(Debug, Clone)]
+pub struct IssuedApiKey {
+    /// The plaintext key, which cannot be retrieved again.
+    pub key: String,
+    /// The stored API key.
+    pub info: ApiKey,
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+fn random_hex(len: usize) -> String {
+    let mut data = vec![0; len];
+    rand::thread_rng().fill_bytes(&mut data);
+    hex::encode(data)
+}
+
+/// Issues, verifies and revokes the API keys in an [`ApiKeyStore`].
+///
+/// The keys have the format `{prefix}_{id}_{secret}`, the prefix makes the
+/// keys easy to recognize, for example by secret scanners.
+///
+/// # Example
+///
+/// ```
+/// use poem::auth::{ApiKeyManager, MemoryApiKeyStore};
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let keys = ApiKeyManager::new(MemoryApiKeyStore::new()).prefix("myapp");
+/// let issued = keys.issue("ci", ["read"], None).await.unwrap();
+/// assert!(issued.key.starts_with("myapp_"));
+///
+/// let info = keys.verify(&issued.key).await.unwrap();
+/// assert_eq!(info.name, "ci");
+/// # });
+/// ```
+pub struct ApiKeyManager<S> {
+    store: Arc<S>,
+    prefix: Arc<str>,
+}
+
+impl<S> Clone for ApiKeyManager<S> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            prefix: self.prefix.clone(),
+        }
+    }
+}
+
+impl<S: ApiKeyStore> ApiKeyManager<S> {
+    /// Create an `ApiKeyManager` with the store.
+    pub fn new(store: S) -> Self {
+        Self {
+            store: Arc::new(store),
+            prefix: "key".into(),
+        }
+    }
+
+    /// Sets the prefix of the keys.
+    ///
+    /// Default is `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the prefix is empty or contains `_`.
+    #[must_use]
+    pub fn prefix(self, prefix: impl Into<String>) -> Self {
+        let prefix = prefix.into();
+        assert!(
+            !prefix.is_empty() && !prefix.contains('_'),
+            "the prefix must be non-empty and must not contain `_`"
+        );
+        Self {
+            prefix: prefix.into(),
+            ..self
+        }
+    }
+
+    /// Returns the store.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Issues a new key with the scopes, which expires after `ttl` if it is
+    /// specified.
+    ///
+    /// Returns [`ApiKeyError::InvalidTtl`] if the expiration time is out of
+    /// range.
+    pub async fn issue(
+        &self,
+        name: impl Into<String>,
+        scopes: impl IntoIterator<Item = impl Into<String>>,
+        ttl: Option<Duration>,
+    ) -> Result<IssuedApiKey> {
+        self.issue_at(name, scopes, ttl, SystemTime::now()).await
+    }
+
+    pub(crate) async fn issue_at(
+        &self,
+        name: impl Into<String>,
+        scopes: impl IntoIterator<Item = impl Into<String>>,
+        ttl: Option<Duration>,
+        created_at: SystemTime,
+    ) -> Result<IssuedApiKey> {
+        let expires_at = ttl
+            .map(|ttl| created_at.checked_add(ttl).ok_or(ApiKeyError::InvalidTtl))
+            .transpose()?;
+        let id = random_hex(8);
+        let secret = random_hex(32);
+        let info = ApiKey {
+            id: id.clone(),
+            name: name.into(),
+            hash: hash_secret(&secret),
+            scopes: scopes.into_iter().map(Into::into).collect(),
+            created_at,
+            expires_at,
+        };
+        self.store.insert(&info).await?;
+        Ok(IssuedApiKey {
+            key: format!("{}_{}_{}", self.prefix, id, secret),
+            info,
+        })
+    }
+
+    /// Verifies the plaintext key and returns the stored key.
+    pub async fn verify(&self, key: &str) -> Result<ApiKey> {
+        self.verify_at(key, SystemTime::now()).await
+    }
+
+    pub(crate) async fn verify_at(&self, key: &str, now: SystemTime) -> Result<ApiKey> {
+        let (id, secret) = key
+            .strip_prefix(&*self.prefix)
+            .and_then(|key| key.strip_prefix('_'))
+            .and_then(|key| key.split_once('_'))
+            .ok_or(ApiKeyError::Invalid)?;
+        let info = self.store.get(id).await?.ok_or(ApiKeyError::Invalid)?;
+        if !bool::from(hash_secret(secret).as_bytes().ct_eq(info.hash.as_bytes())) {
+            return Err(ApiKeyError::Invalid.into());
+        }
+        if info.is_expired_at(now) {
+            return Err(ApiKeyError::Expired.into());
+        }
+        Ok(info)
+    }
+
+    /// Revokes a key by id, returns `false` if it does not exist.
+    pub async fn revoke(&self, id: &str) -> Result<bool> {
+        self.store.remove(id).await
+    }
+
+    /// Returns an endpoint for managing the keys.
+    ///
+    /// See [`ApiKeyAdminEndpoint`] for details.
+    pub fn admin_endpoint(&self) -> ApiKeyAdminEndpoint<S> {
+        ApiKeyAdminEndpoint {
+            manager: self.clone(),
+        }
+    }
+}
+
+/// Middleware for authenticating the requests with the API keys.
+///
+/// By default, the key is read from the `X-API-Key` header. The request is
+/// rejected with `401 Unauthorized` if the key is missing, invalid or expired,
+/// and with `403 Forbidden` if the key is not granted all the required scopes.
+/// Use the [`&ApiKey`](ApiKey) extractor to get the key in the handlers.
+///
+/// # Errors
+///
+/// - [`ApiKeyError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     auth::{ApiKey, ApiKeyAuth, ApiKeyManager, MemoryApiKeyStore},
+///     get, handler, post, EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn list_users(key: &ApiKey) -> String {
+///     format!("hello {}", key.name)
+/// }
+///
+/// #[handler]
+/// fn create_user() {}
+///
+/// let keys = ApiKeyManager::new(MemoryApiKeyStore::new());
+/// let app = Route::new()
+///     .at(
+///         "/users",
+///         get(list_users.with(ApiKeyAuth::new(keys.clone()).scope("users:read")))
+///             .post(create_user.with(ApiKeyAuth::new(keys.clone()).scope("users:write"))),
+///     )
+///     .nest(
+///         "/admin/keys",
+///         keys.admin_endpoint()
+///             .with(ApiKeyAuth::new(keys.clone()).scope("keys:admin")),
+///     );
+/// ```
+pub struct ApiKeyAuth<S> {
+    manager: ApiKeyManager<S>,
+    header: Option<HeaderName>,
+    bearer: bool,
+    query: Option<String>,
+    scopes: Vec<String>,
+}
+
+impl<S: ApiKeyStore> ApiKeyAuth<S> {
+    /// Create `ApiKeyAuth` middleware with the manager.
+    pub fn new(manager: ApiKeyManager<S>) -> Self {
+        Self {
+            manager,
+            header: Some(HeaderName::from_static("x-api-key")),
+            bearer: false,
+            query: None,
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Sets the name of the header to read the key from.
+    ///
+    /// Default is `X-API-Key`.
+    #[must_use]
+    pub fn header(self, name: HeaderName) -> Self {
+        Self {
+            header: Some(name),
+            ..self
+        }
+    }
+
+    /// Also reads the key from the `Authorization: Bearer` header.
+    #[must_use]
+    pub fn bearer(self) -> Self {
+        Self {
+            bearer: true,
+            ..self
+        }
+    }
+
+    /// Also reads the key from the query parameter.
+    #[must_use]
+    pub fn query(self, name: impl Into<String>) -> Self {
+        Self {
+            query: Some(name.into()),
+            ..self
+        }
+    }
+
+    /// Requires the key to be granted the scope.
+    ///
+    /// Can be called multiple times to require multiple scopes.
+    #[must_use]
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scopes.push(scope.into());
+        self
+    }
+}
+
+impl<S: ApiKeyStore, E: Endpoint> Middleware<E> for ApiKeyAuth<S> {
+    type Output = ApiKeyAuthEndpoint<S, E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ApiKeyAuthEndpoint {
+            inner: ep,
+            manager: self.manager.clone(),
+            header: self.header.clone(),
+            bearer: self.bearer,
+            query: self.query.clone(),
+            scopes: self.scopes.clone(),
+        }
+    }
+}
+
+/// Endpoint for the ApiKeyAuth middleware.
+pub struct ApiKeyAuthEndpoint<S, E> {
+    inner: E,
+    manager: ApiKeyManager<S>,
+    header: Option<HeaderName>,
+    bearer: bool,
+    query: Option<String>,
+    scopes: Vec<String>,
+}
+
+impl<S, E> ApiKeyAuthEndpoint<S, E> {
+    fn extract_key(&self, req: &Request) -> Option<String> {
+        if let Some(value) = self
+            .header
+            .as_ref()
+            .and_then(|name| req.headers().get(name))
+            .and_then(|value| value.to_str().ok())
+        {
+            return Some(value.trim().to_string());
+        }
+
+        if self.bearer {
+            if let Some(token) = req
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split_once(' '))
+                .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+                .map(|(_, token)| token.trim().to_string())
+            {
+                return Some(token);
+            }
+        }
+
+        let name = self.query.as_deref()?;
+        crate::urlencoded::from_str::<Vec<(String, String)>>(req.uri().query().unwrap_or_default())
+            .ok()?
+            .into_iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+}
+
+impl<S: ApiKeyStore, E: Endpoint> Endpoint for ApiKeyAuthEndpoint<S, E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let key = self.extract_key(&req).ok_or(ApiKeyError::Missing)?;
+        let now = SharedClock::from_request(&req).system_time();
+        let info = self.manager.verify_at(&key, now).await?;
+        if let Some(scope) = self.scopes.iter().find(|scope| !info.has_scope(scope)) {
+            return Err(ApiKeyError::InsufficientScope {
+                scope: scope.clone(),
+            }
+            .into());
+        }
+        req.extensions_mut().insert(info);
+        self.inner.call(req).await
+    }
+}
+
+#[derive(Deserialize)]
+struct IssueRequest {
+    name: String,
+    #[serde(default)]
+    scopes: Vec<String>,
+    /// The time to live in seconds.
+    #[serde(default)]
+    ttl: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct KeyView<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<&'a str>,
+    id: &'a str,
+    name: &'a str,
+    scopes: &'a BTreeSet<String>,
+    created_at: u64,
+    expires_at: Option<u64>,
+}
+
+fn unix_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl<'a> KeyView<'a> {
+    fn new(info: &'a ApiKey, key: Option<&'a str>) -> Self {
+        Self {
+            key,
+            id: &info.id,
+            name: &info.name,
+            scopes: &info.scopes,
+            created_at: unix_timestamp(info.created_at),
+            expires_at: info.expires_at.map(unix_timestamp),
+        }
+    }
+}
+
+/// An endpoint for managing the API keys of an [`ApiKeyManager`].
+///
+/// It should be nested in a route protected by an authentication middleware,
+/// and handles the following requests:
+///
+/// - `GET /` returns all the keys without the hashes.
+/// - `POST /` issues a key with a JSON body like `{"name": "ci", "scopes":
+///   ["read"], "ttl": 3600}`, and responds with `201 Created` and the key
+///   including the plaintext `key` field.
+/// - `DELETE /{id}` revokes a key, and responds with `204 No Content`, or `404
+///   Not Found` if it does not exist.
+pub struct ApiKeyAdminEndpoint<S> {
+    manager: ApiKeyManager<S>,
+}
+
+impl<S: ApiKeyStore> Endpoint for ApiKeyAdminEndpoint<S> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let id = req.uri().path().trim_matches('/').to_string();
+        match (req.method().clone(), id.is_empty()) {
+            (Method::GET, true) => {
+                let keys = self.manager.store().list().await?;
+                let views = keys
+                    .iter()
+                    .map(|info| KeyView::new(info, None))
+                    .collect::<Vec<_>>();
+                Ok(Json(views).into_response())
+            }
+            (Method::POST, true) => {
+                let now = SharedClock::from_request(&req).system_time();
+                let issue = req.take_body().into_json::<IssueRequest>().await?;
+                let issued = self
+                    .manager
+                    .issue_at(
+                        issue.name,
+                        issue.scopes,
+                        issue.ttl.map(Duration::from_secs),
+                        now,
+                    )
+                    .await?;
+                Ok(Json(KeyView::new(&issued.info, Some(&issued.key)))
+                    .with_status(StatusCode::CREATED)
+                    .into_response())
+            }
+            (Method::DELETE, false) if !id.contains('/') => {
+                if self.manager.revoke(&id).await? {
+                    Ok(StatusCode::NO_CONTENT.into())
+                } else {
+                    Ok(StatusCode::NOT_FOUND.into())
+                }
+            }
+            (_, true) | (Method::DELETE, false) => Ok(StatusCode::METHOD_NOT_ALLOWED.into()),
+            _ => Ok(StatusCode::NOT_FOUND.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::MockClock, handler, test::TestClient, EndpointExt, Route};
+
+    #[handler(internal)]
+    fn index(key: &ApiKey) -> String {
+        key.name.clone()
+    }
+
+    #[tokio::test]
+    async fn api_key_auth() {
+        let keys = ApiKeyManager::new(MemoryApiKeyStore::new()).prefix("test");
+        let issued = keys.issue("ci", ["read"], None).await.unwrap();
+        assert!(issued.key.starts_with("test_"));
+        assert_ne!(issued.info.hash, issued.key);
+
+        let cli = TestClient::new(
+            Route::new()
+                .at(
+                    "/read",
+                    index.with(ApiKeyAuth::new(keys.clone()).query("key")),
+                )
+                .at(
+                    "/write",
+                    index.with(ApiKeyAuth::new(keys.clone()).bearer().scope("write")),
+                ),
+        );
+
+        cli.get("/read")
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+        let resp = cli
+            .get("/read")
+            .header("x-api-key", &issued.key)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("ci").await;
+        cli.get("/read")
+            .query("key", &issued.key)
+            .send()
+            .await
+            .assert_text("ci")
+            .await;
+
+        let mut forged = issued.key.clone();
+        forged.pop();
+        forged.push('x');
+        cli.get("/read")
+            .header("x-api-key", &forged)
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+
+        cli.get("/write")
+            .header("authorization", format!("Bearer {}", issued.key))
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+
+        let expired = keys
+            .issue("old", ["read"], Some(Duration::ZERO))
+            .await
+            .unwrap();
+        cli.get("/read")
+            .header("x-api-key", &expired.key)
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+
+        assert!(keys.revoke(&issued.info.id).await.unwrap());
+        cli.get("/read")
+            .header("x-api-key", &issued.key)
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn admin_endpoint() {
+        let keys = ApiKeyManager::new(MemoryApiKeyStore::new());
+        let cli = TestClient::new(Route::new().nest("/keys", keys.admin_endpoint()));
+
+        let resp = cli
+            .post("/keys")
+            .body_json(&serde_json::json!({ "name": "ci", "scopes": ["read"], "ttl": 60 }))
+            .send()
+            .await;
+        resp.assert_status(StatusCode::CREATED);
+        let value = resp.json().await;
+        let value = value.value().object();
+        let key = value.get("key").string().to_string();
+        let id = value.get("id").string().to_string();
+        assert_eq!(keys.verify(&key).await.unwrap().name, "ci");
+
+        cli.post("/keys")
+            .body_json(&serde_json::json!({ "name": "ci", "ttl": u64::MAX }))
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.post("/keys")
+            .body_json(&serde_json::json!({ "scopes": ["read"] }))
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+
+        let resp = cli.get("/keys").send().await;
+        resp.assert_status_is_ok();
+        let value = resp.json().await;
+        let list = value.value().array();
+        list.assert_len(1);
+        list.get(0).object().get("id").assert_string(&id);
+        assert!(list.get(0).object().get_opt("key").is_none());
+
+        cli.delete(format!("/keys/{id}"))
+            .send()
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+        cli.delete(format!("/keys/{id}"))
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+        let err = keys.verify(&key).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(ApiKeyError::Invalid)));
+    }
+
+    #[tokio::test]
+    async fn expires_with_clock() {
+        let keys = ApiKeyManager::new(MemoryApiKeyStore::new());
+        let clock = MockClock::new();
+        let cli = TestClient::new(
+            Route::new()
+                .nest("/keys", keys.admin_endpoint())
+                .at("/read", index.with(ApiKeyAuth::new(keys.clone())))
+                .data(SharedClock::new(clock.clone())),
+        );
+
+        let resp = cli
+            .post("/keys")
+            .body_json(&serde_json::json!({ "name": "ci", "ttl": 60 }))
+            .send()
+            .await;
+        resp.assert_status(StatusCode::CREATED);
+        let value = resp.json().await;
+        let value = value.value().object();
+        value.get("created_at").assert_i64(0);
+        value.get("expires_at").assert_i64(60);
+        let key = value.get("key").string().to_string();
+
+        clock.advance(Duration::from_secs(59));
+        cli.get("/read")
+            .header("x-api-key", &key)
+            .send()
+            .await
+            .assert_text("ci")
+            .await;
+
+        clock.advance(Duration::from_secs(1));
+        cli.get("/read")
+            .header("x-api-key", &key)
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+}
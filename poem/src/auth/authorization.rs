@@ -0,0 +1,655 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    future::Future,
+    io,
+    marker::PhantomData,
+    path::Path,
+    sync::Arc,
+};
+
+use futures_util::future::BoxFuture;
+
+use super::{ApiKey, AuthenticatedUser};
+use crate::{
+    error::AuthorizationError, Endpoint, FromRequest, Middleware, Request, RequestBody, Result,
+};
+
+/// Returns the name of the subject authenticated by the
+/// [`BasicAuth`](super::BasicAuth), [`DigestAuth`](super::DigestAuth) or
+/// [`ApiKeyAuth`](super::ApiKeyAuth) middleware.
+///
+/// The name of the user is returned for the basic and digest authentication,
+/// and the name of the key prefixed with `key:` is returned for the API key
+/// authentication, such as `key:ci`, so that a key can not be named after a
+/// user to be granted the roles of the user.
+pub fn subject(req: &Request) -> Option<Cow<'_, str>> {
+    if let Some(user) = req.extensions().get::<AuthenticatedUser>() {
+        return Some(Cow::Borrowed(&user.0));
+    }
+    req.extensions()
+        .get::<ApiKey>()
+        .map(|key| Cow::Owned(format!("key:{}", key.name)))
+}
+
+/// Returns `true` if the permission matches the pattern.
+///
+/// The pattern `*` matches any permission, and the pattern ending with `:*`
+/// such as `users:*` matches the permissions starting with `users:`.
+fn permission_matches(pattern: &str, permission: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some("") => true,
+        Some(prefix) if prefix.ends_with(':') => permission.starts_with(prefix),
+        _ => pattern == permission,
+    }
+}
+
+/// Represents an authorization policy.
+pub trait Policy: Send + Sync + 'static {
+    /// Returns `true` if the request is granted the permission.
+    fn authorize<'a>(
+        &'a self,
+        req: &'a Request,
+        permission: &'a str,
+    ) -> impl Future<Output = Result<bool>> + Send + 'a;
+}
+
+impl<T: Policy> Policy for Arc<T> {
+    fn authorize<'a>(
+        &'a self,
+        req: &'a Request,
+        permission: &'a str,
+    ) -> impl Future<Output = Result<bool>> + Send + 'a {
+        self.as_ref().authorize(req, permission)
+    }
+}
+
+trait DynPolicy: Send + Sync {
+    fn authorize<'a>(
+        &'a self,
+        req: &'a Request,
+        permission: &'a str,
+    ) -> BoxFuture<'a, Result<bool>>;
+}
+
+impl<T: Policy> DynPolicy for T {
+    fn authorize<'a>(
+        &'a self,
+        req: &'a Request,
+        permission: &'a str,
+    ) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(Policy::authorize(self, req, permission))
+    }
+}
+
+#[derive(Clone)]
+struct SharedPolicy(Arc<dyn DynPolicy>);
+
+#[doc(hidden)]
+pub async fn check_permission(req: &Request, permission: &str) -> Result<()> {
+    let policy = req
+        .extensions()
+        .get::<SharedPolicy>()
+        .expect("To check the permissions, the `Authorization` middleware is required.");
+    if policy.0.authorize(req, permission).await? {
+        Ok(())
+    } else {
+        Err(AuthorizationError::Forbidden {
+            permission: permission.to_string(),
+        }
+        .into())
+    }
+}
+
+/// A role based access control policy.
+///
+/// The subject of the request is determined by [`subject`], the request is
+/// granted the permission if any role of the subject is granted it. The roles
+/// of an API key are assigned to its name with the `key:` prefix.
+///
+/// # Example
+///
+/// ```
+/// use poem::auth::RbacPolicy;
+///
+/// let policy = RbacPolicy::new()
+///     .role("admin", ["users:*"])
+///     .role("viewer", ["users:read"])
+///     .assign("alice", "admin")
+///     .assign("bob", "viewer")
+///     .assign("key:ci", "viewer");
+/// ```
+#[derive(Default)]
+pub struct RbacPolicy {
+    roles: HashMap<String, Vec<String>>,
+    assignments: HashMap<String, Vec<String>>,
+}
+
+impl RbacPolicy {
+    /// Create a `RbacPolicy`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Grants the permissions to the role.
+    ///
+    /// The permission `*` matches any permission, and the permission ending
+    /// with `:*` matches the permissions with the same prefix.
+    #[must_use]
+    pub fn role(
+        mut self,
+        role: impl Into<String>,
+        permissions: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.roles
+            .entry(role.into())
+            .or_default()
+            .extend(permissions.into_iter().map(Into::into));
+        self
+    }
+
+    /// Assigns the role to the subject.
+    #[must_use]
+    pub fn assign(mut self, subject: impl Into<String>, role: impl Into<String>) -> Self {
+        self.assignments
+            .entry(subject.into())
+            .or_default()
+            .push(role.into());
+        self
+    }
+
+    fn is_granted(&self, subject: &str, permission: &str) -> bool {
+        self.assignments
+            .get(subject)
+            .into_iter()
+            .flatten()
+            .filter_map(|role| self.roles.get(role))
+            .flatten()
+            .any(|pattern| permission_matches(pattern, permission))
+    }
+}
+
+impl Policy for RbacPolicy {
+    async fn authorize<'a>(&'a self, req: &'a Request, permission: &'a str) -> Result<bool> {
+        Ok(subject(req).is_some_and(|subject| self.is_granted(&subject, permission)))
+    }
+}
+
+/// A policy loaded from the policy files in the CSV format of
+/// [Casbin](https://casbin.org) with the RBAC model.
+///
+/// It is not an adapter of the Casbin engine but a small implementation of a
+/// fixed model, use [poem-casbin](https://github.com/casbin-rs/poem-casbin) for
+/// the other models. It supports the `p, sub, obj, act[, eft]` policy rules,
+/// where the effect `eft` is `allow` (the default) or `deny`, and the `g, user,
+/// role` role inheritance rules, which are equivalent to the following model:
+///
+/// ```text
+/// [request_definition]
+/// r = sub, obj, act
+///
+/// [policy_definition]
+/// p = sub, obj, act, eft
+///
+/// [role_definition]
+/// g = _, _
+///
+/// [policy_effect]
+/// e = some(where (p.eft == allow)) && !some(where (p.eft == deny))
+///
+/// [matchers]
+/// m = g(r.sub, p.sub) && keyMatch(r.obj, p.obj) && (r.act == p.act || p.act == "*")
+/// ```
+///
+/// The subject of the request is determined by [`subject`], the subject of
+/// an API key is its name with the `key:` prefix. The permission `obj:act` is
+/// split at the last `:` into the object and the action.
+///
+/// # Example
+///
+/// ```
+/// use poem::auth::CsvPolicy;
+///
+/// let policy = CsvPolicy::parse(
+///     r#"
+/// p, admin, users, *
+/// p, viewer, users, read
+/// p, guest, users, read, deny
+/// g, alice, admin
+/// g, bob, viewer
+/// g, key:ci, viewer
+/// "#,
+/// )
+/// .unwrap();
+/// ```
+pub struct CsvPolicy {
+    rules: Vec<Rule>,
+    groups: HashMap<String, Vec<String>>,
+}
+
+struct Rule {
+    sub: String,
+    obj: String,
+    act: String,
+    allow: bool,
+}
+
+impl CsvPolicy {
+    /// Parse the content of a policy file in the CSV format.
+    ///
+    /// Returns an error of the kind [`io::ErrorKind::InvalidData`] if the
+    /// effect of a policy rule is neither `allow` nor `deny`.
+    pub fn parse(content: &str) -> io::Result<Self> {
+        let mut rules = Vec::new();
+        let mut groups = HashMap::<_, Vec<_>>::new();
+        for (idx, line) in content.lines().enumerate() {
+            let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+            match fields.as_slice() {
+                ["p", sub, obj, act, rest @ ..] => {
+                    let allow = match rest {
+                        [] | ["allow", ..] => true,
+                        ["deny", ..] => false,
+                        [eft, ..] => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("invalid effect `{eft}` at line {}", idx + 1),
+                            ))
+                        }
+                    };
+                    rules.push(Rule {
+                        sub: sub.to_string(),
+                        obj: obj.to_string(),
+                        act: act.to_string(),
+                        allow,
+                    });
+                }
+                ["g", user, role, ..] => groups
+                    .entry(user.to_string())
+                    .or_default()
+                    .push(role.to_string()),
+                _ => {}
+            }
+        }
+        Ok(Self { rules, groups })
+    }
+
+    /// Load a policy file in the CSV format.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Returns the subject and all the roles it inherits.
+    fn subjects<'a>(&'a self, subject: &'a str) -> HashSet<&'a str> {
+        let mut subjects = HashSet::new();
+        let mut pending = vec![subject];
+        while let Some(subject) = pending.pop() {
+            if subjects.insert(subject) {
+                pending.extend(
+                    self.groups
+                        .get(subject)
+                        .into_iter()
+                        .flatten()
+                        .map(String::as_str),
+                );
+            }
+        }
+        subjects
+    }
+
+    /// Returns `true` if the subject is granted the action on the object.
+    ///
+    /// The action is denied if any matching rule denies it, even if other
+    /// matching rules allow it.
+    pub fn enforce(&self, subject: &str, obj: &str, act: &str) -> bool {
+        let subjects = self.subjects(subject);
+        let mut allowed = false;
+        for rule in &self.rules {
+            if subjects.contains(rule.sub.as_str())
+                && key_match(obj, &rule.obj)
+                && (rule.act == act || rule.act == "*")
+            {
+                if !rule.allow {
+                    return false;
+                }
+                allowed = true;
+            }
+        }
+        allowed
+    }
+}
+
+/// The `keyMatch` function of Casbin, `*` in the pattern matches any suffix.
+fn key_match(key: &str, pattern: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, _)) => key.starts_with(prefix),
+        None => key == pattern,
+    }
+}
+
+impl Policy for CsvPolicy {
+    async fn authorize<'a>(&'a self, req: &'a Request, permission: &'a str) -> Result<bool> {
+        let (obj, act) = permission.rsplit_once(':').unwrap_or((permission, ""));
+        Ok(subject(req).is_some_and(|subject| self.enforce(&subject, obj, act)))
+    }
+}
+
+/// Middleware for authorizing the requests with a [`Policy`].
+///
+/// It makes the policy available to the [`RequirePermission`] middleware, the
+/// [`Authorized`] extractor and the handlers declaring a permission with
+/// `#[handler(permission = "...")]`, and should be applied after the
+/// authentication middlewares.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     auth::{
+///         Authorization, Authorized, BasicAuth, MemoryUserProvider, Permission, RbacPolicy,
+///         RequirePermission,
+///     },
+///     get, handler, EndpointExt, Route,
+/// };
+///
+/// struct UsersWrite;
+///
+/// impl Permission for UsersWrite {
+///     const NAME: &'static str = "users:write";
+/// }
+///
+/// #[handler]
+/// fn list_users() {}
+///
+/// #[handler]
+/// fn create_user(_: Authorized<UsersWrite>) {}
+///
+/// #[handler(permission = "users:read")]
+/// fn get_user() {}
+///
+/// let policy = RbacPolicy::new()
+///     .role("admin", ["users:*"])
+///     .assign("alice", "admin");
+/// let app = Route::new()
+///     .at(
+///         "/users",
+///         get(list_users.with(RequirePermission::new("users:read"))).post(create_user),
+///     )
+///     .at("/users/:id", get(get_user))
+///     .with(Authorization::new(policy))
+///     .with(BasicAuth::new(
+///         MemoryUserProvider::new().user("alice", "123456"),
+///     ));
+/// ```
+pub struct Authorization {
+    policy: SharedPolicy,
+}
+
+impl Authorization {
+    /// Create `Authorization` middleware with the policy.
+    pub fn new(policy: impl Policy) -> Self {
+        Self {
+            policy: SharedPolicy(Arc::new(policy)),
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Authorization {
+    type Output = AuthorizationEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        AuthorizationEndpoint {
+            inner: ep,
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+/// Endpoint for the Authorization middleware.
+pub struct AuthorizationEndpoint<E> {
+    inner: E,
+    policy: SharedPolicy,
+}
+
+impl<E: Endpoint> Endpoint for AuthorizationEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        req.extensions_mut().insert(self.policy.clone());
+        self.inner.call(req).await
+    }
+}
+
+/// Middleware for requiring a permission, the request is rejected with `403
+/// Forbidden` if it is not granted the permission.
+///
+/// The [`Authorization`] middleware is required.
+///
+/// # Errors
+///
+/// - [`AuthorizationError`]
+pub struct RequirePermission {
+    permission: String,
+}
+
+impl RequirePermission {
+    /// Create `RequirePermission` middleware with the permission.
+    pub fn new(permission: impl Into<String>) -> Self {
+        Self {
+            permission: permission.into(),
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for RequirePermission {
+    type Output = RequirePermissionEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RequirePermissionEndpoint {
+            inner: ep,
+            permission: self.permission.clone(),
+        }
+    }
+}
+
+/// Endpoint for the RequirePermission middleware.
+pub struct RequirePermissionEndpoint<E> {
+    inner: E,
+    permission: String,
+}
+
+impl<E: Endpoint> Endpoint for RequirePermissionEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        check_permission(&req, &self.permission).await?;
+        self.inner.call(req).await
+    }
+}
+
+/// Represents a permission checked by the [`Authorized`] extractor.
+pub trait Permission: Send + Sync + 'static {
+    /// The name of the permission.
+    const NAME: &'static str;
+}
+
+/// An extractor that requires the request to be granted the permission `P`,
+/// it fails with `403 Forbidden` otherwise.
+///
+/// The [`Authorization`] middleware is required.
+///
+/// # Errors
+///
+/// - [`AuthorizationError`]
+pub struct Authorized<P>(PhantomData<P>);
+
+impl<'a, P: Permission> FromRequest<'a> for Authorized<P> {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        check_permission(req, P::NAME).await?;
+        Ok(Self(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::Authorization as AuthorizationHeader;
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{
+        auth::{ApiKeyAuth, ApiKeyManager, BasicAuth, MemoryApiKeyStore, MemoryUserProvider},
+        get, handler,
+        test::TestClient,
+        EndpointExt, Route,
+    };
+
+    struct UsersWrite;
+
+    impl Permission for UsersWrite {
+        const NAME: &'static str = "users:write";
+    }
+
+    #[handler(internal)]
+    fn list_users() -> &'static str {
+        "list"
+    }
+
+    #[handler(internal)]
+    fn create_user(_: Authorized<UsersWrite>) -> &'static str {
+        "create"
+    }
+
+    #[handler(internal, permission = "users:read")]
+    fn get_user() -> &'static str {
+        "get"
+    }
+
+    async fn check(policy: impl Policy) {
+        let users = Arc::new(
+            MemoryUserProvider::new()
+                .user("alice", "a")
+                .user("bob", "b")
+                .user("eve", "e"),
+        );
+        let cli = TestClient::new(
+            Route::new()
+                .at(
+                    "/users",
+                    get(list_users.with(RequirePermission::new("users:read"))).post(create_user),
+                )
+                .at("/users/1", get(get_user))
+                .with(Authorization::new(policy))
+                .with(BasicAuth::new(users)),
+        );
+
+        for (user, read, write) in [
+            ("alice", StatusCode::OK, StatusCode::OK),
+            ("bob", StatusCode::OK, StatusCode::FORBIDDEN),
+            ("eve", StatusCode::FORBIDDEN, StatusCode::FORBIDDEN),
+        ] {
+            let password = &user[..1];
+            cli.get("/users")
+                .typed_header(AuthorizationHeader::basic(user, password))
+                .send()
+                .await
+                .assert_status(read);
+            cli.get("/users/1")
+                .typed_header(AuthorizationHeader::basic(user, password))
+                .send()
+                .await
+                .assert_status(read);
+            cli.post("/users")
+                .typed_header(AuthorizationHeader::basic(user, password))
+                .send()
+                .await
+                .assert_status(write);
+        }
+    }
+
+    #[test]
+    fn matches() {
+        assert!(permission_matches("*", "users:read"));
+        assert!(permission_matches("users:*", "users:read"));
+        assert!(!permission_matches("users:*", "usersx:read"));
+        assert!(permission_matches("users:read", "users:read"));
+        assert!(!permission_matches("users:read", "users:write"));
+        assert!(key_match("/users/1", "/users/*"));
+        assert!(!key_match("/posts/1", "/users/*"));
+    }
+
+    #[tokio::test]
+    async fn rbac() {
+        check(
+            RbacPolicy::new()
+                .role("admin", ["users:*"])
+                .role("viewer", ["users:read"])
+                .assign("alice", "admin")
+                .assign("bob", "viewer"),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn csv() {
+        check(
+            CsvPolicy::parse(
+                "p, admin, users, *\n\
+                 p, viewer, users, read\n\
+                 g, editor, viewer\n\
+                 g, alice, admin\n\
+                 g, bob, editor\n",
+            )
+            .unwrap(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn api_key_subject() {
+        let keys = ApiKeyManager::new(MemoryApiKeyStore::new());
+        let alice = keys.issue("alice", [] as [&str; 0], None).await.unwrap();
+        let ci = keys.issue("ci", [] as [&str; 0], None).await.unwrap();
+        let policy = RbacPolicy::new()
+            .role("admin", ["users:*"])
+            .assign("alice", "admin")
+            .assign("key:ci", "admin");
+        let cli = TestClient::new(
+            list_users
+                .with(RequirePermission::new("users:read"))
+                .with(Authorization::new(policy))
+                .with(ApiKeyAuth::new(keys)),
+        );
+
+        cli.get("/")
+            .header("x-api-key", &alice.key)
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+        cli.get("/")
+            .header("x-api-key", &ci.key)
+            .send()
+            .await
+            .assert_status_is_ok();
+    }
+
+    #[test]
+    fn csv_deny() {
+        let policy = CsvPolicy::parse(
+            "p, admin, users, *\n\
+             p, intern, users, write, deny\n\
+             p, admin, posts, read, allow\n\
+             g, alice, admin\n\
+             g, bob, admin\n\
+             g, bob, intern\n",
+        )
+        .unwrap();
+        assert!(policy.enforce("alice", "users", "write"));
+        assert!(!policy.enforce("bob", "users", "write"));
+        assert!(policy.enforce("bob", "users", "read"));
+        assert!(policy.enforce("bob", "posts", "read"));
+
+        let err = CsvPolicy::parse("p, admin, users, write, maybe\n")
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use headers::{authorization::Basic, Authorization, HeaderMapExt};
+
+use super::{quote, AuthenticatedUser, UserProvider};
+use crate::{error::HttpAuthError, Endpoint, Middleware, Request, Result};
+
+/// Middleware for the HTTP Basic authentication
+/// ([RFC 7617](https://datatracker.ietf.org/doc/html/rfc7617)).
+///
+/// The request without valid credentials is rejected with
+/// `401 Unauthorized` and a `WWW-Authenticate` challenge. Use the
+/// [`&AuthenticatedUser`](AuthenticatedUser) extractor to get the name of the
+/// user in the handlers.
+///
+/// Basic authentication sends the password in plaintext, it should only be
+/// used over TLS.
+///
+/// # Errors
+///
+/// - [`HttpAuthError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     auth::{AuthenticatedUser, BasicAuth, MemoryUserProvider},
+///     handler, EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index(user: &AuthenticatedUser) -> String {
+///     format!("hello {}", user.0)
+/// }
+///
+/// let app = Route::new().at(
+///     "/",
+///     index.with(
+///         BasicAuth::new(MemoryUserProvider::new().user("admin", "password")).realm("admin"),
+///     ),
+/// );
+/// ```
+pub struct BasicAuth<P> {
+    provider: Arc<P>,
+    realm: String,
+}
+
+impl<P: UserProvider> BasicAuth<P> {
+    /// Create `BasicAuth` middleware with the user provider.
+    ///
+    /// Wrap the provider with [`Arc`] to share it with other middlewares.
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            realm: "poem".to_string(),
+        }
+    }
+
+    /// Sets the realm of the protected endpoints.
+    ///
+    /// Default is `poem`.
+    #[must_use]
+    pub fn realm(self, realm: impl Into<String>) -> Self {
+        Self {
+            realm: realm.into(),
+            ..self
+        }
+    }
+}
+
+impl<P: UserProvider, E: Endpoint> Middleware<E> for BasicAuth<P> {
+    type Output = BasicAuthEndpoint<P, E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        BasicAuthEndpoint {
+            inner: ep,
+            provider: self.provider.clone(),
+            realm: self.realm.clone(),
+            challenge: format!("Basic realm={}, charset=\"UTF-8\"", quote(&self.realm)),
+        }
+    }
+}
+
+/// Endpoint for the BasicAuth middleware.
+pub struct BasicAuthEndpoint<P, E> {
+    inner: E,
+    provider: Arc<P>,
+    realm: String,
+    challenge: String,
+}
+
+impl<P: UserProvider, E: Endpoint> Endpoint for BasicAuthEndpoint<P, E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let unauthorized = || HttpAuthError::Unauthorized {
+            challenge: self.challenge.clone(),
+        };
+        let credentials = req
+            .headers()
+            .typed_get::<Authorization<Basic>>()
+            .ok_or_else(unauthorized)?;
+        if !self
+            .provider
+            .verify_password(&self.realm, credentials.username(), credentials.password())
+            .await?
+        {
+            return Err(unauthorized().into());
+        }
+
+        req.extensions_mut()
+            .insert(AuthenticatedUser(credentials.username().to_string()));
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{auth::MemoryUserProvider, handler, test::TestClient, EndpointExt};
+
+    #[handler(internal)]
+    fn index(user: &AuthenticatedUser) -> String {
+        user.0.clone()
+    }
+
+    #[tokio::test]
+    async fn basic_auth() {
+        let cli =
+            TestClient::new(index.with(
+                BasicAuth::new(MemoryUserProvider::new().user("alice", "secret")).realm("a\"b"),
+            ));
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+        resp.assert_header(
+            "www-authenticate",
+            "Basic realm=\"a\\\"b\", charset=\"UTF-8\"",
+        );
+
+        cli.get("/")
+            .typed_header(Authorization::basic("alice", "wrong"))
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+
+        let resp = cli
+            .get("/")
+            .typed_header(Authorization::basic("alice", "secret"))
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("alice").await;
+    }
+}
@@ -0,0 +1,397 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use md5::{Digest, Md5};
+use rand::RngCore;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+use super::{quote, AuthenticatedUser, UserProvider};
+use crate::{
+    clock::SharedClock, error::HttpAuthError, http::header, Endpoint, Middleware, Request, Result,
+};
+
+fn md5_hex(data: impl AsRef<[u8]>) -> String {
+    hex::encode(Md5::digest(data))
+}
+
+/// Parses the `name=value` or `name="value"` pairs separated by commas.
+fn parse_params(mut s: &str) -> Option<HashMap<String, String>> {
+    let mut params = HashMap::new();
+    loop {
+        s = s.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        if s.is_empty() {
+            return Some(params);
+        }
+
+        let (name, rest) = s.split_once('=')?;
+        let rest = rest.trim_start();
+        let value = match rest.strip_prefix('"') {
+            Some(rest) => {
+                let mut value = String::new();
+                let mut escaped = false;
+                let mut end = None;
+                for (idx, c) in rest.char_indices() {
+                    match c {
+                        _ if escaped => {
+                            value.push(c);
+                            escaped = false;
+                        }
+                        '\\' => escaped = true,
+                        '"' => {
+                            end = Some(idx);
+                            break;
+                        }
+                        _ => value.push(c),
+                    }
+                }
+                s = &rest[end? + 1..];
+                value
+            }
+            None => {
+                let end = rest.find(',').unwrap_or(rest.len());
+                s = &rest[end..];
+                rest[..end].trim().to_string()
+            }
+        };
+        params.insert(name.trim().to_ascii_lowercase(), value);
+    }
+}
+
+enum NonceStatus {
+    Valid,
+    Stale,
+    Invalid,
+}
+
+/// Middleware for the HTTP Digest authentication
+/// ([RFC 7616](https://datatracker.ietf.org/doc/html/rfc7616)) with the `MD5`
+/// algorithm and the `auth` quality of protection.
+///
+/// The nonces are stateless, they are signed with a random key generated
+/// when the middleware is created, and can be reused until they expire. The
+/// request without valid credentials is rejected with `401 Unauthorized` and
+/// a `WWW-Authenticate` challenge. Use the
+/// [`&AuthenticatedUser`](AuthenticatedUser) extractor to get the name of the
+/// user in the handlers.
+///
+/// The user provider must implement [`UserProvider::digest_ha1`].
+///
+/// # Errors
+///
+/// - [`HttpAuthError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     auth::{AuthenticatedUser, DigestAuth, MemoryUserProvider},
+///     handler, EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index(user: &AuthenticatedUser) -> String {
+///     format!("hello {}", user.0)
+/// }
+///
+/// let app = Route::new().at(
+///     "/",
+///     index.with(
+///         DigestAuth::new(MemoryUserProvider::new().user("admin", "password")).realm("admin"),
+///     ),
+/// );
+/// ```
+pub struct DigestAuth<P> {
+    provider: Arc<P>,
+    realm: String,
+    nonce_timeout: Duration,
+}
+
+impl<P: UserProvider> DigestAuth<P> {
+    /// Create `DigestAuth` middleware with the user provider.
+    ///
+    /// Wrap the provider with [`Arc`] to share it with other middlewares.
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            realm: "poem".to_string(),
+            nonce_timeout: Duration::from_secs(300),
+        }
+    }
+
+    /// Sets the realm of the protected endpoints.
+    ///
+    /// Default is `poem`.
+    #[must_use]
+    pub fn realm(self, realm: impl Into<String>) -> Self {
+        Self {
+            realm: realm.into(),
+            ..self
+        }
+    }
+
+    /// Sets the time after which the nonces become stale, the clients retry
+    /// with a new nonce without prompting the user again.
+    ///
+    /// Default is `300s`.
+    #[must_use]
+    pub fn nonce_timeout(self, timeout: Duration) -> Self {
+        Self {
+            nonce_timeout: timeout,
+            ..self
+        }
+    }
+}
+
+impl<P: UserProvider, E: Endpoint> Middleware<E> for DigestAuth<P> {
+    type Output = DigestAuthEndpoint<P, E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        let mut key = [0; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+        DigestAuthEndpoint {
+            inner: ep,
+            provider: self.provider.clone(),
+            realm: self.realm.clone(),
+            nonce_timeout: self.nonce_timeout,
+            key,
+        }
+    }
+}
+
+/// Endpoint for the DigestAuth middleware.
+pub struct DigestAuthEndpoint<P, E> {
+    inner: E,
+    provider: Arc<P>,
+    realm: String,
+    nonce_timeout: Duration,
+    key: [u8; 32],
+}
+
+impl<P, E> DigestAuthEndpoint<P, E> {
+    fn signature(&self, timestamp: u64) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.key);
+        hasher.update(timestamp.to_be_bytes());
+        hasher.update(self.realm.as_bytes());
+        hex::encode(&hasher.finalize()[..16])
+    }
+
+    fn check_nonce(&self, nonce: &str, now: u64) -> NonceStatus {
+        let Some((timestamp, signature)) =
+            nonce.split_once('.').and_then(|(timestamp, signature)| {
+                Some((u64::from_str_radix(timestamp, 16).ok()?, signature))
+            })
+        else {
+            return NonceStatus::Invalid;
+        };
+        if !bool::from(
+            self.signature(timestamp)
+                .as_bytes()
+                .ct_eq(signature.as_bytes()),
+        ) {
+            NonceStatus::Invalid
+        } else if now.saturating_sub(timestamp) > self.nonce_timeout.as_secs() {
+            NonceStatus::Stale
+        } else {
+            NonceStatus::Valid
+        }
+    }
+
+    fn unauthorized(&self, stale: bool, timestamp: u64) -> HttpAuthError {
+        let mut challenge = format!(
+            "Digest realm={}, qop=\"auth\", algorithm=MD5, nonce=\"{:x}.{}\"",
+            quote(&self.realm),
+            timestamp,
+            self.signature(timestamp)
+        );
+        if stale {
+            challenge.push_str(", stale=true");
+        }
+        HttpAuthError::Unauthorized { challenge }
+    }
+}
+
+impl<P: UserProvider, E: Endpoint> Endpoint for DigestAuthEndpoint<P, E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let now = SharedClock::from_request(&req)
+            .elapsed_since_epoch()
+            .as_secs();
+        let Some(params) = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("digest"))
+            .map(|(_, params)| parse_params(params))
+        else {
+            return Err(self.unauthorized(false, now).into());
+        };
+        let params = params.ok_or(HttpAuthError::BadRequest)?;
+        let param = |name: &str| params.get(name).map(String::as_str);
+
+        let (Some(username), Some(realm), Some(nonce), Some(uri), Some(response)) = (
+            param("username"),
+            param("realm"),
+            param("nonce"),
+            param("uri"),
+            param("response"),
+        ) else {
+            return Err(HttpAuthError::BadRequest.into());
+        };
+        let request_uri = req
+            .original_uri()
+            .path_and_query()
+            .map(|path| path.as_str())
+            .unwrap_or("/");
+        if uri != request_uri {
+            return Err(HttpAuthError::BadRequest.into());
+        }
+        if realm != self.realm
+            || param("algorithm").is_some_and(|algorithm| !algorithm.eq_ignore_ascii_case("md5"))
+        {
+            return Err(self.unauthorized(false, now).into());
+        }
+
+        let status = self.check_nonce(nonce, now);
+        if matches!(status, NonceStatus::Invalid) {
+            return Err(self.unauthorized(false, now).into());
+        }
+        let Some(ha1) = self.provider.digest_ha1(&self.realm, username).await? else {
+            return Err(self.unauthorized(false, now).into());
+        };
+
+        let ha2 = md5_hex(format!("{}:{}", req.method(), uri));
+        let expected = match param("qop") {
+            Some("auth") => {
+                let (Some(nc), Some(cnonce)) = (param("nc"), param("cnonce")) else {
+                    return Err(HttpAuthError::BadRequest.into());
+                };
+                md5_hex(format!("{ha1}:{nonce}:{nc}:{cnonce}:auth:{ha2}"))
+            }
+            None => md5_hex(format!("{ha1}:{nonce}:{ha2}")),
+            Some(_) => return Err(self.unauthorized(false, now).into()),
+        };
+        if !bool::from(
+            expected
+                .as_bytes()
+                .ct_eq(response.to_ascii_lowercase().as_bytes()),
+        ) {
+            return Err(self.unauthorized(false, now).into());
+        }
+        if matches!(status, NonceStatus::Stale) {
+            return Err(self.unauthorized(true, now).into());
+        }
+
+        let username = username.to_string();
+        req.extensions_mut().insert(AuthenticatedUser(username));
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{
+        auth::MemoryUserProvider, clock::MockClock, handler, test::TestClient, EndpointExt,
+    };
+
+    #[handler(internal)]
+    fn index(user: &AuthenticatedUser) -> String {
+        user.0.clone()
+    }
+
+    fn authorization(challenge: &str, password: &str, uri: &str) -> String {
+        let params = parse_params(challenge.strip_prefix("Digest ").unwrap()).unwrap();
+        let nonce = &params["nonce"];
+        let ha1 = md5_hex(format!("alice:{}:{password}", params["realm"]));
+        let ha2 = md5_hex(format!("GET:{uri}"));
+        let response = md5_hex(format!("{ha1}:{nonce}:00000001:abc:auth:{ha2}"));
+        format!(
+            "Digest username=\"alice\", realm=\"{}\", nonce=\"{nonce}\", uri=\"{uri}\", \
+             qop=auth, nc=00000001, cnonce=\"abc\", response=\"{response}\"",
+            params["realm"]
+        )
+    }
+
+    #[test]
+    fn params() {
+        let params = parse_params(r#"a=1, b="x, \"y\"",c = "" ,"#).unwrap();
+        assert_eq!(params["a"], "1");
+        assert_eq!(params["b"], "x, \"y\"");
+        assert_eq!(params["c"], "");
+        assert!(parse_params(r#"a="1"#).is_none());
+    }
+
+    #[tokio::test]
+    async fn digest_auth() {
+        let clock = MockClock::new();
+        let ep = index
+            .with(DigestAuth::new(MemoryUserProvider::new().user("alice", "secret")).realm("admin"))
+            .data(SharedClock::new(clock.clone()));
+        let cli = TestClient::new(ep);
+
+        let resp = cli.get("/a?b=1").send().await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+        let challenge = resp
+            .0
+            .headers()
+            .get("www-authenticate")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(challenge.starts_with("Digest realm=\"admin\", qop=\"auth\", algorithm=MD5"));
+
+        let resp = cli
+            .get("/a?b=1")
+            .header(
+                "authorization",
+                authorization(&challenge, "secret", "/a?b=1"),
+            )
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("alice").await;
+
+        cli.get("/a?b=1")
+            .header(
+                "authorization",
+                authorization(&challenge, "wrong", "/a?b=1"),
+            )
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+        cli.get("/a?b=1")
+            .header("authorization", authorization(&challenge, "secret", "/c"))
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+
+        clock.advance(Duration::from_secs(300));
+        cli.get("/a")
+            .header("authorization", authorization(&challenge, "secret", "/a"))
+            .send()
+            .await
+            .assert_status_is_ok();
+
+        clock.advance(Duration::from_secs(1));
+        let resp = cli
+            .get("/a")
+            .header("authorization", authorization(&challenge, "secret", "/a"))
+            .send()
+            .await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+        assert!(resp
+            .0
+            .headers()
+            .get("www-authenticate")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .ends_with("stale=true"));
+    }
+}
@@ -0,0 +1,26 @@
+//! Authentication and authorization middlewares.
+
+mod api_key;
+mod authorization;
+mod basic;
+mod digest;
+mod user_provider;
+
+pub use api_key::{
+    ApiKey, ApiKeyAdminEndpoint, ApiKeyAuth, ApiKeyAuthEndpoint, ApiKeyManager, ApiKeyStore,
+    IssuedApiKey, MemoryApiKeyStore,
+};
+#[doc(hidden)]
+pub use authorization::check_permission;
+pub use authorization::{
+    subject, Authorization, AuthorizationEndpoint, Authorized, CsvPolicy, Permission, Policy,
+    RbacPolicy, RequirePermission, RequirePermissionEndpoint,
+};
+pub use basic::{BasicAuth, BasicAuthEndpoint};
+pub use digest::{DigestAuth, DigestAuthEndpoint};
+pub use user_provider::{AuthenticatedUser, Htdigest, Htpasswd, MemoryUserProvider, UserProvider};
+
+/// Returns the value as a quoted string of the `WWW-Authenticate` header.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,408 @@
+use std::{collections::HashMap, future::Future, io, ops::Deref, path::Path, sync::Arc};
+
+use base64::engine::{general_purpose::STANDARD, Engine};
+use md5::{Digest, Md5};
+use sha1::Sha1;
+use subtle::ConstantTimeEq;
+
+use crate::{FromRequest, Request, RequestBody, Result};
+
+/// The name of the user authenticated by the
+/// [`BasicAuth`](crate::auth::BasicAuth) or
+/// [`DigestAuth`](crate::auth::DigestAuth) middleware.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AuthenticatedUser(pub String);
+
+impl Deref for AuthenticatedUser {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> FromRequest<'a> for &'a AuthenticatedUser {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req.extensions().get::<AuthenticatedUser>().expect(
+            "To use the `AuthenticatedUser` extractor, the `BasicAuth` or `DigestAuth` middleware \
+             is required.",
+        ))
+    }
+}
+
+/// Represents a back-end of the users, such as a database.
+pub trait UserProvider: Send + Sync + 'static {
+    /// Returns `true` if the password of the user in the realm is correct.
+    fn verify_password<'a>(
+        &'a self,
+        realm: &'a str,
+        username: &'a str,
+        password: &'a str,
+    ) -> impl Future<Output = Result<bool>> + Send + 'a;
+
+    /// Returns the hex encoded `MD5(username:realm:password)` of the user,
+    /// which is required by the digest authentication.
+    ///
+    /// Returns `None` if the user does not exist, the default implementation
+    /// always returns `None`.
+    fn digest_ha1<'a>(
+        &'a self,
+        realm: &'a str,
+        username: &'a str,
+    ) -> impl Future<Output = Result<Option<String>>> + Send + 'a {
+        let _ = (realm, username);
+        async { Ok(None) }
+    }
+}
+
+impl<T: UserProvider> UserProvider for Arc<T> {
+    fn verify_password<'a>(
+        &'a self,
+        realm: &'a str,
+        username: &'a str,
+        password: &'a str,
+    ) -> impl Future<Output = Result<bool>> + Send + 'a {
+        self.as_ref().verify_password(realm, username, password)
+    }
+
+    fn digest_ha1<'a>(
+        &'a self,
+        realm: &'a str,
+        username: &'a str,
+    ) -> impl Future<Output = Result<Option<String>>> + Send + 'a {
+        self.as_ref().digest_ha1(realm, username)
+    }
+}
+
+pub(crate) fn digest_ha1(username: &str, realm: &str, password: &str) -> String {
+    hex::encode(Md5::digest(format!("{username}:{realm}:{password}")))
+}
+
+fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// A user provider using memory, the users belong to all the realms.
+#[derive(Default)]
+pub struct MemoryUserProvider {
+    users: HashMap<String, String>,
+}
+
+impl MemoryUserProvider {
+    /// Create a `MemoryUserProvider`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a user with the password.
+    #[must_use]
+    pub fn user(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.users.insert(username.into(), password.into());
+        self
+    }
+}
+
+impl UserProvider for MemoryUserProvider {
+    async fn verify_password<'a>(
+        &'a self,
+        _realm: &'a str,
+        username: &'a str,
+        password: &'a str,
+    ) -> Result<bool> {
+        Ok(self
+            .users
+            .get(username)
+            .is_some_and(|expected| ct_eq(expected.as_bytes(), password.as_bytes())))
+    }
+
+    async fn digest_ha1<'a>(&'a self, realm: &'a str, username: &'a str) -> Result<Option<String>> {
+        Ok(self
+            .users
+            .get(username)
+            .map(|password| digest_ha1(username, realm, password)))
+    }
+}
+
+fn parse_lines(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// A user provider using an Apache `htpasswd` file, the users belong to all
+/// the realms.
+///
+/// The passwords hashed with the `$apr1$` (MD5) and `{SHA}` (SHA-1)
+/// algorithms are supported. The users with the other hashes, such as bcrypt
+/// or crypt(3), always fail to authenticate, unless the plaintext passwords
+/// are allowed with [`Htpasswd::allow_plaintext`].
+///
+/// It only supports the basic authentication, use [`Htdigest`] for the digest
+/// authentication.
+pub struct Htpasswd {
+    users: HashMap<String, String>,
+    allow_plaintext: bool,
+}
+
+impl Htpasswd {
+    /// Parse the content of an `htpasswd` file.
+    pub fn parse(content: &str) -> Self {
+        Self {
+            users: parse_lines(content)
+                .filter_map(|line| line.split_once(':'))
+                .map(|(username, hash)| (username.to_string(), hash.to_string()))
+                .collect(),
+            allow_plaintext: false,
+        }
+    }
+
+    /// Load an `htpasswd` file.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// Treats the entries that are not in a supported hash format as the
+    /// plaintext passwords.
+    ///
+    /// The plaintext entries can not be told apart from the crypt(3) hashes
+    /// created by `htpasswd -d`, the users with such hashes can then log in
+    /// with the hash itself, so only enable it for files without them.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn allow_plaintext(self, allow: bool) -> Self {
+        Self {
+            allow_plaintext: allow,
+            ..self
+        }
+    }
+}
+
+const ITOA64: &[u8] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// The MD5 based crypt with the `$apr1$` magic.
+fn apr1_crypt(password: &[u8], salt: &[u8]) -> String {
+    const MAGIC: &[u8] = b"$apr1$";
+
+    let mut alt = Md5::new();
+    alt.update(password);
+    alt.update(salt);
+    alt.update(password);
+    let alt = alt.finalize();
+
+    let mut ctx = Md5::new();
+    ctx.update(password);
+    ctx.update(MAGIC);
+    ctx.update(salt);
+    for chunk in password.chunks(16) {
+        ctx.update(&alt[..chunk.len()]);
+    }
+    let mut n = password.len();
+    while n > 0 {
+        if n & 1 == 1 {
+            ctx.update([0]);
+        } else {
+            ctx.update(&password[..1]);
+        }
+        n >>= 1;
+    }
+    let mut digest = ctx.finalize();
+
+    for i in 0..1000 {
+        let mut ctx = Md5::new();
+        if i & 1 == 1 {
+            ctx.update(password);
+        } else {
+            ctx.update(digest);
+        }
+        if i % 3 != 0 {
+            ctx.update(salt);
+        }
+        if i % 7 != 0 {
+            ctx.update(password);
+        }
+        if i & 1 == 1 {
+            ctx.update(digest);
+        } else {
+            ctx.update(password);
+        }
+        digest = ctx.finalize();
+    }
+
+    let mut hash = String::from_utf8_lossy(MAGIC).into_owned();
+    hash.push_str(&String::from_utf8_lossy(salt));
+    hash.push('$');
+    let mut encode = |value: u32, n: usize| {
+        let mut value = value;
+        for _ in 0..n {
+            hash.push(ITOA64[(value & 0x3f) as usize] as char);
+            value >>= 6;
+        }
+    };
+    for (a, b, c) in [(0, 6, 12), (1, 7, 13), (2, 8, 14), (3, 9, 15), (4, 10, 5)] {
+        encode(
+            ((digest[a] as u32) << 16) | ((digest[b] as u32) << 8) | digest[c] as u32,
+            4,
+        );
+    }
+    encode(digest[11] as u32, 2);
+    hash
+}
+
+fn verify_htpasswd(hash: &str, password: &str, allow_plaintext: bool) -> bool {
+    if let Some(rest) = hash.strip_prefix("$apr1$") {
+        let salt = rest.split('$').next().unwrap_or_default();
+        let salt = &salt.as_bytes()[..salt.len().min(8)];
+        ct_eq(
+            apr1_crypt(password.as_bytes(), salt).as_bytes(),
+            hash.as_bytes(),
+        )
+    } else if let Some(expected) = hash.strip_prefix("{SHA}") {
+        let actual = STANDARD.encode(Sha1::digest(password.as_bytes()));
+        ct_eq(actual.as_bytes(), expected.as_bytes())
+    } else if allow_plaintext && !hash.starts_with('$') {
+        ct_eq(hash.as_bytes(), password.as_bytes())
+    } else {
+        false
+    }
+}
+
+impl UserProvider for Htpasswd {
+    async fn verify_password<'a>(
+        &'a self,
+        _realm: &'a str,
+        username: &'a str,
+        password: &'a str,
+    ) -> Result<bool> {
+        Ok(self
+            .users
+            .get(username)
+            .is_some_and(|hash| verify_htpasswd(hash, password, self.allow_plaintext)))
+    }
+}
+
+/// A user provider using an Apache `htdigest` file.
+///
+/// It supports both the basic and the digest authentication.
+pub struct Htdigest {
+    users: HashMap<(String, String), String>,
+}
+
+impl Htdigest {
+    /// Parse the content of an `htdigest` file.
+    pub fn parse(content: &str) -> Self {
+        Self {
+            users: parse_lines(content)
+                .filter_map(|line| {
+                    let mut parts = line.splitn(3, ':');
+                    Some((
+                        (parts.next()?.to_string(), parts.next()?.to_string()),
+                        parts.next()?.to_ascii_lowercase(),
+                    ))
+                })
+                .collect(),
+        }
+    }
+
+    /// Load an `htdigest` file.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    fn get(&self, realm: &str, username: &str) -> Option<&String> {
+        self.users.get(&(username.to_string(), realm.to_string()))
+    }
+}
+
+impl UserProvider for Htdigest {
+    async fn verify_password<'a>(
+        &'a self,
+        realm: &'a str,
+        username: &'a str,
+        password: &'a str,
+    ) -> Result<bool> {
+        Ok(self.get(realm, username).is_some_and(|ha1| {
+            ct_eq(
+                digest_ha1(username, realm, password).as_bytes(),
+                ha1.as_bytes(),
+            )
+        }))
+    }
+
+    async fn digest_ha1<'a>(&'a self, realm: &'a str, username: &'a str) -> Result<Option<String>> {
+        Ok(self.get(realm, username).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apr1() {
+        assert_eq!(
+            apr1_crypt(b"myPassword", b"r31....."),
+            "$apr1$r31.....$HqJZimcKQFAMYayBlzkrA/"
+        );
+    }
+
+    #[tokio::test]
+    async fn htpasswd() {
+        let content = "# users\n\
+                       alice:$apr1$r31.....$HqJZimcKQFAMYayBlzkrA/\n\
+                       bob:{SHA}5en6G6MezRroT3XKqkdPOmY/BfQ=\n\
+                       carol:plain\n\
+                       dave:$2y$05$c4WoMPo3SXsafkva.HHa6uXQZWr7oboPiC2bT/r7q1BB8I2s0BRqC\n\
+                       erin:rqXexS6ZhobKA\n";
+        let provider = Htpasswd::parse(content);
+        assert!(provider
+            .verify_password("", "alice", "myPassword")
+            .await
+            .unwrap());
+        assert!(!provider
+            .verify_password("", "alice", "wrong")
+            .await
+            .unwrap());
+        assert!(provider.verify_password("", "bob", "secret").await.unwrap());
+        assert!(!provider
+            .verify_password("", "carol", "plain")
+            .await
+            .unwrap());
+        assert!(!provider.verify_password("", "dave", "").await.unwrap());
+        assert!(!provider
+            .verify_password("", "erin", "rqXexS6ZhobKA")
+            .await
+            .unwrap());
+        assert!(!provider.verify_password("", "eve", "").await.unwrap());
+        assert_eq!(provider.digest_ha1("", "alice").await.unwrap(), None);
+
+        let provider = Htpasswd::parse(content).allow_plaintext(true);
+        assert!(provider
+            .verify_password("", "carol", "plain")
+            .await
+            .unwrap());
+        assert!(!provider.verify_password("", "dave", "").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn htdigest() {
+        let provider = Htdigest::parse(&format!(
+            "alice:admin:{}\n",
+            digest_ha1("alice", "admin", "secret")
+        ));
+        assert!(provider
+            .verify_password("admin", "alice", "secret")
+            .await
+            .unwrap());
+        assert!(!provider
+            .verify_password("other", "alice", "secret")
+            .await
+            .unwrap());
+        assert!(provider
+            .digest_ha1("admin", "alice")
+            .await
+            .unwrap()
+            .is_some());
+    }
+}
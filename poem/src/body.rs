@@ -121,7 +121,7 @@ impl Body {
     #[inline]
     pub fn from_async_read(reader: impl AsyncRead + Send + 'static) -> Self {
         Self(BoxBody::new(http_body_util::StreamBody::new(
-            SyncStream::new(tokio_util::io::ReaderStream::new(reader).map_ok(Frame::data)),
+            SyncStream::new(crate::buffer_pool::ReaderStream::new(reader).map_ok(Frame::data)),
         )))
     }
 
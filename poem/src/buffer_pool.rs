@@ -0,0 +1,156 @@
+//! Pooled buffers for reading the response bodies.
+//!
+//! The chunks of a body are split from a larger buffer and frozen, so they
+//! share its allocation. After the chunks have been written and dropped, the
+//! buffer is reclaimed from the pool and reused by the next body instead of
+//! being allocated again.
+
+use std::{
+    cell::RefCell,
+    io::Result as IoResult,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use futures_util::Stream;
+use pin_project_lite::pin_project;
+use tokio::io::AsyncRead;
+
+/// The size of the pooled buffers.
+const BUFFER_SIZE: usize = 16 * 1024;
+
+/// A buffer is replaced when the remaining capacity is less than this.
+const MIN_READ_SIZE: usize = 1024;
+
+/// The maximum number of the buffers kept by each thread.
+const MAX_POOLED: usize = 32;
+
+thread_local! {
+    static POOL: RefCell<Vec<BytesMut>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Takes a buffer with at least [`BUFFER_SIZE`] bytes of capacity from the
+/// pool of the current thread, or allocates a new one.
+pub(crate) fn get() -> BytesMut {
+    POOL.try_with(|pool| {
+        let mut pool = pool.borrow_mut();
+        match pool.iter_mut().position(|buf| buf.try_reclaim(BUFFER_SIZE)) {
+            Some(idx) => Some(pool.swap_remove(idx)),
+            None => {
+                // the chunks of the oldest buffer are kept for a long time
+                if pool.len() == MAX_POOLED {
+                    pool.remove(0);
+                }
+                None
+            }
+        }
+    })
+    .ok()
+    .flatten()
+    .unwrap_or_else(|| BytesMut::with_capacity(BUFFER_SIZE))
+}
+
+/// Returns a buffer to the pool of the current thread.
+pub(crate) fn put(mut buf: BytesMut) {
+    buf.clear();
+    let _ = POOL.try_with(|pool| {
+        let mut pool = pool.borrow_mut();
+        if pool.len() < MAX_POOLED {
+            pool.push(buf);
+        }
+    });
+}
+
+pin_project! {
+    /// Converts an [`AsyncRead`] into a stream of the chunks read into the
+    /// pooled buffers.
+    pub(crate) struct ReaderStream<R> {
+        #[pin]
+        reader: Option<R>,
+        buf: BytesMut,
+    }
+
+    impl<R> PinnedDrop for ReaderStream<R> {
+        fn drop(this: Pin<&mut Self>) {
+            let this = this.project();
+            if this.buf.capacity() > 0 {
+                put(std::mem::take(this.buf));
+            }
+        }
+    }
+}
+
+impl<R> ReaderStream<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader: Some(reader),
+            buf: BytesMut::new(),
+        }
+    }
+}
+
+impl<R: AsyncRead> Stream for ReaderStream<R> {
+    type Item = IoResult<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        let Some(reader) = this.reader.as_mut().as_pin_mut() else {
+            return Poll::Ready(None);
+        };
+
+        if this.buf.capacity() - this.buf.len() < MIN_READ_SIZE {
+            put(std::mem::replace(this.buf, get()));
+        }
+
+        match tokio_util::io::poll_read_buf(reader, cx, this.buf) {
+            Poll::Ready(Ok(0)) => {
+                this.reader.set(None);
+                Poll::Ready(None)
+            }
+            Poll::Ready(Ok(_)) => Poll::Ready(Some(Ok(this.buf.split().freeze()))),
+            Poll::Ready(Err(err)) => {
+                this.reader.set(None);
+                Poll::Ready(Some(Err(err)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt;
+
+    use super::*;
+
+    async fn read(data: &[u8]) -> Vec<Bytes> {
+        ReaderStream::new(data).try_collect().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn chunks() {
+        let data = (0..BUFFER_SIZE * 3).map(|i| i as u8).collect::<Vec<_>>();
+        let chunks = read(&data).await;
+        assert!(chunks.len() >= 3);
+        assert_eq!(chunks.concat(), data);
+    }
+
+    #[tokio::test]
+    async fn reuse() {
+        let data = vec![1u8; 100];
+        let ptr = read(&data).await[0].as_ptr();
+
+        // the buffer is reclaimed after the chunks have been dropped
+        assert_eq!(read(&data).await[0].as_ptr(), ptr);
+    }
+
+    #[tokio::test]
+    async fn in_use() {
+        let data = vec![1u8; 100];
+        let first = read(&data).await;
+        let second = read(&data).await;
+        assert_eq!(first, second);
+        assert_ne!(first[0].as_ptr(), second[0].as_ptr());
+    }
+}
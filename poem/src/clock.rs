@@ -0,0 +1,245 @@
+//! Clocks used to measure time.
+//!
+//! Middlewares and storages that deal with expiration read the current time
+//! from a [`Clock`] instead of calling [`Instant::now`] directly, so that
+//! tests can replace it with a [`MockClock`] and advance the time
+//! deterministically instead of sleeping.
+//!
+//! A clock can be injected into the request extensions via
+//! [`EndpointExt::data`](crate::EndpointExt::data) as a [`SharedClock`], and
+//! extracted by the handlers. If no clock is injected, the [`SystemClock`] is
+//! used.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use poem::{
+//!     clock::{MockClock, SharedClock},
+//!     handler,
+//!     test::TestClient,
+//!     EndpointExt,
+//! };
+//!
+//! #[handler]
+//! fn index(clock: SharedClock) -> String {
+//!     format!("{}", clock.elapsed_since_epoch().as_secs())
+//! }
+//!
+//! let clock = MockClock::new();
+//! let cli = TestClient::new(index.data(SharedClock::new(clock.clone())));
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let resp = cli.get("/").send().await;
+//! resp.assert_text("0").await;
+//!
+//! clock.advance(Duration::from_secs(60));
+//! let resp = cli.get("/").send().await;
+//! resp.assert_text("60").await;
+//! # });
+//! ```
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use parking_lot::Mutex;
+
+use crate::{FromRequest, Request, RequestBody, Result};
+
+/// Represents a source of the current time.
+pub trait Clock: Send + Sync + 'static {
+    /// Returns the current monotonic time.
+    fn now(&self) -> Instant;
+
+    /// Returns the current system time.
+    fn system_time(&self) -> SystemTime;
+}
+
+/// A clock that reads the time from the operating system.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    #[inline]
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves forward when it is advanced manually.
+///
+/// The clones of a `MockClock` share the same time.
+#[derive(Clone)]
+pub struct MockClock {
+    instant: Instant,
+    system_time: SystemTime,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl Debug for MockClock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockClock")
+            .field("system_time", &self.system_time())
+            .finish()
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockClock {
+    /// Create a `MockClock` whose system time starts at
+    /// [`SystemTime::UNIX_EPOCH`].
+    pub fn new() -> Self {
+        Self::starting_at(SystemTime::UNIX_EPOCH)
+    }
+
+    /// Create a `MockClock` whose system time starts at `system_time`.
+    pub fn starting_at(system_time: SystemTime) -> Self {
+        Self {
+            instant: Instant::now(),
+            system_time,
+            elapsed: Default::default(),
+        }
+    }
+
+    /// Advances the time of this clock by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock() += duration;
+    }
+
+    /// Returns the time elapsed since this clock was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.instant + self.elapsed()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.system_time + self.elapsed()
+    }
+}
+
+/// A cloneable handle to a [`Clock`].
+///
+/// Defaults to the [`SystemClock`].
+///
+/// # Extractor
+///
+/// When used as an extractor, it returns the clock in the request extensions,
+/// or the [`SystemClock`] if there is none.
+#[derive(Clone)]
+pub struct SharedClock(Arc<dyn Clock>);
+
+impl Debug for SharedClock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedClock").finish()
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        Self::new(SystemClock)
+    }
+}
+
+impl SharedClock {
+    /// Create a `SharedClock` from a clock.
+    pub fn new(clock: impl Clock) -> Self {
+        Self(Arc::new(clock))
+    }
+
+    /// Returns the clock in the request extensions, or the [`SystemClock`] if
+    /// there is none.
+    pub fn from_request(req: &Request) -> Self {
+        req.extensions().get::<Self>().cloned().unwrap_or_default()
+    }
+
+    /// Returns the duration since [`SystemTime::UNIX_EPOCH`].
+    pub fn elapsed_since_epoch(&self) -> Duration {
+        self.system_time()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+}
+
+impl Clock for SharedClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        self.0.now()
+    }
+
+    #[inline]
+    fn system_time(&self) -> SystemTime {
+        self.0.system_time()
+    }
+}
+
+impl<'a> FromRequest<'a> for SharedClock {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(SharedClock::from_request(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[test]
+    fn mock_clock() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        assert_eq!(clock.system_time(), SystemTime::UNIX_EPOCH);
+
+        clock.clone().advance(Duration::from_secs(10));
+        assert_eq!(clock.now() - start, Duration::from_secs(10));
+        assert_eq!(
+            clock.system_time(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(10)
+        );
+    }
+
+    #[tokio::test]
+    async fn extract_clock() {
+        #[handler(internal)]
+        fn index(clock: SharedClock) -> String {
+            clock.elapsed_since_epoch().as_secs().to_string()
+        }
+
+        let clock = MockClock::starting_at(SystemTime::UNIX_EPOCH + Duration::from_secs(100));
+        let cli = TestClient::new(index.data(SharedClock::new(clock.clone())));
+        cli.get("/").send().await.assert_text("100").await;
+        clock.advance(Duration::from_secs(5));
+        cli.get("/").send().await.assert_text("105").await;
+
+        let cli = TestClient::new(index);
+        let resp = cli.get("/").send().await;
+        assert!(
+            resp.0
+                .into_body()
+                .into_string()
+                .await
+                .unwrap()
+                .parse::<u64>()
+                .unwrap()
+                > 0
+        );
+    }
+}
@@ -0,0 +1,605 @@
+//! Typed application config with live reload.
+//!
+//! The config is loaded from multiple layered sources, the later sources
+//! override the values of the earlier ones:
+//!
+//! - Files, the format is detected by the extension, `.json` is always
+//!   supported, and `.yaml`/`.yml` is supported with the `yaml` feature.
+//! - Environment variables with the specified prefix. The prefix is stripped,
+//!   and `__` separates the nested keys, for example `APP_SERVER__PORT=8080`
+//!   with the prefix `APP` sets `server.port` to `8080`.
+//!
+//! A [`Config<T>`] can be shared with the endpoints via
+//! [`EndpointExt::data`](crate::EndpointExt::data), and used as an extractor.
+//!
+//! # Example
+//!
+//! ```
+//! use poem::{config::Config, handler, test::TestClient, EndpointExt};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct AppConfig {
+//!     greeting: String,
+//! }
+//!
+//! #[handler]
+//! fn index(config: Config<AppConfig>) -> String {
+//!     config.get().greeting.clone()
+//! }
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! # let path = std::env::temp_dir().join("poem-config-example.json");
+//! # std::fs::write(&path, r#"{"greeting": "hello"}"#).unwrap();
+//! let config = Config::<AppConfig>::builder()
+//!     .add_file(&path)
+//!     .add_env("APP")
+//!     .build()
+//!     .unwrap();
+//! let cli = TestClient::new(index.data(config));
+//!
+//! let resp = cli.get("/").send().await;
+//! resp.assert_status_is_ok();
+//! resp.assert_text("hello").await;
+//! # });
+//! ```
+//!
+//! # Live reload
+//!
+//! Call [`ConfigBuilder::watch`] to check the files for changes periodically.
+//! When the config is reloaded, the change hooks registered with
+//! [`Config::on_change`] are called, and the receivers returned by
+//! [`Config::subscribe`] are notified.
+
+use std::{
+    fs::Metadata,
+    path::{Path, PathBuf},
+    sync::{Arc, Weak},
+    time::{Duration, SystemTime},
+};
+
+use parking_lot::Mutex;
+use serde::{
+    de::{
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeOwned, IntoDeserializer, Visitor,
+    },
+    forward_to_deserialize_any, Deserializer,
+};
+use serde_json::{Map, Value};
+use tokio::sync::watch;
+
+use crate::{
+    error::{ConfigError, GetDataError},
+    FromRequest, Request, RequestBody, Result,
+};
+
+enum Source {
+    File { path: PathBuf, required: bool },
+    Env { prefix: String },
+}
+
+impl Source {
+    fn load(&self, value: &mut Value) -> Result<(), ConfigError> {
+        match self {
+            Source::File { path, required } => {
+                let data = match std::fs::read(path) {
+                    Ok(data) => data,
+                    Err(err) if !required && err.kind() == std::io::ErrorKind::NotFound => {
+                        return Ok(())
+                    }
+                    Err(err) => return Err(err.into()),
+                };
+                merge(value, parse_file(path, &data)?);
+            }
+            Source::Env { prefix } => {
+                let prefix = format!("{prefix}_");
+                for (name, env_value) in std::env::vars() {
+                    if let Some(key) = name.strip_prefix(&prefix) {
+                        merge(value, env_to_value(key, &env_value));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_file(path: &Path, data: &[u8]) -> Result<Value, ConfigError> {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    match extension {
+        "json" => serde_json::from_slice(data).map_err(|err| ConfigError::Parse {
+            path: path.to_path_buf(),
+            reason: err.to_string(),
+        }),
+        #[cfg(feature = "yaml")]
+        "yaml" | "yml" => serde_yaml::from_slice(data).map_err(|err| ConfigError::Parse {
+            path: path.to_path_buf(),
+            reason: err.to_string(),
+        }),
+        _ => Err(ConfigError::UnsupportedFormat(path.to_path_buf())),
+    }
+}
+
+fn env_to_value(key: &str, env_value: &str) -> Value {
+    key.rsplit("__")
+        .fold(Value::String(env_value.to_string()), |value, key| {
+            let mut map = Map::new();
+            map.insert(key.to_lowercase(), value);
+            Value::Object(map)
+        })
+}
+
+fn merge(target: &mut Value, value: Value) {
+    match (target, value) {
+        (Value::Object(target), Value::Object(value)) => {
+            for (key, value) in value {
+                match target.get_mut(&key) {
+                    Some(target) => merge(target, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, value) => *target = value,
+    }
+}
+
+/// A deserializer over the merged config value, which parses the strings as
+/// scalars on demand.
+///
+/// The environment variables are always strings, so `APP_SERVER__PORT=8080`
+/// is deserialized as a number only when the target field is a number, and a
+/// `String` field still accepts `APP_PASSWORD=12345`.
+struct Lenient(Value);
+
+impl<'de> IntoDeserializer<'de, serde_json::Error> for Lenient {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! lenient_parse {
+    ($($deserialize:ident => $visit:ident($ty:ty),)*) => {
+        $(
+            fn $deserialize<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                match self.0 {
+                    Value::String(s) => match s.trim().parse::<$ty>() {
+                        Ok(value) => visitor.$visit(value),
+                        Err(_) => visitor.visit_string(s),
+                    },
+                    value => value.$deserialize(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Lenient {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::String(s) => visitor.visit_string(s),
+            Value::Array(values) => {
+                let mut seq = SeqDeserializer::new(values.into_iter().map(Lenient));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Object(map) => {
+                let mut map = MapDeserializer::new(map.into_iter().map(|(k, v)| (k, Lenient(v))));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    lenient_parse! {
+        deserialize_bool => visit_bool(bool),
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_i128 => visit_i128(i128),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_u128 => visit_u128(u128),
+        deserialize_f32 => visit_f32(f32),
+        deserialize_f64 => visit_f64(f64),
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(Lenient(value)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+type FileVersion = Option<(SystemTime, u64)>;
+
+type ChangeHook<T> = Box<dyn Fn(&T) + Send + Sync>;
+
+struct Inner<T> {
+    sources: Vec<Source>,
+    tx: watch::Sender<Arc<T>>,
+    hooks: Mutex<Vec<ChangeHook<T>>>,
+}
+
+impl<T: DeserializeOwned> Inner<T> {
+    fn load(sources: &[Source]) -> Result<T, ConfigError> {
+        let mut value = Value::Object(Map::new());
+        for source in sources {
+            source.load(&mut value)?;
+        }
+        T::deserialize(Lenient(value)).map_err(|err| ConfigError::Deserialize(err.to_string()))
+    }
+
+    fn file_versions(&self) -> Vec<FileVersion> {
+        self.sources
+            .iter()
+            .filter_map(|source| match source {
+                Source::File { path, .. } => Some(
+                    std::fs::metadata(path)
+                        .and_then(|metadata: Metadata| Ok((metadata.modified()?, metadata.len())))
+                        .ok(),
+                ),
+                Source::Env { .. } => None,
+            })
+            .collect()
+    }
+}
+
+/// A builder for [`Config`].
+pub struct ConfigBuilder<T> {
+    sources: Vec<Source>,
+    watch_interval: Option<Duration>,
+    _mark: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T> ConfigBuilder<T>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    /// Adds a config file, returns an error when building if it does not
+    /// exist.
+    #[must_use]
+    pub fn add_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(Source::File {
+            path: path.into(),
+            required: true,
+        });
+        self
+    }
+
+    /// Adds a config file, which is ignored if it does not exist.
+    #[must_use]
+    pub fn add_optional_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(Source::File {
+            path: path.into(),
+            required: false,
+        });
+        self
+    }
+
+    /// Adds the environment variables starting with `{prefix}_`.
+    #[must_use]
+    pub fn add_env(mut self, prefix: impl Into<String>) -> Self {
+        self.sources.push(Source::Env {
+            prefix: prefix.into(),
+        });
+        self
+    }
+
+    /// Checks the config files for changes at the specified interval, and
+    /// reloads the config when they change.
+    ///
+    /// The watcher task is spawned when building, so it must be called in
+    /// the context of a Tokio runtime. It stops when all the handles of the
+    /// config are dropped.
+    #[must_use]
+    pub fn watch(self, interval: Duration) -> Self {
+        Self {
+            watch_interval: Some(interval),
+            ..self
+        }
+    }
+
+    /// Loads the config.
+    pub fn build(self) -> Result<Config<T>, ConfigError> {
+        let value = Inner::<T>::load(&self.sources)?;
+        let (tx, _) = watch::channel(Arc::new(value));
+        let inner = Arc::new(Inner {
+            sources: self.sources,
+            tx,
+            hooks: Default::default(),
+        });
+
+        if let Some(interval) = self.watch_interval {
+            let versions = inner.file_versions();
+            crate::spawn::spawn(
+                "poem-config-watch",
+                watch_files(Arc::downgrade(&inner), versions, interval),
+            );
+        }
+
+        Ok(Config { inner })
+    }
+}
+
+async fn watch_files<T>(inner: Weak<Inner<T>>, mut versions: Vec<FileVersion>, interval: Duration)
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    loop {
+        tokio::time::sleep(interval).await;
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        let new_versions = inner.file_versions();
+        if new_versions != versions {
+            versions = new_versions;
+            if let Err(err) = (Config { inner }).reload() {
+                tracing::error!(error = %err, "failed to reload config");
+            }
+        }
+    }
+}
+
+/// A typed application config.
+///
+/// It is a cheap cloneable handle, the clones share the same value and see
+/// the reloaded values.
+///
+/// # Errors
+///
+/// When used as an extractor, it returns [`GetDataError`] if the config is
+/// not added to the request extensions.
+pub struct Config<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for Config<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Config<T>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    /// Create a [`ConfigBuilder`].
+    pub fn builder() -> ConfigBuilder<T> {
+        ConfigBuilder {
+            sources: Vec::new(),
+            watch_interval: None,
+            _mark: Default::default(),
+        }
+    }
+
+    /// Returns the current value of the config.
+    pub fn get(&self) -> Arc<T> {
+        self.inner.tx.borrow().clone()
+    }
+
+    /// Reloads the config from the sources.
+    ///
+    /// If it fails, the current value is not changed.
+    pub fn reload(&self) -> Result<(), ConfigError> {
+        let value = Arc::new(Inner::<T>::load(&self.inner.sources)?);
+        self.inner.tx.send_replace(value.clone());
+        for hook in self.inner.hooks.lock().iter() {
+            hook(&value);
+        }
+        tracing::info!("config reloaded");
+        Ok(())
+    }
+
+    /// Registers a hook that is called with the new value whenever the config
+    /// is reloaded.
+    pub fn on_change(&self, hook: impl Fn(&T) + Send + Sync + 'static) {
+        self.inner.hooks.lock().push(Box::new(hook));
+    }
+
+    /// Returns a receiver that is notified whenever the config is reloaded.
+    pub fn subscribe(&self) -> watch::Receiver<Arc<T>> {
+        self.inner.tx.subscribe()
+    }
+}
+
+impl<'a, T> FromRequest<'a> for Config<T>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<Config<T>>()
+            .cloned()
+            .ok_or_else(|| GetDataError(std::any::type_name::<Config<T>>()))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct AppConfig {
+        name: String,
+        server: ServerConfig,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct ServerConfig {
+        port: u16,
+        #[serde(default)]
+        debug: bool,
+    }
+
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("poem-config-{}-{name}", std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn layered() {
+        let base = temp_file("base.json", r#"{"name": "a", "server": {"port": 80}}"#);
+        let local = temp_file("local.json", r#"{"server": {"debug": true}}"#);
+        std::env::set_var("POEM_LAYERED_SERVER__PORT", "8080");
+
+        let config = Config::<AppConfig>::builder()
+            .add_file(&base)
+            .add_optional_file(&local)
+            .add_optional_file("not-exists.json")
+            .add_env("POEM_LAYERED")
+            .build()
+            .unwrap();
+        assert_eq!(
+            *config.get(),
+            AppConfig {
+                name: "a".to_string(),
+                server: ServerConfig {
+                    port: 8080,
+                    debug: true
+                }
+            }
+        );
+
+        assert!(matches!(
+            Config::<AppConfig>::builder()
+                .add_file("not-exists.json")
+                .build(),
+            Err(ConfigError::Io(_))
+        ));
+        assert!(matches!(
+            Config::<AppConfig>::builder().add_file(&local).build(),
+            Err(ConfigError::Deserialize(_))
+        ));
+    }
+
+    #[test]
+    fn env_string_values() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct EnvConfig {
+            password: String,
+            name: String,
+            token: Option<String>,
+            port: u16,
+            debug: bool,
+            ratio: Option<f64>,
+        }
+
+        std::env::set_var("POEM_ENV_STRING_PASSWORD", "12345");
+        std::env::set_var("POEM_ENV_STRING_NAME", "true");
+        std::env::set_var("POEM_ENV_STRING_TOKEN", "null");
+        std::env::set_var("POEM_ENV_STRING_PORT", "8080");
+        std::env::set_var("POEM_ENV_STRING_DEBUG", "true");
+        std::env::set_var("POEM_ENV_STRING_RATIO", "0.5");
+
+        let config = Config::<EnvConfig>::builder()
+            .add_env("POEM_ENV_STRING")
+            .build()
+            .unwrap();
+        assert_eq!(
+            *config.get(),
+            EnvConfig {
+                password: "12345".to_string(),
+                name: "true".to_string(),
+                token: Some("null".to_string()),
+                port: 8080,
+                debug: true,
+                ratio: Some(0.5),
+            }
+        );
+
+        std::env::set_var("POEM_ENV_STRING_PORT", "abc");
+        assert!(matches!(config.reload(), Err(ConfigError::Deserialize(_))));
+    }
+
+    #[tokio::test]
+    async fn reload() {
+        let path = temp_file("reload.json", r#"{"name": "a", "server": {"port": 80}}"#);
+        let config = Config::<AppConfig>::builder()
+            .add_file(&path)
+            .watch(Duration::from_millis(10))
+            .build()
+            .unwrap();
+        let changes = Arc::new(AtomicUsize::new(0));
+        config.on_change({
+            let changes = changes.clone();
+            move |_| {
+                changes.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        let mut rx = config.subscribe();
+
+        std::fs::write(&path, r#"{"name": "bb", "server": {"port": 81}}"#).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), rx.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.get().name, "bb");
+        assert_eq!(config.get().server.port, 81);
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+
+        std::fs::write(&path, "{").unwrap();
+        assert!(matches!(config.reload(), Err(ConfigError::Parse { .. })));
+        assert_eq!(config.get().name, "bb");
+    }
+
+    #[tokio::test]
+    async fn extractor() {
+        #[handler(internal)]
+        fn index(config: Config<AppConfig>) -> String {
+            config.get().name.clone()
+        }
+
+        let path = temp_file("extractor.json", r#"{"name": "a", "server": {"port": 80}}"#);
+        let config = Config::<AppConfig>::builder()
+            .add_file(&path)
+            .build()
+            .unwrap();
+        let cli = TestClient::new(index.data(config));
+        cli.get("/").send().await.assert_text("a").await;
+    }
+}
@@ -0,0 +1,445 @@
+//! Lightweight dependency injection.
+//!
+//! Register the constructors of the dependencies in a [`Container`], and
+//! extract them in the handlers with [`Dep<T>`], instead of threading
+//! `Data<Arc<...>>` through the routes manually.
+//!
+//! A dependency is either a singleton, which is created once and shared by all
+//! requests, or scoped, which is created at most once per request.
+//!
+//! The container can be registered on the server with
+//! [`Server::provide`](crate::Server::provide), or applied to an endpoint as a
+//! middleware.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use poem::{
+//!     di::{Container, Dep},
+//!     handler,
+//!     test::TestClient,
+//!     EndpointExt, Route,
+//! };
+//!
+//! struct Db {
+//!     url: String,
+//! }
+//!
+//! struct UserRepo {
+//!     db: Arc<Db>,
+//! }
+//!
+//! #[handler]
+//! fn index(repo: Dep<UserRepo>) -> String {
+//!     repo.db.url.clone()
+//! }
+//!
+//! let container = Container::new()
+//!     .provide(|_| {
+//!         Ok(Db {
+//!             url: "postgres://localhost".to_string(),
+//!         })
+//!     })
+//!     .provide_scoped(|injector| {
+//!         Ok(UserRepo {
+//!             db: injector.resolve::<Db>()?,
+//!         })
+//!     });
+//!
+//! let app = Route::new().at("/", index).with(container);
+//! let cli = TestClient::new(app);
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let resp = cli.get("/").send().await;
+//! resp.assert_status_is_ok();
+//! resp.assert_text("postgres://localhost").await;
+//! # });
+//! ```
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    ops::Deref,
+    sync::{Arc, OnceLock},
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    error::ResolveDepError, Endpoint, FromRequest, Middleware, Request, RequestBody, Result,
+};
+
+type Instance = Arc<dyn Any + Send + Sync>;
+type Factory = Box<dyn Fn(&Injector) -> Result<Instance> + Send + Sync>;
+
+enum Provider {
+    Singleton {
+        factory: Factory,
+        instance: OnceLock<Instance>,
+    },
+    Scoped(Factory),
+}
+
+#[allow(clippy::result_large_err)]
+fn into_factory<T, F>(factory: F) -> Factory
+where
+    T: Send + Sync + 'static,
+    F: Fn(&Injector) -> Result<T> + Send + Sync + 'static,
+{
+    Box::new(move |injector| factory(injector).map(|value| Arc::new(value) as Instance))
+}
+
+/// A container of the dependency constructors.
+///
+/// See the [module level documentation](self) for more details.
+#[derive(Default, Clone)]
+pub struct Container {
+    providers: Arc<HashMap<TypeId, Arc<Provider>>>,
+}
+
+impl Container {
+    /// Create an empty container.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns `true` if no dependency is registered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+
+    fn insert<T: 'static>(mut self, provider: Provider) -> Self {
+        Arc::make_mut(&mut self.providers).insert(TypeId::of::<T>(), Arc::new(provider));
+        self
+    }
+
+    /// Registers a singleton dependency, the instance will be created by
+    /// `factory` when it is resolved for the first time and shared by all
+    /// requests.
+    #[must_use]
+    pub fn provide<T, F>(self, factory: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&Injector) -> Result<T> + Send + Sync + 'static,
+    {
+        self.insert::<T>(Provider::Singleton {
+            factory: into_factory(factory),
+            instance: OnceLock::new(),
+        })
+    }
+
+    /// Registers a singleton dependency with an existing instance.
+    #[must_use]
+    #[allow(clippy::result_large_err)]
+    pub fn provide_instance<T>(self, instance: T) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        let cell = OnceLock::new();
+        let _ = cell.set(Arc::new(instance) as Instance);
+        self.insert::<T>(Provider::Singleton {
+            factory: Box::new(|_| unreachable!()),
+            instance: cell,
+        })
+    }
+
+    /// Registers a scoped dependency, the instance will be created by
+    /// `factory` at most once per request.
+    #[must_use]
+    pub fn provide_scoped<T, F>(self, factory: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&Injector) -> Result<T> + Send + Sync + 'static,
+    {
+        self.insert::<T>(Provider::Scoped(into_factory(factory)))
+    }
+
+    /// Creates an [`Injector`] for a new request scope.
+    pub fn injector(&self) -> Injector {
+        Injector {
+            providers: self.providers.clone(),
+            scoped: Default::default(),
+            resolving: Vec::new(),
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Container {
+    type Output = ContainerEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ContainerEndpoint {
+            inner: ep,
+            container: self.clone(),
+        }
+    }
+}
+
+/// Endpoint for the [`Container`] middleware.
+pub struct ContainerEndpoint<E> {
+    inner: E,
+    container: Container,
+}
+
+impl<E: Endpoint> Endpoint for ContainerEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        req.extensions_mut().insert(self.container.injector());
+        self.inner.call(req).await
+    }
+}
+
+/// Resolves the dependencies in a request scope.
+///
+/// An injector is passed to the dependency constructors, so that they can
+/// resolve the other dependencies.
+#[derive(Clone)]
+pub struct Injector {
+    providers: Arc<HashMap<TypeId, Arc<Provider>>>,
+    scoped: Arc<Mutex<HashMap<TypeId, Instance>>>,
+    /// The dependencies being constructed by the factories up the call stack.
+    resolving: Vec<TypeId>,
+}
+
+impl Injector {
+    /// Resolves the dependency of type `T`.
+    ///
+    /// # Errors
+    ///
+    /// - [`ResolveDepError`]
+    #[allow(clippy::result_large_err)]
+    pub fn resolve<T: Send + Sync + 'static>(&self) -> Result<Arc<T>> {
+        let type_id = TypeId::of::<T>();
+        let instance = match self.providers.get(&type_id).map(AsRef::as_ref) {
+            Some(Provider::Singleton { factory, instance }) => match instance.get() {
+                Some(instance) => instance.clone(),
+                None => {
+                    let value = self.construct::<T>(factory)?;
+                    instance.get_or_init(|| value).clone()
+                }
+            },
+            Some(Provider::Scoped(factory)) => {
+                if let Some(instance) = self.scoped.lock().get(&type_id) {
+                    return Ok(downcast(instance.clone()));
+                }
+                let value = self.construct::<T>(factory)?;
+                self.scoped.lock().entry(type_id).or_insert(value).clone()
+            }
+            None => return Err(ResolveDepError::NotProvided(std::any::type_name::<T>()).into()),
+        };
+        Ok(downcast(instance))
+    }
+
+    /// Calls the factory of `T` with an injector that remembers `T` is being
+    /// constructed, so that a factory depending on itself is rejected instead
+    /// of overflowing the stack.
+    #[allow(clippy::result_large_err)]
+    fn construct<T: 'static>(&self, factory: &Factory) -> Result<Instance> {
+        let type_id = TypeId::of::<T>();
+        if self.resolving.contains(&type_id) {
+            return Err(ResolveDepError::Cycle(std::any::type_name::<T>()).into());
+        }
+        let mut resolving = self.resolving.clone();
+        resolving.push(type_id);
+        factory(&Injector {
+            providers: self.providers.clone(),
+            scoped: self.scoped.clone(),
+            resolving,
+        })
+    }
+}
+
+fn downcast<T: Send + Sync + 'static>(instance: Instance) -> Arc<T> {
+    instance
+        .downcast::<T>()
+        .unwrap_or_else(|_| unreachable!("the type of the instance is always `T`"))
+}
+
+/// An extractor that resolves a dependency from the [`Container`].
+///
+/// # Errors
+///
+/// - [`ResolveDepError`]
+pub struct Dep<T>(pub Arc<T>);
+
+impl<T> Deref for Dep<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> Clone for Dep<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<'a, T: Send + Sync + 'static> FromRequest<'a> for Dep<T> {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        let injector = req
+            .extensions()
+            .get::<Injector>()
+            .ok_or_else(|| ResolveDepError::NotProvided(std::any::type_name::<T>()))?;
+        injector.resolve::<T>().map(Dep)
+    }
+}
+
+impl<'a> FromRequest<'a> for &'a Injector {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<Injector>()
+            .expect("To use the `Injector` extractor, the `Container` middleware is required."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt, Error};
+
+    struct Counter {
+        n: usize,
+    }
+
+    struct Scoped {
+        n: usize,
+    }
+
+    #[tokio::test]
+    async fn singleton_and_scoped() {
+        static SINGLETONS: AtomicUsize = AtomicUsize::new(0);
+        static SCOPES: AtomicUsize = AtomicUsize::new(0);
+
+        #[handler(internal)]
+        fn index(a: Dep<Counter>, b: Dep<Scoped>, c: Dep<Scoped>) -> String {
+            assert!(Arc::ptr_eq(&b.0, &c.0));
+            format!("{}:{}", a.n, b.n)
+        }
+
+        let container = Container::new()
+            .provide(|_| {
+                Ok(Counter {
+                    n: SINGLETONS.fetch_add(1, Ordering::SeqCst),
+                })
+            })
+            .provide_scoped(|injector| {
+                let counter = injector.resolve::<Counter>()?;
+                Ok(Scoped {
+                    n: counter.n + SCOPES.fetch_add(1, Ordering::SeqCst),
+                })
+            });
+        let cli = TestClient::new(index.with(container));
+
+        cli.get("/").send().await.assert_text("0:0").await;
+        cli.get("/").send().await.assert_text("0:1").await;
+        assert_eq!(SINGLETONS.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn instance_and_errors() {
+        #[handler(internal)]
+        fn index(a: Dep<i32>) -> String {
+            a.to_string()
+        }
+
+        #[handler(internal)]
+        fn missing(_a: Dep<String>) {}
+
+        #[handler(internal)]
+        fn failed(_a: Dep<Scoped>) {}
+
+        let container = Container::new()
+            .provide_instance(100i32)
+            .provide_scoped::<Scoped, _>(|_| {
+                Err(Error::from_status(StatusCode::SERVICE_UNAVAILABLE))
+            });
+
+        let cli = TestClient::new(index.with(container.clone()));
+        cli.get("/").send().await.assert_text("100").await;
+
+        let cli = TestClient::new(missing.with(container.clone()));
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+
+        let cli = TestClient::new(failed.with(container));
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn cycles() {
+        struct A;
+        struct B;
+
+        #[handler(internal)]
+        fn a(_a: Dep<A>) {}
+
+        #[handler(internal)]
+        fn b(_b: Dep<Scoped>) {}
+
+        let container = Container::new()
+            .provide(|injector| {
+                injector.resolve::<B>()?;
+                Ok(A)
+            })
+            .provide_scoped(|injector| {
+                injector.resolve::<A>()?;
+                Ok(B)
+            })
+            .provide_scoped(|injector| {
+                let scoped = injector.resolve::<Scoped>()?;
+                Ok(Scoped { n: scoped.n })
+            });
+
+        assert_eq!(
+            container
+                .injector()
+                .resolve::<A>()
+                .err()
+                .unwrap()
+                .downcast::<ResolveDepError>()
+                .unwrap(),
+            ResolveDepError::Cycle(std::any::type_name::<A>())
+        );
+
+        let cli = TestClient::new(a.with(container.clone()));
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        let cli = TestClient::new(b.with(container));
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn provide_after_clone() {
+        let a = Container::new().provide_instance(1i32);
+        let b = a.clone().provide_instance("b");
+        assert!(a.injector().resolve::<&str>().is_err());
+        assert_eq!(*b.injector().resolve::<&str>().unwrap(), "b");
+        assert_eq!(*b.injector().resolve::<i32>().unwrap(), 1);
+
+        let injector = a.injector();
+        let c = a.provide_instance(2u8);
+        assert_eq!(*injector.resolve::<i32>().unwrap(), 1);
+        assert_eq!(*c.injector().resolve::<u8>().unwrap(), 2);
+    }
+}
@@ -0,0 +1,319 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    error::{MethodNotAllowedError, NotFoundError},
+    http::{Method, StatusCode},
+    web::Json,
+    Endpoint, Error, IntoResponse, Request, Response, Result, Route,
+};
+
+type SectionFn = Arc<dyn Fn() -> Value + Send + Sync>;
+type GuardFn = Arc<dyn Fn(&Request) -> bool + Send + Sync>;
+
+/// An endpoint exposing the runtime information for the troubleshooting.
+///
+/// The information is organized in sections, each section is returned as JSON
+/// by `GET /<section>`, and `GET /` returns the names of the sections. The
+/// following sections are available:
+///
+/// | Section       | Description                                          |
+/// |---------------|------------------------------------------------------|
+/// | `runtime`     | The metrics of the tokio runtime                     |
+/// | `memory`      | The memory usage of the process, only on Linux       |
+/// | `routes`      | The route table, see [`AdminEndpoints::routes`]      |
+/// | `connections` | The active connections, see [`AdminEndpoints::connections`] |
+/// | `config`      | The configuration, see [`AdminEndpoints::config`]    |
+/// | `tasks`       | The task metrics, see `AdminEndpoints::task_metrics` |
+/// | `middleware`  | The middleware traces, see [`AdminEndpoints::middleware_trace`] |
+/// | `usage`       | The resource usage of the routes, see `AdminEndpoints::resource_usage` |
+///
+/// By default only the requests from the loopback addresses are allowed, use
+/// [`AdminEndpoints::guard`] to authorize the requests, the other requests are
+/// rejected with `403 Forbidden`.
+///
+/// # Errors
+///
+/// - [`NotFoundError`]
+/// - [`MethodNotAllowedError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     endpoint::AdminEndpoints, get, handler, http::StatusCode, test::TestClient, Request, Route,
+/// };
+///
+/// #[handler]
+/// fn index() {}
+///
+/// let routes = Route::new()
+///     .at("/", get(index))
+///     .at("/users/:id", get(index));
+/// let admin = AdminEndpoints::new()
+///     .routes(&routes)
+///     .config("cors", ["https://example.com"])
+///     .guard(|req: &Request| req.header("x-admin-token") == Some("secret"));
+/// let app = routes.nest("/admin", admin);
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .get("/admin/routes")
+///     .header("x-admin-token", "secret")
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_json(["/", "/users/:id"]).await;
+///
+/// cli.get("/admin/routes")
+///     .send()
+///     .await
+///     .assert_status(StatusCode::FORBIDDEN);
+/// # });
+/// ```
+pub struct AdminEndpoints {
+    sections: BTreeMap<String, SectionFn>,
+    config: Map<String, Value>,
+    guard: GuardFn,
+}
+
+impl Default for AdminEndpoints {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdminEndpoints {
+    /// Create an `AdminEndpoints` with the `runtime` and `memory` sections.
+    pub fn new() -> Self {
+        Self {
+            sections: BTreeMap::new(),
+            config: Map::new(),
+            guard: Arc::new(|req: &Request| {
+                req.remote_addr()
+                    .as_socket_addr()
+                    .is_some_and(|addr| addr.ip().is_loopback())
+            }),
+        }
+        .section("runtime", runtime)
+        .section("memory", memory)
+    }
+
+    /// Adds a section, the function is called for each request to get the
+    /// value of the section.
+    #[must_use]
+    pub fn section<F>(mut self, name: impl Into<String>, f: F) -> Self
+    where
+        F: Fn() -> Value + Send + Sync + 'static,
+    {
+        self.sections.insert(name.into(), Arc::new(f));
+        self
+    }
+
+    /// Adds the `routes` section with the paths of the route.
+    #[must_use]
+    pub fn routes(self, route: &Route) -> Self {
+        let paths: Value = route.paths().collect::<Vec<_>>().into();
+        self.section("routes", move || paths.clone())
+    }
+
+    /// Adds the `connections` section with the number of the connections
+    /// being served by the server.
+    #[cfg(feature = "server")]
+    #[must_use]
+    pub fn connections(self, connections: crate::ServerConnections) -> Self {
+        self.section(
+            "connections",
+            move || serde_json::json!({ "active": connections.active() }),
+        )
+    }
+
+    /// Adds the task metrics of the
+    /// [`TokioMetrics`](crate::middleware::TokioMetrics) middleware as the
+    /// `tasks` section.
+    #[cfg(feature = "tokio-metrics")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio-metrics")))]
+    #[must_use]
+    pub fn task_metrics(self, metrics: &crate::middleware::TokioMetrics) -> Self {
+        self.section("tasks", metrics.json())
+    }
+
+    /// Adds the traces of the recent requests recorded by the
+    /// [`MiddlewareTrace`](crate::middleware::MiddlewareTrace) as the
+    /// `middleware` section.
+    #[must_use]
+    pub fn middleware_trace(self, trace: &crate::middleware::MiddlewareTrace) -> Self {
+        self.section("middleware", trace.json())
+    }
+
+    /// Adds the usage of each route accumulated by the
+    /// [`ResourceUsage`](crate::middleware::ResourceUsage) middleware as the
+    /// `usage` section, the most expensive first.
+    #[cfg(feature = "resource-usage")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resource-usage")))]
+    #[must_use]
+    pub fn resource_usage(self, usage: &crate::middleware::ResourceUsage) -> Self {
+        self.section("usage", usage.json())
+    }
+
+    /// Adds a value to the `config` section, for example the configuration of
+    /// the middlewares.
+    ///
+    /// The value is ignored if it can not be serialized.
+    #[must_use]
+    pub fn config(mut self, name: impl Into<String>, value: impl Serialize) -> Self {
+        if let Ok(value) = serde_json::to_value(value) {
+            self.config.insert(name.into(), value);
+        }
+        self
+    }
+
+    /// Uses a closure to authorize the requests.
+    #[must_use]
+    pub fn guard<F>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            guard: Arc::new(f),
+            ..self
+        }
+    }
+}
+
+impl Endpoint for AdminEndpoints {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        if !(self.guard)(&req) {
+            return Err(Error::from_status(StatusCode::FORBIDDEN));
+        }
+        if req.method() != Method::GET {
+            return Err(MethodNotAllowedError.into());
+        }
+
+        let value = match req.uri().path().trim_matches('/') {
+            "" => {
+                let mut names: Vec<&str> = self.sections.keys().map(String::as_str).collect();
+                if !self.config.is_empty() {
+                    names.push("config");
+                    names.sort_unstable();
+                }
+                names.into()
+            }
+            "config" if !self.config.is_empty() => Value::Object(self.config.clone()),
+            name => match self.sections.get(name) {
+                Some(f) => f(),
+                None => return Err(NotFoundError.into()),
+            },
+        };
+        Ok(Json(value).into_response())
+    }
+}
+
+fn runtime() -> Value {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => {
+            let metrics = handle.metrics();
+            serde_json::json!({
+                "workers": metrics.num_workers(),
+                "alive_tasks": metrics.num_alive_tasks(),
+                "global_queue_depth": metrics.global_queue_depth(),
+            })
+        }
+        Err(_) => Value::Null,
+    }
+}
+
+/// Returns the memory usage in bytes from `/proc/self/status`, it is empty on
+/// the other platforms.
+fn memory() -> Value {
+    let mut stats = Map::new();
+    if let Ok(status) = std::fs::read_to_string("/proc/self/status") {
+        for (key, value) in status.lines().filter_map(|line| line.split_once(':')) {
+            let name = match key {
+                "VmRSS" => "rss",
+                "VmHWM" => "peak_rss",
+                "VmSize" => "virtual",
+                "VmData" => "data",
+                _ => continue,
+            };
+            if let Some(kb) = value
+                .trim()
+                .strip_suffix("kB")
+                .and_then(|kb| kb.trim().parse::<u64>().ok())
+            {
+                stats.insert(name.to_string(), (kb * 1024).into());
+            }
+        }
+    }
+    Value::Object(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{endpoint::make_sync, test::TestClient, web::RemoteAddr, Addr};
+
+    fn admin() -> AdminEndpoints {
+        let routes = Route::new()
+            .at("/a", make_sync(|_| ()))
+            .nest("/b", Route::new().at("/c", make_sync(|_| ())));
+        AdminEndpoints::new()
+            .routes(&routes)
+            .config("timeout", 30)
+            .section("custom", || "value".into())
+    }
+
+    #[tokio::test]
+    async fn sections() {
+        let cli = TestClient::new(admin().guard(|_| true));
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_json(["config", "custom", "memory", "routes", "runtime"])
+            .await;
+
+        cli.get("/routes")
+            .send()
+            .await
+            .assert_json(["/a", "/b/c"])
+            .await;
+        cli.get("/config")
+            .send()
+            .await
+            .assert_json(serde_json::json!({ "timeout": 30 }))
+            .await;
+        cli.get("/custom/").send().await.assert_json("value").await;
+
+        let resp = cli.get("/runtime").send().await;
+        let runtime = resp.json().await;
+        assert!(runtime.value().object().get("workers").i64() >= 1);
+
+        cli.get("/missing")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+        cli.post("/routes")
+            .send()
+            .await
+            .assert_status(StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn guard() {
+        let ep = admin();
+        let resp = ep.get_response(Request::default()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let mut req = Request::default();
+        req.state_mut().remote_addr = RemoteAddr(Addr::from(
+            "127.0.0.1:1234".parse::<std::net::SocketAddr>().unwrap(),
+        ));
+        let resp = ep.get_response(req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
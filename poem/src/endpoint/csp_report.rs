@@ -0,0 +1,346 @@
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::{Clock, SharedClock},
+    error::BadRequest,
+    http::{Method, StatusCode},
+    Endpoint, Error, Request, Response, Result,
+};
+
+/// A Content-Security-Policy violation report.
+///
+/// Both the legacy `application/csp-report` format and the
+/// [Reporting API](https://www.w3.org/TR/reporting-1/) format are parsed into
+/// this type.
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CspReport {
+    /// The URL of the document in which the violation occurred.
+    #[serde(rename = "document-uri", alias = "documentURL", default)]
+    pub document_uri: Option<String>,
+    /// The referrer of the document.
+    #[serde(default)]
+    pub referrer: Option<String>,
+    /// The URL of the resource that was blocked.
+    #[serde(rename = "blocked-uri", alias = "blockedURL", default)]
+    pub blocked_uri: Option<String>,
+    /// The directive whose enforcement caused the violation.
+    #[serde(rename = "effective-directive", alias = "effectiveDirective", default)]
+    pub effective_directive: Option<String>,
+    /// The directive that was violated, only in the legacy format.
+    #[serde(rename = "violated-directive", default)]
+    pub violated_directive: Option<String>,
+    /// The original policy.
+    #[serde(rename = "original-policy", alias = "originalPolicy", default)]
+    pub original_policy: Option<String>,
+    /// `enforce` or `report`.
+    #[serde(default)]
+    pub disposition: Option<String>,
+    /// The HTTP status code of the document.
+    #[serde(rename = "status-code", alias = "statusCode", default)]
+    pub status_code: Option<u16>,
+    /// The URL of the resource where the violation occurred.
+    #[serde(rename = "source-file", alias = "sourceFile", default)]
+    pub source_file: Option<String>,
+    /// The line number in the source file.
+    #[serde(rename = "line-number", alias = "lineNumber", default)]
+    pub line_number: Option<u32>,
+    /// The column number in the source file.
+    #[serde(rename = "column-number", alias = "columnNumber", default)]
+    pub column_number: Option<u32>,
+    /// The first characters of the inline script or style.
+    #[serde(rename = "script-sample", alias = "sample", default)]
+    pub sample: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LegacyReport {
+    #[serde(rename = "csp-report")]
+    csp_report: CspReport,
+}
+
+#[derive(Deserialize)]
+struct Report {
+    #[serde(rename = "type")]
+    ty: String,
+    body: CspReport,
+}
+
+struct RateLimit {
+    max_reports: usize,
+    period: Duration,
+    /// The start of the current window, and the number of accepted reports.
+    window: Mutex<(Option<Instant>, usize)>,
+}
+
+impl RateLimit {
+    fn acquire(&self, n: usize, now: Instant) -> usize {
+        let mut window = self.window.lock();
+        if window.0.map_or(true, |start| {
+            now.saturating_duration_since(start) >= self.period
+        }) {
+            *window = (Some(now), 0);
+        }
+        let n = n.min(self.max_reports - window.1);
+        window.1 += n;
+        n
+    }
+}
+
+/// An endpoint that accepts Content-Security-Policy violation reports.
+///
+/// The reports in the `application/csp-report` and `application/reports+json`
+/// formats are parsed and passed to the callback, and the reports of the
+/// other types in the Reporting API are ignored. It responds with
+/// `204 No Content`.
+///
+/// Use [`report_uri`](crate::middleware::ContentSecurityPolicy::report_uri) or
+/// [`report_to`](crate::middleware::ContentSecurityPolicy::report_to) to
+/// specify the URL of this endpoint in the policy.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{endpoint::CspReportEndpoint, Route};
+///
+/// let app = Route::new().at(
+///     "/csp-report",
+///     CspReportEndpoint::new(|report| {
+///         tracing::warn!(
+///             blocked_uri = ?report.blocked_uri,
+///             directive = ?report.effective_directive,
+///             "csp violation"
+///         );
+///     })
+///     .rate_limit(100, Duration::from_secs(60)),
+/// );
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+pub struct CspReportEndpoint<F> {
+    callback: F,
+    rate_limit: Option<RateLimit>,
+}
+
+impl<F> CspReportEndpoint<F>
+where
+    F: Fn(CspReport) + Send + Sync,
+{
+    /// Create a `CspReportEndpoint` with the callback.
+    pub fn new(callback: F) -> Self {
+        Self {
+            callback,
+            rate_limit: None,
+        }
+    }
+
+    /// Accepts at most `max_reports` reports per `period`, the excess reports
+    /// are dropped.
+    #[must_use]
+    pub fn rate_limit(self, max_reports: usize, period: Duration) -> Self {
+        Self {
+            rate_limit: Some(RateLimit {
+                max_reports,
+                period,
+                window: Mutex::new((None, 0)),
+            }),
+            ..self
+        }
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn parse_reports(content_type: &str, data: &[u8]) -> Result<Vec<CspReport>> {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    match essence.as_str() {
+        "application/csp-report" | "application/json" => {
+            let report = serde_json::from_slice::<LegacyReport>(data).map_err(BadRequest)?;
+            Ok(vec![report.csp_report])
+        }
+        "application/reports+json" => {
+            let reports = serde_json::from_slice::<Vec<Report>>(data).map_err(BadRequest)?;
+            Ok(reports
+                .into_iter()
+                .filter(|report| report.ty == "csp-violation")
+                .map(|report| report.body)
+                .collect())
+        }
+        _ => Err(Error::from_string(
+            format!("unsupported content type `{content_type}`"),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        )),
+    }
+}
+
+impl<F> Endpoint for CspReportEndpoint<F>
+where
+    F: Fn(CspReport) + Send + Sync,
+{
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if req.method() != Method::POST {
+            return Ok(StatusCode::METHOD_NOT_ALLOWED.into());
+        }
+
+        let content_type = req.content_type().unwrap_or_default().to_string();
+        let data = req.take_body().into_bytes().await?;
+        let mut reports = parse_reports(&content_type, &data)?;
+
+        if let Some(rate_limit) = &self.rate_limit {
+            let n = rate_limit.acquire(reports.len(), SharedClock::from_request(&req).now());
+            reports.truncate(n);
+        }
+        for report in reports {
+            (self.callback)(report);
+        }
+
+        Ok(StatusCode::NO_CONTENT.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{clock::MockClock, test::TestClient, EndpointExt};
+
+    #[tokio::test]
+    async fn csp_report() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let ep = CspReportEndpoint::new({
+            let reports = reports.clone();
+            move |report| reports.lock().push(report)
+        })
+        .rate_limit(2, Duration::from_secs(60));
+        let cli = TestClient::new(ep);
+
+        cli.post("/")
+            .content_type("application/csp-report")
+            .body(
+                json!({
+                    "csp-report": {
+                        "document-uri": "https://example.com/a",
+                        "blocked-uri": "inline",
+                        "violated-directive": "script-src-elem",
+                        "effective-directive": "script-src-elem",
+                        "original-policy": "script-src 'self'",
+                        "status-code": 200,
+                        "line-number": 10,
+                    }
+                })
+                .to_string(),
+            )
+            .send()
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+
+        cli.post("/")
+            .content_type("application/reports+json")
+            .body(
+                json!([
+                    {
+                        "type": "csp-violation",
+                        "body": {
+                            "documentURL": "https://example.com/b",
+                            "blockedURL": "https://evil.com/a.js",
+                            "effectiveDirective": "script-src",
+                            "disposition": "enforce",
+                            "sample": "",
+                        }
+                    },
+                    { "type": "deprecation", "body": {} },
+                    { "type": "csp-violation", "body": { "documentURL": "https://example.com/c" } },
+                ])
+                .to_string(),
+            )
+            .send()
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+
+        let reports = reports.lock();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(
+            reports[0].document_uri.as_deref(),
+            Some("https://example.com/a")
+        );
+        assert_eq!(
+            reports[0].violated_directive.as_deref(),
+            Some("script-src-elem")
+        );
+        assert_eq!(reports[0].status_code, Some(200));
+        assert_eq!(reports[0].line_number, Some(10));
+        assert_eq!(
+            reports[1].blocked_uri.as_deref(),
+            Some("https://evil.com/a.js")
+        );
+        assert_eq!(reports[1].disposition.as_deref(), Some("enforce"));
+    }
+
+    #[tokio::test]
+    async fn rate_limit() {
+        let reports = Arc::new(Mutex::new(0));
+        let clock = MockClock::new();
+        let ep = CspReportEndpoint::new({
+            let reports = reports.clone();
+            move |_| *reports.lock() += 1
+        })
+        .rate_limit(1, Duration::from_secs(60))
+        .data(SharedClock::new(clock.clone()));
+        let cli = TestClient::new(ep);
+        let send = || {
+            cli.post("/")
+                .content_type("application/csp-report")
+                .body(json!({ "csp-report": {} }).to_string())
+                .send()
+        };
+
+        send().await.assert_status(StatusCode::NO_CONTENT);
+        send().await.assert_status(StatusCode::NO_CONTENT);
+        assert_eq!(*reports.lock(), 1);
+
+        clock.advance(Duration::from_secs(59));
+        send().await.assert_status(StatusCode::NO_CONTENT);
+        assert_eq!(*reports.lock(), 1);
+
+        clock.advance(Duration::from_secs(1));
+        send().await.assert_status(StatusCode::NO_CONTENT);
+        send().await.assert_status(StatusCode::NO_CONTENT);
+        assert_eq!(*reports.lock(), 2);
+    }
+
+    #[tokio::test]
+    async fn bad_requests() {
+        let cli = TestClient::new(CspReportEndpoint::new(|_| {}));
+
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::METHOD_NOT_ALLOWED);
+        cli.post("/")
+            .content_type("text/plain")
+            .body("a")
+            .send()
+            .await
+            .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        cli.post("/")
+            .content_type("application/csp-report")
+            .body("{")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+}
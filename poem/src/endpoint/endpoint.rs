@@ -1,10 +1,10 @@
-use std::{future::Future, marker::PhantomData, sync::Arc};
+use std::{any::Any, future::Future, marker::PhantomData, sync::Arc};
 
 use futures_util::{future::BoxFuture, FutureExt};
 
 use super::{
-    After, AndThen, Around, Before, CatchAllError, CatchError, InspectAllError, InspectError, Map,
-    MapToResponse, ToResponse,
+    After, AndThen, Around, Before, CatchAllError, CatchError, HyperService, InspectAllError,
+    InspectError, Map, MapErrType, MapToResponse, ToResponse,
 };
 use crate::{
     error::IntoResult,
@@ -240,11 +240,34 @@ where
 
 /// An owned dynamically typed `Endpoint` for use in cases where you can’t
 /// statically type your result or need to add some indirection.
+///
+/// `BoxEndpoint` is `Send + Sync`, boxing the deeply composed middleware
+/// chains at a few points with [`EndpointExt::boxed`] or
+/// [`EndpointExt::with_boxed`] hides their types from the outer layers, which
+/// reduces the compile time and the binary size of the large applications.
 pub type BoxEndpoint<'a, T = Response> = Box<dyn DynEndpoint<Output = T> + 'a>;
 
+/// Boxes the endpoint for the routers, the endpoints which are already boxed
+/// as [`BoxEndpoint`] are not boxed again.
+pub(crate) fn box_endpoint<E>(ep: E) -> BoxEndpoint<'static>
+where
+    E: Endpoint + 'static,
+{
+    let mut ep = Some(ep);
+    if let Some(boxed) = (&mut ep as &mut dyn Any).downcast_mut::<Option<BoxEndpoint<'static>>>() {
+        return boxed.take().expect("the endpoint is taken once");
+    }
+    ep.expect("the endpoint is taken once")
+        .map_to_response()
+        .boxed()
+}
+
 /// Extension trait for [`Endpoint`].
 pub trait EndpointExt: IntoEndpoint {
     /// Wrap the endpoint in a Box.
+    ///
+    /// The returned [`BoxEndpoint`] is still `Send + Sync`, and the routers
+    /// do not box it again.
     fn boxed<'a>(self) -> BoxEndpoint<'a, <Self::Endpoint as Endpoint>::Output>
     where
         Self: Sized + 'a,
@@ -252,6 +275,56 @@ pub trait EndpointExt: IntoEndpoint {
         Box::new(ToDynEndpoint(self.into_endpoint()))
     }
 
+    /// Use middleware to transform this endpoint, and wrap the result in a
+    /// Box, the output is converted to [`Response`].
+    ///
+    /// This is useful to stop the types of a long middleware chain from
+    /// growing, the following middlewares only see the [`BoxEndpoint`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     endpoint::BoxEndpoint,
+    ///     handler,
+    ///     middleware::{AddData, SetHeader},
+    ///     EndpointExt,
+    /// };
+    ///
+    /// #[handler]
+    /// fn index() -> &'static str {
+    ///     "hello"
+    /// }
+    ///
+    /// let ep: BoxEndpoint = index
+    ///     .with(AddData::new(100i32))
+    ///     .with_boxed(SetHeader::new().appending("x-a", "1"));
+    /// let ep = ep.with(SetHeader::new().appending("x-b", "2"));
+    /// ```
+    fn with_boxed<'a, T>(self, middleware: T) -> BoxEndpoint<'a>
+    where
+        T: Middleware<Self::Endpoint>,
+        T::Output: 'a,
+        Self: Sized,
+    {
+        middleware
+            .transform(self.into_endpoint())
+            .map_to_response()
+            .boxed()
+    }
+
+    /// Converts this endpoint into a [`hyper::service::Service`], so that it
+    /// can be served by an externally managed hyper 1.x connection.
+    ///
+    /// See also [`HyperService`].
+    fn into_hyper_service(self) -> HyperService
+    where
+        Self: Sized,
+        Self::Endpoint: 'static,
+    {
+        HyperService::new(self.into_endpoint())
+    }
+
     /// Use middleware to transform this endpoint.
     ///
     /// # Example
@@ -759,6 +832,43 @@ pub trait EndpointExt: IntoEndpoint {
     {
         InspectError::new(self, f)
     }
+
+    /// Maps the specified type of error into another error.
+    ///
+    /// Unlike [`EndpointExt::catch_error`], the result is still an error, so
+    /// the outer middlewares can handle it. Other types of errors are passed
+    /// through unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     error::NotFoundError, handler, http::StatusCode, test::TestClient, EndpointExt, Error,
+    ///     Route,
+    /// };
+    ///
+    /// #[handler]
+    /// fn index() {}
+    ///
+    /// let app = Route::new()
+    ///     .at("/", index)
+    ///     .map_err_type(|_: NotFoundError| Error::from_string("page not found", StatusCode::GONE));
+    /// let cli = TestClient::new(app);
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let resp = cli.get("/abc").send().await;
+    /// resp.assert_status(StatusCode::GONE);
+    /// resp.assert_text("page not found").await;
+    /// # });
+    /// ```
+    fn map_err_type<ErrType, F>(self, f: F) -> MapErrType<Self, F, ErrType>
+    where
+        F: Fn(ErrType) -> Error + Send + Sync,
+        ErrType: std::error::Error + Send + Sync + 'static,
+        Self: Sized,
+    {
+        MapErrType::new(self, f)
+    }
 }
 
 impl<T: IntoEndpoint> EndpointExt for T {}
@@ -785,13 +895,13 @@ mod test {
     use http::{HeaderValue, Uri};
 
     use crate::{
-        endpoint::{make, make_sync},
+        endpoint::{make, make_sync, DynEndpoint},
         get, handler,
         http::{Method, StatusCode},
         middleware::SetHeader,
         test::TestClient,
         web::Data,
-        Endpoint, EndpointExt, Error, IntoEndpoint, Request, Route,
+        Endpoint, EndpointExt, Error, IntoEndpoint, Request, Response, Route,
     };
 
     #[tokio::test]
@@ -981,4 +1091,52 @@ mod test {
         resp.assert_status_is_ok();
         resp.assert_text("none").await;
     }
+
+    #[tokio::test]
+    async fn test_map_err_type() {
+        let ep = make_sync(|req| {
+            if req.uri().path() == "/a" {
+                Err::<(), Error>(crate::error::NotFoundError.into())
+            } else {
+                Err(crate::error::MethodNotAllowedError.into())
+            }
+        })
+        .map_err_type(|_: crate::error::NotFoundError| {
+            Error::from_string("gone", StatusCode::GONE)
+        });
+        let cli = TestClient::new(ep);
+
+        let resp = cli.get("/a").send().await;
+        resp.assert_status(StatusCode::GONE);
+        resp.assert_text("gone").await;
+
+        cli.get("/b")
+            .send()
+            .await
+            .assert_status(StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn test_with_boxed() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let ep = make_sync(|_| "hello")
+            .with(SetHeader::new().appending("x-a", "1"))
+            .with_boxed(SetHeader::new().appending("x-b", "2"));
+        assert_send_sync(&ep);
+
+        let ptr = &*ep as *const dyn DynEndpoint<Output = Response> as *const ();
+        let ep = super::box_endpoint(ep);
+        assert_eq!(
+            &*ep as *const dyn DynEndpoint<Output = Response> as *const (),
+            ptr
+        );
+
+        let cli = TestClient::new(Route::new().at("/", ep));
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("x-a", "1");
+        resp.assert_header("x-b", "2");
+        resp.assert_text("hello").await;
+    }
 }
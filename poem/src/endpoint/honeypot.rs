@@ -0,0 +1,244 @@
+use std::{net::IpAddr, sync::Arc, time::Duration};
+
+use crate::{
+    clock::{Clock, SharedClock},
+    endpoint::Tarpit,
+    http::StatusCode,
+    middleware::{remote_ip, Blocklist, IpFn},
+    Endpoint, IntoResponse, Request, Response, Result, Route,
+};
+
+/// The paths which are commonly probed by the vulnerability scanners.
+const DEFAULT_PATHS: &[&str] = &[
+    "/.env",
+    "/.git/*path",
+    "/.aws/*path",
+    "/wp-login.php",
+    "/wp-admin",
+    "/wp-admin/*path",
+    "/xmlrpc.php",
+    "/phpmyadmin",
+    "/phpmyadmin/*path",
+    "/cgi-bin/*path",
+];
+
+/// An endpoint which adds the clients requesting it to a [`Blocklist`], to
+/// block the vulnerability scanners with the
+/// [`IpFilter`](crate::middleware::IpFilter) middleware.
+///
+/// Use [`Honeypot::register`] to add it to the paths which are never used by
+/// the application but are commonly probed by the scanners, such as
+/// `/wp-login.php` and `/.env`. It responds `404 Not Found`, or keeps the
+/// clients busy with a [`Tarpit`].
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     endpoint::Honeypot,
+///     handler,
+///     http::StatusCode,
+///     middleware::{Blocklist, IpFilter, IpRules},
+///     test::TestClient,
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// fn client_ip(req: &poem::Request) -> Option<std::net::IpAddr> {
+///     req.header("x-client-ip")?.parse().ok()
+/// }
+///
+/// let blocklist = Blocklist::new();
+/// let app = Honeypot::new(blocklist.clone())
+///     .ip(client_ip)
+///     .register(Route::new().at("/", index))
+///     .with(
+///         IpFilter::new(IpRules::new())
+///             .blocklist(blocklist)
+///             .ip(client_ip),
+///     );
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .get("/.env")
+///     .header("x-client-ip", "192.0.2.1")
+///     .send()
+///     .await;
+/// resp.assert_status(StatusCode::NOT_FOUND);
+///
+/// let resp = cli.get("/").header("x-client-ip", "192.0.2.1").send().await;
+/// resp.assert_status(StatusCode::FORBIDDEN);
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "ip-filter")))]
+pub struct Honeypot {
+    blocklist: Blocklist,
+    paths: Vec<String>,
+    ttl: Duration,
+    ip: IpFn,
+    tarpit: Option<Tarpit>,
+}
+
+impl Honeypot {
+    /// Create a `Honeypot` endpoint which adds the clients to the blocklist.
+    pub fn new(blocklist: Blocklist) -> Self {
+        Self {
+            blocklist,
+            paths: DEFAULT_PATHS.iter().map(ToString::to_string).collect(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            ip: Arc::new(remote_ip),
+            tarpit: None,
+        }
+    }
+
+    /// Appends a path which is registered by [`Honeypot::register`].
+    #[must_use]
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Sets the paths which are registered by [`Honeypot::register`].
+    ///
+    /// Default is the paths of the common vulnerability scans, such as
+    /// `/.env`, `/.git/*path`, `/wp-login.php` and `/phpmyadmin`.
+    #[must_use]
+    pub fn paths<I, T>(self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self {
+            paths: paths.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Sets the time for which the clients are blocked.
+    ///
+    /// Default is `24h`.
+    #[must_use]
+    pub fn ttl(self, ttl: Duration) -> Self {
+        Self { ttl, ..self }
+    }
+
+    /// Sets the function which returns the client IP address of a request.
+    ///
+    /// Default is the IP address of the remote address.
+    #[must_use]
+    pub fn ip(self, f: impl Fn(&Request) -> Option<IpAddr> + Send + Sync + 'static) -> Self {
+        Self {
+            ip: Arc::new(f),
+            ..self
+        }
+    }
+
+    /// Responds with the tarpit instead of `404 Not Found`.
+    #[must_use]
+    pub fn tarpit(self, tarpit: Tarpit) -> Self {
+        Self {
+            tarpit: Some(tarpit),
+            ..self
+        }
+    }
+
+    /// Adds the honeypot to the paths of the route.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the paths is already added to the route.
+    #[track_caller]
+    pub fn register(mut self, route: Route) -> Route {
+        let paths = std::mem::take(&mut self.paths);
+        let honeypot = Arc::new(self);
+        paths
+            .iter()
+            .fold(route, |route, path| route.at(path, honeypot.clone()))
+    }
+}
+
+impl Endpoint for Honeypot {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        if let Some(ip) = (self.ip)(&req) {
+            tracing::warn!(ip = %ip, path = req.uri().path(), "honeypot triggered");
+            self.blocklist
+                .block_at(ip, self.ttl, SharedClock::from_request(&req).now());
+        }
+
+        Ok(match &self.tarpit {
+            Some(tarpit) => tarpit.response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, test::TestClient};
+
+    #[handler(internal)]
+    fn index() -> &'static str {
+        "hello"
+    }
+
+    fn client_ip(req: &Request) -> Option<IpAddr> {
+        req.header("x-client-ip")?.parse().ok()
+    }
+
+    #[tokio::test]
+    async fn honeypot() {
+        let blocklist = Blocklist::new();
+        let app = Honeypot::new(blocklist.clone())
+            .path("/admin.php")
+            .ip(client_ip)
+            .register(Route::new().at("/", index));
+        let cli = TestClient::new(app);
+        let ip = "192.0.2.1".parse().unwrap();
+
+        cli.get("/")
+            .header("x-client-ip", "192.0.2.1")
+            .send()
+            .await
+            .assert_text("hello")
+            .await;
+        assert!(!blocklist.is_blocked(ip));
+
+        for path in ["/.git/config", "/wp-admin", "/admin.php"] {
+            blocklist.unblock(ip);
+            cli.get(path)
+                .header("x-client-ip", "192.0.2.1")
+                .send()
+                .await
+                .assert_status(StatusCode::NOT_FOUND);
+            assert!(blocklist.is_blocked(ip));
+        }
+    }
+
+    #[tokio::test]
+    async fn tarpit() {
+        let app = Honeypot::new(Blocklist::new())
+            .paths(["/.env"])
+            .tarpit(
+                Tarpit::new()
+                    .interval(Duration::from_millis(1))
+                    .duration(Duration::from_millis(2))
+                    .chunk("a"),
+            )
+            .register(Route::new());
+        let cli = TestClient::new(app);
+
+        cli.get("/.env").send().await.assert_text("aa").await;
+        cli.get("/wp-login.php")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,207 @@
+use std::{convert::Infallible, error::Error as StdError, sync::Arc};
+
+use bytes::Bytes;
+use futures_util::{future::BoxFuture, FutureExt};
+use http::uri::Scheme;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    body::BoxBody,
+    endpoint::{DynEndpoint, ToDynEndpoint},
+    web::{CancelToken, LocalAddr, RemoteAddr},
+    Addr, Endpoint, EndpointExt, Request, Response,
+};
+
+/// A [`hyper::service::Service`] adapter for an [`Endpoint`].
+///
+/// This is returned by
+/// [`into_hyper_service`](super::EndpointExt::into_hyper_service), and allows
+/// the endpoint to be served by an externally managed hyper 1.x connection.
+///
+/// # Example
+///
+/// ```no_run
+/// use hyper_util::rt::{TokioExecutor, TokioIo};
+/// use poem::{handler, EndpointExt, Route};
+/// use tokio::net::TcpListener as TokioTcpListener;
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let service = Route::new().at("/", index).into_hyper_service();
+/// let listener = TokioTcpListener::bind("127.0.0.1:3000").await.unwrap();
+///
+/// loop {
+///     let (stream, remote_addr) = listener.accept().await.unwrap();
+///     let service = service.clone().remote_addr(remote_addr);
+///     tokio::spawn(async move {
+///         let _ = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new())
+///             .serve_connection_with_upgrades(TokioIo::new(stream), service)
+///             .await;
+///     });
+/// }
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct HyperService {
+    ep: Arc<dyn DynEndpoint<Output = Response>>,
+    local_addr: LocalAddr,
+    remote_addr: RemoteAddr,
+    scheme: Scheme,
+}
+
+impl HyperService {
+    pub(crate) fn new<E>(ep: E) -> Self
+    where
+        E: Endpoint + 'static,
+    {
+        Self {
+            ep: Arc::new(ToDynEndpoint(ep.map_to_response())),
+            local_addr: LocalAddr::default(),
+            remote_addr: RemoteAddr::default(),
+            scheme: Scheme::HTTP,
+        }
+    }
+
+    /// Sets the local address which will be exposed to the endpoint as
+    /// [`LocalAddr`].
+    #[must_use]
+    pub fn local_addr(self, addr: impl Into<Addr>) -> Self {
+        Self {
+            local_addr: LocalAddr(addr.into()),
+            ..self
+        }
+    }
+
+    /// Sets the remote address which will be exposed to the endpoint as
+    /// [`RemoteAddr`].
+    #[must_use]
+    pub fn remote_addr(self, addr: impl Into<Addr>) -> Self {
+        Self {
+            remote_addr: RemoteAddr(addr.into()),
+            ..self
+        }
+    }
+
+    /// Sets the scheme of the connection, defaults to `http`.
+    #[must_use]
+    pub fn scheme(self, scheme: Scheme) -> Self {
+        Self { scheme, ..self }
+    }
+}
+
+impl<B> hyper::service::Service<http::Request<B>> for HyperService
+where
+    B: hyper::body::Body<Data = Bytes> + Send + Sync + 'static,
+    B::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    type Response = hyper::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn call(&self, req: http::Request<B>) -> Self::Future {
+        let ep = self.ep.clone();
+        let mut req: Request = (
+            req,
+            self.local_addr.clone(),
+            self.remote_addr.clone(),
+            self.scheme.clone(),
+        )
+            .into();
+        let cancel_token = CancellationToken::new();
+        req.extensions_mut()
+            .insert(CancelToken::new(cancel_token.clone()));
+
+        async move {
+            let guard = cancel_token.drop_guard();
+            let resp = ep.get_response(req).await;
+            guard.disarm();
+            Ok(resp.into())
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+    use http_body_util::{BodyExt, Full};
+    use hyper::service::Service;
+    use tokio::sync::Notify;
+
+    use crate::{
+        handler,
+        http::{Method, StatusCode},
+        web::{CancelToken, Data, RemoteAddr},
+        EndpointExt, Route,
+    };
+
+    #[tokio::test]
+    async fn test_hyper_service() {
+        #[handler(internal)]
+        fn index(remote_addr: &RemoteAddr, body: String) -> String {
+            format!("{}:{}", remote_addr, body)
+        }
+
+        let addr: SocketAddr = "127.0.0.1:8000".parse().unwrap();
+        let service = Route::new()
+            .at("/", index)
+            .into_hyper_service()
+            .remote_addr(addr);
+
+        let resp = service
+            .call(
+                http::Request::builder()
+                    .method(Method::POST)
+                    .uri("/")
+                    .body(Full::new(bytes::Bytes::from_static(b"abc")))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "socket://127.0.0.1:8000:abc");
+
+        let resp = service
+            .call(
+                http::Request::builder()
+                    .uri("/missing")
+                    .body(Full::new(bytes::Bytes::new()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn cancel_on_drop() {
+        #[handler(internal)]
+        async fn index(token: CancelToken, notify: Data<&Arc<Notify>>) {
+            let notify = notify.0.clone();
+            tokio::spawn(async move {
+                token.cancelled().await;
+                notify.notify_one();
+            });
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }
+
+        let notify = Arc::new(Notify::new());
+        let service = index.data(notify.clone()).into_hyper_service();
+        let fut = service.call(
+            http::Request::builder()
+                .uri("/")
+                .body(Full::new(bytes::Bytes::new()))
+                .unwrap(),
+        );
+        let _ = tokio::time::timeout(Duration::from_millis(10), fut).await;
+        tokio::time::timeout(Duration::from_secs(5), notify.notified())
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,41 @@
+use std::marker::PhantomData;
+
+use crate::{Endpoint, Error, Request, Result};
+
+/// Endpoint for the
+/// [`map_err_type`](super::EndpointExt::map_err_type) method.
+pub struct MapErrType<E, F, ErrType> {
+    inner: E,
+    f: F,
+    _mark: PhantomData<ErrType>,
+}
+
+impl<E, F, ErrType> MapErrType<E, F, ErrType> {
+    #[inline]
+    pub(crate) fn new(inner: E, f: F) -> MapErrType<E, F, ErrType> {
+        Self {
+            inner,
+            f,
+            _mark: PhantomData,
+        }
+    }
+}
+
+impl<E, F, ErrType> Endpoint for MapErrType<E, F, ErrType>
+where
+    E: Endpoint,
+    F: Fn(ErrType) -> Error + Send + Sync,
+    ErrType: std::error::Error + Send + Sync + 'static,
+{
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        match self.inner.call(req).await {
+            Ok(resp) => Ok(resp),
+            Err(err) => match err.downcast::<ErrType>() {
+                Ok(err) => Err((self.f)(err)),
+                Err(err) => Err(err),
+            },
+        }
+    }
+}
@@ -1,47 +1,72 @@
 //! Endpoint related types.
 
+mod admin;
 mod after;
 mod and_then;
 mod around;
 mod before;
 mod catch_all_error;
 mod catch_error;
+#[cfg(feature = "secure-headers")]
+mod csp_report;
 #[cfg(feature = "embed")]
 mod embed;
 #[allow(clippy::module_inception)]
 mod endpoint;
+#[cfg(feature = "ip-filter")]
+mod honeypot;
+mod hyper_service;
 mod inspect_all_err;
 mod inspect_err;
 mod map;
+mod map_err_type;
 mod map_to_response;
+#[cfg(feature = "pprof")]
+mod profiling;
 #[cfg(feature = "prometheus")]
 mod prometheus_exporter;
+#[cfg(feature = "soap")]
+mod soap;
 #[cfg(feature = "static-files")]
 mod static_files;
+mod tarpit;
 mod to_response;
 #[cfg(feature = "tower-compat")]
 mod tower_compat;
 
+pub use admin::AdminEndpoints;
 pub use after::After;
 pub use and_then::AndThen;
 pub use around::Around;
 pub use before::Before;
 pub use catch_all_error::CatchAllError;
 pub use catch_error::CatchError;
+#[cfg(feature = "secure-headers")]
+pub use csp_report::{CspReport, CspReportEndpoint};
 #[cfg(feature = "embed")]
 pub use embed::{EmbeddedFileEndpoint, EmbeddedFilesEndpoint};
+pub(crate) use endpoint::box_endpoint;
 pub use endpoint::{
     make, make_sync, BoxEndpoint, DynEndpoint, EitherEndpoint, Endpoint, EndpointExt, IntoEndpoint,
     ToDynEndpoint,
 };
+#[cfg(feature = "ip-filter")]
+pub use honeypot::Honeypot;
+pub use hyper_service::HyperService;
 pub use inspect_all_err::InspectAllError;
 pub use inspect_err::InspectError;
 pub use map::Map;
+pub use map_err_type::MapErrType;
 pub use map_to_response::MapToResponse;
+#[cfg(feature = "pprof")]
+pub use profiling::PprofEndpoints;
 #[cfg(feature = "prometheus")]
 pub use prometheus_exporter::PrometheusExporter;
+#[cfg(feature = "soap")]
+pub use soap::SoapEndpoint;
 #[cfg(feature = "static-files")]
 pub use static_files::{StaticFileEndpoint, StaticFilesEndpoint};
+pub use tarpit::Tarpit;
 pub use to_response::ToResponse;
 #[cfg(feature = "tower-compat")]
 pub use tower_compat::TowerCompatExt;
@@ -0,0 +1,281 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures_util::{future::BoxFuture, FutureExt};
+use pprof::{protos::Message, ProfilerGuardBuilder};
+use serde::Deserialize;
+
+use crate::{
+    error::{MethodNotAllowedError, NotFoundError, ParseQueryError, PprofError},
+    http::{header, Method, StatusCode},
+    Endpoint, Error, Request, Response, Result,
+};
+
+/// Only one CPU profiler can be running in the process.
+static PROFILING: AtomicBool = AtomicBool::new(false);
+
+/// Clears [`PROFILING`] when the profile is finished, or the request is
+/// cancelled.
+struct Running;
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        PROFILING.store(false, Ordering::Release);
+    }
+}
+
+type HeapFn = Arc<dyn Fn() -> BoxFuture<'static, Result<Vec<u8>>> + Send + Sync>;
+type GuardFn = Arc<dyn Fn(&Request) -> bool + Send + Sync>;
+
+#[derive(Deserialize)]
+struct ProfileParams {
+    seconds: Option<u64>,
+}
+
+/// Endpoints for capturing the profiles in the
+/// [pprof](https://github.com/google/pprof) protobuf format, which can be
+/// viewed with `go tool pprof`, or converted to the flamegraphs.
+///
+/// - `GET /profile?seconds=30` collects a CPU profile for the duration, only
+///   one CPU profile can be collected at the same time.
+/// - `GET /heap` returns a heap profile from the function registered with
+///   [`PprofEndpoints::heap`], for example from
+///   [`jemalloc_pprof`](https://crates.io/crates/jemalloc_pprof).
+///
+/// The profiles expose the internals of the service, so by default only the
+/// requests from the loopback addresses are allowed, use
+/// [`PprofEndpoints::guard`] to authorize the requests, the other requests are
+/// rejected with `403 Forbidden`.
+///
+/// # Errors
+///
+/// - [`PprofError`]
+/// - [`NotFoundError`]
+/// - [`MethodNotAllowedError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{endpoint::PprofEndpoints, Request, Route};
+///
+/// let app = Route::new().nest(
+///     "/debug/pprof",
+///     PprofEndpoints::new()
+///         .heap(|| async {
+///             // dump the heap profile of the allocator
+///             Ok(Vec::new())
+///         })
+///         .guard(|req: &Request| req.header("x-admin-token") == Some("secret")),
+/// );
+/// ```
+///
+/// ```shell
+/// go tool pprof -http=:8080 http://localhost:3000/debug/pprof/profile?seconds=10
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "pprof")))]
+pub struct PprofEndpoints {
+    frequency: i32,
+    max_duration: Duration,
+    heap: Option<HeapFn>,
+    guard: GuardFn,
+}
+
+impl Default for PprofEndpoints {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PprofEndpoints {
+    /// Create a `PprofEndpoints`.
+    pub fn new() -> Self {
+        Self {
+            frequency: 100,
+            max_duration: Duration::from_secs(60),
+            heap: None,
+            guard: Arc::new(|req: &Request| {
+                req.remote_addr()
+                    .as_socket_addr()
+                    .is_some_and(|addr| addr.ip().is_loopback())
+            }),
+        }
+    }
+
+    /// Sets the sampling frequency of the CPU profiles in Hz.
+    ///
+    /// Default is `100`.
+    #[must_use]
+    pub fn frequency(self, frequency: i32) -> Self {
+        Self { frequency, ..self }
+    }
+
+    /// Sets the maximum duration of the CPU profiles, the longer `seconds`
+    /// are truncated.
+    ///
+    /// Default is `60s`.
+    #[must_use]
+    pub fn max_duration(self, max_duration: Duration) -> Self {
+        Self {
+            max_duration,
+            ..self
+        }
+    }
+
+    /// Uses an async function to dump the heap profile in the pprof format.
+    #[must_use]
+    pub fn heap<F, Fut>(self, f: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Vec<u8>>> + Send + 'static,
+    {
+        Self {
+            heap: Some(Arc::new(move || f().boxed())),
+            ..self
+        }
+    }
+
+    /// Uses a closure to authorize the requests.
+    #[must_use]
+    pub fn guard<F>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            guard: Arc::new(f),
+            ..self
+        }
+    }
+
+    async fn cpu_profile(&self, duration: Duration) -> Result<Vec<u8>, PprofError> {
+        if PROFILING.swap(true, Ordering::AcqRel) {
+            return Err(PprofError::InProgress);
+        }
+        let _running = Running;
+
+        let guard = ProfilerGuardBuilder::default()
+            .frequency(self.frequency)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()
+            .map_err(|err| PprofError::Profile(err.to_string()))?;
+        tokio::time::sleep(duration).await;
+        let profile = guard
+            .report()
+            .build()
+            .and_then(|report| report.pprof())
+            .map_err(|err| PprofError::Profile(err.to_string()))?;
+        Ok(profile.encode_to_vec())
+    }
+}
+
+impl Endpoint for PprofEndpoints {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        if !(self.guard)(&req) {
+            return Err(Error::from_status(StatusCode::FORBIDDEN));
+        }
+        if req.method() != Method::GET {
+            return Err(MethodNotAllowedError.into());
+        }
+
+        let (data, filename) = match req.uri().path().trim_matches('/') {
+            "profile" => {
+                let params: ProfileParams =
+                    crate::urlencoded::from_str(req.uri().query().unwrap_or_default())
+                        .map_err(ParseQueryError)?;
+                let duration =
+                    Duration::from_secs(params.seconds.unwrap_or(30).max(1)).min(self.max_duration);
+                (self.cpu_profile(duration).await?, "profile")
+            }
+            "heap" => match &self.heap {
+                Some(heap) => (heap().await?, "heap"),
+                None => return Err(PprofError::HeapNotEnabled.into()),
+            },
+            _ => return Err(NotFoundError.into()),
+        };
+
+        Ok(Response::builder()
+            .content_type("application/octet-stream")
+            .header(
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            )
+            .body(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pprof::protos::Profile;
+
+    use super::*;
+    use crate::{test::TestClient, web::RemoteAddr, Addr};
+
+    fn pprof() -> PprofEndpoints {
+        PprofEndpoints::new().guard(|_| true)
+    }
+
+    #[tokio::test]
+    async fn cpu_profile() {
+        let cli = TestClient::new(pprof().max_duration(Duration::from_millis(200)));
+
+        let (resp, busy) = tokio::join!(cli.get("/profile").query("seconds", &30).send(), async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cli.get("/profile").send().await
+        });
+        busy.assert_status(StatusCode::CONFLICT);
+        resp.assert_status_is_ok();
+        resp.assert_content_type("application/octet-stream");
+        let data = resp.0.into_body().into_vec().await.unwrap();
+        let profile = Profile::decode(data.as_slice()).unwrap();
+        assert!(!profile.sample_type.is_empty());
+
+        cli.get("/profile").send().await.assert_status_is_ok();
+    }
+
+    #[tokio::test]
+    async fn heap_profile() {
+        let cli = TestClient::new(pprof());
+        cli.get("/heap")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_IMPLEMENTED);
+
+        let cli = TestClient::new(pprof().heap(|| async { Ok(vec![1, 2, 3]) }));
+        let resp = cli.get("/heap").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("content-disposition", "attachment; filename=\"heap\"");
+        resp.assert_bytes([1, 2, 3]).await;
+
+        cli.get("/missing")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+        cli.post("/heap")
+            .send()
+            .await
+            .assert_status(StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn guard() {
+        let ep = PprofEndpoints::new().heap(|| async { Ok(vec![1, 2, 3]) });
+        let resp = ep
+            .get_response(Request::builder().uri_str("/heap").finish())
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let mut req = Request::builder().uri_str("/heap").finish();
+        req.state_mut().remote_addr = RemoteAddr(Addr::from(
+            "127.0.0.1:1234".parse::<std::net::SocketAddr>().unwrap(),
+        ));
+        let resp = ep.get_response(req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
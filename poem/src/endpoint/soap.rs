@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use crate::{
+    endpoint::{box_endpoint, BoxEndpoint},
+    http::{Method, StatusCode},
+    web::{parse_envelope, request_version_and_action, SoapFault},
+    Body, Endpoint, IntoEndpoint, IntoResponse, Request, Response, Result,
+};
+
+/// An endpoint that dispatches the SOAP requests by the action.
+///
+/// The action of a request is the `SOAPAction` header in SOAP 1.1, or the
+/// `action` parameter of the content type in SOAP 1.2. If the request does
+/// not have an action, the local name of the first element in the body is
+/// used instead, so the operations can also be registered by the element
+/// names.
+///
+/// It responds with a `Client` fault if no endpoint matches the action.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     endpoint::SoapEndpoint,
+///     handler,
+///     http::{header, StatusCode},
+///     test::TestClient,
+///     web::Soap,
+///     Route,
+/// };
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize)]
+/// struct Echo {
+///     value: String,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct EchoResponse {
+///     value: String,
+/// }
+///
+/// #[handler]
+/// async fn echo(req: Soap<Echo>) -> Soap<EchoResponse> {
+///     req.reply(EchoResponse {
+///         value: req.value.clone(),
+///     })
+/// }
+///
+/// let app = Route::new().at("/soap", SoapEndpoint::new().action("urn:Echo", echo));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .post("/soap")
+///     .header(header::CONTENT_TYPE, "text/xml")
+///     .header("SOAPAction", "\"urn:Echo\"")
+///     .body(
+///         r#"<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+///             <soap:Body><Echo><value>hello</value></Echo></soap:Body>
+///         </soap:Envelope>"#,
+///     )
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "soap")))]
+#[derive(Default)]
+pub struct SoapEndpoint {
+    actions: HashMap<String, BoxEndpoint<'static>>,
+}
+
+impl SoapEndpoint {
+    /// Create a `SoapEndpoint`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds an endpoint for the action.
+    #[must_use]
+    pub fn action<E>(mut self, action: impl Into<String>, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.actions
+            .insert(action.into(), box_endpoint(ep.into_endpoint()));
+        self
+    }
+}
+
+impl Endpoint for SoapEndpoint {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if req.method() != Method::POST {
+            return Ok(StatusCode::METHOD_NOT_ALLOWED.into());
+        }
+
+        let (version, action) = request_version_and_action(&req)?;
+        let ep = match action {
+            Some(action) => self.actions.get(&action),
+            None => {
+                let data = req.take_body().into_string().await?;
+                let operation = parse_envelope(version, &data)?.operation;
+                req.set_body(Body::from_string(data));
+                operation.and_then(|operation| self.actions.get(&operation))
+            }
+        };
+
+        match ep {
+            Some(ep) => ep.call(req).await,
+            None => Ok(SoapFault::client("unknown SOAP action")
+                .with_version(version)
+                .into_response()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{handler, http::header, test::TestClient, web::Soap};
+
+    #[derive(Deserialize)]
+    struct Add {
+        a: i32,
+        b: i32,
+    }
+
+    #[derive(Serialize)]
+    struct AddResponse {
+        result: i32,
+    }
+
+    #[handler(internal)]
+    async fn add(req: Soap<Add>) -> Soap<AddResponse> {
+        req.reply(AddResponse {
+            result: req.a + req.b,
+        })
+    }
+
+    fn envelope(namespace: &str, body: &str) -> String {
+        format!(
+            r#"<?xml version="1.0"?>
+            <s:Envelope xmlns:s="{namespace}">
+                <s:Header><Token>abc</Token></s:Header>
+                <s:Body>{body}</s:Body>
+            </s:Envelope>"#
+        )
+    }
+
+    #[tokio::test]
+    async fn dispatch() {
+        let cli = TestClient::new(SoapEndpoint::new().action("urn:Add", add));
+        let v11 = "http://schemas.xmlsoap.org/soap/envelope/";
+        let v12 = "http://www.w3.org/2003/05/soap-envelope";
+
+        let resp = cli
+            .post("/")
+            .header(header::CONTENT_TYPE, "text/xml; charset=utf-8")
+            .header("SOAPAction", "\"urn:Add\"")
+            .body(envelope(
+                v11,
+                "<m:Add xmlns:m=\"urn:calc\"><a>1</a><b>2</b></m:Add>",
+            ))
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_content_type("text/xml; charset=utf-8");
+        resp.assert_text(format!(
+            r#"<?xml version="1.0" encoding="utf-8"?><soap:Envelope xmlns:soap="{v11}"><soap:Body><AddResponse><result>3</result></AddResponse></soap:Body></soap:Envelope>"#
+        ))
+        .await;
+
+        let resp = cli
+            .post("/")
+            .header(
+                header::CONTENT_TYPE,
+                "application/soap+xml; charset=utf-8; action=\"urn:Add\"",
+            )
+            .body(envelope(v12, "<Add><a>3</a><b>4</b></Add>"))
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_content_type("application/soap+xml; charset=utf-8");
+        resp.assert_text(format!(
+            r#"<?xml version="1.0" encoding="utf-8"?><soap:Envelope xmlns:soap="{v12}"><soap:Body><AddResponse><result>7</result></AddResponse></soap:Body></soap:Envelope>"#
+        ))
+        .await;
+
+        // without action
+        let endpoint = SoapEndpoint::new().action("Add", add);
+        let resp = TestClient::new(endpoint)
+            .post("/")
+            .header(header::CONTENT_TYPE, "text/xml")
+            .body(envelope(v11, "<Add><a>1</a><b>1</b></Add>"))
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+
+        let resp = cli
+            .post("/")
+            .header(
+                header::CONTENT_TYPE,
+                "application/soap+xml; action=\"urn:Sub\"",
+            )
+            .body(envelope(v12, "<Sub/>"))
+            .send()
+            .await;
+        resp.assert_status(StatusCode::BAD_REQUEST);
+        resp.assert_text(format!(
+            r#"<?xml version="1.0" encoding="utf-8"?><soap:Envelope xmlns:soap="{v12}"><soap:Body><soap:Fault><soap:Code><soap:Value>soap:Sender</soap:Value></soap:Code><soap:Reason><soap:Text xml:lang="en">unknown SOAP action</soap:Text></soap:Reason></soap:Fault></soap:Body></soap:Envelope>"#
+        ))
+        .await;
+
+        cli.post("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body("{}")
+            .send()
+            .await
+            .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn faults() {
+        let cli = TestClient::new(SoapEndpoint::new().action("urn:Add", add));
+        let v11 = "http://schemas.xmlsoap.org/soap/envelope/";
+
+        // version mismatch
+        let resp = cli
+            .post("/")
+            .header(header::CONTENT_TYPE, "text/xml")
+            .header("SOAPAction", "urn:Add")
+            .body(envelope(
+                "http://www.w3.org/2003/05/soap-envelope",
+                "<Add/>",
+            ))
+            .send()
+            .await;
+        resp.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        resp.assert_text(format!(
+            r#"<?xml version="1.0" encoding="utf-8"?><soap:Envelope xmlns:soap="{v11}"><soap:Body><soap:Fault><faultcode>soap:VersionMismatch</faultcode><faultstring>the envelope does not match the SOAP version of the content type</faultstring></soap:Fault></soap:Body></soap:Envelope>"#
+        ))
+        .await;
+
+        // invalid body
+        let resp = cli
+            .post("/")
+            .header(header::CONTENT_TYPE, "text/xml")
+            .header("SOAPAction", "urn:Add")
+            .body(envelope(v11, "<Add><a>x</a></Add>"))
+            .send()
+            .await;
+        resp.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        let text = resp.0.into_body().into_string().await.unwrap();
+        assert!(text.contains("<faultcode>soap:Client</faultcode>"));
+
+        // DTD
+        let resp = cli
+            .post("/")
+            .header(header::CONTENT_TYPE, "text/xml")
+            .header("SOAPAction", "urn:Add")
+            .body(format!(
+                "<!DOCTYPE foo [<!ENTITY x \"y\">]>{}",
+                envelope(v11, "<Add/>")
+            ))
+            .send()
+            .await;
+        resp.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn parse_envelope() {
+        let data = envelope(
+            "http://schemas.xmlsoap.org/soap/envelope/",
+            "<m:Add xmlns:m=\"urn:calc\"><a>1</a></m:Add>",
+        );
+        let envelope = super::parse_envelope(crate::web::SoapVersion::V11, &data).unwrap();
+        assert_eq!(envelope.header, Some("<Token>abc</Token>"));
+        assert_eq!(
+            envelope.body,
+            "<m:Add xmlns:m=\"urn:calc\"><a>1</a></m:Add>"
+        );
+        assert_eq!(envelope.operation.as_deref(), Some("Add"));
+    }
+}
@@ -175,8 +175,7 @@ impl Endpoint for StaticFilesEndpoint {
             .trim_start_matches('/')
             .trim_end_matches('/');
 
-        let path = percent_encoding::percent_decode_str(path)
-            .decode_utf8()
+        let path = crate::urlencoded::percent_decode_str(path)
             .map_err(|_| StaticFileError::InvalidPath)?;
 
         let mut file_path = self.path.clone();
@@ -0,0 +1,199 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bytes::Bytes;
+
+use crate::{
+    http::{header, StatusCode},
+    Body, Endpoint, IntoResponse, Request, Response, Result,
+};
+
+struct ActiveGuard(Arc<AtomicUsize>);
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// An endpoint which keeps the suspected scanners busy by sending the
+/// response body very slowly.
+///
+/// The response headers are sent immediately, then a chunk is sent every
+/// interval until the duration elapses or the client disconnects. When the
+/// maximum number of the active responses is reached, the requests respond
+/// `404 Not Found` immediately so the tarpit cannot exhaust the resources of
+/// the server.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{endpoint::Tarpit, Route};
+///
+/// let app = Route::new().at(
+///     "/wp-login.php",
+///     Tarpit::new()
+///         .interval(Duration::from_secs(10))
+///         .max_active(100),
+/// );
+/// ```
+pub struct Tarpit {
+    interval: Duration,
+    duration: Duration,
+    chunk: Bytes,
+    max_active: usize,
+    active: Arc<AtomicUsize>,
+}
+
+impl Default for Tarpit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tarpit {
+    /// Create a `Tarpit` endpoint.
+    pub fn new() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            duration: Duration::from_secs(10 * 60),
+            chunk: Bytes::from_static(b"\n"),
+            max_active: 1024,
+            active: Default::default(),
+        }
+    }
+
+    /// Sets the interval between the chunks.
+    ///
+    /// Default is `5s`.
+    #[must_use]
+    pub fn interval(self, interval: Duration) -> Self {
+        Self { interval, ..self }
+    }
+
+    /// Sets the time after which the response is finished.
+    ///
+    /// Default is `10m`.
+    #[must_use]
+    pub fn duration(self, duration: Duration) -> Self {
+        Self { duration, ..self }
+    }
+
+    /// Sets the chunk which is sent every interval.
+    ///
+    /// Default is a newline.
+    #[must_use]
+    pub fn chunk(self, chunk: impl Into<Bytes>) -> Self {
+        Self {
+            chunk: chunk.into(),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of the active responses.
+    ///
+    /// Default is `1024`.
+    #[must_use]
+    pub fn max_active(self, max_active: usize) -> Self {
+        Self { max_active, ..self }
+    }
+
+    /// Returns the number of the active responses.
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    fn acquire(&self) -> Option<ActiveGuard> {
+        self.active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+                (active < self.max_active).then_some(active + 1)
+            })
+            .ok()
+            .map(|_| ActiveGuard(self.active.clone()))
+    }
+
+    pub(crate) fn response(&self) -> Response {
+        let guard = match self.acquire() {
+            Some(guard) => guard,
+            None => return StatusCode::NOT_FOUND.into_response(),
+        };
+
+        let chunks = self.duration.as_nanos() / self.interval.as_nanos().max(1);
+        let interval = self.interval;
+        let chunk = self.chunk.clone();
+        let stream = futures_util::stream::unfold((guard, chunks), move |(guard, chunks)| {
+            let chunk = chunk.clone();
+            async move {
+                if chunks == 0 {
+                    return None;
+                }
+                tokio::time::sleep(interval).await;
+                Some((Ok::<_, std::io::Error>(chunk), (guard, chunks - 1)))
+            }
+        });
+
+        Response::builder()
+            .content_type("text/html; charset=utf-8")
+            .header(header::CACHE_CONTROL, "no-store")
+            .body(Body::from_bytes_stream(stream))
+    }
+}
+
+impl Endpoint for Tarpit {
+    type Output = Response;
+
+    async fn call(&self, _req: Request) -> Result<Self::Output> {
+        Ok(self.response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::test::TestClient;
+
+    #[tokio::test]
+    async fn tarpit() {
+        let tarpit = Tarpit::new()
+            .interval(Duration::from_millis(50))
+            .duration(Duration::from_millis(150))
+            .chunk("a")
+            .max_active(1);
+        let cli = TestClient::new(&tarpit);
+
+        let start = Instant::now();
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        assert_eq!(tarpit.active(), 1);
+
+        // the tarpit is full
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+
+        resp.assert_text("aaa").await;
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        assert_eq!(tarpit.active(), 0);
+    }
+
+    #[tokio::test]
+    async fn disconnect() {
+        let tarpit = Tarpit::new().interval(Duration::from_secs(60));
+        let cli = TestClient::new(&tarpit);
+
+        let resp = cli.get("/").send().await;
+        assert_eq!(tarpit.active(), 1);
+        drop(resp);
+        assert_eq!(tarpit.active(), 0);
+    }
+}
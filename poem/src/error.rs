@@ -5,10 +5,11 @@ use std::{
     error::Error as StdError,
     fmt::{self, Debug, Display, Formatter},
     string::FromUtf8Error,
+    time::Duration,
 };
 
 use headers::{ContentRange, HeaderMapExt};
-use http::{Extensions, Method};
+use http::{header, Extensions, HeaderValue, Method};
 
 use crate::{http::StatusCode, IntoResponse, Response};
 
@@ -654,8 +655,58 @@ define_simple_errors!(
 
     /// Error occurred in the router.
     (MethodNotAllowedError, METHOD_NOT_ALLOWED, "method not allowed");
+
+    /// Error occurred in the `Timeout` middleware.
+    (RequestTimeoutError, REQUEST_TIMEOUT, "request timeout");
 );
 
+/// The value of the `Allow` header in the response of
+/// [`MethodNotAllowedError`].
+#[derive(Clone)]
+struct AllowedMethods(HeaderValue);
+
+impl MethodNotAllowedError {
+    /// Converts to an [`Error`] whose response has the `Allow` header with the
+    /// allowed methods.
+    pub(crate) fn with_allow(self, allow: HeaderValue) -> Error {
+        let mut err = Error {
+            as_response: AsResponse::Fn(
+                |err| {
+                    let mut resp = MethodNotAllowedError.as_response();
+                    if let Some(AllowedMethods(allow)) = err.data::<AllowedMethods>() {
+                        resp.headers_mut().insert(header::ALLOW, allow.clone());
+                    }
+                    resp
+                },
+                |_| StatusCode::METHOD_NOT_ALLOWED,
+            ),
+            source: Some(ErrorSource::BoxedError(Box::new(self))),
+            extensions: Extensions::default(),
+            msg: None,
+        };
+        err.set_data(AllowedMethods(allow));
+        err
+    }
+}
+
+/// Error occurred in the `TlsInfo` extractor when the request was not
+/// received over TLS.
+#[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls")))
+)]
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+#[error("the connection is not tls")]
+pub struct NotTlsConnectionError;
+
+#[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
+impl ResponseError for NotTlsConnectionError {
+    fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
 /// A possible error value when reading the body.
 #[derive(Debug, thiserror::Error)]
 pub enum ReadBodyError {
@@ -718,6 +769,36 @@ impl ResponseError for ParseCookieError {
     }
 }
 
+/// A possible error value when a cookie violates the requirements of its
+/// prefix or attributes.
+#[cfg(feature = "cookie")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum InvalidCookieError {
+    /// The cookie with the `__Secure-` or `__Host-` prefix is not secure.
+    #[error("cookie with the `{0}` prefix must be secure")]
+    PrefixRequiresSecure(&'static str),
+
+    /// The cookie with the `__Host-` prefix has a domain.
+    #[error("cookie with the `__Host-` prefix must not have a domain")]
+    HostPrefixWithDomain,
+
+    /// The path of the cookie with the `__Host-` prefix is not `/`.
+    #[error("cookie with the `__Host-` prefix must have the path `/`")]
+    HostPrefixRequiresRootPath,
+
+    /// The partitioned cookie is not secure.
+    #[error("partitioned cookie must be secure")]
+    PartitionedRequiresSecure,
+}
+
+#[cfg(feature = "cookie")]
+impl ResponseError for InvalidCookieError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
 /// A possible error value when extracts data from request fails.
 #[derive(Debug, thiserror::Error, Eq, PartialEq)]
 #[error("data of type `{0}` was not found.")]
@@ -729,6 +810,25 @@ impl ResponseError for GetDataError {
     }
 }
 
+/// A possible error value when resolving a dependency fails.
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum ResolveDepError {
+    /// The dependency was not provided.
+    #[error("dependency of type `{0}` was not provided.")]
+    NotProvided(&'static str),
+
+    /// The dependency depends on itself, directly or through other
+    /// dependencies.
+    #[error("dependency of type `{0}` depends on itself.")]
+    Cycle(&'static str),
+}
+
+impl ResponseError for ResolveDepError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
 /// A possible error value when parsing form.
 #[derive(Debug, thiserror::Error)]
 pub enum ParseFormError {
@@ -815,6 +915,123 @@ impl ResponseError for ParseXmlError {
     }
 }
 
+/// A possible error value when parsing CSV.
+#[cfg(feature = "csv")]
+#[derive(Debug, thiserror::Error)]
+pub enum ParseCsvError {
+    /// Invalid content type.
+    #[error("invalid content type `{0}`, expect: `text/csv`")]
+    InvalidContentType(String),
+
+    /// `Content-Type` header is required.
+    #[error("expect content type `text/csv`")]
+    ContentTypeRequired,
+
+    /// Invalid record.
+    #[error("parse error at record {record}: {message}")]
+    Parse {
+        /// The number of the record, starting from 1 for the header.
+        record: usize,
+        /// The error message.
+        message: String,
+    },
+
+    /// Io error.
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(feature = "csv")]
+impl ResponseError for ParseCsvError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ParseCsvError::InvalidContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ParseCsvError::ContentTypeRequired => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ParseCsvError::Parse { .. } => StatusCode::BAD_REQUEST,
+            ParseCsvError::Io(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// A possible error value when parsing SOAP messages.
+#[cfg(feature = "soap")]
+#[derive(Debug, thiserror::Error)]
+pub enum ParseSoapError {
+    /// Invalid content type.
+    #[error("invalid content type `{0}`, expect: `text/xml` or `application/soap+xml`")]
+    InvalidContentType(String),
+
+    /// `Content-Type` header is required.
+    #[error("expect content type `text/xml` or `application/soap+xml`")]
+    ContentTypeRequired,
+
+    /// The namespace of the envelope does not match the SOAP version of the
+    /// content type.
+    #[error("the envelope does not match the SOAP version of the content type")]
+    VersionMismatch {
+        /// The SOAP version of the content type.
+        version: crate::web::SoapVersion,
+    },
+
+    /// Invalid envelope.
+    #[error("invalid envelope: {reason}")]
+    InvalidEnvelope {
+        /// The SOAP version of the content type.
+        version: crate::web::SoapVersion,
+        /// The reason of the error.
+        reason: String,
+    },
+
+    /// Failed to deserialize the content of the body.
+    #[error("parse error: {err}")]
+    Parse {
+        /// The SOAP version of the content type.
+        version: crate::web::SoapVersion,
+        /// The deserialize error.
+        err: quick_xml::de::DeError,
+    },
+}
+
+#[cfg(feature = "soap")]
+impl ParseSoapError {
+    fn fault(&self) -> Option<crate::web::SoapFault> {
+        use crate::web::{SoapFault, SoapFaultCode};
+
+        match self {
+            ParseSoapError::InvalidContentType(_) | ParseSoapError::ContentTypeRequired => None,
+            ParseSoapError::VersionMismatch { version } => Some(
+                SoapFault::new(SoapFaultCode::VersionMismatch, self.to_string())
+                    .with_version(*version),
+            ),
+            ParseSoapError::InvalidEnvelope { version, .. }
+            | ParseSoapError::Parse { version, .. } => {
+                Some(SoapFault::client(self.to_string()).with_version(*version))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "soap")]
+impl ResponseError for ParseSoapError {
+    fn status(&self) -> StatusCode {
+        match self.fault() {
+            Some(fault) => fault.status(),
+            None => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        }
+    }
+
+    fn as_response(&self) -> Response {
+        match self.fault() {
+            Some(fault) => fault.as_response(),
+            None => {
+                let mut resp = self.to_string().into_response();
+                resp.set_status(self.status());
+                resp
+            }
+        }
+    }
+}
+
 /// A possible error value when parsing YAML.
 #[cfg(feature = "yaml")]
 #[derive(Debug, thiserror::Error)]
@@ -942,6 +1159,11 @@ pub enum UpgradeError {
     #[error("no upgrade")]
     NoUpgrade,
 
+    /// The request does not contain the `Connection: upgrade` and `Upgrade`
+    /// headers
+    #[error("not an upgrade request")]
+    NotUpgradeRequest,
+
     /// Other error
     #[error("{0}")]
     Other(String),
@@ -951,6 +1173,7 @@ impl ResponseError for UpgradeError {
     fn status(&self) -> StatusCode {
         match self {
             UpgradeError::NoUpgrade => StatusCode::INTERNAL_SERVER_ERROR,
+            UpgradeError::NotUpgradeRequest => StatusCode::BAD_REQUEST,
             UpgradeError::Other(_) => StatusCode::BAD_REQUEST,
         }
     }
@@ -1015,6 +1238,17 @@ impl ResponseError for StaticFileError {
     }
 }
 
+/// A possible error value occurred in the `ExpectContinue` middleware.
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+#[error("unsupported expectation")]
+pub struct UnsupportedExpectationError;
+
+impl ResponseError for UnsupportedExpectationError {
+    fn status(&self) -> StatusCode {
+        StatusCode::EXPECTATION_FAILED
+    }
+}
+
 /// A possible error value occurred in the `SizeLimit` middleware.
 #[derive(Debug, thiserror::Error, Eq, PartialEq)]
 pub enum SizedLimitError {
@@ -1047,6 +1281,22 @@ pub enum RouteError {
     #[error("duplicate path: {0}")]
     Duplicate(String),
 
+    /// The path matches the same requests as a registered path
+    #[error("path `{path}` at {location} conflicts with `{existing}` at {existing_location}")]
+    Conflict {
+        /// Path
+        path: String,
+
+        /// Location where the path is registered
+        location: String,
+
+        /// Registered path
+        existing: String,
+
+        /// Location where the registered path is registered
+        existing_location: String,
+    },
+
     /// Invalid regex in path
     #[error("invalid regex in path: {path}")]
     InvalidRegex {
@@ -1078,6 +1328,10 @@ pub enum CorsError {
     /// Headers not allowed
     #[error("request-headers not allowed")]
     HeadersNotAllowed,
+
+    /// Private network access not allowed
+    #[error("private network access not allowed")]
+    PrivateNetworkNotAllowed,
 }
 
 impl ResponseError for CorsError {
@@ -1086,6 +1340,103 @@ impl ResponseError for CorsError {
     }
 }
 
+/// A possible error value occurred in the `ConcurrencyLimit` and
+/// `AdaptiveConcurrencyLimit` middleware.
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum ConcurrencyLimitError {
+    /// The queue is full
+    #[error("too many requests")]
+    QueueFull {
+        /// The time after which the client can retry
+        retry_after: Duration,
+    },
+
+    /// The request can not be started within the queue timeout
+    #[error("queue timeout")]
+    QueueTimeout {
+        /// The time after which the client can retry
+        retry_after: Duration,
+    },
+
+    /// The adaptive concurrency limit is reached
+    #[error("concurrency limit exceeded")]
+    LimitExceeded {
+        /// The time after which the client can retry
+        retry_after: Duration,
+    },
+}
+
+impl ResponseError for ConcurrencyLimitError {
+    fn status(&self) -> StatusCode {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+
+    fn as_response(&self) -> Response {
+        let (ConcurrencyLimitError::QueueFull { retry_after }
+        | ConcurrencyLimitError::QueueTimeout { retry_after }
+        | ConcurrencyLimitError::LimitExceeded { retry_after }) = self;
+        let mut resp = self.to_string().into_response();
+        resp.set_status(self.status());
+        resp.headers_mut().insert(
+            header::RETRY_AFTER,
+            HeaderValue::from(retry_after.as_secs_f64().ceil().max(1.0) as u64),
+        );
+        resp
+    }
+}
+
+/// An error returned by the `RateLimit` middleware when the client exceeds
+/// the limit.
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+#[error("rate limit exceeded")]
+pub struct RateLimitError {
+    /// The state of the limit.
+    pub state: crate::middleware::RateLimitState,
+}
+
+impl ResponseError for RateLimitError {
+    fn status(&self) -> StatusCode {
+        StatusCode::TOO_MANY_REQUESTS
+    }
+
+    fn as_response(&self) -> Response {
+        let mut resp = self.to_string().into_response();
+        resp.set_status(self.status());
+        self.state.insert_headers(resp.headers_mut());
+        if let Some(retry_after) = self.state.retry_after {
+            resp.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(crate::middleware::ceil_secs(retry_after).max(1)),
+            );
+        }
+        resp
+    }
+}
+
+/// A possible error value occurred in the `IpFilter` middleware.
+#[cfg(feature = "ip-filter")]
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+pub enum IpFilterError {
+    /// The IP address is not allowed
+    #[error("ip address is not allowed")]
+    IpDenied,
+
+    /// The country of the IP address is not allowed
+    #[error("country is not allowed")]
+    CountryDenied,
+
+    /// The IP address is in the blocklist
+    #[error("ip address is blocked")]
+    Blocked,
+}
+
+#[cfg(feature = "ip-filter")]
+impl ResponseError for IpFilterError {
+    fn status(&self) -> StatusCode {
+        StatusCode::FORBIDDEN
+    }
+}
+
 /// A possible error value occurred when loading i18n resources.
 #[cfg(feature = "i18n")]
 #[derive(Debug, thiserror::Error)]
@@ -1125,6 +1476,537 @@ impl ResponseError for I18NError {
     }
 }
 
+/// A possible error value occurred when loading the config.
+#[cfg(feature = "config")]
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    /// Io error
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The format of the config file is not supported.
+    #[error("unsupported config format: `{}`", .0.display())]
+    UnsupportedFormat(std::path::PathBuf),
+
+    /// Failed to parse the config file.
+    #[error("failed to parse config file `{}`: {reason}", path.display())]
+    Parse {
+        /// The path of the config file.
+        path: std::path::PathBuf,
+        /// The reason of the error.
+        reason: String,
+    },
+
+    /// Failed to deserialize the config.
+    #[error("failed to deserialize config: {0}")]
+    Deserialize(String),
+}
+
+#[cfg(feature = "config")]
+impl ResponseError for ConfigError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// A possible error value occurred when loading the feature flags.
+#[cfg(feature = "flags")]
+#[derive(Debug, thiserror::Error)]
+pub enum FlagsError {
+    /// Io error
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Failed to parse the feature flags.
+    #[error("failed to parse feature flags: {0}")]
+    Parse(String),
+}
+
+#[cfg(feature = "flags")]
+impl ResponseError for FlagsError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// A possible error value occurred when collecting a profile.
+#[cfg(feature = "pprof")]
+#[derive(Debug, thiserror::Error)]
+pub enum PprofError {
+    /// Another CPU profile is being collected.
+    #[error("a cpu profile is already being collected")]
+    InProgress,
+
+    /// The heap profile is not configured.
+    #[error("heap profile is not enabled")]
+    HeapNotEnabled,
+
+    /// Failed to collect the profile.
+    #[error("failed to collect profile: {0}")]
+    Profile(String),
+}
+
+#[cfg(feature = "pprof")]
+impl ResponseError for PprofError {
+    fn status(&self) -> StatusCode {
+        match self {
+            PprofError::InProgress => StatusCode::CONFLICT,
+            PprofError::HeapNotEnabled => StatusCode::NOT_IMPLEMENTED,
+            PprofError::Profile(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// A possible error value occurred when exporting the routes to static files.
+#[cfg(feature = "export")]
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    /// Io error
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The path can not be mapped to a file.
+    #[error("invalid export path: `{0}`")]
+    InvalidPath(String),
+
+    /// The endpoint returned an unsuccessful response.
+    #[error("failed to export `{path}`: {status}")]
+    Status {
+        /// The path of the request.
+        path: String,
+        /// The status of the response.
+        status: StatusCode,
+    },
+
+    /// Failed to read the response body.
+    #[error("read body: {0}")]
+    ReadBody(#[from] ReadBodyError),
+}
+
+#[cfg(feature = "export")]
+impl ResponseError for ExportError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// A possible error value occurred when parsing a cron expression.
+#[cfg(feature = "cron")]
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+#[error("invalid cron expression: {0}")]
+pub struct ParseCronError(pub String);
+
+/// A possible error value occurred when rendering a template.
+#[cfg(feature = "templates")]
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    /// Failed to serialize the template context.
+    #[error("failed to serialize template context: {0}")]
+    Context(#[from] serde_json::Error),
+
+    /// Failed to render the template.
+    #[error("failed to render template `{name}`: {source}")]
+    Render {
+        /// Template name
+        name: String,
+
+        /// The error of the template engine
+        source: Box<dyn StdError + Send + Sync>,
+    },
+
+    /// Failed to reload the templates.
+    #[error("failed to reload templates: {0}")]
+    Reload(Box<dyn StdError + Send + Sync>),
+}
+
+#[cfg(feature = "templates")]
+impl ResponseError for TemplateError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// A possible error value occurred in the `ApiKeyAuth` middleware.
+#[cfg(feature = "auth")]
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum ApiKeyError {
+    /// The API key is missing.
+    #[error("missing api key")]
+    Missing,
+
+    /// The API key is invalid or has been revoked.
+    #[error("invalid api key")]
+    Invalid,
+
+    /// The API key has expired.
+    #[error("api key expired")]
+    Expired,
+
+    /// The API key is not granted the required scope.
+    #[error("api key is not granted the scope `{scope}`")]
+    InsufficientScope {
+        /// The required scope.
+        scope: String,
+    },
+
+    /// The time to live of the issued API key is too large.
+    #[error("invalid time to live of api key")]
+    InvalidTtl,
+}
+
+#[cfg(feature = "auth")]
+impl ResponseError for ApiKeyError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiKeyError::InsufficientScope { .. } => StatusCode::FORBIDDEN,
+            ApiKeyError::InvalidTtl => StatusCode::BAD_REQUEST,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+/// A possible error value occurred in the `BasicAuth` and `DigestAuth`
+/// middlewares.
+#[cfg(feature = "auth")]
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum HttpAuthError {
+    /// The credentials are missing or invalid.
+    #[error("unauthorized")]
+    Unauthorized {
+        /// The value of the `WWW-Authenticate` header.
+        challenge: String,
+    },
+
+    /// The `Authorization` header is malformed.
+    #[error("malformed authorization header")]
+    BadRequest,
+}
+
+#[cfg(feature = "auth")]
+impl ResponseError for HttpAuthError {
+    fn status(&self) -> StatusCode {
+        match self {
+            HttpAuthError::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+            HttpAuthError::BadRequest => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn as_response(&self) -> Response {
+        let mut resp = self.to_string().into_response();
+        resp.set_status(self.status());
+        if let HttpAuthError::Unauthorized { challenge } = self {
+            if let Ok(value) = HeaderValue::from_str(challenge) {
+                resp.headers_mut().insert(header::WWW_AUTHENTICATE, value);
+            }
+        }
+        resp
+    }
+}
+
+/// A possible error value occurred when authorizing the requests.
+#[cfg(feature = "auth")]
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum AuthorizationError {
+    /// The request is not granted the permission.
+    #[error("permission `{permission}` is required")]
+    Forbidden {
+        /// The required permission.
+        permission: String,
+    },
+}
+
+#[cfg(feature = "auth")]
+impl ResponseError for AuthorizationError {
+    fn status(&self) -> StatusCode {
+        StatusCode::FORBIDDEN
+    }
+}
+
+/// A possible error value occurred when verifying the HTTP message signatures.
+#[cfg(feature = "http-signature")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http-signature")))]
+#[derive(Debug, thiserror::Error, Clone, Eq, PartialEq)]
+pub enum HttpSignatureError {
+    /// The `Signature` and `Signature-Input` headers are required.
+    #[error("missing http message signature")]
+    Missing,
+
+    /// The signature is invalid.
+    #[error("invalid http message signature")]
+    Invalid,
+
+    /// The signature does not cover a required component.
+    #[error("the signature does not cover the component `{0}`")]
+    MissingComponent(String),
+
+    /// The signature has expired, or is created in the future.
+    #[error("the signature has expired")]
+    Expired,
+
+    /// The key of the signature is not found.
+    #[error("unknown signature key")]
+    UnknownKey,
+
+    /// The `alg` parameter does not match the algorithm of the key.
+    #[error("the signature algorithm does not match the key")]
+    AlgorithmMismatch,
+
+    /// Failed to verify the signature with the key.
+    #[error("failed to verify the http message signature")]
+    Failed,
+}
+
+#[cfg(feature = "http-signature")]
+impl ResponseError for HttpSignatureError {
+    fn status(&self) -> StatusCode {
+        match self {
+            HttpSignatureError::Invalid => StatusCode::BAD_REQUEST,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+/// A possible error value occurred in the engine.io transport.
+///
+/// The response body is the JSON error of the engine.io protocol, such as
+/// `{"code":1,"message":"Session ID unknown"}`.
+#[cfg(feature = "engine-io")]
+#[cfg_attr(docsrs, doc(cfg(feature = "engine-io")))]
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+pub enum EngineIoError {
+    /// The transport is not `polling` or `websocket`.
+    #[error("Transport unknown")]
+    UnknownTransport,
+
+    /// The session does not exist or has been closed.
+    #[error("Session ID unknown")]
+    UnknownSession,
+
+    /// The handshake is not a `GET` request.
+    #[error("Bad handshake method")]
+    BadHandshakeMethod,
+
+    /// The request is invalid for the session.
+    #[error("Bad request")]
+    BadRequest,
+
+    /// The `EIO` query parameter is not `4`.
+    #[error("Unsupported protocol version")]
+    UnsupportedProtocolVersion,
+}
+
+#[cfg(feature = "engine-io")]
+impl EngineIoError {
+    fn code(&self) -> u8 {
+        match self {
+            EngineIoError::UnknownTransport => 0,
+            EngineIoError::UnknownSession => 1,
+            EngineIoError::BadHandshakeMethod => 2,
+            EngineIoError::BadRequest => 3,
+            EngineIoError::UnsupportedProtocolVersion => 5,
+        }
+    }
+}
+
+#[cfg(feature = "engine-io")]
+impl ResponseError for EngineIoError {
+    fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+
+    fn as_response(&self) -> Response {
+        let body = serde_json::json!({ "code": self.code(), "message": self.to_string() });
+        let mut resp = crate::web::Json(body).into_response();
+        resp.set_status(self.status());
+        resp
+    }
+}
+
+/// A possible error value occurred in the `Idempotency` middleware.
+#[cfg(feature = "idempotency")]
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+pub enum IdempotencyError {
+    /// The `Idempotency-Key` header is required.
+    #[error("missing idempotency key")]
+    MissingKey,
+
+    /// The request with the same key is being processed.
+    #[error("a request with the same idempotency key is being processed")]
+    Conflict,
+
+    /// The key has been used with a different request.
+    #[error("the idempotency key has been used with a different request")]
+    Mismatch,
+}
+
+#[cfg(feature = "idempotency")]
+impl ResponseError for IdempotencyError {
+    fn status(&self) -> StatusCode {
+        match self {
+            IdempotencyError::MissingKey => StatusCode::BAD_REQUEST,
+            IdempotencyError::Conflict => StatusCode::CONFLICT,
+            IdempotencyError::Mismatch => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+/// A possible error value occurred in the `ContentDigest` middleware.
+#[cfg(feature = "content-digest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "content-digest")))]
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+pub enum ContentDigestError {
+    /// The request body has no digest with an enabled algorithm.
+    #[error("missing content digest")]
+    Missing,
+
+    /// The `Content-Digest` or `Repr-Digest` header is invalid.
+    #[error("invalid content digest")]
+    Invalid,
+
+    /// The digest does not match the request body.
+    #[error("content digest mismatch")]
+    Mismatch,
+}
+
+#[cfg(feature = "content-digest")]
+impl ResponseError for ContentDigestError {
+    fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// A possible error value occurred when checking the preconditions of a
+/// conditional request.
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+pub enum PreconditionError {
+    /// The `If-Match` or `If-None-Match` precondition failed.
+    #[error("precondition failed")]
+    Failed,
+
+    /// The `If-Match` header is required.
+    #[error("precondition required")]
+    Required,
+}
+
+impl ResponseError for PreconditionError {
+    fn status(&self) -> StatusCode {
+        match self {
+            PreconditionError::Failed => StatusCode::PRECONDITION_FAILED,
+            PreconditionError::Required => StatusCode::PRECONDITION_REQUIRED,
+        }
+    }
+}
+
+/// A possible error value occurred when parsing or applying a JSON patch.
+#[derive(Debug, thiserror::Error, Clone, Eq, PartialEq)]
+pub enum JsonPatchError {
+    /// The patch document is malformed.
+    #[error("parse error: {0}")]
+    Parse(String),
+
+    /// The JSON pointer is invalid.
+    #[error("invalid JSON pointer `{0}`")]
+    InvalidPointer(String),
+
+    /// The target location does not exist.
+    #[error("path `{0}` does not exist")]
+    PathNotFound(String),
+
+    /// The `test` operation failed.
+    #[error("test operation failed at `{0}`")]
+    TestFailed(String),
+
+    /// The patched document is not a valid representation of the resource.
+    #[error("invalid document: {0}")]
+    InvalidDocument(String),
+}
+
+impl From<serde_json::Error> for JsonPatchError {
+    fn from(err: serde_json::Error) -> Self {
+        JsonPatchError::InvalidDocument(err.to_string())
+    }
+}
+
+impl ResponseError for JsonPatchError {
+    fn status(&self) -> StatusCode {
+        match self {
+            JsonPatchError::Parse(_) => StatusCode::BAD_REQUEST,
+            JsonPatchError::TestFailed(_) => StatusCode::CONFLICT,
+            JsonPatchError::InvalidPointer(_)
+            | JsonPatchError::PathNotFound(_)
+            | JsonPatchError::InvalidDocument(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+/// A possible error value occurred when parsing the pagination or sorting
+/// parameters.
+#[cfg(feature = "pagination")]
+#[derive(Debug, thiserror::Error, Clone, Eq, PartialEq)]
+pub enum PaginationError {
+    /// The `page` parameter is not a positive integer.
+    #[error("invalid page")]
+    InvalidPage,
+
+    /// The `size` parameter is not an integer between `1` and the maximum
+    /// page size.
+    #[error("invalid page size, expect an integer between 1 and {max}")]
+    InvalidSize {
+        /// The maximum page size.
+        max: u64,
+    },
+
+    /// The `cursor` parameter is malformed or its signature is invalid.
+    #[error("invalid cursor")]
+    InvalidCursor,
+
+    /// The field is not allowed to sort by.
+    #[error("invalid sort field `{0}`")]
+    InvalidSortField(String),
+}
+
+#[cfg(feature = "pagination")]
+impl ResponseError for PaginationError {
+    fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// A possible error value when a session was updated by another request
+/// after it was loaded.
+#[cfg(feature = "session")]
+#[cfg_attr(docsrs, doc(cfg(feature = "session")))]
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+#[error("the session was modified concurrently")]
+pub struct SessionConflictError;
+
+#[cfg(feature = "session")]
+impl ResponseError for SessionConflictError {
+    fn status(&self) -> StatusCode {
+        StatusCode::CONFLICT
+    }
+}
+
+/// A possible error value occurred when deal with the SQL session storages.
+#[cfg(any(feature = "postgres-session", feature = "sqlite-session"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "postgres-session", feature = "sqlite-session")))
+)]
+#[derive(Debug, thiserror::Error)]
+pub enum SqlSessionError {
+    /// Sqlx error.
+    #[error("sqlx: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+#[cfg(any(feature = "postgres-session", feature = "sqlite-session"))]
+impl ResponseError for SqlSessionError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
 /// A possible error value occurred when deal with redis session.
 #[cfg(feature = "redis-session")]
 #[derive(Debug, thiserror::Error)]
@@ -1141,6 +2023,22 @@ impl ResponseError for RedisSessionError {
     }
 }
 
+/// A possible error value occurred in the `RedisRateLimitStore`.
+#[cfg(feature = "redis-rate-limit")]
+#[derive(Debug, thiserror::Error)]
+pub enum RedisRateLimitError {
+    /// Redis error.
+    #[error("redis: {0}")]
+    Redis(redis::RedisError),
+}
+
+#[cfg(feature = "redis-rate-limit")]
+impl ResponseError for RedisRateLimitError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::{Error as IoError, ErrorKind};
@@ -0,0 +1,238 @@
+//! Export the responses of the routes to static files.
+//!
+//! [`Exporter`] calls the `GET` endpoints of a [`Route`] and writes the
+//! responses to a directory, so the pages can be deployed as a static site,
+//! or served by [`StaticFilesEndpoint`](crate::endpoint::StaticFilesEndpoint)
+//! along with the dynamic endpoints.
+//!
+//! The paths are obtained from [`Route::paths`], the paths with parameters,
+//! wildcards or regexs are skipped unless the concrete paths are added with
+//! [`Exporter::path`].
+//!
+//! The paths are mapped to the files as follows:
+//!
+//! | Path          | File                |
+//! |---------------|---------------------|
+//! | `/`           | `index.html`        |
+//! | `/about`      | `about/index.html`  |
+//! | `/docs/`      | `docs/index.html`   |
+//! | `/feed.xml`   | `feed.xml`          |
+//!
+//! # Example
+//!
+//! ```
+//! use poem::{export::Exporter, get, handler, web::Path, Route};
+//!
+//! #[handler]
+//! fn index() -> &'static str {
+//!     "home"
+//! }
+//!
+//! #[handler]
+//! fn post(Path(id): Path<u32>) -> String {
+//!     format!("post {id}")
+//! }
+//!
+//! let app = Route::new().at("/", get(index)).at("/posts/:id", get(post));
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! # let dir = std::env::temp_dir().join("poem-export-example");
+//! let files = Exporter::new()
+//!     .path("/posts/1")
+//!     .path("/posts/2")
+//!     .export(&app, &dir)
+//!     .await
+//!     .unwrap();
+//! assert_eq!(files.len(), 3);
+//! assert_eq!(
+//!     std::fs::read_to_string(dir.join("posts/1/index.html")).unwrap(),
+//!     "post 1"
+//! );
+//! # });
+//! ```
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::ExportError,
+    http::{Method, StatusCode, Uri},
+    Endpoint, Request, Route,
+};
+
+/// Exports the responses of the `GET` endpoints of a [`Route`] to a
+/// directory.
+///
+/// The paths responding with `404 Not Found` or `405 Method Not Allowed` are
+/// skipped, the other unsuccessful responses fail the export.
+///
+/// See the [module level documentation](self) for more details.
+#[derive(Debug, Default)]
+pub struct Exporter {
+    paths: Vec<String>,
+}
+
+impl Exporter {
+    /// Create a new `Exporter`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a path to export, in addition to the static paths of the route.
+    ///
+    /// This is used for the paths with parameters, for example `/posts/1` for
+    /// `/posts/:id`.
+    #[must_use]
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Exports the responses of the route to the directory, and returns the
+    /// paths of the written files.
+    pub async fn export(
+        &self,
+        route: &Route,
+        dir: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>, ExportError> {
+        let dir = dir.as_ref();
+        let mut exported = HashSet::new();
+        let mut files = Vec::new();
+
+        let paths = route
+            .paths()
+            .filter(|path| !path.contains([':', '*', '<']))
+            .chain(self.paths.iter().map(String::as_str));
+        for path in paths {
+            if !exported.insert(path) {
+                continue;
+            }
+
+            let (Ok(uri), Some(file)) = (path.parse::<Uri>(), file_path(path)) else {
+                return Err(ExportError::InvalidPath(path.to_string()));
+            };
+            let resp = route
+                .get_response(Request::builder().method(Method::GET).uri(uri).finish())
+                .await;
+            match resp.status() {
+                StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => continue,
+                status if !status.is_success() => {
+                    return Err(ExportError::Status {
+                        path: path.to_string(),
+                        status,
+                    })
+                }
+                _ => {}
+            }
+
+            let data = resp.into_body().into_bytes().await?;
+            let file = dir.join(file);
+            if let Some(parent) = file.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&file, data).await?;
+            files.push(file);
+        }
+
+        Ok(files)
+    }
+}
+
+/// Maps the request path to the relative path of the file, returns `None` if
+/// the path is not a plain path.
+fn file_path(path: &str) -> Option<PathBuf> {
+    let mut file = PathBuf::new();
+    let mut last = None;
+
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        if matches!(segment, "." | "..") || segment.contains(['\\', '?', '#']) {
+            return None;
+        }
+        file.push(segment);
+        last = Some(segment);
+    }
+
+    if path.ends_with('/') || last.map_or(true, |segment| !segment.contains('.')) {
+        file.push("index.html");
+    }
+    Some(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get, handler, post};
+
+    #[test]
+    fn map_file_path() {
+        assert_eq!(file_path("/").unwrap(), Path::new("index.html"));
+        assert_eq!(file_path("/about").unwrap(), Path::new("about/index.html"));
+        assert_eq!(file_path("/docs/").unwrap(), Path::new("docs/index.html"));
+        assert_eq!(file_path("/a/feed.xml").unwrap(), Path::new("a/feed.xml"));
+        assert!(file_path("/a/../b").is_none());
+        assert!(file_path("/a?b=1").is_none());
+    }
+
+    #[tokio::test]
+    async fn export() {
+        #[handler(internal)]
+        fn text(req: &Request) -> String {
+            req.uri().path().to_string()
+        }
+
+        #[handler(internal)]
+        fn error() -> StatusCode {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+
+        let app = Route::new()
+            .at("/", get(text))
+            .at("/feed.xml", text)
+            .at("/submit", post(text))
+            .at("/users/:id", get(text))
+            .nest(
+                "/docs",
+                Route::new().at("/", get(text)).at("/intro", get(text)),
+            );
+
+        let dir = std::env::temp_dir().join(format!("poem-export-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let files = Exporter::new()
+            .path("/users/1")
+            .path("/")
+            .export(&app, &dir)
+            .await
+            .unwrap();
+        assert_eq!(
+            files,
+            [
+                dir.join("index.html"),
+                dir.join("feed.xml"),
+                dir.join("docs/index.html"),
+                dir.join("docs/intro/index.html"),
+                dir.join("users/1/index.html"),
+            ]
+        );
+        assert_eq!(std::fs::read_to_string(&files[0]).unwrap(), "/");
+        assert_eq!(std::fs::read_to_string(&files[2]).unwrap(), "/");
+        assert_eq!(std::fs::read_to_string(&files[3]).unwrap(), "/intro");
+        assert_eq!(std::fs::read_to_string(&files[4]).unwrap(), "/users/1");
+        assert!(!dir.join("submit").exists());
+
+        let app = Route::new().at("/error", get(error));
+        assert!(matches!(
+            Exporter::new().export(&app, &dir).await,
+            Err(ExportError::Status { status, .. }) if status == StatusCode::INTERNAL_SERVER_ERROR
+        ));
+        assert!(matches!(
+            Exporter::new()
+                .path("/../a")
+                .export(&Route::new(), &dir)
+                .await,
+            Err(ExportError::InvalidPath(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
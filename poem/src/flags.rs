@@ -0,0 +1,548 @@
+//! Feature flags evaluated per request.
+//!
+//! A [`FeatureFlags`] provider decides if a flag is enabled for a
+//! [`FlagContext`], which describes the user of the request. The
+//! [`FlagsMiddleware`] builds the context of each request and inserts the
+//! [`Flags`], so the handlers can branch on the flags with the extractor.
+//!
+//! [`InMemoryFlags`] is a provider keeping the flags in memory, they can be
+//! loaded from TOML and changed at runtime:
+//!
+//! ```toml
+//! [new-checkout]
+//! enabled = true
+//! # always enabled for these users
+//! users = ["alice"]
+//! # enabled for 25% of the other users
+//! percentage = 25.0
+//!
+//! [new-checkout.attributes]
+//! # and only for the users in these countries
+//! country = ["US", "CA"]
+//! ```
+//!
+//! # Example
+//!
+//! ```
+//! use poem::{
+//!     flags::{FlagContext, Flags, FlagsMiddleware, InMemoryFlags},
+//!     get, handler,
+//!     test::TestClient,
+//!     EndpointExt, Request, Route,
+//! };
+//!
+//! #[handler]
+//! fn index(flags: &Flags) -> &'static str {
+//!     if flags.is_enabled("new-checkout") {
+//!         "new"
+//!     } else {
+//!         "old"
+//!     }
+//! }
+//!
+//! let flags = InMemoryFlags::from_toml(
+//!     r#"
+//! [new-checkout]
+//! users = ["alice"]
+//! percentage = 0
+//! "#,
+//! )
+//! .unwrap();
+//!
+//! let app = Route::new()
+//!     .at("/", get(index))
+//!     .with(FlagsMiddleware::new(flags).context(|req: &Request| {
+//!         FlagContext::new().user(req.header("x-user").unwrap_or_default())
+//!     }));
+//! let cli = TestClient::new(app);
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let resp = cli.get("/").header("x-user", "alice").send().await;
+//! resp.assert_text("new").await;
+//!
+//! let resp = cli.get("/").header("x-user", "bob").send().await;
+//! resp.assert_text("old").await;
+//! # });
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    path::Path,
+    sync::Arc,
+};
+
+use parking_lot::{Mutex, RwLock};
+use serde::Deserialize;
+
+use crate::{
+    error::FlagsError, hash::fnv1a, Endpoint, FromRequest, IntoResponse, Middleware, Request,
+    RequestBody, Response, Result,
+};
+
+/// Represents a provider of the feature flags.
+pub trait FeatureFlags: Send + Sync + 'static {
+    /// Returns `true` if the flag is enabled for the context.
+    ///
+    /// The unknown flags should be disabled.
+    fn is_enabled(&self, name: &str, ctx: &FlagContext) -> bool;
+}
+
+impl<T: FeatureFlags> FeatureFlags for Arc<T> {
+    fn is_enabled(&self, name: &str, ctx: &FlagContext) -> bool {
+        self.as_ref().is_enabled(name, ctx)
+    }
+}
+
+/// The context of the flags evaluation, which describes the user of the
+/// request.
+#[derive(Debug, Default, Clone)]
+pub struct FlagContext {
+    user: Option<String>,
+    attributes: HashMap<String, String>,
+}
+
+impl FlagContext {
+    /// Create an empty `FlagContext`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the key of the user, it is ignored if empty.
+    #[must_use]
+    pub fn user(self, key: impl Into<String>) -> Self {
+        Self {
+            user: Some(key.into()).filter(|key| !key.is_empty()),
+            ..self
+        }
+    }
+
+    /// Sets an attribute of the user.
+    #[must_use]
+    pub fn attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(name.into(), value.into());
+        self
+    }
+
+    /// Returns the key of the user.
+    pub fn user_key(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// Returns the value of the attribute.
+    pub fn get_attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+}
+
+/// The definition of a flag of [`InMemoryFlags`].
+///
+/// A flag is enabled for a context if:
+///
+/// - it is enabled, and
+/// - the user is in [`Flag::users`], or the attributes of the context match all
+///   [`Flag::attribute`] and the user is in the rollout [`Flag::percentage`].
+///
+/// The rollout is derived from the user key, so a user stays in the rollout
+/// when the percentage is increased, and the contexts without the user key
+/// are only enabled if the percentage is `100`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Flag {
+    #[serde(default = "enabled_default")]
+    enabled: bool,
+    #[serde(default)]
+    users: Vec<String>,
+    #[serde(default)]
+    attributes: HashMap<String, Vec<String>>,
+    #[serde(default = "percentage_default")]
+    percentage: f64,
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+fn percentage_default() -> f64 {
+    100.0
+}
+
+impl Flag {
+    /// Create a flag which is enabled for everyone.
+    pub fn on() -> Self {
+        Self {
+            enabled: true,
+            users: Vec::new(),
+            attributes: HashMap::new(),
+            percentage: 100.0,
+        }
+    }
+
+    /// Create a flag which is disabled for everyone.
+    pub fn off() -> Self {
+        Self {
+            enabled: false,
+            ..Self::on()
+        }
+    }
+
+    /// Enables the flag for the users regardless of the other rules.
+    #[must_use]
+    pub fn users<I, T>(mut self, users: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.users.extend(users.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only enables the flag if the attribute is one of the values.
+    #[must_use]
+    pub fn attribute<I, T>(mut self, name: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.attributes
+            .insert(name.into(), values.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets the percentage of the users to enable the flag for, from `0.0` to
+    /// `100.0`.
+    ///
+    /// Default is `100.0`.
+    #[must_use]
+    pub fn percentage(self, percentage: f64) -> Self {
+        Self {
+            percentage: percentage.clamp(0.0, 100.0),
+            ..self
+        }
+    }
+
+    fn evaluate(&self, name: &str, ctx: &FlagContext) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if let Some(user) = &ctx.user {
+            if self.users.contains(user) {
+                return true;
+            }
+        }
+
+        let matched = self.attributes.iter().all(|(attr, values)| {
+            ctx.get_attribute(attr)
+                .is_some_and(|value| values.iter().any(|v| v == value))
+        });
+        if !matched {
+            return false;
+        }
+
+        if self.percentage >= 100.0 {
+            return true;
+        }
+        match &ctx.user {
+            Some(user) => {
+                let bucket = fnv1a(format!("{name}:{user}").as_bytes()) % 10000;
+                (bucket as f64) < self.percentage * 100.0
+            }
+            None => false,
+        }
+    }
+}
+
+/// A [`FeatureFlags`] provider which keeps the flags in memory.
+///
+/// The flags can be changed at runtime with [`InMemoryFlags::set`], share it
+/// with an [`Arc`] to keep a handle.
+#[derive(Default)]
+pub struct InMemoryFlags {
+    flags: RwLock<HashMap<String, Flag>>,
+}
+
+impl Debug for InMemoryFlags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InMemoryFlags")
+            .field("flags", &*self.flags.read())
+            .finish()
+    }
+}
+
+impl InMemoryFlags {
+    /// Create an empty `InMemoryFlags`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Parses the flags from TOML, each table is a flag.
+    pub fn from_toml(data: &str) -> Result<Self, FlagsError> {
+        let flags: HashMap<String, Flag> =
+            toml::from_str(data).map_err(|err| FlagsError::Parse(err.message().to_string()))?;
+        Ok(Self {
+            flags: RwLock::new(flags),
+        })
+    }
+
+    /// Loads the flags from a TOML file.
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Result<Self, FlagsError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Adds a flag.
+    #[must_use]
+    pub fn flag(self, name: impl Into<String>, flag: Flag) -> Self {
+        self.set(name, flag);
+        self
+    }
+
+    /// Adds or replaces a flag at runtime.
+    pub fn set(&self, name: impl Into<String>, flag: Flag) {
+        self.flags.write().insert(name.into(), flag);
+    }
+
+    /// Removes a flag at runtime, and returns the removed flag.
+    pub fn remove(&self, name: &str) -> Option<Flag> {
+        self.flags.write().remove(name)
+    }
+}
+
+impl FeatureFlags for InMemoryFlags {
+    fn is_enabled(&self, name: &str, ctx: &FlagContext) -> bool {
+        self.flags
+            .read()
+            .get(name)
+            .is_some_and(|flag| flag.evaluate(name, ctx))
+    }
+}
+
+/// The feature flags of a request.
+///
+/// Each flag is evaluated once per request, so it does not change while the
+/// request is handled even if the provider is updated.
+///
+/// This type can be used as an extractor, and the [`FlagsMiddleware`] is
+/// required.
+#[derive(Clone)]
+pub struct Flags {
+    provider: Arc<dyn FeatureFlags>,
+    context: FlagContext,
+    cache: Arc<Mutex<HashMap<String, bool>>>,
+}
+
+impl Debug for Flags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Flags")
+            .field("context", &self.context)
+            .finish()
+    }
+}
+
+impl Flags {
+    /// Create a `Flags` evaluating the flags of the provider for the context.
+    pub fn new(provider: impl FeatureFlags, context: FlagContext) -> Self {
+        Self::with_provider(Arc::new(provider), context)
+    }
+
+    fn with_provider(provider: Arc<dyn FeatureFlags>, context: FlagContext) -> Self {
+        Self {
+            provider,
+            context,
+            cache: Default::default(),
+        }
+    }
+
+    /// Returns `true` if the flag is enabled.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        if let Some(enabled) = self.cache.lock().get(name) {
+            return *enabled;
+        }
+        let enabled = self.provider.is_enabled(name, &self.context);
+        self.cache.lock().insert(name.to_string(), enabled);
+        enabled
+    }
+
+    /// Returns the context of the request.
+    pub fn context(&self) -> &FlagContext {
+        &self.context
+    }
+}
+
+impl<'a> FromRequest<'a> for &'a Flags {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<Flags>()
+            .expect("To use the `Flags` extractor, the `FlagsMiddleware` is required."))
+    }
+}
+
+type ContextFn = Arc<dyn Fn(&Request) -> FlagContext + Send + Sync>;
+
+/// Middleware which inserts the [`Flags`] of each request.
+pub struct FlagsMiddleware {
+    provider: Arc<dyn FeatureFlags>,
+    context: Option<ContextFn>,
+}
+
+impl FlagsMiddleware {
+    /// Create a new `FlagsMiddleware` with the provider.
+    pub fn new(provider: impl FeatureFlags) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            context: None,
+        }
+    }
+
+    /// Uses a closure to build the context of the request, for example from
+    /// the user added by the authentication middleware.
+    ///
+    /// The context is empty by default.
+    #[must_use]
+    pub fn context<F>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> FlagContext + Send + Sync + 'static,
+    {
+        Self {
+            context: Some(Arc::new(f)),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for FlagsMiddleware {
+    type Output = FlagsEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        FlagsEndpoint {
+            inner: ep,
+            provider: self.provider.clone(),
+            context: self.context.clone(),
+        }
+    }
+}
+
+/// Endpoint for the `FlagsMiddleware`.
+pub struct FlagsEndpoint<E> {
+    inner: E,
+    provider: Arc<dyn FeatureFlags>,
+    context: Option<ContextFn>,
+}
+
+impl<E: Endpoint> Endpoint for FlagsEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let context = match &self.context {
+            Some(f) => f(&req),
+            None => FlagContext::new(),
+        };
+        req.extensions_mut()
+            .insert(Flags::with_provider(self.provider.clone(), context));
+        self.inner.call(req).await.map(IntoResponse::into_response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[test]
+    fn evaluate_flags() {
+        let flags = InMemoryFlags::new()
+            .flag("on", Flag::on())
+            .flag("off", Flag::off().users(["alice"]))
+            .flag("users", Flag::on().users(["alice"]).percentage(0.0))
+            .flag("country", Flag::on().attribute("country", ["US", "CA"]));
+        let alice = FlagContext::new().user("alice").attribute("country", "CA");
+        let bob = FlagContext::new().user("bob").attribute("country", "FR");
+
+        assert!(flags.is_enabled("on", &FlagContext::new()));
+        assert!(!flags.is_enabled("missing", &alice));
+        assert!(!flags.is_enabled("off", &alice));
+        assert!(flags.is_enabled("users", &alice));
+        assert!(!flags.is_enabled("users", &bob));
+        assert!(flags.is_enabled("country", &alice));
+        assert!(!flags.is_enabled("country", &bob));
+        assert!(!flags.is_enabled("country", &FlagContext::new()));
+
+        flags.set("off", Flag::on());
+        assert!(flags.is_enabled("off", &bob));
+        assert!(flags.remove("off").is_some());
+        assert!(!flags.is_enabled("off", &bob));
+    }
+
+    #[test]
+    fn percentage_rollout() {
+        fn enabled(percentage: f64) -> Vec<bool> {
+            let flags = InMemoryFlags::new().flag("a", Flag::on().percentage(percentage));
+            (0..200)
+                .map(|id| flags.is_enabled("a", &FlagContext::new().user(id.to_string())))
+                .collect()
+        }
+
+        let low = enabled(20.0);
+        let count = low.iter().filter(|enabled| **enabled).count();
+        assert!((10..70).contains(&count));
+        assert_eq!(low, enabled(20.0));
+        for (low, high) in low.iter().zip(enabled(60.0)) {
+            assert!(!low || high);
+        }
+        assert!(!InMemoryFlags::new()
+            .flag("a", Flag::on().percentage(99.0))
+            .is_enabled("a", &FlagContext::new()));
+    }
+
+    #[test]
+    fn parse_toml() {
+        let flags = InMemoryFlags::from_toml(
+            r#"
+            [a]
+            users = ["alice"]
+            percentage = 0
+
+            [a.attributes]
+            country = ["US"]
+
+            [b]
+            enabled = false
+            "#,
+        )
+        .unwrap();
+        assert!(flags.is_enabled("a", &FlagContext::new().user("alice")));
+        assert!(!flags.is_enabled("a", &FlagContext::new().attribute("country", "US")));
+        assert!(!flags.is_enabled("b", &FlagContext::new()));
+
+        assert!(matches!(
+            InMemoryFlags::from_toml("[a]\nenable = true"),
+            Err(FlagsError::Parse(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn middleware() {
+        #[handler(internal)]
+        fn index(flags: &Flags) -> String {
+            let first = flags.is_enabled("a");
+            format!("{} {}", first, flags.is_enabled("a"))
+        }
+
+        let provider =
+            Arc::new(InMemoryFlags::new().flag("a", Flag::on().users(["alice"]).percentage(0.0)));
+        let cli = TestClient::new(index.with(FlagsMiddleware::new(provider.clone()).context(
+            |req: &Request| FlagContext::new().user(req.header("x-user").unwrap_or_default()),
+        )));
+
+        cli.get("/")
+            .header("x-user", "alice")
+            .send()
+            .await
+            .assert_text("true true")
+            .await;
+        cli.get("/").send().await.assert_text("false false").await;
+
+        provider.set("a", Flag::on());
+        cli.get("/").send().await.assert_text("true true").await;
+    }
+}
@@ -0,0 +1,9 @@
+//! Non-cryptographic hashes.
+
+/// The 64-bit FNV-1a hash, which is stable across the processes and versions
+/// unlike the hasher of the standard library.
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -15,6 +15,9 @@ type LanguageArray = SmallVec<[LanguageIdentifier; 8]>;
 /// An extractor that parses the `Accept-Language` header and negotiates
 /// language bundles.
 ///
+/// If the [`I18N`](crate::i18n::I18N) middleware is used, the locale
+/// negotiated by the middleware is returned.
+///
 /// # Example
 ///
 /// ```
@@ -60,11 +63,42 @@ type LanguageArray = SmallVec<[LanguageIdentifier; 8]>;
 /// resp.assert_text("你好世界！").await;
 /// # });
 /// ```
+#[derive(Clone)]
 pub struct Locale {
     bundle: I18NBundle,
 }
 
 impl Locale {
+    pub(crate) fn negotiate(
+        req: &Request,
+        resources: &I18NResources,
+        preferred: Option<LanguageIdentifier>,
+    ) -> Self {
+        let mut languages = req
+            .headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(parse_accept_languages)
+            .unwrap_or_default();
+        if let Some(language) = preferred {
+            languages.insert(0, language);
+        }
+
+        Self {
+            bundle: resources.negotiate_languages(&languages),
+        }
+    }
+
+    /// Returns the preferred language, or `None` if no language matches.
+    pub fn language(&self) -> Option<&LanguageIdentifier> {
+        self.bundle.languages().next()
+    }
+
+    /// Returns the negotiated language bundle.
+    pub fn bundle(&self) -> &I18NBundle {
+        &self.bundle
+    }
+
     /// Gets the text with arguments.
     ///
     /// See also: [`I18NBundle::text_with_args`](I18NBundle::text_with_args)
@@ -86,21 +120,15 @@ impl Locale {
 
 impl<'a> FromRequest<'a> for Locale {
     async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        if let Some(locale) = req.extensions().get::<Locale>() {
+            return Ok(locale.clone());
+        }
+
         let resources = req
             .extensions()
             .get::<I18NResources>()
             .expect("To use the `Locale` extractor, the `I18NResources` data is required.");
-
-        let accept_languages = req
-            .headers()
-            .get(header::ACCEPT_LANGUAGE)
-            .and_then(|value| value.to_str().ok())
-            .map(parse_accept_languages)
-            .unwrap_or_default();
-
-        Ok(Self {
-            bundle: resources.negotiate_languages(&accept_languages),
-        })
+        Ok(Self::negotiate(req, resources, None))
     }
 }
 
@@ -0,0 +1,188 @@
+use std::{collections::HashMap, str::FromStr};
+
+use unic_langid::LanguageIdentifier;
+
+use crate::{
+    i18n::{I18NResources, Locale},
+    Endpoint, Middleware, Request, Result,
+};
+
+/// Middleware for negotiating the [`Locale`] of each request.
+///
+/// The languages are negotiated from the `Accept-Language` header, with the
+/// quality values respected. If a query parameter is specified with
+/// [`I18N::query_param`], its value takes precedence over the header.
+///
+/// The negotiated locale is shared with the [`Locale`] extractor and the other
+/// middlewares, so things like errors and templates can be localized
+/// consistently.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     http::header,
+///     i18n::{I18NResources, Locale, I18N},
+///     test::TestClient,
+///     EndpointExt,
+/// };
+///
+/// let resources = I18NResources::builder()
+///     .add_ftl("en-US", "hello-world = hello world!")
+///     .add_ftl("zh-CN", "hello-world = 你好世界！")
+///     .build()
+///     .unwrap();
+///
+/// #[handler]
+/// async fn index(locale: Locale) -> String {
+///     locale
+///         .text("hello-world")
+///         .unwrap_or_else(|_| "error".to_string())
+/// }
+///
+/// let app = index.with(I18N::new(resources).query_param("lang"));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .get("/")
+///     .header(header::ACCEPT_LANGUAGE, "zh-CN;q=0.8, en-US;q=0.5")
+///     .send()
+///     .await;
+/// resp.assert_text("你好世界！").await;
+///
+/// let resp = cli
+///     .get("/")
+///     .query("lang", &"en-US")
+///     .header(header::ACCEPT_LANGUAGE, "zh-CN")
+///     .send()
+///     .await;
+/// resp.assert_text("hello world!").await;
+/// # });
+/// ```
+pub struct I18N {
+    resources: I18NResources,
+    query_param: Option<String>,
+}
+
+impl I18N {
+    /// Create `I18N` middleware with the resources.
+    pub fn new(resources: I18NResources) -> Self {
+        Self {
+            resources,
+            query_param: None,
+        }
+    }
+
+    /// Sets the name of the query parameter used to override the language.
+    #[must_use]
+    pub fn query_param(self, name: impl Into<String>) -> Self {
+        Self {
+            query_param: Some(name.into()),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for I18N {
+    type Output = I18NEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        I18NEndpoint {
+            inner: ep,
+            resources: self.resources.clone(),
+            query_param: self.query_param.clone(),
+        }
+    }
+}
+
+/// Endpoint for the I18N middleware.
+pub struct I18NEndpoint<E> {
+    inner: E,
+    resources: I18NResources,
+    query_param: Option<String>,
+}
+
+impl<E: Endpoint> Endpoint for I18NEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let preferred = self.query_param.as_deref().and_then(|name| {
+            let mut params = crate::urlencoded::from_str::<HashMap<String, String>>(
+                req.uri().query().unwrap_or_default(),
+            )
+            .ok()?;
+            LanguageIdentifier::from_str(&params.remove(name)?).ok()
+        });
+        let locale = Locale::negotiate(&req, &self.resources, preferred);
+
+        req.extensions_mut().insert(self.resources.clone());
+        req.extensions_mut().insert(locale);
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header;
+    use unic_langid::langid;
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[tokio::test]
+    async fn negotiate() {
+        #[handler(internal)]
+        fn index(locale: Locale) -> String {
+            format!(
+                "{}:{}",
+                locale.language().unwrap(),
+                locale.text("hello").unwrap()
+            )
+        }
+
+        let resources = I18NResources::builder()
+            .add_ftl("en-US", "hello = hello\nbye = bye")
+            .add_ftl("fr", "hello = bonjour")
+            .default_language(langid!("en-US"))
+            .build()
+            .unwrap();
+        let cli = TestClient::new(index.with(I18N::new(resources.clone()).query_param("lang")));
+
+        cli.get("/")
+            .header(header::ACCEPT_LANGUAGE, "de;q=0.9, fr;q=0.5")
+            .send()
+            .await
+            .assert_text("fr:bonjour")
+            .await;
+
+        cli.get("/")
+            .header(header::ACCEPT_LANGUAGE, "de")
+            .send()
+            .await
+            .assert_text("en-US:hello")
+            .await;
+
+        cli.get("/")
+            .query("lang", &"fr")
+            .header(header::ACCEPT_LANGUAGE, "en-US")
+            .send()
+            .await
+            .assert_text("fr:bonjour")
+            .await;
+
+        let locale = Locale::negotiate(
+            &Request::builder()
+                .header(header::ACCEPT_LANGUAGE, "fr")
+                .finish(),
+            &resources,
+            None,
+        );
+        assert_eq!(
+            locale.bundle().languages().collect::<Vec<_>>(),
+            vec![&langid!("fr"), &langid!("en-US")]
+        );
+        assert_eq!(locale.text("bye").unwrap(), "bye");
+    }
+}
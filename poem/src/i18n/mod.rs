@@ -58,9 +58,14 @@
 //! # Use extractor
 //!
 //! See also: [`crate::i18n::Locale`]
+//!
+//! # Use middleware
+//!
+//! See also: [`crate::i18n::I18N`]
 
 mod args;
 mod locale;
+mod middleware;
 mod resources;
 
 pub use fluent_langneg::NegotiationStrategy;
@@ -69,5 +74,6 @@ pub use unic_langid;
 pub use self::{
     args::I18NArgs,
     locale::Locale,
+    middleware::{I18NEndpoint, I18N},
     resources::{I18NBundle, I18NResources, I18NResourcesBuilder},
 };
@@ -219,9 +219,15 @@ impl I18NResources {
 }
 
 /// A collection of localization messages.
+#[derive(Clone)]
 pub struct I18NBundle(SmallVec<[Arc<FluentBundle>; 8]>);
 
 impl I18NBundle {
+    /// Returns the negotiated languages, in the order of the fallback chain.
+    pub fn languages(&self) -> impl Iterator<Item = &LanguageIdentifier> {
+        self.0.iter().flat_map(|bundle| bundle.locales.first())
+    }
+
     fn message(&self, id: impl AsRef<str>) -> Result<(&FluentBundle, FluentMessage), I18NError> {
         let id = id.as_ref();
         for bundle in &self.0 {
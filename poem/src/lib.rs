@@ -231,20 +231,37 @@
 //! |Feature           |Description                     |
 //! |------------------|--------------------------------|
 //! | server | Server and listener APIs(enable by default) |
+//! |auth              | Support for API key, Basic and Digest authentication, and role based authorization |
+//! |bot-challenge     | Support for challenging the suspicious clients with `BotChallenge` |
 //! |compression  | Support decompress request body and compress response body |
+//! |content-digest    | Support for the `Content-Digest` and `Repr-Digest` headers |
+//! |config            | Support for typed application config with live reload |
+//! |cron              | Support for background tasks on cron schedules |
 //! |cookie            | Support for Cookie             |
 //! |csrf | Support for Cross-Site Request Forgery (CSRF) protection |
+//! |engine-io         | Support for the engine.io transport of the socket.io clients |
+//! |http-signature    | Support for the HTTP message signatures of RFC 9421 |
+//! |idempotency       | Support for the `Idempotency-Key` header |
+//! |ip-filter         | Support for the IP allow/deny lists with `IpFilter` |
+//! |geoip             | Support for the MaxMind GeoIP databases in `IpFilter` |
 //! |multipart         | Support for Multipart          |
 //! |native-tls        | Support for HTTP server over TLS with [`native-tls`](https://crates.io/crates/native-tls)  |
 //! |openssl-tls        | Support for HTTP server over TLS with [`openssl-tls`](https://crates.io/crates/openssl)  |
+//! |pagination        | Support for pagination and sorting extractors |
 //! |opentelemetry     | Support for opentelemetry    |
 //! |prometheus        | Support for Prometheus       |
 //! |redis-session     | Support for RedisSession     |
+//! |redis-rate-limit  | Support for RedisRateLimitStore |
+//! |postgres-session  | Support for PostgresStorage  |
+//! |sqlite-session    | Support for SqliteStorage    |
 //! |rustls            | Support for HTTP server over TLS with [`rustls`](https://crates.io/crates/rustls)  |
+//! |secure-headers    | Support for security related response headers and CSP nonces |
 //! |session           | Support for session    |
 //! |sse               | Support Server-Sent Events (SSE)       |
+//! |templates         | Support for server-side template rendering |
 //! |tempfile          | Support for [`tempfile`](https://crates.io/crates/tempfile) |
 //! |test              | Test utilities to test your endpoints. |
+//! |turnstile         | Support for the Cloudflare Turnstile challenge |
 //! |tower-compat      | Adapters for `tower::Layer` and `tower::Service`. |
 //! |websocket         | Support for WebSocket          |
 //! | anyhow        | Integrate with the [`anyhow`](https://crates.io/crates/anyhow) crate. |
@@ -255,7 +272,26 @@
 //! | tokio-metrics | Integrate with the [`tokio-metrics`](https://crates.io/crates/tokio-metrics) crate. |
 //! | embed  | Integrate with [`rust-embed`](https://crates.io/crates/rust-embed) crate. |
 //! | xml | Integrate with [`quick-xml`](https://crates.io/crates/quick-xml) crate. |
+//! | soap | Support for SOAP 1.1/1.2 endpoints |
+//! | fastcgi | Support for serving over the FastCGI protocol |
+//! | scgi | Support for serving over the SCGI protocol |
 //! | yaml | Integrate with [`serde-yaml`](https://crates.io/crates/serde-yaml) crate.                   |
+//! | archive | Support for streaming zip and tar archives |
+//! | csv | Support for streaming CSV responses and requests |
+//! | export | Support for exporting the routes to static files |
+//! | dev | Support for reloading the pages when the files are changed in development |
+//! | sentry | Integrate with the [`sentry`](https://crates.io/crates/sentry) crate. |
+//! | shadow | Support for mirroring the requests to a shadow upstream |
+//! | retry | Support for retrying and hedging the requests |
+//! | audit-log | Support for the `AuditLog` middleware |
+//! | route-split | Support for splitting the traffic between two endpoints with `Route::split` |
+//! | flags | Support for feature flags evaluated per request |
+//! | pprof | Support for the CPU and heap profiling endpoints |
+//! | capture | Support for capturing the requests to files and replaying them |
+//! | service-registry | Support for registering the server in Consul or etcd |
+//! | arena | Support for the request-scoped bump arenas with `Arena` |
+//! | resource-usage | Support for measuring the CPU time and the allocations of each request |
+//! | tokio-console | Name the spawned tasks for [`tokio-console`](https://github.com/tokio-rs/console), requires `--cfg tokio_unstable` |
 //! |sonic-rs          | Uses [`sonic-rs`](https://github.com/cloudwego/sonic-rs) instead of `serde_json`. Pls, checkout `sonic-rs` requirements to properly enable `sonic-rs` capabilities |
 
 #![doc(html_favicon_url = "https://raw.githubusercontent.com/poem-web/poem/master/favicon.ico")]
@@ -266,8 +302,22 @@
 #![warn(rustdoc::broken_intra_doc_links)]
 #![warn(missing_docs)]
 
+#[cfg(feature = "auth")]
+#[cfg_attr(docsrs, doc(cfg(feature = "auth")))]
+pub mod auth;
+pub mod clock;
+#[cfg(feature = "config")]
+#[cfg_attr(docsrs, doc(cfg(feature = "config")))]
+pub mod config;
+pub mod di;
 pub mod endpoint;
 pub mod error;
+#[cfg(feature = "export")]
+#[cfg_attr(docsrs, doc(cfg(feature = "export")))]
+pub mod export;
+#[cfg(feature = "flags")]
+#[cfg_attr(docsrs, doc(cfg(feature = "flags")))]
+pub mod flags;
 #[cfg(feature = "i18n")]
 #[cfg_attr(docsrs, doc(cfg(feature = "i18n")))]
 pub mod i18n;
@@ -278,6 +328,12 @@ pub mod middleware;
 #[cfg(feature = "session")]
 #[cfg_attr(docsrs, doc(cfg(feature = "session")))]
 pub mod session;
+#[cfg(feature = "server")]
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+pub mod task;
+#[cfg(feature = "templates")]
+#[cfg_attr(docsrs, doc(cfg(feature = "templates")))]
+pub mod templates;
 #[cfg(feature = "test")]
 #[cfg_attr(docsrs, doc(cfg(feature = "test")))]
 pub mod test;
@@ -288,11 +344,15 @@ pub use http;
 
 mod addr;
 mod body;
+mod buffer_pool;
+mod hash;
 mod request;
 mod response;
 mod route;
 #[cfg(feature = "server")]
 mod server;
+mod spawn;
+mod urlencoded;
 
 pub use addr::Addr;
 pub use body::Body;
@@ -306,6 +366,13 @@ pub use route::{
     connect, delete, get, head, options, patch, post, put, trace, PathPattern, Route, RouteDomain,
     RouteMethod, RouteScheme,
 };
+#[cfg(feature = "route-split")]
+pub use route::{RouteSplit, SplitVariant};
+#[cfg(feature = "service-registry")]
+pub use server::{ConsulRegistry, EtcdRegistry, ServiceRegistration, ServiceRegistry};
 #[cfg(feature = "server")]
-pub use server::Server;
+pub use server::{
+    HttpsRedirect, Readiness, RequestLimits, RequestRejections, Server, ServerConnections,
+    ServerInfo, ShardAcceptor, ShardStream, ShardedServer, ShutdownReport,
+};
 pub use web::{FromRequest, IntoResponse, RequestBody};
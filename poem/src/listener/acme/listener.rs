@@ -4,7 +4,7 @@ use std::{
     time::{Duration, UNIX_EPOCH},
 };
 
-use http::uri::Scheme;
+use http::{uri::Scheme, Extensions};
 use rcgen::{
     Certificate, CertificateParams, CustomExtension, DistinguishedName, PKCS_ECDSA_P256_SHA256,
 };
@@ -28,9 +28,10 @@ use crate::{
             resolver::{ResolveServerCert, ACME_TLS_ALPN_NAME},
             AutoCert, ChallengeType, Http01TokensMap,
         },
+        rustls::rustls_tls_info,
         Acceptor, HandshakeStream, Listener,
     },
-    web::{LocalAddr, RemoteAddr},
+    web::{with_tls_info, LocalAddr, RemoteAddr},
 };
 
 pub(crate) async fn auto_cert_acceptor<T: Listener>(
@@ -169,7 +170,7 @@ impl<T: Listener> Listener for AutoCertListener<T> {
         let domains = self.auto_cert.domains;
         let keys_for_http01 = self.auto_cert.keys_for_http01;
         let cache_path = self.auto_cert.cache_path;
-        tokio::spawn(async move {
+        crate::spawn::spawn("poem-acme-renew", async move {
             while let Some(cert_resolver) = Weak::upgrade(&weak_cert_resolver) {
                 if cert_resolver.is_expired() {
                     match issue_cert(
@@ -222,9 +223,22 @@ impl<T: Acceptor> Acceptor for AutoCertAcceptor<T> {
     }
 
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
-        let (stream, local_addr, remote_addr, _) = self.inner.accept().await?;
-        let stream = HandshakeStream::new(self.acceptor.accept(stream));
-        Ok((stream, local_addr, remote_addr, Scheme::HTTPS))
+        let (stream, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((stream, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
+        let (stream, local_addr, remote_addr, _, mut extensions) =
+            self.inner.accept_with_extensions().await?;
+        let fut = with_tls_info(
+            &mut extensions,
+            self.acceptor.accept(stream),
+            rustls_tls_info,
+        );
+        let stream = HandshakeStream::new(fut);
+        Ok((stream, local_addr, remote_addr, Scheme::HTTPS, extensions))
     }
 }
 
@@ -0,0 +1,241 @@
+//! The shared parts of the FastCGI and SCGI listeners.
+//!
+//! The requests received from the web server are forwarded to the poem
+//! server with an HTTP/1.1 client over an in-memory pipe, so that they are
+//! handled like the other connections.
+
+use std::io::{Error, ErrorKind, Result as IoResult};
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use http::{header, response::Parts, HeaderName, HeaderValue, Method, Request, Uri};
+use http_body_util::{combinators::UnsyncBoxBody, BodyExt, StreamBody};
+use hyper::{body::Frame, client::conn::http1::SendRequest};
+use hyper_util::rt::TokioIo;
+use tokio::{io::DuplexStream, sync::mpsc};
+
+/// The buffer size of the pipe between the adapter and the server.
+pub(crate) const PIPE_BUFFER_SIZE: usize = 64 * 1024;
+
+pub(crate) type ProxyBody = UnsyncBoxBody<Bytes, Error>;
+
+/// Connects to the server side of the pipe.
+pub(crate) async fn connect(io: DuplexStream) -> IoResult<SendRequest<ProxyBody>> {
+    let (sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(io))
+        .await
+        .map_err(Error::other)?;
+    crate::spawn::spawn("poem-cgi-proxy", async move {
+        let _ = conn.await;
+    });
+    Ok(sender)
+}
+
+/// Creates a request body from the stream.
+pub(crate) fn stream_body(
+    stream: impl Stream<Item = IoResult<Bytes>> + Send + 'static,
+) -> ProxyBody {
+    UnsyncBoxBody::new(StreamBody::new(stream.map(|res| res.map(Frame::data))))
+}
+
+/// Creates a request body which is fed by the returned sender.
+pub(crate) fn channel_body() -> (mpsc::Sender<IoResult<Bytes>>, ProxyBody) {
+    let (tx, rx) = mpsc::channel(16);
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    });
+    (tx, stream_body(stream))
+}
+
+fn param<'a>(params: &'a [(Vec<u8>, Vec<u8>)], name: &str) -> Option<&'a [u8]> {
+    params
+        .iter()
+        .find(|(key, _)| key == name.as_bytes())
+        .map(|(_, value)| value.as_slice())
+}
+
+/// Converts the CGI meta-variables to an HTTP request.
+pub(crate) fn build_request(
+    params: &[(Vec<u8>, Vec<u8>)],
+    body: ProxyBody,
+) -> IoResult<Request<ProxyBody>> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+
+    let method = Method::from_bytes(param(params, "REQUEST_METHOD").unwrap_or(b"GET"))
+        .map_err(|_| invalid("invalid REQUEST_METHOD"))?;
+    let uri = match param(params, "REQUEST_URI") {
+        Some(uri) => uri.to_vec(),
+        None => {
+            let mut uri = param(params, "SCRIPT_NAME").unwrap_or_default().to_vec();
+            uri.extend_from_slice(param(params, "PATH_INFO").unwrap_or_default());
+            if uri.is_empty() {
+                uri.push(b'/');
+            }
+            match param(params, "QUERY_STRING") {
+                Some(query) if !query.is_empty() => {
+                    uri.push(b'?');
+                    uri.extend_from_slice(query);
+                }
+                _ => {}
+            }
+            uri
+        }
+    };
+    let uri = Uri::try_from(uri).map_err(|_| invalid("invalid REQUEST_URI"))?;
+
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(body)
+        .map_err(Error::other)?;
+    let headers = request.headers_mut();
+
+    for (name, value) in params {
+        let name = match name.as_slice() {
+            b"CONTENT_TYPE" => header::CONTENT_TYPE,
+            b"CONTENT_LENGTH" if !value.is_empty() => header::CONTENT_LENGTH,
+            _ => match name.strip_prefix(b"HTTP_") {
+                Some(name) => {
+                    let name = name
+                        .iter()
+                        .map(|c| match c {
+                            b'_' => b'-',
+                            c => c.to_ascii_lowercase(),
+                        })
+                        .collect::<Vec<_>>();
+                    match HeaderName::from_bytes(&name) {
+                        Ok(name) => name,
+                        Err(_) => continue,
+                    }
+                }
+                None => continue,
+            },
+        };
+        if let Ok(value) = HeaderValue::from_bytes(value) {
+            headers.append(name, value);
+        }
+    }
+
+    // the hop-by-hop headers are meaningless on the pipe
+    headers.remove(header::CONNECTION);
+    headers.remove(header::TRANSFER_ENCODING);
+
+    if let Some(Ok(addr)) = param(params, "REMOTE_ADDR").map(HeaderValue::from_bytes) {
+        headers.insert("x-real-ip", addr);
+    }
+    if matches!(param(params, "HTTPS"), Some(value) if value.eq_ignore_ascii_case(b"on")) {
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+    }
+
+    Ok(request)
+}
+
+/// Encodes the head of the response in the CGI format.
+pub(crate) fn response_head(parts: &Parts) -> Vec<u8> {
+    let mut head = format!(
+        "Status: {} {}\r\n",
+        parts.status.as_str(),
+        parts.status.canonical_reason().unwrap_or_default()
+    )
+    .into_bytes();
+    for (name, value) in &parts.headers {
+        if matches!(
+            *name,
+            header::CONNECTION | header::TRANSFER_ENCODING | header::TE | header::TRAILER
+        ) || name == "keep-alive"
+        {
+            continue;
+        }
+        head.extend_from_slice(name.as_str().as_bytes());
+        head.extend_from_slice(b": ");
+        head.extend_from_slice(value.as_bytes());
+        head.extend_from_slice(b"\r\n");
+    }
+    head.extend_from_slice(b"\r\n");
+    head
+}
+
+/// Returns the response of the request, or a `502 Bad Gateway` response if
+/// the server failed to respond.
+pub(crate) async fn send_request(
+    sender: &mut SendRequest<ProxyBody>,
+    request: Request<ProxyBody>,
+) -> http::Response<ProxyBody> {
+    let res = match sender.ready().await {
+        Ok(()) => sender.send_request(request).await,
+        Err(err) => Err(err),
+    };
+    match res {
+        Ok(resp) => resp.map(|body| body.map_err(Error::other).boxed_unsync()),
+        Err(err) => {
+            tracing::debug!(error = %err, "failed to forward the request");
+            let mut resp = http::Response::new(stream_body(futures_util::stream::empty()));
+            *resp.status_mut() = http::StatusCode::BAD_GATEWAY;
+            resp
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(items: &[(&str, &str)]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        items
+            .iter()
+            .map(|(name, value)| (name.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn build_request() {
+        let request = super::build_request(
+            &params(&[
+                ("REQUEST_METHOD", "POST"),
+                ("SCRIPT_NAME", "/app"),
+                ("PATH_INFO", "/users"),
+                ("QUERY_STRING", "a=1"),
+                ("CONTENT_TYPE", "application/json"),
+                ("CONTENT_LENGTH", ""),
+                ("HTTP_X_CUSTOM_HEADER", "value"),
+                ("HTTP_HOST", "example.com"),
+                ("REMOTE_ADDR", "10.0.0.1"),
+                ("HTTPS", "on"),
+            ]),
+            stream_body(futures_util::stream::empty()),
+        )
+        .unwrap();
+
+        assert_eq!(request.method(), Method::POST);
+        assert_eq!(request.uri(), "/app/users?a=1");
+        let headers = request.headers();
+        assert_eq!(headers[header::CONTENT_TYPE], "application/json");
+        assert!(!headers.contains_key(header::CONTENT_LENGTH));
+        assert_eq!(headers["x-custom-header"], "value");
+        assert_eq!(headers[header::HOST], "example.com");
+        assert_eq!(headers["x-real-ip"], "10.0.0.1");
+        assert_eq!(headers["x-forwarded-proto"], "https");
+
+        let request = super::build_request(
+            &params(&[("REQUEST_URI", "/a/b?c=d"), ("PATH_INFO", "/ignored")]),
+            stream_body(futures_util::stream::empty()),
+        )
+        .unwrap();
+        assert_eq!(request.method(), Method::GET);
+        assert_eq!(request.uri(), "/a/b?c=d");
+    }
+
+    #[test]
+    fn response_head() {
+        let (parts, _) = http::Response::builder()
+            .status(404)
+            .header("content-type", "text/plain")
+            .header("transfer-encoding", "chunked")
+            .body(())
+            .unwrap()
+            .into_parts();
+        assert_eq!(
+            super::response_head(&parts),
+            b"Status: 404 Not Found\r\ncontent-type: text/plain\r\n\r\n"
+        );
+    }
+}
@@ -1,9 +1,10 @@
 use std::{
+    io::IoSlice,
     pin::Pin,
     task::{Context, Poll},
 };
 
-use http::uri::Scheme;
+use http::{uri::Scheme, Extensions};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult};
 
 use crate::{
@@ -47,14 +48,21 @@ impl<A: Acceptor, B: Acceptor> Acceptor for Combined<A, B> {
     }
 
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (stream, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((stream, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
         tokio::select! {
-            res = self.a.accept() => {
-                let (stream, local_addr, remote_addr, scheme) = res?;
-                Ok((CombinedStream::A(stream), local_addr, remote_addr, scheme))
+            res = self.a.accept_with_extensions() => {
+                let (stream, local_addr, remote_addr, scheme, extensions) = res?;
+                Ok((CombinedStream::A(stream), local_addr, remote_addr, scheme, extensions))
             }
-            res = self.b.accept() => {
-                let (stream, local_addr, remote_addr, scheme) = res?;
-                Ok((CombinedStream::B(stream), local_addr, remote_addr, scheme))
+            res = self.b.accept_with_extensions() => {
+                let (stream, local_addr, remote_addr, scheme, extensions) = res?;
+                Ok((CombinedStream::B(stream), local_addr, remote_addr, scheme, extensions))
             }
         }
     }
@@ -118,6 +126,25 @@ where
             CombinedStream::B(b) => Pin::new(b).poll_shutdown(cx),
         }
     }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<IoResult<usize>> {
+        let this = &mut *self;
+        match this {
+            CombinedStream::A(a) => Pin::new(a).poll_write_vectored(cx, bufs),
+            CombinedStream::B(b) => Pin::new(b).poll_write_vectored(cx, bufs),
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match self {
+            CombinedStream::A(a) => a.is_write_vectored(),
+            CombinedStream::B(b) => b.is_write_vectored(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,480 @@
+use bytes::Bytes;
+use http::{uri::Scheme, Extensions};
+use http_body_util::BodyExt;
+use tokio::io::{
+    AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, Result as IoResult,
+};
+
+use crate::{
+    listener::{
+        cgi::{self, PIPE_BUFFER_SIZE},
+        Acceptor, Listener,
+    },
+    web::{LocalAddr, RemoteAddr},
+};
+
+const VERSION_1: u8 = 1;
+
+const BEGIN_REQUEST: u8 = 1;
+const ABORT_REQUEST: u8 = 2;
+const END_REQUEST: u8 = 3;
+const PARAMS: u8 = 4;
+const STDIN: u8 = 5;
+const STDOUT: u8 = 6;
+const GET_VALUES: u8 = 9;
+const GET_VALUES_RESULT: u8 = 10;
+const UNKNOWN_TYPE: u8 = 11;
+
+const RESPONDER: u16 = 1;
+const KEEP_CONN: u8 = 1;
+
+const REQUEST_COMPLETE: u8 = 0;
+const CANT_MPX_CONN: u8 = 1;
+const UNKNOWN_ROLE: u8 = 3;
+
+const MAX_CONTENT_LENGTH: usize = 0xffff;
+
+/// A wrapper around an underlying listener which speaks the
+/// [FastCGI](https://fastcgi-archives.github.io/FastCGI_Specification.html)
+/// protocol.
+///
+/// This allows the application to run behind a web server such as nginx or
+/// Apache with `fastcgi_pass`/`mod_proxy_fcgi`. Only the `Responder` role is
+/// supported, and the requests on the same connection are handled one by one.
+///
+/// The `REMOTE_ADDR` and `HTTPS` variables are forwarded as the `x-real-ip`
+/// and `x-forwarded-proto` headers, so [`RealIp`](crate::web::RealIp) returns
+/// the address of the client.
+///
+/// # Example
+///
+/// ```
+/// use poem::listener::{FastCgiListener, Listener, TcpListener};
+///
+/// let listener = TcpListener::bind("127.0.0.1:9000").fastcgi();
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "fastcgi")))]
+pub struct FastCgiListener<T> {
+    inner: T,
+}
+
+impl<T> FastCgiListener<T> {
+    /// Create a `FastCgiListener`.
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T: Listener> Listener for FastCgiListener<T> {
+    type Acceptor = FastCgiAcceptor<T::Acceptor>;
+
+    async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
+        Ok(FastCgiAcceptor::new(self.inner.into_acceptor().await?))
+    }
+}
+
+/// A acceptor which speaks the FastCGI protocol.
+#[cfg_attr(docsrs, doc(cfg(feature = "fastcgi")))]
+pub struct FastCgiAcceptor<T> {
+    inner: T,
+}
+
+impl<T> FastCgiAcceptor<T> {
+    /// Create a `FastCgiAcceptor`.
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T: Acceptor> Acceptor for FastCgiAcceptor<T> {
+    type Io = DuplexStream;
+
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        self.inner.local_addr()
+    }
+
+    async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (io, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((io, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
+        let (io, local_addr, remote_addr, scheme, extensions) =
+            self.inner.accept_with_extensions().await?;
+        let (server_io, proxy_io) = tokio::io::duplex(PIPE_BUFFER_SIZE);
+        crate::spawn::spawn("poem-fastcgi-connection", async move {
+            if let Err(err) = serve(io, proxy_io).await {
+                tracing::debug!(error = %err, "fastcgi connection error");
+            }
+        });
+        Ok((server_io, local_addr, remote_addr, scheme, extensions))
+    }
+}
+
+struct Record {
+    ty: u8,
+    request_id: u16,
+    content: Vec<u8>,
+}
+
+async fn read_record<R: AsyncRead + Unpin>(reader: &mut R) -> IoResult<Record> {
+    let mut header = [0; 8];
+    reader.read_exact(&mut header).await?;
+    let content_length = u16::from_be_bytes([header[4], header[5]]) as usize;
+    let padding_length = header[6] as usize;
+    let mut content = vec![0; content_length + padding_length];
+    reader.read_exact(&mut content).await?;
+    content.truncate(content_length);
+    Ok(Record {
+        ty: header[1],
+        request_id: u16::from_be_bytes([header[2], header[3]]),
+        content,
+    })
+}
+
+async fn write_record<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    ty: u8,
+    request_id: u16,
+    content: &[u8],
+) -> IoResult<()> {
+    let [id_hi, id_lo] = request_id.to_be_bytes();
+    let [len_hi, len_lo] = (content.len() as u16).to_be_bytes();
+    writer
+        .write_all(&[VERSION_1, ty, id_hi, id_lo, len_hi, len_lo, 0, 0])
+        .await?;
+    writer.write_all(content).await
+}
+
+/// Writes the content in as many records as needed, or an empty record
+/// which terminates the stream if the content is empty.
+async fn write_records<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    ty: u8,
+    request_id: u16,
+    content: &[u8],
+) -> IoResult<()> {
+    if content.is_empty() {
+        return write_record(writer, ty, request_id, &[]).await;
+    }
+    for chunk in content.chunks(MAX_CONTENT_LENGTH) {
+        write_record(writer, ty, request_id, chunk).await?;
+    }
+    Ok(())
+}
+
+async fn end_request<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    request_id: u16,
+    protocol_status: u8,
+) -> IoResult<()> {
+    let content = [0, 0, 0, 0, protocol_status, 0, 0, 0];
+    write_records(writer, END_REQUEST, request_id, &content).await?;
+    writer.flush().await
+}
+
+fn read_length(data: &mut &[u8]) -> Option<usize> {
+    match *data {
+        [len, rest @ ..] if *len < 0x80 => {
+            *data = rest;
+            Some(*len as usize)
+        }
+        [a, b, c, d, rest @ ..] => {
+            *data = rest;
+            Some(u32::from_be_bytes([*a & 0x7f, *b, *c, *d]) as usize)
+        }
+        _ => None,
+    }
+}
+
+fn parse_pairs(mut data: &[u8]) -> IoResult<Vec<(Vec<u8>, Vec<u8>)>> {
+    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid fastcgi params");
+    let mut pairs = Vec::new();
+    while !data.is_empty() {
+        let name_length = read_length(&mut data).ok_or_else(invalid)?;
+        let value_length = read_length(&mut data).ok_or_else(invalid)?;
+        // the lengths are up to 31 bits, the sum can overflow on 32-bit targets
+        let length = name_length.checked_add(value_length).ok_or_else(invalid)?;
+        if data.len() < length {
+            return Err(invalid());
+        }
+        let (name, rest) = data.split_at(name_length);
+        let (value, rest) = rest.split_at(value_length);
+        pairs.push((name.to_vec(), value.to_vec()));
+        data = rest;
+    }
+    Ok(pairs)
+}
+
+fn encode_pair(buf: &mut Vec<u8>, name: &[u8], value: &[u8]) {
+    for len in [name.len(), value.len()] {
+        if len < 0x80 {
+            buf.push(len as u8);
+        } else {
+            buf.extend_from_slice(&(len as u32 | 0x8000_0000).to_be_bytes());
+        }
+    }
+    buf.extend_from_slice(name);
+    buf.extend_from_slice(value);
+}
+
+/// Handles the management records and the records of the other requests,
+/// which are not multiplexed.
+async fn handle_other_record<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    record: &Record,
+) -> IoResult<()> {
+    match record.ty {
+        GET_VALUES if record.request_id == 0 => {
+            let mut content = Vec::new();
+            for (name, _) in parse_pairs(&record.content)? {
+                match name.as_slice() {
+                    b"FCGI_MPXS_CONNS" => encode_pair(&mut content, &name, b"0"),
+                    b"FCGI_MAX_REQS" => encode_pair(&mut content, &name, b"1"),
+                    _ => {}
+                }
+            }
+            write_records(writer, GET_VALUES_RESULT, 0, &content).await?;
+            writer.flush().await
+        }
+        ty if record.request_id == 0 => {
+            write_records(writer, UNKNOWN_TYPE, 0, &[ty, 0, 0, 0, 0, 0, 0, 0]).await?;
+            writer.flush().await
+        }
+        BEGIN_REQUEST => end_request(writer, record.request_id, CANT_MPX_CONN).await,
+        _ => Ok(()),
+    }
+}
+
+async fn serve<IO>(io: IO, proxy_io: DuplexStream) -> IoResult<()>
+where
+    IO: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let mut sender = cgi::connect(proxy_io).await?;
+    let (mut reader, mut writer) = tokio::io::split(io);
+
+    loop {
+        let begin = match read_record(&mut reader).await {
+            Ok(record) if record.ty == BEGIN_REQUEST && record.request_id != 0 => record,
+            Ok(record) => {
+                handle_other_record(&mut writer, &record).await?;
+                continue;
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        let request_id = begin.request_id;
+        let (role, flags) = match begin.content.as_slice() {
+            [role_hi, role_lo, flags, ..] => (u16::from_be_bytes([*role_hi, *role_lo]), *flags),
+            _ => (0, 0),
+        };
+        let keep_conn = flags & KEEP_CONN != 0;
+
+        if role != RESPONDER {
+            end_request(&mut writer, request_id, UNKNOWN_ROLE).await?;
+        } else {
+            serve_request(&mut reader, &mut writer, &mut sender, request_id).await?;
+        }
+
+        if !keep_conn {
+            writer.shutdown().await?;
+            return Ok(());
+        }
+    }
+}
+
+async fn serve_request<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    sender: &mut hyper::client::conn::http1::SendRequest<cgi::ProxyBody>,
+    request_id: u16,
+) -> IoResult<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut params = Vec::new();
+    loop {
+        let record = read_record(reader).await?;
+        match record.ty {
+            _ if record.request_id != request_id => handle_other_record(writer, &record).await?,
+            PARAMS if record.content.is_empty() => break,
+            PARAMS => params.extend_from_slice(&record.content),
+            ABORT_REQUEST => return end_request(writer, request_id, REQUEST_COMPLETE).await,
+            _ => {}
+        }
+    }
+
+    let (tx, body) = cgi::channel_body();
+    let request = cgi::build_request(&parse_pairs(&params)?, body)?;
+
+    // forward the request body while waiting for the response
+    let forward_body = async {
+        loop {
+            let record = read_record(reader).await?;
+            match record.ty {
+                _ if record.request_id != request_id => {
+                    handle_other_record(writer, &record).await?
+                }
+                STDIN if record.content.is_empty() => break,
+                STDIN => {
+                    // the request body may not be read by the endpoint
+                    let _ = tx.send(Ok(Bytes::from(record.content))).await;
+                }
+                ABORT_REQUEST => {
+                    let _ = tx
+                        .send(Err(std::io::Error::new(
+                            std::io::ErrorKind::ConnectionAborted,
+                            "request aborted",
+                        )))
+                        .await;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        drop(tx);
+        Ok::<_, std::io::Error>(())
+    };
+    let (resp, res) = tokio::join!(cgi::send_request(sender, request), forward_body);
+    res?;
+
+    let (parts, mut body) = resp.into_parts();
+    write_records(writer, STDOUT, request_id, &cgi::response_head(&parts)).await?;
+    while let Some(frame) = body.frame().await {
+        match frame {
+            Ok(frame) => {
+                if let Some(data) = frame.data_ref().filter(|data| !data.is_empty()) {
+                    write_records(writer, STDOUT, request_id, data).await?;
+                }
+            }
+            Err(err) => {
+                tracing::debug!(error = %err, "failed to read the response body");
+                break;
+            }
+        }
+    }
+    write_records(writer, STDOUT, request_id, &[]).await?;
+    end_request(writer, request_id, REQUEST_COMPLETE).await
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::{handler, listener::TcpListener, web::RealIp, Server};
+
+    #[handler(internal)]
+    async fn index(RealIp(ip): RealIp, body: String, req: &crate::Request) -> String {
+        format!("{} {} {:?} {}", req.method(), req.uri(), ip, body)
+    }
+
+    fn encode_params(params: &[(&str, &str)]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for (name, value) in params {
+            encode_pair(&mut buf, name.as_bytes(), value.as_bytes());
+        }
+        buf
+    }
+
+    async fn write_request(stream: &mut TcpStream, request_id: u16, keep_conn: bool) {
+        let flags = if keep_conn { KEEP_CONN } else { 0 };
+        write_records(
+            stream,
+            BEGIN_REQUEST,
+            request_id,
+            &[0, 1, flags, 0, 0, 0, 0, 0],
+        )
+        .await
+        .unwrap();
+        let params = encode_params(&[
+            ("REQUEST_METHOD", "POST"),
+            ("REQUEST_URI", "/users?id=1"),
+            ("CONTENT_LENGTH", "5"),
+            ("REMOTE_ADDR", "10.0.0.1"),
+            ("HTTP_HOST", "example.com"),
+        ]);
+        write_records(stream, PARAMS, request_id, &params)
+            .await
+            .unwrap();
+        write_records(stream, PARAMS, request_id, &[])
+            .await
+            .unwrap();
+        write_records(stream, STDIN, request_id, b"hello")
+            .await
+            .unwrap();
+        write_records(stream, STDIN, request_id, &[]).await.unwrap();
+    }
+
+    async fn read_response(stream: &mut TcpStream, request_id: u16) -> String {
+        let mut stdout = Vec::new();
+        loop {
+            let record = read_record(stream).await.unwrap();
+            assert_eq!(record.request_id, request_id);
+            match record.ty {
+                STDOUT => stdout.extend_from_slice(&record.content),
+                END_REQUEST => {
+                    assert_eq!(record.content[4], REQUEST_COMPLETE);
+                    break;
+                }
+                ty => panic!("unexpected record type: {ty}"),
+            }
+        }
+        String::from_utf8(stdout).unwrap()
+    }
+
+    #[tokio::test]
+    async fn fastcgi() {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .fastcgi()
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(index));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+
+        // management record
+        let content = encode_params(&[("FCGI_MPXS_CONNS", "")]);
+        write_records(&mut stream, GET_VALUES, 0, &content)
+            .await
+            .unwrap();
+        let record = read_record(&mut stream).await.unwrap();
+        assert_eq!(record.ty, GET_VALUES_RESULT);
+        assert_eq!(
+            parse_pairs(&record.content).unwrap(),
+            vec![(b"FCGI_MPXS_CONNS".to_vec(), b"0".to_vec())]
+        );
+
+        for request_id in [1, 2] {
+            write_request(&mut stream, request_id, true).await;
+            let response = read_response(&mut stream, request_id).await;
+            assert!(response.starts_with("Status: 200 OK\r\n"));
+            assert!(response.ends_with("\r\n\r\nPOST /users?id=1 Some(10.0.0.1) hello"));
+        }
+
+        write_request(&mut stream, 3, false).await;
+        read_response(&mut stream, 3).await;
+        let mut buf = Vec::new();
+        assert_eq!(stream.read_to_end(&mut buf).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn pairs() {
+        let long_value = "x".repeat(200);
+        let data = encode_params(&[("A", "1"), ("LONG", &long_value)]);
+        assert_eq!(
+            parse_pairs(&data).unwrap(),
+            vec![
+                (b"A".to_vec(), b"1".to_vec()),
+                (b"LONG".to_vec(), long_value.into_bytes())
+            ]
+        );
+        assert!(parse_pairs(&[5, 1, b'a']).is_err());
+        assert!(parse_pairs(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, b'a']).is_err());
+    }
+}
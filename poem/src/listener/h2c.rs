@@ -0,0 +1,627 @@
+use std::{
+    io::{Error, ErrorKind, IoSlice},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use http::{uri::Scheme, Extensions, Uri};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf, Result as IoResult};
+
+use crate::{
+    listener::{Acceptor, HandshakeStream, Listener},
+    web::{LocalAddr, RemoteAddr},
+};
+
+/// The connection preface of HTTP/2.
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// The maximum size of the head of the request to upgrade.
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// The default `SETTINGS_MAX_FRAME_SIZE` of the server.
+const MAX_FRAME_SIZE: usize = 16384;
+
+const FRAME_HEADERS: u8 = 0x1;
+const FRAME_SETTINGS: u8 = 0x4;
+const FRAME_CONTINUATION: u8 = 0x9;
+
+const FLAG_END_STREAM: u8 = 0x1;
+const FLAG_END_HEADERS: u8 = 0x4;
+
+const SWITCHING_PROTOCOLS: &[u8] =
+    b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: h2c\r\n\r\n";
+
+#[derive(Debug, Copy, Clone)]
+struct Options {
+    prior_knowledge: bool,
+    upgrade: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            prior_knowledge: true,
+            upgrade: true,
+        }
+    }
+}
+
+/// A wrapper around an underlying listener which accepts HTTP/2 over
+/// cleartext TCP (h2c).
+///
+/// Two ways of starting an h2c connection are supported:
+///
+/// - With prior knowledge, the client sends the HTTP/2 connection preface
+///   directly, which is what the gRPC clients do.
+/// - With the `Upgrade: h2c` header of an HTTP/1.1 request, the server responds
+///   with `101 Switching Protocols` and serves that request, and the subsequent
+///   ones, over HTTP/2. Only the first request of a connection can be upgraded,
+///   and the requests with a body are served over HTTP/1.1.
+///
+/// The connections that use neither are served over HTTP/1.1 as usual.
+///
+/// Note that the server also detects the connection preface on the listeners
+/// that are not wrapped. Use `prior_knowledge(false)` to refuse these
+/// connections on a listener.
+///
+/// # Example
+///
+/// ```
+/// use poem::listener::{H2cListener, Listener, TcpListener};
+///
+/// let listener = TcpListener::bind("127.0.0.1:3000").h2c();
+/// let upgrade_only = TcpListener::bind("127.0.0.1:3001")
+///     .h2c()
+///     .prior_knowledge(false);
+/// ```
+pub struct H2cListener<T> {
+    inner: T,
+    options: Options,
+}
+
+impl<T> H2cListener<T> {
+    /// Create a `H2cListener`.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            options: Options::default(),
+        }
+    }
+
+    /// Specify whether to accept the HTTP/2 connections with prior knowledge.
+    ///
+    /// If disabled, the connections starting with the HTTP/2 connection
+    /// preface are closed.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn prior_knowledge(self, enable: bool) -> Self {
+        Self {
+            options: Options {
+                prior_knowledge: enable,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Specify whether to upgrade the connections with the `Upgrade: h2c`
+    /// header.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn upgrade(self, enable: bool) -> Self {
+        Self {
+            options: Options {
+                upgrade: enable,
+                ..self.options
+            },
+            ..self
+        }
+    }
+}
+
+impl<T: Listener> Listener for H2cListener<T> {
+    type Acceptor = H2cAcceptor<T::Acceptor>;
+
+    async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
+        Ok(H2cAcceptor {
+            inner: self.inner.into_acceptor().await?,
+            options: self.options,
+        })
+    }
+}
+
+/// A acceptor which accepts HTTP/2 over cleartext TCP.
+///
+/// See also [`H2cListener`].
+pub struct H2cAcceptor<T> {
+    inner: T,
+    options: Options,
+}
+
+impl<T> H2cAcceptor<T> {
+    /// Create a `H2cAcceptor`.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            options: Options::default(),
+        }
+    }
+
+    /// Specify whether to accept the HTTP/2 connections with prior knowledge.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn prior_knowledge(self, enable: bool) -> Self {
+        Self {
+            options: Options {
+                prior_knowledge: enable,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Specify whether to upgrade the connections with the `Upgrade: h2c`
+    /// header.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn upgrade(self, enable: bool) -> Self {
+        Self {
+            options: Options {
+                upgrade: enable,
+                ..self.options
+            },
+            ..self
+        }
+    }
+}
+
+impl<T: Acceptor> Acceptor for H2cAcceptor<T> {
+    type Io = HandshakeStream<H2cStream<T::Io>>;
+
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        self.inner.local_addr()
+    }
+
+    async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (io, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((io, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
+        let (io, local_addr, remote_addr, scheme, extensions) =
+            self.inner.accept_with_extensions().await?;
+        let stream = HandshakeStream::new(handshake(io, self.options));
+        Ok((stream, local_addr, remote_addr, scheme, extensions))
+    }
+}
+
+/// A stream which returns the bytes read during the handshake before the
+/// bytes of the underlying stream.
+pub struct H2cStream<T> {
+    inner: T,
+    prefix: Bytes,
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for H2cStream<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        if !self.prefix.is_empty() {
+            let n = self.prefix.len().min(buf.remaining());
+            buf.put_slice(&self.prefix[..n]);
+            self.prefix.advance(n);
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for H2cStream<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+async fn fill<T: AsyncRead + Unpin>(io: &mut T, buf: &mut BytesMut) -> IoResult<bool> {
+    buf.reserve(4096);
+    Ok(io.read_buf(buf).await? > 0)
+}
+
+async fn handshake<T>(mut io: T, options: Options) -> IoResult<H2cStream<T>>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut buf = BytesMut::new();
+
+    // wait until the connection preface can be recognized
+    while buf.len() < PREFACE.len() && PREFACE.starts_with(&buf[..]) {
+        if !fill(&mut io, &mut buf).await? {
+            break;
+        }
+    }
+
+    if buf.starts_with(PREFACE) {
+        if !options.prior_knowledge {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "h2c with prior knowledge is disabled",
+            ));
+        }
+    } else if options.upgrade {
+        let head_len = loop {
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break Some(pos + 4);
+            }
+            if buf.len() > MAX_HEAD_SIZE || !fill(&mut io, &mut buf).await? {
+                break None;
+            }
+        };
+
+        if let Some(block) = head_len.and_then(|len| upgrade_request(&buf[..len])) {
+            io.write_all(SWITCHING_PROTOCOLS).await?;
+            io.flush().await?;
+
+            // the client sends the connection preface and a SETTINGS frame after it
+            // receives the 101 response, and the upgraded request becomes the stream 1
+            let mut rest = buf.split_off(head_len.unwrap_or_default());
+            let settings_len = loop {
+                if rest.len() >= PREFACE.len() + 9 {
+                    if !rest.starts_with(PREFACE) || rest[PREFACE.len() + 3] != FRAME_SETTINGS {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            "invalid h2c connection preface",
+                        ));
+                    }
+                    let header = &rest[PREFACE.len()..];
+                    let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+                    if rest.len() >= PREFACE.len() + 9 + len {
+                        break PREFACE.len() + 9 + len;
+                    }
+                }
+                if !fill(&mut io, &mut rest).await? {
+                    return Err(ErrorKind::UnexpectedEof.into());
+                }
+            };
+
+            let mut prefix = BytesMut::from(&rest[..settings_len]);
+            encode_headers_frames(&mut prefix, 1, &block, true);
+            prefix.extend_from_slice(&rest[settings_len..]);
+            return Ok(H2cStream {
+                inner: io,
+                prefix: prefix.freeze(),
+            });
+        }
+    }
+
+    Ok(H2cStream {
+        inner: io,
+        prefix: buf.freeze(),
+    })
+}
+
+fn trim(value: &[u8]) -> &[u8] {
+    let start = value
+        .iter()
+        .position(|c| !c.is_ascii_whitespace())
+        .unwrap_or(value.len());
+    let end = value
+        .iter()
+        .rposition(|c| !c.is_ascii_whitespace())
+        .map_or(start, |n| n + 1);
+    &value[start..end]
+}
+
+fn has_token(value: &[u8], token: &[u8]) -> bool {
+    value
+        .split(|c| *c == b',')
+        .any(|item| trim(item).eq_ignore_ascii_case(token))
+}
+
+/// Returns the HPACK encoded header block of the request if it asks to
+/// upgrade to h2c.
+fn upgrade_request(head: &[u8]) -> Option<Vec<u8>> {
+    let mut lines = head
+        .split(|c| *c == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line));
+    let mut request_line = lines.next()?.split(|c| *c == b' ');
+    let method = request_line.next()?;
+    let target = request_line.next()?;
+    if request_line.next()? != b"HTTP/1.1" || method == b"CONNECT" {
+        return None;
+    }
+
+    let mut headers = Vec::new();
+    for line in lines.take_while(|line| !line.is_empty()) {
+        let colon = line.iter().position(|c| *c == b':')?;
+        if line[0] == b' ' || line[0] == b'\t' {
+            return None;
+        }
+        headers.push((line[..colon].to_ascii_lowercase(), trim(&line[colon + 1..])));
+    }
+    let header = |name: &'static [u8]| {
+        headers
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .map(|(_, value)| *value)
+    };
+
+    // RFC 7540 section 3.2
+    let connection = header(b"connection").collect::<Vec<_>>();
+    let upgrade = header(b"upgrade").any(|value| has_token(value, b"h2c"));
+    let connection_upgrade = connection.iter().any(|value| has_token(value, b"upgrade"))
+        && connection
+            .iter()
+            .any(|value| has_token(value, b"http2-settings"));
+    if !upgrade || !connection_upgrade || header(b"http2-settings").count() != 1 {
+        return None;
+    }
+    let has_body = header(b"transfer-encoding").next().is_some()
+        || header(b"content-length").any(|value| value != b"0");
+    if has_body {
+        return None;
+    }
+
+    let (authority, path) = if target.starts_with(b"/") || target == b"*" {
+        (header(b"host").next()?.to_vec(), target.to_vec())
+    } else {
+        let uri = Uri::try_from(target).ok()?;
+        (
+            uri.authority()?.as_str().as_bytes().to_vec(),
+            uri.path_and_query()
+                .map_or("/", |path| path.as_str())
+                .as_bytes()
+                .to_vec(),
+        )
+    };
+
+    let mut block = Vec::new();
+    encode_header(&mut block, b":method", method);
+    encode_header(&mut block, b":scheme", b"http");
+    encode_header(&mut block, b":authority", &authority);
+    encode_header(&mut block, b":path", &path);
+    for (name, value) in &headers {
+        // the connection-specific headers are not allowed in HTTP/2
+        let connection_specific = matches!(
+            name.as_slice(),
+            b"connection"
+                | b"keep-alive"
+                | b"proxy-connection"
+                | b"transfer-encoding"
+                | b"upgrade"
+                | b"http2-settings"
+                | b"host"
+        ) || (name == b"te" && *value != b"trailers")
+            || connection.iter().any(|value| has_token(value, name));
+        if !connection_specific {
+            encode_header(&mut block, name, value);
+        }
+    }
+    Some(block)
+}
+
+fn encode_integer(buf: &mut Vec<u8>, mut value: usize) {
+    // a string length with the 7-bit prefix, without the Huffman encoding
+    if value < 0x7f {
+        buf.push(value as u8);
+        return;
+    }
+    buf.push(0x7f);
+    value -= 0x7f;
+    while value >= 0x80 {
+        buf.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Encodes a literal header field without indexing.
+fn encode_header(buf: &mut Vec<u8>, name: &[u8], value: &[u8]) {
+    buf.push(0);
+    encode_integer(buf, name.len());
+    buf.extend_from_slice(name);
+    encode_integer(buf, value.len());
+    buf.extend_from_slice(value);
+}
+
+fn encode_frame_header(buf: &mut BytesMut, len: usize, ty: u8, flags: u8, stream_id: u32) {
+    buf.extend_from_slice(&(len as u32).to_be_bytes()[1..]);
+    buf.extend_from_slice(&[ty, flags]);
+    buf.extend_from_slice(&stream_id.to_be_bytes());
+}
+
+/// Encodes a HEADERS frame, followed by the CONTINUATION frames if the header
+/// block does not fit in a frame.
+fn encode_headers_frames(buf: &mut BytesMut, stream_id: u32, block: &[u8], end_stream: bool) {
+    let mut chunks = block.chunks(MAX_FRAME_SIZE).peekable();
+    let mut ty = FRAME_HEADERS;
+    let mut flags = if end_stream { FLAG_END_STREAM } else { 0 };
+    loop {
+        let chunk = chunks.next().unwrap_or_default();
+        if chunks.peek().is_none() {
+            flags |= FLAG_END_HEADERS;
+        }
+        encode_frame_header(buf, chunk.len(), ty, flags, stream_id);
+        buf.extend_from_slice(chunk);
+        if flags & FLAG_END_HEADERS != 0 {
+            break;
+        }
+        ty = FRAME_CONTINUATION;
+        flags = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::{handler, http::HeaderMap, listener::TcpListener, Request, Server};
+
+    const FRAME_DATA: u8 = 0x0;
+
+    #[handler(internal)]
+    async fn index(req: &Request, headers: &HeaderMap) -> String {
+        format!(
+            "{:?} {} {} {:?}",
+            req.version(),
+            req.method(),
+            req.uri(),
+            headers.get("x-custom")
+        )
+    }
+
+    async fn start(listener: H2cListener<TcpListener<&'static str>>) -> std::net::SocketAddr {
+        let acceptor = listener.into_acceptor().await.unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(index));
+        addr
+    }
+
+    async fn write_preface(stream: &mut TcpStream) {
+        let mut buf = BytesMut::from(PREFACE);
+        encode_frame_header(&mut buf, 0, FRAME_SETTINGS, 0, 0);
+        stream.write_all(&buf).await.unwrap();
+    }
+
+    /// Reads the frames until the end of the stream 1, and returns the body.
+    async fn read_body(stream: &mut TcpStream) -> String {
+        let mut body = Vec::new();
+        loop {
+            let mut header = [0; 9];
+            stream.read_exact(&mut header).await.unwrap();
+            let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+            let mut payload = vec![0; len];
+            stream.read_exact(&mut payload).await.unwrap();
+            let stream_id = u32::from_be_bytes([header[5], header[6], header[7], header[8]]);
+            if stream_id == 1 && header[3] == FRAME_DATA {
+                body.extend_from_slice(&payload);
+                if header[4] & FLAG_END_STREAM != 0 {
+                    return String::from_utf8(body).unwrap();
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn prior_knowledge() {
+        let addr = start(TcpListener::bind("127.0.0.1:0").h2c()).await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        write_preface(&mut stream).await;
+
+        let mut block = Vec::new();
+        encode_header(&mut block, b":method", b"GET");
+        encode_header(&mut block, b":scheme", b"http");
+        encode_header(&mut block, b":authority", b"example.com");
+        encode_header(&mut block, b":path", b"/a?b=1");
+        encode_header(&mut block, b"x-custom", b"1");
+        let mut buf = BytesMut::new();
+        encode_headers_frames(&mut buf, 1, &block, true);
+        stream.write_all(&buf).await.unwrap();
+
+        assert_eq!(
+            read_body(&mut stream).await,
+            "HTTP/2.0 GET http://example.com/a?b=1 Some(\"1\")"
+        );
+
+        // disabled
+        let addr = start(
+            TcpListener::bind("127.0.0.1:0")
+                .h2c()
+                .prior_knowledge(false),
+        )
+        .await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        write_preface(&mut stream).await;
+        let mut buf = Vec::new();
+        assert!(matches!(stream.read_to_end(&mut buf).await, Ok(0) | Err(_)));
+    }
+
+    #[tokio::test]
+    async fn upgrade() {
+        let addr = start(TcpListener::bind("127.0.0.1:0").h2c()).await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"GET /a?b=1 HTTP/1.1\r\nHost: example.com\r\nConnection: Upgrade, HTTP2-Settings\r\nUpgrade: h2c\r\nHTTP2-Settings: AAMAAABkAAQAAP__\r\nX-Custom: 2\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut response = vec![0; SWITCHING_PROTOCOLS.len()];
+        stream.read_exact(&mut response).await.unwrap();
+        assert_eq!(response, SWITCHING_PROTOCOLS);
+
+        write_preface(&mut stream).await;
+        assert_eq!(
+            read_body(&mut stream).await,
+            "HTTP/2.0 GET http://example.com/a?b=1 Some(\"2\")"
+        );
+
+        // disabled
+        let addr = start(TcpListener::bind("127.0.0.1:0").h2c().upgrade(false)).await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: example.com\r\nConnection: Upgrade, HTTP2-Settings\r\nUpgrade: h2c\r\nHTTP2-Settings: AAMAAABkAAQAAP__\r\nConnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("HTTP/1.1 GET / None"));
+    }
+
+    #[test]
+    fn upgrade_request() {
+        let head = b"POST / HTTP/1.1\r\nHost: a\r\nConnection: Upgrade, HTTP2-Settings\r\nUpgrade: h2c\r\nHTTP2-Settings: \r\nContent-Length: 5\r\n\r\n";
+        assert!(super::upgrade_request(head).is_none());
+        let head = b"GET / HTTP/1.1\r\nHost: a\r\nConnection: Upgrade\r\nUpgrade: h2c\r\nHTTP2-Settings: \r\n\r\n";
+        assert!(super::upgrade_request(head).is_none());
+        let head =
+            b"GET / HTTP/1.1\r\nHost: a\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\r\n";
+        assert!(super::upgrade_request(head).is_none());
+    }
+
+    #[test]
+    fn encode_integer() {
+        let mut buf = Vec::new();
+        super::encode_integer(&mut buf, 126);
+        assert_eq!(buf, [126]);
+        buf.clear();
+        super::encode_integer(&mut buf, 1337);
+        assert_eq!(buf, [0x7f, 0xba, 0x09]);
+    }
+}
@@ -14,7 +14,8 @@ enum State<S> {
     Error,
 }
 
-/// A handshake stream for tls.
+/// A stream which completes a handshake, such as the TLS handshake, before
+/// it is read or written.
 pub struct HandshakeStream<S> {
     state: State<S>,
 }
@@ -3,15 +3,22 @@
 #[cfg(feature = "acme-base")]
 #[cfg_attr(docsrs, doc(cfg(feature = "acme-base")))]
 pub mod acme;
+#[cfg(any(feature = "fastcgi", feature = "scgi"))]
+mod cgi;
 mod combined;
-#[cfg(any(feature = "native-tls", feature = "rustls", feature = "openssl-tls"))]
+#[cfg(feature = "fastcgi")]
+mod fastcgi;
+mod h2c;
 mod handshake_stream;
+mod multi;
 #[cfg(feature = "native-tls")]
 mod native_tls;
 #[cfg(feature = "openssl-tls")]
 mod openssl_tls;
 #[cfg(feature = "rustls")]
 mod rustls;
+#[cfg(feature = "scgi")]
+mod scgi;
 mod tcp;
 #[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
 mod tls;
@@ -20,48 +27,54 @@ mod unix;
 
 use std::{
     convert::Infallible,
-    io::Error,
+    io::{Error, IoSlice},
     pin::Pin,
     task::{Context, Poll},
 };
 
 use futures_util::{future::BoxFuture, Future, FutureExt, TryFutureExt};
-use http::uri::Scheme;
+use http::{uri::Scheme, Extensions};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult};
 
 #[cfg(feature = "acme-base")]
 use self::acme::{AutoCert, AutoCertListener};
-#[cfg(any(feature = "native-tls", feature = "rustls", feature = "openssl-tls"))]
-pub use self::handshake_stream::HandshakeStream;
+#[cfg(feature = "fastcgi")]
+pub use self::fastcgi::{FastCgiAcceptor, FastCgiListener};
 #[cfg(feature = "native-tls")]
 pub use self::native_tls::{NativeTlsAcceptor, NativeTlsConfig, NativeTlsListener};
 #[cfg(feature = "openssl-tls")]
 pub use self::openssl_tls::{OpensslTlsAcceptor, OpensslTlsConfig, OpensslTlsListener};
 #[cfg(feature = "rustls")]
 pub use self::rustls::{RustlsAcceptor, RustlsCertificate, RustlsConfig, RustlsListener};
+#[cfg(feature = "scgi")]
+pub use self::scgi::{ScgiAcceptor, ScgiListener};
 #[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
 pub use self::tls::IntoTlsConfigStream;
 #[cfg(unix)]
 pub use self::unix::{UnixAcceptor, UnixListener};
 pub use self::{
     combined::{Combined, CombinedStream},
-    tcp::{TcpAcceptor, TcpListener},
+    h2c::{H2cAcceptor, H2cListener, H2cStream},
+    handshake_stream::HandshakeStream,
+    multi::{ListenerTag, MultiAcceptor, MultiListener},
+    tcp::{TcpAcceptor, TcpKeepalive, TcpListener},
 };
 use crate::web::{LocalAddr, RemoteAddr};
 
+trait Io: AsyncRead + AsyncWrite + Send + Unpin + 'static {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin + 'static> Io for T {}
+
 /// An IO type for BoxAcceptor.
-pub struct BoxIo {
-    reader: Box<dyn AsyncRead + Send + Unpin + 'static>,
-    writer: Box<dyn AsyncWrite + Send + Unpin + 'static>,
-}
+///
+/// The vectored writes are forwarded to the inner IO, so the responses are
+/// written with a single `writev` call instead of being copied into a
+/// buffer first.
+pub struct BoxIo(Box<dyn Io>);
 
 impl BoxIo {
     fn new(io: impl AsyncRead + AsyncWrite + Send + Unpin + 'static) -> Self {
-        let (reader, writer) = tokio::io::split(io);
-        Self {
-            reader: Box::new(reader),
-            writer: Box::new(writer),
-        }
+        Self(Box::new(io))
     }
 }
 
@@ -71,8 +84,7 @@ impl AsyncRead for BoxIo {
         cx: &mut Context<'_>,
         buf: &mut ReadBuf<'_>,
     ) -> Poll<IoResult<()>> {
-        let this = &mut *self;
-        Pin::new(&mut this.reader).poll_read(cx, buf)
+        Pin::new(&mut *self.0).poll_read(cx, buf)
     }
 }
 
@@ -82,18 +94,27 @@ impl AsyncWrite for BoxIo {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<Result<usize, Error>> {
-        let this = &mut *self;
-        Pin::new(&mut this.writer).poll_write(cx, buf)
+        Pin::new(&mut *self.0).poll_write(cx, buf)
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
-        let this = &mut *self;
-        Pin::new(&mut this.writer).poll_flush(cx)
+        Pin::new(&mut *self.0).poll_flush(cx)
     }
 
     fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
-        let this = &mut *self;
-        Pin::new(&mut this.writer).poll_shutdown(cx)
+        Pin::new(&mut *self.0).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, Error>> {
+        Pin::new(&mut *self.0).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.0.is_write_vectored()
     }
 }
 
@@ -108,6 +129,21 @@ pub trait DynAcceptor: Send {
     /// established, the corresponding IO stream and the remote peer’s
     /// address will be returned.
     fn accept(&mut self) -> BoxFuture<IoResult<(BoxIo, LocalAddr, RemoteAddr, Scheme)>>;
+
+    /// Accepts a new incoming connection from this listener, and returns the
+    /// extensions of it.
+    ///
+    /// See also [`Acceptor::accept_with_extensions`].
+    #[allow(clippy::type_complexity)]
+    fn accept_with_extensions(
+        &mut self,
+    ) -> BoxFuture<'_, IoResult<(BoxIo, LocalAddr, RemoteAddr, Scheme, Extensions)>> {
+        self.accept()
+            .map_ok(|(io, local_addr, remote_addr, scheme)| {
+                (io, local_addr, remote_addr, scheme, Extensions::new())
+            })
+            .boxed()
+    }
 }
 
 /// A [`Acceptor`] wrapper used to implement [`DynAcceptor`].
@@ -128,6 +164,19 @@ impl<A: Acceptor> DynAcceptor for ToDynAcceptor<A> {
         }
         .boxed()
     }
+
+    #[inline]
+    fn accept_with_extensions(
+        &mut self,
+    ) -> BoxFuture<'_, IoResult<(BoxIo, LocalAddr, RemoteAddr, Scheme, Extensions)>> {
+        async move {
+            let (io, local_addr, remote_addr, scheme, extensions) =
+                self.0.accept_with_extensions().await?;
+            let io = BoxIo::new(io);
+            Ok((io, local_addr, remote_addr, scheme, extensions))
+        }
+        .boxed()
+    }
 }
 
 impl Acceptor for dyn DynAcceptor + '_ {
@@ -142,6 +191,13 @@ impl Acceptor for dyn DynAcceptor + '_ {
     async fn accept(&mut self) -> IoResult<(BoxIo, LocalAddr, RemoteAddr, Scheme)> {
         DynAcceptor::accept(self).await
     }
+
+    #[inline]
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(BoxIo, LocalAddr, RemoteAddr, Scheme, Extensions)> {
+        DynAcceptor::accept_with_extensions(self).await
+    }
 }
 
 /// Represents a acceptor type.
@@ -160,6 +216,21 @@ pub trait Acceptor: Send {
     fn accept(
         &mut self,
     ) -> impl Future<Output = IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)>> + Send;
+
+    /// Accepts a new incoming connection from this listener, and returns the
+    /// extensions which are added to all the requests received on it.
+    ///
+    /// The default implementation returns empty extensions. The acceptors
+    /// which wrap another acceptor should forward the extensions of it.
+    fn accept_with_extensions(
+        &mut self,
+    ) -> impl Future<Output = IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)>> + Send
+    {
+        self.accept()
+            .map_ok(|(io, local_addr, remote_addr, scheme)| {
+                (io, local_addr, remote_addr, scheme, Extensions::new())
+            })
+    }
 }
 
 /// An owned dynamically typed Acceptor for use in cases where you can’t
@@ -217,6 +288,37 @@ pub trait AcceptorExt: Acceptor {
     {
         OpensslTlsAcceptor::new(self, config_stream)
     }
+
+    /// Consume this acceptor and return a new acceptor which accepts HTTP/2
+    /// over cleartext TCP.
+    fn h2c(self) -> H2cAcceptor<Self>
+    where
+        Self: Sized,
+    {
+        H2cAcceptor::new(self)
+    }
+
+    /// Consume this acceptor and return a new acceptor which speaks the
+    /// FastCGI protocol.
+    #[cfg(feature = "fastcgi")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "fastcgi")))]
+    fn fastcgi(self) -> FastCgiAcceptor<Self>
+    where
+        Self: Sized,
+    {
+        FastCgiAcceptor::new(self)
+    }
+
+    /// Consume this acceptor and return a new acceptor which speaks the SCGI
+    /// protocol.
+    #[cfg(feature = "scgi")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "scgi")))]
+    fn scgi(self) -> ScgiAcceptor<Self>
+    where
+        Self: Sized,
+    {
+        ScgiAcceptor::new(self)
+    }
 }
 
 impl<T: Acceptor> AcceptorExt for T {}
@@ -290,6 +392,40 @@ pub trait Listener: Send {
         OpensslTlsListener::new(self, config_stream)
     }
 
+    /// Consume this listener and return a new listener which accepts HTTP/2
+    /// over cleartext TCP.
+    #[must_use]
+    fn h2c(self) -> H2cListener<Self>
+    where
+        Self: Sized,
+    {
+        H2cListener::new(self)
+    }
+
+    /// Consume this listener and return a new listener which speaks the
+    /// FastCGI protocol.
+    #[cfg(feature = "fastcgi")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "fastcgi")))]
+    #[must_use]
+    fn fastcgi(self) -> FastCgiListener<Self>
+    where
+        Self: Sized,
+    {
+        FastCgiListener::new(self)
+    }
+
+    /// Consume this listener and return a new listener which speaks the SCGI
+    /// protocol.
+    #[cfg(feature = "scgi")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "scgi")))]
+    #[must_use]
+    fn scgi(self) -> ScgiListener<Self>
+    where
+        Self: Sized,
+    {
+        ScgiListener::new(self)
+    }
+
     /// Consume this listener and return a new ACME listener.
     ///
     /// # Example
@@ -353,6 +489,12 @@ impl<T: Acceptor + ?Sized> Acceptor for Box<T> {
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
         self.as_mut().accept().await
     }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
+        self.as_mut().accept_with_extensions().await
+    }
 }
 
 impl Acceptor for Infallible {
@@ -410,4 +552,33 @@ mod tests {
 
         let _ = a.combine(b);
     }
+
+    #[tokio::test]
+    async fn box_io_vectored() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap()
+            .boxed();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let mut buf = Vec::new();
+            stream.read_to_end(&mut buf).await.unwrap();
+            assert_eq!(buf, b"hello world");
+        });
+
+        let (mut io, _, _, _) = acceptor.accept().await.unwrap();
+        assert!(io.is_write_vectored());
+        let n = io
+            .write_vectored(&[IoSlice::new(b"hello "), IoSlice::new(b"world")])
+            .await
+            .unwrap();
+        io.write_all(&b"hello world"[n..]).await.unwrap();
+        io.shutdown().await.unwrap();
+        client.await.unwrap();
+    }
 }
@@ -0,0 +1,257 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::Deref,
+    sync::Arc,
+};
+
+use futures_util::FutureExt;
+use http::{uri::Scheme, Extensions};
+use tokio::io::Result as IoResult;
+
+use crate::{
+    listener::{Acceptor, AcceptorExt, BoxAcceptor, BoxIo, BoxListener, Listener},
+    web::{LocalAddr, RemoteAddr},
+};
+
+/// The tag of the listener which received the request.
+///
+/// It is added to the extensions of the requests received by the listeners
+/// tagged with [`MultiListener::add_tagged`], and can be extracted with
+/// `Data<&ListenerTag>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ListenerTag(Arc<str>);
+
+impl ListenerTag {
+    /// Create a `ListenerTag`.
+    pub fn new(tag: impl Into<Arc<str>>) -> Self {
+        Self(tag.into())
+    }
+
+    /// Returns the tag as a string slice.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for ListenerTag {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for ListenerTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A listener which binds multiple listeners, and tags the requests with the
+/// listener which received them.
+///
+/// It is the N-ary version of [`Listener::combine`], the tags allow to apply
+/// different policies per interface within a single route, e.g. with the
+/// [`ForListener`](crate::middleware::ForListener) middleware.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     listener::{ListenerTag, MultiListener, TcpListener},
+///     web::Data,
+/// };
+///
+/// #[handler]
+/// fn index(tag: Option<Data<&ListenerTag>>) -> String {
+///     match tag {
+///         Some(Data(tag)) => format!("received by {tag}"),
+///         None => "received by an untagged listener".to_string(),
+///     }
+/// }
+///
+/// let listener = MultiListener::new()
+///     .add_tagged("public", TcpListener::bind("0.0.0.0:3000"))
+///     .add_tagged("internal", TcpListener::bind("127.0.0.1:3001"))
+///     .add(TcpListener::bind("127.0.0.1:3002"));
+/// ```
+#[derive(Default)]
+pub struct MultiListener {
+    listeners: Vec<(Option<ListenerTag>, BoxListener)>,
+}
+
+impl MultiListener {
+    /// Create an empty `MultiListener`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a listener whose requests are not tagged.
+    #[must_use]
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, listener: impl Listener + 'static) -> Self {
+        self.listeners.push((None, listener.boxed()));
+        self
+    }
+
+    /// Adds a listener whose requests are tagged with the [`ListenerTag`].
+    #[must_use]
+    pub fn add_tagged(
+        mut self,
+        tag: impl Into<Arc<str>>,
+        listener: impl Listener + 'static,
+    ) -> Self {
+        self.listeners
+            .push((Some(ListenerTag::new(tag)), listener.boxed()));
+        self
+    }
+}
+
+impl Listener for MultiListener {
+    type Acceptor = MultiAcceptor;
+
+    async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
+        let mut acceptors = Vec::with_capacity(self.listeners.len());
+        for (tag, listener) in self.listeners {
+            acceptors.push((tag, listener.into_acceptor().await?));
+        }
+        Ok(MultiAcceptor { acceptors })
+    }
+}
+
+/// A acceptor for the [`MultiListener`].
+#[derive(Default)]
+pub struct MultiAcceptor {
+    acceptors: Vec<(Option<ListenerTag>, BoxAcceptor)>,
+}
+
+impl MultiAcceptor {
+    /// Create an empty `MultiAcceptor`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds an acceptor whose requests are not tagged.
+    #[must_use]
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, acceptor: impl Acceptor + 'static) -> Self {
+        self.acceptors.push((None, acceptor.boxed()));
+        self
+    }
+
+    /// Adds an acceptor whose requests are tagged with the [`ListenerTag`].
+    #[must_use]
+    pub fn add_tagged(
+        mut self,
+        tag: impl Into<Arc<str>>,
+        acceptor: impl Acceptor + 'static,
+    ) -> Self {
+        self.acceptors
+            .push((Some(ListenerTag::new(tag)), acceptor.boxed()));
+        self
+    }
+}
+
+impl Acceptor for MultiAcceptor {
+    type Io = BoxIo;
+
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        self.acceptors
+            .iter()
+            .flat_map(|(_, acceptor)| acceptor.local_addr())
+            .collect()
+    }
+
+    async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (io, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((io, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
+        if self.acceptors.is_empty() {
+            return futures_util::future::pending().await;
+        }
+
+        let (res, index, _) = futures_util::future::select_all(
+            self.acceptors
+                .iter_mut()
+                .map(|(_, acceptor)| acceptor.accept_with_extensions().boxed()),
+        )
+        .await;
+        let (io, local_addr, remote_addr, scheme, mut extensions) = res?;
+        if let Some(tag) = &self.acceptors[index].0 {
+            extensions.insert(tag.clone());
+        }
+        Ok((io, local_addr, remote_addr, scheme, extensions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::{handler, listener::TcpListener, web::Data, Server};
+
+    #[handler(internal)]
+    fn index(tag: Option<Data<&ListenerTag>>) -> String {
+        tag.map(|Data(tag)| tag.to_string()).unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn multi_listener() {
+        let acceptor = MultiListener::new()
+            .add_tagged("public", TcpListener::bind("127.0.0.1:0"))
+            .add_tagged("internal", TcpListener::bind("127.0.0.1:0"))
+            .add(TcpListener::bind("127.0.0.1:0"))
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addrs = acceptor
+            .local_addr()
+            .into_iter()
+            .map(|addr| *addr.as_socket_addr().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(addrs.len(), 3);
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(index));
+
+        for (addr, tag) in addrs.into_iter().zip(["public", "internal", ""]) {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nhost: a\r\nconnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut resp = String::new();
+            stream.read_to_string(&mut resp).await.unwrap();
+            assert!(resp.ends_with(&format!("\r\n\r\n{tag}")));
+        }
+    }
+
+    #[tokio::test]
+    async fn extensions_are_forwarded() {
+        let a = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *a.local_addr()[0].as_socket_addr().unwrap();
+        let mut acceptor = MultiAcceptor::new()
+            .add_tagged("a", a)
+            .combine(MultiAcceptor::new())
+            .boxed();
+
+        let _stream = TcpStream::connect(addr).await.unwrap();
+        let (_, _, _, _, extensions) = acceptor.accept_with_extensions().await.unwrap();
+        assert_eq!(
+            extensions.get::<ListenerTag>().map(ListenerTag::as_str),
+            Some("a")
+        );
+    }
+}
@@ -2,13 +2,13 @@ use futures_util::{
     stream::{BoxStream, Chain, Pending},
     Stream, StreamExt, TryFutureExt,
 };
-use http::uri::Scheme;
+use http::{uri::Scheme, Extensions};
 use tokio::io::{Error as IoError, ErrorKind, Result as IoResult};
 use tokio_native_tls::{native_tls::Identity, TlsStream};
 
 use crate::{
     listener::{Acceptor, HandshakeStream, IntoTlsConfigStream, Listener},
-    web::{LocalAddr, RemoteAddr},
+    web::{with_tls_info, LocalAddr, RemoteAddr, TlsInfo},
 };
 
 /// Native TLS Config.
@@ -147,6 +147,13 @@ where
     }
 
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (stream, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((stream, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
         loop {
             tokio::select! {
                 res = self.config_stream.next() => {
@@ -166,15 +173,17 @@ where
                         unreachable!()
                     }
                 }
-                res = self.inner.accept() => {
-                    let (stream, local_addr, remote_addr, _) = res?;
+                res = self.inner.accept_with_extensions() => {
+                    let (stream, local_addr, remote_addr, _, mut extensions) = res?;
                     let tls_acceptor = match &self.current_tls_acceptor {
                         Some(tls_acceptor) => tls_acceptor.clone(),
                         None => return Err(IoError::new(ErrorKind::Other, "no valid tls config.")),
                     };
                     let fut = async move { tls_acceptor.accept(stream).map_err(|err| IoError::new(ErrorKind::Other, err.to_string())).await };
+                    // `native-tls` exposes none of the details of `TlsInfo`.
+                    let fut = with_tls_info(&mut extensions, fut, |_| TlsInfo::default());
                     let stream = HandshakeStream::new(fut);
-                    return Ok((stream, local_addr, remote_addr, Scheme::HTTPS));
+                    return Ok((stream, local_addr, remote_addr, Scheme::HTTPS, extensions));
                 }
             }
         }
@@ -7,10 +7,10 @@ use futures_util::{
     stream::{BoxStream, Chain, Pending},
     Stream, StreamExt,
 };
-use http::uri::Scheme;
+use http::{uri::Scheme, Extensions};
 use openssl::{
     pkey::PKey,
-    ssl::{Ssl, SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod, SslRef},
+    ssl::{NameType, Ssl, SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod, SslRef},
     x509::X509,
 };
 use tokio::io::{Error as IoError, ErrorKind, Result as IoResult};
@@ -19,7 +19,7 @@ use tokio_util::either::Either;
 
 use crate::{
     listener::{Acceptor, HandshakeStream, IntoTlsConfigStream, Listener},
-    web::{LocalAddr, RemoteAddr},
+    web::{with_tls_info, LocalAddr, RemoteAddr, TlsInfo, TlsVersion},
 };
 
 /// Openssl configuration contains certificate's chain and private key.
@@ -192,6 +192,13 @@ where
     }
 
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (stream, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((stream, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
         loop {
             tokio::select! {
                 res = self.config_stream.next() => {
@@ -211,8 +218,8 @@ where
                         unreachable!()
                     }
                 }
-                res = self.inner.accept() => {
-                    let (stream, local_addr, remote_addr, _) = res?;
+                res = self.inner.accept_with_extensions() => {
+                    let (stream, local_addr, remote_addr, _, mut extensions) = res?;
                     let tls_acceptor = match &self.current_tls_acceptor {
                         Some(tls_acceptor) => tls_acceptor.clone(),
                         None => return Err(IoError::new(ErrorKind::Other, "no valid tls config.")),
@@ -226,14 +233,32 @@ where
                         Pin::new(&mut tls_stream).accept().await.map_err(|err|
                             IoError::new(ErrorKind::Other, err.to_string()))?;
                         Ok(tls_stream) };
+                    let fut = with_tls_info(&mut extensions, fut, openssl_tls_info);
                     let stream = HandshakeStream::new(fut);
-                    return Ok((stream, local_addr, remote_addr, Scheme::HTTPS));
+                    return Ok((stream, local_addr, remote_addr, Scheme::HTTPS, extensions));
                 }
             }
         }
     }
 }
 
+fn openssl_tls_info<S>(stream: &SslStream<S>) -> TlsInfo {
+    let ssl = stream.ssl();
+    TlsInfo {
+        version: match ssl.version_str() {
+            "SSLv3" => Some(TlsVersion::Ssl3),
+            "TLSv1" => Some(TlsVersion::Tls10),
+            "TLSv1.1" => Some(TlsVersion::Tls11),
+            "TLSv1.2" => Some(TlsVersion::Tls12),
+            "TLSv1.3" => Some(TlsVersion::Tls13),
+            _ => None,
+        },
+        cipher_suite: ssl.current_cipher().map(|cipher| cipher.name().to_string()),
+        alpn_protocol: ssl.selected_alpn_protocol().map(ToOwned::to_owned),
+        server_name: ssl.servername(NameType::HOST_NAME).map(ToOwned::to_owned),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use openssl::ssl::SslConnector;
@@ -281,4 +306,49 @@ mod tests {
         let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
         assert_eq!(stream.read_i32().await.unwrap(), 10);
     }
+
+    #[tokio::test]
+    async fn tls_info() {
+        let listener = TcpListener::bind("127.0.0.1:0").openssl_tls(
+            OpensslTlsConfig::new()
+                .cert_from_file("src/listener/certs/cert1.pem")
+                .key_from_file("src/listener/certs/key1.pem"),
+        );
+        let mut acceptor = listener.into_acceptor().await.unwrap();
+        let local_addr = acceptor.local_addr().pop().unwrap();
+
+        tokio::spawn(async move {
+            let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+            connector
+                .set_ca_file("src/listener/certs/chain1.pem")
+                .unwrap();
+
+            let ssl = connector
+                .build()
+                .configure()
+                .unwrap()
+                .into_ssl("testserver.com")
+                .unwrap();
+
+            let stream = TcpStream::connect(local_addr.as_socket_addr().unwrap())
+                .await
+                .unwrap();
+            let mut tls_stream = SslStream::new(ssl, stream).unwrap();
+            use std::pin::Pin;
+            Pin::new(&mut tls_stream).connect().await.unwrap();
+
+            tls_stream.write_i32(10).await.unwrap();
+        });
+
+        let (mut stream, _, _, _, extensions) = acceptor.accept_with_extensions().await.unwrap();
+        assert_eq!(stream.read_i32().await.unwrap(), 10);
+
+        let tls_info = extensions
+            .get::<crate::web::TlsInfoSlot>()
+            .and_then(|slot| slot.get())
+            .unwrap();
+        assert!(tls_info.version() >= Some(TlsVersion::Tls12));
+        assert!(tls_info.cipher_suite().is_some());
+        assert_eq!(tls_info.server_name(), Some("testserver.com"));
+    }
 }
@@ -4,7 +4,7 @@ use futures_util::{
     stream::{BoxStream, Chain, Pending},
     Stream, StreamExt,
 };
-use http::uri::Scheme;
+use http::{uri::Scheme, Extensions};
 use rustls_pemfile::Item;
 use tokio::io::{Error as IoError, ErrorKind, Result as IoResult};
 use tokio_rustls::{
@@ -19,7 +19,7 @@ use tokio_rustls::{
 
 use crate::{
     listener::{Acceptor, HandshakeStream, IntoTlsConfigStream, Listener},
-    web::{LocalAddr, RemoteAddr},
+    web::{with_tls_info, LocalAddr, RemoteAddr, TlsInfo, TlsVersion},
 };
 
 #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
@@ -364,6 +364,13 @@ where
     }
 
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (stream, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((stream, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
         loop {
             tokio::select! {
                 res = self.config_stream.next() => {
@@ -384,21 +391,43 @@ where
                         unreachable!()
                     }
                 }
-                res = self.inner.accept() => {
-                    let (stream, local_addr, remote_addr, _) = res?;
+                res = self.inner.accept_with_extensions() => {
+                    let (stream, local_addr, remote_addr, _, mut extensions) = res?;
                     let tls_acceptor = match &self.current_tls_acceptor {
                         Some(tls_acceptor) => tls_acceptor,
                         None => return Err(IoError::new(ErrorKind::Other, "no valid tls config.")),
                     };
 
-                    let stream = HandshakeStream::new(tls_acceptor.accept(stream));
-                    return Ok((stream, local_addr, remote_addr, Scheme::HTTPS));
+                    let fut = with_tls_info(&mut extensions, tls_acceptor.accept(stream), rustls_tls_info);
+                    let stream = HandshakeStream::new(fut);
+                    return Ok((stream, local_addr, remote_addr, Scheme::HTTPS, extensions));
                 }
             }
         }
     }
 }
 
+pub(crate) fn rustls_tls_info<IO>(stream: &TlsStream<IO>) -> TlsInfo {
+    use tokio_rustls::rustls::ProtocolVersion;
+
+    let (_, conn) = stream.get_ref();
+    TlsInfo {
+        version: conn.protocol_version().and_then(|version| match version {
+            ProtocolVersion::SSLv3 => Some(TlsVersion::Ssl3),
+            ProtocolVersion::TLSv1_0 => Some(TlsVersion::Tls10),
+            ProtocolVersion::TLSv1_1 => Some(TlsVersion::Tls11),
+            ProtocolVersion::TLSv1_2 => Some(TlsVersion::Tls12),
+            ProtocolVersion::TLSv1_3 => Some(TlsVersion::Tls13),
+            _ => None,
+        }),
+        cipher_suite: conn
+            .negotiated_cipher_suite()
+            .map(|suite| format!("{:?}", suite.suite())),
+        alpn_protocol: conn.alpn_protocol().map(ToOwned::to_owned),
+        server_name: conn.server_name().map(ToOwned::to_owned),
+    }
+}
+
 #[derive(Debug)]
 struct ResolveServerCert {
     certificate_keys: HashMap<String, Arc<CertifiedKey>>,
@@ -456,4 +485,45 @@ mod tests {
         let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
         assert_eq!(stream.read_i32().await.unwrap(), 10);
     }
+
+    #[tokio::test]
+    async fn tls_info() {
+        let listener = TcpListener::bind("127.0.0.1:0").rustls(
+            RustlsConfig::new().fallback(
+                RustlsCertificate::new()
+                    .cert(include_bytes!("certs/cert1.pem").as_ref())
+                    .key(include_bytes!("certs/key1.pem").as_ref()),
+            ),
+        );
+        let mut acceptor = listener.into_acceptor().await.unwrap();
+        let local_addr = acceptor.local_addr().pop().unwrap();
+
+        tokio::spawn(async move {
+            let mut config = ClientConfig::builder()
+                .with_root_certificates(
+                    read_trust_anchor(include_bytes!("certs/chain1.pem")).unwrap(),
+                )
+                .with_no_client_auth();
+            config.alpn_protocols = vec![b"h2".to_vec()];
+
+            let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+            let domain = ServerName::try_from("testserver.com").unwrap();
+            let stream = TcpStream::connect(*local_addr.as_socket_addr().unwrap())
+                .await
+                .unwrap();
+            let mut stream = connector.connect(domain, stream).await.unwrap();
+            stream.write_i32(10).await.unwrap();
+        });
+
+        let (mut stream, _, _, _, extensions) = acceptor.accept_with_extensions().await.unwrap();
+        let slot = extensions.get::<crate::web::TlsInfoSlot>().unwrap();
+        assert!(slot.get().is_none());
+        assert_eq!(stream.read_i32().await.unwrap(), 10);
+
+        let tls_info = slot.get().unwrap();
+        assert_eq!(tls_info.version(), Some(TlsVersion::Tls13));
+        assert!(tls_info.cipher_suite().unwrap().starts_with("TLS13_"));
+        assert_eq!(tls_info.alpn_protocol(), Some(&b"h2"[..]));
+        assert_eq!(tls_info.server_name(), Some("testserver.com"));
+    }
 }
@@ -0,0 +1,208 @@
+use std::io::{Error, ErrorKind};
+
+use http::{uri::Scheme, Extensions};
+use http_body_util::BodyExt;
+use tokio::io::{
+    AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, Result as IoResult,
+};
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    listener::{
+        cgi::{self, PIPE_BUFFER_SIZE},
+        Acceptor, Listener,
+    },
+    web::{LocalAddr, RemoteAddr},
+};
+
+/// The maximum length of the request headers.
+const MAX_HEADERS_LENGTH: usize = 1024 * 1024;
+
+/// A wrapper around an underlying listener which speaks the
+/// [SCGI](https://python.ca/scgi/protocol.txt) protocol.
+///
+/// This allows the application to run behind a web server such as nginx or
+/// Apache with `scgi_pass`/`mod_proxy_scgi`. Each connection carries one
+/// request.
+///
+/// The `REMOTE_ADDR` and `HTTPS` variables are forwarded as the `x-real-ip`
+/// and `x-forwarded-proto` headers, so [`RealIp`](crate::web::RealIp) returns
+/// the address of the client.
+///
+/// # Example
+///
+/// ```
+/// use poem::listener::{Listener, ScgiListener, TcpListener};
+///
+/// let listener = TcpListener::bind("127.0.0.1:4000").scgi();
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "scgi")))]
+pub struct ScgiListener<T> {
+    inner: T,
+}
+
+impl<T> ScgiListener<T> {
+    /// Create a `ScgiListener`.
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T: Listener> Listener for ScgiListener<T> {
+    type Acceptor = ScgiAcceptor<T::Acceptor>;
+
+    async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
+        Ok(ScgiAcceptor::new(self.inner.into_acceptor().await?))
+    }
+}
+
+/// A acceptor which speaks the SCGI protocol.
+#[cfg_attr(docsrs, doc(cfg(feature = "scgi")))]
+pub struct ScgiAcceptor<T> {
+    inner: T,
+}
+
+impl<T> ScgiAcceptor<T> {
+    /// Create a `ScgiAcceptor`.
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T: Acceptor> Acceptor for ScgiAcceptor<T> {
+    type Io = DuplexStream;
+
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        self.inner.local_addr()
+    }
+
+    async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (io, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((io, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
+        let (io, local_addr, remote_addr, scheme, extensions) =
+            self.inner.accept_with_extensions().await?;
+        let (server_io, proxy_io) = tokio::io::duplex(PIPE_BUFFER_SIZE);
+        crate::spawn::spawn("poem-scgi-connection", async move {
+            if let Err(err) = serve(io, proxy_io).await {
+                tracing::debug!(error = %err, "scgi connection error");
+            }
+        });
+        Ok((server_io, local_addr, remote_addr, scheme, extensions))
+    }
+}
+
+/// Reads the headers netstring, `<length>:<name>\0<value>\0...,`.
+async fn read_headers<R: AsyncRead + Unpin>(reader: &mut R) -> IoResult<Vec<(Vec<u8>, Vec<u8>)>> {
+    let invalid = || Error::new(ErrorKind::InvalidData, "invalid scgi headers");
+
+    let mut length = 0usize;
+    loop {
+        match reader.read_u8().await? {
+            b':' => break,
+            c @ b'0'..=b'9' => {
+                length = length
+                    .checked_mul(10)
+                    .and_then(|length| length.checked_add((c - b'0') as usize))
+                    .filter(|length| *length <= MAX_HEADERS_LENGTH)
+                    .ok_or_else(invalid)?;
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    let mut data = vec![0; length + 1];
+    reader.read_exact(&mut data).await?;
+    if data.pop() != Some(b',') {
+        return Err(invalid());
+    }
+
+    let mut items = data.split(|c| *c == 0);
+    let mut headers = Vec::new();
+    while let Some(name) = items.next().filter(|name| !name.is_empty()) {
+        let value = items.next().ok_or_else(invalid)?;
+        headers.push((name.to_vec(), value.to_vec()));
+    }
+    Ok(headers)
+}
+
+async fn serve<IO>(io: IO, proxy_io: DuplexStream) -> IoResult<()>
+where
+    IO: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let mut sender = cgi::connect(proxy_io).await?;
+    let (mut reader, mut writer) = tokio::io::split(io);
+
+    let headers = read_headers(&mut reader).await?;
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name == b"CONTENT_LENGTH")
+        .and_then(|(_, value)| std::str::from_utf8(value).ok()?.parse::<u64>().ok())
+        .unwrap_or_default();
+    let body = cgi::stream_body(ReaderStream::new(reader.take(content_length)));
+    let request = cgi::build_request(&headers, body)?;
+
+    let (parts, mut body) = cgi::send_request(&mut sender, request).await.into_parts();
+    writer.write_all(&cgi::response_head(&parts)).await?;
+    while let Some(frame) = body.frame().await {
+        if let Some(data) = frame?.data_ref() {
+            writer.write_all(data).await?;
+        }
+    }
+    writer.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::{handler, listener::TcpListener, Server};
+
+    #[handler(internal)]
+    async fn index(body: String, req: &crate::Request) -> String {
+        format!("{} {} {}", req.method(), req.uri(), body)
+    }
+
+    #[tokio::test]
+    async fn scgi() {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .scgi()
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(index));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let headers = b"CONTENT_LENGTH\x005\x00SCGI\x001\x00REQUEST_METHOD\x00PUT\x00REQUEST_URI\x00/a?b=c\x00";
+        stream
+            .write_all(format!("{}:", headers.len()).as_bytes())
+            .await
+            .unwrap();
+        stream.write_all(headers).await.unwrap();
+        stream.write_all(b",hello").await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("Status: 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nPUT /a?b=c hello"));
+    }
+
+    #[tokio::test]
+    async fn invalid_headers() {
+        let mut reader: &[u8] = b"5:abc,";
+        assert!(read_headers(&mut reader).await.is_err());
+        let mut reader: &[u8] = b"x:";
+        assert!(read_headers(&mut reader).await.is_err());
+        let mut reader: &[u8] = b"4:A\x00B\x00,";
+        assert_eq!(
+            read_headers(&mut reader).await.unwrap(),
+            vec![(b"A".to_vec(), b"B".to_vec())]
+        );
+    }
+}
@@ -1,6 +1,11 @@
-use std::io::Result;
+use std::{
+    io::{Error, ErrorKind, Result},
+    net::SocketAddr,
+    time::Duration,
+};
 
 use http::uri::Scheme;
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
 use tokio::{
     io::Result as IoResult,
     net::{TcpListener as TokioTcpListener, TcpStream, ToSocketAddrs},
@@ -11,28 +16,255 @@ use crate::{
     web::{LocalAddr, RemoteAddr},
 };
 
+/// The TCP keepalive parameters, used by [`TcpListener::keepalive`].
+///
+/// The parameters which are not set use the system defaults.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct TcpKeepalive {
+    time: Option<Duration>,
+    interval: Option<Duration>,
+    retries: Option<u32>,
+}
+
+impl TcpKeepalive {
+    /// Create the keepalive parameters with the system defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the idle time before the first keepalive probe is sent.
+    #[must_use]
+    pub fn time(self, time: Duration) -> Self {
+        Self {
+            time: Some(time),
+            ..self
+        }
+    }
+
+    /// Sets the interval between the keepalive probes.
+    ///
+    /// This is ignored on the platforms which do not support it.
+    #[must_use]
+    pub fn interval(self, interval: Duration) -> Self {
+        Self {
+            interval: Some(interval),
+            ..self
+        }
+    }
+
+    /// Sets the number of the unacknowledged probes before the connection
+    /// is dropped.
+    ///
+    /// This is ignored on the platforms which do not support it, such as
+    /// Windows.
+    #[must_use]
+    pub fn retries(self, retries: u32) -> Self {
+        Self {
+            retries: Some(retries),
+            ..self
+        }
+    }
+
+    fn to_socket2(self) -> socket2::TcpKeepalive {
+        let mut keepalive = socket2::TcpKeepalive::new();
+        if let Some(time) = self.time {
+            keepalive = keepalive.with_time(time);
+        }
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            windows,
+        ))]
+        if let Some(interval) = self.interval {
+            keepalive = keepalive.with_interval(interval);
+        }
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "freebsd",
+            target_os = "netbsd",
+        ))]
+        if let Some(retries) = self.retries {
+            keepalive = keepalive.with_retries(retries);
+        }
+        keepalive
+    }
+}
+
+/// The options which are set on each accepted connection.
+#[derive(Debug, Default, Clone, Copy)]
+struct StreamOptions {
+    nodelay: Option<bool>,
+    keepalive: Option<TcpKeepalive>,
+    tos: Option<u32>,
+}
+
+impl StreamOptions {
+    fn apply(&self, stream: &TcpStream) -> Result<()> {
+        if let Some(nodelay) = self.nodelay {
+            stream.set_nodelay(nodelay)?;
+        }
+        let socket = SockRef::from(stream);
+        if let Some(keepalive) = self.keepalive {
+            socket.set_tcp_keepalive(&keepalive.to_socket2())?;
+        }
+        #[cfg(not(any(
+            target_os = "fuchsia",
+            target_os = "redox",
+            target_os = "solaris",
+            target_os = "illumos",
+            target_os = "haiku",
+        )))]
+        if let Some(tos) = self.tos {
+            if stream.local_addr()?.is_ipv4() {
+                socket.set_tos(tos)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// A TCP listener.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::listener::{TcpKeepalive, TcpListener};
+///
+/// let listener = TcpListener::bind("0.0.0.0:3000")
+///     .nodelay(true)
+///     .keepalive(TcpKeepalive::new().time(Duration::from_secs(60)))
+///     .backlog(4096);
+/// ```
 pub struct TcpListener<T> {
     addr: T,
+    options: StreamOptions,
+    reuse_port: bool,
+    backlog: u32,
 }
 
 impl<T> TcpListener<T> {
     /// Binds to the provided address, and returns a [`TcpListener<T>`].
     pub fn bind(addr: T) -> Self {
-        Self { addr }
+        Self {
+            addr,
+            options: StreamOptions::default(),
+            reuse_port: false,
+            backlog: 1024,
+        }
+    }
+
+    /// Sets the `TCP_NODELAY` option on the accepted connections.
+    ///
+    /// Default is the system default.
+    #[must_use]
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.options.nodelay = Some(nodelay);
+        self
+    }
+
+    /// Enables the TCP keepalive on the accepted connections.
+    #[must_use]
+    pub fn keepalive(mut self, keepalive: TcpKeepalive) -> Self {
+        self.options.keepalive = Some(keepalive);
+        self
     }
+
+    /// Sets the `IP_TOS` option on the accepted IPv4 connections.
+    ///
+    /// This is ignored on the platforms which do not support it.
+    #[must_use]
+    pub fn tos(mut self, tos: u32) -> Self {
+        self.options.tos = Some(tos);
+        self
+    }
+
+    /// Sets the `SO_REUSEPORT` option on the listening socket, so that
+    /// multiple processes can listen on the same port.
+    ///
+    /// Binding fails with this option on the platforms which do not support
+    /// it, such as Windows.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn reuse_port(self, reuse_port: bool) -> Self {
+        Self { reuse_port, ..self }
+    }
+
+    /// Sets the maximum number of the pending connections.
+    ///
+    /// Default is `1024`.
+    #[must_use]
+    pub fn backlog(self, backlog: u32) -> Self {
+        Self { backlog, ..self }
+    }
+
+    /// Returns a listener with the same options, which binds to another
+    /// address.
+    pub(crate) fn with_addr<U>(&self, addr: U) -> TcpListener<U> {
+        TcpListener {
+            addr,
+            options: self.options,
+            reuse_port: self.reuse_port,
+            backlog: self.backlog,
+        }
+    }
+}
+
+fn bind_socket(addr: SocketAddr, reuse_port: bool, backlog: u32) -> Result<TokioTcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    if reuse_port {
+        #[cfg(all(
+            unix,
+            not(any(target_os = "solaris", target_os = "illumos", target_os = "cygwin"))
+        ))]
+        socket.set_reuse_port(true)?;
+        #[cfg(not(all(
+            unix,
+            not(any(target_os = "solaris", target_os = "illumos", target_os = "cygwin"))
+        )))]
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "`SO_REUSEPORT` is not supported",
+        ));
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(i32::try_from(backlog).unwrap_or(i32::MAX))?;
+    TokioTcpListener::from_std(socket.into())
 }
 
 impl<T: ToSocketAddrs + Send> Listener for TcpListener<T> {
     type Acceptor = TcpAcceptor;
 
     async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
-        let listener = TokioTcpListener::bind(self.addr).await?;
-        let local_addr = listener.local_addr().map(|addr| LocalAddr(addr.into()))?;
-        Ok(TcpAcceptor {
-            local_addr,
-            listener,
-        })
+        let mut last_err = None;
+        for addr in tokio::net::lookup_host(self.addr).await? {
+            match bind_socket(addr, self.reuse_port, self.backlog) {
+                Ok(listener) => {
+                    let local_addr = listener.local_addr().map(|addr| LocalAddr(addr.into()))?;
+                    return Ok(TcpAcceptor {
+                        local_addr,
+                        listener,
+                        options: self.options,
+                    });
+                }
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            Error::new(ErrorKind::InvalidInput, "could not resolve to any address")
+        }))
     }
 }
 
@@ -40,6 +272,7 @@ impl<T: ToSocketAddrs + Send> Listener for TcpListener<T> {
 pub struct TcpAcceptor {
     local_addr: LocalAddr,
     listener: TokioTcpListener,
+    options: StreamOptions,
 }
 
 impl TcpAcceptor {
@@ -49,6 +282,7 @@ impl TcpAcceptor {
         Ok(Self {
             local_addr,
             listener: TokioTcpListener::from_std(listener)?,
+            options: StreamOptions::default(),
         })
     }
 
@@ -58,6 +292,7 @@ impl TcpAcceptor {
         Ok(Self {
             local_addr,
             listener,
+            options: StreamOptions::default(),
         })
     }
 }
@@ -72,14 +307,16 @@ impl Acceptor for TcpAcceptor {
 
     #[inline]
     async fn accept(&mut self) -> Result<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
-        self.listener.accept().await.map(|(io, addr)| {
-            (
-                io,
-                self.local_addr.clone(),
-                RemoteAddr(addr.into()),
-                Scheme::HTTP,
-            )
-        })
+        let (io, addr) = self.listener.accept().await?;
+        if let Err(err) = self.options.apply(&io) {
+            tracing::warn!(error = %err, "failed to set the socket options");
+        }
+        Ok((
+            io,
+            self.local_addr.clone(),
+            RemoteAddr(addr.into()),
+            Scheme::HTTP,
+        ))
     }
 }
 
@@ -105,4 +342,44 @@ mod tests {
         let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
         assert_eq!(stream.read_i32().await.unwrap(), 10);
     }
+
+    #[tokio::test]
+    async fn socket_options() {
+        let mut acceptor = TcpListener::bind("127.0.0.1:0")
+            .nodelay(true)
+            .keepalive(
+                TcpKeepalive::new()
+                    .time(Duration::from_secs(30))
+                    .interval(Duration::from_secs(5))
+                    .retries(3),
+            )
+            .tos(0x10)
+            .reuse_port(true)
+            .backlog(16)
+            .into_acceptor()
+            .await
+            .unwrap();
+        let local_addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+
+        // another listener can bind to the same port
+        TcpListener::bind(local_addr)
+            .reuse_port(true)
+            .into_acceptor()
+            .await
+            .unwrap();
+        assert!(TcpListener::bind(local_addr).into_acceptor().await.is_err());
+
+        let _client = TcpStream::connect(local_addr).await.unwrap();
+        let (stream, _, _, _) = acceptor.accept().await.unwrap();
+        assert!(stream.nodelay().unwrap());
+        let socket = SockRef::from(&stream);
+        assert!(socket.keepalive().unwrap());
+        assert_eq!(socket.tos().unwrap(), 0x10);
+        #[cfg(target_os = "linux")]
+        {
+            assert_eq!(socket.keepalive_time().unwrap(), Duration::from_secs(30));
+            assert_eq!(socket.keepalive_interval().unwrap(), Duration::from_secs(5));
+            assert_eq!(socket.keepalive_retries().unwrap(), 3);
+        }
+    }
 }
@@ -0,0 +1,529 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    error::ConcurrencyLimitError, Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// A sample of a processed request, used to update the concurrency limit.
+#[derive(Debug, Clone, Copy)]
+pub struct LimitSample {
+    /// The time to process the request.
+    pub latency: Duration,
+    /// The number of the requests being processed when the request started,
+    /// including itself.
+    pub in_flight: usize,
+    /// Whether the request failed with a server error, which indicates that
+    /// the service is overloaded.
+    pub dropped: bool,
+}
+
+/// Represents an algorithm for the [`AdaptiveConcurrencyLimit`] middleware.
+pub trait LimitAlgorithm: Send + 'static {
+    /// Returns the new concurrency limit for the sample.
+    fn update(&mut self, limit: usize, sample: &LimitSample) -> usize;
+}
+
+/// The additive-increase/multiplicative-decrease algorithm.
+///
+/// The limit is increased by one when a request succeeds while the limit is
+/// being used, and multiplied by the backoff ratio when a request is dropped
+/// or exceeds the timeout.
+#[derive(Debug, Clone)]
+pub struct Aimd {
+    backoff_ratio: f64,
+    timeout: Duration,
+}
+
+impl Default for Aimd {
+    fn default() -> Self {
+        Self {
+            backoff_ratio: 0.9,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Aimd {
+    /// Create an `Aimd` algorithm.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the ratio by which the limit is multiplied on drops.
+    ///
+    /// Default is `0.9`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ratio is not in `[0.5, 1.0)`.
+    #[must_use]
+    pub fn backoff_ratio(self, ratio: f64) -> Self {
+        assert!(
+            (0.5..1.0).contains(&ratio),
+            "the backoff ratio must be in [0.5, 1.0)"
+        );
+        Self {
+            backoff_ratio: ratio,
+            ..self
+        }
+    }
+
+    /// Sets the latency above which a request is considered to be dropped.
+    ///
+    /// Default is `5s`.
+    #[must_use]
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+}
+
+impl LimitAlgorithm for Aimd {
+    fn update(&mut self, limit: usize, sample: &LimitSample) -> usize {
+        if sample.dropped || sample.latency > self.timeout {
+            (limit as f64 * self.backoff_ratio) as usize
+        } else if sample.in_flight * 2 >= limit {
+            limit + 1
+        } else {
+            limit
+        }
+    }
+}
+
+/// The gradient algorithm, which compares the latency of each request to the
+/// long-term average latency.
+///
+/// The limit is decreased when the latency is higher than the long-term
+/// average more than the tolerance, and increased by the square root of the
+/// limit otherwise, similar to `Gradient2Limit` in
+/// [Netflix concurrency-limits](https://github.com/Netflix/concurrency-limits).
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    tolerance: f64,
+    smoothing: f64,
+    long_window: usize,
+    long_latency: Option<f64>,
+    estimated: Option<f64>,
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self {
+            tolerance: 1.5,
+            smoothing: 0.2,
+            long_window: 600,
+            long_latency: None,
+            estimated: None,
+        }
+    }
+}
+
+impl Gradient {
+    /// Create a `Gradient` algorithm.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how much the latency can exceed the long-term average before the
+    /// limit is decreased.
+    ///
+    /// Default is `1.5`.
+    #[must_use]
+    pub fn tolerance(self, tolerance: f64) -> Self {
+        Self {
+            tolerance: tolerance.max(1.0),
+            ..self
+        }
+    }
+
+    /// Sets the factor for smoothing the changes of the limit, in `(0, 1]`.
+    ///
+    /// Default is `0.2`.
+    #[must_use]
+    pub fn smoothing(self, smoothing: f64) -> Self {
+        Self {
+            smoothing: smoothing.clamp(f64::EPSILON, 1.0),
+            ..self
+        }
+    }
+
+    /// Sets the number of the samples in the long-term average latency.
+    ///
+    /// Default is `600`.
+    #[must_use]
+    pub fn long_window(self, samples: usize) -> Self {
+        Self {
+            long_window: samples.max(1),
+            ..self
+        }
+    }
+}
+
+impl LimitAlgorithm for Gradient {
+    fn update(&mut self, limit: usize, sample: &LimitSample) -> usize {
+        // start over from the limit if it was clamped by the middleware, so
+        // that the estimate never drifts beyond the bounds
+        let estimated = match self.estimated {
+            Some(estimated) if estimated as usize == limit => estimated,
+            _ => limit as f64,
+        };
+        let short = sample.latency.as_secs_f64().max(f64::EPSILON);
+        let factor = 2.0 / (self.long_window as f64 + 1.0);
+        let mut long = match self.long_latency {
+            Some(long) => long * (1.0 - factor) + short * factor,
+            None => short,
+        };
+
+        // the long-term latency drifted after a load change, recover faster
+        if long / short > 2.0 {
+            long *= 0.95;
+        }
+        self.long_latency = Some(long);
+
+        // don't grow the limit if it is not used
+        if !sample.dropped && (sample.in_flight as f64) < estimated / 2.0 {
+            return limit;
+        }
+
+        let gradient = if sample.dropped {
+            0.5
+        } else {
+            (self.tolerance * long / short).clamp(0.5, 1.0)
+        };
+        let target = estimated * gradient + estimated.sqrt();
+        let estimated = estimated * (1.0 - self.smoothing) + target * self.smoothing;
+        self.estimated = Some(estimated);
+        estimated as usize
+    }
+}
+
+struct State {
+    limit: AtomicUsize,
+    in_flight: AtomicUsize,
+    rejected: AtomicU64,
+    algorithm: Mutex<Box<dyn LimitAlgorithm>>,
+}
+
+struct InFlightGuard<'a>(&'a AtomicUsize);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The metrics of the [`AdaptiveConcurrencyLimit`] middleware.
+#[derive(Clone)]
+pub struct AdaptiveConcurrencyLimitMetrics(Arc<State>);
+
+impl AdaptiveConcurrencyLimitMetrics {
+    /// Returns the current concurrency limit.
+    pub fn limit(&self) -> usize {
+        self.0.limit.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of the requests being processed.
+    pub fn in_flight(&self) -> usize {
+        self.0.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of the rejected requests.
+    pub fn rejected(&self) -> u64 {
+        self.0.rejected.load(Ordering::Relaxed)
+    }
+}
+
+/// Middleware for limiting the number of the requests processed concurrently,
+/// with a limit that is adjusted by the observed latency.
+///
+/// The requests that exceed the limit are rejected immediately with
+/// `503 Service Unavailable` and the `Retry-After` header. The requests that
+/// fail with a server error are considered to be dropped, which decreases the
+/// limit.
+///
+/// The limit is shared by all the endpoints that this middleware is applied
+/// to.
+///
+/// # Errors
+///
+/// - [`ConcurrencyLimitError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     middleware::{AdaptiveConcurrencyLimit, Gradient},
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// async fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let limit = AdaptiveConcurrencyLimit::new(Gradient::new())
+///     .initial_limit(20)
+///     .max_limit(500);
+/// let metrics = limit.metrics();
+/// let app = Route::new().at("/", index).with(limit);
+///
+/// assert_eq!(metrics.limit(), 20);
+/// ```
+pub struct AdaptiveConcurrencyLimit {
+    state: Arc<State>,
+    min_limit: usize,
+    max_limit: usize,
+    retry_after: Duration,
+}
+
+impl AdaptiveConcurrencyLimit {
+    /// Create `AdaptiveConcurrencyLimit` middleware with the algorithm.
+    pub fn new(algorithm: impl LimitAlgorithm) -> Self {
+        Self {
+            state: Arc::new(State {
+                limit: AtomicUsize::new(20),
+                in_flight: AtomicUsize::new(0),
+                rejected: AtomicU64::new(0),
+                algorithm: Mutex::new(Box::new(algorithm)),
+            }),
+            min_limit: 1,
+            max_limit: 1000,
+            retry_after: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets the initial concurrency limit.
+    ///
+    /// Default is `20`.
+    #[must_use]
+    pub fn initial_limit(self, limit: usize) -> Self {
+        self.state.limit.store(limit, Ordering::SeqCst);
+        self
+    }
+
+    /// Sets the minimum concurrency limit.
+    ///
+    /// Default is `1`.
+    #[must_use]
+    pub fn min_limit(self, limit: usize) -> Self {
+        Self {
+            min_limit: limit.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the maximum concurrency limit.
+    ///
+    /// Default is `1000`.
+    #[must_use]
+    pub fn max_limit(self, limit: usize) -> Self {
+        Self {
+            max_limit: limit,
+            ..self
+        }
+    }
+
+    /// Sets the value of the `Retry-After` header of the rejected requests.
+    ///
+    /// Default is `1s`.
+    #[must_use]
+    pub fn retry_after(self, retry_after: Duration) -> Self {
+        Self {
+            retry_after,
+            ..self
+        }
+    }
+
+    /// Returns the metrics of this middleware.
+    pub fn metrics(&self) -> AdaptiveConcurrencyLimitMetrics {
+        AdaptiveConcurrencyLimitMetrics(self.state.clone())
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for AdaptiveConcurrencyLimit {
+    type Output = AdaptiveConcurrencyLimitEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        let limit = self.state.limit.load(Ordering::SeqCst);
+        self.state.limit.store(
+            limit.clamp(self.min_limit, self.max_limit.max(self.min_limit)),
+            Ordering::SeqCst,
+        );
+        AdaptiveConcurrencyLimitEndpoint {
+            inner: ep,
+            state: self.state.clone(),
+            min_limit: self.min_limit,
+            max_limit: self.max_limit.max(self.min_limit),
+            retry_after: self.retry_after,
+        }
+    }
+}
+
+/// Endpoint for the AdaptiveConcurrencyLimit middleware.
+pub struct AdaptiveConcurrencyLimitEndpoint<E> {
+    inner: E,
+    state: Arc<State>,
+    min_limit: usize,
+    max_limit: usize,
+    retry_after: Duration,
+}
+
+impl<E: Endpoint> Endpoint for AdaptiveConcurrencyLimitEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let limit = self.state.limit.load(Ordering::SeqCst);
+        let Ok(in_flight) =
+            self.state
+                .in_flight
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |in_flight| {
+                    (in_flight < limit).then_some(in_flight + 1)
+                })
+        else {
+            self.state.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(ConcurrencyLimitError::LimitExceeded {
+                retry_after: self.retry_after,
+            }
+            .into());
+        };
+        let _guard = InFlightGuard(&self.state.in_flight);
+
+        let start = Instant::now();
+        let res = self.inner.call(req).await.map(IntoResponse::into_response);
+        let status = match &res {
+            Ok(resp) => resp.status(),
+            Err(err) => err.status(),
+        };
+        let sample = LimitSample {
+            latency: start.elapsed(),
+            in_flight: in_flight + 1,
+            dropped: status.is_server_error(),
+        };
+
+        let mut algorithm = self.state.algorithm.lock();
+        let limit = self.state.limit.load(Ordering::SeqCst);
+        let limit = algorithm
+            .update(limit, &sample)
+            .clamp(self.min_limit, self.max_limit);
+        self.state.limit.store(limit, Ordering::SeqCst);
+        drop(algorithm);
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::join_all;
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{endpoint::make_sync, handler, test::TestClient, EndpointExt};
+
+    fn sample(latency_ms: u64, in_flight: usize, dropped: bool) -> LimitSample {
+        LimitSample {
+            latency: Duration::from_millis(latency_ms),
+            in_flight,
+            dropped,
+        }
+    }
+
+    #[test]
+    fn aimd() {
+        let mut aimd = Aimd::new().timeout(Duration::from_millis(100));
+        assert_eq!(aimd.update(10, &sample(10, 5, false)), 11);
+        assert_eq!(aimd.update(10, &sample(10, 2, false)), 10);
+        assert_eq!(aimd.update(10, &sample(10, 5, true)), 9);
+        assert_eq!(aimd.update(10, &sample(200, 5, false)), 9);
+    }
+
+    #[test]
+    fn gradient() {
+        let mut gradient = Gradient::new().smoothing(1.0);
+        let mut limit = 16;
+        for _ in 0..10 {
+            limit = gradient.update(limit, &sample(10, limit, false));
+        }
+        assert!(limit > 16);
+
+        let before = limit;
+        for _ in 0..5 {
+            limit = gradient.update(limit, &sample(100, limit, false));
+        }
+        assert!(limit < before);
+
+        assert_eq!(gradient.update(limit, &sample(10, 1, false)), limit);
+    }
+
+    #[test]
+    fn gradient_clamped() {
+        let mut gradient = Gradient::new();
+        let mut limit = 4;
+        for _ in 0..100 {
+            limit = gradient
+                .update(limit, &sample(10, limit, false))
+                .clamp(1, 5);
+        }
+        assert_eq!(limit, 5);
+
+        for _ in 0..20 {
+            limit = gradient
+                .update(limit, &sample(1000, limit, false))
+                .clamp(1, 5);
+        }
+        assert!(limit < 5);
+    }
+
+    #[tokio::test]
+    async fn limit() {
+        #[handler(internal)]
+        async fn index() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let limit = AdaptiveConcurrencyLimit::new(Aimd::new())
+            .initial_limit(2)
+            .max_limit(3);
+        let metrics = limit.metrics();
+        let cli = TestClient::new(index.with(limit));
+
+        let resps = join_all((0..3).map(|_| cli.get("/").send())).await;
+        resps[0].assert_status_is_ok();
+        resps[1].assert_status_is_ok();
+        resps[2].assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        resps[2].assert_header("retry-after", "1");
+        assert_eq!(metrics.rejected(), 1);
+        assert_eq!(metrics.in_flight(), 0);
+        assert_eq!(metrics.limit(), 3);
+
+        let resps = join_all((0..3).map(|_| cli.get("/").send())).await;
+        assert!(resps.iter().all(|resp| resp.0.status() == StatusCode::OK));
+        assert_eq!(metrics.limit(), 3);
+    }
+
+    #[tokio::test]
+    async fn dropped() {
+        let limit = AdaptiveConcurrencyLimit::new(Aimd::new().backoff_ratio(0.5)).initial_limit(8);
+        let metrics = limit.metrics();
+        let cli = TestClient::new(make_sync(|_| StatusCode::BAD_GATEWAY).with(limit));
+
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_GATEWAY);
+        assert_eq!(metrics.limit(), 4);
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_GATEWAY);
+        assert_eq!(metrics.limit(), 2);
+    }
+}
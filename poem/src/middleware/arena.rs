@@ -0,0 +1,182 @@
+use bumpalo::Bump;
+use parking_lot::Mutex;
+
+use crate::{web::RequestArena, Endpoint, Middleware, Request, Result};
+
+/// Middleware for providing a [`RequestArena`] to each request.
+///
+/// The arenas are kept in a pool. After the response is returned, the arena
+/// of the request is reset and returned to the pool, so the memory is reused
+/// by the next requests instead of being allocated again. If the arena is
+/// still used, for example by a spawned task, it is dropped instead.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler, middleware::Arena, test::TestClient, web::RequestArena, EndpointExt,
+/// };
+///
+/// #[handler]
+/// fn index(arena: &RequestArena) -> String {
+///     arena.with(|bump| poem::web::bumpalo::format!(in bump, "{}-{}", "hello", "world").to_string())
+/// }
+///
+/// let cli = TestClient::new(index.with(Arena::new()));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// cli.get("/").send().await.assert_text("hello-world").await;
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "arena")))]
+pub struct Arena {
+    capacity: usize,
+    max_pooled: usize,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Arena {
+    /// Create `Arena` middleware.
+    pub fn new() -> Self {
+        Self {
+            capacity: 4096,
+            max_pooled: 256,
+        }
+    }
+
+    /// Sets the initial capacity of the new arenas in bytes.
+    ///
+    /// Default is `4096`.
+    #[must_use]
+    pub fn capacity(self, capacity: usize) -> Self {
+        Self { capacity, ..self }
+    }
+
+    /// Sets the maximum number of the arenas kept in the pool, which is the
+    /// number of the concurrent requests that reuse the arenas.
+    ///
+    /// Default is `256`.
+    #[must_use]
+    pub fn max_pooled(self, max_pooled: usize) -> Self {
+        Self { max_pooled, ..self }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Arena {
+    type Output = ArenaEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ArenaEndpoint {
+            inner: ep,
+            capacity: self.capacity,
+            max_pooled: self.max_pooled,
+            pool: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+/// Endpoint for the `Arena` middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "arena")))]
+pub struct ArenaEndpoint<E> {
+    inner: E,
+    capacity: usize,
+    max_pooled: usize,
+    pool: Mutex<Vec<Bump>>,
+}
+
+impl<E: Endpoint> Endpoint for ArenaEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let bump = self
+            .pool
+            .lock()
+            .pop()
+            .unwrap_or_else(|| Bump::with_capacity(self.capacity));
+        let arena = RequestArena::new(bump);
+        req.extensions_mut().insert(arena.clone());
+
+        let res = self.inner.call(req).await;
+
+        if let Some(mut bump) = arena.into_inner() {
+            bump.reset();
+            let mut pool = self.pool.lock();
+            if pool.len() < self.max_pooled {
+                pool.push(bump);
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, EndpointExt, Response};
+
+    /// Returns the size of the arena before and after allocating 16KiB.
+    #[handler(internal)]
+    async fn alloc(arena: &RequestArena) -> String {
+        let before = arena.allocated_bytes();
+        arena.with(|bump| {
+            bump.alloc_slice_fill_copy(16 * 1024, 0u8);
+        });
+        format!("{before},{}", arena.allocated_bytes())
+    }
+
+    async fn sizes(ep: &impl Endpoint<Output = Response>) -> (usize, usize) {
+        let text = ep.call(Request::default()).await.unwrap();
+        let text = text.into_body().into_string().await.unwrap();
+        let (before, after) = text.split_once(',').unwrap();
+        (before.parse().unwrap(), after.parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn reuse() {
+        let ep = alloc.with(Arena::new().capacity(1024));
+
+        let (before, after) = sizes(&ep).await;
+        assert!(before < 16 * 1024);
+        assert!(after >= 16 * 1024);
+
+        // the grown arena is reused
+        let (before, after) = sizes(&ep).await;
+        assert!(before >= 16 * 1024);
+        assert_eq!(after, before);
+    }
+
+    #[tokio::test]
+    async fn no_pool() {
+        let ep = alloc.with(Arena::new().capacity(1024).max_pooled(0));
+
+        for _ in 0..2 {
+            let (before, _) = sizes(&ep).await;
+            assert!(before < 16 * 1024);
+        }
+    }
+
+    #[tokio::test]
+    async fn in_use() {
+        #[handler(internal)]
+        async fn keep(arena: &RequestArena) -> String {
+            let arena = arena.clone();
+            arena.with(|bump| {
+                bump.alloc_slice_fill_copy(16 * 1024, 0u8);
+            });
+            tokio::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                drop(arena);
+            });
+            String::new()
+        }
+
+        let ep = keep.with(Arena::new().capacity(1024));
+        ep.call(Request::default()).await.unwrap();
+        assert!(ep.pool.lock().is_empty());
+    }
+}
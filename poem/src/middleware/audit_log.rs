@@ -0,0 +1,450 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    io,
+    path::Path,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{fs::File, io::AsyncWriteExt};
+
+use crate::{
+    error::InternalServerError, http::StatusCode, route::PathPattern, Endpoint, FromRequest,
+    IntoResponse, Middleware, Request, RequestBody, Response, Result,
+};
+
+/// The outcome of an audited operation.
+#[cfg_attr(docsrs, doc(cfg(feature = "audit-log")))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    /// The operation succeeded.
+    Success,
+    /// The request was not authenticated or authorized, with the status `401`
+    /// or `403`.
+    Denied,
+    /// The operation failed.
+    Failure,
+}
+
+impl AuditOutcome {
+    fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => AuditOutcome::Denied,
+            _ if status.is_client_error() || status.is_server_error() => AuditOutcome::Failure,
+            _ => AuditOutcome::Success,
+        }
+    }
+}
+
+/// The state of a resource before and after an operation.
+#[cfg_attr(docsrs, doc(cfg(feature = "audit-log")))]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct AuditDiff {
+    /// The state before the operation.
+    pub before: Value,
+    /// The state after the operation.
+    pub after: Value,
+}
+
+/// An event recorded by the [`AuditLog`] middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "audit-log")))]
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct AuditEvent {
+    /// The time when the request was received, in milliseconds since the
+    /// Unix epoch.
+    pub timestamp: u64,
+    /// The authenticated principal.
+    pub principal: Option<String>,
+    /// The operation, defaults to the method and the matched path pattern,
+    /// such as `DELETE /users/:id`.
+    pub operation: String,
+    /// The request method.
+    pub method: String,
+    /// The request path.
+    pub path: String,
+    /// The identifiers of the resources, including the path parameters.
+    pub resources: BTreeMap<String, String>,
+    /// The outcome of the operation.
+    pub outcome: AuditOutcome,
+    /// The response status code.
+    pub status: u16,
+    /// The remote address of the request.
+    pub remote_addr: Option<String>,
+    /// The changes of the resource.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<AuditDiff>,
+    /// The custom fields.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, Value>,
+}
+
+/// Represents a back-end that stores the audit events, such as a file, a
+/// database or a message queue.
+#[cfg_attr(docsrs, doc(cfg(feature = "audit-log")))]
+pub trait AuditSink: Send + Sync + 'static {
+    /// Records an audit event.
+    fn record<'a>(&'a self, event: &'a AuditEvent) -> impl Future<Output = Result<()>> + Send + 'a;
+}
+
+impl<T: AuditSink> AuditSink for Arc<T> {
+    fn record<'a>(&'a self, event: &'a AuditEvent) -> impl Future<Output = Result<()>> + Send + 'a {
+        self.as_ref().record(event)
+    }
+}
+
+/// An audit sink that appends the events to a file as JSON lines.
+#[cfg_attr(docsrs, doc(cfg(feature = "audit-log")))]
+pub struct FileAuditSink {
+    file: tokio::sync::Mutex<File>,
+}
+
+impl FileAuditSink {
+    /// Opens the file in the append mode, it is created if it does not exist.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        Ok(Self {
+            file: tokio::sync::Mutex::new(file),
+        })
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    async fn record<'a>(&'a self, event: &'a AuditEvent) -> Result<()> {
+        let mut line = serde_json::to_vec(event).map_err(InternalServerError)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await.map_err(InternalServerError)?;
+        file.flush().await.map_err(InternalServerError)?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct AuditState {
+    principal: Option<String>,
+    operation: Option<String>,
+    resources: BTreeMap<String, String>,
+    diff: Option<AuditDiff>,
+    fields: BTreeMap<String, Value>,
+}
+
+/// The context of the audit event of the current request, which can be used
+/// to add the details of the operation in the handlers.
+///
+/// The [`AuditLog`] middleware is required.
+#[cfg_attr(docsrs, doc(cfg(feature = "audit-log")))]
+#[derive(Clone)]
+pub struct AuditContext(Arc<Mutex<AuditState>>);
+
+impl AuditContext {
+    /// Sets the principal, which overrides the one determined by the
+    /// middleware.
+    pub fn set_principal(&self, principal: impl Into<String>) {
+        self.0.lock().principal = Some(principal.into());
+    }
+
+    /// Sets the name of the operation.
+    pub fn set_operation(&self, operation: impl Into<String>) {
+        self.0.lock().operation = Some(operation.into());
+    }
+
+    /// Adds an identifier of the resources.
+    pub fn add_resource(&self, name: impl Into<String>, id: impl Into<String>) {
+        self.0.lock().resources.insert(name.into(), id.into());
+    }
+
+    /// Records the state of the resource before and after the operation.
+    pub fn set_diff(&self, before: impl Serialize, after: impl Serialize) {
+        self.0.lock().diff = Some(AuditDiff {
+            before: serde_json::to_value(before).unwrap_or_default(),
+            after: serde_json::to_value(after).unwrap_or_default(),
+        });
+    }
+
+    /// Adds a custom field.
+    pub fn add_field(&self, name: impl Into<String>, value: impl Serialize) {
+        self.0
+            .lock()
+            .fields
+            .insert(name.into(), serde_json::to_value(value).unwrap_or_default());
+    }
+
+    pub(crate) fn add_path_params(&self, params: &[(Arc<str>, String)]) {
+        self.0.lock().resources.extend(
+            params
+                .iter()
+                .filter(|(name, _)| !name.starts_with("--poem-"))
+                .map(|(name, value)| (name.to_string(), value.clone())),
+        );
+    }
+}
+
+impl<'a> FromRequest<'a> for &'a AuditContext {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<AuditContext>()
+            .expect("To use the `AuditContext` extractor, the `AuditLog` middleware is required."))
+    }
+}
+
+type PrincipalFn = Arc<dyn Fn(&Request) -> Option<String> + Send + Sync>;
+
+/// Middleware for recording the audit events of the requests to an
+/// [`AuditSink`].
+///
+/// Each event contains the principal, the operation, the resource
+/// identifiers from the path parameters, and the outcome. Use the
+/// [`&AuditContext`](AuditContext) extractor to add more details, such as the
+/// changes of the resource, in the handlers.
+///
+/// By default, the principal is the subject authenticated by the middlewares
+/// in the `auth` module if the `auth` feature is enabled. The authentication
+/// middleware must run before this middleware, such as
+/// `ep.with(AuditLog::new(sink)).with(BasicAuth::new(users))`.
+///
+/// The errors of the sink are logged and do not affect the responses.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     delete, handler,
+///     middleware::{AuditContext, AuditLog, AuditSink, AuditEvent},
+///     web::Path,
+///     EndpointExt, Route,
+/// };
+///
+/// struct StdoutSink;
+///
+/// impl AuditSink for StdoutSink {
+///     async fn record<'a>(&'a self, event: &'a AuditEvent) -> poem::Result<()> {
+///         println!("{}", serde_json::to_string(event).unwrap());
+///         Ok(())
+///     }
+/// }
+///
+/// #[handler]
+/// fn delete_user(Path(id): Path<u64>, audit: &AuditContext) {
+///     audit.set_diff(serde_json::json!({ "id": id }), serde_json::Value::Null);
+/// }
+///
+/// let app = Route::new()
+///     .at("/users/:id", delete(delete_user))
+///     .with(AuditLog::new(StdoutSink));
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "audit-log")))]
+pub struct AuditLog<S> {
+    sink: Arc<S>,
+    principal: PrincipalFn,
+}
+
+impl<S: AuditSink> AuditLog<S> {
+    /// Create `AuditLog` middleware with the sink.
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink: Arc::new(sink),
+            principal: Arc::new(default_principal),
+        }
+    }
+
+    /// Sets the function to determine the principal of the request.
+    #[must_use]
+    pub fn principal<F>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> Option<String> + Send + Sync + 'static,
+    {
+        Self {
+            principal: Arc::new(f),
+            ..self
+        }
+    }
+}
+
+#[cfg(feature = "auth")]
+fn default_principal(req: &Request) -> Option<String> {
+    crate::auth::subject(req).map(std::borrow::Cow::into_owned)
+}
+
+#[cfg(not(feature = "auth"))]
+fn default_principal(_req: &Request) -> Option<String> {
+    None
+}
+
+impl<S: AuditSink, E: Endpoint> Middleware<E> for AuditLog<S> {
+    type Output = AuditLogEndpoint<S, E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        AuditLogEndpoint {
+            inner: ep,
+            sink: self.sink.clone(),
+            principal: self.principal.clone(),
+        }
+    }
+}
+
+/// Endpoint for the AuditLog middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "audit-log")))]
+pub struct AuditLogEndpoint<S, E> {
+    inner: E,
+    sink: Arc<S>,
+    principal: PrincipalFn,
+}
+
+impl<S: AuditSink, E: Endpoint> Endpoint for AuditLogEndpoint<S, E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let context = AuditContext(Default::default());
+        context.add_path_params(&req.state().match_params);
+        req.extensions_mut().insert(context.clone());
+
+        let principal = (self.principal)(&req);
+        let method = req.method().to_string();
+        let path = req.uri().path().to_string();
+        let remote_addr = req.remote_addr().as_socket_addr().map(ToString::to_string);
+
+        let res = self.inner.call(req).await.map(IntoResponse::into_response);
+        let (status, pattern) = match &res {
+            Ok(resp) => (resp.status(), resp.data::<PathPattern>()),
+            Err(err) => (err.status(), err.data::<PathPattern>()),
+        };
+
+        let state = std::mem::take(&mut *context.0.lock());
+        let event = AuditEvent {
+            timestamp,
+            principal: state.principal.or(principal),
+            operation: state.operation.unwrap_or_else(|| match pattern {
+                Some(pattern) => format!("{method} {}", pattern.0),
+                None => format!("{method} {path}"),
+            }),
+            method,
+            path,
+            resources: state.resources,
+            outcome: AuditOutcome::from_status(status),
+            status: status.as_u16(),
+            remote_addr,
+            diff: state.diff,
+            fields: state.fields,
+        };
+        if let Err(err) = self.sink.record(&event).await {
+            tracing::error!(error = %err, "failed to record the audit event");
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        endpoint::make_sync, get, handler, test::TestClient, web::Path as PathParam, EndpointExt,
+        Route,
+    };
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<AuditEvent>>);
+
+    impl AuditSink for MemorySink {
+        async fn record<'a>(&'a self, event: &'a AuditEvent) -> Result<()> {
+            self.0.lock().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[handler(internal)]
+    fn update_user(PathParam((org, id)): PathParam<(String, u32)>, audit: &AuditContext) {
+        audit.set_diff(
+            serde_json::json!({ "id": id, "org": org, "name": "a" }),
+            serde_json::json!({ "id": id, "org": org, "name": "b" }),
+        );
+        audit.add_field("reason", "rename");
+    }
+
+    #[tokio::test]
+    async fn audit_log() {
+        let sink = Arc::new(MemorySink::default());
+        let app = Route::new()
+            .nest(
+                "/api",
+                Route::new().at("/orgs/:org/users/:id", get(update_user).put(update_user)),
+            )
+            .at("/forbidden", make_sync(|_| StatusCode::FORBIDDEN))
+            .with(
+                AuditLog::new(sink.clone())
+                    .principal(|req| req.header("x-user").map(ToString::to_string)),
+            );
+        let cli = TestClient::new(app);
+
+        cli.put("/api/orgs/acme/users/1")
+            .header("x-user", "alice")
+            .send()
+            .await
+            .assert_status_is_ok();
+        cli.get("/forbidden")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+        cli.get("/missing")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+
+        let events = sink.0.lock();
+        assert_eq!(events.len(), 3);
+
+        assert_eq!(events[0].principal.as_deref(), Some("alice"));
+        assert_eq!(events[0].operation, "PUT /api/orgs/:org/users/:id");
+        assert_eq!(events[0].path, "/api/orgs/acme/users/1");
+        assert_eq!(
+            events[0].resources,
+            BTreeMap::from([
+                ("org".to_string(), "acme".to_string()),
+                ("id".to_string(), "1".to_string())
+            ])
+        );
+        assert_eq!(events[0].outcome, AuditOutcome::Success);
+        assert_eq!(events[0].diff.as_ref().unwrap().after["name"], "b");
+        assert_eq!(events[0].fields["reason"], "rename");
+
+        assert_eq!(events[1].principal, None);
+        assert_eq!(events[1].outcome, AuditOutcome::Denied);
+        assert_eq!(events[1].status, 403);
+
+        assert_eq!(events[2].operation, "GET /missing");
+        assert_eq!(events[2].outcome, AuditOutcome::Failure);
+    }
+
+    #[tokio::test]
+    async fn file_sink() {
+        let path = std::env::temp_dir().join(format!("poem-audit-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let sink = FileAuditSink::open(&path).await.unwrap();
+        let cli = TestClient::new(make_sync(|_| "ok").with(AuditLog::new(sink)));
+        cli.get("/a").send().await.assert_status_is_ok();
+        cli.get("/b").send().await.assert_status_is_ok();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        let event: Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(event["path"], "/b");
+        assert_eq!(event["outcome"], "success");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
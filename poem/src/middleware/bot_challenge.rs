@@ -0,0 +1,700 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use hmac::{Hmac, Mac};
+use rand::{thread_rng, RngCore};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    clock::SharedClock,
+    http::{header, StatusCode},
+    middleware::{CookieJarManager, CookieJarManagerEndpoint},
+    web::{
+        cookie::{Cookie, CookieKey, SameSite},
+        Json,
+    },
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// A challenge which the suspicious clients must solve to pass the
+/// [`BotChallenge`] middleware.
+///
+/// # Example
+///
+/// A challenge which redirects the clients to a captcha page, which sends
+/// them back with a signed token after the captcha is solved.
+///
+/// ```
+/// use poem::{middleware::Challenge, web::Redirect, IntoResponse, Request, Response, Result};
+///
+/// struct CaptchaRedirect;
+///
+/// fn verify_token(token: &str) -> bool {
+///     // verifies the signature of the token
+///     token == "valid"
+/// }
+///
+/// impl Challenge for CaptchaRedirect {
+///     async fn verify(&self, req: &Request) -> Result<bool> {
+///         Ok(req.header("x-captcha-token").is_some_and(verify_token))
+///     }
+///
+///     fn challenge(&self, req: &Request) -> Response {
+///         Redirect::see_other(format!("/captcha?return_to={}", req.uri().path())).into_response()
+///     }
+/// }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "bot-challenge")))]
+pub trait Challenge: Send + Sync + 'static {
+    /// Returns `true` if the request contains a valid solution of the
+    /// challenge.
+    fn verify<'a>(&'a self, req: &'a Request) -> impl Future<Output = Result<bool>> + Send + 'a;
+
+    /// Returns the response which asks the client to solve the challenge.
+    fn challenge(&self, req: &Request) -> Response;
+}
+
+/// The default heuristics of the [`BotChallenge`] middleware.
+///
+/// A request is suspicious if it has no `User-Agent` or `Accept-Language`
+/// header, or the user agent looks like an HTTP library or a crawler.
+pub fn is_suspicious(req: &Request) -> bool {
+    const AUTOMATED: &[&str] = &[
+        "bot",
+        "crawler",
+        "spider",
+        "scrapy",
+        "curl",
+        "wget",
+        "python",
+        "go-http-client",
+        "java/",
+        "okhttp",
+        "headless",
+    ];
+
+    let user_agent = match req.header(header::USER_AGENT) {
+        Some(user_agent) => user_agent.to_ascii_lowercase(),
+        None => return true,
+    };
+    req.header(header::ACCEPT_LANGUAGE).is_none()
+        || AUTOMATED.iter().any(|name| user_agent.contains(name))
+}
+
+type PredicateFn = Arc<dyn Fn(&Request) -> bool + Send + Sync>;
+
+/// Middleware for challenging the suspicious clients, to protect the
+/// endpoints from the scraping abuse.
+///
+/// The clients which are considered suspicious by the heuristics, which is
+/// [`is_suspicious`] by default, receive the response of the
+/// [`Challenge`]. Once a client solves the challenge, it receives a signed
+/// clearance cookie and is not challenged again until the cookie expires.
+///
+/// Use [`BotChallenge::always`] on the routes which must be challenged
+/// regardless of the heuristics.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     get, handler,
+///     http::StatusCode,
+///     middleware::{BotChallenge, ProofOfWork},
+///     test::TestClient,
+///     web::cookie::CookieKey,
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let key = CookieKey::generate();
+/// let app = Route::new()
+///     .at("/", get(index))
+///     .at(
+///         "/search",
+///         get(index).with(BotChallenge::new(ProofOfWork::new(), key.clone()).always()),
+///     )
+///     .with(BotChallenge::new(ProofOfWork::new(), key));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").header("user-agent", "curl/8.0").send().await;
+/// resp.assert_status(StatusCode::FORBIDDEN);
+/// resp.assert_header_exist("x-pow-challenge");
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "bot-challenge")))]
+pub struct BotChallenge<C> {
+    challenge: Arc<C>,
+    key: Arc<CookieKey>,
+    when: PredicateFn,
+    cookie_name: String,
+    clearance_ttl: Duration,
+}
+
+impl<C: Challenge> BotChallenge<C> {
+    /// Create `BotChallenge` middleware with the challenge and the key to
+    /// sign the clearance cookies.
+    pub fn new(challenge: C, key: CookieKey) -> Self {
+        Self {
+            challenge: Arc::new(challenge),
+            key: Arc::new(key),
+            when: Arc::new(is_suspicious),
+            cookie_name: "poem-clearance".to_string(),
+            clearance_ttl: Duration::from_secs(60 * 60),
+        }
+    }
+
+    /// Sets the heuristics which returns `true` if the request should be
+    /// challenged.
+    ///
+    /// Default is [`is_suspicious`].
+    #[must_use]
+    pub fn when(self, f: impl Fn(&Request) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            when: Arc::new(f),
+            ..self
+        }
+    }
+
+    /// Challenges all the clients without a clearance.
+    #[must_use]
+    pub fn always(self) -> Self {
+        self.when(|_| true)
+    }
+
+    /// Sets the name of the clearance cookie.
+    ///
+    /// Default is `poem-clearance`.
+    #[must_use]
+    pub fn cookie_name(self, name: impl Into<String>) -> Self {
+        Self {
+            cookie_name: name.into(),
+            ..self
+        }
+    }
+
+    /// Sets the time for which a client is not challenged again after it
+    /// solved the challenge.
+    ///
+    /// Default is `1h`.
+    #[must_use]
+    pub fn clearance_ttl(self, ttl: Duration) -> Self {
+        Self {
+            clearance_ttl: ttl,
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint, C: Challenge> Middleware<E> for BotChallenge<C> {
+    type Output = CookieJarManagerEndpoint<BotChallengeEndpoint<E, C>>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        CookieJarManager::new().transform(BotChallengeEndpoint {
+            inner: ep,
+            challenge: self.challenge.clone(),
+            key: self.key.clone(),
+            when: self.when.clone(),
+            cookie_name: self.cookie_name.clone(),
+            clearance_ttl: self.clearance_ttl,
+        })
+    }
+}
+
+/// Endpoint for the BotChallenge middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "bot-challenge")))]
+pub struct BotChallengeEndpoint<E, C> {
+    inner: E,
+    challenge: Arc<C>,
+    key: Arc<CookieKey>,
+    when: PredicateFn,
+    cookie_name: String,
+    clearance_ttl: Duration,
+}
+
+impl<E: Endpoint, C: Challenge> Endpoint for BotChallengeEndpoint<E, C> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let now = SharedClock::from_request(&req)
+            .elapsed_since_epoch()
+            .as_secs();
+        let cookie_jar = req.cookie().clone();
+        let jar = cookie_jar.signed_with_key(&self.key);
+
+        // the value of the clearance is its expiration time
+        let cleared = jar
+            .get(&self.cookie_name)
+            .and_then(|cookie| cookie.value_str().parse::<u64>().ok())
+            .is_some_and(|expires| expires > now);
+        if cleared || !(self.when)(&req) {
+            return Ok(self.inner.call(req).await?.into_response());
+        }
+
+        if !self.challenge.verify(&req).await? {
+            return Ok(self.challenge.challenge(&req));
+        }
+
+        let mut cookie = Cookie::new_with_str(
+            &self.cookie_name,
+            (now + self.clearance_ttl.as_secs()).to_string(),
+        );
+        cookie.set_path("/");
+        cookie.set_http_only(true);
+        cookie.set_same_site(SameSite::Lax);
+        cookie.set_max_age(self.clearance_ttl);
+        jar.add(cookie);
+        Ok(self.inner.call(req).await?.into_response())
+    }
+}
+
+/// A proof-of-work challenge, the clients must find a suffix for which the
+/// SHA-256 hash of the challenge has the specified number of leading zero
+/// bits.
+///
+/// The challenges are signed, so no state is stored on the server. The
+/// challenge response is `403 Forbidden` with the challenge in the
+/// `X-PoW-Challenge` header and the JSON body
+/// `{"challenge": string, "difficulty": number}`, and the clients send
+/// `X-PoW-Solution: <challenge>:<suffix>` where
+/// `sha256("<challenge>:<suffix>")` starts with `difficulty` zero bits.
+///
+/// A solved challenge can be reused until it expires.
+#[cfg_attr(docsrs, doc(cfg(feature = "bot-challenge")))]
+pub struct ProofOfWork {
+    secret: Arc<[u8]>,
+    difficulty: u8,
+    ttl: Duration,
+}
+
+impl Default for ProofOfWork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProofOfWork {
+    /// Create a `ProofOfWork` challenge with a random secret.
+    pub fn new() -> Self {
+        let mut secret = [0; 32];
+        thread_rng().fill_bytes(&mut secret);
+        Self {
+            secret: secret.into(),
+            difficulty: 20,
+            ttl: Duration::from_secs(5 * 60),
+        }
+    }
+
+    /// Sets the secret to sign the challenges, which must be the same on all
+    /// the servers behind a load balancer.
+    #[must_use]
+    pub fn secret(self, secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: secret.into().into(),
+            ..self
+        }
+    }
+
+    /// Sets the number of the leading zero bits of the hash, each bit doubles
+    /// the average work of the clients.
+    ///
+    /// Default is `20`.
+    #[must_use]
+    pub fn difficulty(self, bits: u8) -> Self {
+        Self {
+            difficulty: bits,
+            ..self
+        }
+    }
+
+    /// Sets the time after which an unsolved challenge expires.
+    ///
+    /// Default is `5m`.
+    #[must_use]
+    pub fn ttl(self, ttl: Duration) -> Self {
+        Self { ttl, ..self }
+    }
+
+    fn mac(&self, value: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC can take key of any size");
+        mac.update(value.as_bytes());
+        mac
+    }
+
+    /// Returns a new challenge which expires at `now + ttl`.
+    fn new_challenge(&self, now: u64) -> String {
+        let mut nonce = [0; 16];
+        thread_rng().fill_bytes(&mut nonce);
+        let value = format!(
+            "{}.{}.{}",
+            now + self.ttl.as_secs(),
+            self.difficulty,
+            hex::encode(nonce)
+        );
+        let signature = hex::encode(self.mac(&value).finalize().into_bytes());
+        format!("{value}.{signature}")
+    }
+
+    fn verify_solution(&self, solution: &str, now: u64) -> bool {
+        let Some((challenge, _)) = solution.rsplit_once(':') else {
+            return false;
+        };
+        let Some((value, signature)) = challenge.rsplit_once('.') else {
+            return false;
+        };
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        if self.mac(value).verify_slice(&signature).is_err() {
+            return false;
+        }
+
+        let mut parts = value.split('.');
+        let expires = parts.next().and_then(|s| s.parse::<u64>().ok());
+        let difficulty = parts.next().and_then(|s| s.parse::<u32>().ok());
+        match (expires, difficulty) {
+            (Some(expires), Some(difficulty)) if expires > now => {
+                leading_zero_bits(&Sha256::digest(solution.as_bytes())) >= difficulty
+            }
+            _ => false,
+        }
+    }
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+impl Challenge for ProofOfWork {
+    async fn verify(&self, req: &Request) -> Result<bool> {
+        let now = SharedClock::from_request(req)
+            .elapsed_since_epoch()
+            .as_secs();
+        Ok(req
+            .header("x-pow-solution")
+            .is_some_and(|solution| self.verify_solution(solution, now)))
+    }
+
+    fn challenge(&self, req: &Request) -> Response {
+        let now = SharedClock::from_request(req)
+            .elapsed_since_epoch()
+            .as_secs();
+        let challenge = self.new_challenge(now);
+        Json(serde_json::json!({
+            "challenge": challenge,
+            "difficulty": self.difficulty,
+        }))
+        .with_header("x-pow-challenge", challenge)
+        .with_status(StatusCode::FORBIDDEN)
+        .into_response()
+    }
+}
+
+/// A challenge using [Cloudflare Turnstile](https://developers.cloudflare.com/turnstile/).
+///
+/// The challenge response is a page with the Turnstile widget, which reloads
+/// the page with the token in the `cf-turnstile-response` query parameter
+/// once it is solved. The API clients can also send the token in the
+/// `CF-Turnstile-Response` header.
+#[cfg(feature = "turnstile")]
+#[cfg_attr(docsrs, doc(cfg(feature = "turnstile")))]
+pub struct Turnstile {
+    site_key: String,
+    secret: String,
+    verify_url: String,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "turnstile")]
+impl Turnstile {
+    /// Create a `Turnstile` challenge with the site key and the secret key.
+    pub fn new(site_key: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self {
+            site_key: site_key.into(),
+            secret: secret.into(),
+            verify_url: "https://challenges.cloudflare.com/turnstile/v0/siteverify".to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Sets the URL of the siteverify API.
+    #[must_use]
+    pub fn verify_url(self, url: impl Into<String>) -> Self {
+        Self {
+            verify_url: url.into(),
+            ..self
+        }
+    }
+
+    fn token(&self, req: &Request) -> Option<String> {
+        const NAME: &str = "cf-turnstile-response";
+
+        if let Some(token) = req.header(NAME) {
+            return Some(token.to_string());
+        }
+        crate::urlencoded::from_str::<Vec<(String, String)>>(req.uri().query()?)
+            .ok()?
+            .into_iter()
+            .find_map(|(name, value)| (name == NAME).then_some(value))
+    }
+}
+
+#[cfg(feature = "turnstile")]
+impl Challenge for Turnstile {
+    async fn verify(&self, req: &Request) -> Result<bool> {
+        #[derive(serde::Deserialize)]
+        struct VerifyResponse {
+            success: bool,
+        }
+
+        let token = match self.token(req) {
+            Some(token) => token,
+            None => return Ok(false),
+        };
+        let mut form = vec![("secret", self.secret.clone()), ("response", token)];
+        if let Some(addr) = req.remote_addr().as_socket_addr() {
+            form.push(("remoteip", addr.ip().to_string()));
+        }
+
+        let resp = self
+            .client
+            .post(&self.verify_url)
+            .form(&form)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        match resp {
+            Ok(resp) => Ok(resp
+                .json::<VerifyResponse>()
+                .await
+                .is_ok_and(|resp| resp.success)),
+            Err(err) => {
+                tracing::warn!(error = %err, "failed to verify the turnstile token");
+                Ok(false)
+            }
+        }
+    }
+
+    fn challenge(&self, _req: &Request) -> Response {
+        let site_key = serde_json::to_string(&self.site_key).unwrap_or_default();
+        let page = format!(
+            r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Checking your browser</title>
+<script src="https://challenges.cloudflare.com/turnstile/v0/api.js" async defer></script>
+</head>
+<body>
+<div id="turnstile"></div>
+<script>
+window.addEventListener("load", function () {{
+  turnstile.render("#turnstile", {{
+    sitekey: {site_key},
+    callback: function (token) {{
+      var url = new URL(window.location.href);
+      url.searchParams.set("cf-turnstile-response", token);
+      window.location.replace(url.toString());
+    }},
+  }});
+}});
+</script>
+</body>
+</html>"##
+        );
+        Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .content_type("text/html; charset=utf-8")
+            .header(header::CACHE_CONTROL, "no-store")
+            .body(page)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::MockClock, endpoint::make_sync, test::TestClient, web::cookie::CookieJar,
+        EndpointExt,
+    };
+
+    fn solve(challenge: &str, difficulty: u32) -> String {
+        (0u64..)
+            .map(|suffix| format!("{challenge}:{suffix}"))
+            .find(|solution| leading_zero_bits(&Sha256::digest(solution.as_bytes())) >= difficulty)
+            .unwrap()
+    }
+
+    #[test]
+    fn heuristics() {
+        let req = Request::builder()
+            .header("user-agent", "Mozilla/5.0")
+            .header("accept-language", "en")
+            .finish();
+        assert!(!is_suspicious(&req));
+        assert!(is_suspicious(&Request::builder().finish()));
+        assert!(is_suspicious(
+            &Request::builder()
+                .header("user-agent", "Mozilla/5.0")
+                .finish()
+        ));
+        assert!(is_suspicious(
+            &Request::builder()
+                .header("user-agent", "python-requests/2.31")
+                .header("accept-language", "en")
+                .finish()
+        ));
+    }
+
+    #[test]
+    fn proof_of_work() {
+        let pow = ProofOfWork::new().difficulty(8);
+        let challenge = pow.new_challenge(100);
+        let solution = solve(&challenge, 8);
+        assert!(pow.verify_solution(&solution, 100));
+        // expired
+        assert!(!pow.verify_solution(&solution, 100 + 5 * 60));
+        // signed by another secret
+        assert!(!ProofOfWork::new().verify_solution(&solution, 100));
+
+        // the difficulty is signed
+        let forged = challenge.replacen(".8.", ".0.", 1);
+        assert!(!pow.verify_solution(&format!("{forged}:0"), 100));
+        assert!(!pow.verify_solution("invalid", 100));
+    }
+
+    #[tokio::test]
+    async fn bot_challenge() {
+        let clock = MockClock::new();
+        let key = CookieKey::generate();
+        let ep = make_sync(|_| "hello")
+            .with(
+                BotChallenge::new(ProofOfWork::new().difficulty(8), key.clone())
+                    .clearance_ttl(Duration::from_secs(60)),
+            )
+            .data(SharedClock::new(clock.clone()));
+        let cli = TestClient::new(ep);
+
+        let resp = cli
+            .get("/")
+            .header("user-agent", "Mozilla/5.0")
+            .header("accept-language", "en")
+            .send()
+            .await;
+        resp.assert_text("hello").await;
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::FORBIDDEN);
+        let challenge = resp.0.headers()["x-pow-challenge"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let json = resp.json().await;
+        json.value().object().get("difficulty").assert_i64(8);
+
+        let resp = cli
+            .get("/")
+            .header("x-pow-solution", solve(&challenge, 8))
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let set_cookie = resp.0.headers()[header::SET_COOKIE].to_str().unwrap();
+        let clearance = set_cookie.split(';').next().unwrap().to_string();
+        resp.assert_text("hello").await;
+
+        let resp = cli.get("/").header(header::COOKIE, &clearance).send().await;
+        resp.assert_text("hello").await;
+
+        // the clearance expires
+        clock.advance(Duration::from_secs(60));
+        let resp = cli.get("/").header(header::COOKIE, &clearance).send().await;
+        resp.assert_status(StatusCode::FORBIDDEN);
+
+        // the clearance is signed
+        let jar = CookieJar::default();
+        jar.add(Cookie::new_with_str("poem-clearance", u64::MAX.to_string()));
+        let forged = jar.get("poem-clearance").unwrap();
+        let resp = cli
+            .get("/")
+            .header(
+                header::COOKIE,
+                format!("poem-clearance={}", forged.value_str()),
+            )
+            .send()
+            .await;
+        resp.assert_status(StatusCode::FORBIDDEN);
+    }
+
+    #[cfg(feature = "turnstile")]
+    #[tokio::test]
+    async fn turnstile() {
+        use crate::{
+            handler,
+            listener::{Acceptor, Listener, TcpListener},
+            web::Form,
+            Server,
+        };
+
+        #[derive(serde::Deserialize)]
+        struct Params {
+            secret: String,
+            response: String,
+        }
+
+        #[handler(internal)]
+        fn siteverify(Form(params): Form<Params>) -> Json<serde_json::Value> {
+            Json(serde_json::json!({
+                "success": params.secret == "secret" && params.response == "a token",
+            }))
+        }
+
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor.local_addr()[0].as_socket_addr().cloned().unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(siteverify));
+
+        let turnstile = Turnstile::new("site key", "secret").verify_url(format!("http://{addr}/"));
+        let ep = make_sync(|_| "hello")
+            .with(BotChallenge::new(turnstile, CookieKey::generate()).always());
+        let cli = TestClient::new(ep);
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::FORBIDDEN);
+        resp.assert_content_type("text/html; charset=utf-8");
+        assert!(resp
+            .0
+            .into_body()
+            .into_string()
+            .await
+            .unwrap()
+            .contains("\"site key\""));
+
+        cli.get("/")
+            .query("cf-turnstile-response", &"a token")
+            .send()
+            .await
+            .assert_text("hello")
+            .await;
+        cli.get("/")
+            .header("cf-turnstile-response", "invalid")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+}
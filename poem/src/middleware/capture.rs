@@ -0,0 +1,410 @@
+use std::{
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use hyper::body::Frame;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    body::BoxBody,
+    http::{header, HeaderName},
+    Body, Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// A request recorded by the [`Capture`] middleware, it is stored as JSON and
+/// can be replayed with
+/// [`TestClient::replay`](crate::test::TestClient::replay).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedRequest {
+    /// The time when the request was received, in milliseconds since the
+    /// Unix epoch.
+    pub timestamp: u64,
+    /// The request method.
+    pub method: String,
+    /// The original request uri, including the query string.
+    pub uri: String,
+    /// The request headers, the redacted values are replaced with
+    /// `[redacted]`.
+    pub headers: Vec<(String, String)>,
+    /// The request body, encoded as base64 if it is not UTF-8.
+    pub body: String,
+    /// Whether the body is encoded as base64.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub body_base64: bool,
+    /// Whether the body is incomplete, because it exceeds the size limit or
+    /// is not read completely by the endpoint.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl CapturedRequest {
+    /// Loads a captured request from the file.
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    /// Returns the decoded body.
+    pub fn body_bytes(&self) -> std::io::Result<Vec<u8>> {
+        if self.body_base64 {
+            STANDARD
+                .decode(&self.body)
+                .map_err(|err| IoError::new(std::io::ErrorKind::InvalidData, err))
+        } else {
+            Ok(self.body.clone().into_bytes())
+        }
+    }
+}
+
+type FilterFn = Arc<dyn Fn(&Request) -> bool + Send + Sync>;
+
+/// The sequence number of the captured requests, to name the files uniquely.
+static SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Middleware which records the requests to a directory, so the bugs in the
+/// production can be reproduced locally with
+/// [`TestClient::replay`](crate::test::TestClient::replay).
+///
+/// Each request is written as a [`CapturedRequest`] to a JSON file, named
+/// with the timestamp and a sequence number, after the endpoint returns. The
+/// body is recorded while it is read by the endpoint, up to the size limit.
+///
+/// The values of the `Authorization`, `Proxy-Authorization` and `Cookie`
+/// headers are redacted by default, use [`Capture::redact_headers`] to change
+/// the headers.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, middleware::Capture, EndpointExt, Request};
+///
+/// #[handler]
+/// fn index(body: String) -> String {
+///     body
+/// }
+///
+/// let app = index.with(
+///     Capture::new("./captures")
+///         .max_body_size(1024 * 1024)
+///         .filter(|req: &Request| req.uri().path().starts_with("/api")),
+/// );
+/// ```
+pub struct Capture {
+    dir: PathBuf,
+    max_body_size: usize,
+    redact_headers: Vec<HeaderName>,
+    filter: Option<FilterFn>,
+}
+
+impl Capture {
+    /// Create a new `Capture` middleware which writes the requests to the
+    /// directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_body_size: 64 * 1024,
+            redact_headers: vec![
+                header::AUTHORIZATION,
+                header::PROXY_AUTHORIZATION,
+                header::COOKIE,
+            ],
+            filter: None,
+        }
+    }
+
+    /// Sets the maximum size of the recorded body, the rest of the body is
+    /// not recorded.
+    ///
+    /// Default is `64KiB`.
+    #[must_use]
+    pub fn max_body_size(self, size: usize) -> Self {
+        Self {
+            max_body_size: size,
+            ..self
+        }
+    }
+
+    /// Sets the headers whose values are redacted.
+    #[must_use]
+    pub fn redact_headers<I, K>(self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: TryInto<HeaderName>,
+    {
+        Self {
+            redact_headers: headers
+                .into_iter()
+                .filter_map(|key| key.try_into().ok())
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Uses a closure to determine if a request is recorded.
+    #[must_use]
+    pub fn filter(self, predicate: impl Fn(&Request) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            filter: Some(Arc::new(predicate)),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Capture {
+    type Output = CaptureEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        CaptureEndpoint {
+            inner: ep,
+            dir: self.dir.clone(),
+            max_body_size: self.max_body_size,
+            redact_headers: self.redact_headers.clone(),
+            filter: self.filter.clone(),
+        }
+    }
+}
+
+/// Endpoint for the `Capture` middleware.
+pub struct CaptureEndpoint<E> {
+    inner: E,
+    dir: PathBuf,
+    max_body_size: usize,
+    redact_headers: Vec<HeaderName>,
+    filter: Option<FilterFn>,
+}
+
+impl<E> CaptureEndpoint<E> {
+    async fn write(&self, captured: &CapturedRequest) -> std::io::Result<PathBuf> {
+        let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+        let path = self
+            .dir
+            .join(format!("{}-{:06}.json", captured.timestamp, seq));
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(&path, serde_json::to_vec_pretty(captured)?).await?;
+        Ok(path)
+    }
+}
+
+impl<E: Endpoint> Endpoint for CaptureEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if self.filter.as_ref().is_some_and(|filter| !filter(&req)) {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let method = req.method().to_string();
+        let uri = req.original_uri().to_string();
+        let headers = req
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.redact_headers.contains(name) {
+                    "[redacted]".to_string()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.to_string(), value)
+            })
+            .collect();
+
+        let recorded = Arc::new(Mutex::new(Recorded::default()));
+        let body = req.take_body();
+        req.set_body(Body(BoxBody::new(RecordBody {
+            inner: body.0,
+            recorded: recorded.clone(),
+            max_size: self.max_body_size,
+        })));
+
+        let res = self.inner.call(req).await.map(IntoResponse::into_response);
+
+        let recorded = std::mem::take(&mut *recorded.lock());
+        let (body, body_base64) = match String::from_utf8(recorded.data) {
+            Ok(body) => (body, false),
+            Err(err) => (STANDARD.encode(err.into_bytes()), true),
+        };
+        let captured = CapturedRequest {
+            timestamp,
+            method,
+            uri,
+            headers,
+            body,
+            body_base64,
+            truncated: recorded.truncated || !recorded.complete,
+        };
+        match self.write(&captured).await {
+            Ok(path) => tracing::debug!(path = %path.display(), "request captured"),
+            Err(err) => tracing::warn!(error = %err, "failed to write the captured request"),
+        }
+
+        res
+    }
+}
+
+#[derive(Default)]
+struct Recorded {
+    data: Vec<u8>,
+    truncated: bool,
+    complete: bool,
+}
+
+/// A body which records the data while it is read.
+struct RecordBody {
+    inner: BoxBody,
+    recorded: Arc<Mutex<Recorded>>,
+    max_size: usize,
+}
+
+impl hyper::body::Body for RecordBody {
+    type Data = Bytes;
+    type Error = IoError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let res = Pin::new(&mut this.inner).poll_frame(cx);
+        match &res {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let Some(data) = frame.data_ref() {
+                    let mut recorded = this.recorded.lock();
+                    let remaining = this.max_size.saturating_sub(recorded.data.len());
+                    if data.len() > remaining {
+                        recorded.truncated = true;
+                    }
+                    recorded
+                        .data
+                        .extend_from_slice(&data[..data.len().min(remaining)]);
+                }
+            }
+            Poll::Ready(None) => this.recorded.lock().complete = true,
+            Poll::Ready(Some(Err(_))) | Poll::Pending => {}
+        }
+        res
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> hyper::body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, http::StatusCode, test::TestClient, EndpointExt};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("poem-capture-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn captured(dir: &Path) -> Vec<CapturedRequest> {
+        let mut files: Vec<_> = std::fs::read_dir(dir)
+            .map(|entries| entries.map(|entry| entry.unwrap().path()).collect())
+            .unwrap_or_default();
+        files.sort();
+        files
+            .iter()
+            .map(|path| CapturedRequest::load(path).unwrap())
+            .collect()
+    }
+
+    #[handler(internal)]
+    fn echo(req: &Request, body: Vec<u8>) -> Vec<u8> {
+        let mut data = req.original_uri().to_string().into_bytes();
+        data.extend(body);
+        data
+    }
+
+    #[tokio::test]
+    async fn capture_and_replay() {
+        let dir = temp_dir("replay");
+        let cli = TestClient::new(
+            echo.with(Capture::new(&dir).filter(|req| req.uri().path() != "/health")),
+        );
+
+        let resp = cli
+            .post("/a?b=1")
+            .header("x-custom", "1")
+            .header("cookie", "session=secret")
+            .body("hello")
+            .send()
+            .await;
+        resp.assert_text("/a?b=1hello").await;
+        cli.post("/bin").body(vec![0xff, 0x00]).send().await;
+        cli.get("/health").send().await;
+
+        let requests = captured(&dir);
+        assert_eq!(requests.len(), 2);
+        let req = &requests[0];
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.uri, "/a?b=1");
+        assert!(req
+            .headers
+            .contains(&("x-custom".to_string(), "1".to_string())));
+        assert!(req
+            .headers
+            .contains(&("cookie".to_string(), "[redacted]".to_string())));
+        assert_eq!(req.body, "hello");
+        assert!(!req.truncated);
+        assert!(requests[1].body_base64);
+        assert_eq!(requests[1].body_bytes().unwrap(), [0xff, 0x00]);
+
+        let mut files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        let resp = TestClient::new(echo).replay(&files[1]).await;
+        resp.assert_status_is_ok();
+        resp.assert_bytes(b"/bin\xff\x00").await;
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn truncated_body() {
+        #[handler(internal)]
+        fn unread() -> StatusCode {
+            StatusCode::ACCEPTED
+        }
+
+        let dir = temp_dir("truncated");
+        let cli = TestClient::new(echo.with(Capture::new(&dir).max_body_size(3)));
+        cli.post("/")
+            .body("hello")
+            .send()
+            .await
+            .assert_status_is_ok();
+        let cli = TestClient::new(unread.with(Capture::new(&dir)));
+        cli.post("/").body("hello").send().await;
+
+        let requests = captured(&dir);
+        assert_eq!(requests[0].body, "hel");
+        assert!(requests[0].truncated);
+        assert_eq!(requests[1].body, "");
+        assert!(requests[1].truncated);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
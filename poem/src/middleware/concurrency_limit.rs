@@ -0,0 +1,315 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{error::ConcurrencyLimitError, Endpoint, Middleware, Request, Result};
+
+struct State {
+    max_concurrency: usize,
+    semaphore: Arc<Semaphore>,
+    queued: AtomicUsize,
+    rejected: AtomicU64,
+    latency: Mutex<Option<Duration>>,
+}
+
+impl State {
+    fn record_latency(&self, elapsed: Duration) {
+        let mut latency = self.latency.lock();
+        *latency = Some(match *latency {
+            Some(latency) => latency.mul_f64(0.8) + elapsed.mul_f64(0.2),
+            None => elapsed,
+        });
+    }
+}
+
+struct QueueGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The metrics of the [`ConcurrencyLimit`] middleware.
+#[derive(Clone)]
+pub struct ConcurrencyLimitMetrics(Arc<State>);
+
+impl ConcurrencyLimitMetrics {
+    /// Returns the number of the requests being processed.
+    pub fn in_flight(&self) -> usize {
+        self.0.max_concurrency - self.0.semaphore.available_permits()
+    }
+
+    /// Returns the number of the requests waiting in the queue.
+    pub fn queued(&self) -> usize {
+        self.0.queued.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of the rejected requests.
+    pub fn rejected(&self) -> u64 {
+        self.0.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Returns the moving average of the time to process a request.
+    pub fn average_latency(&self) -> Option<Duration> {
+        *self.0.latency.lock()
+    }
+}
+
+/// Middleware for limiting the number of the requests processed concurrently.
+///
+/// The requests that exceed the limit wait in a bounded queue. When the queue
+/// is full, or the request is not expected to be started within the queue
+/// timeout, it is rejected immediately with `503 Service Unavailable` and the
+/// `Retry-After` header.
+///
+/// The limit is shared by all the endpoints that this middleware is applied
+/// to.
+///
+/// # Errors
+///
+/// - [`ConcurrencyLimitError`]
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{handler, middleware::ConcurrencyLimit, EndpointExt, Route};
+///
+/// #[handler]
+/// async fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let limit = ConcurrencyLimit::new(100)
+///     .queue_size(1000)
+///     .queue_timeout(Duration::from_secs(5));
+/// let metrics = limit.metrics();
+/// let app = Route::new().at("/", index).with(limit);
+///
+/// assert_eq!(metrics.queued(), 0);
+/// ```
+pub struct ConcurrencyLimit {
+    state: Arc<State>,
+    queue_size: usize,
+    queue_timeout: Option<Duration>,
+    retry_after: Duration,
+}
+
+impl ConcurrencyLimit {
+    /// Create `ConcurrencyLimit` middleware that processes at most
+    /// `max_concurrency` requests concurrently.
+    pub fn new(max_concurrency: usize) -> Self {
+        Self {
+            state: Arc::new(State {
+                max_concurrency,
+                semaphore: Arc::new(Semaphore::new(max_concurrency)),
+                queued: AtomicUsize::new(0),
+                rejected: AtomicU64::new(0),
+                latency: Mutex::new(None),
+            }),
+            queue_size: max_concurrency,
+            queue_timeout: None,
+            retry_after: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets the maximum number of the requests waiting in the queue.
+    ///
+    /// Default is the maximum concurrency.
+    #[must_use]
+    pub fn queue_size(self, size: usize) -> Self {
+        Self {
+            queue_size: size,
+            ..self
+        }
+    }
+
+    /// Sets the maximum time a request waits in the queue.
+    ///
+    /// The requests whose estimated waiting time, based on the average latency,
+    /// exceeds it are rejected without being queued.
+    #[must_use]
+    pub fn queue_timeout(self, timeout: Duration) -> Self {
+        Self {
+            queue_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Sets the value of the `Retry-After` header of the rejected requests.
+    ///
+    /// Default is `1s`.
+    #[must_use]
+    pub fn retry_after(self, retry_after: Duration) -> Self {
+        Self {
+            retry_after,
+            ..self
+        }
+    }
+
+    /// Returns the metrics of this middleware.
+    pub fn metrics(&self) -> ConcurrencyLimitMetrics {
+        ConcurrencyLimitMetrics(self.state.clone())
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ConcurrencyLimit {
+    type Output = ConcurrencyLimitEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ConcurrencyLimitEndpoint {
+            inner: ep,
+            state: self.state.clone(),
+            queue_size: self.queue_size,
+            queue_timeout: self.queue_timeout,
+            retry_after: self.retry_after,
+        }
+    }
+}
+
+/// Endpoint for the ConcurrencyLimit middleware.
+pub struct ConcurrencyLimitEndpoint<E> {
+    inner: E,
+    state: Arc<State>,
+    queue_size: usize,
+    queue_timeout: Option<Duration>,
+    retry_after: Duration,
+}
+
+impl<E> ConcurrencyLimitEndpoint<E> {
+    fn reject(&self, err: ConcurrencyLimitError) -> ConcurrencyLimitError {
+        self.state.rejected.fetch_add(1, Ordering::Relaxed);
+        err
+    }
+
+    async fn acquire(&self) -> Result<OwnedSemaphorePermit, ConcurrencyLimitError> {
+        if let Ok(permit) = self.state.semaphore.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        let retry_after = self.retry_after;
+        let position = self
+            .state
+            .queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                (queued < self.queue_size).then_some(queued + 1)
+            })
+            .map_err(|_| self.reject(ConcurrencyLimitError::QueueFull { retry_after }))?;
+        let _guard = QueueGuard(&self.state.queued);
+
+        let Some(timeout) = self.queue_timeout else {
+            return Ok(self
+                .state
+                .semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("the semaphore is never closed"));
+        };
+
+        if let Some(latency) = *self.state.latency.lock() {
+            let estimated =
+                latency.mul_f64((position + 1) as f64 / self.state.max_concurrency as f64);
+            if estimated > timeout {
+                return Err(self.reject(ConcurrencyLimitError::QueueTimeout { retry_after }));
+            }
+        }
+
+        match tokio::time::timeout(timeout, self.state.semaphore.clone().acquire_owned()).await {
+            Ok(permit) => Ok(permit.expect("the semaphore is never closed")),
+            Err(_) => Err(self.reject(ConcurrencyLimitError::QueueTimeout { retry_after })),
+        }
+    }
+}
+
+impl<E: Endpoint> Endpoint for ConcurrencyLimitEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let permit = self.acquire().await?;
+        let start = Instant::now();
+        let res = self.inner.call(req).await;
+        self.state.record_latency(start.elapsed());
+        drop(permit);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::join_all;
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[handler(internal)]
+    async fn index() {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    #[tokio::test]
+    async fn queue_full() {
+        let limit = ConcurrencyLimit::new(1)
+            .queue_size(1)
+            .retry_after(Duration::from_millis(1500));
+        let metrics = limit.metrics();
+        let cli = TestClient::new(index.with(limit));
+
+        let resps = join_all((0..3).map(|_| cli.get("/").send())).await;
+        let mut statuses = resps.iter().map(|resp| resp.0.status()).collect::<Vec<_>>();
+        statuses.sort();
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::SERVICE_UNAVAILABLE
+            ]
+        );
+        let rejected = resps
+            .iter()
+            .find(|resp| resp.0.status() == StatusCode::SERVICE_UNAVAILABLE)
+            .unwrap();
+        rejected.assert_header("retry-after", "2");
+
+        assert_eq!(metrics.rejected(), 1);
+        assert_eq!(metrics.in_flight(), 0);
+        assert_eq!(metrics.queued(), 0);
+        assert!(metrics.average_latency().unwrap() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn queue_timeout() {
+        let limit = ConcurrencyLimit::new(1).queue_timeout(Duration::from_millis(20));
+        let metrics = limit.metrics();
+        let cli = TestClient::new(index.with(limit));
+
+        // waits in the queue until timeout
+        let resps = join_all((0..2).map(|_| cli.get("/").send())).await;
+        assert_eq!(resps[0].0.status(), StatusCode::OK);
+        resps[1].assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(metrics.rejected(), 1);
+
+        // rejected immediately with the estimated waiting time
+        let (first, second) = tokio::join!(cli.get("/").send(), async {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            let start = Instant::now();
+            let resp = cli.get("/").send().await;
+            (resp, start.elapsed())
+        });
+        first.assert_status_is_ok();
+        second.0.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert!(second.1 < Duration::from_millis(20));
+        assert_eq!(metrics.rejected(), 2);
+    }
+}
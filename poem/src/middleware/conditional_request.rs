@@ -0,0 +1,256 @@
+use std::{future::Future, sync::Arc};
+
+use headers::{ETag, HeaderMapExt};
+
+use crate::{
+    error::PreconditionError,
+    http::{Method, StatusCode},
+    web::Preconditions,
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// Represents a way to get the entity tag of the current representation of
+/// the resource targeted by a request.
+pub trait ResourceVersion: Send + Sync + 'static {
+    /// Returns the entity tag of the resource, or `None` if it does not exist.
+    fn etag<'a>(
+        &'a self,
+        req: &'a Request,
+    ) -> impl Future<Output = Result<Option<ETag>>> + Send + 'a;
+}
+
+/// Middleware for the conditional requests with the `If-Match` and
+/// `If-None-Match` headers.
+///
+/// For the `PUT`, `PATCH` and `DELETE` requests, the entity tag of the current
+/// resource is resolved with the [`ResourceVersion`], and the request is
+/// rejected with `412 Precondition Failed` if the preconditions fail, or with
+/// `428 Precondition Required` if the `If-Match` header is required but
+/// missing.
+///
+/// For the `GET` and `HEAD` requests, the response with an `ETag` header, such
+/// as [`TaggedJson`](crate::web::TaggedJson), is replaced with `304 Not
+/// Modified` if it matches the `If-None-Match` header.
+///
+/// # Errors
+///
+/// - [`PreconditionError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     error::{InternalServerError, NotFoundError},
+///     get, handler,
+///     middleware::{ConditionalRequest, ResourceVersion},
+///     web::{headers::ETag, json_etag, Path, TaggedJson},
+///     EndpointExt, Request, Result, Route,
+/// };
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     id: u64,
+///     name: String,
+/// }
+///
+/// fn load_user(id: u64) -> Option<User> {
+///     Some(User {
+///         id,
+///         name: "a".to_string(),
+///     })
+/// }
+///
+/// struct UserVersion;
+///
+/// impl ResourceVersion for UserVersion {
+///     async fn etag<'a>(&'a self, req: &'a Request) -> Result<Option<ETag>> {
+///         let id = req.path_params::<u64>()?;
+///         load_user(id)
+///             .map(|user| json_etag(&user).map_err(InternalServerError))
+///             .transpose()
+///     }
+/// }
+///
+/// #[handler]
+/// fn get_user(Path(id): Path<u64>) -> Result<TaggedJson<User>> {
+///     load_user(id)
+///         .map(TaggedJson)
+///         .ok_or_else(|| NotFoundError.into())
+/// }
+///
+/// #[handler]
+/// fn update_user(Path(id): Path<u64>) {}
+///
+/// let app = Route::new().at(
+///     "/users/:id",
+///     get(get_user)
+///         .put(update_user)
+///         .with(ConditionalRequest::new(UserVersion).require_if_match()),
+/// );
+/// ```
+pub struct ConditionalRequest<R> {
+    version: Arc<R>,
+    require_if_match: bool,
+}
+
+impl<R: ResourceVersion> ConditionalRequest<R> {
+    /// Create `ConditionalRequest` middleware with the resource version.
+    pub fn new(version: R) -> Self {
+        Self {
+            version: Arc::new(version),
+            require_if_match: false,
+        }
+    }
+
+    /// Rejects the `PUT`, `PATCH` and `DELETE` requests without the `If-Match`
+    /// header with `428 Precondition Required`.
+    #[must_use]
+    pub fn require_if_match(self) -> Self {
+        Self {
+            require_if_match: true,
+            ..self
+        }
+    }
+}
+
+impl<R: ResourceVersion, E: Endpoint> Middleware<E> for ConditionalRequest<R> {
+    type Output = ConditionalRequestEndpoint<R, E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ConditionalRequestEndpoint {
+            inner: ep,
+            version: self.version.clone(),
+            require_if_match: self.require_if_match,
+        }
+    }
+}
+
+/// Endpoint for the ConditionalRequest middleware.
+pub struct ConditionalRequestEndpoint<R, E> {
+    inner: E,
+    version: Arc<R>,
+    require_if_match: bool,
+}
+
+impl<R: ResourceVersion, E: Endpoint> Endpoint for ConditionalRequestEndpoint<R, E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let preconditions = Preconditions::from_headers(req.headers());
+        let method = req.method().clone();
+
+        if matches!(method, Method::PUT | Method::PATCH | Method::DELETE) {
+            if self.require_if_match && !preconditions.has_if_match() {
+                return Err(PreconditionError::Required.into());
+            }
+            if !preconditions.is_empty() {
+                let current = self.version.etag(&req).await?;
+                preconditions.check(current.as_ref())?;
+            }
+        }
+
+        let resp = self.inner.call(req).await?.into_response();
+        if matches!(method, Method::GET | Method::HEAD) && resp.status().is_success() {
+            if let Some(etag) = resp.headers().typed_get::<ETag>() {
+                if preconditions.is_not_modified(&etag) {
+                    let mut not_modified = Response::builder()
+                        .status(StatusCode::NOT_MODIFIED)
+                        .finish();
+                    not_modified.headers_mut().typed_insert(etag);
+                    return Ok(not_modified);
+                }
+            }
+        }
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::{IfMatch, IfNoneMatch};
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::{
+        get, handler,
+        test::TestClient,
+        web::{json_etag, Data, Json, TaggedJson},
+        EndpointExt,
+    };
+
+    type Store = Arc<Mutex<Option<String>>>;
+
+    struct StoreVersion(Store);
+
+    impl ResourceVersion for StoreVersion {
+        async fn etag<'a>(&'a self, _req: &'a Request) -> Result<Option<ETag>> {
+            Ok(self
+                .0
+                .lock()
+                .as_ref()
+                .map(|value| json_etag(value).unwrap()))
+        }
+    }
+
+    #[handler(internal)]
+    fn get_value(store: Data<&Store>) -> Response {
+        match store.lock().clone() {
+            Some(value) => TaggedJson(value).into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    #[handler(internal)]
+    fn put_value(store: Data<&Store>, Json(value): Json<String>) {
+        *store.lock() = Some(value);
+    }
+
+    #[tokio::test]
+    async fn conditional_request() {
+        let store: Store = Arc::new(Mutex::new(Some("a".to_string())));
+        let cli = TestClient::new(
+            get(get_value)
+                .put(put_value)
+                .with(ConditionalRequest::new(StoreVersion(store.clone())).require_if_match())
+                .data(store.clone()),
+        );
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        let etag = resp.0.headers().typed_get::<ETag>().unwrap();
+
+        let resp = cli
+            .get("/")
+            .typed_header(IfNoneMatch::from(etag.clone()))
+            .send()
+            .await;
+        resp.assert_status(StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.0.headers().typed_get::<ETag>(), Some(etag.clone()));
+
+        cli.put("/")
+            .body_json(&"b")
+            .send()
+            .await
+            .assert_status(StatusCode::PRECONDITION_REQUIRED);
+        cli.put("/")
+            .typed_header(IfMatch::from(etag.clone()))
+            .body_json(&"b")
+            .send()
+            .await
+            .assert_status_is_ok();
+        cli.put("/")
+            .typed_header(IfMatch::from(etag.clone()))
+            .body_json(&"c")
+            .send()
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+        assert_eq!(store.lock().as_deref(), Some("b"));
+
+        cli.get("/")
+            .typed_header(IfNoneMatch::from(etag))
+            .send()
+            .await
+            .assert_status_is_ok();
+    }
+}
@@ -0,0 +1,591 @@
+use std::{fmt::Write, io};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures_util::StreamExt;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::{
+    error::ContentDigestError, Body, Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+const CONTENT_DIGEST: HeaderName = HeaderName::from_static("content-digest");
+const REPR_DIGEST: HeaderName = HeaderName::from_static("repr-digest");
+const WANT_CONTENT_DIGEST: HeaderName = HeaderName::from_static("want-content-digest");
+const WANT_REPR_DIGEST: HeaderName = HeaderName::from_static("want-repr-digest");
+
+/// A hashing algorithm of the [`ContentDigest`] middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "content-digest")))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum DigestAlgorithm {
+    /// `sha-256`
+    Sha256,
+    /// `sha-512`
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    /// Returns the name of the algorithm which is used in the headers.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha256 => "sha-256",
+            DigestAlgorithm::Sha512 => "sha-512",
+        }
+    }
+
+    /// Returns the digest of the data.
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha-256" => Some(DigestAlgorithm::Sha256),
+            "sha-512" => Some(DigestAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    fn hasher(&self) -> Hasher {
+        match self {
+            DigestAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            DigestAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+        }
+    }
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha512(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+/// Iterates the members of a structured field dictionary, without the
+/// parameters.
+fn dictionary(value: &str) -> impl Iterator<Item = (&str, &str)> {
+    value.split(',').filter_map(|member| {
+        let member = member.split(';').next().unwrap_or_default().trim();
+        match member.split_once('=') {
+            Some((key, value)) => Some((key.trim(), value.trim())),
+            None if !member.is_empty() => Some((member, "")),
+            None => None,
+        }
+    })
+}
+
+/// Parses the `Content-Digest` or `Repr-Digest` header, the digests with
+/// the unsupported algorithms are ignored.
+fn parse_digests(
+    headers: &HeaderMap,
+    name: &HeaderName,
+    algorithms: &[DigestAlgorithm],
+) -> Result<Vec<(DigestAlgorithm, Vec<u8>)>, ContentDigestError> {
+    let mut digests = Vec::new();
+    for value in headers.get_all(name) {
+        let value = value.to_str().map_err(|_| ContentDigestError::Invalid)?;
+        for (key, value) in dictionary(value) {
+            let value = value
+                .strip_prefix(':')
+                .and_then(|value| value.strip_suffix(':'))
+                .ok_or(ContentDigestError::Invalid)?;
+            let Some(algorithm) = DigestAlgorithm::from_name(key) else {
+                continue;
+            };
+            if algorithms.contains(&algorithm) {
+                let digest = STANDARD
+                    .decode(value)
+                    .map_err(|_| ContentDigestError::Invalid)?;
+                digests.push((algorithm, digest));
+            }
+        }
+    }
+    Ok(digests)
+}
+
+/// Parses the `Want-Content-Digest` or `Want-Repr-Digest` header, and
+/// returns the most preferred algorithm.
+fn parse_want(
+    headers: &HeaderMap,
+    name: &HeaderName,
+    algorithms: &[DigestAlgorithm],
+) -> Option<DigestAlgorithm> {
+    let mut wanted = None;
+    for value in headers.get_all(name) {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        for (key, weight) in dictionary(value) {
+            let (Some(algorithm), Ok(weight)) =
+                (DigestAlgorithm::from_name(key), weight.parse::<u8>())
+            else {
+                continue;
+            };
+            if weight > 0
+                && algorithms.contains(&algorithm)
+                && wanted.map_or(true, |(_, w)| weight > w)
+            {
+                wanted = Some((algorithm, weight));
+            }
+        }
+    }
+    wanted.map(|(algorithm, _)| algorithm)
+}
+
+fn header_value(algorithms: &[DigestAlgorithm], data: &[u8]) -> HeaderValue {
+    let mut value = String::new();
+    for algorithm in algorithms {
+        if !value.is_empty() {
+            value.push_str(", ");
+        }
+        _ = write!(
+            value,
+            "{}=:{}:",
+            algorithm.name(),
+            STANDARD.encode(algorithm.digest(data))
+        );
+    }
+    HeaderValue::try_from(value).expect("valid header value")
+}
+
+/// Middleware for the `Content-Digest` and `Repr-Digest` headers of [RFC 9530](https://www.rfc-editor.org/rfc/rfc9530).
+///
+/// The digests of the incoming requests are verified as the body is read, if
+/// they do not match, the body returns an error at the end of the stream, so
+/// the extractors reading the whole body respond `400 Bad Request`. The
+/// digests with the algorithms which are not enabled are ignored.
+///
+/// The `Content-Digest` header is added to the responses, with the most
+/// preferred algorithm of the `Want-Content-Digest` request header, or all the
+/// enabled algorithms. The `Repr-Digest` header is added if it is enabled with
+/// [`ContentDigest::repr_digest`] or requested with `Want-Repr-Digest`. To
+/// compute the digests, the response body is read into memory.
+///
+/// Because the full representation is not available, the `Repr-Digest` header
+/// is not added to the `206 Partial Content` responses.
+///
+/// # Errors
+///
+/// - [`ContentDigestError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     middleware::{ContentDigest, DigestAlgorithm},
+///     test::TestClient,
+///     EndpointExt,
+/// };
+///
+/// #[handler]
+/// fn index(body: String) -> String {
+///     body
+/// }
+///
+/// let app = index.with(ContentDigest::new().algorithms([DigestAlgorithm::Sha256]));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .post("/")
+///     .header(
+///         "content-digest",
+///         "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:",
+///     )
+///     .body(r#"{"hello": "world"}"#)
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_header(
+///     "content-digest",
+///     "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:",
+/// );
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "content-digest")))]
+pub struct ContentDigest {
+    algorithms: Vec<DigestAlgorithm>,
+    required: bool,
+    verify_requests: bool,
+    digest_responses: bool,
+    repr_digest: bool,
+}
+
+impl Default for ContentDigest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentDigest {
+    /// Create `ContentDigest` middleware.
+    pub fn new() -> Self {
+        Self {
+            algorithms: vec![DigestAlgorithm::Sha256, DigestAlgorithm::Sha512],
+            required: false,
+            verify_requests: true,
+            digest_responses: true,
+            repr_digest: false,
+        }
+    }
+
+    /// Sets the enabled algorithms.
+    ///
+    /// Default is `sha-256` and `sha-512`.
+    #[must_use]
+    pub fn algorithms(self, algorithms: impl IntoIterator<Item = DigestAlgorithm>) -> Self {
+        Self {
+            algorithms: algorithms.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// If `true`, the requests with a body must have a digest with an enabled
+    /// algorithm.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn required(self, required: bool) -> Self {
+        Self { required, ..self }
+    }
+
+    /// If `true`, the digests of the requests are verified.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn verify_requests(self, verify_requests: bool) -> Self {
+        Self {
+            verify_requests,
+            ..self
+        }
+    }
+
+    /// If `true`, the digests are added to the responses.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn digest_responses(self, digest_responses: bool) -> Self {
+        Self {
+            digest_responses,
+            ..self
+        }
+    }
+
+    /// If `true`, the `Repr-Digest` header is added to the responses even if
+    /// it is not requested.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn repr_digest(self, repr_digest: bool) -> Self {
+        Self {
+            repr_digest,
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ContentDigest {
+    type Output = ContentDigestEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ContentDigestEndpoint {
+            inner: ep,
+            algorithms: self.algorithms.clone(),
+            required: self.required,
+            verify_requests: self.verify_requests,
+            digest_responses: self.digest_responses,
+            repr_digest: self.repr_digest,
+        }
+    }
+}
+
+/// Endpoint for the ContentDigest middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "content-digest")))]
+pub struct ContentDigestEndpoint<E> {
+    inner: E,
+    algorithms: Vec<DigestAlgorithm>,
+    required: bool,
+    verify_requests: bool,
+    digest_responses: bool,
+    repr_digest: bool,
+}
+
+impl<E> ContentDigestEndpoint<E> {
+    fn verify_request(&self, req: &mut Request) -> Result<(), ContentDigestError> {
+        let mut digests = parse_digests(req.headers(), &CONTENT_DIGEST, &self.algorithms)?;
+        digests.extend(parse_digests(
+            req.headers(),
+            &REPR_DIGEST,
+            &self.algorithms,
+        )?);
+
+        if digests.is_empty() {
+            let body = req.take_body();
+            let is_empty = body.is_empty();
+            req.set_body(body);
+            if self.required && !is_empty {
+                return Err(ContentDigestError::Missing);
+            }
+            return Ok(());
+        }
+
+        let hashers: Vec<_> = digests
+            .into_iter()
+            .map(|(algorithm, expected)| (algorithm.hasher(), expected))
+            .collect();
+        let stream = req.take_body().into_bytes_stream().boxed();
+        let stream = futures_util::stream::unfold(Some((stream, hashers)), |state| async move {
+            let (mut stream, mut hashers) = state?;
+            match stream.next().await {
+                Some(Ok(data)) => {
+                    for (hasher, _) in &mut hashers {
+                        hasher.update(&data);
+                    }
+                    Some((Ok(data), Some((stream, hashers))))
+                }
+                Some(Err(err)) => Some((Err(err), None)),
+                None => {
+                    if hashers
+                        .into_iter()
+                        .all(|(hasher, expected)| hasher.finalize() == expected)
+                    {
+                        None
+                    } else {
+                        Some((
+                            Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                ContentDigestError::Mismatch,
+                            )),
+                            None,
+                        ))
+                    }
+                }
+            }
+        });
+        req.set_body(Body::from_bytes_stream(stream));
+        Ok(())
+    }
+}
+
+impl<E: Endpoint> Endpoint for ContentDigestEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if self.verify_requests {
+            self.verify_request(&mut req)?;
+        }
+
+        if !self.digest_responses || req.method() == Method::HEAD {
+            return Ok(self.inner.call(req).await?.into_response());
+        }
+
+        let want_content = parse_want(req.headers(), &WANT_CONTENT_DIGEST, &self.algorithms);
+        let want_repr = parse_want(req.headers(), &WANT_REPR_DIGEST, &self.algorithms);
+        let mut resp = self.inner.call(req).await?.into_response();
+
+        let status = resp.status();
+        if status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+            || resp.headers().contains_key(CONTENT_DIGEST)
+        {
+            return Ok(resp);
+        }
+
+        let data = resp.take_body().into_bytes().await?;
+        let content_algorithms = match want_content {
+            Some(algorithm) => vec![algorithm],
+            None => self.algorithms.clone(),
+        };
+        if !content_algorithms.is_empty() {
+            resp.headers_mut()
+                .insert(CONTENT_DIGEST, header_value(&content_algorithms, &data));
+        }
+
+        if status != StatusCode::PARTIAL_CONTENT && !resp.headers().contains_key(REPR_DIGEST) {
+            let repr_algorithms = match want_repr {
+                Some(algorithm) => vec![algorithm],
+                None if self.repr_digest => self.algorithms.clone(),
+                None => Vec::new(),
+            };
+            if !repr_algorithms.is_empty() {
+                resp.headers_mut()
+                    .insert(REPR_DIGEST, header_value(&repr_algorithms, &data));
+            }
+        }
+
+        resp.set_body(data);
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    const HELLO_SHA256: &str = "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:";
+    const HELLO_SHA512: &str = "sha-512=:WZDPaVn/7XgHaAy8pmojAkGWoRx2UFChF41A2svX+TaPm+AbwAgBWnrIiYllu7BNNyealdVLvRwEmTHWXvJwew==:";
+
+    #[handler(internal)]
+    async fn echo(body: String) -> String {
+        body
+    }
+
+    #[tokio::test]
+    async fn verify_request() {
+        let cli = TestClient::new(echo.with(ContentDigest::new().digest_responses(false)));
+
+        for digest in [
+            HELLO_SHA256,
+            HELLO_SHA512,
+            &format!("{HELLO_SHA256}, {HELLO_SHA512}"),
+            &format!("md5=:AAAA:, {HELLO_SHA256};a=1"),
+        ] {
+            cli.post("/")
+                .header("content-digest", digest)
+                .body(r#"{"hello": "world"}"#)
+                .send()
+                .await
+                .assert_text(r#"{"hello": "world"}"#)
+                .await;
+        }
+
+        cli.post("/")
+            .header("repr-digest", HELLO_SHA256)
+            .body(r#"{"hello": "world"}"#)
+            .send()
+            .await
+            .assert_status_is_ok();
+
+        cli.post("/")
+            .header("content-digest", HELLO_SHA256)
+            .body(r#"{"hello": "poem"}"#)
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+
+        cli.post("/")
+            .header(
+                "content-digest",
+                "sha-256=X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=",
+            )
+            .body(r#"{"hello": "world"}"#)
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+
+        // the digests with the disabled algorithms are ignored
+        let cli = TestClient::new(
+            echo.with(
+                ContentDigest::new()
+                    .algorithms([DigestAlgorithm::Sha512])
+                    .digest_responses(false),
+            ),
+        );
+        cli.post("/")
+            .header("content-digest", HELLO_SHA256)
+            .body(r#"{"hello": "poem"}"#)
+            .send()
+            .await
+            .assert_status_is_ok();
+    }
+
+    #[tokio::test]
+    async fn required() {
+        let cli = TestClient::new(echo.with(ContentDigest::new().required(true)));
+
+        cli.get("/").send().await.assert_status_is_ok();
+        cli.post("/")
+            .body("hello")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.post("/")
+            .header("content-digest", HELLO_SHA256)
+            .body(r#"{"hello": "world"}"#)
+            .send()
+            .await
+            .assert_status_is_ok();
+    }
+
+    #[tokio::test]
+    async fn digest_response() {
+        let cli = TestClient::new(echo.with(ContentDigest::new()));
+
+        let resp = cli.post("/").body(r#"{"hello": "world"}"#).send().await;
+        resp.assert_header("content-digest", format!("{HELLO_SHA256}, {HELLO_SHA512}"));
+        resp.assert_header_is_not_exist("repr-digest");
+        resp.assert_text(r#"{"hello": "world"}"#).await;
+
+        let resp = cli
+            .post("/")
+            .header("want-content-digest", "sha-256=3, sha-512=10")
+            .header("want-repr-digest", "sha-512=0, sha-256=1, md5=10")
+            .body(r#"{"hello": "world"}"#)
+            .send()
+            .await;
+        resp.assert_header("content-digest", HELLO_SHA512);
+        resp.assert_header("repr-digest", HELLO_SHA256);
+
+        let cli = TestClient::new(
+            echo.with(
+                ContentDigest::new()
+                    .algorithms([DigestAlgorithm::Sha256])
+                    .repr_digest(true),
+            ),
+        );
+        let resp = cli
+            .post("/")
+            .header("want-content-digest", "sha-512=10")
+            .body(r#"{"hello": "world"}"#)
+            .send()
+            .await;
+        resp.assert_header("content-digest", HELLO_SHA256);
+        resp.assert_header("repr-digest", HELLO_SHA256);
+    }
+
+    #[tokio::test]
+    async fn skip_response() {
+        #[handler(internal)]
+        fn partial() -> Response {
+            Response::builder()
+                .status(StatusCode::PARTIAL_CONTENT)
+                .body("hello")
+        }
+
+        let cli = TestClient::new(partial.with(ContentDigest::new().repr_digest(true)));
+        let resp = cli.get("/").send().await;
+        resp.assert_header_is_not_exist("repr-digest");
+        resp.assert_header(
+            "content-digest",
+            header_value(
+                &[DigestAlgorithm::Sha256, DigestAlgorithm::Sha512],
+                b"hello",
+            ),
+        );
+
+        let cli = TestClient::new(echo.with(ContentDigest::new()));
+        cli.head("/")
+            .send()
+            .await
+            .assert_header_is_not_exist("content-digest");
+    }
+}
@@ -1,5 +1,6 @@
-use std::{collections::HashSet, str::FromStr, sync::Arc};
+use std::{collections::HashSet, future::Future, str::FromStr, sync::Arc};
 
+use futures_util::{future::BoxFuture, FutureExt};
 use headers::{
     AccessControlAllowHeaders, AccessControlAllowMethods, AccessControlExposeHeaders, HeaderMapExt,
 };
@@ -19,6 +20,14 @@ use crate::{
     IntoResponse, Result,
 };
 
+const ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK: HeaderName =
+    HeaderName::from_static("access-control-request-private-network");
+const ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK: HeaderName =
+    HeaderName::from_static("access-control-allow-private-network");
+
+type AllowOriginsFn = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+type AllowOriginsAsyncFn = Arc<dyn Fn(String) -> BoxFuture<'static, bool> + Send + Sync>;
+
 /// Middleware for CORS
 ///
 /// # Errors
@@ -35,17 +44,20 @@ use crate::{
 ///     .allow_method(Method::POST)
 ///     .allow_credentials(false);
 /// ```
-#[derive(Default)]
-#[allow(clippy::type_complexity)]
+#[derive(Default, Clone)]
 pub struct Cors {
     allow_credentials: bool,
+    allow_any_origin: bool,
     allow_origins: HashSet<HeaderValue>,
     allow_origins_wildcard: Vec<WildMatch>,
-    allow_origins_fn: Option<Arc<dyn Fn(&str) -> bool + Send + Sync>>,
+    allow_origins_fn: Option<AllowOriginsFn>,
+    allow_origins_async_fn: Option<AllowOriginsAsyncFn>,
     allow_headers: HashSet<HeaderName>,
     allow_methods: HashSet<Method>,
+    allow_private_network: bool,
     expose_headers: HashSet<HeaderName>,
     max_age: i32,
+    route_policies: Vec<(WildMatch, Cors)>,
 }
 
 impl Cors {
@@ -59,6 +71,11 @@ impl Cors {
     }
 
     /// Set the allow credentials.
+    ///
+    /// The allowed origin of the request is always sent back instead of `*`,
+    /// so if any origin is allowed, such as with the wildcard origin `*` or
+    /// without any allowed origins, the credentials of any origin are
+    /// accepted, and a warning is logged when the middleware is applied.
     #[must_use]
     pub fn allow_credentials(mut self, allow_credentials: bool) -> Self {
         self.allow_credentials = allow_credentials;
@@ -123,6 +140,8 @@ impl Cors {
 
     /// Add an allow origin.
     ///
+    /// The wildcard origin `*` allows any origin.
+    ///
     /// NOTE: The default is to allow any origin.
     #[must_use]
     pub fn allow_origin<T>(mut self, origin: T) -> Self
@@ -133,7 +152,11 @@ impl Cors {
             Ok(origin) => origin,
             Err(_) => panic!("illegal origin"),
         };
-        self.allow_origins.insert(origin);
+        if origin == "*" {
+            self.allow_any_origin = true;
+        } else {
+            self.allow_origins.insert(origin);
+        }
         self
     }
 
@@ -171,6 +194,21 @@ impl Cors {
         self
     }
 
+    /// Like [`Cors::allow_origins_fn`], but the function is asynchronous, e.g.
+    /// the allowed origins are stored in a database.
+    ///
+    /// It is called after the function specified by
+    /// [`Cors::allow_origins_fn`].
+    #[must_use]
+    pub fn allow_origins_fn_async<F, Fut>(mut self, f: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.allow_origins_async_fn = Some(Arc::new(move |origin| f(origin).boxed()));
+        self
+    }
+
     /// Add an expose header.
     #[must_use]
     pub fn expose_header<T>(mut self, header: T) -> Self
@@ -203,6 +241,42 @@ impl Cors {
         self.max_age = max_age;
         self
     }
+
+    /// Allows the requests from public networks to the private networks,
+    /// as defined in [Private Network Access](https://wicg.github.io/private-network-access/).
+    ///
+    /// If enabled, the preflight requests with the
+    /// `Access-Control-Request-Private-Network` header are responded with the
+    /// `Access-Control-Allow-Private-Network` header, otherwise they are
+    /// rejected.
+    #[must_use]
+    pub fn allow_private_network(mut self, allow_private_network: bool) -> Self {
+        self.allow_private_network = allow_private_network;
+        self
+    }
+
+    /// Use another policy for the requests whose path matches the pattern,
+    /// which supports `*` wildcard.
+    ///
+    /// The patterns are tried in the order they are added, and the policy of
+    /// this middleware is used if none of them matches.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::middleware::Cors;
+    ///
+    /// let cors = Cors::new()
+    ///     .allow_origin("https://example.com")
+    ///     .allow_credentials(true)
+    ///     .route_policy("/public/*", Cors::new());
+    /// ```
+    #[must_use]
+    pub fn route_policy(mut self, pattern: impl AsRef<str>, policy: Cors) -> Self {
+        self.route_policies
+            .push((WildMatch::new(pattern.as_ref()), policy));
+        self
+    }
 }
 
 impl<E: Endpoint> Middleware<E> for Cors {
@@ -211,31 +285,26 @@ impl<E: Endpoint> Middleware<E> for Cors {
     fn transform(&self, ep: E) -> Self::Output {
         CorsEndpoint {
             inner: ep,
-            allow_credentials: self.allow_credentials,
-            allow_origins: self.allow_origins.clone(),
-            allow_origins_wildcard: self.allow_origins_wildcard.clone(),
-            allow_origins_fn: self.allow_origins_fn.clone(),
-            allow_headers: self.allow_headers.clone(),
-            allow_methods: self.allow_methods.clone(),
-            expose_headers: self.expose_headers.clone(),
-            allow_headers_header: self.allow_headers.clone().into_iter().collect(),
-            allow_methods_header: self.allow_methods.clone().into_iter().collect(),
-            expose_headers_header: self.expose_headers.clone().into_iter().collect(),
-            max_age: self.max_age,
+            policy: CorsPolicy::new(self),
+            route_policies: self
+                .route_policies
+                .iter()
+                .map(|(pattern, cors)| (pattern.clone(), CorsPolicy::new(cors)))
+                .collect(),
         }
     }
 }
 
-/// Endpoint for Cors middleware.
-#[allow(clippy::type_complexity)]
-pub struct CorsEndpoint<E> {
-    inner: E,
+struct CorsPolicy {
     allow_credentials: bool,
+    allow_any_origin: bool,
     allow_origins: HashSet<HeaderValue>,
     allow_origins_wildcard: Vec<WildMatch>,
-    allow_origins_fn: Option<Arc<dyn Fn(&str) -> bool + Send + Sync>>,
+    allow_origins_fn: Option<AllowOriginsFn>,
+    allow_origins_async_fn: Option<AllowOriginsAsyncFn>,
     allow_headers: HashSet<HeaderName>,
     allow_methods: HashSet<Method>,
+    allow_private_network: bool,
     expose_headers: HashSet<HeaderName>,
     allow_headers_header: AccessControlAllowHeaders,
     allow_methods_header: AccessControlAllowMethods,
@@ -243,12 +312,48 @@ pub struct CorsEndpoint<E> {
     max_age: i32,
 }
 
-impl<E: Endpoint> CorsEndpoint<E> {
-    fn is_valid_origin(&self, origin: &HeaderValue) -> (bool, bool) {
+impl CorsPolicy {
+    fn new(cors: &Cors) -> Self {
+        let any_origin = cors.allow_any_origin
+            || cors.allow_origins_wildcard.iter().any(|m| *m == "*")
+            || (cors.allow_origins.is_empty()
+                && cors.allow_origins_wildcard.is_empty()
+                && cors.allow_origins_fn.is_none()
+                && cors.allow_origins_async_fn.is_none());
+        if cors.allow_credentials && any_origin {
+            tracing::warn!(
+                "CORS: `allow_credentials` is enabled while any origin is allowed, the origin of \
+                 the request is sent back, so the credentials of any origin will be accepted"
+            );
+        }
+
+        Self {
+            allow_credentials: cors.allow_credentials,
+            allow_any_origin: cors.allow_any_origin,
+            allow_origins: cors.allow_origins.clone(),
+            allow_origins_wildcard: cors.allow_origins_wildcard.clone(),
+            allow_origins_fn: cors.allow_origins_fn.clone(),
+            allow_origins_async_fn: cors.allow_origins_async_fn.clone(),
+            allow_headers: cors.allow_headers.clone(),
+            allow_methods: cors.allow_methods.clone(),
+            allow_private_network: cors.allow_private_network,
+            expose_headers: cors.expose_headers.clone(),
+            allow_headers_header: cors.allow_headers.clone().into_iter().collect(),
+            allow_methods_header: cors.allow_methods.clone().into_iter().collect(),
+            expose_headers_header: cors.expose_headers.clone().into_iter().collect(),
+            max_age: cors.max_age,
+        }
+    }
+
+    async fn is_valid_origin(&self, origin: &HeaderValue) -> (bool, bool) {
         if self.allow_origins.contains(origin) {
             return (true, false);
         }
 
+        if self.allow_any_origin {
+            return (true, true);
+        }
+
         if self
             .allow_origins_wildcard
             .iter()
@@ -265,9 +370,18 @@ impl<E: Endpoint> CorsEndpoint<E> {
             }
         }
 
+        if let Some(allow_origins_async_fn) = &self.allow_origins_async_fn {
+            if let Ok(origin) = origin.to_str() {
+                if allow_origins_async_fn(origin.to_string()).await {
+                    return (true, true);
+                }
+            }
+        }
+
         (
             self.allow_origins.is_empty()
                 && self.allow_origins_fn.is_none()
+                && self.allow_origins_async_fn.is_none()
                 && self.allow_origins_wildcard.is_empty(),
             true,
         )
@@ -277,6 +391,7 @@ impl<E: Endpoint> CorsEndpoint<E> {
         &self,
         origin: &HeaderValue,
         request_headers: Option<&HeaderValue>,
+        private_network: bool,
     ) -> Response {
         let mut builder = Response::builder()
             .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin)
@@ -318,6 +433,10 @@ impl<E: Endpoint> CorsEndpoint<E> {
             builder = builder.header(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true");
         }
 
+        if private_network {
+            builder = builder.header(ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK, "true");
+        }
+
         builder.body(())
     }
 
@@ -349,10 +468,24 @@ impl<E: Endpoint> CorsEndpoint<E> {
     }
 }
 
+/// Endpoint for Cors middleware.
+pub struct CorsEndpoint<E> {
+    inner: E,
+    policy: CorsPolicy,
+    route_policies: Vec<(WildMatch, CorsPolicy)>,
+}
+
 impl<E: Endpoint> Endpoint for CorsEndpoint<E> {
     type Output = Response;
 
     async fn call(&self, req: Request) -> Result<Self::Output> {
+        let policy = self
+            .route_policies
+            .iter()
+            .find(|(pattern, _)| pattern.matches(req.uri().path()))
+            .map(|(_, policy)| policy)
+            .unwrap_or(&self.policy);
+
         let origin = match req.headers().get(header::ORIGIN) {
             Some(origin) => origin.clone(),
             None => {
@@ -361,7 +494,7 @@ impl<E: Endpoint> Endpoint for CorsEndpoint<E> {
             }
         };
 
-        let (origin_is_allow, vary_header) = self.is_valid_origin(&origin);
+        let (origin_is_allow, vary_header) = policy.is_valid_origin(&origin).await;
         if !origin_is_allow {
             return Err(CorsError::OriginNotAllowed.into());
         }
@@ -373,23 +506,31 @@ impl<E: Endpoint> Endpoint for CorsEndpoint<E> {
                 .and_then(|value| value.to_str().ok())
                 .and_then(|value| value.parse::<Method>().ok())
                 .map(|method| {
-                    if self.allow_methods.is_empty() {
+                    if policy.allow_methods.is_empty() {
                         true
                     } else {
-                        self.allow_methods.contains(&method)
+                        policy.allow_methods.contains(&method)
                     }
                 });
             if !matches!(allow_method, Some(true)) {
                 return Err(CorsError::MethodNotAllowed.into());
             }
 
-            let (allow_headers, request_headers) = self.check_allow_headers(&req);
+            let (allow_headers, request_headers) = policy.check_allow_headers(&req);
 
             if !allow_headers {
                 return Err(CorsError::HeadersNotAllowed.into());
             }
 
-            return Ok(self.build_preflight_response(&origin, request_headers));
+            let private_network = req
+                .headers()
+                .get(ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK)
+                .is_some_and(|value| value == "true");
+            if private_network && !policy.allow_private_network {
+                return Err(CorsError::PrivateNetworkNotAllowed.into());
+            }
+
+            return Ok(policy.build_preflight_response(&origin, request_headers, private_network));
         }
 
         let mut resp = self.inner.get_response(req).await;
@@ -397,16 +538,16 @@ impl<E: Endpoint> Endpoint for CorsEndpoint<E> {
         resp.headers_mut()
             .insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
 
-        if self.allow_credentials {
+        if policy.allow_credentials {
             resp.headers_mut().insert(
                 header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
                 HeaderValue::from_static("true"),
             );
         }
 
-        if !self.expose_headers.is_empty() {
+        if !policy.expose_headers.is_empty() {
             resp.headers_mut()
-                .typed_insert(self.expose_headers_header.clone());
+                .typed_insert(policy.expose_headers_header.clone());
         }
 
         if vary_header {
@@ -723,4 +864,95 @@ mod tests {
         resp.assert_status_is_ok();
         resp.assert_header(header::ACCESS_CONTROL_ALLOW_HEADERS, "content-type");
     }
+
+    #[tokio::test]
+    async fn private_network() {
+        let cli = TestClient::new(make_sync(|_| "hello").with(cors()));
+        opt_request(&cli)
+            .header("Access-Control-Request-Private-Network", "true")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+
+        let cli = TestClient::new(make_sync(|_| "hello").with(cors().allow_private_network(true)));
+        let resp = opt_request(&cli)
+            .header("Access-Control-Request-Private-Network", "true")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header("Access-Control-Allow-Private-Network", "true");
+
+        let resp = opt_request(&cli).send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist("Access-Control-Allow-Private-Network");
+    }
+
+    #[tokio::test]
+    async fn allow_origins_fn_async() {
+        let ep =
+            make_sync(|_| "hello").with(Cors::new().allow_origins_fn_async(|origin| async move {
+                tokio::task::yield_now().await;
+                origin.ends_with(".example.com")
+            }));
+        let cli = TestClient::new(ep);
+
+        let resp = cli
+            .get("/")
+            .header(header::ORIGIN, "https://a.example.com")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "https://a.example.com");
+        resp.assert_header(header::VARY, "Origin");
+
+        cli.get("/")
+            .header(header::ORIGIN, "https://abc.com")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn route_policy() {
+        let ep = make_sync(|_| "hello")
+            .with(cors().route_policy("/public/*", Cors::new().allow_origin("*").max_age(60)));
+        let cli = TestClient::new(ep);
+
+        let resp = cli
+            .get("/public/a")
+            .header(header::ORIGIN, "https://abc.com")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "https://abc.com");
+        resp.assert_header_is_not_exist(header::ACCESS_CONTROL_ALLOW_CREDENTIALS);
+
+        let resp = cli
+            .options("/public/a")
+            .header(header::ORIGIN, "https://abc.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header(header::ACCESS_CONTROL_MAX_AGE, "60");
+
+        cli.get("/private")
+            .header(header::ORIGIN, "https://abc.com")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn wildcard_with_credentials() {
+        let ep = make_sync(|_| "hello").with(Cors::new().allow_origin("*").allow_credentials(true));
+        let resp = TestClient::new(ep)
+            .get("/")
+            .header(header::ORIGIN, "https://abc.com")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "https://abc.com");
+        resp.assert_header(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true");
+    }
 }
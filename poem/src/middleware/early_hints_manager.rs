@@ -0,0 +1,240 @@
+use crate::{
+    http::{header, HeaderValue, Version},
+    web::{EarlyHints, InformationalSender},
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// Middleware for [`EarlyHints`] support.
+///
+/// The hints added to the middleware are sent for every request before
+/// calling the inner endpoint, and all the hints are added to the final
+/// response as the `Link` headers.
+pub struct EarlyHintsManager {
+    links: Vec<HeaderValue>,
+    final_links: bool,
+}
+
+impl Default for EarlyHintsManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EarlyHintsManager {
+    /// Create `EarlyHintsManager` middleware.
+    pub fn new() -> Self {
+        Self {
+            links: Vec::new(),
+            final_links: true,
+        }
+    }
+
+    /// Adds a `Link` header value which is sent for every request.
+    #[must_use]
+    pub fn link(mut self, link: HeaderValue) -> Self {
+        self.links.push(link);
+        self
+    }
+
+    /// Adds a hint to preload the resource for every request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the hint is not a valid header value.
+    #[must_use]
+    #[track_caller]
+    pub fn preload(self, url: &str, destination: &str) -> Self {
+        self.link(
+            HeaderValue::try_from(format!("<{url}>; rel=preload; as={destination}"))
+                .expect("valid link"),
+        )
+    }
+
+    /// Adds a hint to connect to the origin for every request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the hint is not a valid header value.
+    #[must_use]
+    #[track_caller]
+    pub fn preconnect(self, origin: &str) -> Self {
+        self.link(HeaderValue::try_from(format!("<{origin}>; rel=preconnect")).expect("valid link"))
+    }
+
+    /// If `true`, the hints are added to the final response as the `Link`
+    /// headers.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn final_links(self, final_links: bool) -> Self {
+        Self {
+            final_links,
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for EarlyHintsManager {
+    type Output = EarlyHintsManagerEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        EarlyHintsManagerEndpoint {
+            inner: ep,
+            links: self.links.clone(),
+            final_links: self.final_links,
+        }
+    }
+}
+
+/// Endpoint for the `EarlyHintsManager` middleware.
+pub struct EarlyHintsManagerEndpoint<E> {
+    inner: E,
+    links: Vec<HeaderValue>,
+    final_links: bool,
+}
+
+impl<E: Endpoint> Endpoint for EarlyHintsManagerEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if req.extensions().get::<EarlyHints>().is_some() {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        }
+
+        // the informational responses may be mishandled by the HTTP/1.x clients
+        let sender = match req.version() >= Version::HTTP_2 {
+            true => req.extensions().get::<InformationalSender>().cloned(),
+            false => None,
+        };
+        let hints = EarlyHints::new(sender);
+        for link in &self.links {
+            hints.link(link.clone());
+        }
+        hints.send();
+
+        req.extensions_mut().insert(hints.clone());
+        let mut resp = self.inner.call(req).await?.into_response();
+
+        if self.final_links {
+            for link in hints.take_links() {
+                if !resp
+                    .headers()
+                    .get_all(header::LINK)
+                    .iter()
+                    .any(|v| v == link)
+                {
+                    resp.headers_mut().append(header::LINK, link);
+                }
+            }
+        }
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::{handler, http::StatusCode, test::TestClient, EndpointExt};
+
+    #[handler(internal)]
+    fn index(hints: &EarlyHints) -> Response {
+        hints.preload("/app.js", "script").send();
+        hints.preconnect("https://cdn.example.com");
+        Response::builder()
+            .header(header::LINK, "</style.css>; rel=preload; as=style")
+            .body("hello")
+    }
+
+    #[tokio::test]
+    async fn final_links() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let sender = InformationalSender::new({
+            let sent = sent.clone();
+            move |resp| {
+                sent.lock().push(resp);
+                true
+            }
+        });
+        let cli = TestClient::new(
+            index
+                .with(EarlyHintsManager::new().preload("/style.css", "style"))
+                .data(sender),
+        );
+
+        // HTTP/1.1
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header_all(
+            header::LINK,
+            [
+                "</style.css>; rel=preload; as=style",
+                "</app.js>; rel=preload; as=script",
+                "<https://cdn.example.com>; rel=preconnect",
+            ],
+        );
+        assert!(sent.lock().is_empty());
+
+        let cli = TestClient::new(index.with(EarlyHintsManager::new().final_links(false)));
+        cli.get("/")
+            .send()
+            .await
+            .assert_header_all(header::LINK, ["</style.css>; rel=preload; as=style"]);
+    }
+
+    #[tokio::test]
+    async fn send_early_hints() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let sender = InformationalSender::new({
+            let sent = sent.clone();
+            move |resp: http::Response<()>| {
+                let links = resp
+                    .headers()
+                    .get_all(header::LINK)
+                    .iter()
+                    .map(|v| v.to_str().unwrap().to_string())
+                    .collect::<Vec<_>>();
+                sent.lock().push((resp.status(), links));
+                true
+            }
+        });
+        let http2_request = || {
+            Request::builder()
+                .version(Version::HTTP_2)
+                .extension(sender.clone())
+                .finish()
+        };
+
+        let ep = EarlyHintsManager::new()
+            .preload("/style.css", "style")
+            .transform(index);
+        let resp = ep.call(http2_request()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            std::mem::take(&mut *sent.lock()),
+            vec![
+                (
+                    StatusCode::EARLY_HINTS,
+                    vec!["</style.css>; rel=preload; as=style".to_string()]
+                ),
+                (
+                    StatusCode::EARLY_HINTS,
+                    vec!["</app.js>; rel=preload; as=script".to_string()]
+                ),
+            ]
+        );
+
+        #[handler(internal)]
+        fn no_hints(hints: &EarlyHints) {
+            assert!(hints.is_supported());
+            assert!(!hints.send());
+        }
+
+        let ep = EarlyHintsManager::new().transform(no_hints);
+        ep.call(http2_request()).await.unwrap();
+        assert!(sent.lock().is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use crate::{Endpoint, Error, IntoResponse, Middleware, Request, Response, Result};
+
+type Mapper = Box<dyn Fn(Error) -> Result<Response, Error> + Send + Sync>;
+
+/// Middleware for rendering the specified types of errors with registered
+/// handlers.
+///
+/// The handlers are tried in the order they are registered, the first one
+/// whose error type matches renders the response. Other errors are passed
+/// through unchanged.
+///
+/// Apply it to the outermost endpoint to render the same type of errors
+/// consistently in all handlers, instead of repeating
+/// [`catch_error`](crate::EndpointExt::catch_error) for every route.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     error::{NotFoundError, ParsePathError},
+///     get, handler,
+///     http::StatusCode,
+///     middleware::ErrorHandler,
+///     test::TestClient,
+///     web::Json,
+///     EndpointExt, IntoResponse, Route,
+/// };
+/// use serde_json::json;
+///
+/// #[handler]
+/// fn index() {}
+///
+/// let app = Route::new().at("/", get(index)).with(
+///     ErrorHandler::new()
+///         .on(|_: NotFoundError| {
+///             Json(json!({ "code": "not_found" })).with_status(StatusCode::NOT_FOUND)
+///         })
+///         .on(|_: ParsePathError| StatusCode::BAD_REQUEST),
+/// );
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/abc").send().await;
+/// resp.assert_status(StatusCode::NOT_FOUND);
+/// resp.assert_json(json!({ "code": "not_found" })).await;
+/// # });
+/// ```
+#[derive(Default, Clone)]
+pub struct ErrorHandler {
+    mappers: Arc<Vec<Mapper>>,
+}
+
+impl ErrorHandler {
+    /// Create `ErrorHandler` middleware.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers a handler for the specified type of error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the middleware has already been applied to an endpoint.
+    #[must_use]
+    pub fn on<ErrType, F, R>(mut self, f: F) -> Self
+    where
+        ErrType: std::error::Error + Send + Sync + 'static,
+        F: Fn(ErrType) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        Arc::get_mut(&mut self.mappers)
+            .expect("the error handler can not be modified after it is used")
+            .push(Box::new(
+                #[allow(clippy::result_large_err)]
+                move |err| err.downcast::<ErrType>().map(|err| f(err).into_response()),
+            ));
+        self
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn handle(&self, mut err: Error) -> Result<Response, Error> {
+        for mapper in self.mappers.iter() {
+            match mapper(err) {
+                Ok(resp) => return Ok(resp),
+                Err(e) => err = e,
+            }
+        }
+        Err(err)
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ErrorHandler {
+    type Output = ErrorHandlerEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ErrorHandlerEndpoint {
+            inner: ep,
+            handler: self.clone(),
+        }
+    }
+}
+
+/// Endpoint for the ErrorHandler middleware.
+pub struct ErrorHandlerEndpoint<E> {
+    inner: E,
+    handler: ErrorHandler,
+}
+
+impl<E: Endpoint> Endpoint for ErrorHandlerEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        match self.inner.call(req).await {
+            Ok(resp) => Ok(resp.into_response()),
+            Err(err) => self.handler.handle(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{
+        error::{MethodNotAllowedError, NotFoundError},
+        handler,
+        test::TestClient,
+        EndpointExt,
+    };
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("custom")]
+    struct CustomError;
+
+    #[tokio::test]
+    async fn error_handler() {
+        #[handler(internal)]
+        #[allow(clippy::result_large_err)]
+        fn index(req: &Request) -> Result<()> {
+            match req.uri().path() {
+                "/a" => Err(NotFoundError.into()),
+                "/b" => Err(Error::new(CustomError, StatusCode::BAD_REQUEST)),
+                _ => Err(MethodNotAllowedError.into()),
+            }
+        }
+
+        let handler = ErrorHandler::new()
+            .on(|_: NotFoundError| "a".with_status(StatusCode::NOT_FOUND))
+            .on(|err: CustomError| err.to_string().with_status(StatusCode::CONFLICT));
+        let cli = TestClient::new(index.with(handler));
+
+        let resp = cli.get("/a").send().await;
+        resp.assert_status(StatusCode::NOT_FOUND);
+        resp.assert_text("a").await;
+
+        let resp = cli.get("/b").send().await;
+        resp.assert_status(StatusCode::CONFLICT);
+        resp.assert_text("custom").await;
+
+        cli.get("/c")
+            .send()
+            .await
+            .assert_status(StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
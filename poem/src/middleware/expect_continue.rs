@@ -0,0 +1,276 @@
+use std::{
+    future::ready,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_util::{stream, task::noop_waker, StreamExt};
+use http_body_util::{BodyStream, StreamBody};
+use hyper::body::Body as _;
+use sync_wrapper::SyncStream;
+
+use crate::{
+    body::BoxBody,
+    error::UnsupportedExpectationError,
+    http::{header, Version},
+    Body, Endpoint, Middleware, Request, Result,
+};
+
+/// Middleware for the `Expect: 100-continue` requests.
+///
+/// By default, the server sends `100 Continue` only when the body of the
+/// request starts to be read, so the middlewares which reject the requests
+/// before reading the body, such as the authentication and
+/// [`SizeLimit`](crate::middleware::SizeLimit), respond with the final status
+/// before the client uploads the body.
+///
+/// This middleware responds `417 Expectation Failed` to the requests with an
+/// unsupported expectation. If [`ExpectContinue::immediate`] is enabled, it
+/// also sends `100 Continue` as soon as the request reaches the middleware,
+/// so it should be applied inside the middlewares which check the requests.
+///
+/// # Errors
+///
+/// - [`UnsupportedExpectationError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     http::StatusCode,
+///     middleware::{ExpectContinue, SizeLimit},
+///     test::TestClient,
+///     EndpointExt,
+/// };
+///
+/// #[handler]
+/// fn index(body: String) -> String {
+///     body
+/// }
+///
+/// let app = index
+///     .with(ExpectContinue::new().immediate(true))
+///     .with(SizeLimit::new(1024));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .post("/")
+///     .header("expect", "200-ok")
+///     .header("content-length", 5)
+///     .body("hello")
+///     .send()
+///     .await;
+/// resp.assert_status(StatusCode::EXPECTATION_FAILED);
+/// # });
+/// ```
+pub struct ExpectContinue {
+    immediate: bool,
+}
+
+impl Default for ExpectContinue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExpectContinue {
+    /// Create `ExpectContinue` middleware.
+    pub fn new() -> Self {
+        Self { immediate: false }
+    }
+
+    /// If `true`, `100 Continue` is sent before calling the inner endpoint,
+    /// instead of when the body starts to be read.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn immediate(self, immediate: bool) -> Self {
+        Self { immediate }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ExpectContinue {
+    type Output = ExpectContinueEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ExpectContinueEndpoint {
+            inner: ep,
+            immediate: self.immediate,
+        }
+    }
+}
+
+/// Endpoint for the `ExpectContinue` middleware.
+pub struct ExpectContinueEndpoint<E> {
+    inner: E,
+    immediate: bool,
+}
+
+impl<E: Endpoint> Endpoint for ExpectContinueEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if req.expects_continue() {
+            if self.immediate {
+                let body = prime(req.take_body().into());
+                req.set_body(body);
+            }
+        } else if req.version() >= Version::HTTP_11 && req.headers().contains_key(header::EXPECT) {
+            return Err(UnsupportedExpectationError.into());
+        }
+
+        self.inner.call(req).await
+    }
+}
+
+/// Polls the body once, so that the server sends `100 Continue`.
+fn prime(mut body: BoxBody) -> Body {
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    match Pin::new(&mut body).poll_frame(&mut cx) {
+        Poll::Pending => Body(body),
+        Poll::Ready(None) => Body::empty(),
+        Poll::Ready(Some(frame)) => Body(BoxBody::new(StreamBody::new(SyncStream::new(
+            stream::once(ready(frame)).chain(BodyStream::new(body)),
+        )))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::{
+        handler,
+        http::StatusCode,
+        listener::{Acceptor, Listener, TcpListener},
+        middleware::SizeLimit,
+        test::TestClient,
+        EndpointExt, Server,
+    };
+
+    #[handler(internal)]
+    async fn slow_echo(body: Body) -> Result<String> {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        Ok(format!("[{}]", body.into_string().await?))
+    }
+
+    async fn start(ep: impl Endpoint + 'static) -> SocketAddr {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor
+            .local_addr()
+            .remove(0)
+            .as_socket_addr()
+            .cloned()
+            .unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(ep));
+        addr
+    }
+
+    async fn read_some(stream: &mut TcpStream) -> String {
+        let mut buf = [0; 1024];
+        let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        String::from_utf8_lossy(&buf[..n]).into_owned()
+    }
+
+    const HEAD: &str = "POST / HTTP/1.1\r\nhost: a\r\ncontent-length: 5\r\n\
+                        expect: 100-continue\r\nconnection: close\r\n\r\n";
+
+    #[tokio::test]
+    async fn continue_when_reading() {
+        let addr = start(slow_echo.with(ExpectContinue::new())).await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(HEAD.as_bytes()).await.unwrap();
+
+        // nothing is sent before the handler reads the body
+        let mut buf = [0; 1];
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), stream.read(&mut buf))
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            read_some(&mut stream).await,
+            "HTTP/1.1 100 Continue\r\n\r\n"
+        );
+
+        stream.write_all(b"hello").await.unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(resp.ends_with("[hello]"));
+    }
+
+    #[tokio::test]
+    async fn immediate() {
+        let addr = start(slow_echo.with(ExpectContinue::new().immediate(true))).await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(HEAD.as_bytes()).await.unwrap();
+
+        // sent before the handler reads the body
+        let resp = tokio::time::timeout(Duration::from_millis(100), read_some(&mut stream))
+            .await
+            .unwrap();
+        assert_eq!(resp, "HTTP/1.1 100 Continue\r\n\r\n");
+
+        stream.write_all(b"hello").await.unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(resp.ends_with("[hello]"));
+    }
+
+    #[tokio::test]
+    async fn reject_before_upload() {
+        let addr = start(
+            slow_echo
+                .with(ExpectContinue::new().immediate(true))
+                .with(SizeLimit::new(4)),
+        )
+        .await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(HEAD.as_bytes()).await.unwrap();
+        let resp = read_some(&mut stream).await;
+        assert!(resp.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+        assert!(!resp.contains("100 Continue"));
+    }
+
+    #[tokio::test]
+    async fn unsupported_expectation() {
+        let cli = TestClient::new(slow_echo.with(ExpectContinue::new()));
+        cli.post("/")
+            .header(header::EXPECT, "200-ok")
+            .body("hello")
+            .send()
+            .await
+            .assert_status(StatusCode::EXPECTATION_FAILED);
+        cli.post("/")
+            .header(header::EXPECT, "100-Continue")
+            .body("hello")
+            .send()
+            .await
+            .assert_text("[hello]")
+            .await;
+    }
+
+    #[tokio::test]
+    async fn prime_body() {
+        let body = prime(Body::from_string("hello".to_string()).into());
+        assert_eq!(body.into_string().await.unwrap(), "hello");
+        assert!(prime(Body::empty().into()).is_empty());
+    }
+}
@@ -0,0 +1,347 @@
+use std::sync::Arc;
+
+#[cfg(feature = "session")]
+use crate::session::Session;
+use crate::{
+    middleware::{CookieJarManager, CookieJarManagerEndpoint},
+    web::{
+        cookie::{Cookie, CookieJar, CookieKey},
+        Flash, FlashMessage, OutgoingFlash,
+    },
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+#[derive(Clone)]
+enum Storage {
+    Cookie {
+        name: String,
+        key: Option<Arc<CookieKey>>,
+    },
+    #[cfg(feature = "session")]
+    Session { key: String },
+}
+
+/// Middleware for the flash messages, which are set by a response and
+/// extracted by the next request.
+///
+/// The messages are set by [`FlashSetter`](crate::web::FlashSetter) and
+/// extracted by [`Flash`], they are stored in a cookie by default, or in the
+/// [`Session`](crate::session::Session) with [`FlashMessages::session`].
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     http::header,
+///     middleware::FlashMessages,
+///     post,
+///     test::TestClient,
+///     web::{Flash, FlashSetter, Redirect},
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn form(flash: &Flash) -> String {
+///     flash
+///         .iter()
+///         .map(|message| message.message.as_str())
+///         .collect::<Vec<_>>()
+///         .join(",")
+/// }
+///
+/// #[handler]
+/// fn submit() -> FlashSetter<Redirect> {
+///     FlashSetter::new(Redirect::see_other("/")).success("saved")
+/// }
+///
+/// let app = Route::new()
+///     .at("/", get(form).post(submit))
+///     .with(FlashMessages::new());
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.post("/").send().await;
+/// let cookie = resp.0.headers()[header::SET_COOKIE].to_str().unwrap();
+/// let cookie = cookie.split(';').next().unwrap().to_string();
+///
+/// let resp = cli.get("/").header(header::COOKIE, cookie).send().await;
+/// resp.assert_text("saved").await;
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
+pub struct FlashMessages {
+    storage: Storage,
+}
+
+impl Default for FlashMessages {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlashMessages {
+    /// Create a `FlashMessages` middleware which stores the messages in the
+    /// `poem-flash` cookie.
+    pub fn new() -> Self {
+        Self {
+            storage: Storage::Cookie {
+                name: "poem-flash".to_string(),
+                key: None,
+            },
+        }
+    }
+
+    /// Create a `FlashMessages` middleware which stores the messages in the
+    /// session, it requires the
+    /// [`CookieSession`](crate::session::CookieSession) or the
+    /// [`ServerSession`](crate::session::ServerSession) middleware.
+    #[cfg(feature = "session")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "session")))]
+    pub fn session() -> Self {
+        Self {
+            storage: Storage::Session {
+                key: "__poem_flash".to_string(),
+            },
+        }
+    }
+
+    /// Sets the name of the cookie, or the key of the session entry where the
+    /// messages are stored.
+    #[must_use]
+    pub fn name(self, value: impl Into<String>) -> Self {
+        let storage = match self.storage {
+            Storage::Cookie { key, .. } => Storage::Cookie {
+                name: value.into(),
+                key,
+            },
+            #[cfg(feature = "session")]
+            Storage::Session { .. } => Storage::Session { key: value.into() },
+        };
+        Self { storage }
+    }
+
+    /// Signs the cookie with the key, so the messages cannot be forged by the
+    /// clients.
+    ///
+    /// It has no effect if the messages are stored in the session.
+    #[must_use]
+    pub fn signed(self, key: CookieKey) -> Self {
+        let storage = match self.storage {
+            Storage::Cookie { name, .. } => Storage::Cookie {
+                name,
+                key: Some(Arc::new(key)),
+            },
+            #[cfg(feature = "session")]
+            storage @ Storage::Session { .. } => storage,
+        };
+        Self { storage }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for FlashMessages {
+    type Output = CookieJarManagerEndpoint<FlashMessagesEndpoint<E>>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        CookieJarManager::new().transform(FlashMessagesEndpoint {
+            inner: ep,
+            storage: self.storage.clone(),
+        })
+    }
+}
+
+/// Endpoint for the [`FlashMessages`] middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
+pub struct FlashMessagesEndpoint<E> {
+    inner: E,
+    storage: Storage,
+}
+
+/// The places where the messages of a request are stored.
+struct FlashContext {
+    cookie_jar: CookieJar,
+    #[cfg(feature = "session")]
+    session: Option<Session>,
+}
+
+impl<E> FlashMessagesEndpoint<E> {
+    fn load(&self, ctx: &FlashContext) -> Vec<FlashMessage> {
+        let messages = match &self.storage {
+            Storage::Cookie { name, key } => {
+                let cookie = match key {
+                    Some(key) => ctx.cookie_jar.signed_with_key(key).get(name),
+                    None => ctx.cookie_jar.get(name),
+                };
+                cookie.and_then(|cookie| cookie.value::<Vec<FlashMessage>>().ok())
+            }
+            #[cfg(feature = "session")]
+            Storage::Session { key } => ctx
+                .session
+                .as_ref()
+                .and_then(|session| session.get::<Vec<FlashMessage>>(key)),
+        };
+        messages.unwrap_or_default()
+    }
+
+    fn save(&self, ctx: &FlashContext, messages: &[FlashMessage]) {
+        match &self.storage {
+            Storage::Cookie { name, key } => {
+                if messages.is_empty() {
+                    match key {
+                        Some(key) => ctx.cookie_jar.signed_with_key(key).remove(name),
+                        None => ctx.cookie_jar.remove(name),
+                    }
+                } else {
+                    let mut cookie = Cookie::new(name, messages);
+                    cookie.set_path("/");
+                    cookie.set_http_only(true);
+                    match key {
+                        Some(key) => ctx.cookie_jar.signed_with_key(key).add(cookie),
+                        None => ctx.cookie_jar.add(cookie),
+                    }
+                }
+            }
+            #[cfg(feature = "session")]
+            Storage::Session { key } => {
+                if let Some(session) = &ctx.session {
+                    if messages.is_empty() {
+                        session.remove(key);
+                    } else {
+                        session.set(key, messages);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<E: Endpoint> Endpoint for FlashMessagesEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let ctx = FlashContext {
+            cookie_jar: req.cookie().clone(),
+            #[cfg(feature = "session")]
+            session: req.extensions().get::<Session>().cloned(),
+        };
+        let incoming = self.load(&ctx);
+        let had_messages = !incoming.is_empty();
+        req.extensions_mut().insert(Flash(incoming.into()));
+
+        let mut resp = self.inner.call(req).await?.into_response();
+        match resp.extensions_mut().remove::<OutgoingFlash>() {
+            Some(OutgoingFlash(messages)) if !messages.is_empty() => self.save(&ctx, &messages),
+            // the messages are only displayed once
+            _ if had_messages => self.save(&ctx, &[]),
+            _ => {}
+        }
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        endpoint::make_sync,
+        get, handler,
+        http::{header, StatusCode},
+        test::{TestClient, TestResponse},
+        web::{FlashLevel, FlashSetter, Redirect},
+        EndpointExt, Route,
+    };
+
+    #[handler(internal)]
+    fn show(flash: &Flash) -> String {
+        flash
+            .iter()
+            .map(|message| format!("{:?}:{}", message.level, message.message))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    #[handler(internal)]
+    fn submit() -> FlashSetter<FlashSetter<Redirect>> {
+        FlashSetter::new(FlashSetter::new(Redirect::see_other("/")).success("saved"))
+            .warning("check the name")
+    }
+
+    fn app(flash: FlashMessages) -> impl Endpoint {
+        Route::new()
+            .at("/", get(show).post(submit))
+            .at("/other", make_sync(|_| ()))
+            .with(flash)
+    }
+
+    fn cookie(resp: &TestResponse) -> Option<String> {
+        let value = resp.0.headers().get(header::SET_COOKIE)?.to_str().ok()?;
+        Some(value.split(';').next()?.to_string())
+    }
+
+    #[tokio::test]
+    async fn cookie_flash() {
+        let key = CookieKey::generate();
+        for flash in [FlashMessages::new(), FlashMessages::new().signed(key)] {
+            let cli = TestClient::new(app(flash.name("flash")));
+
+            let resp = cli.post("/").send().await;
+            resp.assert_status(StatusCode::SEE_OTHER);
+            let value = cookie(&resp).unwrap();
+            assert!(value.starts_with("flash="));
+
+            let resp = cli.get("/").header(header::COOKIE, &value).send().await;
+            let removal = cookie(&resp).unwrap();
+            assert_eq!(removal, "flash=");
+            resp.assert_text("Success:saved,Warning:check the name")
+                .await;
+
+            // the messages are removed even if they are not extracted
+            let resp = cli
+                .get("/other")
+                .header(header::COOKIE, &value)
+                .send()
+                .await;
+            assert_eq!(cookie(&resp).as_deref(), Some("flash="));
+
+            let resp = cli.get("/").send().await;
+            assert_eq!(cookie(&resp), None);
+            resp.assert_text("").await;
+        }
+    }
+
+    #[tokio::test]
+    async fn forged_cookie() {
+        let cli = TestClient::new(app(FlashMessages::new().signed(CookieKey::generate())));
+        let value = Cookie::new(
+            "poem-flash",
+            vec![FlashMessage {
+                level: FlashLevel::Info,
+                message: "forged".to_string(),
+            }],
+        );
+        let resp = cli
+            .get("/")
+            .header(header::COOKIE, format!("poem-flash={}", value.value_str()))
+            .send()
+            .await;
+        resp.assert_text("").await;
+    }
+
+    #[cfg(feature = "session")]
+    #[tokio::test]
+    async fn session_flash() {
+        use crate::session::{CookieConfig, CookieSession};
+
+        let cli = TestClient::new(
+            app(FlashMessages::session()).with(CookieSession::new(CookieConfig::default())),
+        );
+        let resp = cli.post("/").send().await;
+        let value = cookie(&resp).unwrap();
+        assert!(value.starts_with("poem-session="));
+
+        let resp = cli.get("/").header(header::COOKIE, &value).send().await;
+        assert_eq!(cookie(&resp).as_deref(), Some("poem-session=%7B%7D"));
+        resp.assert_text("Success:saved,Warning:check the name")
+            .await;
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use crate::{listener::ListenerTag, Endpoint, IntoResponse, Middleware, Request, Response, Result};
+
+/// Middleware which applies the inner middleware only to the requests
+/// received by the listener with the [`ListenerTag`], the other requests are
+/// passed to the endpoint directly.
+///
+/// See also [`MultiListener`](crate::listener::MultiListener).
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     http::StatusCode,
+///     listener::ListenerTag,
+///     middleware::{AddData, ForListener},
+///     test::TestClient,
+///     web::Data,
+///     EndpointExt,
+/// };
+///
+/// #[handler]
+/// fn index(user: Option<Data<&&'static str>>) -> &'static str {
+///     user.map(|Data(user)| *user).unwrap_or("anonymous")
+/// }
+///
+/// let app = index.with(ForListener::new("internal", AddData::new("admin")));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// cli.get("/")
+///     .data(ListenerTag::new("internal"))
+///     .send()
+///     .await
+///     .assert_text("admin")
+///     .await;
+/// cli.get("/").send().await.assert_text("anonymous").await;
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+pub struct ForListener<M> {
+    tag: Arc<str>,
+    middleware: M,
+}
+
+impl<M> ForListener<M> {
+    /// Create a `ForListener` middleware applying the middleware to the
+    /// requests received by the listener with the tag.
+    pub fn new(tag: impl Into<Arc<str>>, middleware: M) -> Self {
+        Self {
+            tag: tag.into(),
+            middleware,
+        }
+    }
+}
+
+impl<E, M> Middleware<E> for ForListener<M>
+where
+    E: Endpoint,
+    M: Middleware<Arc<E>>,
+{
+    type Output = ForListenerEndpoint<Arc<E>, M::Output>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        let ep = Arc::new(ep);
+        ForListenerEndpoint {
+            tag: self.tag.clone(),
+            matched: self.middleware.transform(ep.clone()),
+            inner: ep,
+        }
+    }
+}
+
+/// Endpoint for the ForListener middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+pub struct ForListenerEndpoint<E, T> {
+    tag: Arc<str>,
+    inner: E,
+    matched: T,
+}
+
+impl<E: Endpoint, T: Endpoint> Endpoint for ForListenerEndpoint<E, T> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let matched = req
+            .extensions()
+            .get::<ListenerTag>()
+            .is_some_and(|tag| tag.as_str() == &*self.tag);
+        if matched {
+            self.matched
+                .call(req)
+                .await
+                .map(IntoResponse::into_response)
+        } else {
+            self.inner.call(req).await.map(IntoResponse::into_response)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, http::StatusCode, middleware::SetHeader, test::TestClient, EndpointExt};
+
+    #[handler(internal)]
+    fn index() -> &'static str {
+        "hello"
+    }
+
+    #[tokio::test]
+    async fn for_listener() {
+        let cli = TestClient::new(index.with(ForListener::new(
+            "public",
+            SetHeader::new().overriding("x-public", "1"),
+        )));
+
+        let resp = cli.get("/").data(ListenerTag::new("public")).send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("x-public", "1");
+
+        let resp = cli.get("/").data(ListenerTag::new("internal")).send().await;
+        resp.assert_status(StatusCode::OK);
+        resp.assert_header_is_not_exist("x-public");
+
+        cli.get("/")
+            .send()
+            .await
+            .assert_header_is_not_exist("x-public");
+    }
+}
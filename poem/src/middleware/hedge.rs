@@ -0,0 +1,373 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use super::retry::{buffer_body, is_idempotent};
+use crate::{
+    middleware::RetryBudget, Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// The number of samples between two computations of the hedging delay.
+const UPDATE_INTERVAL: usize = 64;
+
+/// Middleware for hedging the requests, a second attempt is sent if the first
+/// one does not complete within a percentile of the recent latencies, and the
+/// response of the attempt which completes first is returned, the other
+/// attempt is cancelled.
+///
+/// This reduces the tail latency of the endpoints forwarding the requests to
+/// an upstream. The latencies are tracked separately for each endpoint the
+/// middleware is applied to, so it can be configured per route.
+///
+/// Only the requests with the idempotent methods are hedged, and the request
+/// body is buffered as [`BufferedBody`](crate::middleware::BufferedBody) to
+/// be sent twice, the requests with a body larger than
+/// [`Hedge::max_body_size`] or of an unknown size are not hedged. The
+/// additional attempts are limited by a [`RetryBudget`], which is `10%` of
+/// the requests by default.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{endpoint::make_sync, get, middleware::Hedge, EndpointExt, Route};
+///
+/// let app = Route::new().at(
+///     "/users",
+///     get(make_sync(|_| "users")).with(Hedge::new().percentile(0.99)),
+/// );
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "retry")))]
+pub struct Hedge {
+    percentile: f64,
+    min_delay: Duration,
+    min_samples: usize,
+    max_samples: usize,
+    max_body_size: usize,
+    budget: RetryBudget,
+}
+
+impl Default for Hedge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hedge {
+    /// Create a `Hedge` middleware.
+    pub fn new() -> Self {
+        Self {
+            percentile: 0.95,
+            min_delay: Duration::from_millis(1),
+            min_samples: 100,
+            max_samples: 1000,
+            max_body_size: 64 * 1024,
+            budget: RetryBudget::new(0.1, 10, Duration::from_secs(10)),
+        }
+    }
+
+    /// Sets the percentile of the latencies after which the second attempt
+    /// is sent, in `[0, 1]`.
+    ///
+    /// Default is `0.95`.
+    #[must_use]
+    pub fn percentile(self, percentile: f64) -> Self {
+        Self {
+            percentile: percentile.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    /// Sets the minimum delay before the second attempt.
+    ///
+    /// Default is `1ms`.
+    #[must_use]
+    pub fn min_delay(self, min_delay: Duration) -> Self {
+        Self { min_delay, ..self }
+    }
+
+    /// Sets the number of the latency samples required to hedge the
+    /// requests.
+    ///
+    /// Default is `100`.
+    #[must_use]
+    pub fn min_samples(self, min_samples: usize) -> Self {
+        Self {
+            min_samples,
+            ..self
+        }
+    }
+
+    /// Sets the number of the recent latency samples to compute the
+    /// percentile.
+    ///
+    /// Default is `1000`.
+    #[must_use]
+    pub fn max_samples(self, max_samples: usize) -> Self {
+        Self {
+            max_samples: max_samples.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the maximum size of the request body to be buffered for the
+    /// second attempt.
+    ///
+    /// Default is `64KiB`.
+    #[must_use]
+    pub fn max_body_size(self, max_body_size: usize) -> Self {
+        Self {
+            max_body_size,
+            ..self
+        }
+    }
+
+    /// Sets the budget of the second attempts.
+    #[must_use]
+    pub fn budget(self, budget: RetryBudget) -> Self {
+        Self { budget, ..self }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Hedge {
+    type Output = HedgeEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        HedgeEndpoint {
+            inner: ep,
+            min_delay: self.min_delay,
+            max_body_size: self.max_body_size,
+            budget: self.budget.clone(),
+            latencies: Arc::new(Latencies {
+                percentile: self.percentile,
+                min_samples: self.min_samples,
+                max_samples: self.max_samples,
+                samples: Mutex::new(Samples {
+                    values: VecDeque::with_capacity(self.max_samples),
+                    updates: 0,
+                }),
+                delay: AtomicU64::new(u64::MAX),
+            }),
+        }
+    }
+}
+
+/// The recent latencies of an endpoint.
+struct Latencies {
+    percentile: f64,
+    min_samples: usize,
+    max_samples: usize,
+    samples: Mutex<Samples>,
+    /// The hedging delay in nanoseconds, `u64::MAX` if there are not enough
+    /// samples.
+    delay: AtomicU64,
+}
+
+struct Samples {
+    values: VecDeque<u64>,
+    updates: usize,
+}
+
+impl Latencies {
+    fn delay(&self) -> Option<Duration> {
+        match self.delay.load(Ordering::Relaxed) {
+            u64::MAX if self.min_samples == 0 => Some(Duration::ZERO),
+            u64::MAX => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        }
+    }
+
+    fn record(&self, latency: Duration) {
+        let mut samples = self.samples.lock();
+        if samples.values.len() >= self.max_samples {
+            samples.values.pop_front();
+        }
+        samples
+            .values
+            .push_back(latency.as_nanos().try_into().unwrap_or(u64::MAX - 1));
+        samples.updates += 1;
+
+        let len = samples.values.len();
+        if len >= self.min_samples.max(1)
+            && (samples.updates >= UPDATE_INTERVAL || len == self.min_samples)
+        {
+            samples.updates = 0;
+            let mut values: Vec<u64> = samples.values.iter().copied().collect();
+            let index = ((len - 1) as f64 * self.percentile).round() as usize;
+            let (_, value, _) = values.select_nth_unstable(index);
+            self.delay.store(*value, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Endpoint for the Hedge middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "retry")))]
+pub struct HedgeEndpoint<E> {
+    inner: E,
+    min_delay: Duration,
+    max_body_size: usize,
+    budget: RetryBudget,
+    latencies: Arc<Latencies>,
+}
+
+impl<E: Endpoint> HedgeEndpoint<E> {
+    async fn attempt(&self, req: Request) -> (Result<Response>, Duration) {
+        let start = Instant::now();
+        let res = self.inner.call(req).await.map(IntoResponse::into_response);
+        (res, start.elapsed())
+    }
+}
+
+impl<E: Endpoint> Endpoint for HedgeEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let delay = match self.latencies.delay() {
+            Some(delay) if is_idempotent(req.method()) => delay.max(self.min_delay),
+            _ => {
+                let (res, latency) = self.attempt(req).await;
+                self.latencies.record(latency);
+                return res;
+            }
+        };
+        let Some(body) = buffer_body(&mut req, self.max_body_size).await? else {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        };
+        self.budget.deposit();
+
+        let mut first_req = req.clone_without_body();
+        first_req.set_body(body.clone());
+        let first = self.attempt(first_req);
+        tokio::pin!(first);
+
+        tokio::select! {
+            (res, latency) = &mut first => {
+                self.latencies.record(latency);
+                return res;
+            }
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        if !self.budget.withdraw() {
+            let (res, latency) = first.await;
+            self.latencies.record(latency);
+            return res;
+        }
+
+        req.set_body(body);
+        let second = self.attempt(req);
+        tokio::pin!(second);
+
+        // the attempt which does not complete first is dropped
+        let (res, latency) = tokio::select! {
+            (res, latency) = &mut first => (res, latency),
+            (res, latency) = &mut second => (res, latency + delay),
+        };
+        self.latencies.record(latency);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::{endpoint::make, http::StatusCode, test::TestClient, EndpointExt};
+
+    /// Increments `completed` when the attempt completes, the first attempt
+    /// is slow.
+    fn slow_first(
+        attempts: Arc<AtomicUsize>,
+        completed: Arc<AtomicUsize>,
+    ) -> impl Endpoint<Output = String> {
+        make(move |req| {
+            let attempts = attempts.clone();
+            let completed = completed.clone();
+            async move {
+                let attempt = attempts.fetch_add(1, Ordering::Relaxed);
+                if attempt == 0 {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                let body = req.into_body().into_string().await?;
+                completed.fetch_add(1, Ordering::Relaxed);
+                Ok::<_, crate::Error>(format!("{attempt}:{body}"))
+            }
+        })
+    }
+
+    fn hedge() -> Hedge {
+        Hedge::new()
+            .min_samples(0)
+            .min_delay(Duration::from_millis(20))
+    }
+
+    #[tokio::test]
+    async fn hedge_slow_request() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let completed = Arc::new(AtomicUsize::new(0));
+        let cli = TestClient::new(slow_first(attempts.clone(), completed.clone()).with(hedge()));
+
+        let start = Instant::now();
+        let resp = cli.put("/").body("abc").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("1:abc").await;
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+        assert_eq!(completed.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn not_hedged() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let completed = Arc::new(AtomicUsize::new(0));
+        let ep = slow_first(attempts.clone(), completed.clone()).with(hedge());
+        let cli = TestClient::new(ep);
+
+        // the fast requests are not hedged
+        attempts.store(1, Ordering::Relaxed);
+        cli.get("/").send().await.assert_text("1:").await;
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+
+        // the non-idempotent requests are not hedged
+        let ep = make(|_| async { StatusCode::CREATED }).with(hedge());
+        TestClient::new(ep)
+            .post("/")
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED);
+
+        // not enough samples
+        attempts.store(0, Ordering::Relaxed);
+        let ep = slow_first(attempts.clone(), completed.clone())
+            .with(Hedge::new().min_delay(Duration::from_millis(20)));
+        let resp = tokio::time::timeout(Duration::from_millis(500), async {
+            TestClient::new(ep).get("/").send().await
+        })
+        .await;
+        assert!(resp.is_err());
+    }
+
+    #[test]
+    fn percentile() {
+        let ep = Hedge::new()
+            .percentile(0.9)
+            .min_samples(10)
+            .max_samples(10)
+            .transform(make(|_| async {}));
+        for i in 1..=9 {
+            ep.latencies.record(Duration::from_millis(i));
+        }
+        assert_eq!(ep.latencies.delay(), None);
+        ep.latencies.record(Duration::from_millis(10));
+        assert_eq!(ep.latencies.delay(), Some(Duration::from_millis(9)));
+    }
+}
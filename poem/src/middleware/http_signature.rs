@@ -0,0 +1,1815 @@
+use std::{collections::HashMap, fmt::Write, future::Future, io, sync::Arc, time::Duration};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{uri::Scheme, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri};
+use percent_encoding::{percent_decode_str, percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use ring::{
+    hmac,
+    rand::SystemRandom,
+    signature::{self, EcdsaKeyPair, Ed25519KeyPair, RsaKeyPair, UnparsedPublicKey},
+};
+
+use crate::{
+    clock::SharedClock, error::HttpSignatureError, Endpoint, FromRequest, IntoResponse, Middleware,
+    Request, RequestBody, Response, Result,
+};
+
+const SIGNATURE: HeaderName = HeaderName::from_static("signature");
+const SIGNATURE_INPUT: HeaderName = HeaderName::from_static("signature-input");
+
+/// A signature algorithm of [RFC 9421](https://www.rfc-editor.org/rfc/rfc9421#section-6.2.2).
+#[cfg_attr(docsrs, doc(cfg(feature = "http-signature")))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SignatureAlgorithm {
+    /// `hmac-sha256`
+    HmacSha256,
+    /// `ed25519`
+    Ed25519,
+    /// `ecdsa-p256-sha256`
+    EcdsaP256Sha256,
+    /// `ecdsa-p384-sha384`
+    EcdsaP384Sha384,
+    /// `rsa-pss-sha512`
+    RsaPssSha512,
+    /// `rsa-v1_5-sha256`
+    RsaV15Sha256,
+}
+
+impl SignatureAlgorithm {
+    /// Returns the name of the algorithm which is used in the `alg`
+    /// parameter.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SignatureAlgorithm::HmacSha256 => "hmac-sha256",
+            SignatureAlgorithm::Ed25519 => "ed25519",
+            SignatureAlgorithm::EcdsaP256Sha256 => "ecdsa-p256-sha256",
+            SignatureAlgorithm::EcdsaP384Sha384 => "ecdsa-p384-sha384",
+            SignatureAlgorithm::RsaPssSha512 => "rsa-pss-sha512",
+            SignatureAlgorithm::RsaV15Sha256 => "rsa-v1_5-sha256",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            SignatureAlgorithm::HmacSha256,
+            SignatureAlgorithm::Ed25519,
+            SignatureAlgorithm::EcdsaP256Sha256,
+            SignatureAlgorithm::EcdsaP384Sha384,
+            SignatureAlgorithm::RsaPssSha512,
+            SignatureAlgorithm::RsaV15Sha256,
+        ]
+        .into_iter()
+        .find(|algorithm| algorithm.name() == name)
+    }
+}
+
+fn key_rejected(err: ring::error::KeyRejected) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err.to_string())
+}
+
+enum SigningKeyInner {
+    Hmac(hmac::Key),
+    Ed25519(Ed25519KeyPair),
+    Ecdsa(EcdsaKeyPair),
+    Rsa(RsaKeyPair, &'static dyn signature::RsaEncoding),
+}
+
+/// A private key to create the HTTP message signatures.
+#[cfg_attr(docsrs, doc(cfg(feature = "http-signature")))]
+pub struct SigningKey {
+    algorithm: SignatureAlgorithm,
+    inner: SigningKeyInner,
+}
+
+impl SigningKey {
+    /// Create a `hmac-sha256` key with the shared secret.
+    pub fn hmac_sha256(secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            algorithm: SignatureAlgorithm::HmacSha256,
+            inner: SigningKeyInner::Hmac(hmac::Key::new(hmac::HMAC_SHA256, secret.as_ref())),
+        }
+    }
+
+    /// Create an `ed25519` key from a PKCS#8 document in DER format.
+    pub fn ed25519_pkcs8(pkcs8: &[u8]) -> io::Result<Self> {
+        Ok(Self {
+            algorithm: SignatureAlgorithm::Ed25519,
+            inner: SigningKeyInner::Ed25519(
+                Ed25519KeyPair::from_pkcs8_maybe_unchecked(pkcs8).map_err(key_rejected)?,
+            ),
+        })
+    }
+
+    /// Create an `ecdsa-p256-sha256` key from a PKCS#8 document in DER
+    /// format.
+    pub fn ecdsa_p256_sha256_pkcs8(pkcs8: &[u8]) -> io::Result<Self> {
+        Self::ecdsa(
+            SignatureAlgorithm::EcdsaP256Sha256,
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            pkcs8,
+        )
+    }
+
+    /// Create an `ecdsa-p384-sha384` key from a PKCS#8 document in DER
+    /// format.
+    pub fn ecdsa_p384_sha384_pkcs8(pkcs8: &[u8]) -> io::Result<Self> {
+        Self::ecdsa(
+            SignatureAlgorithm::EcdsaP384Sha384,
+            &signature::ECDSA_P384_SHA384_FIXED_SIGNING,
+            pkcs8,
+        )
+    }
+
+    /// Create a `rsa-pss-sha512` key from a PKCS#8 document in DER format.
+    pub fn rsa_pss_sha512_pkcs8(pkcs8: &[u8]) -> io::Result<Self> {
+        Ok(Self {
+            algorithm: SignatureAlgorithm::RsaPssSha512,
+            inner: SigningKeyInner::Rsa(
+                RsaKeyPair::from_pkcs8(pkcs8).map_err(key_rejected)?,
+                &signature::RSA_PSS_SHA512,
+            ),
+        })
+    }
+
+    /// Create a `rsa-v1_5-sha256` key from a PKCS#8 document in DER format.
+    pub fn rsa_v1_5_sha256_pkcs8(pkcs8: &[u8]) -> io::Result<Self> {
+        Ok(Self {
+            algorithm: SignatureAlgorithm::RsaV15Sha256,
+            inner: SigningKeyInner::Rsa(
+                RsaKeyPair::from_pkcs8(pkcs8).map_err(key_rejected)?,
+                &signature::RSA_PKCS1_SHA256,
+            ),
+        })
+    }
+
+    fn ecdsa(
+        algorithm: SignatureAlgorithm,
+        signing: &'static signature::EcdsaSigningAlgorithm,
+        pkcs8: &[u8],
+    ) -> io::Result<Self> {
+        Ok(Self {
+            algorithm,
+            inner: SigningKeyInner::Ecdsa(
+                EcdsaKeyPair::from_pkcs8(signing, pkcs8, &SystemRandom::new())
+                    .map_err(key_rejected)?,
+            ),
+        })
+    }
+
+    /// Returns the algorithm of the key.
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    fn sign(&self, data: &[u8]) -> Vec<u8> {
+        match &self.inner {
+            SigningKeyInner::Hmac(key) => hmac::sign(key, data).as_ref().to_vec(),
+            SigningKeyInner::Ed25519(key) => key.sign(data).as_ref().to_vec(),
+            SigningKeyInner::Ecdsa(key) => key
+                .sign(&SystemRandom::new(), data)
+                .expect("sign with ecdsa")
+                .as_ref()
+                .to_vec(),
+            SigningKeyInner::Rsa(key, encoding) => {
+                let mut signature = vec![0; key.public().modulus_len()];
+                key.sign(*encoding, &SystemRandom::new(), data, &mut signature)
+                    .expect("sign with rsa");
+                signature
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum VerifyingKeyInner {
+    Hmac(hmac::Key),
+    Public(UnparsedPublicKey<Vec<u8>>),
+}
+
+/// A public key, or a shared secret, to verify the HTTP message signatures.
+#[cfg_attr(docsrs, doc(cfg(feature = "http-signature")))]
+#[derive(Debug, Clone)]
+pub struct VerifyingKey {
+    algorithm: SignatureAlgorithm,
+    inner: VerifyingKeyInner,
+}
+
+impl VerifyingKey {
+    /// Create a `hmac-sha256` key with the shared secret.
+    pub fn hmac_sha256(secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            algorithm: SignatureAlgorithm::HmacSha256,
+            inner: VerifyingKeyInner::Hmac(hmac::Key::new(hmac::HMAC_SHA256, secret.as_ref())),
+        }
+    }
+
+    /// Create an `ed25519` key from the 32 bytes public key.
+    pub fn ed25519(public_key: impl Into<Vec<u8>>) -> Self {
+        Self::public(SignatureAlgorithm::Ed25519, &signature::ED25519, public_key)
+    }
+
+    /// Create an `ecdsa-p256-sha256` key from the uncompressed public key
+    /// point.
+    pub fn ecdsa_p256_sha256(public_key: impl Into<Vec<u8>>) -> Self {
+        Self::public(
+            SignatureAlgorithm::EcdsaP256Sha256,
+            &signature::ECDSA_P256_SHA256_FIXED,
+            public_key,
+        )
+    }
+
+    /// Create an `ecdsa-p384-sha384` key from the uncompressed public key
+    /// point.
+    pub fn ecdsa_p384_sha384(public_key: impl Into<Vec<u8>>) -> Self {
+        Self::public(
+            SignatureAlgorithm::EcdsaP384Sha384,
+            &signature::ECDSA_P384_SHA384_FIXED,
+            public_key,
+        )
+    }
+
+    /// Create a `rsa-pss-sha512` key from the PKCS#1 `RSAPublicKey` in DER
+    /// format.
+    pub fn rsa_pss_sha512(public_key: impl Into<Vec<u8>>) -> Self {
+        Self::public(
+            SignatureAlgorithm::RsaPssSha512,
+            &signature::RSA_PSS_2048_8192_SHA512,
+            public_key,
+        )
+    }
+
+    /// Create a `rsa-v1_5-sha256` key from the PKCS#1 `RSAPublicKey` in DER
+    /// format.
+    pub fn rsa_v1_5_sha256(public_key: impl Into<Vec<u8>>) -> Self {
+        Self::public(
+            SignatureAlgorithm::RsaV15Sha256,
+            &signature::RSA_PKCS1_2048_8192_SHA256,
+            public_key,
+        )
+    }
+
+    fn public(
+        algorithm: SignatureAlgorithm,
+        verification: &'static dyn signature::VerificationAlgorithm,
+        public_key: impl Into<Vec<u8>>,
+    ) -> Self {
+        Self {
+            algorithm,
+            inner: VerifyingKeyInner::Public(UnparsedPublicKey::new(
+                verification,
+                public_key.into(),
+            )),
+        }
+    }
+
+    /// Returns the algorithm of the key.
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    fn verify(&self, data: &[u8], signature: &[u8]) -> bool {
+        match &self.inner {
+            VerifyingKeyInner::Hmac(key) => hmac::verify(key, data, signature).is_ok(),
+            VerifyingKeyInner::Public(key) => key.verify(data, signature).is_ok(),
+        }
+    }
+}
+
+/// Resolves the keys to verify the HTTP message signatures by the `keyid`
+/// parameter.
+///
+/// It is implemented for `HashMap<String, VerifyingKey>` and the async
+/// functions such as `async fn(String) -> Result<Option<VerifyingKey>>`, which
+/// can fetch the keys from a database or over the network.
+#[cfg_attr(docsrs, doc(cfg(feature = "http-signature")))]
+pub trait KeyResolver: Send + Sync + 'static {
+    /// Returns the key with the id, or `None` if it is not found.
+    fn resolve<'a>(
+        &'a self,
+        key_id: &'a str,
+    ) -> impl Future<Output = Result<Option<VerifyingKey>>> + Send + 'a;
+}
+
+impl KeyResolver for HashMap<String, VerifyingKey> {
+    async fn resolve<'a>(&'a self, key_id: &'a str) -> Result<Option<VerifyingKey>> {
+        Ok(self.get(key_id).cloned())
+    }
+}
+
+impl<T: KeyResolver> KeyResolver for Arc<T> {
+    fn resolve<'a>(
+        &'a self,
+        key_id: &'a str,
+    ) -> impl Future<Output = Result<Option<VerifyingKey>>> + Send + 'a {
+        self.as_ref().resolve(key_id)
+    }
+}
+
+impl<F, Fut> KeyResolver for F
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Option<VerifyingKey>>> + Send + 'static,
+{
+    fn resolve<'a>(
+        &'a self,
+        key_id: &'a str,
+    ) -> impl Future<Output = Result<Option<VerifyingKey>>> + Send + 'a {
+        (self)(key_id.to_string())
+    }
+}
+
+/// A bare item of the structured fields of [RFC 8941](https://www.rfc-editor.org/rfc/rfc8941).
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum BareItem {
+    Integer(i64),
+    String(String),
+    Token(String),
+    Boolean(bool),
+    Bytes(Vec<u8>),
+}
+
+impl BareItem {
+    fn serialize(&self, s: &mut String) {
+        match self {
+            BareItem::Integer(n) => _ = write!(s, "{n}"),
+            BareItem::String(value) => {
+                s.push('"');
+                for c in value.chars() {
+                    if c == '"' || c == '\\' {
+                        s.push('\\');
+                    }
+                    s.push(c);
+                }
+                s.push('"');
+            }
+            BareItem::Token(value) => s.push_str(value),
+            BareItem::Boolean(value) => s.push_str(if *value { "?1" } else { "?0" }),
+            BareItem::Bytes(value) => _ = write!(s, ":{}:", STANDARD.encode(value)),
+        }
+    }
+}
+
+type Params = Vec<(String, BareItem)>;
+
+fn serialize_params(params: &Params, s: &mut String) {
+    for (key, value) in params {
+        s.push(';');
+        s.push_str(key);
+        if value != &BareItem::Boolean(true) {
+            s.push('=');
+            value.serialize(s);
+        }
+    }
+}
+
+fn get_param<'a>(params: &'a Params, key: &str) -> Option<&'a BareItem> {
+    params
+        .iter()
+        .find_map(|(name, value)| (name == key).then_some(value))
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input: input.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn is_end(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        let matched = self.peek() == Some(c);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn skip_sp(&mut self) {
+        while self.peek() == Some(b' ') {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_ows(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(u8) -> bool) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.input[start..self.pos]).unwrap_or_default()
+    }
+
+    fn key(&mut self) -> Option<String> {
+        if !self
+            .peek()
+            .is_some_and(|c| c.is_ascii_lowercase() || c == b'*')
+        {
+            return None;
+        }
+        Some(
+            self.take_while(|c| {
+                c.is_ascii_lowercase()
+                    || c.is_ascii_digit()
+                    || matches!(c, b'_' | b'-' | b'.' | b'*')
+            })
+            .to_string(),
+        )
+    }
+
+    fn bare_item(&mut self) -> Option<BareItem> {
+        match self.peek()? {
+            b'"' => {
+                self.pos += 1;
+                let mut value = String::new();
+                loop {
+                    match self.peek()? {
+                        b'"' => {
+                            self.pos += 1;
+                            return Some(BareItem::String(value));
+                        }
+                        b'\\' => {
+                            self.pos += 1;
+                            let c = self.peek().filter(|c| matches!(c, b'"' | b'\\'))?;
+                            value.push(c as char);
+                        }
+                        c @ 0x20..=0x7e => value.push(c as char),
+                        _ => return None,
+                    }
+                    self.pos += 1;
+                }
+            }
+            b':' => {
+                self.pos += 1;
+                let value = self.take_while(|c| c.is_ascii_alphanumeric() || b"+/=".contains(&c));
+                if !self.eat(b':') {
+                    return None;
+                }
+                STANDARD.decode(value).ok().map(BareItem::Bytes)
+            }
+            b'?' => {
+                self.pos += 1;
+                let value = match self.peek()? {
+                    b'0' => false,
+                    b'1' => true,
+                    _ => return None,
+                };
+                self.pos += 1;
+                Some(BareItem::Boolean(value))
+            }
+            b'-' | b'0'..=b'9' => {
+                let negative = self.eat(b'-');
+                let digits = self.take_while(|c| c.is_ascii_digit());
+                if digits.is_empty() || digits.len() > 15 {
+                    return None;
+                }
+                let value: i64 = digits.parse().ok()?;
+                Some(BareItem::Integer(if negative { -value } else { value }))
+            }
+            c if c.is_ascii_alphabetic() || c == b'*' => Some(BareItem::Token(
+                self.take_while(|c| c.is_ascii_graphic() && !b"\"(),;<=>?@[\\]{}".contains(&c))
+                    .to_string(),
+            )),
+            _ => None,
+        }
+    }
+
+    fn params(&mut self) -> Option<Params> {
+        let mut params = Vec::new();
+        while self.eat(b';') {
+            self.skip_sp();
+            let key = self.key()?;
+            let value = if self.eat(b'=') {
+                self.bare_item()?
+            } else {
+                BareItem::Boolean(true)
+            };
+            params.push((key, value));
+        }
+        Some(params)
+    }
+
+    fn inner_list(&mut self) -> Option<Vec<(BareItem, Params)>> {
+        if !self.eat(b'(') {
+            return None;
+        }
+        let mut items = Vec::new();
+        loop {
+            self.skip_sp();
+            if self.eat(b')') {
+                return Some(items);
+            }
+            let item = self.bare_item()?;
+            let params = self.params()?;
+            items.push((item, params));
+            if !matches!(self.peek(), Some(b' ' | b')')) {
+                return None;
+            }
+        }
+    }
+
+    fn dictionary<T>(
+        &mut self,
+        mut value: impl FnMut(&mut Self) -> Option<T>,
+    ) -> Option<Vec<(String, T)>> {
+        let mut members = Vec::new();
+        self.skip_sp();
+        while !self.is_end() {
+            let key = self.key()?;
+            if !self.eat(b'=') {
+                return None;
+            }
+            members.push((key, value(self)?));
+            self.skip_ows();
+            if self.is_end() {
+                break;
+            }
+            if !self.eat(b',') {
+                return None;
+            }
+            self.skip_ows();
+            if self.is_end() {
+                return None;
+            }
+        }
+        Some(members)
+    }
+}
+
+/// A covered component of a signature, such as `@method` or `content-type`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Component {
+    name: String,
+    params: Params,
+}
+
+impl Component {
+    #[track_caller]
+    fn parse(id: &str) -> Self {
+        let (name, params) = id.split_once(';').unwrap_or((id, ""));
+        let params = if params.is_empty() {
+            String::new()
+        } else {
+            format!(";{params}")
+        };
+        let mut parser = Parser::new(&params);
+        match parser.params() {
+            Some(params) if parser.is_end() && !name.is_empty() => Self {
+                name: name.to_ascii_lowercase(),
+                params,
+            },
+            _ => panic!("invalid component identifier: `{id}`"),
+        }
+    }
+
+    fn from_item(item: BareItem, params: Params) -> Option<Self> {
+        let BareItem::String(name) = item else {
+            return None;
+        };
+        let component = Self { name, params };
+        let valid = component
+            .params
+            .iter()
+            .all(|(key, value)| match key.as_str() {
+                "req" => value == &BareItem::Boolean(true),
+                "name" => component.name == "@query-param" && matches!(value, BareItem::String(_)),
+                _ => false,
+            });
+        valid.then_some(component)
+    }
+
+    fn is_derived(&self) -> bool {
+        self.name.starts_with('@')
+    }
+
+    fn is_req(&self) -> bool {
+        get_param(&self.params, "req").is_some()
+    }
+
+    fn serialize(&self, s: &mut String) {
+        BareItem::String(self.name.clone()).serialize(s);
+        serialize_params(&self.params, s);
+    }
+
+    fn to_identifier(&self) -> String {
+        let mut s = self.name.clone();
+        serialize_params(&self.params, &mut s);
+        s
+    }
+}
+
+/// The parts of a request which are used to compute the component values.
+struct RequestParts<'a> {
+    method: &'a Method,
+    uri: &'a Uri,
+    scheme: &'a Scheme,
+    headers: &'a HeaderMap,
+}
+
+impl<'a> RequestParts<'a> {
+    fn from_request(req: &'a Request) -> Self {
+        Self {
+            method: req.method(),
+            uri: req.original_uri(),
+            scheme: req.scheme(),
+            headers: req.headers(),
+        }
+    }
+
+    fn authority(&self) -> Option<String> {
+        match self.uri.authority() {
+            Some(authority) => Some(authority.as_str().to_ascii_lowercase()),
+            None => self
+                .headers
+                .get(http::header::HOST)?
+                .to_str()
+                .ok()
+                .map(str::to_ascii_lowercase),
+        }
+    }
+
+    fn derived(&self, component: &Component) -> Option<String> {
+        let path = match self.uri.path() {
+            "" => "/",
+            path => path,
+        };
+        let query = self.uri.query();
+        Some(match component.name.as_str() {
+            "@method" => self.method.to_string(),
+            "@target-uri" => format!(
+                "{}://{}{path}{}",
+                self.scheme.as_str().to_ascii_lowercase(),
+                self.authority()?,
+                query.map(|query| format!("?{query}")).unwrap_or_default()
+            ),
+            "@authority" => self.authority()?,
+            "@scheme" => self.scheme.as_str().to_ascii_lowercase(),
+            "@request-target" => match query {
+                Some(query) => format!("{path}?{query}"),
+                None => path.to_string(),
+            },
+            "@path" => path.to_string(),
+            "@query" => format!("?{}", query.unwrap_or_default()),
+            "@query-param" => {
+                let Some(BareItem::String(name)) = get_param(&component.params, "name") else {
+                    return None;
+                };
+                query?.split('&').find_map(|pair| {
+                    let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    (normalize_query_param(key) == *name).then(|| normalize_query_param(value))
+                })?
+            }
+            _ => return None,
+        })
+    }
+}
+
+/// Decodes the name or value of a query parameter as
+/// `application/x-www-form-urlencoded` and encodes it again, see
+/// <https://www.rfc-editor.org/rfc/rfc9421#section-2.2.8>.
+fn normalize_query_param(s: &str) -> String {
+    const QUERY_PARAM: &AsciiSet = &NON_ALPHANUMERIC
+        .remove(b'*')
+        .remove(b'-')
+        .remove(b'.')
+        .remove(b'_');
+    let decoded = percent_decode_str(&s.replace('+', " ")).collect::<Vec<_>>();
+    percent_encode(&decoded, QUERY_PARAM).to_string()
+}
+
+fn field_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    let mut values = headers.get_all(name).iter().peekable();
+    values.peek()?;
+    let values = values
+        .map(|value| value.to_str().map(str::trim))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    Some(values.join(", "))
+}
+
+/// Returns the value of a component, the request components of a response
+/// must have the `req` parameter.
+fn component_value(
+    component: &Component,
+    req: &RequestParts<'_>,
+    resp: Option<(StatusCode, &HeaderMap)>,
+) -> Option<String> {
+    match (resp, component.is_req()) {
+        (Some((status, _)), false) if component.name == "@status" => {
+            Some(status.as_str().to_string())
+        }
+        (Some(_), false) if component.is_derived() => None,
+        (Some((_, headers)), false) => field_value(headers, &component.name),
+        (None, true) => None,
+        _ if component.name == "@status" => None,
+        _ if component.is_derived() => req.derived(component),
+        _ => field_value(req.headers, &component.name),
+    }
+}
+
+/// The inner list and the parameters of a `Signature-Input` member.
+struct SignatureInput {
+    components: Vec<Component>,
+    params: Params,
+}
+
+impl SignatureInput {
+    fn serialize(&self) -> String {
+        let mut s = String::from("(");
+        for (idx, component) in self.components.iter().enumerate() {
+            if idx > 0 {
+                s.push(' ');
+            }
+            component.serialize(&mut s);
+        }
+        s.push(')');
+        serialize_params(&self.params, &mut s);
+        s
+    }
+
+    fn signature_base(
+        &self,
+        req: &RequestParts<'_>,
+        resp: Option<(StatusCode, &HeaderMap)>,
+    ) -> Option<String> {
+        let mut base = String::new();
+        for component in &self.components {
+            let value = component_value(component, req, resp)?;
+            component.serialize(&mut base);
+            _ = writeln!(base, ": {value}");
+        }
+        _ = write!(base, "\"@signature-params\": {}", self.serialize());
+        Some(base)
+    }
+
+    fn int_param(&self, key: &str) -> Option<i64> {
+        match get_param(&self.params, key)? {
+            BareItem::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn str_param(&self, key: &str) -> Option<&str> {
+        match get_param(&self.params, key)? {
+            BareItem::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+fn header_str(headers: &HeaderMap, name: &HeaderName) -> Option<String> {
+    let values = headers
+        .get_all(name)
+        .iter()
+        .map(HeaderValue::to_str)
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    (!values.is_empty()).then(|| values.join(", "))
+}
+
+/// Parses the `Signature-Input` and `Signature` headers.
+fn parse_signatures(
+    headers: &HeaderMap,
+) -> Result<Vec<(String, SignatureInput, Vec<u8>)>, HttpSignatureError> {
+    let (Some(inputs), Some(signatures)) = (
+        header_str(headers, &SIGNATURE_INPUT),
+        header_str(headers, &SIGNATURE),
+    ) else {
+        return Err(HttpSignatureError::Missing);
+    };
+
+    let inputs = Parser::new(&inputs)
+        .dictionary(|parser| Some((parser.inner_list()?, parser.params()?)))
+        .ok_or(HttpSignatureError::Invalid)?;
+    let mut signatures = Parser::new(&signatures)
+        .dictionary(|parser| {
+            let item = parser.bare_item()?;
+            parser.params()?;
+            Some(item)
+        })
+        .ok_or(HttpSignatureError::Invalid)?;
+
+    inputs
+        .into_iter()
+        .map(|(label, (items, params))| {
+            let idx = signatures
+                .iter()
+                .position(|(name, _)| name == &label)
+                .ok_or(HttpSignatureError::Invalid)?;
+            let BareItem::Bytes(signature) = signatures.swap_remove(idx).1 else {
+                return Err(HttpSignatureError::Invalid);
+            };
+            let components = items
+                .into_iter()
+                .map(|(item, params)| Component::from_item(item, params))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(HttpSignatureError::Invalid)?;
+            Ok((label, SignatureInput { components, params }, signature))
+        })
+        .collect()
+}
+
+/// A verified HTTP message signature of the request.
+#[cfg_attr(docsrs, doc(cfg(feature = "http-signature")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VerifiedSignature {
+    /// The label of the signature.
+    pub label: String,
+    /// The `keyid` parameter.
+    pub key_id: String,
+    /// The algorithm of the key.
+    pub algorithm: SignatureAlgorithm,
+    /// The `created` parameter, in seconds since the Unix epoch.
+    pub created: Option<u64>,
+    /// The identifiers of the covered components, such as `@method` or
+    /// `content-digest`.
+    pub components: Vec<String>,
+}
+
+impl<'a> FromRequest<'a> for &'a VerifiedSignature {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req.extensions().get::<VerifiedSignature>().expect(
+            "To use the `VerifiedSignature` extractor, the `VerifySignature` middleware is \
+             required.",
+        ))
+    }
+}
+
+/// Middleware for verifying the HTTP message signatures of [RFC 9421](https://www.rfc-editor.org/rfc/rfc9421)
+/// of the requests.
+///
+/// The keys are resolved by the `keyid` parameter with a [`KeyResolver`]. A
+/// signature is accepted if it covers all the required components, is created
+/// within the maximum age, and is verified with the key. If there are multiple
+/// signatures, any of them is accepted unless the label is specified.
+///
+/// The signature does not cover the body itself, to protect the body, require
+/// the `content-digest` component and use the
+/// [`ContentDigest`](crate::middleware::ContentDigest) middleware in the inner
+/// layer to verify the digest.
+///
+/// The verified signature can be extracted with [`VerifiedSignature`].
+///
+/// # Errors
+///
+/// - [`HttpSignatureError`]
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use poem::{
+///     handler,
+///     http::StatusCode,
+///     middleware::{VerifiedSignature, VerifySignature, VerifyingKey},
+///     test::TestClient,
+///     EndpointExt,
+/// };
+///
+/// #[handler]
+/// fn index(signature: &VerifiedSignature) -> String {
+///     signature.key_id.clone()
+/// }
+///
+/// let keys = HashMap::from([(
+///     "my-key".to_string(),
+///     VerifyingKey::hmac_sha256(b"my secret"),
+/// )]);
+/// let app = index.with(VerifySignature::new(keys));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status(StatusCode::UNAUTHORIZED);
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "http-signature")))]
+pub struct VerifySignature<R> {
+    resolver: Arc<R>,
+    label: Option<String>,
+    components: Vec<Component>,
+    max_age: Duration,
+    clock_skew: Duration,
+    tag: Option<String>,
+}
+
+impl<R: KeyResolver> VerifySignature<R> {
+    /// Create `VerifySignature` middleware with the key resolver.
+    pub fn new(resolver: R) -> Self {
+        Self {
+            resolver: Arc::new(resolver),
+            label: None,
+            components: ["@method", "@authority", "@path"]
+                .into_iter()
+                .map(Component::parse)
+                .collect(),
+            max_age: Duration::from_secs(5 * 60),
+            clock_skew: Duration::from_secs(5),
+            tag: None,
+        }
+    }
+
+    /// Only accepts the signature with the label.
+    #[must_use]
+    pub fn label(self, label: impl Into<String>) -> Self {
+        Self {
+            label: Some(label.into()),
+            ..self
+        }
+    }
+
+    /// Sets the components which must be covered by the signature, such as
+    /// `@method`, `@target-uri`, `@query-param;name="id"` or `content-digest`.
+    ///
+    /// Default is `@method`, `@authority` and `@path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the component identifiers is invalid.
+    #[must_use]
+    #[track_caller]
+    pub fn components<I, T>(self, components: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        Self {
+            components: components
+                .into_iter()
+                .map(|id| Component::parse(id.as_ref()))
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Sets the maximum age of the signatures, the signatures without the
+    /// `created` parameter are rejected.
+    ///
+    /// Default is `5m`.
+    #[must_use]
+    pub fn max_age(self, max_age: Duration) -> Self {
+        Self { max_age, ..self }
+    }
+
+    /// Sets the allowed clock skew between the signer and the server, the
+    /// signatures created later than this duration in the future are
+    /// rejected.
+    ///
+    /// Default is `5s`.
+    #[must_use]
+    pub fn clock_skew(self, clock_skew: Duration) -> Self {
+        Self { clock_skew, ..self }
+    }
+
+    /// Only accepts the signatures with the `tag` parameter, which is the
+    /// application specific name of the signatures.
+    #[must_use]
+    pub fn tag(self, tag: impl Into<String>) -> Self {
+        Self {
+            tag: Some(tag.into()),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint, R: KeyResolver> Middleware<E> for VerifySignature<R> {
+    type Output = VerifySignatureEndpoint<E, R>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        VerifySignatureEndpoint {
+            inner: ep,
+            resolver: self.resolver.clone(),
+            label: self.label.clone(),
+            components: self.components.clone(),
+            max_age: self.max_age,
+            clock_skew: self.clock_skew,
+            tag: self.tag.clone(),
+        }
+    }
+}
+
+/// Endpoint for the VerifySignature middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "http-signature")))]
+pub struct VerifySignatureEndpoint<E, R> {
+    inner: E,
+    resolver: Arc<R>,
+    label: Option<String>,
+    components: Vec<Component>,
+    max_age: Duration,
+    clock_skew: Duration,
+    tag: Option<String>,
+}
+
+impl<E, R: KeyResolver> VerifySignatureEndpoint<E, R> {
+    async fn verify(&self, req: &Request) -> Result<VerifiedSignature> {
+        let mut res = Err(HttpSignatureError::Missing.into());
+        for (label, input, signature) in parse_signatures(req.headers())? {
+            if self.label.as_ref().is_some_and(|l| l != &label) {
+                continue;
+            }
+            res = self.verify_signature(req, label, input, &signature).await;
+            if res.is_ok() {
+                break;
+            }
+        }
+        res
+    }
+
+    async fn verify_signature(
+        &self,
+        req: &Request,
+        label: String,
+        input: SignatureInput,
+        signature: &[u8],
+    ) -> Result<VerifiedSignature> {
+        if let Some(component) = self
+            .components
+            .iter()
+            .find(|component| !input.components.contains(component))
+        {
+            return Err(HttpSignatureError::MissingComponent(component.to_identifier()).into());
+        }
+
+        if self
+            .tag
+            .as_deref()
+            .is_some_and(|tag| input.str_param("tag") != Some(tag))
+        {
+            return Err(HttpSignatureError::Invalid.into());
+        }
+
+        let now = SharedClock::from_request(req)
+            .elapsed_since_epoch()
+            .as_secs() as i64;
+        let created = input
+            .int_param("created")
+            .ok_or(HttpSignatureError::Expired)?;
+        if now.saturating_sub(created) > self.max_age.as_secs() as i64
+            || created.saturating_sub(now) > self.clock_skew.as_secs() as i64
+        {
+            return Err(HttpSignatureError::Expired.into());
+        }
+        if input
+            .int_param("expires")
+            .is_some_and(|expires| expires <= now)
+        {
+            return Err(HttpSignatureError::Expired.into());
+        }
+
+        let key_id = input
+            .str_param("keyid")
+            .ok_or(HttpSignatureError::UnknownKey)?;
+        let key = self
+            .resolver
+            .resolve(key_id)
+            .await?
+            .ok_or(HttpSignatureError::UnknownKey)?;
+        if get_param(&input.params, "alg").is_some()
+            && input
+                .str_param("alg")
+                .and_then(SignatureAlgorithm::from_name)
+                != Some(key.algorithm())
+        {
+            return Err(HttpSignatureError::AlgorithmMismatch.into());
+        }
+
+        let base = input
+            .signature_base(&RequestParts::from_request(req), None)
+            .ok_or(HttpSignatureError::Invalid)?;
+        if !key.verify(base.as_bytes(), signature) {
+            return Err(HttpSignatureError::Failed.into());
+        }
+
+        Ok(VerifiedSignature {
+            label,
+            key_id: key_id.to_string(),
+            algorithm: key.algorithm(),
+            created: u64::try_from(created).ok(),
+            components: input
+                .components
+                .iter()
+                .map(Component::to_identifier)
+                .collect(),
+        })
+    }
+}
+
+impl<E: Endpoint, R: KeyResolver> Endpoint for VerifySignatureEndpoint<E, R> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let signature = self.verify(&req).await?;
+        req.extensions_mut().insert(signature);
+        self.inner.call(req).await
+    }
+}
+
+/// Middleware for signing the responses with the HTTP message signatures of
+/// [RFC 9421](https://www.rfc-editor.org/rfc/rfc9421).
+///
+/// The `Signature-Input` and `Signature` headers are added to the responses.
+/// The signatures always have the `created`, `keyid` and `alg` parameters.
+/// The header fields which are not present in a response are not covered by
+/// its signature.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     middleware::{SignResponse, SigningKey},
+///     test::TestClient,
+///     EndpointExt,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let app = index.with(
+///     SignResponse::new("my-key", SigningKey::hmac_sha256(b"my secret")).components([
+///         "@status",
+///         "content-type",
+///         "@method;req",
+///         "@path;req",
+///     ]),
+/// );
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status_is_ok();
+/// assert!(resp.0.headers().contains_key("signature"));
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "http-signature")))]
+pub struct SignResponse {
+    key: Arc<SigningKey>,
+    key_id: String,
+    label: String,
+    components: Vec<Component>,
+    expires_in: Option<Duration>,
+    tag: Option<String>,
+}
+
+impl SignResponse {
+    /// Create `SignResponse` middleware with the key and its id.
+    pub fn new(key_id: impl Into<String>, key: SigningKey) -> Self {
+        Self {
+            key: Arc::new(key),
+            key_id: key_id.into(),
+            label: "sig1".to_string(),
+            components: ["@status", "content-type", "content-digest"]
+                .into_iter()
+                .map(Component::parse)
+                .collect(),
+            expires_in: None,
+            tag: None,
+        }
+    }
+
+    /// Sets the label of the signature.
+    ///
+    /// Default is `sig1`.
+    #[must_use]
+    pub fn label(self, label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            ..self
+        }
+    }
+
+    /// Sets the covered components, the components of the request must have
+    /// the `req` parameter, such as `@method;req`.
+    ///
+    /// Default is `@status`, `content-type` and `content-digest`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the component identifiers is invalid, or a derived
+    /// component of the request does not have the `req` parameter.
+    #[must_use]
+    #[track_caller]
+    pub fn components<I, T>(self, components: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let components: Vec<_> = components
+            .into_iter()
+            .map(|id| Component::parse(id.as_ref()))
+            .collect();
+        for component in &components {
+            assert!(
+                !component.is_derived() || (component.name == "@status") != component.is_req(),
+                "invalid component of the response: `{}`",
+                component.to_identifier()
+            );
+        }
+        Self { components, ..self }
+    }
+
+    /// Sets the time after which the signatures expire.
+    #[must_use]
+    pub fn expires_in(self, expires_in: Duration) -> Self {
+        Self {
+            expires_in: Some(expires_in),
+            ..self
+        }
+    }
+
+    /// Sets the `tag` parameter of the signatures.
+    #[must_use]
+    pub fn tag(self, tag: impl Into<String>) -> Self {
+        Self {
+            tag: Some(tag.into()),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for SignResponse {
+    type Output = SignResponseEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        SignResponseEndpoint {
+            inner: ep,
+            key: self.key.clone(),
+            key_id: self.key_id.clone(),
+            label: self.label.clone(),
+            components: self.components.clone(),
+            expires_in: self.expires_in,
+            tag: self.tag.clone(),
+        }
+    }
+}
+
+/// Endpoint for the SignResponse middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "http-signature")))]
+pub struct SignResponseEndpoint<E> {
+    inner: E,
+    key: Arc<SigningKey>,
+    key_id: String,
+    label: String,
+    components: Vec<Component>,
+    expires_in: Option<Duration>,
+    tag: Option<String>,
+}
+
+impl<E: Endpoint> Endpoint for SignResponseEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let created = SharedClock::from_request(&req).elapsed_since_epoch();
+        let (method, uri, scheme, headers) = match self.components.iter().any(Component::is_req) {
+            true => (
+                req.method().clone(),
+                req.original_uri().clone(),
+                req.scheme().clone(),
+                req.headers().clone(),
+            ),
+            false => (Method::GET, Uri::default(), Scheme::HTTP, HeaderMap::new()),
+        };
+        let mut resp = self.inner.call(req).await?.into_response();
+
+        let req = RequestParts {
+            method: &method,
+            uri: &uri,
+            scheme: &scheme,
+            headers: &headers,
+        };
+        let resp_parts = Some((resp.status(), resp.headers()));
+        let components = self
+            .components
+            .iter()
+            .filter(|component| component_value(component, &req, resp_parts).is_some())
+            .cloned()
+            .collect();
+
+        let mut params = vec![(
+            "created".to_string(),
+            BareItem::Integer(created.as_secs() as i64),
+        )];
+        if let Some(expires_in) = self.expires_in {
+            params.push((
+                "expires".to_string(),
+                BareItem::Integer((created + expires_in).as_secs() as i64),
+            ));
+        }
+        params.push(("keyid".to_string(), BareItem::String(self.key_id.clone())));
+        params.push((
+            "alg".to_string(),
+            BareItem::String(self.key.algorithm().name().to_string()),
+        ));
+        if let Some(tag) = &self.tag {
+            params.push(("tag".to_string(), BareItem::String(tag.clone())));
+        }
+
+        let input = SignatureInput { components, params };
+        let base = input
+            .signature_base(&req, resp_parts)
+            .expect("covered components");
+        let signature = self.key.sign(base.as_bytes());
+
+        let headers = resp.headers_mut();
+        let input = format!("{}={}", self.label, input.serialize());
+        let signature = format!("{}=:{}:", self.label, STANDARD.encode(signature));
+        if let (Ok(input), Ok(signature)) = (
+            HeaderValue::try_from(input),
+            HeaderValue::try_from(signature),
+        ) {
+            headers.append(SIGNATURE_INPUT, input);
+            headers.append(SIGNATURE, signature);
+        }
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use ring::signature::KeyPair;
+
+    use super::*;
+    use crate::{
+        clock::MockClock,
+        error::ResponseError,
+        handler,
+        test::{TestClient, TestRequestBuilder},
+        EndpointExt,
+    };
+
+    // The keys and the signatures of the test vectors of RFC 9421 appendix B.
+    const ED25519_PRIVATE: &str =
+        "MC4CAQAwBQYDK2VwBCIEIJ+DYvh6SEqVTm50DFtMDoQikTmiCqirVv9mWG9qfSnF";
+    const ED25519_PUBLIC: &str = "MCowBQYDK2VwAyEAJrQLj5P/89iXES9+vFgrIy29clF9CC/oPPsw3c5D0bs=";
+    const SHARED_SECRET: &str =
+        "uzvJfB4u3N0Jy4T7NZ75MDVcr8zSTInedJtkgcu46YW4XByzNJjxBdtjUkdJPBtbmHhIDi6pcl8jsasjlTMtDQ==";
+    const CREATED: u64 = 1618884473;
+
+    #[handler(internal)]
+    fn index(signature: &VerifiedSignature) -> String {
+        format!("{}:{}", signature.label, signature.key_id)
+    }
+
+    fn keys() -> HashMap<String, VerifyingKey> {
+        let public = STANDARD.decode(ED25519_PUBLIC).unwrap();
+        HashMap::from([
+            (
+                "test-key-ed25519".to_string(),
+                VerifyingKey::ed25519(&public[public.len() - 32..]),
+            ),
+            (
+                "test-shared-secret".to_string(),
+                VerifyingKey::hmac_sha256(STANDARD.decode(SHARED_SECRET).unwrap()),
+            ),
+        ])
+    }
+
+    fn clock() -> SharedClock {
+        SharedClock::new(MockClock::starting_at(
+            UNIX_EPOCH + Duration::from_secs(CREATED),
+        ))
+    }
+
+    fn test_request<E: Endpoint>(cli: &TestClient<E>) -> TestRequestBuilder<'_, E> {
+        cli.post("/foo?param=Value&Pet=dog")
+            .header("host", "example.com")
+            .header("date", "Tue, 20 Apr 2021 02:07:55 GMT")
+            .content_type("application/json")
+            .header(
+                "content-digest",
+                "sha-512=:WZDPaVn/7XgHaAy8pmojAkGWoRx2UFChF41A2svX+TaPm+AbwAgBWnrIiYllu7BNNyealdVLvRwEmTHWXvJwew==:",
+            )
+            .header("content-length", "18")
+            .body(r#"{"hello": "world"}"#)
+    }
+
+    #[test]
+    fn parse_structured_fields() {
+        let mut parser = Parser::new(
+            r#"sig1=("@method" "@query-param";name="Pet" "content-digest";req);created=1;keyid="a\"b", sig2=()"#,
+        );
+        let members = parser
+            .dictionary(|parser| Some((parser.inner_list()?, parser.params()?)))
+            .unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].0, "sig1");
+        assert_eq!(members[0].1 .0.len(), 3);
+        assert_eq!(
+            members[0].1 .1,
+            vec![
+                ("created".to_string(), BareItem::Integer(1)),
+                ("keyid".to_string(), BareItem::String("a\"b".to_string())),
+            ]
+        );
+        assert!(members[1].1 .0.is_empty());
+
+        let input = SignatureInput {
+            components: members[0]
+                .1
+                 .0
+                .iter()
+                .cloned()
+                .map(|(item, params)| Component::from_item(item, params).unwrap())
+                .collect(),
+            params: members[0].1 .1.clone(),
+        };
+        assert_eq!(
+            input.serialize(),
+            r#"("@method" "@query-param";name="Pet" "content-digest";req);created=1;keyid="a\"b""#
+        );
+
+        for value in ["sig1", "sig1=(", "sig1=(\"a\"", "sig1=(), ", "Sig1=()"] {
+            assert!(Parser::new(value)
+                .dictionary(|parser| parser.inner_list())
+                .is_none());
+        }
+        assert!(Component::from_item(
+            BareItem::String("date".to_string()),
+            vec![("sf".to_string(), BareItem::Boolean(true))]
+        )
+        .is_none());
+    }
+
+    #[tokio::test]
+    async fn signature_base() {
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(Uri::from_static("/foo?param=Value&Pet=dog"))
+            .header("host", "Example.com")
+            .header("x-list", "a, b")
+            .header("x-list", " c ")
+            .finish();
+        let req = RequestParts::from_request(&req);
+        let value = |id: &str| component_value(&Component::parse(id), &req, None);
+
+        assert_eq!(value("@method").as_deref(), Some("POST"));
+        assert_eq!(
+            value("@target-uri").as_deref(),
+            Some("http://example.com/foo?param=Value&Pet=dog")
+        );
+        assert_eq!(value("@authority").as_deref(), Some("example.com"));
+        assert_eq!(value("@scheme").as_deref(), Some("http"));
+        assert_eq!(
+            value("@request-target").as_deref(),
+            Some("/foo?param=Value&Pet=dog")
+        );
+        assert_eq!(value("@path").as_deref(), Some("/foo"));
+        assert_eq!(value("@query").as_deref(), Some("?param=Value&Pet=dog"));
+        assert_eq!(value(r#"@query-param;name="Pet""#).as_deref(), Some("dog"));
+        assert_eq!(value(r#"@query-param;name="pet""#), None);
+        assert_eq!(value("x-list").as_deref(), Some("a, b, c"));
+        assert_eq!(value("x-missing"), None);
+        assert_eq!(value("@status"), None);
+        assert_eq!(value("x-list;req"), None);
+
+        let headers = HeaderMap::new();
+        let resp = Some((StatusCode::OK, &headers));
+        let value = |id: &str| component_value(&Component::parse(id), &req, resp);
+        assert_eq!(value("@status").as_deref(), Some("200"));
+        assert_eq!(value("@method"), None);
+        assert_eq!(value("@method;req").as_deref(), Some("POST"));
+        assert_eq!(value("x-list;req").as_deref(), Some("a, b, c"));
+        assert_eq!(value("x-list"), None);
+
+        // https://www.rfc-editor.org/rfc/rfc9421#section-2.2.8
+        let req = Request::builder()
+            .uri(Uri::from_static(
+                "/path?var=this%20is%20a%20big%0Avalue&bar=with+plus+whitespace&fa%C3%A7ade%22%3A%20=something",
+            ))
+            .finish();
+        let req = RequestParts::from_request(&req);
+        let value = |id: &str| component_value(&Component::parse(id), &req, None);
+        assert_eq!(
+            value(r#"@query-param;name="var""#).as_deref(),
+            Some("this%20is%20a%20big%0Avalue")
+        );
+        assert_eq!(
+            value(r#"@query-param;name="bar""#).as_deref(),
+            Some("with%20plus%20whitespace")
+        );
+        assert_eq!(
+            value(r#"@query-param;name="fa%C3%A7ade%22%3A%20""#).as_deref(),
+            Some("something")
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_test_vectors() {
+        let app = index.with(VerifySignature::new(keys())).data(clock());
+        let cli = TestClient::new(app);
+
+        // RFC 9421 B.2.6
+        test_request(&cli)
+            .header(
+                "signature-input",
+                r#"sig-b26=("date" "@method" "@path" "@authority" "content-type" "content-length");created=1618884473;keyid="test-key-ed25519""#,
+            )
+            .header(
+                "signature",
+                "sig-b26=:wqcAqbmYJ2ji2glfAMaRy4gruYYnx2nEFN2HN6jrnDnQCK1u02Gb04v9EDgwUPiu4A0w6vuQv5lIp5WPpBKRCw==:",
+            )
+            .send()
+            .await
+            .assert_text("sig-b26:test-key-ed25519")
+            .await;
+
+        // RFC 9421 B.2.5, which does not cover `@method` and `@path`
+        let resp = test_request(&cli)
+            .header(
+                "signature-input",
+                r#"sig-b25=("date" "@authority" "content-type");created=1618884473;keyid="test-shared-secret""#,
+            )
+            .header(
+                "signature",
+                "sig-b25=:pxcQw6G3AjtMBQjwo8XzkZf/bws5LelbaMk5rGIGtE8=:",
+            )
+            .send()
+            .await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+
+        let app = index
+            .with(VerifySignature::new(keys()).components(["@authority"]))
+            .data(clock());
+        let cli = TestClient::new(app);
+        test_request(&cli)
+            .header(
+                "signature-input",
+                r#"sig-b25=("date" "@authority" "content-type");created=1618884473;keyid="test-shared-secret""#,
+            )
+            .header(
+                "signature",
+                "sig-b25=:pxcQw6G3AjtMBQjwo8XzkZf/bws5LelbaMk5rGIGtE8=:",
+            )
+            .send()
+            .await
+            .assert_text("sig-b25:test-shared-secret")
+            .await;
+    }
+
+    #[tokio::test]
+    async fn verify_errors() {
+        let input = r#"sig-b26=("date" "@method" "@path" "@authority" "content-type" "content-length");created=1618884473;keyid="test-key-ed25519""#;
+        let signature = "sig-b26=:wqcAqbmYJ2ji2glfAMaRy4gruYYnx2nEFN2HN6jrnDnQCK1u02Gb04v9EDgwUPiu4A0w6vuQv5lIp5WPpBKRCw==:";
+        let clock = MockClock::starting_at(UNIX_EPOCH + Duration::from_secs(CREATED));
+        let ep = VerifySignature::new(keys())
+            .transform(index)
+            .data(SharedClock::new(clock.clone()));
+        let cli = TestClient::new(ep);
+
+        let check = |input: String, signature: String, err: HttpSignatureError| {
+            let req = test_request(&cli)
+                .header("signature-input", input)
+                .header("signature", signature);
+            async move {
+                let resp = req.send().await;
+                resp.assert_status(err.status());
+                resp.assert_text(err.to_string()).await;
+            }
+        };
+
+        check(
+            input.to_string(),
+            "sig1=:AAAA:".to_string(),
+            HttpSignatureError::Invalid,
+        )
+        .await;
+        check(
+            input.replace("test-key-ed25519", "unknown"),
+            signature.to_string(),
+            HttpSignatureError::UnknownKey,
+        )
+        .await;
+        check(
+            format!(r#"{input};alg="hmac-sha256""#),
+            signature.to_string(),
+            HttpSignatureError::AlgorithmMismatch,
+        )
+        .await;
+        check(
+            input.replace("\"date\" ", ""),
+            signature.to_string(),
+            HttpSignatureError::Failed,
+        )
+        .await;
+        check(
+            input.replace(";created=1618884473", ""),
+            signature.to_string(),
+            HttpSignatureError::Expired,
+        )
+        .await;
+        check(
+            input.replace("created=1618884473", "created=1618894473"),
+            signature.to_string(),
+            HttpSignatureError::Expired,
+        )
+        .await;
+        check(
+            input.replace("created=1618884473", "created=1618884476"),
+            signature.to_string(),
+            HttpSignatureError::Failed,
+        )
+        .await;
+        check(
+            format!("{input};expires=1618884473"),
+            signature.to_string(),
+            HttpSignatureError::Expired,
+        )
+        .await;
+
+        clock.advance(Duration::from_secs(5 * 60 + 1));
+        check(
+            input.to_string(),
+            signature.to_string(),
+            HttpSignatureError::Expired,
+        )
+        .await;
+
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+
+        let ep = VerifySignature::new(keys())
+            .label("sig1")
+            .tag("app")
+            .transform(index)
+            .data(self::clock());
+        let cli = TestClient::new(ep);
+        test_request(&cli)
+            .header("signature-input", input)
+            .header("signature", signature)
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn sign_and_verify() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&signature::ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+            .unwrap();
+        let public_key = EcdsaKeyPair::from_pkcs8(
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            pkcs8.as_ref(),
+            &rng,
+        )
+        .unwrap()
+        .public_key()
+        .as_ref()
+        .to_vec();
+        let signing_key = SigningKey::ecdsa_p256_sha256_pkcs8(pkcs8.as_ref()).unwrap();
+        let verifying_key = VerifyingKey::ecdsa_p256_sha256(public_key);
+
+        let resolved = verifying_key.clone();
+        let resolver = move |key_id: String| {
+            let key = resolved.clone();
+            async move { Ok((key_id == "server").then_some(key)) }
+        };
+
+        #[handler(internal)]
+        fn hello() -> &'static str {
+            "hello"
+        }
+
+        let app = hello
+            .with(
+                SignResponse::new("server", signing_key)
+                    .components([
+                        "@status",
+                        "content-type",
+                        "x-missing",
+                        "@method;req",
+                        "@authority;req",
+                    ])
+                    .expires_in(Duration::from_secs(60))
+                    .tag("app"),
+            )
+            .with(
+                VerifySignature::new(resolver)
+                    .components(["@method"])
+                    .tag("app"),
+            )
+            .data(clock());
+        let cli = TestClient::new(app);
+
+        // the request must be signed
+        let resp = cli.get("/").header("host", "example.com").send().await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+
+        let request_key = SigningKey::ecdsa_p256_sha256_pkcs8(pkcs8.as_ref()).unwrap();
+        let input = SignatureInput {
+            components: vec![Component::parse("@method"), Component::parse("@path")],
+            params: vec![
+                ("created".to_string(), BareItem::Integer(CREATED as i64)),
+                ("keyid".to_string(), BareItem::String("server".to_string())),
+                ("tag".to_string(), BareItem::String("app".to_string())),
+            ],
+        };
+        let req = Request::builder().uri(Uri::from_static("/")).finish();
+        let base = input
+            .signature_base(&RequestParts::from_request(&req), None)
+            .unwrap();
+        let resp = cli
+            .get("/")
+            .header("host", "example.com")
+            .header("signature-input", format!("req={}", input.serialize()))
+            .header(
+                "signature",
+                format!(
+                    "req=:{}:",
+                    STANDARD.encode(request_key.sign(base.as_bytes()))
+                ),
+            )
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+
+        let mut parsed = parse_signatures(resp.0.headers()).unwrap();
+        assert_eq!(parsed.len(), 1);
+        let (label, input, signature) = parsed.remove(0);
+        assert_eq!(label, "sig1");
+        assert_eq!(
+            input.serialize(),
+            format!(
+                r#"("@status" "content-type" "@method";req "@authority";req);created={CREATED};expires={};keyid="server";alg="ecdsa-p256-sha256";tag="app""#,
+                CREATED + 60
+            )
+        );
+
+        let req = Request::builder().header("host", "example.com").finish();
+        let base = input
+            .signature_base(
+                &RequestParts::from_request(&req),
+                Some((resp.0.status(), resp.0.headers())),
+            )
+            .unwrap();
+        assert_eq!(
+            base,
+            format!(
+                "\"@status\": 200\n\"content-type\": text/plain; charset=utf-8\n\"@method\";req: GET\n\"@authority\";req: example.com\n\"@signature-params\": {}",
+                input.serialize()
+            )
+        );
+        assert!(verifying_key.verify(base.as_bytes(), &signature));
+    }
+
+    #[test]
+    fn sign_keys() {
+        let rng = SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let public_key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())
+            .unwrap()
+            .public_key()
+            .as_ref()
+            .to_vec();
+        let signature = SigningKey::ed25519_pkcs8(pkcs8.as_ref())
+            .unwrap()
+            .sign(b"hello");
+        assert!(VerifyingKey::ed25519(public_key.clone()).verify(b"hello", &signature));
+        assert!(!VerifyingKey::ed25519(public_key).verify(b"world", &signature));
+
+        let signature = SigningKey::ed25519_pkcs8(&STANDARD.decode(ED25519_PRIVATE).unwrap())
+            .unwrap()
+            .sign(b"hello");
+        assert!(keys()["test-key-ed25519"].verify(b"hello", &signature));
+
+        let signature = SigningKey::hmac_sha256(b"secret").sign(b"hello");
+        assert!(VerifyingKey::hmac_sha256(b"secret").verify(b"hello", &signature));
+        assert!(!VerifyingKey::hmac_sha256(b"other").verify(b"hello", &signature));
+
+        assert!(SigningKey::rsa_pss_sha512_pkcs8(b"invalid").is_err());
+        assert_eq!(
+            SignatureAlgorithm::from_name("rsa-v1_5-sha256"),
+            Some(SignatureAlgorithm::RsaV15Sha256)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid component of the response: `@method`")]
+    fn sign_response_components() {
+        let _ =
+            SignResponse::new("key", SigningKey::hmac_sha256(b"secret")).components(["@method"]);
+    }
+}
@@ -0,0 +1,652 @@
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use http::{HeaderName, HeaderValue, StatusCode};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    clock::SharedClock, error::IdempotencyError, http::Method, Body, Endpoint, IntoResponse,
+    Middleware, Request, Response, Result,
+};
+
+const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// A response stored by the [`Idempotency`] middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "idempotency")))]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    /// The fingerprint of the request.
+    pub fingerprint: String,
+    /// The status code of the response.
+    pub status: u16,
+    /// The headers of the response.
+    pub headers: Vec<(String, String)>,
+    /// The body of the response.
+    pub body: Vec<u8>,
+}
+
+/// The state of an idempotency key.
+#[cfg_attr(docsrs, doc(cfg(feature = "idempotency")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum IdempotencyState {
+    /// The key is new and has been locked by the current request.
+    Acquired,
+    /// The key is locked by another request that is being processed.
+    InProgress {
+        /// The fingerprint of the request.
+        fingerprint: String,
+    },
+    /// The request with the key has been processed.
+    Completed(IdempotencyRecord),
+}
+
+/// Represents a back-end storage of the idempotency keys and the responses.
+#[cfg_attr(docsrs, doc(cfg(feature = "idempotency")))]
+pub trait IdempotencyStore: Send + Sync + 'static {
+    /// Locks the key for the request with the fingerprint if the key does not
+    /// exist, or returns the current state of the key.
+    ///
+    /// The lock expires after `ttl`, so that the key can be reused if the
+    /// processing is interrupted.
+    ///
+    /// `now` is the time of the request since the unix epoch, the storages
+    /// shared by multiple servers may use their own time instead.
+    fn lock<'a>(
+        &'a self,
+        key: &'a str,
+        fingerprint: &'a str,
+        ttl: Duration,
+        now: Duration,
+    ) -> impl Future<Output = Result<IdempotencyState>> + Send + 'a;
+
+    /// Stores the response of the locked key, which expires after `ttl`.
+    ///
+    /// `now` is the time since the unix epoch, as in
+    /// [`IdempotencyStore::lock`].
+    fn complete<'a>(
+        &'a self,
+        key: &'a str,
+        record: &'a IdempotencyRecord,
+        ttl: Duration,
+        now: Duration,
+    ) -> impl Future<Output = Result<()>> + Send + 'a;
+
+    /// Releases the locked key without storing the response.
+    fn release<'a>(&'a self, key: &'a str) -> impl Future<Output = Result<()>> + Send + 'a;
+}
+
+type ScopeFn = Arc<dyn Fn(&Request) -> String + Send + Sync>;
+
+enum Entry {
+    Locked(String),
+    Completed(IdempotencyRecord),
+}
+
+/// An idempotency store using memory.
+#[cfg_attr(docsrs, doc(cfg(feature = "idempotency")))]
+#[derive(Default)]
+pub struct MemoryIdempotencyStore {
+    inner: Mutex<MemoryInner>,
+}
+
+/// The expiration time of an entry since the unix epoch, `None` means never.
+type ExpiresAt = Option<Duration>;
+
+fn is_alive(expires_at: ExpiresAt, now: Duration) -> bool {
+    expires_at.map_or(true, |expires_at| expires_at > now)
+}
+
+#[derive(Default)]
+struct MemoryInner {
+    entries: HashMap<String, (Entry, ExpiresAt)>,
+    next_purge: usize,
+}
+
+impl MemoryIdempotencyStore {
+    /// Create a `MemoryIdempotencyStore`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl IdempotencyStore for MemoryIdempotencyStore {
+    async fn lock<'a>(
+        &'a self,
+        key: &'a str,
+        fingerprint: &'a str,
+        ttl: Duration,
+        now: Duration,
+    ) -> Result<IdempotencyState> {
+        let mut inner = self.inner.lock();
+
+        // removes the expired keys when the map grows
+        if inner.entries.len() >= inner.next_purge {
+            inner
+                .entries
+                .retain(|_, (_, expires_at)| is_alive(*expires_at, now));
+            inner.next_purge = (inner.entries.len() * 2).max(1024);
+        }
+
+        match inner
+            .entries
+            .get(key)
+            .filter(|(_, expires_at)| is_alive(*expires_at, now))
+        {
+            Some((Entry::Locked(fingerprint), _)) => Ok(IdempotencyState::InProgress {
+                fingerprint: fingerprint.clone(),
+            }),
+            Some((Entry::Completed(record), _)) => Ok(IdempotencyState::Completed(record.clone())),
+            None => {
+                inner.entries.insert(
+                    key.to_string(),
+                    (Entry::Locked(fingerprint.to_string()), now.checked_add(ttl)),
+                );
+                Ok(IdempotencyState::Acquired)
+            }
+        }
+    }
+
+    async fn complete<'a>(
+        &'a self,
+        key: &'a str,
+        record: &'a IdempotencyRecord,
+        ttl: Duration,
+        now: Duration,
+    ) -> Result<()> {
+        self.inner.lock().entries.insert(
+            key.to_string(),
+            (Entry::Completed(record.clone()), now.checked_add(ttl)),
+        );
+        Ok(())
+    }
+
+    async fn release<'a>(&'a self, key: &'a str) -> Result<()> {
+        self.inner.lock().entries.remove(key);
+        Ok(())
+    }
+}
+
+/// Middleware for the `Idempotency-Key` header, see
+/// [The Idempotency-Key HTTP Header Field](https://datatracker.ietf.org/doc/draft-ietf-httpapi-idempotency-key-header/).
+///
+/// The first `POST` or `PATCH` request with a key is processed and its
+/// response is stored, the retries with the same key replay the stored
+/// response with the `Idempotent-Replayed: true` header. The retry is
+/// rejected with `409 Conflict` if the first request is still being
+/// processed, and with `422 Unprocessable Entity` if the method, the path or
+/// the body differs from the first request.
+///
+/// The responses with the server error status codes are not stored, so the
+/// requests can be retried.
+///
+/// The keys are global by default, and the requests of different clients with
+/// the same key, method, path and body replay the same response. If the keys
+/// are chosen by the clients, use [`Idempotency::scope`] to scope them to the
+/// authenticated caller so that a client cannot obtain the response of
+/// another one.
+///
+/// # Errors
+///
+/// - [`IdempotencyError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     middleware::{Idempotency, MemoryIdempotencyStore},
+///     post, EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn create_payment() -> &'static str {
+///     "created"
+/// }
+///
+/// let app = Route::new().at(
+///     "/payments",
+///     post(create_payment).with(Idempotency::new(MemoryIdempotencyStore::new()).required()),
+/// );
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "idempotency")))]
+pub struct Idempotency<S> {
+    store: Arc<S>,
+    scope: Option<ScopeFn>,
+    methods: Vec<Method>,
+    required: bool,
+    ttl: Duration,
+    lock_timeout: Duration,
+}
+
+impl<S: IdempotencyStore> Idempotency<S> {
+    /// Create `Idempotency` middleware with the store.
+    pub fn new(store: S) -> Self {
+        Self {
+            store: Arc::new(store),
+            scope: None,
+            methods: vec![Method::POST, Method::PATCH],
+            required: false,
+            ttl: Duration::from_secs(24 * 60 * 60),
+            lock_timeout: Duration::from_secs(60),
+        }
+    }
+
+    /// Sets the methods that the idempotency keys apply to.
+    ///
+    /// Default is `POST` and `PATCH`.
+    #[must_use]
+    pub fn methods(self, methods: impl IntoIterator<Item = Method>) -> Self {
+        Self {
+            methods: methods.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Sets the function which returns the scope of the keys of a request, such
+    /// as the id of the authenticated user, the same key in different scopes
+    /// refers to different requests.
+    #[must_use]
+    pub fn scope(self, f: impl Fn(&Request) -> String + Send + Sync + 'static) -> Self {
+        Self {
+            scope: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
+    /// Rejects the requests without the `Idempotency-Key` header with `400 Bad
+    /// Request`.
+    #[must_use]
+    pub fn required(self) -> Self {
+        Self {
+            required: true,
+            ..self
+        }
+    }
+
+    /// Sets the retention time of the stored responses.
+    ///
+    /// Default is `24h`.
+    #[must_use]
+    pub fn ttl(self, ttl: Duration) -> Self {
+        Self { ttl, ..self }
+    }
+
+    /// Sets the time after which a key whose request is still being processed
+    /// can be reused, in case the processing was interrupted.
+    ///
+    /// Default is `60s`.
+    #[must_use]
+    pub fn lock_timeout(self, timeout: Duration) -> Self {
+        Self {
+            lock_timeout: timeout,
+            ..self
+        }
+    }
+}
+
+impl<S: IdempotencyStore, E: Endpoint> Middleware<E> for Idempotency<S> {
+    type Output = IdempotencyEndpoint<S, E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        IdempotencyEndpoint {
+            inner: ep,
+            store: self.store.clone(),
+            scope: self.scope.clone(),
+            methods: self.methods.clone(),
+            required: self.required,
+            ttl: self.ttl,
+            lock_timeout: self.lock_timeout,
+        }
+    }
+}
+
+/// Endpoint for the Idempotency middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "idempotency")))]
+pub struct IdempotencyEndpoint<S, E> {
+    inner: E,
+    store: Arc<S>,
+    scope: Option<ScopeFn>,
+    methods: Vec<Method>,
+    required: bool,
+    ttl: Duration,
+    lock_timeout: Duration,
+}
+
+fn fingerprint(req: &Request, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(req.method().as_str());
+    hasher.update([0]);
+    hasher.update(
+        req.uri()
+            .path_and_query()
+            .map(|path| path.as_str())
+            .unwrap_or_default(),
+    );
+    hasher.update([0]);
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+fn replay(record: IdempotencyRecord) -> Response {
+    let mut resp = Response::builder()
+        .status(StatusCode::from_u16(record.status).unwrap_or(StatusCode::OK))
+        .body(record.body);
+    for (name, value) in record.headers {
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+            resp.headers_mut().append(name, value);
+        }
+    }
+    resp.headers_mut()
+        .insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    resp
+}
+
+impl<S: IdempotencyStore, E: Endpoint> Endpoint for IdempotencyEndpoint<S, E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if !self.methods.contains(req.method()) {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        }
+        let Some(key) = req
+            .headers()
+            .get(IDEMPOTENCY_KEY)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().trim_matches('"').to_string())
+            .filter(|key| !key.is_empty())
+        else {
+            if self.required {
+                return Err(IdempotencyError::MissingKey.into());
+            }
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        };
+        // the key cannot contain a newline, so the scoped keys are unambiguous
+        let key = match &self.scope {
+            Some(scope) => format!("{}\n{key}", scope(&req)),
+            None => key,
+        };
+
+        let body = req.take_body().into_bytes().await?;
+        let fingerprint = fingerprint(&req, &body);
+        req.set_body(body);
+
+        let clock = SharedClock::from_request(&req);
+        match self
+            .store
+            .lock(
+                &key,
+                &fingerprint,
+                self.lock_timeout,
+                clock.elapsed_since_epoch(),
+            )
+            .await?
+        {
+            IdempotencyState::Acquired => {}
+            IdempotencyState::InProgress { fingerprint: other } if other == fingerprint => {
+                return Err(IdempotencyError::Conflict.into());
+            }
+            IdempotencyState::Completed(record) if record.fingerprint == fingerprint => {
+                return Ok(replay(record));
+            }
+            IdempotencyState::InProgress { .. } | IdempotencyState::Completed(_) => {
+                return Err(IdempotencyError::Mismatch.into());
+            }
+        }
+
+        let resp = match self.inner.call(req).await {
+            Ok(resp) => resp.into_response(),
+            Err(err) => err.into_response(),
+        };
+        if resp.status().is_server_error() {
+            self.store.release(&key).await?;
+            return Ok(resp);
+        }
+
+        let (parts, body) = resp.into_parts();
+        let body: Bytes = body.into_bytes().await?;
+        let record = IdempotencyRecord {
+            fingerprint,
+            status: parts.status.as_u16(),
+            headers: parts
+                .headers
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            body: body.to_vec(),
+        };
+        self.store
+            .complete(&key, &record, self.ttl, clock.elapsed_since_epoch())
+            .await?;
+        Ok(Response::from_parts(parts, Body::from(body)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{clock::MockClock, handler, test::TestClient, web::Data, EndpointExt};
+
+    #[handler(internal)]
+    async fn create(counter: Data<&Arc<AtomicUsize>>, body: String) -> Response {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        Response::builder()
+            .status(StatusCode::CREATED)
+            .header("x-n", n.to_string())
+            .body(format!("{body}{n}"))
+    }
+
+    #[tokio::test]
+    async fn replay_and_conflicts() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let cli = TestClient::new(
+            create
+                .with(Idempotency::new(MemoryIdempotencyStore::new()))
+                .data(counter.clone()),
+        );
+
+        let (first, second) = tokio::join!(
+            cli.post("/")
+                .header("idempotency-key", "a")
+                .body("x")
+                .send(),
+            async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                cli.post("/")
+                    .header("idempotency-key", "a")
+                    .body("x")
+                    .send()
+                    .await
+            }
+        );
+        first.assert_status(StatusCode::CREATED);
+        first.assert_text("x1").await;
+        second.assert_status(StatusCode::CONFLICT);
+
+        let resp = cli
+            .post("/")
+            .header("idempotency-key", "a")
+            .body("x")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::CREATED);
+        resp.assert_header("x-n", "1");
+        resp.assert_header("idempotent-replayed", "true");
+        resp.assert_text("x1").await;
+
+        cli.post("/")
+            .header("idempotency-key", "a")
+            .body("y")
+            .send()
+            .await
+            .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+        cli.post("/").body("x").send().await.assert_text("x2").await;
+        cli.get("/")
+            .header("idempotency-key", "a")
+            .body("x")
+            .send()
+            .await
+            .assert_text("x3")
+            .await;
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn required_and_errors() {
+        let failures = Arc::new(AtomicUsize::new(0));
+        let ep = crate::endpoint::make({
+            let failures = failures.clone();
+            move |_| {
+                let failures = failures.clone();
+                async move {
+                    if failures.fetch_add(1, Ordering::SeqCst) == 0 {
+                        StatusCode::SERVICE_UNAVAILABLE
+                    } else {
+                        StatusCode::OK
+                    }
+                }
+            }
+        });
+        let cli =
+            TestClient::new(ep.with(Idempotency::new(MemoryIdempotencyStore::new()).required()));
+
+        cli.post("/")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.post("/")
+            .header("idempotency-key", "a")
+            .send()
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        cli.post("/")
+            .header("idempotency-key", "a")
+            .send()
+            .await
+            .assert_status_is_ok();
+        assert_eq!(failures.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn scoped_keys() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let cli = TestClient::new(
+            create
+                .with(
+                    Idempotency::new(MemoryIdempotencyStore::new())
+                        .scope(|req| req.header("x-user").unwrap_or_default().to_string()),
+                )
+                .data(counter.clone()),
+        );
+        let send = |user: &'static str| {
+            cli.post("/")
+                .header("x-user", user)
+                .header("idempotency-key", "a")
+                .body("x")
+                .send()
+        };
+
+        send("alice").await.assert_text("x1").await;
+        send("bob").await.assert_text("x2").await;
+        let resp = send("alice").await;
+        resp.assert_header("idempotent-replayed", "true");
+        resp.assert_text("x1").await;
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn retention() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let clock = MockClock::new();
+        let cli = TestClient::new(
+            create
+                .with(Idempotency::new(MemoryIdempotencyStore::new()).ttl(Duration::from_secs(60)))
+                .data(counter.clone())
+                .data(SharedClock::new(clock.clone())),
+        );
+        let send = || {
+            cli.post("/")
+                .header("idempotency-key", "a")
+                .body("x")
+                .send()
+        };
+
+        send().await.assert_text("x1").await;
+        clock.advance(Duration::from_secs(59));
+        send().await.assert_text("x1").await;
+        clock.advance(Duration::from_secs(1));
+        send().await.assert_text("x2").await;
+
+        let cli = TestClient::new(
+            create
+                .with(Idempotency::new(MemoryIdempotencyStore::new()).ttl(Duration::MAX))
+                .data(counter.clone()),
+        );
+        cli.post("/")
+            .header("idempotency-key", "a")
+            .body("x")
+            .send()
+            .await
+            .assert_text("x3")
+            .await;
+    }
+
+    #[tokio::test]
+    async fn memory_store_expires() {
+        let store = MemoryIdempotencyStore::new();
+        let now = Duration::from_secs(100);
+        assert_eq!(
+            store.lock("a", "x", Duration::ZERO, now).await.unwrap(),
+            IdempotencyState::Acquired
+        );
+        assert_eq!(
+            store
+                .lock("a", "y", Duration::from_secs(60), now)
+                .await
+                .unwrap(),
+            IdempotencyState::Acquired
+        );
+        assert_eq!(
+            store
+                .lock("a", "z", Duration::from_secs(60), now)
+                .await
+                .unwrap(),
+            IdempotencyState::InProgress {
+                fingerprint: "y".to_string()
+            }
+        );
+        assert_eq!(
+            store
+                .lock(
+                    "a",
+                    "z",
+                    Duration::from_secs(60),
+                    now + Duration::from_secs(60)
+                )
+                .await
+                .unwrap(),
+            IdempotencyState::Acquired
+        );
+        assert_eq!(
+            store.lock("b", "x", Duration::MAX, now).await.unwrap(),
+            IdempotencyState::Acquired
+        );
+        assert_eq!(
+            store
+                .lock("b", "y", Duration::MAX, Duration::MAX)
+                .await
+                .unwrap(),
+            IdempotencyState::InProgress {
+                fingerprint: "x".to_string()
+            }
+        );
+    }
+}
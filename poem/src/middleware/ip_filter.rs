@@ -0,0 +1,819 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::IpAddr,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ipnet::{AddrParseError, IpNet};
+use parking_lot::RwLock;
+use tokio::sync::watch;
+
+use crate::{
+    clock::{Clock, SharedClock},
+    error::IpFilterError,
+    web::GeoInfo,
+    Endpoint, Middleware, Request, Result,
+};
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Node {
+    /// The indexes of the children, `0` if there is no child since the root
+    /// is never a child.
+    children: [u32; 2],
+    terminal: bool,
+}
+
+/// A binary trie of the network prefixes.
+#[derive(Debug, Clone)]
+struct Trie {
+    width: u32,
+    nodes: Vec<Node>,
+}
+
+impl Trie {
+    fn new(width: u32) -> Self {
+        Self {
+            width,
+            nodes: vec![Node::default()],
+        }
+    }
+
+    fn bit(&self, bits: u128, i: u32) -> usize {
+        ((bits >> (self.width - 1 - i)) & 1) as usize
+    }
+
+    fn insert(&mut self, bits: u128, prefix_len: u32) {
+        let mut idx = 0;
+        for i in 0..prefix_len {
+            if self.nodes[idx].terminal {
+                // covered by a shorter prefix
+                return;
+            }
+            let bit = self.bit(bits, i);
+            idx = match self.nodes[idx].children[bit] {
+                0 => {
+                    self.nodes.push(Node::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[idx].children[bit] = child as u32;
+                    child
+                }
+                child => child as usize,
+            };
+        }
+        // the longer prefixes are unreachable now
+        self.nodes[idx] = Node {
+            children: [0, 0],
+            terminal: true,
+        };
+    }
+
+    fn contains(&self, bits: u128) -> bool {
+        let mut idx = 0;
+        for i in 0..self.width {
+            if self.nodes[idx].terminal {
+                return true;
+            }
+            idx = match self.nodes[idx].children[self.bit(bits, i)] {
+                0 => return false,
+                child => child as usize,
+            };
+        }
+        self.nodes[idx].terminal
+    }
+}
+
+/// A set of the IPv4 and IPv6 networks, the lookups take the time
+/// proportional to the length of the address, regardless of the number of
+/// the networks.
+///
+/// The IPv4-mapped IPv6 addresses are matched as the IPv4 addresses.
+///
+/// It can be parsed from a list of the networks or the addresses separated by
+/// the whitespaces, and the lines starting with `#` are ignored.
+///
+/// # Example
+///
+/// ```
+/// use poem::middleware::CidrSet;
+///
+/// let set: CidrSet = "
+///     10.0.0.0/8
+///     192.168.0.0/16
+///     fd00::/8
+///     203.0.113.7
+/// "
+/// .parse()
+/// .unwrap();
+///
+/// assert!(set.contains("10.1.2.3".parse().unwrap()));
+/// assert!(set.contains("203.0.113.7".parse().unwrap()));
+/// assert!(!set.contains("203.0.113.8".parse().unwrap()));
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "ip-filter")))]
+#[derive(Debug, Clone)]
+pub struct CidrSet {
+    v4: Trie,
+    v6: Trie,
+    len: usize,
+}
+
+impl Default for CidrSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CidrSet {
+    /// Create an empty `CidrSet`.
+    pub fn new() -> Self {
+        Self {
+            v4: Trie::new(32),
+            v6: Trie::new(128),
+            len: 0,
+        }
+    }
+
+    /// Inserts a network, or a single address.
+    pub fn insert(&mut self, net: impl Into<IpNet>) {
+        match net.into().trunc() {
+            IpNet::V4(net) => self
+                .v4
+                .insert(u128::from(u32::from(net.addr())), net.prefix_len().into()),
+            IpNet::V6(net) => self
+                .v6
+                .insert(u128::from(net.addr()), net.prefix_len().into()),
+        }
+        self.len += 1;
+    }
+
+    /// Returns `true` if the address is in any of the networks.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match ip.to_canonical() {
+            IpAddr::V4(ip) => self.v4.contains(u128::from(u32::from(ip))),
+            IpAddr::V6(ip) => self.v6.contains(u128::from(ip)),
+        }
+    }
+
+    /// Returns the number of the inserted networks.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the set contains no networks.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<T: Into<IpNet>> FromIterator<T> for CidrSet {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for net in iter {
+            set.insert(net);
+        }
+        set
+    }
+}
+
+impl FromStr for CidrSet {
+    type Err = AddrParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut set = Self::new();
+        for line in s.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            for item in line.split_whitespace() {
+                match item.parse::<IpNet>() {
+                    Ok(net) => set.insert(net),
+                    Err(err) => set.insert(item.parse::<IpAddr>().map_err(|_| err)?),
+                }
+            }
+        }
+        Ok(set)
+    }
+}
+
+/// The rules of the [`IpFilter`] middleware.
+///
+/// A request is rejected if its IP address is in any of the denied networks,
+/// or not in any of the allowed networks when there are allowed networks.
+/// The countries are checked in the same way if there is a GeoIP lookup, and
+/// the requests from the unknown countries are rejected only when there are
+/// allowed countries.
+#[cfg_attr(docsrs, doc(cfg(feature = "ip-filter")))]
+#[derive(Debug, Clone, Default)]
+pub struct IpRules {
+    allow: CidrSet,
+    deny: CidrSet,
+    allow_countries: BTreeSet<String>,
+    deny_countries: BTreeSet<String>,
+}
+
+impl IpRules {
+    /// Create an empty `IpRules` which allows all the requests.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Allows a network, or a single address.
+    #[must_use]
+    pub fn allow(mut self, net: impl Into<IpNet>) -> Self {
+        self.allow.insert(net);
+        self
+    }
+
+    /// Denies a network, or a single address.
+    #[must_use]
+    pub fn deny(mut self, net: impl Into<IpNet>) -> Self {
+        self.deny.insert(net);
+        self
+    }
+
+    /// Sets the allowed networks.
+    #[must_use]
+    pub fn allow_set(self, set: CidrSet) -> Self {
+        Self { allow: set, ..self }
+    }
+
+    /// Sets the denied networks.
+    #[must_use]
+    pub fn deny_set(self, set: CidrSet) -> Self {
+        Self { deny: set, ..self }
+    }
+
+    /// Allows a country by the ISO 3166-1 alpha-2 code, such as `US`.
+    #[must_use]
+    pub fn allow_country(mut self, code: impl AsRef<str>) -> Self {
+        self.allow_countries
+            .insert(code.as_ref().to_ascii_uppercase());
+        self
+    }
+
+    /// Denies a country by the ISO 3166-1 alpha-2 code, such as `US`.
+    #[must_use]
+    pub fn deny_country(mut self, code: impl AsRef<str>) -> Self {
+        self.deny_countries
+            .insert(code.as_ref().to_ascii_uppercase());
+        self
+    }
+
+    fn check(&self, ip: Option<IpAddr>, geo: Option<&GeoInfo>) -> Result<(), IpFilterError> {
+        match ip {
+            Some(ip) if self.deny.contains(ip) => return Err(IpFilterError::IpDenied),
+            Some(ip) if !self.allow.is_empty() && !self.allow.contains(ip) => {
+                return Err(IpFilterError::IpDenied)
+            }
+            None if !self.allow.is_empty() => return Err(IpFilterError::IpDenied),
+            _ => {}
+        }
+
+        let country = geo
+            .and_then(|geo| geo.country.as_deref())
+            .map(str::to_ascii_uppercase);
+        match &country {
+            Some(country) if self.deny_countries.contains(country) => {
+                Err(IpFilterError::CountryDenied)
+            }
+            Some(country)
+                if !self.allow_countries.is_empty() && !self.allow_countries.contains(country) =>
+            {
+                Err(IpFilterError::CountryDenied)
+            }
+            None if !self.allow_countries.is_empty() => Err(IpFilterError::CountryDenied),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A lookup of the [`GeoInfo`] of the IP addresses.
+///
+/// It is implemented for the functions, and for
+/// [`GeoIpDatabase`](crate::middleware::GeoIpDatabase) with the `geoip`
+/// feature.
+#[cfg_attr(docsrs, doc(cfg(feature = "ip-filter")))]
+pub trait GeoIpLookup: Send + Sync + 'static {
+    /// Returns the information of the address, or `None` if it is unknown.
+    fn lookup(&self, ip: IpAddr) -> Option<GeoInfo>;
+}
+
+impl<F> GeoIpLookup for F
+where
+    F: Fn(IpAddr) -> Option<GeoInfo> + Send + Sync + 'static,
+{
+    fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+        (self)(ip)
+    }
+}
+
+/// A GeoIP lookup using a MaxMind database, such as GeoLite2 Country.
+#[cfg(feature = "geoip")]
+#[cfg_attr(docsrs, doc(cfg(feature = "geoip")))]
+pub struct GeoIpDatabase(maxminddb::Reader<Vec<u8>>);
+
+#[cfg(feature = "geoip")]
+impl GeoIpDatabase {
+    /// Opens the database file.
+    pub fn open(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    /// Create a `GeoIpDatabase` from the content of the database.
+    pub fn from_bytes(data: Vec<u8>) -> std::io::Result<Self> {
+        maxminddb::Reader::from_source(data)
+            .map(Self)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
+    }
+}
+
+#[cfg(feature = "geoip")]
+impl GeoIpLookup for GeoIpDatabase {
+    fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+        let record = self.0.lookup::<maxminddb::geoip2::Country<'_>>(ip).ok()?;
+        Some(GeoInfo {
+            country: record
+                .country
+                .and_then(|country| country.iso_code)
+                .map(ToString::to_string),
+            continent: record
+                .continent
+                .and_then(|continent| continent.code)
+                .map(ToString::to_string),
+        })
+    }
+}
+
+#[derive(Default)]
+struct BlocklistInner {
+    /// The expiration time of the blocked addresses, `None` means never.
+    blocked: HashMap<IpAddr, Option<Instant>>,
+    next_purge: usize,
+}
+
+/// A list of the temporarily blocked IP addresses, which is shared by the
+/// [`IpFilter`] middleware and the [`Honeypot`](crate::endpoint::Honeypot)
+/// endpoints.
+///
+/// The clones of a `Blocklist` share the same addresses.
+#[cfg_attr(docsrs, doc(cfg(feature = "ip-filter")))]
+#[derive(Clone, Default)]
+pub struct Blocklist(Arc<RwLock<BlocklistInner>>);
+
+impl Blocklist {
+    /// Create an empty `Blocklist`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Blocks the address for the duration.
+    ///
+    /// If the expiration time overflows, for example with [`Duration::MAX`],
+    /// the address is blocked forever.
+    pub fn block(&self, ip: IpAddr, ttl: Duration) {
+        self.block_at(ip, ttl, Instant::now());
+    }
+
+    pub(crate) fn block_at(&self, ip: IpAddr, ttl: Duration, now: Instant) {
+        let mut inner = self.0.write();
+
+        // removes the expired addresses when the map grows
+        if inner.blocked.len() >= inner.next_purge {
+            inner
+                .blocked
+                .retain(|_, expires| expires.map_or(true, |expires| expires > now));
+            inner.next_purge = (inner.blocked.len() * 2).max(1024);
+        }
+        inner
+            .blocked
+            .insert(ip.to_canonical(), now.checked_add(ttl));
+    }
+
+    /// Unblocks the address.
+    pub fn unblock(&self, ip: IpAddr) {
+        self.0.write().blocked.remove(&ip.to_canonical());
+    }
+
+    /// Returns `true` if the address is blocked.
+    pub fn is_blocked(&self, ip: IpAddr) -> bool {
+        self.is_blocked_at(ip, Instant::now())
+    }
+
+    pub(crate) fn is_blocked_at(&self, ip: IpAddr, now: Instant) -> bool {
+        self.0
+            .read()
+            .blocked
+            .get(&ip.to_canonical())
+            .is_some_and(|expires| expires.map_or(true, |expires| expires > now))
+    }
+}
+
+pub(crate) type IpFn = Arc<dyn Fn(&Request) -> Option<IpAddr> + Send + Sync>;
+
+/// Returns the IP address of the remote address.
+pub(crate) fn remote_ip(req: &Request) -> Option<IpAddr> {
+    req.remote_addr().as_socket_addr().map(|addr| addr.ip())
+}
+
+/// Middleware for allowing or denying the requests by the client IP address
+/// and its country.
+///
+/// The rejected requests respond `403 Forbidden` with [`IpFilterError`]. The
+/// rules are received from a watch channel with [`IpFilter::reloadable`], so
+/// they can be replaced at runtime without restarting the server.
+///
+/// With a GeoIP lookup, the [`GeoInfo`] of the client is also available to
+/// the handlers as an extractor.
+///
+/// The IP address is the remote address of the connection by default, the
+/// forwarding headers are not trusted.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use poem::{
+///     handler,
+///     http::StatusCode,
+///     middleware::{IpFilter, IpNet, IpRules},
+///     test::TestClient,
+///     web::GeoInfo,
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index(geo: &GeoInfo) -> String {
+///     geo.country.clone().unwrap_or_default()
+/// }
+///
+/// let rules = IpRules::new()
+///     .deny("192.0.2.0/24".parse::<IpNet>().unwrap())
+///     .deny_country("KP");
+/// let (tx, rx) = tokio::sync::watch::channel(Arc::new(rules));
+///
+/// let app = Route::new().at("/", index).with(
+///     IpFilter::reloadable(rx)
+///         .geoip(|_| {
+///             Some(GeoInfo {
+///                 country: Some("US".to_string()),
+///                 continent: Some("NA".to_string()),
+///             })
+///         })
+///         .ip(|req| req.header("x-client-ip")?.parse().ok()),
+/// );
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").header("x-client-ip", "192.0.2.1").send().await;
+/// resp.assert_status(StatusCode::FORBIDDEN);
+///
+/// let resp = cli
+///     .get("/")
+///     .header("x-client-ip", "198.51.100.1")
+///     .send()
+///     .await;
+/// resp.assert_text("US").await;
+///
+/// // replaces the rules
+/// tx.send_replace(Arc::new(IpRules::new().deny_country("US")));
+/// let resp = cli
+///     .get("/")
+///     .header("x-client-ip", "198.51.100.1")
+///     .send()
+///     .await;
+/// resp.assert_status(StatusCode::FORBIDDEN);
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "ip-filter")))]
+pub struct IpFilter {
+    rules: watch::Receiver<Arc<IpRules>>,
+    geoip: Option<Arc<dyn GeoIpLookup>>,
+    blocklist: Option<Blocklist>,
+    ip: IpFn,
+}
+
+impl IpFilter {
+    /// Create `IpFilter` middleware with the rules.
+    pub fn new(rules: IpRules) -> Self {
+        Self::reloadable(watch::channel(Arc::new(rules)).1)
+    }
+
+    /// Create `IpFilter` middleware with the receiver of the rules, the
+    /// latest rules are used for each request.
+    pub fn reloadable(rules: watch::Receiver<Arc<IpRules>>) -> Self {
+        Self {
+            rules,
+            geoip: None,
+            blocklist: None,
+            ip: Arc::new(remote_ip),
+        }
+    }
+
+    /// Rejects the requests from the addresses in the blocklist.
+    #[must_use]
+    pub fn blocklist(self, blocklist: Blocklist) -> Self {
+        Self {
+            blocklist: Some(blocklist),
+            ..self
+        }
+    }
+
+    /// Sets the GeoIP lookup, which is required by the country rules and the
+    /// [`GeoInfo`] extractor.
+    #[must_use]
+    pub fn geoip(self, lookup: impl GeoIpLookup) -> Self {
+        Self {
+            geoip: Some(Arc::new(lookup)),
+            ..self
+        }
+    }
+
+    /// Sets the function which returns the client IP address of a request,
+    /// for example from the `X-Forwarded-For` header set by a trusted proxy.
+    ///
+    /// Default is the IP address of the remote address.
+    #[must_use]
+    pub fn ip(self, f: impl Fn(&Request) -> Option<IpAddr> + Send + Sync + 'static) -> Self {
+        Self {
+            ip: Arc::new(f),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for IpFilter {
+    type Output = IpFilterEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        IpFilterEndpoint {
+            inner: ep,
+            rules: self.rules.clone(),
+            geoip: self.geoip.clone(),
+            blocklist: self.blocklist.clone(),
+            ip: self.ip.clone(),
+        }
+    }
+}
+
+/// Endpoint for the IpFilter middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "ip-filter")))]
+pub struct IpFilterEndpoint<E> {
+    inner: E,
+    rules: watch::Receiver<Arc<IpRules>>,
+    geoip: Option<Arc<dyn GeoIpLookup>>,
+    blocklist: Option<Blocklist>,
+    ip: IpFn,
+}
+
+impl<E: Endpoint> Endpoint for IpFilterEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let ip = (self.ip)(&req);
+        if let (Some(ip), Some(blocklist)) = (ip, &self.blocklist) {
+            if blocklist.is_blocked_at(ip, SharedClock::from_request(&req).now()) {
+                return Err(IpFilterError::Blocked.into());
+            }
+        }
+
+        let geo = self.geoip.as_ref().map(|geoip| {
+            ip.and_then(|ip| geoip.lookup(ip.to_canonical()))
+                .unwrap_or_default()
+        });
+
+        let rules = self.rules.borrow().clone();
+        rules.check(ip, geo.as_ref())?;
+
+        if let Some(geo) = geo {
+            req.extensions_mut().insert(geo);
+        }
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{clock::MockClock, endpoint::make_sync, test::TestClient, EndpointExt};
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn cidr_set() {
+        let set: CidrSet = "
+            # comment
+            10.0.0.0/8 172.16.0.0/12
+            10.1.0.0/16
+            192.0.2.1
+            2001:db8::/32
+            ::/0
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(set.len(), 6);
+        assert!(set.contains(ip("10.255.0.1")));
+        assert!(set.contains(ip("172.31.255.255")));
+        assert!(!set.contains(ip("172.32.0.0")));
+        assert!(set.contains(ip("192.0.2.1")));
+        assert!(!set.contains(ip("192.0.2.2")));
+        assert!(set.contains(ip("::ffff:10.0.0.1")));
+        assert!(set.contains(ip("fe80::1")));
+
+        let set = ["2001:db8::/32".parse::<IpNet>().unwrap()]
+            .into_iter()
+            .collect::<CidrSet>();
+        assert!(set.contains(ip("2001:db8:1::1")));
+        assert!(!set.contains(ip("2001:db9::1")));
+        assert!(!set.contains(ip("10.0.0.1")));
+
+        assert!("10.0.0.0/33".parse::<CidrSet>().is_err());
+        assert!("example.com".parse::<CidrSet>().is_err());
+    }
+
+    #[test]
+    fn shorter_prefix() {
+        let mut set = CidrSet::new();
+        set.insert("10.1.0.0/16".parse::<IpNet>().unwrap());
+        set.insert("10.0.0.0/8".parse::<IpNet>().unwrap());
+        set.insert("10.2.0.0/16".parse::<IpNet>().unwrap());
+        assert!(set.contains(ip("10.3.0.1")));
+        assert!(set.contains(ip("10.1.0.1")));
+    }
+
+    #[test]
+    fn rules() {
+        let us = GeoInfo {
+            country: Some("US".to_string()),
+            continent: None,
+        };
+        let rules = IpRules::new()
+            .allow("10.0.0.0/8".parse::<IpNet>().unwrap())
+            .deny(ip("10.0.0.1"));
+        assert_eq!(rules.check(Some(ip("10.0.0.2")), None), Ok(()));
+        assert_eq!(
+            rules.check(Some(ip("10.0.0.1")), None),
+            Err(IpFilterError::IpDenied)
+        );
+        assert_eq!(
+            rules.check(Some(ip("11.0.0.1")), None),
+            Err(IpFilterError::IpDenied)
+        );
+        assert_eq!(rules.check(None, None), Err(IpFilterError::IpDenied));
+
+        let rules = IpRules::new().allow_country("us");
+        assert_eq!(rules.check(None, Some(&us)), Ok(()));
+        assert_eq!(
+            rules.check(None, Some(&GeoInfo::default())),
+            Err(IpFilterError::CountryDenied)
+        );
+
+        let rules = IpRules::new().deny_country("US");
+        assert_eq!(
+            rules.check(None, Some(&us)),
+            Err(IpFilterError::CountryDenied)
+        );
+        assert_eq!(rules.check(None, Some(&GeoInfo::default())), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn ip_filter() {
+        let rules = IpRules::new().deny("127.0.0.0/8".parse::<IpNet>().unwrap());
+        let cli = TestClient::new(make_sync(|_| "hello").with(IpFilter::new(rules.clone())));
+        // the remote address of the test client is not an IP address
+        cli.get("/").send().await.assert_text("hello").await;
+
+        let cli = TestClient::new(
+            make_sync(|_| "hello")
+                .with(IpFilter::new(rules).ip(|req| req.header("x-client-ip")?.parse().ok())),
+        );
+        cli.get("/")
+            .header("x-client-ip", "127.0.0.1")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+        cli.get("/")
+            .header("x-client-ip", "::ffff:127.0.0.1")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+        cli.get("/")
+            .header("x-client-ip", "192.0.2.1")
+            .send()
+            .await
+            .assert_text("hello")
+            .await;
+
+        let rules = IpRules::new().allow("127.0.0.0/8".parse::<IpNet>().unwrap());
+        let cli = TestClient::new(make_sync(|_| "hello").with(IpFilter::new(rules)));
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn blocklist() {
+        let blocklist = Blocklist::new();
+        let cli = TestClient::new(
+            make_sync(|_| "hello").with(
+                IpFilter::new(IpRules::new())
+                    .blocklist(blocklist.clone())
+                    .ip(|req| req.header("x-client-ip")?.parse().ok()),
+            ),
+        );
+
+        blocklist.block(ip("192.0.2.1"), Duration::from_secs(60));
+        blocklist.block(ip("192.0.2.2"), Duration::ZERO);
+        blocklist.block(ip("192.0.2.3"), Duration::MAX);
+        assert!(blocklist.is_blocked(ip("192.0.2.3")));
+        assert!(blocklist.is_blocked(ip("::ffff:192.0.2.1")));
+        assert!(!blocklist.is_blocked(ip("192.0.2.2")));
+        cli.get("/")
+            .header("x-client-ip", "192.0.2.1")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+
+        blocklist.unblock(ip("192.0.2.1"));
+        cli.get("/")
+            .header("x-client-ip", "192.0.2.1")
+            .send()
+            .await
+            .assert_text("hello")
+            .await;
+    }
+
+    #[tokio::test]
+    async fn geo_info() {
+        let ep = make_sync(|req| {
+            req.extensions()
+                .get::<GeoInfo>()
+                .cloned()
+                .unwrap_or_default()
+                .country
+                .unwrap_or_default()
+        })
+        .with(
+            IpFilter::new(IpRules::new().allow_country("DE"))
+                .geoip(|ip: IpAddr| {
+                    ip.is_loopback().then(|| GeoInfo {
+                        country: Some("DE".to_string()),
+                        continent: Some("EU".to_string()),
+                    })
+                })
+                .ip(|req| req.header("x-client-ip")?.parse().ok()),
+        );
+        let cli = TestClient::new(ep);
+        cli.get("/")
+            .header("x-client-ip", "127.0.0.1")
+            .send()
+            .await
+            .assert_text("DE")
+            .await;
+        cli.get("/")
+            .header("x-client-ip", "192.0.2.1")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn blocklist_expires() {
+        let blocklist = Blocklist::new();
+        let clock = MockClock::new();
+        let cli = TestClient::new(
+            make_sync(|_| "hello")
+                .with(
+                    IpFilter::new(IpRules::new())
+                        .blocklist(blocklist.clone())
+                        .ip(|req| req.header("x-client-ip")?.parse().ok()),
+                )
+                .data(SharedClock::new(clock.clone())),
+        );
+
+        blocklist.block_at(ip("192.0.2.1"), Duration::from_secs(60), clock.now());
+        cli.get("/")
+            .header("x-client-ip", "192.0.2.1")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+
+        clock.advance(Duration::from_secs(60));
+        cli.get("/")
+            .header("x-client-ip", "192.0.2.1")
+            .send()
+            .await
+            .assert_text("hello")
+            .await;
+    }
+}
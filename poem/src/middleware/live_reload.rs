@@ -0,0 +1,270 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Once, Weak},
+    time::{Duration, SystemTime},
+};
+
+use futures_util::stream;
+use tokio::sync::watch;
+
+use crate::{
+    http::{header, Method},
+    web::sse::{Event, SSE},
+    Body, Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// Middleware which reloads the pages in the browsers when the watched files
+/// are changed.
+///
+/// A script is injected into the HTML responses, it subscribes to the reload
+/// channel, an SSE endpoint at `/__livereload` by default, and reloads the
+/// page when the files are changed. The files are checked for changes
+/// periodically, the watcher task is spawned when the first request is
+/// received, and stops when the endpoint is dropped.
+///
+/// It is intended for the development, so the templates and the static files
+/// can be edited without refreshing the pages by hand.
+///
+/// # Example
+///
+/// ```
+/// use poem::{get, handler, middleware::LiveReload, web::Html, EndpointExt, Route};
+///
+/// #[handler]
+/// fn index() -> Html<String> {
+///     Html(std::fs::read_to_string("./public/index.html").unwrap_or_default())
+/// }
+///
+/// let app = Route::new()
+///     .at("/", get(index))
+///     .with(LiveReload::new().watch("./public"));
+/// ```
+pub struct LiveReload {
+    paths: Vec<PathBuf>,
+    interval: Duration,
+    endpoint: String,
+}
+
+impl Default for LiveReload {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            interval: Duration::from_millis(500),
+            endpoint: "/__livereload".to_string(),
+        }
+    }
+}
+
+impl LiveReload {
+    /// Create a new `LiveReload` middleware.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Watches the file, or all the files in the directory.
+    #[must_use]
+    pub fn watch(mut self, path: impl Into<PathBuf>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Sets the interval of checking the files for changes.
+    ///
+    /// Default is `500ms`.
+    #[must_use]
+    pub fn interval(self, interval: Duration) -> Self {
+        Self { interval, ..self }
+    }
+
+    /// Sets the path of the reload channel.
+    ///
+    /// Default is `/__livereload`.
+    #[must_use]
+    pub fn endpoint(self, path: impl Into<String>) -> Self {
+        Self {
+            endpoint: path.into(),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for LiveReload {
+    type Output = LiveReloadEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        LiveReloadEndpoint {
+            inner: ep,
+            shared: Arc::new(Shared {
+                paths: self.paths.clone().into(),
+                interval: self.interval,
+                tx: watch::channel(0).0,
+                start: Once::new(),
+            }),
+            script: format!(
+                "<script>new EventSource({}).addEventListener(\"reload\",function(){{location.reload()}});</script>",
+                serde_json::Value::from(self.endpoint.as_str())
+            ),
+            endpoint: self.endpoint.clone(),
+        }
+    }
+}
+
+struct Shared {
+    paths: Arc<[PathBuf]>,
+    interval: Duration,
+    tx: watch::Sender<u64>,
+    start: Once,
+}
+
+/// Endpoint for the LiveReload middleware.
+pub struct LiveReloadEndpoint<E> {
+    inner: E,
+    shared: Arc<Shared>,
+    script: String,
+    endpoint: String,
+}
+
+impl<E: Endpoint> Endpoint for LiveReloadEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        self.shared.start.call_once(|| {
+            let fingerprint = fingerprint(&self.shared.paths);
+            crate::spawn::spawn(
+                "poem-live-reload",
+                watch_files(Arc::downgrade(&self.shared), fingerprint),
+            );
+        });
+
+        if req.method() == Method::GET && req.uri().path() == self.endpoint {
+            let mut rx = self.shared.tx.subscribe();
+            rx.borrow_and_update();
+            let events = stream::unfold(rx, |mut rx| async move {
+                rx.changed().await.ok()?;
+                Some((Event::message("reload").event_type("reload"), rx))
+            });
+            return Ok(SSE::new(events).into_response());
+        }
+
+        let mut resp = self.inner.call(req).await?.into_response();
+        let is_html = resp
+            .content_type()
+            .is_some_and(|content_type| content_type.starts_with("text/html"));
+        if !is_html || resp.headers().contains_key(header::CONTENT_ENCODING) {
+            return Ok(resp);
+        }
+
+        let mut html = resp.take_body().into_vec().await?;
+        let pos = html
+            .windows(7)
+            .rposition(|tag| tag.eq_ignore_ascii_case(b"</body>"))
+            .unwrap_or(html.len());
+        html.splice(pos..pos, self.script.bytes());
+        resp.headers_mut().remove(header::CONTENT_LENGTH);
+        resp.set_body(Body::from(html));
+        Ok(resp)
+    }
+}
+
+async fn watch_files(shared: Weak<Shared>, mut fingerprint: Fingerprint) {
+    loop {
+        let Some(interval) = shared.upgrade().map(|shared| shared.interval) else {
+            return;
+        };
+        tokio::time::sleep(interval).await;
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        let new_fingerprint = self::fingerprint(&shared.paths);
+        if new_fingerprint != fingerprint {
+            fingerprint = new_fingerprint;
+            tracing::debug!("files changed, reload the pages");
+            shared.tx.send_modify(|version| *version += 1);
+        }
+    }
+}
+
+type Fingerprint = Vec<(PathBuf, Option<SystemTime>, u64)>;
+
+fn fingerprint(paths: &[PathBuf]) -> Fingerprint {
+    fn visit(path: &Path, fingerprint: &mut Fingerprint) {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return;
+        };
+        if metadata.is_dir() {
+            if let Ok(entries) = std::fs::read_dir(path) {
+                for entry in entries.flatten() {
+                    visit(&entry.path(), fingerprint);
+                }
+            }
+        } else {
+            fingerprint.push((path.to_path_buf(), metadata.modified().ok(), metadata.len()));
+        }
+    }
+
+    let mut fingerprint = Vec::new();
+    for path in paths {
+        visit(path, &mut fingerprint);
+    }
+    fingerprint.sort();
+    fingerprint
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{endpoint::make_sync, handler, test::TestClient, web::Html, EndpointExt};
+
+    #[tokio::test]
+    async fn inject_script() {
+        #[handler(internal)]
+        fn index(req: &Request) -> Response {
+            match req.uri().path() {
+                "/text" => "</body>".into_response(),
+                _ => Html("<html><body>hello</body></html>").into_response(),
+            }
+        }
+
+        let cli = TestClient::new(index.with(LiveReload::new().endpoint("/reload")));
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text(
+            "<html><body>hello<script>new EventSource(\"/reload\").addEventListener(\"reload\",function(){location.reload()});</script></body></html>",
+        )
+        .await;
+
+        cli.get("/text").send().await.assert_text("</body>").await;
+    }
+
+    #[tokio::test]
+    async fn reload_channel() {
+        let dir = std::env::temp_dir().join(format!("poem-live-reload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("index.html"), "a").unwrap();
+
+        let ep = make_sync(|_| "hello").with(
+            LiveReload::new()
+                .watch(&dir)
+                .interval(Duration::from_millis(10)),
+        );
+        let resp = ep
+            .call(Request::builder().uri_str("/__livereload").finish())
+            .await
+            .unwrap();
+        assert_eq!(resp.content_type(), Some("text/event-stream"));
+        let mut events = resp.into_body().into_bytes_stream();
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        std::fs::write(dir.join("index.html"), "ab").unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(event, "event: reload\ndata: reload\n\n");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,334 @@
+use std::{net::IpAddr, sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use tokio::sync::watch;
+
+use crate::{
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    web::Json,
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// A switch of the maintenance mode, which can be toggled at runtime.
+///
+/// It is also an endpoint for the administrators to toggle the maintenance
+/// mode: `GET` returns the state as `{"enabled": bool}`, `POST` or `PUT`
+/// enables it and `DELETE` disables it. The endpoint should be protected by
+/// the authentication, and it must not be behind the [`Maintenance`]
+/// middleware unless the administrators are in the allowlist.
+#[derive(Clone)]
+pub struct MaintenanceSwitch(Arc<watch::Sender<bool>>);
+
+impl MaintenanceSwitch {
+    /// Create a new `MaintenanceSwitch` with the initial state.
+    pub fn new(enabled: bool) -> Self {
+        Self(Arc::new(watch::channel(enabled).0))
+    }
+
+    /// Enables the maintenance mode.
+    pub fn enable(&self) {
+        self.set(true);
+    }
+
+    /// Disables the maintenance mode.
+    pub fn disable(&self) {
+        self.set(false);
+    }
+
+    /// Sets the state of the maintenance mode.
+    pub fn set(&self, enabled: bool) {
+        self.0
+            .send_if_modified(|state| std::mem::replace(state, enabled) != enabled);
+    }
+
+    /// Returns `true` if the maintenance mode is enabled.
+    pub fn is_enabled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Returns a receiver of the state, which is used to create the
+    /// [`Maintenance`] middleware.
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.0.subscribe()
+    }
+}
+
+impl Endpoint for MaintenanceSwitch {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        match *req.method() {
+            Method::GET => {}
+            Method::POST | Method::PUT => self.enable(),
+            Method::DELETE => self.disable(),
+            _ => return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response()),
+        }
+        Ok(Json(serde_json::json!({ "enabled": self.is_enabled() })).into_response())
+    }
+}
+
+/// Middleware which responds `503 Service Unavailable` with the `Retry-After`
+/// header while the maintenance mode is enabled.
+///
+/// The state is received from a watch channel, which is usually created by a
+/// [`MaintenanceSwitch`]. The requests from the allowed IP addresses, or with
+/// the allowed headers, are passed through, so the service can be checked
+/// before the maintenance mode is disabled.
+///
+/// The IP address is the remote address of the connection, the forwarding
+/// headers are not trusted.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     http::StatusCode,
+///     middleware::{Maintenance, MaintenanceSwitch},
+///     test::TestClient,
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let switch = MaintenanceSwitch::new(false);
+/// let app = Route::new()
+///     .at(
+///         "/",
+///         get(index).with(
+///             Maintenance::new(switch.subscribe())
+///                 .allow_header("x-maintenance-bypass", "secret")
+///                 .html("<h1>Back soon</h1>"),
+///         ),
+///     )
+///     .at("/admin/maintenance", switch.clone());
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// cli.post("/admin/maintenance")
+///     .send()
+///     .await
+///     .assert_status_is_ok();
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+/// resp.assert_header("retry-after", "60");
+/// resp.assert_text("<h1>Back soon</h1>").await;
+///
+/// let resp = cli
+///     .get("/")
+///     .header("x-maintenance-bypass", "secret")
+///     .send()
+///     .await;
+/// resp.assert_text("hello").await;
+///
+/// switch.disable();
+/// cli.get("/").send().await.assert_text("hello").await;
+/// # });
+/// ```
+pub struct Maintenance {
+    state: watch::Receiver<bool>,
+    retry_after: Duration,
+    page: Option<(Bytes, &'static str)>,
+    ips: Vec<IpAddr>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl Maintenance {
+    /// Create a new `Maintenance` middleware with the receiver of the state.
+    pub fn new(state: watch::Receiver<bool>) -> Self {
+        Self {
+            state,
+            retry_after: Duration::from_secs(60),
+            page: None,
+            ips: Vec::new(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Sets the value of the `Retry-After` header.
+    ///
+    /// Default is `60s`.
+    #[must_use]
+    pub fn retry_after(self, retry_after: Duration) -> Self {
+        Self {
+            retry_after,
+            ..self
+        }
+    }
+
+    /// Sets the HTML page of the responses.
+    #[must_use]
+    pub fn html(self, page: impl Into<String>) -> Self {
+        Self {
+            page: Some((page.into().into(), "text/html; charset=utf-8")),
+            ..self
+        }
+    }
+
+    /// Sets the plain text of the responses.
+    #[must_use]
+    pub fn text(self, text: impl Into<String>) -> Self {
+        Self {
+            page: Some((text.into().into(), "text/plain; charset=utf-8")),
+            ..self
+        }
+    }
+
+    /// Passes the requests from the IP address through.
+    #[must_use]
+    pub fn allow_ip(mut self, ip: IpAddr) -> Self {
+        self.ips.push(ip);
+        self
+    }
+
+    /// Passes the requests with the header through.
+    #[must_use]
+    pub fn allow_header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: TryInto<HeaderName>,
+        V: TryInto<HeaderValue>,
+    {
+        if let (Ok(key), Ok(value)) = (key.try_into(), value.try_into()) {
+            self.headers.push((key, value));
+        }
+        self
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Maintenance {
+    type Output = MaintenanceEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        MaintenanceEndpoint {
+            inner: ep,
+            state: self.state.clone(),
+            retry_after: self.retry_after,
+            page: self.page.clone(),
+            ips: self.ips.clone(),
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+/// Endpoint for the `Maintenance` middleware.
+pub struct MaintenanceEndpoint<E> {
+    inner: E,
+    state: watch::Receiver<bool>,
+    retry_after: Duration,
+    page: Option<(Bytes, &'static str)>,
+    ips: Vec<IpAddr>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl<E> MaintenanceEndpoint<E> {
+    fn is_allowed(&self, req: &Request) -> bool {
+        let ip = req.remote_addr().as_socket_addr().map(|addr| addr.ip());
+        ip.is_some_and(|ip| self.ips.contains(&ip))
+            || self.headers.iter().any(|(key, value)| {
+                req.headers()
+                    .get_all(key)
+                    .iter()
+                    .any(|header_value| header_value == value)
+            })
+    }
+}
+
+impl<E: Endpoint> Endpoint for MaintenanceEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        if !*self.state.borrow() || self.is_allowed(&req) {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        }
+
+        let mut resp = match &self.page {
+            Some((page, content_type)) => Response::builder()
+                .content_type(*content_type)
+                .body(page.clone()),
+            None => Response::builder().body("service unavailable for maintenance"),
+        };
+        resp.set_status(StatusCode::SERVICE_UNAVAILABLE);
+        resp.headers_mut().insert(
+            header::RETRY_AFTER,
+            HeaderValue::from(self.retry_after.as_secs_f64().ceil().max(1.0) as u64),
+        );
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{endpoint::make_sync, test::TestClient, web::RemoteAddr, Addr, EndpointExt};
+
+    #[tokio::test]
+    async fn maintenance() {
+        let switch = MaintenanceSwitch::new(true);
+        let ep = make_sync(|_| "hello").with(
+            Maintenance::new(switch.subscribe())
+                .retry_after(Duration::from_millis(1500))
+                .allow_ip("10.0.0.1".parse().unwrap())
+                .allow_header("x-bypass", "1"),
+        );
+        let cli = TestClient::new(&ep);
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        resp.assert_header("retry-after", "2");
+        resp.assert_text("service unavailable for maintenance")
+            .await;
+
+        cli.get("/")
+            .header("x-bypass", "1")
+            .send()
+            .await
+            .assert_text("hello")
+            .await;
+        cli.get("/")
+            .header("x-bypass", "2")
+            .send()
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+
+        let mut req = Request::default();
+        req.state_mut().remote_addr = RemoteAddr(Addr::from(
+            "10.0.0.1:1234".parse::<std::net::SocketAddr>().unwrap(),
+        ));
+        let resp = ep.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        switch.disable();
+        cli.get("/").send().await.assert_text("hello").await;
+    }
+
+    #[tokio::test]
+    async fn admin_endpoint() {
+        let switch = MaintenanceSwitch::new(false);
+        let mut rx = switch.subscribe();
+        let cli = TestClient::new(switch.clone());
+
+        let resp = cli.get("/").send().await;
+        resp.assert_json(serde_json::json!({ "enabled": false }))
+            .await;
+
+        let resp = cli.put("/").send().await;
+        resp.assert_json(serde_json::json!({ "enabled": true }))
+            .await;
+        assert!(rx.has_changed().unwrap());
+        assert!(*rx.borrow_and_update());
+
+        cli.post("/").send().await.assert_status_is_ok();
+        assert!(!rx.has_changed().unwrap());
+
+        cli.delete("/").send().await.assert_status_is_ok();
+        assert!(!switch.is_enabled());
+
+        cli.patch("/")
+            .send()
+            .await
+            .assert_status(StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
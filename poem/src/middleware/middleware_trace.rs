@@ -0,0 +1,354 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::{Endpoint, IntoResponse, Middleware, Request, Response, Result};
+
+/// Records the middleware chain each request traverses, with the timing and
+/// the outcome of each layer, to find out which middleware rejected a
+/// request.
+///
+/// Only the middlewares wrapped by [`MiddlewareTrace::layer`] are recorded,
+/// and the traces of the recent requests are returned by the `middleware`
+/// section of the [`AdminEndpoints`](crate::endpoint::AdminEndpoints), see
+/// [`AdminEndpoints::middleware_trace`](crate::endpoint::AdminEndpoints::middleware_trace).
+///
+/// For each layer the trace contains the total duration, whether the layer
+/// called the next endpoint, and the status code or the error it returned.
+/// The `rejected_by` field is the name of the layer which returned without
+/// calling the next endpoint.
+///
+/// Recording has some overhead, so it is intended for debugging, and can be
+/// switched at runtime by [`MiddlewareTrace::set_enabled`].
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     endpoint::{make_sync, AdminEndpoints},
+///     http::StatusCode,
+///     middleware::{MiddlewareTrace, SetHeader, SizeLimit},
+///     test::TestClient,
+///     EndpointExt, Route,
+/// };
+///
+/// let trace = MiddlewareTrace::new();
+/// let app = Route::new()
+///     .at("/", make_sync(|_| "hello"))
+///     .with(trace.layer("size_limit", SizeLimit::new(1024)))
+///     .with(trace.layer("set_header", SetHeader::new().appending("x-a", "1")));
+/// let admin = AdminEndpoints::new()
+///     .middleware_trace(&trace)
+///     .guard(|_| true);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// // rejected without the `Content-Length` header
+/// let resp = TestClient::new(app).get("/").send().await;
+/// resp.assert_status(StatusCode::LENGTH_REQUIRED);
+///
+/// let resp = TestClient::new(admin).get("/middleware").send().await;
+/// let json = resp.json().await;
+/// let request = json.value().array().get(0).object();
+/// request.get("rejected_by").assert_string("size_limit");
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct MiddlewareTrace {
+    state: Arc<TraceState>,
+}
+
+struct TraceState {
+    enabled: AtomicBool,
+    capacity: usize,
+    requests: Mutex<VecDeque<RequestRecord>>,
+}
+
+impl Default for MiddlewareTrace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MiddlewareTrace {
+    /// Create a `MiddlewareTrace` which keeps the traces of the last `100`
+    /// requests.
+    pub fn new() -> Self {
+        Self::with_capacity(100)
+    }
+
+    /// Create a `MiddlewareTrace` which keeps the traces of the last
+    /// `capacity` requests.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            state: Arc::new(TraceState {
+                enabled: AtomicBool::new(true),
+                capacity,
+                requests: Mutex::new(VecDeque::with_capacity(capacity)),
+            }),
+        }
+    }
+
+    /// Enables or disables the recording, it is enabled by default.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.state.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the recording is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.state.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Clears the recorded traces.
+    pub fn clear(&self) {
+        self.state.requests.lock().clear();
+    }
+
+    /// Wraps a middleware to record it with the specified name.
+    pub fn layer<M>(&self, name: impl Into<Arc<str>>, middleware: M) -> TracedLayer<M> {
+        TracedLayer {
+            name: name.into(),
+            middleware,
+            state: self.state.clone(),
+        }
+    }
+
+    /// Returns a function to get the recent traces as JSON, the newest
+    /// first.
+    pub(crate) fn json(&self) -> impl Fn() -> serde_json::Value + Send + Sync + 'static {
+        let state = self.state.clone();
+        move || {
+            let requests = state.requests.lock();
+            serde_json::to_value(requests.iter().rev().collect::<Vec<_>>()).unwrap_or_default()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RequestRecord {
+    method: String,
+    path: String,
+    status: u16,
+    duration_us: u64,
+    rejected_by: Option<String>,
+    layers: Vec<LayerRecord>,
+}
+
+#[derive(Serialize)]
+struct LayerRecord {
+    name: String,
+    duration_us: u64,
+    next: bool,
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// The layers entered by a request.
+#[derive(Clone, Default)]
+struct ChainContext(Arc<Mutex<Vec<LayerRecord>>>);
+
+/// The index of the layer which is calling the next endpoint.
+#[derive(Clone, Copy)]
+struct CurrentLayer(usize);
+
+fn as_micros(duration: Duration) -> u64 {
+    duration.as_micros().try_into().unwrap_or(u64::MAX)
+}
+
+/// Middleware for [`MiddlewareTrace::layer`].
+pub struct TracedLayer<M> {
+    name: Arc<str>,
+    middleware: M,
+    state: Arc<TraceState>,
+}
+
+impl<E, M> Middleware<E> for TracedLayer<M>
+where
+    E: Endpoint,
+    M: Middleware<TracedNext<E>>,
+{
+    type Output = TracedLayerEndpoint<M::Output>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        TracedLayerEndpoint {
+            inner: self.middleware.transform(TracedNext { inner: ep }),
+            name: self.name.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// Endpoint for the [`TracedLayer`] middleware.
+pub struct TracedLayerEndpoint<E> {
+    inner: E,
+    name: Arc<str>,
+    state: Arc<TraceState>,
+}
+
+impl<E: Endpoint> Endpoint for TracedLayerEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if !self.state.enabled.load(Ordering::Relaxed) {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        }
+
+        let (ctx, root) = match req.extensions().get::<ChainContext>() {
+            Some(ctx) => (ctx.clone(), None),
+            None => {
+                let ctx = ChainContext::default();
+                req.extensions_mut().insert(ctx.clone());
+                (
+                    ctx,
+                    Some((req.method().to_string(), req.uri().path().to_string())),
+                )
+            }
+        };
+        let index = {
+            let mut layers = ctx.0.lock();
+            layers.push(LayerRecord {
+                name: self.name.to_string(),
+                duration_us: 0,
+                next: false,
+                status: None,
+                error: None,
+            });
+            layers.len() - 1
+        };
+        req.extensions_mut().insert(CurrentLayer(index));
+
+        let start = Instant::now();
+        let res = self.inner.call(req).await.map(IntoResponse::into_response);
+        let duration = start.elapsed();
+
+        let mut layers = ctx.0.lock();
+        let layer = &mut layers[index];
+        layer.duration_us = as_micros(duration);
+        let status = match &res {
+            Ok(resp) => resp.status(),
+            Err(err) => {
+                layer.error = Some(err.to_string());
+                err.status()
+            }
+        };
+        layer.status = Some(status.as_u16());
+
+        if let Some((method, path)) = root {
+            let layers = std::mem::take(&mut *layers);
+            let rejected_by = layers
+                .last()
+                .filter(|layer| !layer.next)
+                .map(|layer| layer.name.clone());
+            let mut requests = self.state.requests.lock();
+            if requests.len() >= self.state.capacity {
+                requests.pop_front();
+            }
+            if self.state.capacity > 0 {
+                requests.push_back(RequestRecord {
+                    method,
+                    path,
+                    status: status.as_u16(),
+                    duration_us: as_micros(duration),
+                    rejected_by,
+                    layers,
+                });
+            }
+        }
+
+        res
+    }
+}
+
+/// The next endpoint of a [`TracedLayer`], which marks that the layer called
+/// the next endpoint.
+pub struct TracedNext<E> {
+    inner: E,
+}
+
+impl<E: Endpoint> Endpoint for TracedNext<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        if let (Some(ctx), Some(CurrentLayer(index))) = (
+            req.extensions().get::<ChainContext>(),
+            req.extensions().get::<CurrentLayer>(),
+        ) {
+            if let Some(layer) = ctx.0.lock().get_mut(*index) {
+                layer.next = true;
+            }
+        }
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        endpoint::make_sync,
+        http::StatusCode,
+        middleware::{SetHeader, SizeLimit},
+        test::TestClient,
+        EndpointExt,
+    };
+
+    #[tokio::test]
+    async fn trace() {
+        let trace = MiddlewareTrace::with_capacity(2);
+        let ep = make_sync(|_| "hello")
+            .with(trace.layer("size_limit", SizeLimit::new(5)))
+            .with(trace.layer("set_header", SetHeader::new().appending("x-a", "1")));
+        let cli = TestClient::new(ep);
+        let json = trace.json();
+
+        cli.get("/a")
+            .send()
+            .await
+            .assert_status(StatusCode::LENGTH_REQUIRED);
+        let value = json();
+        let request = &value[0];
+        assert_eq!(request["method"], "GET");
+        assert_eq!(request["path"], "/a");
+        assert_eq!(request["status"], 411);
+        assert_eq!(request["rejected_by"], "size_limit");
+        assert_eq!(request["layers"][0]["name"], "set_header");
+        assert_eq!(request["layers"][0]["next"], true);
+        assert_eq!(request["layers"][0]["status"], 411);
+        assert_eq!(request["layers"][1]["name"], "size_limit");
+        assert_eq!(request["layers"][1]["next"], false);
+        assert!(request["layers"][1]["error"].is_string());
+
+        cli.post("/b")
+            .header("content-length", 3)
+            .body("abc")
+            .send()
+            .await
+            .assert_status_is_ok();
+        let value = json();
+        assert_eq!(value[0]["path"], "/b");
+        assert_eq!(value[0]["status"], 200);
+        assert_eq!(value[0]["rejected_by"], json!(null));
+        assert_eq!(value[0]["layers"][1]["next"], true);
+        assert_eq!(value[1]["path"], "/a");
+
+        cli.get("/c").send().await;
+        let value = json();
+        assert_eq!(value.as_array().unwrap().len(), 2);
+        assert_eq!(value[0]["path"], "/c");
+
+        trace.set_enabled(false);
+        trace.clear();
+        cli.get("/d").send().await;
+        assert_eq!(json(), json!([]));
+    }
+}
@@ -1,61 +1,191 @@
 //! Commonly used middleware.
 
+mod adaptive_concurrency_limit;
 mod add_data;
+#[cfg(feature = "arena")]
+mod arena;
+#[cfg(feature = "audit-log")]
+mod audit_log;
+#[cfg(feature = "bot-challenge")]
+mod bot_challenge;
+#[cfg(feature = "capture")]
+mod capture;
 mod catch_panic;
 #[cfg(feature = "compression")]
 mod compression;
+mod concurrency_limit;
+mod conditional_request;
+#[cfg(feature = "content-digest")]
+mod content_digest;
 #[cfg(feature = "cookie")]
 mod cookie_jar_manager;
 mod cors;
 #[cfg(feature = "csrf")]
 mod csrf;
+mod early_hints_manager;
+mod error_handler;
+mod expect_continue;
+#[cfg(feature = "cookie")]
+mod flash;
+#[cfg(feature = "server")]
+mod for_listener;
 mod force_https;
+#[cfg(feature = "retry")]
+mod hedge;
+#[cfg(feature = "http-signature")]
+mod http_signature;
+#[cfg(feature = "idempotency")]
+mod idempotency;
+#[cfg(feature = "ip-filter")]
+mod ip_filter;
+#[cfg(feature = "dev")]
+mod live_reload;
+mod maintenance;
+mod middleware_trace;
 mod normalize_path;
 #[cfg(feature = "opentelemetry")]
 mod opentelemetry_metrics;
 #[cfg(feature = "opentelemetry")]
 mod opentelemetry_tracing;
+mod problem_json;
 mod propagate_header;
+mod rate_limit;
+#[cfg(feature = "redis-rate-limit")]
+mod redis_rate_limit;
 #[cfg(feature = "requestid")]
 mod requestid;
+#[cfg(feature = "resource-usage")]
+mod resource_usage;
+#[cfg(feature = "retry")]
+mod retry;
+#[cfg(feature = "secure-headers")]
+mod secure_headers;
 mod sensitive_header;
+#[cfg(feature = "sentry")]
+mod sentry_mw;
 mod set_header;
+#[cfg(feature = "shadow")]
+mod shadow;
 mod size_limit;
+mod throttle;
+mod timeout;
 #[cfg(feature = "tokio-metrics")]
 mod tokio_metrics_mw;
 #[cfg(feature = "tower-compat")]
 mod tower_compat;
 mod tracing_mw;
+mod transform_body;
 
 use std::marker::PhantomData;
 
+#[cfg(feature = "ip-filter")]
+pub use ipnet::IpNet;
+
+#[cfg(feature = "arena")]
+pub use self::arena::{Arena, ArenaEndpoint};
+#[cfg(feature = "audit-log")]
+pub use self::audit_log::{
+    AuditContext, AuditDiff, AuditEvent, AuditLog, AuditLogEndpoint, AuditOutcome, AuditSink,
+    FileAuditSink,
+};
+#[cfg(feature = "turnstile")]
+pub use self::bot_challenge::Turnstile;
+#[cfg(feature = "bot-challenge")]
+pub use self::bot_challenge::{
+    is_suspicious, BotChallenge, BotChallengeEndpoint, Challenge, ProofOfWork,
+};
+#[cfg(feature = "capture")]
+pub use self::capture::{Capture, CaptureEndpoint, CapturedRequest};
 #[cfg(feature = "compression")]
 pub use self::compression::{Compression, CompressionEndpoint};
+#[cfg(feature = "content-digest")]
+pub use self::content_digest::{ContentDigest, ContentDigestEndpoint, DigestAlgorithm};
 #[cfg(feature = "cookie")]
 pub use self::cookie_jar_manager::{CookieJarManager, CookieJarManagerEndpoint};
 #[cfg(feature = "csrf")]
 pub use self::csrf::{Csrf, CsrfEndpoint};
+#[cfg(feature = "cookie")]
+pub use self::flash::{FlashMessages, FlashMessagesEndpoint};
+#[cfg(feature = "server")]
+pub use self::for_listener::{ForListener, ForListenerEndpoint};
+#[cfg(feature = "server")]
+pub(crate) use self::force_https::redirect_host;
+#[cfg(feature = "retry")]
+pub use self::hedge::{Hedge, HedgeEndpoint};
+#[cfg(feature = "http-signature")]
+pub use self::http_signature::{
+    KeyResolver, SignResponse, SignResponseEndpoint, SignatureAlgorithm, SigningKey,
+    VerifiedSignature, VerifySignature, VerifySignatureEndpoint, VerifyingKey,
+};
+#[cfg(feature = "idempotency")]
+pub use self::idempotency::{
+    Idempotency, IdempotencyEndpoint, IdempotencyRecord, IdempotencyState, IdempotencyStore,
+    MemoryIdempotencyStore,
+};
+#[cfg(feature = "geoip")]
+pub use self::ip_filter::GeoIpDatabase;
+#[cfg(feature = "ip-filter")]
+pub(crate) use self::ip_filter::{remote_ip, IpFn};
+#[cfg(feature = "ip-filter")]
+pub use self::ip_filter::{Blocklist, CidrSet, GeoIpLookup, IpFilter, IpFilterEndpoint, IpRules};
+#[cfg(feature = "dev")]
+pub use self::live_reload::{LiveReload, LiveReloadEndpoint};
 #[cfg(feature = "opentelemetry")]
 pub use self::opentelemetry_metrics::{OpenTelemetryMetrics, OpenTelemetryMetricsEndpoint};
 #[cfg(feature = "opentelemetry")]
 pub use self::opentelemetry_tracing::{OpenTelemetryTracing, OpenTelemetryTracingEndpoint};
+pub(crate) use self::rate_limit::ceil_secs;
+#[cfg(feature = "redis-rate-limit")]
+pub use self::redis_rate_limit::RedisRateLimitStore;
 #[cfg(feature = "requestid")]
 pub use self::requestid::{ReqId, RequestId, RequestIdEndpoint, ReuseId};
+#[cfg(feature = "resource-usage")]
+pub use self::resource_usage::{
+    RequestUsage, ResourceUsage, ResourceUsageEndpoint, TrackingAllocator,
+};
+#[cfg(feature = "retry")]
+pub use self::retry::{BufferedBody, Retry, RetryBudget, RetryEndpoint};
+#[cfg(feature = "secure-headers")]
+pub use self::secure_headers::{
+    ContentSecurityPolicy, CspSource, Hsts, SecureHeaders, SecureHeadersEndpoint,
+};
+#[cfg(feature = "sentry")]
+pub use self::sentry_mw::{Sentry, SentryEndpoint};
+#[cfg(feature = "shadow")]
+pub use self::shadow::{Shadow, ShadowEndpoint};
 #[cfg(feature = "tokio-metrics")]
 pub use self::tokio_metrics_mw::{TokioMetrics, TokioMetricsEndpoint};
 #[cfg(feature = "tower-compat")]
 pub use self::tower_compat::TowerLayerCompatExt;
 pub use self::{
+    adaptive_concurrency_limit::{
+        AdaptiveConcurrencyLimit, AdaptiveConcurrencyLimitEndpoint,
+        AdaptiveConcurrencyLimitMetrics, Aimd, Gradient, LimitAlgorithm, LimitSample,
+    },
     add_data::{AddData, AddDataEndpoint},
     catch_panic::{CatchPanic, CatchPanicEndpoint, PanicHandler},
+    concurrency_limit::{ConcurrencyLimit, ConcurrencyLimitEndpoint, ConcurrencyLimitMetrics},
+    conditional_request::{ConditionalRequest, ConditionalRequestEndpoint, ResourceVersion},
     cors::{Cors, CorsEndpoint},
+    early_hints_manager::{EarlyHintsManager, EarlyHintsManagerEndpoint},
+    error_handler::{ErrorHandler, ErrorHandlerEndpoint},
+    expect_continue::{ExpectContinue, ExpectContinueEndpoint},
     force_https::ForceHttps,
+    maintenance::{Maintenance, MaintenanceEndpoint, MaintenanceSwitch},
+    middleware_trace::{MiddlewareTrace, TracedLayer, TracedLayerEndpoint, TracedNext},
     normalize_path::{NormalizePath, NormalizePathEndpoint, TrailingSlash},
+    problem_json::{ProblemJson, ProblemJsonEndpoint},
     propagate_header::{PropagateHeader, PropagateHeaderEndpoint},
+    rate_limit::{
+        MemoryRateLimitStore, RateLimit, RateLimitEndpoint, RateLimitState, RateLimitStore,
+    },
     sensitive_header::{SensitiveHeader, SensitiveHeaderEndpoint},
     set_header::{SetHeader, SetHeaderEndpoint},
     size_limit::{SizeLimit, SizeLimitEndpoint},
-    tracing_mw::{Tracing, TracingEndpoint},
+    throttle::{Throttle, ThrottleEndpoint},
+    timeout::{Timeout, TimeoutEndpoint},
+    tracing_mw::{Tracing, TracingBuilder, TracingEndpoint},
+    transform_body::{BodyTransform, TransformBody, TransformBodyEndpoint},
 };
 use crate::endpoint::{EitherEndpoint, Endpoint};
 
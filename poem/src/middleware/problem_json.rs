@@ -0,0 +1,143 @@
+use crate::{web::ProblemDetails, Endpoint, Middleware, Request, Result};
+
+/// Middleware for converting all the errors into
+/// [RFC 9457](https://www.rfc-editor.org/rfc/rfc9457) problem details.
+///
+/// The status code of the error is preserved, and the error message is used
+/// as the `detail` member. If the `instance` member is not specified, it is
+/// set to the path of the request.
+///
+/// Errors created by [`Error::from_response`](crate::Error::from_response)
+/// are returned unchanged.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler, http::StatusCode, middleware::ProblemJson, test::TestClient, EndpointExt,
+///     Route,
+/// };
+/// use serde_json::json;
+///
+/// #[handler]
+/// fn index() {}
+///
+/// let app = Route::new().at("/", get(index)).with(ProblemJson);
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/abc").send().await;
+/// resp.assert_status(StatusCode::NOT_FOUND);
+/// resp.assert_content_type("application/problem+json");
+/// resp.assert_json(json!({
+///     "title": "Not Found",
+///     "status": 404,
+///     "detail": "not found",
+///     "instance": "/abc",
+/// }))
+/// .await;
+/// # });
+/// ```
+#[derive(Default)]
+pub struct ProblemJson;
+
+impl<E: Endpoint> Middleware<E> for ProblemJson {
+    type Output = ProblemJsonEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ProblemJsonEndpoint { inner: ep }
+    }
+}
+
+/// Endpoint for the ProblemJson middleware.
+pub struct ProblemJsonEndpoint<E> {
+    inner: E,
+}
+
+impl<E: Endpoint> Endpoint for ProblemJsonEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let path = req.uri().path().to_string();
+        match self.inner.call(req).await {
+            Ok(resp) => Ok(resp),
+            Err(err) if err.is_from_response() => Err(err),
+            Err(err) => {
+                let problem = ProblemDetails::from_error(&err);
+                let problem = if problem.has_instance() {
+                    problem
+                } else {
+                    problem.instance(path)
+                };
+                Err(problem.into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        error::ParsePathError, handler, test::TestClient, EndpointExt, Error, IntoResponse,
+    };
+
+    #[tokio::test]
+    async fn problem_json() {
+        #[handler(internal)]
+        fn custom() -> Result<()> {
+            Err(ProblemDetails::new(StatusCode::CONFLICT)
+                .ty("https://example.com/conflict")
+                .instance("/abc")
+                .into())
+        }
+
+        let cli = TestClient::new(custom.with(ProblemJson));
+        let resp = cli.get("/a").send().await;
+        resp.assert_status(StatusCode::CONFLICT);
+        resp.assert_json(json!({
+            "type": "https://example.com/conflict",
+            "title": "Conflict",
+            "status": 409,
+            "instance": "/abc",
+        }))
+        .await;
+
+        #[handler(internal)]
+        fn parse_path() -> Result<()> {
+            Err(ParsePathError.into())
+        }
+
+        let cli = TestClient::new(parse_path.with(ProblemJson));
+        let resp = cli.get("/a").send().await;
+        resp.assert_status(StatusCode::BAD_REQUEST);
+        resp.assert_content_type("application/problem+json");
+        resp.assert_json(json!({
+            "title": "Bad Request",
+            "status": 400,
+            "detail": "invalid path params",
+            "instance": "/a",
+        }))
+        .await;
+    }
+
+    #[tokio::test]
+    async fn from_response_unchanged() {
+        #[handler(internal)]
+        fn index() -> Result<()> {
+            Err(Error::from_response(
+                "teapot"
+                    .with_status(StatusCode::IM_A_TEAPOT)
+                    .into_response(),
+            ))
+        }
+
+        let cli = TestClient::new(index.with(ProblemJson));
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::IM_A_TEAPOT);
+        resp.assert_text("teapot").await;
+    }
+}
@@ -0,0 +1,420 @@
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+
+use http::{HeaderMap, HeaderValue};
+use parking_lot::Mutex;
+
+use crate::{
+    clock::SharedClock, error::RateLimitError, Endpoint, IntoResponse, Middleware, Request,
+    Response, Result,
+};
+
+/// The state of a rate limit after a request.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RateLimitState {
+    /// The maximum number of the requests in a period.
+    pub limit: u64,
+    /// The number of the requests which can be made immediately.
+    pub remaining: u64,
+    /// The time after which the limit is fully reset.
+    pub reset: Duration,
+    /// The time after which the client can retry, `None` if the request is
+    /// allowed.
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimitState {
+    /// Returns `true` if the request is allowed.
+    #[inline]
+    pub fn is_allowed(&self) -> bool {
+        self.retry_after.is_none()
+    }
+
+    pub(crate) fn insert_headers(&self, headers: &mut HeaderMap) {
+        headers.insert("x-ratelimit-limit", HeaderValue::from(self.limit));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from(self.remaining));
+        headers.insert(
+            "x-ratelimit-reset",
+            HeaderValue::from(ceil_secs(self.reset)),
+        );
+    }
+}
+
+pub(crate) fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+/// The generic cell rate algorithm, the time is in milliseconds.
+///
+/// The state of a key is its theoretical arrival time, the time at which the
+/// limit would be fully reset.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Gcra {
+    pub(crate) limit: u64,
+    /// The time between two requests at the sustained rate.
+    pub(crate) interval: u64,
+    pub(crate) period: u64,
+}
+
+impl Gcra {
+    pub(crate) fn new(limit: u64, period: Duration) -> Self {
+        let period = (period.as_millis() as u64).max(1);
+        Self {
+            limit,
+            interval: (period / limit).max(1),
+            period,
+        }
+    }
+
+    /// Checks a request at `now`, returns the new arrival time of the key if
+    /// the request is allowed.
+    pub(crate) fn check(&self, tat: Option<u64>, now: u64) -> (Option<u64>, RateLimitState) {
+        let tat = tat.unwrap_or(now).max(now);
+        let new_tat = tat + self.interval;
+        if new_tat > now + self.period {
+            (
+                None,
+                self.state(false, tat - now, new_tat - self.period - now),
+            )
+        } else {
+            (Some(new_tat), self.state(true, new_tat - now, 0))
+        }
+    }
+
+    /// Returns the state of an allowed or rejected request, with the
+    /// milliseconds to reset and to retry.
+    pub(crate) fn state(&self, allowed: bool, reset: u64, retry_after: u64) -> RateLimitState {
+        RateLimitState {
+            limit: self.limit,
+            remaining: if allowed {
+                self.period.saturating_sub(reset) / self.interval
+            } else {
+                0
+            },
+            reset: Duration::from_millis(reset),
+            retry_after: (!allowed).then(|| Duration::from_millis(retry_after.max(1))),
+        }
+    }
+}
+
+/// A storage for the states of the [`RateLimit`] middleware.
+pub trait RateLimitStore: Send + Sync {
+    /// Counts a request of the key, and returns the state of the limit of
+    /// `limit` requests per `period`.
+    ///
+    /// `now` is the time of the request since the unix epoch, the storages
+    /// shared by multiple servers may use their own time instead.
+    fn check<'a>(
+        &'a self,
+        key: &'a str,
+        limit: u64,
+        period: Duration,
+        now: Duration,
+    ) -> impl Future<Output = Result<RateLimitState>> + Send + 'a;
+}
+
+/// A rate limit storage using memory, the limits are not shared between the
+/// servers.
+#[derive(Default)]
+pub struct MemoryRateLimitStore {
+    inner: Mutex<MemoryInner>,
+}
+
+#[derive(Default)]
+struct MemoryInner {
+    arrivals: HashMap<String, u64>,
+    next_purge: usize,
+}
+
+impl MemoryRateLimitStore {
+    /// Create a `MemoryRateLimitStore`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub(crate) fn check_sync(
+        &self,
+        key: &str,
+        limit: u64,
+        period: Duration,
+        now: Duration,
+    ) -> RateLimitState {
+        let now = now.as_millis() as u64;
+        let mut inner = self.inner.lock();
+
+        // removes the keys which are fully reset when the map grows
+        if inner.arrivals.len() >= inner.next_purge {
+            inner.arrivals.retain(|_, tat| *tat > now);
+            inner.next_purge = (inner.arrivals.len() * 2).max(1024);
+        }
+
+        let gcra = Gcra::new(limit, period);
+        let (tat, state) = gcra.check(inner.arrivals.get(key).copied(), now);
+        if let Some(tat) = tat {
+            inner.arrivals.insert(key.to_string(), tat);
+        }
+        state
+    }
+}
+
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn check<'a>(
+        &'a self,
+        key: &'a str,
+        limit: u64,
+        period: Duration,
+        now: Duration,
+    ) -> Result<RateLimitState> {
+        Ok(self.check_sync(key, limit, period, now))
+    }
+}
+
+type KeyFn = Arc<dyn Fn(&Request) -> Option<String> + Send + Sync>;
+
+/// Middleware for limiting the number of the requests of each client, with
+/// the generic cell rate algorithm (GCRA).
+///
+/// The clients are identified by the IP address by default, the requests
+/// exceeding the limit are rejected with [`RateLimitError`] which responds
+/// `429 Too Many Requests`. The `X-RateLimit-Limit`, `X-RateLimit-Remaining`
+/// and `X-RateLimit-Reset` headers are added to the responses.
+///
+/// The states are stored in memory by default, use
+/// [`RedisRateLimitStore`](crate::middleware::RedisRateLimitStore) to share
+/// the limits between the servers.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     handler,
+///     http::{HeaderMap, StatusCode},
+///     middleware::RateLimit,
+///     test::TestClient,
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// fn api_key(headers: &HeaderMap) -> Option<String> {
+///     Some(headers.get("x-api-key")?.to_str().ok()?.to_string())
+/// }
+///
+/// let app = Route::new()
+///     .at("/", index)
+///     .with(RateLimit::new(2, Duration::from_secs(60)).key(|req| api_key(req.headers())));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").header("x-api-key", "a").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_header("x-ratelimit-remaining", "1");
+///
+/// cli.get("/").header("x-api-key", "a").send().await;
+/// let resp = cli.get("/").header("x-api-key", "a").send().await;
+/// resp.assert_status(StatusCode::TOO_MANY_REQUESTS);
+/// resp.assert_header("retry-after", "30");
+/// # });
+/// ```
+pub struct RateLimit<S = MemoryRateLimitStore> {
+    store: Arc<S>,
+    limit: u64,
+    period: Duration,
+    key: KeyFn,
+}
+
+impl RateLimit {
+    /// Create `RateLimit` middleware which allows `limit` requests per
+    /// `period` for each client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is `0`.
+    pub fn new(limit: u64, period: Duration) -> Self {
+        assert!(limit > 0, "the limit must be greater than 0");
+        Self {
+            store: Arc::new(MemoryRateLimitStore::new()),
+            limit,
+            period,
+            key: Arc::new(|req| {
+                let addr = req.remote_addr();
+                Some(match addr.as_socket_addr() {
+                    Some(addr) => addr.ip().to_string(),
+                    None => addr.to_string(),
+                })
+            }),
+        }
+    }
+}
+
+impl<S> RateLimit<S> {
+    /// Sets the storage of the states.
+    pub fn store<T: RateLimitStore>(self, store: T) -> RateLimit<T> {
+        RateLimit {
+            store: Arc::new(store),
+            limit: self.limit,
+            period: self.period,
+            key: self.key,
+        }
+    }
+
+    /// Sets the function which returns the key identifying the client of a
+    /// request, the requests without a key are not limited.
+    ///
+    /// Default is the IP address of the client.
+    #[must_use]
+    pub fn key(self, f: impl Fn(&Request) -> Option<String> + Send + Sync + 'static) -> Self {
+        Self {
+            key: Arc::new(f),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint, S: RateLimitStore> Middleware<E> for RateLimit<S> {
+    type Output = RateLimitEndpoint<E, S>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RateLimitEndpoint {
+            inner: ep,
+            store: self.store.clone(),
+            limit: self.limit,
+            period: self.period,
+            key: self.key.clone(),
+        }
+    }
+}
+
+/// Endpoint for the RateLimit middleware.
+pub struct RateLimitEndpoint<E, S> {
+    inner: E,
+    store: Arc<S>,
+    limit: u64,
+    period: Duration,
+    key: KeyFn,
+}
+
+impl<E: Endpoint, S: RateLimitStore> Endpoint for RateLimitEndpoint<E, S> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let key = match (self.key)(&req) {
+            Some(key) => key,
+            None => return Ok(self.inner.call(req).await?.into_response()),
+        };
+
+        let now = SharedClock::from_request(&req).elapsed_since_epoch();
+        let state = self.store.check(&key, self.limit, self.period, now).await?;
+        if !state.is_allowed() {
+            return Err(RateLimitError { state }.into());
+        }
+
+        let mut resp = self.inner.call(req).await?.into_response();
+        state.insert_headers(resp.headers_mut());
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{clock::MockClock, endpoint::make_sync, test::TestClient, EndpointExt};
+
+    #[test]
+    fn gcra() {
+        let gcra = Gcra::new(3, Duration::from_secs(3));
+        let (tat, state) = gcra.check(None, 0);
+        assert_eq!(tat, Some(1000));
+        assert_eq!(state.remaining, 2);
+        assert_eq!(state.reset, Duration::from_secs(1));
+
+        let (tat, state) = gcra.check(Some(2000), 0);
+        assert_eq!(tat, Some(3000));
+        assert_eq!(state.remaining, 0);
+
+        let (tat, state) = gcra.check(Some(3000), 500);
+        assert_eq!(tat, None);
+        assert_eq!(state.remaining, 0);
+        assert_eq!(state.reset, Duration::from_millis(2500));
+        assert_eq!(state.retry_after, Some(Duration::from_millis(500)));
+
+        // the state is reset after the period
+        let (tat, state) = gcra.check(Some(3000), 5000);
+        assert_eq!(tat, Some(6000));
+        assert_eq!(state.remaining, 2);
+    }
+
+    #[tokio::test]
+    async fn rate_limit() {
+        let clock = MockClock::new();
+        let ep = make_sync(|_| "hello")
+            .with(RateLimit::new(2, Duration::from_secs(10)))
+            .data(SharedClock::new(clock.clone()));
+        let cli = TestClient::new(ep);
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("x-ratelimit-limit", "2");
+        resp.assert_header("x-ratelimit-remaining", "1");
+        resp.assert_header("x-ratelimit-reset", "5");
+
+        let resp = cli.get("/").send().await;
+        resp.assert_header("x-ratelimit-remaining", "0");
+        resp.assert_header("x-ratelimit-reset", "10");
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        resp.assert_header("x-ratelimit-remaining", "0");
+        resp.assert_header("retry-after", "5");
+
+        clock.advance(Duration::from_secs(5));
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("x-ratelimit-remaining", "0");
+    }
+
+    #[tokio::test]
+    async fn key() {
+        let ep = make_sync(|_| "hello").with(
+            RateLimit::new(1, Duration::from_secs(10))
+                .key(|req| req.header("x-user").map(ToString::to_string)),
+        );
+        let cli = TestClient::new(ep);
+
+        cli.get("/")
+            .header("x-user", "a")
+            .send()
+            .await
+            .assert_status_is_ok();
+        cli.get("/")
+            .header("x-user", "b")
+            .send()
+            .await
+            .assert_status_is_ok();
+        cli.get("/")
+            .header("x-user", "a")
+            .send()
+            .await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist("x-ratelimit-limit");
+    }
+
+    #[test]
+    fn purge() {
+        let store = MemoryRateLimitStore::new();
+        for i in 0..1024 {
+            store.check_sync(&i.to_string(), 1, Duration::from_secs(1), Duration::ZERO);
+        }
+        store.check_sync("a", 1, Duration::from_secs(1), Duration::from_secs(2));
+        assert_eq!(store.inner.lock().arrivals.len(), 1);
+    }
+}
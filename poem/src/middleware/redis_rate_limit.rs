@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use redis::{aio::ConnectionLike, Script};
+
+use crate::{
+    error::RedisRateLimitError,
+    middleware::{rate_limit::Gcra, MemoryRateLimitStore, RateLimitState, RateLimitStore},
+    Result,
+};
+
+/// Checks a request with the GCRA atomically, the arrival time of the key is
+/// stored in milliseconds and the time of the redis server is used.
+///
+/// Returns whether the request is allowed, and the milliseconds to reset and
+/// to retry.
+const GCRA_SCRIPT: &str = r#"
+local interval = tonumber(ARGV[1])
+local period = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local tat = tonumber(redis.call('GET', KEYS[1])) or now
+if tat < now then
+    tat = now
+end
+local new_tat = tat + interval
+if new_tat > now + period then
+    return {0, tat - now, new_tat - period - now}
+end
+redis.call('SET', KEYS[1], new_tat, 'PX', new_tat - now)
+return {1, new_tat - now, 0}
+"#;
+
+/// A rate limit storage using redis, the limits are shared by the servers
+/// using the same redis.
+///
+/// The requests are checked with a Lua script atomically. If redis is
+/// unreachable, the requests are checked with the states in memory until it
+/// recovers, use [`RedisRateLimitStore::without_fallback`] to reject them
+/// instead.
+///
+/// # Errors
+///
+/// - [`RedisRateLimitError`]
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use poem::{
+///     middleware::{RateLimit, RedisRateLimitStore},
+///     EndpointExt, Route,
+/// };
+/// use redis::{aio::ConnectionManager, Client};
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let client = Client::open("redis://127.0.0.1/").unwrap();
+/// let connection = ConnectionManager::new(client).await.unwrap();
+///
+/// let app = Route::new().with(
+///     RateLimit::new(100, Duration::from_secs(60))
+///         .store(RedisRateLimitStore::new(connection).prefix("api:")),
+/// );
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "redis-rate-limit")))]
+pub struct RedisRateLimitStore<T> {
+    connection: T,
+    script: Script,
+    prefix: String,
+    fallback: Option<MemoryRateLimitStore>,
+}
+
+impl<T> RedisRateLimitStore<T> {
+    /// Create a `RedisRateLimitStore`, the keys are prefixed with
+    /// `poem:rate-limit:`.
+    pub fn new(connection: T) -> Self {
+        Self {
+            connection,
+            script: Script::new(GCRA_SCRIPT),
+            prefix: "poem:rate-limit:".to_string(),
+            fallback: Some(MemoryRateLimitStore::new()),
+        }
+    }
+
+    /// Sets the prefix of the redis keys.
+    #[must_use]
+    pub fn prefix(self, prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            ..self
+        }
+    }
+
+    /// Returns the redis errors instead of checking the requests in memory
+    /// when redis is unreachable.
+    #[must_use]
+    pub fn without_fallback(self) -> Self {
+        Self {
+            fallback: None,
+            ..self
+        }
+    }
+}
+
+impl<T: ConnectionLike + Clone + Sync + Send> RateLimitStore for RedisRateLimitStore<T> {
+    async fn check<'a>(
+        &'a self,
+        key: &'a str,
+        limit: u64,
+        period: Duration,
+        now: Duration,
+    ) -> Result<RateLimitState> {
+        let gcra = Gcra::new(limit, period);
+        let res = self
+            .script
+            .key(format!("{}{}", self.prefix, key))
+            .arg(gcra.interval)
+            .arg(gcra.period)
+            .invoke_async::<(u8, u64, u64)>(&mut self.connection.clone())
+            .await;
+
+        match res {
+            Ok((allowed, reset, retry_after)) => Ok(gcra.state(allowed == 1, reset, retry_after)),
+            Err(err) => match &self.fallback {
+                Some(fallback) => {
+                    tracing::warn!(error = %err, "redis is unreachable, fall back to the local rate limit");
+                    Ok(fallback.check_sync(key, limit, period, now))
+                }
+                None => Err(RedisRateLimitError::Redis(err).into()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use redis::{
+        aio::ConnectionManager, Client, ErrorKind, Pipeline, RedisError, RedisFuture, Value,
+    };
+
+    use super::*;
+    use crate::{endpoint::make_sync, middleware::RateLimit, test::TestClient, EndpointExt};
+
+    /// A connection which fails all the commands.
+    #[derive(Clone)]
+    struct Unreachable;
+
+    impl ConnectionLike for Unreachable {
+        fn req_packed_command<'a>(&'a mut self, _cmd: &'a redis::Cmd) -> RedisFuture<'a, Value> {
+            Box::pin(async { Err(RedisError::from((ErrorKind::IoError, "unreachable"))) })
+        }
+
+        fn req_packed_commands<'a>(
+            &'a mut self,
+            _cmd: &'a Pipeline,
+            _offset: usize,
+            _count: usize,
+        ) -> RedisFuture<'a, Vec<Value>> {
+            Box::pin(async { Err(RedisError::from((ErrorKind::IoError, "unreachable"))) })
+        }
+
+        fn get_db(&self) -> i64 {
+            0
+        }
+    }
+
+    #[tokio::test]
+    async fn fallback() {
+        let ep = make_sync(|_| "hello").with(
+            RateLimit::new(1, Duration::from_secs(10)).store(RedisRateLimitStore::new(Unreachable)),
+        );
+        let cli = TestClient::new(ep);
+        cli.get("/").send().await.assert_status_is_ok();
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+        let ep = make_sync(|_| "hello").with(
+            RateLimit::new(1, Duration::from_secs(10))
+                .store(RedisRateLimitStore::new(Unreachable).without_fallback()),
+        );
+        let cli = TestClient::new(ep);
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn redis_rate_limit() {
+        let mut client = match Client::open("redis://127.0.0.1/") {
+            Ok(client) => client,
+            Err(_) => return,
+        };
+        if !redis::ConnectionLike::check_connection(&mut client) {
+            return;
+        }
+        let connection = ConnectionManager::new(client).await.unwrap();
+
+        let key = format!(
+            "test-{}",
+            std::time::UNIX_EPOCH.elapsed().unwrap().as_nanos()
+        );
+        let store = RedisRateLimitStore::new(connection).without_fallback();
+        let period = Duration::from_secs(10);
+        let state = store.check(&key, 2, period, Duration::ZERO).await.unwrap();
+        assert!(state.is_allowed());
+        assert_eq!(state.remaining, 1);
+
+        let state = store.check(&key, 2, period, Duration::ZERO).await.unwrap();
+        assert_eq!(state.remaining, 0);
+
+        let state = store.check(&key, 2, period, Duration::ZERO).await.unwrap();
+        assert!(!state.is_allowed());
+        assert!(state.retry_after.unwrap() <= Duration::from_secs(5));
+    }
+}
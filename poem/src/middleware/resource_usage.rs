@@ -0,0 +1,444 @@
+use std::{
+    cell::Cell,
+    cmp::Reverse,
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, OnceLock},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use cpu_time::ThreadTime;
+use http::{HeaderValue, Method};
+use parking_lot::Mutex;
+use pin_project_lite::pin_project;
+use serde::Serialize;
+use tracking_allocator::{AllocationGroupId, AllocationRegistry, AllocationTracker};
+
+use crate::{route::PathPattern, Endpoint, IntoResponse, Middleware, Request, Response, Result};
+
+/// A global allocator which counts the allocations for the
+/// [`ResourceUsage`] middleware.
+///
+/// It must be installed as the global allocator of the application, otherwise
+/// the allocations are not counted.
+///
+/// ```
+/// use poem::middleware::TrackingAllocator;
+///
+/// #[global_allocator]
+/// static GLOBAL: TrackingAllocator<std::alloc::System> = TrackingAllocator::system();
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "resource-usage")))]
+pub type TrackingAllocator<A> = tracking_allocator::Allocator<A>;
+
+thread_local! {
+    static ALLOCATIONS: Cell<(u64, u64)> = const { Cell::new((0, 0)) };
+}
+
+/// Counts the allocations of the current thread.
+struct Tracker;
+
+impl AllocationTracker for Tracker {
+    fn allocated(
+        &self,
+        _addr: usize,
+        object_size: usize,
+        _wrapped_size: usize,
+        _group_id: AllocationGroupId,
+    ) {
+        let _ = ALLOCATIONS.try_with(|allocations| {
+            let (count, bytes) = allocations.get();
+            allocations.set((count + 1, bytes + object_size as u64));
+        });
+    }
+
+    fn deallocated(
+        &self,
+        _addr: usize,
+        _object_size: usize,
+        _wrapped_size: usize,
+        _source_group_id: AllocationGroupId,
+        _current_group_id: AllocationGroupId,
+    ) {
+    }
+}
+
+/// Installs the tracker, returns `true` if the allocations are counted, that
+/// is the [`TrackingAllocator`] is the global allocator.
+fn install() -> bool {
+    static TRACKING: OnceLock<bool> = OnceLock::new();
+
+    *TRACKING.get_or_init(|| {
+        if AllocationRegistry::set_global_tracker(Tracker).is_err() {
+            return false;
+        }
+        AllocationRegistry::enable_tracking();
+
+        let (count, _) = ALLOCATIONS.with(Cell::get);
+        drop(std::hint::black_box(Box::new(0u64)));
+        ALLOCATIONS.with(Cell::get).0 != count
+    })
+}
+
+/// The resources used by a request, which is added to the extensions of the
+/// response by the [`ResourceUsage`] middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "resource-usage")))]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct RequestUsage {
+    /// The CPU time spent in the endpoint.
+    pub cpu_time: Duration,
+    /// The number of the allocations made by the endpoint, it is always `0`
+    /// without the [`TrackingAllocator`].
+    pub allocations: u64,
+    /// The number of the bytes allocated by the endpoint, it is always `0`
+    /// without the [`TrackingAllocator`].
+    pub allocated_bytes: u64,
+}
+
+#[derive(Default, Serialize)]
+struct EndpointUsage {
+    method: String,
+    path: Option<String>,
+    count: u64,
+    cpu_time_us: u64,
+    max_cpu_time_us: u64,
+    allocations: u64,
+    allocated_bytes: u64,
+    max_allocated_bytes: u64,
+}
+
+/// The method and the path pattern of a route.
+type RouteKey = (Method, Option<Arc<str>>);
+
+#[derive(Default)]
+struct UsageState {
+    endpoints: Mutex<HashMap<RouteKey, EndpointUsage>>,
+}
+
+fn as_micros(duration: Duration) -> u64 {
+    duration.as_micros().try_into().unwrap_or(u64::MAX)
+}
+
+/// Middleware for measuring the CPU time and the allocations of each request,
+/// to find out the expensive endpoints.
+///
+/// The resources used by the inner endpoint are measured each time it is
+/// polled, the work done on the other tasks and while streaming the response
+/// body are not included. The usage of a request is added to the extensions of
+/// the response as [`RequestUsage`], and to the `Server-Timing` header unless
+/// it is disabled by [`ResourceUsage::server_timing`], for example:
+///
+/// ```text
+/// server-timing: cpu;dur=0.412, alloc;desc="37 allocations, 5210 bytes"
+/// ```
+///
+/// The usage is also accumulated for each route, and returned by the `usage`
+/// section of the [`AdminEndpoints`](crate::endpoint::AdminEndpoints), see
+/// [`AdminEndpoints::resource_usage`](crate::endpoint::AdminEndpoints::resource_usage).
+///
+/// The allocations are only counted when the [`TrackingAllocator`] is the
+/// global allocator. Measuring has some overhead for each poll, so it is
+/// intended to be enabled when investigating.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     endpoint::make_sync,
+///     get,
+///     middleware::{RequestUsage, ResourceUsage},
+///     test::TestClient,
+///     EndpointExt, Route,
+/// };
+///
+/// let app = Route::new()
+///     .at("/", get(make_sync(|_| "hello")))
+///     .with(ResourceUsage::new());
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status_is_ok();
+/// assert!(resp
+///     .0
+///     .header("server-timing")
+///     .unwrap()
+///     .starts_with("cpu;dur="));
+/// assert!(resp.0.data::<RequestUsage>().is_some());
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "resource-usage")))]
+#[derive(Clone)]
+pub struct ResourceUsage {
+    server_timing: bool,
+    state: Arc<UsageState>,
+}
+
+impl Default for ResourceUsage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResourceUsage {
+    /// Create `ResourceUsage` middleware.
+    pub fn new() -> Self {
+        Self {
+            server_timing: true,
+            state: Default::default(),
+        }
+    }
+
+    /// Sets whether to add the usage to the `Server-Timing` header of the
+    /// responses.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn server_timing(self, enabled: bool) -> Self {
+        Self {
+            server_timing: enabled,
+            ..self
+        }
+    }
+
+    /// Clears the accumulated usage.
+    pub fn clear(&self) {
+        self.state.endpoints.lock().clear();
+    }
+
+    /// Returns a function to get the accumulated usage of each route as JSON,
+    /// the most expensive first.
+    pub(crate) fn json(&self) -> impl Fn() -> serde_json::Value + Send + Sync + 'static {
+        let state = self.state.clone();
+        move || {
+            let endpoints = state.endpoints.lock();
+            let mut endpoints = endpoints.values().collect::<Vec<_>>();
+            endpoints.sort_by_key(|endpoint| Reverse(endpoint.cpu_time_us));
+            serde_json::to_value(endpoints).unwrap_or_default()
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ResourceUsage {
+    type Output = ResourceUsageEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ResourceUsageEndpoint {
+            inner: ep,
+            server_timing: self.server_timing,
+            tracking: install(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// Endpoint for the [`ResourceUsage`] middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "resource-usage")))]
+pub struct ResourceUsageEndpoint<E> {
+    inner: E,
+    server_timing: bool,
+    tracking: bool,
+    state: Arc<UsageState>,
+}
+
+impl<E: Endpoint> ResourceUsageEndpoint<E> {
+    fn record(&self, method: Method, path: Option<Arc<str>>, usage: RequestUsage) {
+        let mut endpoints = self.state.endpoints.lock();
+        let endpoint = endpoints
+            .entry((method.clone(), path.clone()))
+            .or_insert_with(|| EndpointUsage {
+                method: method.to_string(),
+                path: path.map(|path| path.to_string()),
+                ..Default::default()
+            });
+        let cpu_time_us = as_micros(usage.cpu_time);
+        endpoint.count += 1;
+        endpoint.cpu_time_us = endpoint.cpu_time_us.saturating_add(cpu_time_us);
+        endpoint.max_cpu_time_us = endpoint.max_cpu_time_us.max(cpu_time_us);
+        endpoint.allocations = endpoint.allocations.saturating_add(usage.allocations);
+        endpoint.allocated_bytes = endpoint
+            .allocated_bytes
+            .saturating_add(usage.allocated_bytes);
+        endpoint.max_allocated_bytes = endpoint.max_allocated_bytes.max(usage.allocated_bytes);
+    }
+}
+
+impl<E: Endpoint> Endpoint for ResourceUsageEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let method = req.method().clone();
+        let (res, usage) = Measure {
+            inner: self.inner.call(req),
+            usage: RequestUsage::default(),
+        }
+        .await;
+
+        match res {
+            Ok(resp) => {
+                let mut resp = resp.into_response();
+                self.record(
+                    method,
+                    resp.data::<PathPattern>().map(|p| p.0.clone()),
+                    usage,
+                );
+                if self.server_timing {
+                    let mut value = format!("cpu;dur={:.3}", usage.cpu_time.as_secs_f64() * 1000.0);
+                    if self.tracking {
+                        value.push_str(&format!(
+                            ", alloc;desc=\"{} allocations, {} bytes\"",
+                            usage.allocations, usage.allocated_bytes
+                        ));
+                    }
+                    if let Ok(value) = HeaderValue::from_str(&value) {
+                        resp.headers_mut().append("server-timing", value);
+                    }
+                }
+                resp.set_data(usage);
+                Ok(resp)
+            }
+            Err(err) => {
+                self.record(
+                    method,
+                    err.data::<PathPattern>().map(|p| p.0.clone()),
+                    usage,
+                );
+                Err(err)
+            }
+        }
+    }
+}
+
+pin_project! {
+    /// Measures the resources used by each poll of the inner future.
+    struct Measure<F> {
+        #[pin]
+        inner: F,
+        usage: RequestUsage,
+    }
+}
+
+impl<F: Future> Future for Measure<F> {
+    type Output = (F::Output, RequestUsage);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let start = ThreadTime::try_now().ok();
+        let (count, bytes) = ALLOCATIONS.with(Cell::get);
+
+        let res = this.inner.poll(cx);
+
+        let (new_count, new_bytes) = ALLOCATIONS.with(Cell::get);
+        this.usage.allocations += new_count - count;
+        this.usage.allocated_bytes += new_bytes - bytes;
+        if let Some(start) = start {
+            this.usage.cpu_time += start.try_elapsed().unwrap_or_default();
+        }
+
+        match res {
+            Poll::Ready(output) => Poll::Ready((output, *this.usage)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        endpoint::{make_sync, AdminEndpoints},
+        get, handler,
+        http::StatusCode,
+        test::TestClient,
+        EndpointExt, Route,
+    };
+
+    #[global_allocator]
+    static GLOBAL: TrackingAllocator<std::alloc::System> = TrackingAllocator::system();
+
+    #[handler(internal)]
+    fn alloc() -> String {
+        let mut total = 0;
+        for i in 0..100 {
+            total += std::hint::black_box(vec![0u8; 1024]).len() + i;
+        }
+        total.to_string()
+    }
+
+    #[tokio::test]
+    async fn usage() {
+        let usage = ResourceUsage::new();
+        let cli = TestClient::new(
+            Route::new()
+                .at("/alloc", get(alloc))
+                .at("/users/:id", get(make_sync(|_| "user")))
+                .with(usage.clone()),
+        );
+
+        let resp = cli.get("/alloc").send().await;
+        resp.assert_status_is_ok();
+        let data = *resp.0.data::<RequestUsage>().unwrap();
+        assert!(data.allocations >= 100);
+        assert!(data.allocated_bytes >= 100 * 1024);
+        let timing = resp.0.header("server-timing").unwrap();
+        assert!(timing.starts_with("cpu;dur="));
+        assert!(timing.contains(&format!(
+            "alloc;desc=\"{} allocations, {} bytes\"",
+            data.allocations, data.allocated_bytes
+        )));
+
+        for id in 0..3 {
+            cli.get(format!("/users/{id}"))
+                .send()
+                .await
+                .assert_status_is_ok();
+        }
+        cli.get("/missing")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+
+        let admin = TestClient::new(AdminEndpoints::new().resource_usage(&usage).guard(|_| true));
+        let resp = admin.get("/usage").send().await;
+        resp.assert_status_is_ok();
+        let endpoints = resp
+            .json()
+            .await
+            .value()
+            .deserialize::<Vec<serde_json::Value>>();
+        assert_eq!(endpoints.len(), 3);
+        let find = |path: Option<&str>| {
+            endpoints
+                .iter()
+                .find(|endpoint| endpoint["path"].as_str() == path)
+                .unwrap()
+        };
+        assert_eq!(find(Some("/users/:id"))["count"], 3);
+        assert_eq!(find(Some("/users/:id"))["method"], "GET");
+        assert!(find(Some("/alloc"))["allocated_bytes"].as_u64().unwrap() >= 100 * 1024);
+        assert_eq!(find(None)["count"], 1);
+
+        usage.clear();
+        admin
+            .get("/usage")
+            .send()
+            .await
+            .json()
+            .await
+            .value()
+            .array()
+            .assert_len(0);
+    }
+
+    #[tokio::test]
+    async fn without_server_timing() {
+        let cli =
+            TestClient::new(make_sync(|_| "hello").with(ResourceUsage::new().server_timing(false)));
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist("server-timing");
+        assert!(resp.0.data::<RequestUsage>().is_some());
+    }
+}
@@ -0,0 +1,533 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use bytes::Bytes;
+use parking_lot::Mutex;
+
+use crate::{
+    http::{header, Method, StatusCode},
+    web::headers::{Date, Header},
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// The buffered request body, which is inserted into the request extensions
+/// by the [`Retry`] middleware to replay the body for each attempt.
+///
+/// If the request already has this extension, the body of the request is not
+/// read again, and this value is sent instead.
+#[cfg_attr(docsrs, doc(cfg(feature = "retry")))]
+#[derive(Debug, Clone)]
+pub struct BufferedBody(pub Bytes);
+
+/// A budget that limits the number of retries to a ratio of the requests, so
+/// that the retries do not overload a failing upstream.
+///
+/// The requests and retries are counted in a time window, up to
+/// `min_retries + ratio * requests` retries are allowed in each window. The
+/// budget can be cloned to be shared by multiple [`Retry`] middlewares.
+///
+/// Default is `20%` of the requests, and at least `10` retries in a `10s`
+/// window.
+#[cfg_attr(docsrs, doc(cfg(feature = "retry")))]
+#[derive(Clone)]
+pub struct RetryBudget {
+    ratio: f64,
+    min_retries: u64,
+    window: Duration,
+    state: Arc<Mutex<BudgetState>>,
+}
+
+struct BudgetState {
+    start: Instant,
+    requests: u64,
+    retries: u64,
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self::new(0.2, 10, Duration::from_secs(10))
+    }
+}
+
+impl RetryBudget {
+    /// Create a `RetryBudget`.
+    pub fn new(ratio: f64, min_retries: u64, window: Duration) -> Self {
+        Self {
+            ratio,
+            min_retries,
+            window,
+            state: Arc::new(Mutex::new(BudgetState {
+                start: Instant::now(),
+                requests: 0,
+                retries: 0,
+            })),
+        }
+    }
+
+    fn with_state<T>(&self, f: impl FnOnce(&mut BudgetState) -> T) -> T {
+        let mut state = self.state.lock();
+        if state.start.elapsed() >= self.window {
+            state.start = Instant::now();
+            state.requests = 0;
+            state.retries = 0;
+        }
+        f(&mut state)
+    }
+
+    pub(crate) fn deposit(&self) {
+        self.with_state(|state| state.requests += 1);
+    }
+
+    pub(crate) fn withdraw(&self) -> bool {
+        self.with_state(|state| {
+            let limit = self.min_retries as f64 + self.ratio * state.requests as f64;
+            if (state.retries as f64) < limit {
+                state.retries += 1;
+                true
+            } else {
+                false
+            }
+        })
+    }
+}
+
+/// Middleware for retrying the failed requests, for example of the endpoints
+/// forwarding the requests to an upstream.
+///
+/// A request is retried if the endpoint returns one of the retryable status
+/// codes, or an error with one of them, which are `429`, `502`, `503` and
+/// `504` by default. The delay between the attempts grows exponentially with
+/// a random jitter, and the `Retry-After` header of the response is used as
+/// the delay if it is present. If the `Retry-After` delay is greater than
+/// the maximum backoff, the response is returned without retrying.
+///
+/// Only the idempotent methods are retried unless
+/// [`Retry::retry_non_idempotent`] is enabled. The request body is buffered
+/// as [`BufferedBody`] to be replayed, the requests with a body larger than
+/// [`Retry::max_body_size`] or of an unknown size are not retried.
+///
+/// # Example
+///
+/// ```
+/// use std::{
+///     sync::atomic::{AtomicUsize, Ordering},
+///     time::Duration,
+/// };
+///
+/// use poem::{
+///     endpoint::make_sync, http::StatusCode, middleware::Retry, test::TestClient, EndpointExt,
+/// };
+///
+/// let attempts = AtomicUsize::new(0);
+/// let ep = make_sync(move |_| {
+///     if attempts.fetch_add(1, Ordering::Relaxed) < 2 {
+///         StatusCode::SERVICE_UNAVAILABLE
+///     } else {
+///         StatusCode::OK
+///     }
+/// })
+/// .with(Retry::new().backoff(Duration::from_millis(1), Duration::from_millis(10)));
+/// let cli = TestClient::new(ep);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// cli.get("/").send().await.assert_status_is_ok();
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "retry")))]
+pub struct Retry {
+    max_retries: usize,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    statuses: Vec<StatusCode>,
+    retry_non_idempotent: bool,
+    max_body_size: usize,
+    budget: RetryBudget,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Retry {
+    /// Create a `Retry` middleware.
+    pub fn new() -> Self {
+        Self {
+            max_retries: 3,
+            base_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_non_idempotent: false,
+            max_body_size: 64 * 1024,
+            budget: RetryBudget::default(),
+        }
+    }
+
+    /// Sets the maximum number of retries.
+    ///
+    /// Default is `3`.
+    #[must_use]
+    pub fn max_retries(self, max_retries: usize) -> Self {
+        Self {
+            max_retries,
+            ..self
+        }
+    }
+
+    /// Sets the delay before the first retry, and the maximum delay.
+    ///
+    /// Default is `100ms` and `10s`.
+    #[must_use]
+    pub fn backoff(self, base: Duration, max: Duration) -> Self {
+        Self {
+            base_backoff: base,
+            max_backoff: max,
+            ..self
+        }
+    }
+
+    /// Adds a random jitter to the delays, so that the clients do not retry
+    /// at the same time.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn jitter(self, jitter: bool) -> Self {
+        Self { jitter, ..self }
+    }
+
+    /// Sets the status codes to retry.
+    #[must_use]
+    pub fn statuses(self, statuses: impl IntoIterator<Item = StatusCode>) -> Self {
+        Self {
+            statuses: statuses.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Retries the requests with the non-idempotent methods, such as `POST`
+    /// and `PATCH`.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn retry_non_idempotent(self, retry_non_idempotent: bool) -> Self {
+        Self {
+            retry_non_idempotent,
+            ..self
+        }
+    }
+
+    /// Sets the maximum size of the request body to be buffered for the
+    /// retries.
+    ///
+    /// Default is `64KiB`.
+    #[must_use]
+    pub fn max_body_size(self, max_body_size: usize) -> Self {
+        Self {
+            max_body_size,
+            ..self
+        }
+    }
+
+    /// Sets the retry budget.
+    #[must_use]
+    pub fn budget(self, budget: RetryBudget) -> Self {
+        Self { budget, ..self }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Retry {
+    type Output = RetryEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RetryEndpoint {
+            inner: ep,
+            max_retries: self.max_retries,
+            base_backoff: self.base_backoff,
+            max_backoff: self.max_backoff,
+            jitter: self.jitter,
+            statuses: self.statuses.clone(),
+            retry_non_idempotent: self.retry_non_idempotent,
+            max_body_size: self.max_body_size,
+            budget: self.budget.clone(),
+        }
+    }
+}
+
+/// Endpoint for the Retry middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "retry")))]
+pub struct RetryEndpoint<E> {
+    inner: E,
+    max_retries: usize,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    statuses: Vec<StatusCode>,
+    retry_non_idempotent: bool,
+    max_body_size: usize,
+    budget: RetryBudget,
+}
+
+pub(crate) fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::PUT | Method::DELETE
+    )
+}
+
+/// Returns the body to replay, or `None` if the body is larger than
+/// `max_body_size` or of an unknown size.
+pub(crate) async fn buffer_body(req: &mut Request, max_body_size: usize) -> Result<Option<Bytes>> {
+    if let Some(BufferedBody(data)) = req.extensions().get::<BufferedBody>() {
+        return Ok(Some(data.clone()));
+    }
+
+    let body = req.take_body();
+    if body.is_empty() {
+        return Ok(Some(Bytes::new()));
+    }
+    match hyper::body::Body::size_hint(&body.0).upper() {
+        Some(size) if size <= max_body_size as u64 => {
+            let data = body.into_bytes().await?;
+            req.extensions_mut().insert(BufferedBody(data.clone()));
+            Ok(Some(data))
+        }
+        _ => {
+            req.set_body(body);
+            Ok(None)
+        }
+    }
+}
+
+fn retry_after(resp: &Response) -> Option<Duration> {
+    let value = resp.headers().get(header::RETRY_AFTER)?;
+    if let Some(seconds) = value.to_str().ok().and_then(|s| s.trim().parse().ok()) {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date: SystemTime = Date::decode(&mut std::iter::once(value)).ok()?.into();
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+impl<E: Endpoint> RetryEndpoint<E> {
+    fn backoff(&self, attempt: usize) -> Duration {
+        let delay = self
+            .base_backoff
+            .saturating_mul(1 << attempt.min(31))
+            .min(self.max_backoff);
+        if self.jitter {
+            delay / 2 + delay.mul_f64(rand::random::<f64>() / 2.0)
+        } else {
+            delay
+        }
+    }
+}
+
+impl<E: Endpoint> Endpoint for RetryEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if self.max_retries == 0 || !(self.retry_non_idempotent || is_idempotent(req.method())) {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        }
+        let Some(body) = buffer_body(&mut req, self.max_body_size).await? else {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        };
+        self.budget.deposit();
+
+        let mut attempt = 0;
+        loop {
+            let mut attempt_req = req.clone_without_body();
+            attempt_req.set_body(body.clone());
+            let res = self
+                .inner
+                .call(attempt_req)
+                .await
+                .map(IntoResponse::into_response);
+
+            let status = match &res {
+                Ok(resp) => resp.status(),
+                Err(err) => err.status(),
+            };
+            if attempt >= self.max_retries || !self.statuses.contains(&status) {
+                return res;
+            }
+
+            let delay = match res.as_ref().ok().and_then(retry_after) {
+                Some(delay) if delay > self.max_backoff => return res,
+                Some(delay) => delay,
+                None => self.backoff(attempt),
+            };
+            if !self.budget.withdraw() {
+                return res;
+            }
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{endpoint::make, test::TestClient, EndpointExt, Error};
+
+    fn failing(times: usize, attempts: Arc<AtomicUsize>) -> impl Endpoint<Output = Response> {
+        make(move |req| {
+            let attempts = attempts.clone();
+            async move {
+                let body = req.into_body().into_string().await?;
+                if attempts.fetch_add(1, Ordering::Relaxed) < times {
+                    Err(Error::from_status(StatusCode::BAD_GATEWAY))
+                } else {
+                    Ok(body.into_response())
+                }
+            }
+        })
+        .map_to_response()
+    }
+
+    fn retry() -> Retry {
+        Retry::new().backoff(Duration::from_millis(1), Duration::from_millis(100))
+    }
+
+    #[tokio::test]
+    async fn retry_idempotent() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let cli = TestClient::new(failing(2, attempts.clone()).with(retry()));
+        let resp = cli.put("/").body("abc").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("abc").await;
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let cli = TestClient::new(failing(5, attempts.clone()).with(retry().max_retries(2)));
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_GATEWAY);
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn non_idempotent() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let cli = TestClient::new(failing(1, attempts.clone()).with(retry()));
+        cli.post("/")
+            .body("abc")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_GATEWAY);
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let cli =
+            TestClient::new(failing(1, attempts.clone()).with(retry().retry_non_idempotent(true)));
+        cli.post("/")
+            .body("abc")
+            .send()
+            .await
+            .assert_text("abc")
+            .await;
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn large_body() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let cli = TestClient::new(failing(1, attempts.clone()).with(retry().max_body_size(2)));
+        cli.put("/")
+            .body("abc")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_GATEWAY);
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn retry_after_header() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let ep = make_sync_retry_after("0", attempts.clone()).with(retry());
+        TestClient::new(ep)
+            .get("/")
+            .send()
+            .await
+            .assert_status_is_ok();
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let ep = make_sync_retry_after("60", attempts.clone()).with(retry());
+        TestClient::new(ep)
+            .get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+
+    fn make_sync_retry_after(
+        retry_after: &'static str,
+        attempts: Arc<AtomicUsize>,
+    ) -> impl Endpoint<Output = Response> {
+        crate::endpoint::make_sync(move |_| {
+            if attempts.fetch_add(1, Ordering::Relaxed) == 0 {
+                Response::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .header(header::RETRY_AFTER, retry_after)
+                    .finish()
+            } else {
+                Response::default()
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn budget() {
+        let budget = RetryBudget::new(0.0, 1, Duration::from_secs(60));
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let cli = TestClient::new(failing(10, attempts.clone()).with(retry().budget(budget)));
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_GATEWAY);
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_GATEWAY);
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn backoff() {
+        let ep = Retry::new()
+            .backoff(Duration::from_millis(100), Duration::from_millis(500))
+            .jitter(false)
+            .transform(crate::endpoint::make_sync(|_| ()));
+        assert_eq!(ep.backoff(0), Duration::from_millis(100));
+        assert_eq!(ep.backoff(2), Duration::from_millis(400));
+        assert_eq!(ep.backoff(3), Duration::from_millis(500));
+
+        let ep = Retry::new()
+            .backoff(Duration::from_millis(100), Duration::from_millis(500))
+            .transform(crate::endpoint::make_sync(|_| ()));
+        for _ in 0..10 {
+            let delay = ep.backoff(0);
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+        }
+    }
+}
@@ -0,0 +1,562 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::RngCore;
+
+use crate::{
+    http::{
+        header::{self, HeaderName},
+        HeaderValue,
+    },
+    web::CspNonce,
+    Endpoint, Middleware, Request, Response, Result,
+};
+
+const PERMISSIONS_POLICY: HeaderName = HeaderName::from_static("permissions-policy");
+const CROSS_ORIGIN_OPENER_POLICY: HeaderName =
+    HeaderName::from_static("cross-origin-opener-policy");
+const CROSS_ORIGIN_EMBEDDER_POLICY: HeaderName =
+    HeaderName::from_static("cross-origin-embedder-policy");
+const CROSS_ORIGIN_RESOURCE_POLICY: HeaderName =
+    HeaderName::from_static("cross-origin-resource-policy");
+
+/// A source of the [`ContentSecurityPolicy`] directives.
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CspSource {
+    /// `'self'`
+    SelfOrigin,
+    /// `'none'`
+    None,
+    /// `'unsafe-inline'`
+    UnsafeInline,
+    /// `'unsafe-eval'`
+    UnsafeEval,
+    /// `'strict-dynamic'`
+    StrictDynamic,
+    /// `'nonce-<value>'`, the value is generated for each response and can be
+    /// extracted with [`CspNonce`].
+    Nonce,
+    /// A host or scheme source, e.g. `https://cdn.example.com` or `data:`.
+    Host(String),
+}
+
+impl From<&str> for CspSource {
+    fn from(value: &str) -> Self {
+        CspSource::Host(value.to_string())
+    }
+}
+
+impl From<String> for CspSource {
+    fn from(value: String) -> Self {
+        CspSource::Host(value)
+    }
+}
+
+/// A typed `Content-Security-Policy` builder.
+///
+/// # Example
+///
+/// ```
+/// use poem::middleware::{ContentSecurityPolicy, CspSource};
+///
+/// let csp = ContentSecurityPolicy::new()
+///     .default_src([CspSource::SelfOrigin])
+///     .script_src([CspSource::SelfOrigin, CspSource::Nonce])
+///     .img_src([CspSource::SelfOrigin, "data:".into()])
+///     .upgrade_insecure_requests();
+/// assert_eq!(
+///     csp.to_string(),
+///     "default-src 'self'; script-src 'self' 'nonce-{nonce}'; img-src 'self' data:; \
+///      upgrade-insecure-requests"
+/// );
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ContentSecurityPolicy {
+    directives: Vec<(String, Vec<CspSource>)>,
+}
+
+macro_rules! define_directives {
+    ($($(#[$docs:meta])* ($fn_name:ident, $name:literal)),*) => {
+        $(
+        $(#[$docs])*
+        #[must_use]
+        pub fn $fn_name(self, sources: impl IntoIterator<Item = CspSource>) -> Self {
+            self.directive($name, sources)
+        }
+        )*
+    };
+}
+
+impl ContentSecurityPolicy {
+    /// Create an empty policy.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a directive with the sources.
+    #[must_use]
+    pub fn directive(
+        mut self,
+        name: impl Into<String>,
+        sources: impl IntoIterator<Item = CspSource>,
+    ) -> Self {
+        self.directives
+            .push((name.into(), sources.into_iter().collect()));
+        self
+    }
+
+    define_directives!(
+        /// Adds the `default-src` directive.
+        (default_src, "default-src"),
+        /// Adds the `script-src` directive.
+        (script_src, "script-src"),
+        /// Adds the `style-src` directive.
+        (style_src, "style-src"),
+        /// Adds the `img-src` directive.
+        (img_src, "img-src"),
+        /// Adds the `connect-src` directive.
+        (connect_src, "connect-src"),
+        /// Adds the `font-src` directive.
+        (font_src, "font-src"),
+        /// Adds the `object-src` directive.
+        (object_src, "object-src"),
+        /// Adds the `media-src` directive.
+        (media_src, "media-src"),
+        /// Adds the `frame-src` directive.
+        (frame_src, "frame-src"),
+        /// Adds the `frame-ancestors` directive.
+        (frame_ancestors, "frame-ancestors"),
+        /// Adds the `base-uri` directive.
+        (base_uri, "base-uri"),
+        /// Adds the `form-action` directive.
+        (form_action, "form-action")
+    );
+
+    /// Adds the `upgrade-insecure-requests` directive.
+    #[must_use]
+    pub fn upgrade_insecure_requests(self) -> Self {
+        self.directive("upgrade-insecure-requests", [])
+    }
+
+    /// Adds the `report-uri` directive.
+    #[must_use]
+    pub fn report_uri(self, uri: impl Into<String>) -> Self {
+        self.directive("report-uri", [CspSource::Host(uri.into())])
+    }
+
+    /// Adds the `report-to` directive.
+    #[must_use]
+    pub fn report_to(self, group: impl Into<String>) -> Self {
+        self.directive("report-to", [CspSource::Host(group.into())])
+    }
+
+    fn has_nonce(&self) -> bool {
+        self.directives
+            .iter()
+            .any(|(_, sources)| sources.contains(&CspSource::Nonce))
+    }
+
+    fn render(&self, nonce: Option<&str>) -> String {
+        let mut s = String::new();
+        for (idx, (name, sources)) in self.directives.iter().enumerate() {
+            if idx > 0 {
+                s.push_str("; ");
+            }
+            s.push_str(name);
+            for source in sources {
+                s.push(' ');
+                match source {
+                    CspSource::SelfOrigin => s.push_str("'self'"),
+                    CspSource::None => s.push_str("'none'"),
+                    CspSource::UnsafeInline => s.push_str("'unsafe-inline'"),
+                    CspSource::UnsafeEval => s.push_str("'unsafe-eval'"),
+                    CspSource::StrictDynamic => s.push_str("'strict-dynamic'"),
+                    CspSource::Nonce => {
+                        s.push_str("'nonce-");
+                        s.push_str(nonce.unwrap_or("{nonce}"));
+                        s.push('\'');
+                    }
+                    CspSource::Host(host) => s.push_str(host),
+                }
+            }
+        }
+        s
+    }
+}
+
+impl Display for ContentSecurityPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(None))
+    }
+}
+
+/// The `Strict-Transport-Security` header.
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Hsts {
+    max_age: Duration,
+    include_subdomains: bool,
+    preload: bool,
+}
+
+impl Hsts {
+    /// Create an `Hsts` with the max age.
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            include_subdomains: false,
+            preload: false,
+        }
+    }
+
+    /// Adds the `includeSubDomains` directive.
+    #[must_use]
+    pub fn include_subdomains(self) -> Self {
+        Self {
+            include_subdomains: true,
+            ..self
+        }
+    }
+
+    /// Adds the `preload` directive.
+    #[must_use]
+    pub fn preload(self) -> Self {
+        Self {
+            preload: true,
+            ..self
+        }
+    }
+}
+
+impl Display for Hsts {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "max-age={}", self.max_age.as_secs())?;
+        if self.include_subdomains {
+            f.write_str("; includeSubDomains")?;
+        }
+        if self.preload {
+            f.write_str("; preload")?;
+        }
+        Ok(())
+    }
+}
+
+/// Middleware for setting the security related response headers.
+///
+/// The following headers are set by default:
+///
+/// - `Strict-Transport-Security: max-age=31536000; includeSubDomains`
+/// - `X-Content-Type-Options: nosniff`
+/// - `Referrer-Policy: strict-origin-when-cross-origin`
+/// - `Cross-Origin-Opener-Policy: same-origin`
+/// - `Cross-Origin-Resource-Policy: same-origin`
+///
+/// The headers already set by the inner endpoint are not overridden.
+///
+/// If the [`ContentSecurityPolicy`] contains [`CspSource::Nonce`], a nonce is
+/// generated for each request, and can be extracted with [`CspNonce`].
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     middleware::{ContentSecurityPolicy, CspSource, SecureHeaders},
+///     test::TestClient,
+///     web::{CspNonce, Html},
+///     EndpointExt,
+/// };
+///
+/// #[handler]
+/// fn index(nonce: &CspNonce) -> Html<String> {
+///     Html(format!(r#"<script nonce="{}">alert(1)</script>"#, nonce.0))
+/// }
+///
+/// let app = index.with(SecureHeaders::new().content_security_policy(
+///     ContentSecurityPolicy::new().script_src([CspSource::SelfOrigin, CspSource::Nonce]),
+/// ));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_header("x-content-type-options", "nosniff");
+/// let csp = resp.0.headers()["content-security-policy"]
+///     .to_str()
+///     .unwrap();
+/// assert!(csp.starts_with("script-src 'self' 'nonce-"));
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+pub struct SecureHeaders {
+    headers: Vec<(HeaderName, HeaderValue)>,
+    csp: Option<ContentSecurityPolicy>,
+    csp_report_only: bool,
+}
+
+impl Default for SecureHeaders {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn header_value(value: impl AsRef<str>) -> HeaderValue {
+    HeaderValue::from_str(value.as_ref()).expect("illegal header value")
+}
+
+impl SecureHeaders {
+    /// Create `SecureHeaders` middleware with the default headers.
+    pub fn new() -> Self {
+        Self {
+            headers: Vec::new(),
+            csp: None,
+            csp_report_only: false,
+        }
+        .hsts(Hsts::new(Duration::from_secs(31536000)).include_subdomains())
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .referrer_policy("strict-origin-when-cross-origin")
+        .cross_origin_opener_policy("same-origin")
+        .cross_origin_resource_policy("same-origin")
+    }
+
+    /// Sets the header, replacing the previous value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not a legal header value.
+    #[must_use]
+    pub fn header(mut self, name: HeaderName, value: impl AsRef<str>) -> Self {
+        let value = header_value(value);
+        match self.headers.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.headers.push((name, value)),
+        }
+        self
+    }
+
+    /// Removes the header, e.g. the default headers.
+    #[must_use]
+    pub fn remove_header(mut self, name: HeaderName) -> Self {
+        self.headers.retain(|(n, _)| *n != name);
+        self
+    }
+
+    /// Sets the `Strict-Transport-Security` header.
+    #[must_use]
+    pub fn hsts(self, hsts: Hsts) -> Self {
+        self.header(header::STRICT_TRANSPORT_SECURITY, hsts.to_string())
+    }
+
+    /// Sets the `Referrer-Policy` header.
+    #[must_use]
+    pub fn referrer_policy(self, value: impl AsRef<str>) -> Self {
+        self.header(header::REFERRER_POLICY, value)
+    }
+
+    /// Sets the `Permissions-Policy` header, e.g. `camera=(), geolocation=()`.
+    #[must_use]
+    pub fn permissions_policy(self, value: impl AsRef<str>) -> Self {
+        self.header(PERMISSIONS_POLICY, value)
+    }
+
+    /// Sets the `Cross-Origin-Opener-Policy` header.
+    #[must_use]
+    pub fn cross_origin_opener_policy(self, value: impl AsRef<str>) -> Self {
+        self.header(CROSS_ORIGIN_OPENER_POLICY, value)
+    }
+
+    /// Sets the `Cross-Origin-Embedder-Policy` header.
+    #[must_use]
+    pub fn cross_origin_embedder_policy(self, value: impl AsRef<str>) -> Self {
+        self.header(CROSS_ORIGIN_EMBEDDER_POLICY, value)
+    }
+
+    /// Sets the `Cross-Origin-Resource-Policy` header.
+    #[must_use]
+    pub fn cross_origin_resource_policy(self, value: impl AsRef<str>) -> Self {
+        self.header(CROSS_ORIGIN_RESOURCE_POLICY, value)
+    }
+
+    /// Sets the `Content-Security-Policy` header.
+    #[must_use]
+    pub fn content_security_policy(self, csp: ContentSecurityPolicy) -> Self {
+        Self {
+            csp: Some(csp),
+            csp_report_only: false,
+            ..self
+        }
+    }
+
+    /// Sets the `Content-Security-Policy-Report-Only` header.
+    #[must_use]
+    pub fn content_security_policy_report_only(self, csp: ContentSecurityPolicy) -> Self {
+        Self {
+            csp: Some(csp),
+            csp_report_only: true,
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for SecureHeaders {
+    type Output = SecureHeadersEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        let csp = self.csp.as_ref().map(|csp| {
+            let name = if self.csp_report_only {
+                header::CONTENT_SECURITY_POLICY_REPORT_ONLY
+            } else {
+                header::CONTENT_SECURITY_POLICY
+            };
+            let value = if csp.has_nonce() {
+                CspValue::Nonce(csp.clone())
+            } else {
+                CspValue::Static(header_value(csp.render(None)))
+            };
+            (name, value)
+        });
+
+        SecureHeadersEndpoint {
+            inner: ep,
+            headers: self.headers.clone(),
+            csp,
+        }
+    }
+}
+
+enum CspValue {
+    Static(HeaderValue),
+    Nonce(ContentSecurityPolicy),
+}
+
+/// Endpoint for the SecureHeaders middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+pub struct SecureHeadersEndpoint<E> {
+    inner: E,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    csp: Option<(HeaderName, CspValue)>,
+}
+
+impl<E: Endpoint> Endpoint for SecureHeadersEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let csp = match &self.csp {
+            Some((name, CspValue::Static(value))) => Some((name, value.clone())),
+            Some((name, CspValue::Nonce(csp))) => {
+                let mut nonce = [0u8; 16];
+                rand::thread_rng().fill_bytes(&mut nonce);
+                let nonce = STANDARD.encode(nonce);
+                let value = header_value(csp.render(Some(&nonce)));
+                req.extensions_mut().insert(CspNonce(nonce));
+                Some((name, value))
+            }
+            None => None,
+        };
+
+        let mut resp = self.inner.get_response(req).await;
+        let headers = resp.headers_mut();
+        for (name, value) in self.headers.iter().map(|(n, v)| (n, v.clone())).chain(csp) {
+            if !headers.contains_key(name) {
+                headers.insert(name, value);
+            }
+        }
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{endpoint::make_sync, handler, test::TestClient, EndpointExt, Error};
+
+    #[tokio::test]
+    async fn default_headers() {
+        let ep = make_sync(|_| Err::<(), _>(Error::from_status(StatusCode::NOT_FOUND))).with(
+            SecureHeaders::new()
+                .permissions_policy("camera=()")
+                .cross_origin_embedder_policy("require-corp")
+                .remove_header(header::REFERRER_POLICY)
+                .hsts(Hsts::new(Duration::from_secs(60)).preload()),
+        );
+        let cli = TestClient::new(ep);
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::NOT_FOUND);
+        resp.assert_header(header::STRICT_TRANSPORT_SECURITY, "max-age=60; preload");
+        resp.assert_header(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+        resp.assert_header(PERMISSIONS_POLICY, "camera=()");
+        resp.assert_header(CROSS_ORIGIN_OPENER_POLICY, "same-origin");
+        resp.assert_header(CROSS_ORIGIN_EMBEDDER_POLICY, "require-corp");
+        resp.assert_header(CROSS_ORIGIN_RESOURCE_POLICY, "same-origin");
+        resp.assert_header_is_not_exist(header::REFERRER_POLICY);
+        resp.assert_header_is_not_exist(header::CONTENT_SECURITY_POLICY);
+    }
+
+    #[tokio::test]
+    async fn keep_existing_headers() {
+        #[handler(internal)]
+        fn index() -> Response {
+            Response::builder()
+                .header(header::REFERRER_POLICY, "no-referrer")
+                .finish()
+        }
+
+        let cli = TestClient::new(index.with(SecureHeaders::new()));
+        cli.get("/")
+            .send()
+            .await
+            .assert_header(header::REFERRER_POLICY, "no-referrer");
+    }
+
+    #[tokio::test]
+    async fn csp_nonce() {
+        #[handler(internal)]
+        fn index(nonce: &CspNonce) -> String {
+            nonce.0.clone()
+        }
+
+        let csp = ContentSecurityPolicy::new()
+            .default_src([CspSource::None])
+            .script_src([CspSource::Nonce, CspSource::StrictDynamic])
+            .report_uri("/csp");
+        let cli = TestClient::new(index.with(SecureHeaders::new().content_security_policy(csp)));
+
+        let mut nonces = Vec::new();
+        for _ in 0..2 {
+            let mut resp = cli.get("/").send().await;
+            resp.assert_status_is_ok();
+            let csp = resp.0.headers()[header::CONTENT_SECURITY_POLICY]
+                .to_str()
+                .unwrap()
+                .to_string();
+            let nonce = resp.0.take_body().into_string().await.unwrap();
+            assert_eq!(
+                csp,
+                format!(
+                    "default-src 'none'; script-src 'nonce-{nonce}' 'strict-dynamic'; report-uri \
+                     /csp"
+                )
+            );
+            nonces.push(nonce);
+        }
+        assert_ne!(nonces[0], nonces[1]);
+
+        let cli = TestClient::new(make_sync(|_| ()).with(
+            SecureHeaders::new().content_security_policy_report_only(
+                ContentSecurityPolicy::new().default_src([CspSource::SelfOrigin]),
+            ),
+        ));
+        cli.get("/").send().await.assert_header(
+            header::CONTENT_SECURITY_POLICY_REPORT_ONLY,
+            "default-src 'self'",
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use std::{any::Any, collections::HashSet, panic::AssertUnwindSafe, sync::Arc};
+
+use futures_util::FutureExt;
+use sentry_core::{
+    protocol::{self, Event, Exception, Level, SpanStatus, User},
+    Hub, SentryFutureExt, TransactionContext,
+};
+
+use crate::{
+    http::{header::HeaderName, StatusCode},
+    route::PathPattern,
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+type UserFn = Arc<dyn Fn(&Request) -> Option<User> + Send + Sync>;
+
+/// Middleware for reporting the errors and the panics to
+/// [Sentry](https://sentry.io).
+///
+/// A transaction is started for each request, it continues the distributed
+/// trace of the `sentry-trace` header, and is named with the method and the
+/// matched route pattern. The errors with the `5xx` status and the panics of
+/// the endpoint are captured as the events of the transaction, so they carry
+/// the trace id, along with the method, the url and the allowed headers of the
+/// request.
+///
+/// The sampling is configured with the options of the Sentry client, the
+/// `sample_rate` applies to the events and the `traces_sample_rate` or the
+/// `traces_sampler` to the transactions. Nothing is reported if the client is
+/// not initialized.
+///
+/// The panics are resumed after they are captured, so they can be handled by
+/// the [`CatchPanic`](crate::middleware::CatchPanic) middleware.
+///
+/// # Example
+///
+/// ```
+/// use poem::{get, handler, middleware::Sentry, EndpointExt, Request, Route};
+/// use sentry_core::protocol::User;
+///
+/// #[handler]
+/// fn index() {}
+///
+/// let app = Route::new()
+///     .at("/", get(index))
+///     .with(
+///         Sentry::new()
+///             .allow_header("user-agent")
+///             .user(|req: &Request| {
+///                 Some(User {
+///                     id: req.header("x-user-id").map(ToString::to_string),
+///                     ..Default::default()
+///                 })
+///             }),
+///     );
+/// ```
+#[derive(Default)]
+pub struct Sentry {
+    headers: HashSet<HeaderName>,
+    user: Option<UserFn>,
+    capture_client_errors: bool,
+}
+
+impl Sentry {
+    /// Create a new `Sentry` middleware.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a header to the request context of the events, the other headers
+    /// are not reported.
+    #[must_use]
+    pub fn allow_header<K>(mut self, key: K) -> Self
+    where
+        K: TryInto<HeaderName>,
+    {
+        if let Ok(key) = key.try_into() {
+            self.headers.insert(key);
+        }
+        self
+    }
+
+    /// Uses a closure to get the user of the request, for example from the
+    /// extensions added by the authentication middleware.
+    #[must_use]
+    pub fn user<F>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> Option<User> + Send + Sync + 'static,
+    {
+        Self {
+            user: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
+    /// Also captures the errors with the `4xx` status.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn capture_client_errors(self, enable: bool) -> Self {
+        Self {
+            capture_client_errors: enable,
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Sentry {
+    type Output = SentryEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        SentryEndpoint {
+            inner: ep,
+            headers: self.headers.clone(),
+            user: self.user.clone(),
+            capture_client_errors: self.capture_client_errors,
+        }
+    }
+}
+
+/// Endpoint for the `Sentry` middleware.
+pub struct SentryEndpoint<E> {
+    inner: E,
+    headers: HashSet<HeaderName>,
+    user: Option<UserFn>,
+    capture_client_errors: bool,
+}
+
+impl<E: Endpoint> Endpoint for SentryEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let hub = Arc::new(Hub::new_from_top(Hub::current()));
+        let method = req.method().clone();
+
+        let request = protocol::Request {
+            url: req.original_uri().to_string().parse().ok(),
+            method: Some(method.to_string()),
+            headers: req
+                .headers()
+                .iter()
+                .filter(|(name, _)| self.headers.contains(*name))
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let ctx = TransactionContext::continue_from_headers(
+            &format!("{} {}", method, req.uri().path()),
+            "http.server",
+            req.headers()
+                .iter()
+                .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+        );
+        let transaction = hub.start_transaction(ctx);
+        transaction.set_request(request.clone());
+
+        let user = self.user.as_ref().and_then(|f| f(&req));
+        hub.configure_scope(|scope| {
+            scope.set_span(Some(transaction.clone().into()));
+            scope.set_user(user);
+            scope.add_event_processor(move |mut event| {
+                if event.request.is_none() {
+                    event.request = Some(request.clone());
+                }
+                Some(event)
+            });
+        });
+
+        let res = AssertUnwindSafe(self.inner.call(req))
+            .catch_unwind()
+            .bind_hub(hub.clone())
+            .await;
+
+        let res = match res {
+            Ok(res) => res.map(IntoResponse::into_response),
+            Err(err) => {
+                Hub::run(hub.clone(), || {
+                    hub.capture_event(event_from_panic(&err));
+                    transaction.set_status(SpanStatus::InternalError);
+                    transaction.finish();
+                });
+                std::panic::resume_unwind(err)
+            }
+        };
+
+        Hub::run(hub.clone(), || {
+            let (status, path_pattern) = match &res {
+                Ok(resp) => (resp.status(), resp.data::<PathPattern>()),
+                Err(err) => (err.status(), err.data::<PathPattern>()),
+            };
+            if let Some(path_pattern) = path_pattern {
+                let name = format!("{} {}", method, path_pattern.0);
+                hub.configure_scope(|scope| scope.set_transaction(Some(&name)));
+            }
+            if let Err(err) = &res {
+                if status.is_server_error()
+                    || (self.capture_client_errors && status.is_client_error())
+                {
+                    hub.capture_error(err);
+                }
+            }
+            transaction.set_status(span_status(status));
+            transaction.finish();
+        });
+        res
+    }
+}
+
+fn span_status(status: StatusCode) -> SpanStatus {
+    match status {
+        StatusCode::BAD_REQUEST => SpanStatus::InvalidArgument,
+        StatusCode::UNAUTHORIZED => SpanStatus::Unauthenticated,
+        StatusCode::FORBIDDEN => SpanStatus::PermissionDenied,
+        StatusCode::NOT_FOUND => SpanStatus::NotFound,
+        StatusCode::CONFLICT => SpanStatus::AlreadyExists,
+        StatusCode::TOO_MANY_REQUESTS => SpanStatus::ResourceExhausted,
+        StatusCode::NOT_IMPLEMENTED => SpanStatus::Unimplemented,
+        StatusCode::SERVICE_UNAVAILABLE => SpanStatus::Unavailable,
+        StatusCode::GATEWAY_TIMEOUT => SpanStatus::DeadlineExceeded,
+        status if status.is_client_error() => SpanStatus::InvalidArgument,
+        status if status.is_server_error() => SpanStatus::InternalError,
+        _ => SpanStatus::Ok,
+    }
+}
+
+fn event_from_panic(err: &Box<dyn Any + Send + 'static>) -> Event<'static> {
+    let msg = if let Some(msg) = err.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = err.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    };
+    Event {
+        exception: vec![Exception {
+            ty: "panic".to_string(),
+            value: Some(msg.clone()),
+            ..Default::default()
+        }]
+        .into(),
+        message: Some(msg),
+        level: Level::Fatal,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sentry_core::{
+        protocol::{EnvelopeItem, Transaction},
+        test::with_captured_envelopes_options,
+        ClientOptions,
+    };
+
+    use super::*;
+    use crate::{
+        error::ResponseError, get, handler, middleware::CatchPanic, test::TestClient, EndpointExt,
+        Route,
+    };
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("boom")]
+    struct BoomError;
+
+    impl ResponseError for BoomError {
+        fn status(&self) -> StatusCode {
+            StatusCode::BAD_GATEWAY
+        }
+    }
+
+    #[handler(internal)]
+    fn boom() -> Result<(), BoomError> {
+        Err(BoomError)
+    }
+
+    #[handler(internal)]
+    fn oops() {
+        panic!("oops")
+    }
+
+    fn run(f: impl std::future::Future) -> Vec<protocol::Envelope> {
+        with_captured_envelopes_options(
+            || {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap()
+                    .block_on(f);
+            },
+            ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn app() -> impl Endpoint {
+        Route::new()
+            .at("/users/:id", get(boom))
+            .at("/panic", get(oops))
+            .with(
+                Sentry::new()
+                    .allow_header("user-agent")
+                    .user(|req: &Request| {
+                        Some(User {
+                            id: req.header("x-user-id").map(ToString::to_string),
+                            ..Default::default()
+                        })
+                    }),
+            )
+            .with(CatchPanic::new())
+    }
+
+    fn events(envelopes: &[protocol::Envelope]) -> Vec<&Event<'static>> {
+        envelopes
+            .iter()
+            .filter_map(|envelope| envelope.event())
+            .collect()
+    }
+
+    fn transactions(envelopes: &[protocol::Envelope]) -> Vec<&Transaction<'static>> {
+        envelopes
+            .iter()
+            .flat_map(|envelope| envelope.items())
+            .filter_map(|item| match item {
+                EnvelopeItem::Transaction(transaction) => Some(transaction),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn capture_error() {
+        let envelopes = run(async {
+            TestClient::new(app())
+                .get("/users/1")
+                .header("user-agent", "test")
+                .header("cookie", "secret")
+                .header("x-user-id", "42")
+                .header(
+                    "sentry-trace",
+                    "0123456789abcdef0123456789abcdef-0123456789abcdef-1",
+                )
+                .send()
+                .await
+                .assert_status(StatusCode::BAD_GATEWAY);
+        });
+
+        let events = events(&envelopes);
+        assert_eq!(events.len(), 1);
+        let event = events[0];
+        assert_eq!(event.exception[0].value.as_deref(), Some("boom"));
+        assert_eq!(event.user.as_ref().unwrap().id.as_deref(), Some("42"));
+        let request = event.request.as_ref().unwrap();
+        assert_eq!(request.method.as_deref(), Some("GET"));
+        assert_eq!(request.headers.keys().collect::<Vec<_>>(), ["user-agent"]);
+        let protocol::Context::Trace(trace) = &event.contexts["trace"] else {
+            panic!("expect the trace context");
+        };
+        assert_eq!(
+            trace.trace_id.to_string(),
+            "0123456789abcdef0123456789abcdef"
+        );
+
+        let transactions = transactions(&envelopes);
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].name.as_deref(), Some("GET /users/:id"));
+    }
+
+    #[test]
+    fn capture_panic() {
+        let envelopes = run(async {
+            TestClient::new(app())
+                .get("/panic")
+                .send()
+                .await
+                .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        });
+
+        let events = events(&envelopes);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].level, Level::Fatal);
+        assert_eq!(events[0].message.as_deref(), Some("oops"));
+    }
+
+    #[test]
+    fn ignore_client_errors() {
+        let envelopes = run(async {
+            TestClient::new(app())
+                .get("/missing")
+                .send()
+                .await
+                .assert_status(StatusCode::NOT_FOUND);
+        });
+        assert!(events(&envelopes).is_empty());
+        assert_eq!(
+            transactions(&envelopes)[0].name.as_deref(),
+            Some("GET /missing")
+        );
+    }
+}
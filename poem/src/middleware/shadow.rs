@@ -0,0 +1,416 @@
+use std::{
+    io::{Error as IoError, ErrorKind},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures_util::stream;
+use hyper::body::Frame;
+use tokio::sync::mpsc;
+
+use crate::{
+    body::BoxBody,
+    http::{header, HeaderMap},
+    Body, Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// The headers which are not forwarded to the shadow upstream.
+const HOP_BY_HOP_HEADERS: &[header::HeaderName] = &[
+    header::HOST,
+    header::CONNECTION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+    header::PROXY_AUTHORIZATION,
+];
+
+type FilterFn = Arc<dyn Fn(&Request) -> bool + Send + Sync>;
+
+/// Middleware which mirrors a percentage of the requests to a shadow
+/// upstream, for testing a new version of the service with the real traffic.
+///
+/// The mirrored requests are sent in the background with the same method,
+/// path, query and headers, and the responses of the shadow upstream are
+/// discarded, so they never affect the responses to the clients.
+///
+/// The request body is teed while it is read by the endpoint, so it is not
+/// buffered. If the endpoint does not read the whole body, or the shadow
+/// upstream can not keep up with the endpoint, the mirrored request is
+/// aborted. The requests without a body are always mirrored, even if the
+/// endpoint never reads the body.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, middleware::Shadow, EndpointExt, Request};
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let app = index.with(
+///     Shadow::new("http://10.0.0.2:8080")
+///         .percentage(10.0)
+///         .filter(|req: &Request| req.uri().path() != "/health"),
+/// );
+/// ```
+pub struct Shadow {
+    upstream: String,
+    percentage: f64,
+    timeout: Duration,
+    client: reqwest::Client,
+    filter: Option<FilterFn>,
+}
+
+impl Shadow {
+    /// Create a new `Shadow` middleware which mirrors all the requests to
+    /// the upstream, for example `http://10.0.0.2:8080`.
+    pub fn new(upstream: impl Into<String>) -> Self {
+        Self {
+            upstream: upstream.into().trim_end_matches('/').to_string(),
+            percentage: 100.0,
+            timeout: Duration::from_secs(30),
+            client: reqwest::Client::new(),
+            filter: None,
+        }
+    }
+
+    /// Sets the percentage of the requests to mirror, from `0.0` to `100.0`.
+    ///
+    /// Default is `100.0`.
+    #[must_use]
+    pub fn percentage(self, percentage: f64) -> Self {
+        Self {
+            percentage: percentage.clamp(0.0, 100.0),
+            ..self
+        }
+    }
+
+    /// Sets the timeout of the mirrored requests.
+    ///
+    /// Default is `30s`.
+    #[must_use]
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Uses the client to send the mirrored requests.
+    #[must_use]
+    pub fn client(self, client: reqwest::Client) -> Self {
+        Self { client, ..self }
+    }
+
+    /// Uses a closure to determine if a request can be mirrored.
+    #[must_use]
+    pub fn filter(self, predicate: impl Fn(&Request) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            filter: Some(Arc::new(predicate)),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Shadow {
+    type Output = ShadowEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ShadowEndpoint {
+            inner: ep,
+            upstream: self.upstream.clone(),
+            percentage: self.percentage,
+            timeout: self.timeout,
+            client: self.client.clone(),
+            filter: self.filter.clone(),
+        }
+    }
+}
+
+/// Endpoint for the `Shadow` middleware.
+pub struct ShadowEndpoint<E> {
+    inner: E,
+    upstream: String,
+    percentage: f64,
+    timeout: Duration,
+    client: reqwest::Client,
+    filter: Option<FilterFn>,
+}
+
+impl<E: Endpoint> ShadowEndpoint<E> {
+    fn sampled(&self, req: &Request) -> bool {
+        if self.filter.as_ref().is_some_and(|filter| !filter(req)) {
+            return false;
+        }
+        self.percentage >= 100.0 || rand::random::<f64>() * 100.0 < self.percentage
+    }
+
+    fn mirror(&self, req: &mut Request) {
+        let path_and_query = req
+            .uri()
+            .path_and_query()
+            .map(|path_and_query| path_and_query.as_str())
+            .unwrap_or("/");
+        let url = format!("{}{}", self.upstream, path_and_query);
+
+        let mut headers = HeaderMap::with_capacity(req.headers().len());
+        for (name, value) in req.headers() {
+            if !HOP_BY_HOP_HEADERS.contains(name) {
+                headers.append(name, value.clone());
+            }
+        }
+
+        let request = self
+            .client
+            .request(req.method().clone(), url)
+            .headers(headers)
+            .timeout(self.timeout);
+
+        let body = req.take_body();
+        if hyper::body::Body::is_end_stream(&body.0) {
+            req.set_body(body);
+            spawn_request(request);
+            return;
+        }
+
+        let (tx, rx) = mpsc::channel(16);
+        let complete = Arc::new(AtomicBool::new(false));
+        req.set_body(Body(BoxBody::new(TeeBody {
+            inner: body.0,
+            tx: Some(tx),
+            complete: complete.clone(),
+        })));
+
+        let body = stream::unfold((rx, complete), |(mut rx, complete)| async move {
+            match rx.recv().await {
+                Some(data) => Some((Ok(data), (rx, complete))),
+                None if complete.load(Ordering::Acquire) => None,
+                None => Some((
+                    Err(IoError::new(
+                        ErrorKind::UnexpectedEof,
+                        "the request body is not completely mirrored",
+                    )),
+                    (rx, complete),
+                )),
+            }
+        });
+        spawn_request(request.body(reqwest::Body::wrap_stream(body)));
+    }
+}
+
+fn spawn_request(request: reqwest::RequestBuilder) {
+    crate::spawn::spawn("poem-shadow", async move {
+        match request.send().await {
+            Ok(resp) => {
+                tracing::debug!(status = %resp.status(), "shadow response");
+            }
+            Err(err) => {
+                tracing::debug!(error = %err, "failed to send the shadow request");
+            }
+        }
+    });
+}
+
+impl<E: Endpoint> Endpoint for ShadowEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if self.sampled(&req) {
+            self.mirror(&mut req);
+        }
+        self.inner.call(req).await.map(IntoResponse::into_response)
+    }
+}
+
+/// A body which sends a copy of the data to the shadow request while it is
+/// read.
+struct TeeBody {
+    inner: BoxBody,
+    tx: Option<mpsc::Sender<Bytes>>,
+    complete: Arc<AtomicBool>,
+}
+
+impl hyper::body::Body for TeeBody {
+    type Data = Bytes;
+    type Error = IoError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let res = Pin::new(&mut this.inner).poll_frame(cx);
+        match &res {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let (Some(data), Some(tx)) = (frame.data_ref(), &this.tx) {
+                    if tx.try_send(data.clone()).is_err() {
+                        // the shadow upstream is too slow, abort the mirrored request
+                        this.tx = None;
+                    }
+                }
+            }
+            Poll::Ready(Some(Err(_))) => this.tx = None,
+            Poll::Ready(None) => {
+                if this.tx.take().is_some() {
+                    this.complete.store(true, Ordering::Release);
+                }
+            }
+            Poll::Pending => {}
+        }
+        res
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> hyper::body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for TeeBody {
+    fn drop(&mut self) {
+        // the endpoint read all the data without polling the end of the stream
+        if self.tx.is_some() && hyper::body::Body::is_end_stream(&self.inner) {
+            self.complete.store(true, Ordering::Release);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        handler,
+        http::Method,
+        listener::{Acceptor, Listener, TcpListener as PoemTcpListener},
+        test::TestClient,
+        web::Data,
+        EndpointExt, Server,
+    };
+
+    type Captured = mpsc::UnboundedSender<(Method, String, Option<String>, String)>;
+
+    #[handler(internal)]
+    async fn capture(req: &Request, body: String, tx: Data<&Captured>) -> &'static str {
+        let _ = tx.send((
+            req.method().clone(),
+            req.original_uri().to_string(),
+            req.header("x-custom").map(ToString::to_string),
+            body,
+        ));
+        "shadow"
+    }
+
+    #[handler(internal)]
+    fn echo(body: String) -> String {
+        body
+    }
+
+    async fn shadow_server() -> (
+        String,
+        mpsc::UnboundedReceiver<(Method, String, Option<String>, String)>,
+    ) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let acceptor = PoemTcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor.local_addr()[0].as_socket_addr().cloned().unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(capture.data(tx)));
+        (format!("http://{addr}"), rx)
+    }
+
+    #[tokio::test]
+    async fn mirror_requests() {
+        let (upstream, mut rx) = shadow_server().await;
+        let cli = TestClient::new(
+            echo.with(Shadow::new(&upstream).filter(|req| req.uri().path() != "/health")),
+        );
+
+        let resp = cli
+            .post("/a?b=1")
+            .header("x-custom", "1")
+            .body("hello")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("hello").await;
+
+        let (method, uri, custom, body) = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(method, Method::POST);
+        assert_eq!(uri, "/a?b=1");
+        assert_eq!(custom.as_deref(), Some("1"));
+        assert_eq!(body, "hello");
+
+        cli.get("/health").send().await.assert_status_is_ok();
+        cli.get("/b").send().await.assert_status_is_ok();
+        let (_, uri, _, _) = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(uri, "/b");
+    }
+
+    #[tokio::test]
+    async fn percentage() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        let cli = TestClient::new(echo.with(Shadow::new(upstream).percentage(0.0)));
+        cli.get("/").send().await.assert_status_is_ok();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), listener.accept())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn mirror_ignored_empty_body() {
+        #[handler(internal)]
+        fn index() {}
+
+        let (upstream, mut rx) = shadow_server().await;
+        let cli = TestClient::new(index.with(Shadow::new(&upstream)));
+        cli.get("/a").send().await.assert_status_is_ok();
+        cli.delete("/b").send().await.assert_status_is_ok();
+        for (expected_method, expected_uri) in [(Method::GET, "/a"), (Method::DELETE, "/b")] {
+            let (method, uri, _, body) = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(method, expected_method);
+            assert_eq!(uri, expected_uri);
+            assert_eq!(body, "");
+        }
+    }
+
+    #[tokio::test]
+    async fn abort_unread_body() {
+        #[handler(internal)]
+        fn index() {}
+
+        let (upstream, mut rx) = shadow_server().await;
+        let cli = TestClient::new(index.with(Shadow::new(&upstream)));
+        cli.post("/")
+            .body("hello")
+            .send()
+            .await
+            .assert_status_is_ok();
+        assert!(tokio::time::timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .is_err());
+    }
+}
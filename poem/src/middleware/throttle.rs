@@ -0,0 +1,291 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use futures_util::StreamExt;
+use http::header;
+use parking_lot::Mutex;
+
+use crate::{
+    clock::{Clock, SharedClock},
+    Body, Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// The maximum number of bytes to wait for when the bucket is empty.
+const MAX_WAIT_BYTES: f64 = 16.0 * 1024.0;
+
+struct Bucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    /// The time when the tokens were last updated, `None` if the bucket has
+    /// not been used.
+    updated: Option<Instant>,
+}
+
+impl Bucket {
+    fn new(rate: u64, burst: u64) -> Self {
+        Self {
+            rate: rate as f64,
+            burst: burst as f64,
+            tokens: burst as f64,
+            updated: None,
+        }
+    }
+
+    /// Takes at most `n` tokens at `now`, or returns the time to wait for them.
+    fn take(&mut self, n: usize, now: Instant) -> Result<usize, Duration> {
+        if let Some(updated) = self.updated {
+            self.tokens = (self.tokens
+                + now.saturating_duration_since(updated).as_secs_f64() * self.rate)
+                .min(self.burst);
+        }
+        self.updated = Some(now);
+
+        if self.tokens >= 1.0 {
+            let n = n.min(self.tokens as usize);
+            self.tokens -= n as f64;
+            Ok(n)
+        } else {
+            let wanted = (n as f64).min(self.burst).min(MAX_WAIT_BYTES);
+            Err(Duration::from_secs_f64((wanted - self.tokens) / self.rate))
+        }
+    }
+}
+
+/// Middleware for limiting the bandwidth of the response bodies with a token
+/// bucket.
+///
+/// By default, the bandwidth is shared by all the responses of the endpoint,
+/// use [`Throttle::per_connection`] to limit each connection separately.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, middleware::Throttle, EndpointExt, Route};
+///
+/// #[handler]
+/// fn download() -> Vec<u8> {
+///     vec![0; 16 * 1024 * 1024]
+/// }
+///
+/// // each connection downloads at most 1MiB per second
+/// let app = Route::new().at(
+///     "/download",
+///     download.with(Throttle::new(1024 * 1024).per_connection()),
+/// );
+/// ```
+pub struct Throttle {
+    rate: u64,
+    burst: u64,
+    per_connection: bool,
+}
+
+impl Throttle {
+    /// Create `Throttle` middleware that limits the bandwidth to
+    /// `bytes_per_second`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_second` is `0`.
+    pub fn new(bytes_per_second: u64) -> Self {
+        assert!(bytes_per_second > 0, "the bandwidth must be greater than 0");
+        Self {
+            rate: bytes_per_second,
+            burst: bytes_per_second,
+            per_connection: false,
+        }
+    }
+
+    /// Sets the number of bytes that can be sent at once after being idle.
+    ///
+    /// Default is the number of bytes per second.
+    #[must_use]
+    pub fn burst(self, bytes: u64) -> Self {
+        Self {
+            burst: bytes.max(1),
+            ..self
+        }
+    }
+
+    /// Limits the bandwidth of each connection separately, the concurrent
+    /// responses on the same connection share the bandwidth.
+    #[must_use]
+    pub fn per_connection(self) -> Self {
+        Self {
+            per_connection: true,
+            ..self
+        }
+    }
+}
+
+enum Buckets {
+    Shared(Arc<Mutex<Bucket>>),
+    PerConnection(Mutex<HashMap<String, Weak<Mutex<Bucket>>>>),
+}
+
+impl<E: Endpoint> Middleware<E> for Throttle {
+    type Output = ThrottleEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ThrottleEndpoint {
+            inner: ep,
+            rate: self.rate,
+            burst: self.burst,
+            buckets: if self.per_connection {
+                Buckets::PerConnection(Default::default())
+            } else {
+                Buckets::Shared(Arc::new(Mutex::new(Bucket::new(self.rate, self.burst))))
+            },
+        }
+    }
+}
+
+/// Endpoint for the Throttle middleware.
+pub struct ThrottleEndpoint<E> {
+    inner: E,
+    rate: u64,
+    burst: u64,
+    buckets: Buckets,
+}
+
+impl<E> ThrottleEndpoint<E> {
+    fn bucket(&self, req: &Request) -> Arc<Mutex<Bucket>> {
+        match &self.buckets {
+            Buckets::Shared(bucket) => bucket.clone(),
+            Buckets::PerConnection(buckets) => {
+                let mut buckets = buckets.lock();
+                let key = req.remote_addr().to_string();
+                if let Some(bucket) = buckets.get(&key).and_then(Weak::upgrade) {
+                    return bucket;
+                }
+                let bucket = Arc::new(Mutex::new(Bucket::new(self.rate, self.burst)));
+                buckets.retain(|_, bucket| bucket.strong_count() > 0);
+                buckets.insert(key, Arc::downgrade(&bucket));
+                bucket
+            }
+        }
+    }
+}
+
+fn throttle(body: Body, bucket: Arc<Mutex<Bucket>>, clock: SharedClock) -> Body {
+    let stream = body.into_bytes_stream();
+    Body::from_bytes_stream(futures_util::stream::unfold(
+        (Box::pin(stream), None::<Bytes>, bucket, clock),
+        |(mut stream, mut pending, bucket, clock)| async move {
+            loop {
+                let mut data = match pending.take() {
+                    Some(data) => data,
+                    None => match stream.next().await? {
+                        Ok(data) if data.is_empty() => continue,
+                        Ok(data) => data,
+                        Err(err) => return Some((Err(err), (stream, None, bucket, clock))),
+                    },
+                };
+
+                let res = bucket.lock().take(data.len(), clock.now());
+                match res {
+                    Ok(n) => {
+                        let rest = data.split_off(n);
+                        pending = (!rest.is_empty()).then_some(rest);
+                        return Some((Ok(data), (stream, pending, bucket, clock)));
+                    }
+                    Err(wait) => {
+                        pending = Some(data);
+                        tokio::time::sleep(wait).await;
+                    }
+                }
+            }
+        },
+    ))
+}
+
+impl<E: Endpoint> Endpoint for ThrottleEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let bucket = self.bucket(&req);
+        let clock = SharedClock::from_request(&req);
+        let mut resp = self.inner.call(req).await?.into_response();
+        let body = resp.take_body();
+        if body.is_empty() {
+            resp.set_body(body);
+            return Ok(resp);
+        }
+
+        if let Some(len) = hyper::body::Body::size_hint(&body.0).exact() {
+            resp.headers_mut()
+                .entry(header::CONTENT_LENGTH)
+                .or_insert_with(|| len.into());
+        }
+        resp.set_body(throttle(body, bucket, clock));
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::MockClock, endpoint::make_sync, test::TestClient, EndpointExt};
+
+    #[tokio::test]
+    async fn throttle() {
+        let ep = make_sync(|_| "a".repeat(3000)).with(Throttle::new(10000).burst(1000));
+        let cli = TestClient::new(ep);
+
+        let start = Instant::now();
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("content-length", "3000");
+        resp.assert_text("a".repeat(3000)).await;
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[test]
+    fn bucket() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(100, 50);
+        assert_eq!(bucket.take(80, start), Ok(50));
+        assert_eq!(bucket.take(10, start), Err(Duration::from_millis(100)));
+        assert_eq!(bucket.take(80, start + Duration::from_millis(300)), Ok(30));
+        assert_eq!(bucket.take(80, start + Duration::from_secs(10)), Ok(50));
+    }
+
+    #[tokio::test]
+    async fn throttle_with_clock() {
+        let clock = MockClock::new();
+        let ep = make_sync(|_| "a".repeat(100))
+            .with(Throttle::new(100).burst(100))
+            .data(SharedClock::new(clock.clone()));
+        let cli = TestClient::new(ep);
+
+        cli.get("/").send().await.assert_text("a".repeat(100)).await;
+        clock.advance(Duration::from_secs(1));
+        // the bucket is refilled by the clock, so it does not wait
+        tokio::time::timeout(Duration::from_millis(500), async {
+            cli.get("/").send().await.assert_text("a".repeat(100)).await
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn per_connection() {
+        let ep = make_sync(|_| "abc").with(Throttle::new(10000).per_connection());
+        let cli = TestClient::new(&ep);
+
+        cli.get("/").send().await.assert_text("abc").await;
+        cli.get("/").send().await.assert_text("abc").await;
+        match &ep.buckets {
+            Buckets::PerConnection(buckets) => {
+                let buckets = buckets.lock();
+                assert_eq!(buckets.len(), 1);
+                assert!(buckets.values().all(|bucket| bucket.strong_count() == 0));
+            }
+            Buckets::Shared(_) => unreachable!(),
+        }
+    }
+}
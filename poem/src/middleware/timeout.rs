@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::{error::RequestTimeoutError, web::CancelToken, Endpoint, Middleware, Request, Result};
+
+/// Middleware to limit the time to process a request.
+///
+/// When the time is exceeded, the [`CancelToken`] of the request is cancelled
+/// and the `REQUEST_TIMEOUT` status code is returned.
+///
+/// # Errors
+///
+/// - [`RequestTimeoutError`]
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{handler, http::StatusCode, middleware::Timeout, test::TestClient, EndpointExt};
+///
+/// #[handler]
+/// async fn index() {
+///     tokio::time::sleep(Duration::from_secs(10)).await;
+/// }
+///
+/// let cli = TestClient::new(index.with(Timeout::new(Duration::from_millis(10))));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status(StatusCode::REQUEST_TIMEOUT);
+/// # });
+/// ```
+pub struct Timeout {
+    duration: Duration,
+}
+
+impl Timeout {
+    /// Create `Timeout` middleware.
+    pub fn new(duration: Duration) -> Self {
+        Self { duration }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Timeout {
+    type Output = TimeoutEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        TimeoutEndpoint {
+            inner: ep,
+            duration: self.duration,
+        }
+    }
+}
+
+/// Endpoint for the Timeout middleware.
+pub struct TimeoutEndpoint<E> {
+    inner: E,
+    duration: Duration,
+}
+
+impl<E: Endpoint> Endpoint for TimeoutEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let token = match req.extensions().get::<CancelToken>() {
+            Some(token) => token.child_token(),
+            None => CancellationToken::new(),
+        };
+        req.extensions_mut().insert(CancelToken::new(token.clone()));
+
+        match tokio::time::timeout(self.duration, self.inner.call(req)).await {
+            Ok(res) => res,
+            Err(_) => {
+                token.cancel();
+                Err(RequestTimeoutError.into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{handler, test::TestClient, web::Data, EndpointExt};
+
+    #[tokio::test]
+    async fn timeout() {
+        #[handler(internal)]
+        async fn index(token: CancelToken, aborted: Data<&Arc<AtomicBool>>) {
+            let aborted = aborted.0.clone();
+            tokio::spawn(async move {
+                token.cancelled().await;
+                aborted.store(true, Ordering::SeqCst);
+            });
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }
+
+        #[handler(internal)]
+        async fn fast(token: CancelToken) -> String {
+            token.is_cancelled().to_string()
+        }
+
+        let aborted = Arc::new(AtomicBool::new(false));
+        let cli = TestClient::new(
+            index
+                .with(Timeout::new(Duration::from_millis(50)))
+                .data(aborted.clone()),
+        );
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::REQUEST_TIMEOUT);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(aborted.load(Ordering::SeqCst));
+
+        let cli = TestClient::new(fast.with(Timeout::new(Duration::from_secs(5))));
+        cli.get("/").send().await.assert_text("false").await;
+    }
+}
@@ -2,6 +2,7 @@ use std::{sync::Arc, time::Duration};
 
 use parking_lot::Mutex;
 use serde::Serialize;
+use tokio::runtime::Handle;
 use tokio_metrics::{TaskMetrics, TaskMonitor};
 
 use crate::{
@@ -9,6 +10,12 @@ use crate::{
 };
 
 /// Middleware for metrics with [`tokio-metrics`](https://crates.io/crates/tokio-metrics) crate.
+///
+/// Besides the metrics of the instrumented requests, the metrics of the tokio
+/// runtime are exported in the `runtime` object. The number of workers, the
+/// number of alive tasks and the depth of the global queue are always
+/// available, the poll counts, the poll and busy durations and the depths of
+/// the local queues require `--cfg tokio_unstable`.
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio-metrics")))]
 pub struct TokioMetrics {
     interval: Duration,
@@ -53,6 +60,12 @@ impl TokioMetrics {
             }
         }))
     }
+
+    /// Returns a function to get the current metrics as JSON.
+    pub(crate) fn json(&self) -> impl Fn() -> serde_json::Value + Send + Sync + 'static {
+        let metrics = self.metrics.clone();
+        move || serde_json::to_value(&*metrics.lock()).unwrap_or_default()
+    }
 }
 
 impl<E: Endpoint> Middleware<E> for TokioMetrics {
@@ -62,15 +75,24 @@ impl<E: Endpoint> Middleware<E> for TokioMetrics {
         let monitor = TaskMonitor::new();
         let interval = self.interval;
         let metrics = self.metrics.clone();
+        let handle = Handle::current();
 
-        tokio::spawn({
+        crate::spawn::spawn("poem-tokio-metrics", {
             let monitor = monitor.clone();
             async move {
                 let mut intervals = monitor.intervals();
+                #[cfg(tokio_unstable)]
+                let mut runtime_intervals = tokio_metrics::RuntimeMonitor::new(&handle).intervals();
                 loop {
                     tokio::time::sleep(interval).await;
+                    let mut metrics = metrics.lock();
                     if let Some(m) = intervals.next() {
-                        *metrics.lock() = m.into();
+                        metrics.task = m.into();
+                    }
+                    metrics.runtime = RuntimeMetrics::new(&handle);
+                    #[cfg(tokio_unstable)]
+                    if let Some(m) = runtime_intervals.next() {
+                        metrics.runtime.unstable = Some(m.into());
                     }
                 }
             }
@@ -100,6 +122,13 @@ impl<E: Endpoint> Endpoint for TokioMetricsEndpoint<E> {
 
 #[derive(Serialize, Default)]
 struct Metrics {
+    #[serde(flatten)]
+    task: TaskMetricsData,
+    runtime: RuntimeMetrics,
+}
+
+#[derive(Serialize, Default)]
+struct TaskMetricsData {
     instrumented_count: u64,
     dropped_count: u64,
     first_poll_count: u64,
@@ -116,7 +145,7 @@ struct Metrics {
     total_slow_poll_duration: Duration,
 }
 
-impl From<TaskMetrics> for Metrics {
+impl From<TaskMetrics> for TaskMetricsData {
     fn from(metrics: TaskMetrics) -> Self {
         Self {
             instrumented_count: metrics.instrumented_count,
@@ -136,3 +165,79 @@ impl From<TaskMetrics> for Metrics {
         }
     }
 }
+
+#[derive(Serialize, Default)]
+struct RuntimeMetrics {
+    workers_count: usize,
+    alive_tasks_count: usize,
+    global_queue_depth: usize,
+    #[cfg(tokio_unstable)]
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    unstable: Option<UnstableRuntimeMetrics>,
+}
+
+impl RuntimeMetrics {
+    fn new(handle: &Handle) -> Self {
+        let metrics = handle.metrics();
+        Self {
+            workers_count: metrics.num_workers(),
+            alive_tasks_count: metrics.num_alive_tasks(),
+            global_queue_depth: metrics.global_queue_depth(),
+            #[cfg(tokio_unstable)]
+            unstable: None,
+        }
+    }
+}
+
+#[cfg(tokio_unstable)]
+#[derive(Serialize)]
+struct UnstableRuntimeMetrics {
+    total_polls_count: u64,
+    mean_poll_duration: Duration,
+    total_busy_duration: Duration,
+    busy_ratio: f64,
+    total_local_queue_depth: usize,
+    max_local_queue_depth: usize,
+    elapsed: Duration,
+}
+
+#[cfg(tokio_unstable)]
+impl From<tokio_metrics::RuntimeMetrics> for UnstableRuntimeMetrics {
+    fn from(metrics: tokio_metrics::RuntimeMetrics) -> Self {
+        Self {
+            total_polls_count: metrics.total_polls_count,
+            mean_poll_duration: metrics.mean_poll_duration,
+            total_busy_duration: metrics.total_busy_duration,
+            busy_ratio: metrics.busy_ratio(),
+            total_local_queue_depth: metrics.total_local_queue_depth,
+            max_local_queue_depth: metrics.max_local_queue_depth,
+            elapsed: metrics.elapsed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[tokio::test]
+    async fn runtime_metrics() {
+        #[handler(internal)]
+        fn index() {}
+
+        let metrics = TokioMetrics::new().interval(Duration::from_millis(10));
+        let exporter = TestClient::new(metrics.exporter());
+        let cli = TestClient::new(index.with(metrics));
+        cli.get("/").send().await.assert_status_is_ok();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let resp = exporter.get("/").send().await;
+        resp.assert_status_is_ok();
+        let json = resp.json().await;
+        let json = json.value().object();
+        let runtime = json.get("runtime").object();
+        runtime.get("workers_count").assert_i64(1);
+        runtime.get("global_queue_depth").assert_i64(0);
+    }
+}
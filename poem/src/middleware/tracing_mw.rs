@@ -1,93 +1,252 @@
-use std::time::Instant;
+use std::{sync::Arc, time::Instant};
 
-use tracing::{Instrument, Level};
+use tracing::{field::Empty, Instrument, Level, Span};
 
 use crate::{
-    route::PathPattern, web::RealIp, Endpoint, FromRequest, IntoResponse, Middleware, Request,
-    Response, Result,
+    http::Method, route::PathPattern, web::RealIp, Endpoint, FromRequest, IntoResponse, Middleware,
+    Request, Response, Result,
 };
 
+type SpanNameFn = Arc<dyn Fn(&Method, Option<&str>) -> String + Send + Sync>;
+type MakeSpanFn = Arc<dyn Fn(&Request) -> Span + Send + Sync>;
+type FilterFn = Arc<dyn Fn(&Request) -> bool + Send + Sync>;
+
 /// Middleware for [`tracing`](https://crates.io/crates/tracing).
+///
+/// Each request is traced in a span named `request`, with the remote address,
+/// the version, the method and the uri of the request. The matched route
+/// pattern is recorded in the `path_pattern` field when the request is
+/// handled by a [`Route`](crate::Route).
+///
+/// Use [`Tracing::builder`] to customize the spans.
 #[derive(Default)]
 pub struct Tracing;
 
+impl Tracing {
+    /// Create a [`TracingBuilder`] to customize the spans.
+    pub fn builder() -> TracingBuilder {
+        Default::default()
+    }
+}
+
 impl<E: Endpoint> Middleware<E> for Tracing {
     type Output = TracingEndpoint<E>;
 
     fn transform(&self, ep: E) -> Self::Output {
-        TracingEndpoint { inner: ep }
+        TracingBuilder::default().transform(ep)
+    }
+}
+
+/// Middleware for [`tracing`](https://crates.io/crates/tracing) with
+/// customized spans, created by [`Tracing::builder`].
+///
+/// # Example
+///
+/// ```
+/// use poem::{get, handler, http::Method, middleware::Tracing, EndpointExt, Request, Route};
+///
+/// #[handler]
+/// fn index() {}
+///
+/// let app = Route::new()
+///     .at("/users/:id", get(index))
+///     .at("/health", get(index))
+///     .with(
+///         Tracing::builder()
+///             .span_name(|method: &Method, pattern: Option<&str>| {
+///                 format!("{method} {}", pattern.unwrap_or("unknown"))
+///             })
+///             .filter(|req: &Request| req.uri().path() != "/health"),
+///     );
+/// ```
+#[derive(Default)]
+pub struct TracingBuilder {
+    span_name: Option<SpanNameFn>,
+    make_span: Option<MakeSpanFn>,
+    filter: Option<FilterFn>,
+}
+
+impl TracingBuilder {
+    /// Names the spans with the method and the matched route pattern, the
+    /// pattern is `None` if no route is matched.
+    ///
+    /// Because the names of the `tracing` spans are static, the name is
+    /// recorded in the `otel.name` field, which is used as the span name by
+    /// [`tracing-opentelemetry`](https://crates.io/crates/tracing-opentelemetry).
+    #[must_use]
+    pub fn span_name<F>(self, f: F) -> Self
+    where
+        F: Fn(&Method, Option<&str>) -> String + Send + Sync + 'static,
+    {
+        Self {
+            span_name: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
+    /// Uses a closure to create the span of the request, so the custom fields
+    /// can be added from the request.
+    ///
+    /// Declare the `path_pattern` and `otel.name` fields with
+    /// [`tracing::field::Empty`] to record the matched route pattern and the
+    /// name of the span.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{middleware::Tracing, Request};
+    ///
+    /// let tracing = Tracing::builder().make_span(|req: &Request| {
+    ///     tracing::info_span!(
+    ///         "request",
+    ///         method = %req.method(),
+    ///         tenant = req.header("x-tenant").unwrap_or_default(),
+    ///         path_pattern = tracing::field::Empty,
+    ///     )
+    /// });
+    /// ```
+    #[must_use]
+    pub fn make_span<F>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> Span + Send + Sync + 'static,
+    {
+        Self {
+            make_span: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
+    /// Uses a closure to determine if a request should be traced, for example
+    /// to skip the health checks.
+    #[must_use]
+    pub fn filter<F>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            filter: Some(Arc::new(f)),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for TracingBuilder {
+    type Output = TracingEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        TracingEndpoint {
+            inner: ep,
+            span_name: self.span_name.clone(),
+            make_span: self.make_span.clone(),
+            filter: self.filter.clone(),
+        }
     }
 }
 
 /// Endpoint for the `Tracing` middleware.
 pub struct TracingEndpoint<E> {
     inner: E,
+    span_name: Option<SpanNameFn>,
+    make_span: Option<MakeSpanFn>,
+    filter: Option<FilterFn>,
+}
+
+async fn default_span(req: &Request) -> Span {
+    let remote_addr = RealIp::from_request_without_body(req)
+        .await
+        .ok()
+        .and_then(|real_ip| real_ip.0)
+        .map(|addr| addr.to_string())
+        .unwrap_or_else(|| req.remote_addr().to_string());
+
+    #[cfg(not(feature = "requestid"))]
+    let span = tracing::span!(
+        target: module_path!(),
+        Level::INFO,
+        "request",
+        remote_addr = %remote_addr,
+        version = ?req.version(),
+        method = %req.method(),
+        uri = %req.original_uri(),
+        path_pattern = Empty,
+        otel.name = Empty,
+    );
+    #[cfg(feature = "requestid")]
+    let span = {
+        req.extensions()
+            .get::<crate::middleware::requestid::ReqId>()
+            .map_or_else(
+                || {
+                    tracing::span!(
+                        target: module_path!(),
+                        Level::INFO,
+                        "request",
+                        remote_addr = %remote_addr,
+                        version = ?req.version(),
+                        method = %req.method(),
+                        uri = %req.original_uri(),
+                        path_pattern = Empty,
+                        otel.name = Empty,
+                    )
+                },
+                |request_id| {
+                    tracing::span!(
+                        target: module_path!(),
+                        Level::INFO,
+                        "request",
+                        remote_addr = %remote_addr,
+                        version = ?req.version(),
+                        method = %req.method(),
+                        uri = %req.original_uri(),
+                        path_pattern = Empty,
+                        otel.name = Empty,
+                        %request_id
+                    )
+                },
+            )
+    };
+    span
 }
 
 impl<E: Endpoint> Endpoint for TracingEndpoint<E> {
     type Output = Response;
 
     async fn call(&self, req: Request) -> Result<Self::Output> {
-        let remote_addr = RealIp::from_request_without_body(&req)
-            .await
-            .ok()
-            .and_then(|real_ip| real_ip.0)
-            .map(|addr| addr.to_string())
-            .unwrap_or_else(|| req.remote_addr().to_string());
-
-        #[cfg(not(feature = "requestid"))]
-        let span = tracing::span!(
-            target: module_path!(),
-            Level::INFO,
-            "request",
-            remote_addr = %remote_addr,
-            version = ?req.version(),
-            method = %req.method(),
-            uri = %req.original_uri(),
-        );
-        #[cfg(feature = "requestid")]
-        let span = {
-            req.extensions()
-                .get::<crate::middleware::requestid::ReqId>()
-                .map_or_else(
-                    || {
-                        tracing::span!(
-                            target: module_path!(),
-                            Level::INFO,
-                            "request",
-                            remote_addr = %remote_addr,
-                            version = ?req.version(),
-                            method = %req.method(),
-                            uri = %req.original_uri(),
-                        )
-                    },
-                    |request_id| {
-                        tracing::span!(
-                            target: module_path!(),
-                            Level::INFO,
-                            "request",
-                            remote_addr = %remote_addr,
-                            version = ?req.version(),
-                            method = %req.method(),
-                            uri = %req.original_uri(),
-                            %request_id
-                        )
-                    },
-                )
-        };
+        if let Some(filter) = &self.filter {
+            if !filter(&req) {
+                return self.inner.call(req).await.map(IntoResponse::into_response);
+            }
+        }
 
+        let span = match &self.make_span {
+            Some(make_span) => make_span(&req),
+            None => default_span(&req).await,
+        };
         if let Some(path_pattern) = req.data::<PathPattern>() {
             span.record("path_pattern", path_pattern.0.as_ref());
         }
+        let method = req.method().clone();
 
+        let request_span = span.clone();
         async move {
             let now = Instant::now();
-            let res = self.inner.call(req).await;
+            let res = self.inner.call(req).await.map(IntoResponse::into_response);
             let duration = now.elapsed();
 
+            let path_pattern = match &res {
+                Ok(resp) => resp.data::<PathPattern>(),
+                Err(err) => err.data::<PathPattern>(),
+            };
+            if let Some(path_pattern) = path_pattern {
+                request_span.record("path_pattern", path_pattern.0.as_ref());
+            }
+            if let Some(span_name) = &self.span_name {
+                let name = span_name(&method, path_pattern.map(|pattern| pattern.0.as_ref()));
+                request_span.record("otel.name", name);
+            }
+
             match res {
                 Ok(resp) => {
-                    let resp = resp.into_response();
                     tracing::info!(
                         status = %resp.status(),
                         duration = ?duration,
@@ -110,3 +269,132 @@ impl<E: Endpoint> Endpoint for TracingEndpoint<E> {
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fmt::Debug,
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    use parking_lot::Mutex;
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Metadata, Subscriber,
+    };
+
+    use super::*;
+    use crate::{get, handler, test::TestClient, EndpointExt, Route};
+
+    /// Collects the fields of the spans.
+    #[derive(Default, Clone)]
+    struct Collector {
+        next_id: Arc<AtomicU64>,
+        fields: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    impl Visit for &Collector {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.fields
+                .lock()
+                .push((field.name().to_string(), value.to_string()));
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.record_str(field, &format!("{value:?}"));
+        }
+    }
+
+    impl Subscriber for Collector {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            span.record(&mut &*self);
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+        }
+
+        fn record(&self, _span: &Id, values: &Record<'_>) {
+            values.record(&mut &*self);
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &tracing::Event<'_>) {}
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    impl Collector {
+        fn take(&self, name: &str) -> Vec<String> {
+            let mut values = Vec::new();
+            self.fields.lock().retain(|(field, value)| {
+                if field == name {
+                    values.push(value.clone());
+                }
+                field != name
+            });
+            values
+        }
+    }
+
+    #[handler(internal)]
+    fn index() {}
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn span_name_and_filter() {
+        let collector = Collector::default();
+        let _guard = tracing::subscriber::set_default(collector.clone());
+
+        let cli = TestClient::new(
+            Route::new()
+                .at("/users/:id", get(index))
+                .at("/health", get(index))
+                .with(
+                    Tracing::builder()
+                        .span_name(|method, pattern| {
+                            format!("{method} {}", pattern.unwrap_or("unknown"))
+                        })
+                        .filter(|req| req.uri().path() != "/health"),
+                ),
+        );
+
+        cli.get("/users/1").send().await.assert_status_is_ok();
+        assert_eq!(collector.take("path_pattern"), ["/users/:id"]);
+        assert_eq!(collector.take("otel.name"), ["GET /users/:id"]);
+        collector.fields.lock().clear();
+
+        cli.get("/health").send().await.assert_status_is_ok();
+        assert!(collector.take("uri").is_empty());
+
+        cli.get("/missing").send().await;
+        assert_eq!(collector.take("otel.name"), ["GET unknown"]);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn make_span() {
+        let collector = Collector::default();
+        let _guard = tracing::subscriber::set_default(collector.clone());
+
+        let cli = TestClient::new(Route::new().at("/users/:id", get(index)).with(
+            Tracing::builder().make_span(|req| {
+                tracing::info_span!(
+                    "request",
+                    tenant = req.header("x-tenant").unwrap_or_default(),
+                    path_pattern = Empty,
+                )
+            }),
+        ));
+
+        cli.get("/users/1")
+            .header("x-tenant", "acme")
+            .send()
+            .await
+            .assert_status_is_ok();
+        assert_eq!(collector.take("tenant"), ["acme"]);
+    }
+}
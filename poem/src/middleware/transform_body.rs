@@ -0,0 +1,361 @@
+use std::{
+    io::{Error as IoError, Result as IoResult},
+    mem,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use hyper::body::Frame;
+
+use crate::{
+    body::BoxBody,
+    http::{header, HeaderMap},
+    Body, Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// A transformation of a streaming body.
+///
+/// A new transformation is created for each body, and it is called for each
+/// data chunk as the body is read, so the body is never buffered unless the
+/// transformation does so.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Result;
+///
+/// use bytes::Bytes;
+/// use poem::{
+///     handler,
+///     middleware::{BodyTransform, TransformBody},
+///     test::TestClient,
+///     EndpointExt,
+/// };
+///
+/// struct Redact;
+///
+/// impl BodyTransform for Redact {
+///     fn transform_data(&mut self, data: Bytes) -> Result<Bytes> {
+///         let data = String::from_utf8_lossy(&data).replace("secret", "******");
+///         Ok(data.into())
+///     }
+/// }
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "the secret is 42"
+/// }
+///
+/// let app = index.with(TransformBody::new().response(|_| Some(Redact)));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// cli.get("/")
+///     .send()
+///     .await
+///     .assert_text("the ****** is 42")
+///     .await;
+/// # });
+/// ```
+pub trait BodyTransform: Send + Sync + 'static {
+    /// Transforms a data chunk of the body.
+    ///
+    /// The chunks may be split at any position, the transformation can keep
+    /// the incomplete data and return it with the following chunks, or in
+    /// [`BodyTransform::finish`].
+    fn transform_data(&mut self, data: Bytes) -> IoResult<Bytes>;
+
+    /// Called when all the data of the body have been transformed, and
+    /// returns the remaining data.
+    fn finish(&mut self) -> IoResult<Bytes> {
+        Ok(Bytes::new())
+    }
+
+    /// Transforms the trailers of the body, it is called after
+    /// [`BodyTransform::finish`] with `None` if the body has no trailers.
+    fn transform_trailers(&mut self, trailers: Option<HeaderMap>) -> IoResult<Option<HeaderMap>> {
+        Ok(trailers)
+    }
+}
+
+impl<T: BodyTransform + ?Sized> BodyTransform for Box<T> {
+    #[inline]
+    fn transform_data(&mut self, data: Bytes) -> IoResult<Bytes> {
+        self.as_mut().transform_data(data)
+    }
+
+    #[inline]
+    fn finish(&mut self) -> IoResult<Bytes> {
+        self.as_mut().finish()
+    }
+
+    #[inline]
+    fn transform_trailers(&mut self, trailers: Option<HeaderMap>) -> IoResult<Option<HeaderMap>> {
+        self.as_mut().transform_trailers(trailers)
+    }
+}
+
+type RequestTransformFn = Arc<dyn Fn(&Request) -> Option<Box<dyn BodyTransform>> + Send + Sync>;
+type ResponseTransformFn = Arc<dyn Fn(&Response) -> Option<Box<dyn BodyTransform>> + Send + Sync>;
+
+/// Middleware which transforms the streaming bodies of the requests and the
+/// responses with [`BodyTransform`].
+///
+/// Because the length of a transformed body is unknown, the `Content-Length`
+/// header is removed, and the body is sent with the chunked transfer
+/// encoding.
+///
+/// See also [`BodyTransform`].
+#[derive(Default)]
+pub struct TransformBody {
+    request: Option<RequestTransformFn>,
+    response: Option<ResponseTransformFn>,
+}
+
+impl TransformBody {
+    /// Create a new `TransformBody` middleware.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Transforms the request body with the transformation created by the
+    /// function, or leaves it unchanged if the function returns `None`.
+    #[must_use]
+    pub fn request<F, T>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> Option<T> + Send + Sync + 'static,
+        T: BodyTransform,
+    {
+        Self {
+            request: Some(Arc::new(move |req| {
+                f(req).map(|transform| Box::new(transform) as Box<dyn BodyTransform>)
+            })),
+            ..self
+        }
+    }
+
+    /// Transforms the response body with the transformation created by the
+    /// function, or leaves it unchanged if the function returns `None`.
+    #[must_use]
+    pub fn response<F, T>(self, f: F) -> Self
+    where
+        F: Fn(&Response) -> Option<T> + Send + Sync + 'static,
+        T: BodyTransform,
+    {
+        Self {
+            response: Some(Arc::new(move |resp| {
+                f(resp).map(|transform| Box::new(transform) as Box<dyn BodyTransform>)
+            })),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for TransformBody {
+    type Output = TransformBodyEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        TransformBodyEndpoint {
+            inner: ep,
+            request: self.request.clone(),
+            response: self.response.clone(),
+        }
+    }
+}
+
+/// Endpoint for the TransformBody middleware.
+pub struct TransformBodyEndpoint<E> {
+    inner: E,
+    request: Option<RequestTransformFn>,
+    response: Option<ResponseTransformFn>,
+}
+
+impl<E: Endpoint> Endpoint for TransformBodyEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if let Some(transform) = self.request.as_ref().and_then(|f| f(&req)) {
+            let body = req.take_body();
+            req.headers_mut().remove(header::CONTENT_LENGTH);
+            req.set_body(transform_body(body, transform));
+        }
+
+        let mut resp = self.inner.call(req).await?.into_response();
+        if let Some(transform) = self.response.as_ref().and_then(|f| f(&resp)) {
+            let body = resp.take_body();
+            resp.headers_mut().remove(header::CONTENT_LENGTH);
+            resp.set_body(transform_body(body, transform));
+        }
+        Ok(resp)
+    }
+}
+
+fn transform_body(body: Body, transform: Box<dyn BodyTransform>) -> Body {
+    Body(BoxBody::new(TransformedBody {
+        inner: body.0,
+        transform,
+        state: State::Streaming,
+    }))
+}
+
+enum State {
+    Streaming,
+    Trailers(HeaderMap),
+    Done,
+}
+
+struct TransformedBody {
+    inner: BoxBody,
+    transform: Box<dyn BodyTransform>,
+    state: State,
+}
+
+impl TransformedBody {
+    fn finish(&mut self, trailers: Option<HeaderMap>) -> IoResult<Bytes> {
+        let data = self.transform.finish()?;
+        if let Some(trailers) = self.transform.transform_trailers(trailers)? {
+            self.state = State::Trailers(trailers);
+        }
+        Ok(data)
+    }
+}
+
+impl hyper::body::Body for TransformedBody {
+    type Data = Bytes;
+    type Error = IoError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+
+        loop {
+            match mem::replace(&mut this.state, State::Done) {
+                State::Done => return Poll::Ready(None),
+                State::Trailers(trailers) => {
+                    return Poll::Ready(Some(Ok(Frame::trailers(trailers))))
+                }
+                State::Streaming => {}
+            }
+
+            let res = match Pin::new(&mut this.inner).poll_frame(cx) {
+                Poll::Ready(Some(Ok(frame))) => match frame.into_data() {
+                    Ok(data) => {
+                        this.state = State::Streaming;
+                        this.transform.transform_data(data)
+                    }
+                    Err(frame) => match frame.into_trailers() {
+                        Ok(trailers) => this.finish(Some(trailers)),
+                        Err(_) => {
+                            this.state = State::Streaming;
+                            continue;
+                        }
+                    },
+                },
+                Poll::Ready(Some(Err(err))) => Err(err),
+                Poll::Ready(None) => this.finish(None),
+                Poll::Pending => {
+                    this.state = State::Streaming;
+                    return Poll::Pending;
+                }
+            };
+
+            match res {
+                Ok(data) if data.is_empty() => continue,
+                Ok(data) => return Poll::Ready(Some(Ok(Frame::data(data)))),
+                Err(err) => {
+                    this.state = State::Done;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    /// Converts the data to uppercase, and appends the number of the bytes.
+    #[derive(Default)]
+    struct Uppercase(usize);
+
+    impl BodyTransform for Uppercase {
+        fn transform_data(&mut self, data: Bytes) -> IoResult<Bytes> {
+            self.0 += data.len();
+            Ok(data.to_ascii_uppercase().into())
+        }
+
+        fn finish(&mut self) -> IoResult<Bytes> {
+            Ok(format!(" ({})", self.0).into())
+        }
+
+        fn transform_trailers(
+            &mut self,
+            trailers: Option<HeaderMap>,
+        ) -> IoResult<Option<HeaderMap>> {
+            let mut trailers = trailers.unwrap_or_default();
+            trailers.insert("x-length", self.0.into());
+            Ok(Some(trailers))
+        }
+    }
+
+    #[handler(internal)]
+    fn echo(body: String) -> String {
+        body
+    }
+
+    #[tokio::test]
+    async fn transform_request_and_response() {
+        let cli = TestClient::new(
+            echo.with(
+                TransformBody::new()
+                    .request(|req| {
+                        req.headers()
+                            .contains_key("x-transform")
+                            .then(Uppercase::default)
+                    })
+                    .response(|_| Some(Uppercase::default())),
+            ),
+        );
+
+        let resp = cli.post("/").body("abc").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist(header::CONTENT_LENGTH);
+        resp.assert_text("ABC (3)").await;
+
+        cli.post("/")
+            .header("x-transform", "1")
+            .body("abc")
+            .send()
+            .await
+            .assert_text("ABC (3) (7)")
+            .await;
+    }
+
+    #[tokio::test]
+    async fn transform_trailers() {
+        let body = Body::from_bytes_stream(futures_util::stream::iter([
+            Ok::<_, IoError>(Bytes::from_static(b"ab")),
+            Ok(Bytes::from_static(b"c")),
+        ]));
+        let mut body = transform_body(body, Box::new(Uppercase::default())).0;
+
+        let mut data = Vec::new();
+        let mut trailers = None;
+        while let Some(frame) = body.frame().await {
+            match frame.unwrap().into_data() {
+                Ok(chunk) => data.extend_from_slice(&chunk),
+                Err(frame) => trailers = frame.into_trailers().ok(),
+            }
+        }
+        assert_eq!(data, b"ABC (3)");
+        assert_eq!(trailers.unwrap()["x-length"], "3");
+    }
+}
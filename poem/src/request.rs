@@ -1,15 +1,17 @@
 use std::{
+    error::Error as StdError,
     fmt::{self, Debug, Formatter},
     future::Future,
-    io::Error,
+    io::{Error, IoSlice},
     pin::Pin,
     str::FromStr,
+    sync::Arc,
     task::{Context, Poll},
 };
 
+use bytes::Bytes;
 use http::uri::Scheme;
 use http_body_util::BodyExt;
-use hyper::{body::Incoming, rt::Write as _};
 use parking_lot::Mutex;
 use serde::de::DeserializeOwned;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
@@ -35,7 +37,7 @@ pub(crate) struct RequestState {
     pub(crate) local_addr: LocalAddr,
     pub(crate) remote_addr: RemoteAddr,
     pub(crate) scheme: Scheme,
-    pub(crate) original_uri: Uri,
+    pub(crate) original_uri: Arc<Uri>,
     pub(crate) match_params: PathParams,
     #[cfg(feature = "cookie")]
     pub(crate) cookie_jar: Option<CookieJar>,
@@ -57,6 +59,10 @@ impl Default for RequestState {
     }
 }
 
+fn unwrap_or_clone<T: Clone>(value: Arc<T>) -> T {
+    Arc::try_unwrap(value).unwrap_or_else(|value| (*value).clone())
+}
+
 /// Component parts of an HTTP Request.
 ///
 /// The HTTP request head consists of a method, uri, version, and a set of
@@ -87,12 +93,15 @@ impl Debug for RequestParts {
 }
 
 /// Represents an HTTP request.
+///
+/// The URI and headers are shared by the copies created by
+/// [`Request::clone_without_body`], and are copied on the first modification.
 #[derive(Default)]
 pub struct Request {
     method: Method,
-    uri: Uri,
+    uri: Arc<Uri>,
     version: Version,
-    headers: HeaderMap,
+    headers: Arc<HeaderMap>,
     extensions: Extensions,
     body: Body,
     state: RequestState,
@@ -109,35 +118,34 @@ impl Debug for Request {
     }
 }
 
-impl From<(http::Request<Incoming>, LocalAddr, RemoteAddr, Scheme)> for Request {
+impl<B> From<(http::Request<B>, LocalAddr, RemoteAddr, Scheme)> for Request
+where
+    B: hyper::body::Body<Data = Bytes> + Send + Sync + 'static,
+    B::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
     fn from(
-        (req, local_addr, remote_addr, scheme): (
-            http::Request<Incoming>,
-            LocalAddr,
-            RemoteAddr,
-            Scheme,
-        ),
+        (req, local_addr, remote_addr, scheme): (http::Request<B>, LocalAddr, RemoteAddr, Scheme),
     ) -> Self {
         let (mut parts, body) = req.into_parts();
-        let on_upgrade = Mutex::new(
-            parts
-                .extensions
-                .remove::<hyper::upgrade::OnUpgrade>()
-                .map(|fut| OnUpgrade { fut }),
-        );
+        let on_upgrade = Mutex::new(parts.extensions.remove::<hyper::upgrade::OnUpgrade>().map(
+            |fut| OnUpgrade {
+                inner: OnUpgradeInner::Hyper(fut),
+            },
+        ));
 
+        let uri = Arc::new(parts.uri);
         Self {
             method: parts.method,
-            uri: parts.uri.clone(),
+            uri: uri.clone(),
             version: parts.version,
-            headers: parts.headers,
+            headers: Arc::new(parts.headers),
             extensions: parts.extensions,
             body: Body(body.map_err(Error::other).boxed()),
             state: RequestState {
                 local_addr,
                 remote_addr,
                 scheme,
-                original_uri: parts.uri,
+                original_uri: uri,
                 match_params: Default::default(),
                 #[cfg(feature = "cookie")]
                 cookie_jar: None,
@@ -151,11 +159,11 @@ impl From<Request> for hyper::Request<BoxBody> {
     fn from(req: Request) -> Self {
         let mut hyper_req = http::Request::builder()
             .method(req.method)
-            .uri(req.uri)
+            .uri(unwrap_or_clone(req.uri))
             .version(req.version)
             .body(req.body.0)
             .unwrap();
-        *hyper_req.headers_mut() = req.headers;
+        *hyper_req.headers_mut() = unwrap_or_clone(req.headers);
         *hyper_req.extensions_mut() = req.extensions;
         hyper_req
     }
@@ -166,9 +174,9 @@ impl Request {
     pub fn from_parts(parts: RequestParts, body: Body) -> Self {
         Self {
             method: parts.method,
-            uri: parts.uri,
+            uri: Arc::new(parts.uri),
             version: parts.version,
-            headers: parts.headers,
+            headers: Arc::new(parts.headers),
             extensions: parts.extensions,
             body,
             state: parts.state,
@@ -207,7 +215,7 @@ impl Request {
     /// Returns a mutable reference to the associated URI.
     #[inline]
     pub fn uri_mut(&mut self) -> &mut Uri {
-        &mut self.uri
+        Arc::make_mut(&mut self.uri)
     }
 
     /// Returns a reference to the associated original URI.
@@ -243,7 +251,7 @@ impl Request {
     /// Returns a mutable reference to the associated header map.
     #[inline]
     pub fn headers_mut(&mut self) -> &mut HeaderMap {
-        &mut self.headers
+        Arc::make_mut(&mut self.headers)
     }
 
     /// Returns the string value of the specified header.
@@ -260,7 +268,7 @@ impl Request {
         self.state
             .match_params
             .iter()
-            .find(|(key, _)| key == name)
+            .find(|(key, _)| &**key == name)
             .map(|(_, value)| value.as_str())
     }
 
@@ -340,7 +348,7 @@ impl Request {
     /// # });
     /// ```
     pub fn params<T: DeserializeOwned>(&self) -> Result<T, ParseQueryError> {
-        Ok(serde_urlencoded::from_str(
+        Ok(crate::urlencoded::from_str(
             self.uri().query().unwrap_or_default(),
         )?)
     }
@@ -352,6 +360,16 @@ impl Request {
             .and_then(|value| value.to_str().ok())
     }
 
+    /// Returns `true` if the client sent `Expect: 100-continue`, and waits
+    /// for the `100 Continue` response before sending the body.
+    pub fn expects_continue(&self) -> bool {
+        self.version >= Version::HTTP_11
+            && self
+                .headers()
+                .get(header::EXPECT)
+                .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"100-continue"))
+    }
+
     /// Returns a reference to the associated extensions.
     #[inline]
     pub fn extensions(&self) -> &Extensions {
@@ -389,6 +407,19 @@ impl Request {
         &self.state.local_addr
     }
 
+    /// Returns the [`TlsInfo`](crate::web::TlsInfo) of the connection, or
+    /// `None` if the request was not received over TLS.
+    #[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls")))
+    )]
+    pub fn tls_info(&self) -> Option<&crate::web::TlsInfo> {
+        self.extensions()
+            .get::<crate::web::TlsInfoSlot>()
+            .and_then(|slot| slot.get())
+    }
+
     /// Returns a reference to the [`CookieJar`]
     #[cfg(feature = "cookie")]
     #[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
@@ -426,6 +457,32 @@ impl Request {
         &mut self.state
     }
 
+    /// Returns a copy of this request with an empty body, for example to
+    /// retry or mirror the request.
+    ///
+    /// The URI and headers are shared with this request without being copied,
+    /// and the upgrade of the connection is not available in the copy.
+    pub fn clone_without_body(&self) -> Request {
+        Request {
+            method: self.method.clone(),
+            uri: self.uri.clone(),
+            version: self.version,
+            headers: self.headers.clone(),
+            extensions: self.extensions.clone(),
+            body: Body::empty(),
+            state: RequestState {
+                local_addr: self.state.local_addr.clone(),
+                remote_addr: self.state.remote_addr.clone(),
+                scheme: self.state.scheme.clone(),
+                original_uri: self.state.original_uri.clone(),
+                match_params: self.state.match_params.clone(),
+                #[cfg(feature = "cookie")]
+                cookie_jar: self.state.cookie_jar.clone(),
+                on_upgrade: Default::default(),
+            },
+        }
+    }
+
     /// Returns the parameters used by the extractor.
     pub fn split(mut self) -> (Request, RequestBody) {
         let body = self.take_body();
@@ -437,9 +494,9 @@ impl Request {
         (
             RequestParts {
                 method: self.method,
-                uri: self.uri,
+                uri: unwrap_or_clone(self.uri),
                 version: self.version,
-                headers: self.headers,
+                headers: unwrap_or_clone(self.headers),
                 extensions: self.extensions,
                 state: self.state,
             },
@@ -457,10 +514,24 @@ impl Request {
     }
 }
 
-pin_project_lite::pin_project! {
-    /// A future for a possible HTTP upgrade.
-    pub struct OnUpgrade {
-        #[pin] fut: hyper::upgrade::OnUpgrade,
+/// A future for a possible HTTP upgrade.
+pub struct OnUpgrade {
+    inner: OnUpgradeInner,
+}
+
+enum OnUpgradeInner {
+    Hyper(hyper::upgrade::OnUpgrade),
+    Io(tokio::sync::oneshot::Receiver<BoxUpgradedIo>),
+}
+
+impl OnUpgrade {
+    /// Creates an upgrade which resolves to the IO object sent through the
+    /// channel, used to drive upgrades without a real connection.
+    #[cfg_attr(not(feature = "test"), allow(dead_code))]
+    pub(crate) fn from_io(rx: tokio::sync::oneshot::Receiver<BoxUpgradedIo>) -> Self {
+        Self {
+            inner: OnUpgradeInner::Io(rx),
+        }
     }
 }
 
@@ -468,51 +539,108 @@ impl Future for OnUpgrade {
     type Output = Result<Upgraded, UpgradeError>;
 
     #[inline]
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.project();
-        this.fut
-            .poll(cx)
-            .map_ok(|stream| Upgraded { stream })
-            .map_err(|err| UpgradeError::Other(err.to_string()))
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.inner {
+            OnUpgradeInner::Hyper(fut) => Pin::new(fut)
+                .poll(cx)
+                .map_ok(|stream| Upgraded {
+                    stream: UpgradedStream::Hyper(hyper_util::rt::TokioIo::new(stream)),
+                })
+                .map_err(|err| UpgradeError::Other(err.to_string())),
+            OnUpgradeInner::Io(rx) => Pin::new(rx)
+                .poll(cx)
+                .map_ok(Upgraded::from_io)
+                .map_err(|err| UpgradeError::Other(err.to_string())),
+        }
     }
 }
 
-pin_project_lite::pin_project! {
-    /// An upgraded HTTP connection.
-    pub struct Upgraded {
-        #[pin] stream: hyper::upgrade::Upgraded,
+pub(crate) type BoxUpgradedIo = Box<dyn UpgradedIo>;
+
+pub(crate) trait UpgradedIo: AsyncRead + AsyncWrite + Send + Unpin + 'static {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin + 'static> UpgradedIo for T {}
+
+enum UpgradedStream {
+    Hyper(hyper_util::rt::TokioIo<hyper::upgrade::Upgraded>),
+    Io(BoxUpgradedIo),
+}
+
+/// An upgraded HTTP connection.
+pub struct Upgraded {
+    stream: UpgradedStream,
+}
+
+impl Upgraded {
+    #[inline]
+    pub(crate) fn from_io(io: BoxUpgradedIo) -> Self {
+        Self {
+            stream: UpgradedStream::Io(io),
+        }
     }
 }
 
 impl AsyncRead for Upgraded {
     #[inline]
     fn poll_read(
-        self: Pin<&mut Self>,
+        mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &mut ReadBuf<'_>,
     ) -> Poll<std::io::Result<()>> {
-        Pin::new(&mut hyper_util::rt::TokioIo::new(self.project().stream)).poll_read(cx, buf)
+        match &mut self.stream {
+            UpgradedStream::Hyper(stream) => Pin::new(stream).poll_read(cx, buf),
+            UpgradedStream::Io(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
     }
 }
 
 impl AsyncWrite for Upgraded {
     #[inline]
     fn poll_write(
-        self: Pin<&mut Self>,
+        mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<Result<usize, Error>> {
-        self.project().stream.poll_write(cx, buf)
+        match &mut self.stream {
+            UpgradedStream::Hyper(stream) => Pin::new(stream).poll_write(cx, buf),
+            UpgradedStream::Io(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    #[inline]
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        match &mut self.stream {
+            UpgradedStream::Hyper(stream) => Pin::new(stream).poll_flush(cx),
+            UpgradedStream::Io(stream) => Pin::new(stream).poll_flush(cx),
+        }
     }
 
     #[inline]
-    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
-        self.project().stream.poll_flush(cx)
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        match &mut self.stream {
+            UpgradedStream::Hyper(stream) => Pin::new(stream).poll_shutdown(cx),
+            UpgradedStream::Io(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, Error>> {
+        match &mut self.stream {
+            UpgradedStream::Hyper(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+            UpgradedStream::Io(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+        }
     }
 
     #[inline]
-    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
-        self.project().stream.poll_shutdown(cx)
+    fn is_write_vectored(&self) -> bool {
+        match &self.stream {
+            UpgradedStream::Hyper(stream) => stream.is_write_vectored(),
+            UpgradedStream::Io(stream) => stream.is_write_vectored(),
+        }
     }
 }
 
@@ -607,14 +735,18 @@ impl RequestBuilder {
     /// Consumes this builder, using the provided body to return a constructed
     /// [Request].
     pub fn body(self, body: impl Into<Body>) -> Request {
+        let uri = Arc::new(self.uri);
         Request {
             method: self.method,
-            uri: self.uri,
+            uri: uri.clone(),
             version: self.version,
-            headers: self.headers,
+            headers: Arc::new(self.headers),
             extensions: self.extensions,
             body: body.into(),
-            state: Default::default(),
+            state: RequestState {
+                original_uri: uri,
+                ..Default::default()
+            },
         }
     }
 
@@ -624,3 +756,38 @@ impl RequestBuilder {
         self.body(Body::empty())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clone_without_body() {
+        let mut req = Request::builder()
+            .uri_str("/a?b=1")
+            .header("x-custom", "1")
+            .extension(10i32)
+            .body("abc");
+        req.state_mut()
+            .match_params
+            .push(("id".into(), "1".to_string()));
+
+        let mut cloned = req.clone_without_body();
+        assert!(Arc::ptr_eq(&req.uri, &cloned.uri));
+        assert!(Arc::ptr_eq(&req.headers, &cloned.headers));
+        assert_eq!(cloned.header("x-custom"), Some("1"));
+        assert_eq!(cloned.data::<i32>(), Some(&10));
+        assert_eq!(cloned.raw_path_param("id"), Some("1"));
+        assert!(cloned.take_body().is_empty());
+
+        cloned
+            .headers_mut()
+            .insert("x-custom", HeaderValue::from_static("2"));
+        *cloned.uri_mut() = Uri::from_static("/c");
+        assert_eq!(req.header("x-custom"), Some("1"));
+        assert_eq!(req.uri().path(), "/a");
+        assert_eq!(cloned.header("x-custom"), Some("2"));
+        assert_eq!(cloned.uri().path(), "/c");
+        assert_eq!(cloned.original_uri(), req.original_uri());
+    }
+}
@@ -36,6 +36,68 @@ fn find_slash(path: &[u8]) -> Option<usize> {
     None
 }
 
+/// The regular expressions of the built-in constraints in the `<name:type>`
+/// segments.
+const BUILTIN_CONSTRAINTS: &[(&str, &str)] = &[
+    ("int", "[+-]?[0-9]+"),
+    ("uint", "[0-9]+"),
+    (
+        "uuid",
+        "[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}",
+    ),
+    ("alpha", "[a-zA-Z]+"),
+    ("alnum", "[a-zA-Z0-9]+"),
+    ("slug", "[a-zA-Z0-9_-]+"),
+];
+
+fn builtin_constraint(constraint: &[u8]) -> Option<&'static [u8]> {
+    BUILTIN_CONSTRAINTS
+        .iter()
+        .find(|(name, _)| name.as_bytes() == constraint)
+        .map(|(_, re)| re.as_bytes())
+}
+
+/// Returns the regular expression of a constraint in the `<name:constraint>`
+/// segments, which is either the name of a built-in constraint or a regular
+/// expression.
+pub(crate) fn constraint_regex(constraint: &str) -> &str {
+    BUILTIN_CONSTRAINTS
+        .iter()
+        .find(|(name, _)| *name == constraint)
+        .map(|(_, re)| *re)
+        .unwrap_or(constraint)
+}
+
+/// Splits `name:constraint` if the name is an identifier.
+fn split_constraint(re: &[u8]) -> Option<(&[u8], &[u8])> {
+    let pos = re.iter().position(|c| *c == b':')?;
+    let (name, constraint) = (&re[..pos], &re[pos + 1..]);
+    let is_ident = name
+        .first()
+        .is_some_and(|c| c.is_ascii_alphabetic() || *c == b'_')
+        && name.iter().all(|c| c.is_ascii_alphanumeric() || *c == b'_');
+    (is_ident && !constraint.is_empty()).then_some((name, constraint))
+}
+
+/// Returns a key of the path which is the same for the paths matching the same
+/// requests, the names of the parameters are ignored.
+pub(crate) fn conflict_key(path: &str) -> Option<String> {
+    let mut key = String::new();
+    for segment in parse_path_segments(path.as_bytes()).ok()? {
+        match segment {
+            RawSegment::Static(value) => key.push_str(std::str::from_utf8(value).ok()?),
+            RawSegment::Param(_) => key.push_str("\0:"),
+            RawSegment::CatchAll(_) => key.push_str("\0*"),
+            RawSegment::Regex(_, re) => {
+                key.push_str("\0<");
+                key.push_str(std::str::from_utf8(re).ok()?);
+                key.push('>');
+            }
+        }
+    }
+    Some(key)
+}
+
 fn parse_path_segments(path: &[u8]) -> Result<Vec<RawSegment<'_>>, ()> {
     fn parse_static<'a>(path: &'a [u8], i: &mut usize) -> &'a [u8] {
         let s = *i;
@@ -111,7 +173,13 @@ fn parse_path_segments(path: &[u8]) -> Result<Vec<RawSegment<'_>>, ()> {
             b'<' => {
                 i += 1;
                 let re = parse_re(path, &mut i)?;
-                segments.push(RawSegment::Regex(None, re));
+                match split_constraint(re) {
+                    Some((name, constraint)) => segments.push(RawSegment::Regex(
+                        Some(name),
+                        builtin_constraint(constraint).unwrap_or(constraint),
+                    )),
+                    None => segments.push(RawSegment::Regex(None, re)),
+                }
             }
             _ => {
                 let s = parse_static(path, &mut i);
@@ -142,7 +210,7 @@ impl PathRegex {
         let re_str = std::str::from_utf8(re_bytes).ok()?;
         Some(PathRegex {
             re_str: re_str.to_string(),
-            re: Regex::new(re_str).ok()?,
+            re: Regex::new(&format!("^(?:{re_str})")).ok()?,
         })
     }
 }
@@ -165,6 +233,9 @@ impl Eq for PathRegex {}
 pub(crate) struct NodeData<T> {
     pub(crate) data: T,
     pub(crate) pattern: Arc<str>,
+    /// The names of the parameters in the pattern, in the same order as the
+    /// captured values.
+    param_names: Box<[Arc<str>]>,
 }
 
 impl<T> NodeData<T> {
@@ -173,9 +244,22 @@ impl<T> NodeData<T> {
     where
         P: Into<Arc<str>>,
     {
+        let pattern = pattern.into();
+        let param_names = parse_path_segments(pattern.as_bytes())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|segment| match segment {
+                RawSegment::Param(name)
+                | RawSegment::CatchAll(Some(name))
+                | RawSegment::Regex(Some(name), _) => std::str::from_utf8(name).ok(),
+                _ => None,
+            })
+            .map(Arc::from)
+            .collect();
         Self {
             data,
-            pattern: pattern.into(),
+            pattern,
+            param_names,
         }
     }
 }
@@ -237,7 +321,7 @@ impl<T> Node<T> {
                         re: None,
                         param_children: ::std::mem::take(&mut child.param_children),
                         catch_all_child: child.catch_all_child.take(),
-                        regex_children: vec![],
+                        regex_children: ::std::mem::take(&mut child.regex_children),
                         data: child.data.take(),
                     };
 
@@ -327,6 +411,9 @@ impl<T> Node<T> {
     }
 
     fn insert_catch_all_child(&mut self, name: Option<&[u8]>, data: NodeData<T>) -> bool {
+        if self.catch_all_child.is_some() {
+            return false;
+        }
         self.catch_all_child
             .replace(Box::new(Node {
                 node_type: NodeType::CatchAll,
@@ -375,15 +462,17 @@ impl<T> Node<T> {
         child.insert_child(segments, data)
     }
 
-    fn matches<'a: 'b, 'b>(
+    /// Matches the path, the values of the named parameters are captured as
+    /// `(offset from the end of the path, length)`, so there is no allocation.
+    fn matches<'a>(
         &'a self,
-        path: &'b [u8],
-        params: &mut SmallVec<[(&'b [u8], &'b [u8]); 8]>,
+        path: &[u8],
+        captures: &mut SmallVec<[(usize, usize); 8]>,
     ) -> Option<&'a NodeData<T>> {
         if path.is_empty() {
             return if let Some(catch_all_child) = &self.catch_all_child {
                 if !catch_all_child.name.is_empty() {
-                    params.push((&catch_all_child.name, path));
+                    captures.push((0, 0));
                 }
                 catch_all_child.data.as_ref()
             } else {
@@ -391,47 +480,45 @@ impl<T> Node<T> {
             };
         }
 
-        let num_params = params.len();
+        let num_captures = captures.len();
 
         if let Some(pos) = self.find_static_child(path[0]) {
             let child = &self.children[pos];
             if let Some(tail_path) = path.strip_prefix(child.name.as_slice()) {
-                if let Some(data) = child.matches(tail_path, params) {
+                if let Some(data) = child.matches(tail_path, captures) {
                     return Some(data);
                 }
             }
         }
 
         for regex_children in &self.regex_children {
-            params.truncate(num_params);
+            captures.truncate(num_captures);
 
-            if let Some(captures) = regex_children.re.as_ref().unwrap().re.captures(path) {
-                let value = &path[..captures[0].len()];
+            if let Some(m) = regex_children.re.as_ref().unwrap().re.find(path) {
                 if !regex_children.name.is_empty() {
-                    params.push((&regex_children.name, value));
+                    captures.push((path.len(), m.end()));
                 }
-                if let Some(data) = regex_children.matches(&path[value.len()..], params) {
+                if let Some(data) = regex_children.matches(&path[m.end()..], captures) {
                     return Some(data);
                 }
             }
         }
 
         for param_children in &self.param_children {
-            params.truncate(num_params);
+            captures.truncate(num_captures);
 
-            let value = match find_slash(path) {
-                Some(pos) => &path[..pos],
-                None => path,
-            };
-            params.push((&param_children.name, value));
-            if let Some(data) = param_children.matches(&path[value.len()..], params) {
+            let len = find_slash(path).unwrap_or(path.len());
+            captures.push((path.len(), len));
+            if let Some(data) = param_children.matches(&path[len..], captures) {
                 return Some(data);
             }
         }
 
-        params.truncate(num_params);
+        captures.truncate(num_captures);
         if let Some(catch_all_child) = &self.catch_all_child {
-            params.push((&catch_all_child.name, path));
+            if !catch_all_child.name.is_empty() {
+                captures.push((path.len(), path.len()));
+            }
             return catch_all_child.data.as_ref();
         }
 
@@ -439,12 +526,34 @@ impl<T> Node<T> {
     }
 }
 
-pub(crate) type PathParams = Vec<(String, String)>;
+pub(crate) type PathParams = Vec<(Arc<str>, String)>;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug)]
 pub(crate) struct Matches<'a, T> {
-    pub(crate) params: PathParams,
     pub(crate) data: &'a NodeData<T>,
+    captures: SmallVec<[(usize, usize); 8]>,
+}
+
+impl<T> Matches<'_, T> {
+    /// Returns the percent-decoded path parameters, `path` must be the matched
+    /// path.
+    ///
+    /// The parameters which are not valid UTF-8 are ignored.
+    pub(crate) fn params<'a>(
+        &'a self,
+        path: &'a str,
+    ) -> impl Iterator<Item = (Arc<str>, String)> + 'a {
+        self.data
+            .param_names
+            .iter()
+            .zip(&self.captures)
+            .filter_map(move |(name, (offset, len))| {
+                let start = path.len() - offset;
+                let value = &path.as_bytes()[start..start + len];
+                let value = crate::urlencoded::percent_decode(value).ok()?;
+                Some((name.clone(), value.into_owned()))
+            })
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -505,31 +614,14 @@ impl<T> RadixTree<T> {
         }
     }
 
-    pub(crate) fn matches(&self, path: &str) -> Option<Matches<T>> {
+    pub(crate) fn matches(&self, path: &str) -> Option<Matches<'_, T>> {
         if path.is_empty() {
             return None;
         }
 
-        let mut params = SmallVec::default();
-
-        match self.root.matches(path.as_bytes(), &mut params) {
-            Some(data) => {
-                let mut params2 = Vec::with_capacity(params.len());
-                for (name, value) in params {
-                    if let (Ok(name), Ok(value)) = (
-                        std::str::from_utf8(name),
-                        percent_encoding::percent_decode(value).decode_utf8(),
-                    ) {
-                        params2.push((name.to_string(), value.into_owned()));
-                    }
-                }
-                Some(Matches {
-                    params: params2,
-                    data,
-                })
-            }
-            None => None,
-        }
+        let mut captures = SmallVec::new();
+        let data = self.root.matches(path.as_bytes(), &mut captures)?;
+        Some(Matches { data, captures })
     }
 }
 
@@ -604,6 +696,20 @@ mod tests {
         );
 
         assert_eq!(parse_path_segments(b"/a/:"), Err(()));
+
+        assert_eq!(
+            parse_path_segments(b"/users/<id:uint>/<path:.*\\.png>/<(?:a|b)>/<:c>"),
+            Ok(vec![
+                RawSegment::Static(b"/users/"),
+                RawSegment::Regex(Some(b"id"), b"[0-9]+"),
+                RawSegment::Static(b"/"),
+                RawSegment::Regex(Some(b"path"), b".*\\.png"),
+                RawSegment::Static(b"/"),
+                RawSegment::Regex(None, b"(?:a|b)"),
+                RawSegment::Static(b"/"),
+                RawSegment::Regex(None, b":c"),
+            ])
+        );
     }
 
     #[test]
@@ -1061,7 +1167,7 @@ mod tests {
     fn create_url_params<I, K, V>(values: I) -> PathParams
     where
         I: IntoIterator<Item = (K, V)>,
-        K: Into<String>,
+        K: Into<Arc<str>>,
         V: Into<String>,
     {
         values
@@ -1167,17 +1273,72 @@ mod tests {
             ),
         ];
 
-        for (path, mut res) in matches {
+        for (path, res) in matches {
             assert_eq!(
-                tree.matches(path),
-                res.as_mut().map(|(params, data)| Matches {
-                    params: std::mem::take(params),
-                    data
-                })
+                tree.matches(path)
+                    .map(|matches| (matches.params(path).collect::<PathParams>(), matches.data)),
+                res.as_ref().map(|(params, data)| (params.clone(), data))
             );
         }
     }
 
+    #[test]
+    fn test_match_constraints() {
+        let mut tree = RadixTree::default();
+        tree.add("/users/<id:uuid>", 1).unwrap();
+        tree.add("/users/<id:int>/posts", 2).unwrap();
+        tree.add("/files/<path:.*\\.png>", 3).unwrap();
+        tree.add("/a<\\d+>", 4).unwrap();
+        tree.add("/users/<name:alpha>/a", 6).unwrap();
+
+        let matches = tree
+            .matches("/users/67e55044-10b1-426f-9247-bb680e5fe0c8")
+            .unwrap();
+        assert_eq!(matches.data.data, 1);
+        assert_eq!(
+            matches
+                .params("/users/67e55044-10b1-426f-9247-bb680e5fe0c8")
+                .collect::<PathParams>(),
+            create_url_params(vec![("id", "67e55044-10b1-426f-9247-bb680e5fe0c8")])
+        );
+        assert!(tree.matches("/users/abc").is_none());
+        assert_eq!(tree.matches("/users/abc/a").unwrap().data.data, 6);
+
+        let matches = tree.matches("/users/-12/posts").unwrap();
+        assert_eq!(matches.data.data, 2);
+        assert_eq!(
+            matches.params("/users/-12/posts").collect::<PathParams>(),
+            create_url_params(vec![("id", "-12")])
+        );
+
+        let matches = tree.matches("/files/a/b.png").unwrap();
+        assert_eq!(matches.data.data, 3);
+        assert_eq!(
+            matches.params("/files/a/b.png").collect::<PathParams>(),
+            create_url_params(vec![("path", "a/b.png")])
+        );
+        assert!(tree.matches("/files/a/b.jpg").is_none());
+
+        assert_eq!(tree.matches("/a12").unwrap().data.data, 4);
+        assert!(tree.matches("/ab12").is_none());
+
+        assert!(matches!(
+            tree.add("/b/<id:(>", 5),
+            Err(RouteError::InvalidRegex { .. })
+        ));
+    }
+
+    #[test]
+    fn test_conflict_key() {
+        assert_eq!(conflict_key("/a/:id"), conflict_key("/a/:name"));
+        assert_eq!(conflict_key("/a/*p"), conflict_key("/a/*"));
+        assert_eq!(conflict_key("/a/:id<\\d+>"), conflict_key("/a/<\\d+>"));
+        assert_eq!(conflict_key("/a/<id:uint>"), conflict_key("/a/:n<[0-9]+>"));
+        assert_ne!(conflict_key("/a/:id"), conflict_key("/a/*p"));
+        assert_ne!(conflict_key("/:id/a"), conflict_key("/:id/b"));
+        assert_eq!(conflict_key("/a/:"), None);
+    }
+
     #[test]
     fn test_match_priority() {
         let mut tree = RadixTree::default();
@@ -1225,15 +1386,17 @@ mod tests {
 
         let matches = tree.matches("/abc/a").unwrap();
         assert_eq!(matches.data.data, 1);
-        assert_eq!(matches.params.len(), 1);
-        assert_eq!(matches.params[0].0, "id1");
-        assert_eq!(matches.params[0].1, "abc");
+        let params: PathParams = matches.params("/abc/a").collect();
+        assert_eq!(params.len(), 1);
+        assert_eq!(&*params[0].0, "id1");
+        assert_eq!(params[0].1, "abc");
 
         let matches = tree.matches("/def/b").unwrap();
         assert_eq!(matches.data.data, 2);
-        assert_eq!(matches.params.len(), 1);
-        assert_eq!(matches.params[0].0, "id2");
-        assert_eq!(matches.params[0].1, "def");
+        let params: PathParams = matches.params("/def/b").collect();
+        assert_eq!(params.len(), 1);
+        assert_eq!(&*params[0].0, "id2");
+        assert_eq!(params[0].1, "def");
     }
 
     #[test]
@@ -1243,12 +1406,14 @@ mod tests {
 
         let matches = tree.matches("/a/abc").unwrap();
         assert_eq!(matches.data.data, 1);
-        assert_eq!(matches.params[0].0, "id");
-        assert_eq!(matches.params[0].1, "abc");
+        let params: PathParams = matches.params("/a/abc").collect();
+        assert_eq!(&*params[0].0, "id");
+        assert_eq!(params[0].1, "abc");
 
         let matches = tree.matches("/a/%E4%BD%A0%E5%A5%BD").unwrap();
         assert_eq!(matches.data.data, 1);
-        assert_eq!(matches.params[0].0, "id");
-        assert_eq!(matches.params[0].1, "你好");
+        let params: PathParams = matches.params("/a/%E4%BD%A0%E5%A5%BD").collect();
+        assert_eq!(&*params[0].0, "id");
+        assert_eq!(params[0].1, "你好");
     }
 }
@@ -5,6 +5,8 @@ mod router;
 mod router_domain;
 mod router_method;
 mod router_scheme;
+#[cfg(feature = "route-split")]
+mod router_split;
 
 pub(crate) use internal::radix_tree::PathParams;
 pub use router::{PathPattern, Route};
@@ -16,14 +18,19 @@ pub use router_method::{
 };
 #[allow(unreachable_pub)]
 pub use router_scheme::RouteScheme;
+#[cfg(feature = "route-split")]
+#[allow(unreachable_pub)]
+pub use router_split::{RouteSplit, SplitVariant};
 
 use crate::error::RouteError;
 
+#[track_caller]
 pub(crate) fn check_result<T>(res: Result<T, RouteError>) -> T {
     match res {
         Ok(value) => value,
         Err(RouteError::InvalidPath(path)) => panic!("invalid path: {path}"),
         Err(RouteError::Duplicate(path)) => panic!("duplicate path: {path}"),
+        Err(err @ RouteError::Conflict { .. }) => panic!("{err}"),
         Err(RouteError::InvalidRegex { path, regex }) => {
             panic!("invalid regex in path: {path} `{regex}`")
         }
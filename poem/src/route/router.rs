@@ -1,18 +1,37 @@
-use std::{str::FromStr, sync::Arc};
+use std::{any::Any, collections::HashMap, panic::Location, str::FromStr, sync::Arc};
 
 use regex::Regex;
 
 use crate::{
-    endpoint::BoxEndpoint,
+    endpoint::{box_endpoint, BoxEndpoint},
     error::{NotFoundError, ParsePathError, RouteError},
     http::{uri::PathAndQuery, Uri},
-    route::{check_result, internal::radix_tree::RadixTree},
+    route::{
+        check_result,
+        internal::{self, radix_tree::RadixTree},
+    },
+    web::MountPrefix,
     Endpoint, EndpointExt, IntoEndpoint, IntoResponse, Request, Response, Result,
 };
 
+#[cfg(feature = "audit-log")]
+use crate::middleware::AuditContext;
+#[cfg(feature = "route-split")]
+use crate::route::RouteSplit;
+
 #[derive(Debug, Clone, Copy)]
 struct PathPrefix(usize);
 
+/// The handler for the unmatched paths of the nearest [`Route`], which is
+/// passed to the nested routes.
+#[derive(Clone)]
+struct DefaultHandler(Arc<BoxEndpoint<'static>>);
+
+/// The handler for the unmatched methods of the nearest [`Route`], which is
+/// used by the [`RouteMethod`](crate::RouteMethod)s.
+#[derive(Clone)]
+pub(crate) struct MethodNotAllowedHandler(pub(crate) Arc<BoxEndpoint<'static>>);
+
 /// Routing object
 ///
 /// You can match the full path or wildcard path, and use the
@@ -22,6 +41,10 @@ struct PathPrefix(usize);
 ///
 /// - [`NotFoundError`]
 ///
+/// The unmatched paths can be handled by [`Route::default_handler`], and the
+/// unmatched methods by [`Route::method_not_allowed_handler`], the handlers
+/// also apply to the nested routes unless they have their own handlers.
+///
 /// # Example
 ///
 /// ```
@@ -57,7 +80,10 @@ struct PathPrefix(usize);
 ///     // match regex
 ///     .at("/d/<\\d+>", get(a))
 ///     // capture with regex
-///     .at("/e/:name<\\d+>", get(a));
+///     .at("/e/:name<\\d+>", get(a))
+///     // capture with constraint
+///     .at("/f/<id:uuid>", get(a))
+///     .at("/g/<path:.*\\.png>", get(a));
 ///
 /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
 /// let cli = TestClient::new(app);
@@ -76,9 +102,38 @@ struct PathPrefix(usize);
 ///
 /// // /e/:name<\\d>
 /// cli.get("/e/123").send().await.assert_status_is_ok();
+///
+/// // /f/<id:uuid>
+/// cli.get("/f/67e55044-10b1-426f-9247-bb680e5fe0c8")
+///     .send()
+///     .await
+///     .assert_status_is_ok();
+/// cli.get("/f/123")
+///     .send()
+///     .await
+///     .assert_status(StatusCode::NOT_FOUND);
+///
+/// // /g/<path:.*\\.png>
+/// cli.get("/g/d/e.png").send().await.assert_status_is_ok();
 /// # });
 /// ```
 ///
+/// # Constraints
+///
+/// The `<name:constraint>` segment captures a parameter matching the
+/// constraint, which is a regular expression, or one of the following built-in
+/// constraints. The requests not matching the constraints are not routed to
+/// the endpoint.
+///
+/// | Constraint | Matches                           |
+/// |------------|-----------------------------------|
+/// | `int`      | The signed integers               |
+/// | `uint`     | The unsigned integers             |
+/// | `uuid`     | The UUIDs                         |
+/// | `alpha`    | The ASCII letters                 |
+/// | `alnum`    | The ASCII letters and digits      |
+/// | `slug`     | The ASCII letters, digits, `-` and `_` |
+///
 /// # Nested
 ///
 /// ```
@@ -131,6 +186,10 @@ struct PathPrefix(usize);
 #[derive(Default)]
 pub struct Route {
     tree: RadixTree<BoxEndpoint<'static>>,
+    paths: Vec<String>,
+    registered: HashMap<String, (String, &'static Location<'static>)>,
+    default_handler: Option<DefaultHandler>,
+    method_not_allowed_handler: Option<MethodNotAllowedHandler>,
 }
 
 impl Route {
@@ -143,8 +202,9 @@ impl Route {
     ///
     /// # Panics
     ///
-    /// Panic when there are duplicates in the routing table.
+    /// Panic when there are duplicates or conflicts in the routing table.
     #[must_use]
+    #[track_caller]
     pub fn at<E>(self, path: impl AsRef<str>, ep: E) -> Self
     where
         E: IntoEndpoint,
@@ -154,22 +214,53 @@ impl Route {
     }
 
     /// Attempts to add an [Endpoint] to the specified path.
+    #[track_caller]
     pub fn try_at<E>(mut self, path: impl AsRef<str>, ep: E) -> Result<Self, RouteError>
     where
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        self.tree
-            .add(&normalize_path(path.as_ref()), ep.map_to_response().boxed())?;
+        let path = normalize_path(path.as_ref());
+        self.register(std::slice::from_ref(&path), Location::caller())?;
+        self.tree.add(&path, box_endpoint(ep.into_endpoint()))?;
+        self.paths.push(path);
         Ok(self)
     }
 
+    /// Checks that the paths do not match the same requests as the registered
+    /// paths, and registers them.
+    fn register(
+        &mut self,
+        paths: &[String],
+        location: &'static Location<'static>,
+    ) -> Result<(), RouteError> {
+        let keys = paths
+            .iter()
+            .filter_map(|path| Some((internal::radix_tree::conflict_key(path)?, path)))
+            .collect::<Vec<_>>();
+        for (key, path) in &keys {
+            if let Some((existing, existing_location)) = self.registered.get(key) {
+                return Err(RouteError::Conflict {
+                    path: path.to_string(),
+                    location: location.to_string(),
+                    existing: existing.clone(),
+                    existing_location: existing_location.to_string(),
+                });
+            }
+        }
+        for (key, path) in keys {
+            self.registered.insert(key, (path.clone(), location));
+        }
+        Ok(())
+    }
+
     /// Add an [Endpoint] to the `/` path.
     ///
     /// Same as `self.at("/", ep)`.
     ///
     /// See [`Route::at`] for more details.
     #[must_use]
+    #[track_caller]
     pub fn just_at<E>(self, ep: E) -> Self
     where
         E: IntoEndpoint,
@@ -182,8 +273,9 @@ impl Route {
     ///
     /// # Panics
     ///
-    /// Panic when there are duplicates in the routing table.
+    /// Panic when there are duplicates or conflicts in the routing table.
     #[must_use]
+    #[track_caller]
     pub fn nest<E>(self, path: impl AsRef<str>, ep: E) -> Self
     where
         E: IntoEndpoint,
@@ -194,6 +286,7 @@ impl Route {
 
     /// Attempts to nest a `Endpoint` to the specified path and strip the
     /// prefix.
+    #[track_caller]
     pub fn try_nest<E>(self, path: impl AsRef<str>, ep: E) -> Result<Self, RouteError>
     where
         E: IntoEndpoint,
@@ -206,8 +299,9 @@ impl Route {
     ///
     /// # Panics
     ///
-    /// Panic when there are duplicates in the routing table.
+    /// Panic when there are duplicates or conflicts in the routing table.
     #[must_use]
+    #[track_caller]
     pub fn nest_no_strip<E>(self, path: impl AsRef<str>, ep: E) -> Self
     where
         E: IntoEndpoint,
@@ -218,6 +312,7 @@ impl Route {
 
     /// Attempts to nest a `Endpoint` to the specified path, but do not strip
     /// the prefix.
+    #[track_caller]
     pub fn try_nest_no_strip<E>(self, path: impl AsRef<str>, ep: E) -> Result<Self, RouteError>
     where
         E: IntoEndpoint,
@@ -226,16 +321,41 @@ impl Route {
         self.internal_nest(&normalize_path(path.as_ref()), ep, false)
     }
 
+    #[track_caller]
     fn internal_nest<E>(mut self, path: &str, ep: E, strip: bool) -> Result<Self, RouteError>
     where
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        let ep = Arc::new(ep.into_endpoint());
+        let ep = ep.into_endpoint();
         let mut path = path.to_string();
         if !path.ends_with('/') {
             path.push('/');
         }
+        let prefix = &path[..path.len() - 1];
+        let nested_paths = (&ep as &dyn Any)
+            .downcast_ref::<Route>()
+            .map(|route| {
+                route
+                    .paths
+                    .iter()
+                    .map(|nested_path| match strip {
+                        true if nested_path == "/" && !prefix.is_empty() => prefix.to_string(),
+                        true => format!("{prefix}{nested_path}"),
+                        false => nested_path.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let mut registered_paths = vec![format!("{path}*"), prefix.to_string()];
+        registered_paths.extend(
+            nested_paths
+                .iter()
+                .filter(|nested_path| *nested_path != prefix)
+                .cloned(),
+        );
+        self.register(&registered_paths, Location::caller())?;
+        let ep = Arc::new(ep);
 
         struct Nest<T> {
             inner: T,
@@ -250,7 +370,7 @@ impl Route {
             async fn call(&self, mut req: Request) -> Result<Self::Output> {
                 if !self.root {
                     let params = &mut req.state_mut().match_params;
-                    if params.last().map(|(name, _)| name.as_str()) != Some("--poem-rest") {
+                    if params.last().map(|(name, _)| &**name) != Some("--poem-rest") {
                         return Err(ParsePathError.into());
                     }
 
@@ -260,8 +380,16 @@ impl Route {
                 let new_uri = {
                     let uri = std::mem::take(req.uri_mut());
                     let mut uri_parts = uri.into_parts();
-                    let path =
-                        &uri_parts.path_and_query.as_ref().unwrap().as_str()[self.prefix_len..];
+                    let path_and_query = uri_parts.path_and_query.as_ref().unwrap().as_str();
+                    if self.prefix_len > 0 {
+                        let prefix = &path_and_query[..self.prefix_len];
+                        let mount_prefix = match req.data::<MountPrefix>() {
+                            Some(parent) => format!("{}{prefix}", parent.0),
+                            None => prefix.to_string(),
+                        };
+                        req.set_data(MountPrefix(mount_prefix.into()));
+                    }
+                    let path = &path_and_query[self.prefix_len..];
                     uri_parts.path_and_query = Some(if !path.starts_with('/') {
                         PathAndQuery::from_str(&format!("/{path}")).unwrap()
                     } else {
@@ -320,8 +448,150 @@ impl Route {
             .boxed(),
         )?;
 
+        self.paths.extend(nested_paths);
         Ok(self)
     }
+
+    /// Returns the paths of the endpoints added to this route, including the
+    /// paths of the nested routes.
+    ///
+    /// The endpoints nested with [`Route::nest`] or [`Route::nest_no_strip`]
+    /// are introspected only if they are [`Route`]s, the paths of the other
+    /// endpoints are unknown.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{handler, Route};
+    ///
+    /// #[handler]
+    /// fn index() {}
+    ///
+    /// let app = Route::new()
+    ///     .at("/", index)
+    ///     .at("/users/:id", index)
+    ///     .nest("/docs", Route::new().at("/", index).at("/intro", index));
+    /// assert_eq!(
+    ///     app.paths().collect::<Vec<_>>(),
+    ///     ["/", "/users/:id", "/docs", "/docs/intro"]
+    /// );
+    /// ```
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.paths.iter().map(String::as_str)
+    }
+
+    /// Sets the endpoint to handle the requests whose paths are not matched,
+    /// instead of responding `404 Not Found`.
+    ///
+    /// The status code of the response is not changed, so it's usually set by
+    /// the endpoint.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     get, handler,
+    ///     http::{Method, StatusCode},
+    ///     test::TestClient,
+    ///     web::Html,
+    ///     IntoResponse, Route,
+    /// };
+    ///
+    /// #[handler]
+    /// fn index() {}
+    ///
+    /// #[handler]
+    /// fn not_found() -> impl IntoResponse {
+    ///     Html("<h1>Nothing here</h1>").with_status(StatusCode::NOT_FOUND)
+    /// }
+    ///
+    /// #[handler]
+    /// fn method_not_allowed(method: Method) -> impl IntoResponse {
+    ///     format!("{method} is not allowed").with_status(StatusCode::METHOD_NOT_ALLOWED)
+    /// }
+    ///
+    /// let app = Route::new()
+    ///     .nest("/api", Route::new().at("/users", get(index)))
+    ///     .default_handler(not_found)
+    ///     .method_not_allowed_handler(method_not_allowed);
+    /// let cli = TestClient::new(app);
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let resp = cli.get("/api/missing").send().await;
+    /// resp.assert_status(StatusCode::NOT_FOUND);
+    /// resp.assert_text("<h1>Nothing here</h1>").await;
+    ///
+    /// let resp = cli.delete("/api/users").send().await;
+    /// resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+    /// resp.assert_header("allow", "GET, HEAD, OPTIONS");
+    /// resp.assert_text("DELETE is not allowed").await;
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn default_handler<E>(self, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        Self {
+            default_handler: Some(DefaultHandler(Arc::new(box_endpoint(ep.into_endpoint())))),
+            ..self
+        }
+    }
+
+    /// Sets the endpoint to handle the requests whose paths are matched by
+    /// the [`RouteMethod`](crate::RouteMethod)s but the methods are not,
+    /// instead of responding `405 Method Not Allowed`.
+    ///
+    /// The `Allow` header is added to the response if it is not set by the
+    /// endpoint. See [`Route::default_handler`] for an example.
+    #[must_use]
+    pub fn method_not_allowed_handler<E>(self, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        Self {
+            method_not_allowed_handler: Some(MethodNotAllowedHandler(Arc::new(box_endpoint(
+                ep.into_endpoint(),
+            )))),
+            ..self
+        }
+    }
+
+    /// Returns the regular expression of a constraint in the
+    /// `<name:constraint>` segments, which is the constraint itself if it is
+    /// not a built-in constraint.
+    ///
+    /// See [`Route`](#constraints) for the built-in constraints.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::Route;
+    ///
+    /// assert_eq!(Route::constraint_regex("uint"), "[0-9]+");
+    /// assert_eq!(Route::constraint_regex(".*\\.png"), ".*\\.png");
+    /// ```
+    pub fn constraint_regex(constraint: &str) -> &str {
+        internal::radix_tree::constraint_regex(constraint)
+    }
+
+    /// Create a [`RouteSplit`] object which routes `weight` percent of the
+    /// requests to `b`, and the others to `a`.
+    ///
+    /// See [`RouteSplit`] for more details.
+    #[cfg(feature = "route-split")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "route-split")))]
+    pub fn split<A, B>(a: A, b: B, weight: f64) -> RouteSplit
+    where
+        A: IntoEndpoint,
+        A::Endpoint: 'static,
+        B: IntoEndpoint,
+        B::Endpoint: 'static,
+    {
+        RouteSplit::new(a, b, weight)
+    }
 }
 
 /// Container that can be used to obtain path pattern from the request.
@@ -332,9 +602,24 @@ impl Endpoint for Route {
     type Output = Response;
 
     async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if let Some(handler) = &self.default_handler {
+            req.set_data(handler.clone());
+        }
+        if let Some(handler) = &self.method_not_allowed_handler {
+            req.set_data(handler.clone());
+        }
+
         match self.tree.matches(req.uri().path()) {
             Some(matches) => {
-                req.state_mut().match_params.extend(matches.params);
+                let mut match_params = std::mem::take(&mut req.state_mut().match_params);
+                #[cfg(feature = "audit-log")]
+                let num_params = match_params.len();
+                match_params.extend(matches.params(req.uri().path()));
+                #[cfg(feature = "audit-log")]
+                if let Some(context) = req.extensions().get::<AuditContext>() {
+                    context.add_path_params(&match_params[num_params..]);
+                }
+                req.state_mut().match_params = match_params;
 
                 let pattern = match matches.data.pattern.strip_suffix("/*--poem-rest") {
                     Some(pattern) => pattern.into(),
@@ -349,6 +634,10 @@ impl Endpoint for Route {
                     (None, None) => PathPattern(pattern),
                     (Some(parent), None) => PathPattern(format!("{}{}", parent.0, pattern).into()),
                 };
+                #[cfg(feature = "server")]
+                if let Some(request) = req.extensions().get::<crate::server::InFlightRequest>() {
+                    request.set_route(pattern.0.clone());
+                }
                 req.set_data(pattern.clone());
 
                 let result = matches.data.data.call(req).await;
@@ -370,7 +659,10 @@ impl Endpoint for Route {
                     }
                 }
             }
-            None => Err(NotFoundError.into()),
+            None => match req.data::<DefaultHandler>().cloned() {
+                Some(DefaultHandler(handler)) => handler.call(req).await,
+                None => Err(NotFoundError.into()),
+            },
         }
     }
 }
@@ -392,6 +684,17 @@ mod tests {
     use super::*;
     use crate::{endpoint::make_sync, handler, test::TestClient, Error};
 
+    #[test]
+    fn paths() {
+        let ep = || make_sync(|_| ());
+        let app = Route::new()
+            .at("a", ep())
+            .nest("/", Route::new().at("/", ep()).at("/b", ep()))
+            .nest_no_strip("/c", Route::new().at("/c/d", ep()))
+            .nest("/e", ep());
+        assert_eq!(app.paths().collect::<Vec<_>>(), ["/a", "/", "/b", "/c/d"]);
+    }
+
     #[test]
     fn test_normalize_path() {
         assert_eq!(normalize_path("/a/b/c"), "/a/b/c");
@@ -784,4 +1087,108 @@ mod tests {
             "/nest_no_strip1/nest_no_strip2/:id"
         );
     }
+
+    #[tokio::test]
+    async fn default_handlers() {
+        let app = Route::new()
+            .at("/a", crate::get(make_sync(|_| "a")))
+            .nest("/b", Route::new().at("/c", crate::post(make_sync(|_| "c"))))
+            .nest(
+                "/d",
+                Route::new()
+                    .at("/e", crate::get(make_sync(|_| "e")))
+                    .default_handler(make_sync(|_| "inner not found")),
+            )
+            .default_handler(make_sync(|req| {
+                format!("{} not found", req.uri().path()).with_status(StatusCode::NOT_FOUND)
+            }))
+            .method_not_allowed_handler(make_sync(|_| {
+                StatusCode::METHOD_NOT_ALLOWED.with_body("not allowed")
+            }));
+        let cli = TestClient::new(app);
+
+        cli.get("/a").send().await.assert_text("a").await;
+
+        let resp = cli.get("/x").send().await;
+        resp.assert_status(StatusCode::NOT_FOUND);
+        resp.assert_text("/x not found").await;
+
+        let resp = cli.get("/b/x").send().await;
+        resp.assert_status(StatusCode::NOT_FOUND);
+        resp.assert_text("/x not found").await;
+
+        let resp = cli.get("/d/x").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("inner not found").await;
+
+        let resp = cli.get("/b/c").send().await;
+        resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+        resp.assert_header("allow", "POST, OPTIONS");
+        resp.assert_text("not allowed").await;
+
+        let resp = TestClient::new(Route::new()).get("/").send().await;
+        resp.assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn conflicts() {
+        let ep = || make_sync(|_| ());
+        let conflict = |res: Result<Route, RouteError>| match res {
+            Err(RouteError::Conflict {
+                path,
+                location,
+                existing,
+                existing_location,
+            }) => {
+                assert!(location.starts_with(file!()));
+                assert!(existing_location.starts_with(file!()));
+                assert_ne!(location, existing_location);
+                (path, existing)
+            }
+            _ => panic!("expected a conflict"),
+        };
+
+        let route = Route::new().at("/a/:id", ep()).at("/b", ep());
+        assert_eq!(
+            conflict(route.try_at("/a/:name", ep())),
+            ("/a/:name".to_string(), "/a/:id".to_string())
+        );
+
+        let route = Route::new().at("/a/<id:uint>", ep());
+        assert_eq!(
+            conflict(route.try_at("/a/:id<[0-9]+>", ep())),
+            ("/a/:id<[0-9]+>".to_string(), "/a/<id:uint>".to_string())
+        );
+
+        let route = Route::new().nest("/api", Route::new().at("/users", ep()));
+        assert_eq!(
+            conflict(route.try_at("/api/users", ep())),
+            ("/api/users".to_string(), "/api/users".to_string())
+        );
+
+        let route = Route::new().nest("/api", ep());
+        assert_eq!(
+            conflict(route.try_at("/api/*path", ep())),
+            ("/api/*path".to_string(), "/api/*".to_string())
+        );
+
+        let route = Route::new().at("/api/users", ep());
+        assert_eq!(
+            conflict(route.try_nest("/api", Route::new().at("/users", ep()))),
+            ("/api/users".to_string(), "/api/users".to_string())
+        );
+
+        assert!(Route::new()
+            .at("/:id1/a", ep())
+            .try_at("/:id2/b", ep())
+            .is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "path `/a/:name` at")]
+    fn conflict_panic() {
+        let _ = Route::new()
+            .at("/a/:id", make_sync(|_| ()))
+            .at("/a/:name", make_sync(|_| ()));
+    }
 }
@@ -1,9 +1,9 @@
 use crate::{
-    endpoint::BoxEndpoint,
+    endpoint::{box_endpoint, BoxEndpoint},
     error::{NotFoundError, RouteError},
     http::header,
     route::{check_result, internal::trie::Trie},
-    Endpoint, EndpointExt, IntoEndpoint, Request, Response, Result,
+    Endpoint, IntoEndpoint, Request, Response, Result,
 };
 
 /// Routing object for `HOST` header
@@ -79,10 +79,8 @@ impl RouteDomain {
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        self.tree.add(
-            pattern.as_ref(),
-            ep.into_endpoint().map_to_response().boxed(),
-        )?;
+        self.tree
+            .add(pattern.as_ref(), box_endpoint(ep.into_endpoint()))?;
         Ok(self)
     }
 }
@@ -3,12 +3,26 @@ use std::future::Future;
 use futures_util::{future::Either, FutureExt};
 
 use crate::{
-    endpoint::BoxEndpoint, error::MethodNotAllowedError, http::Method, Endpoint, EndpointExt,
-    IntoEndpoint, Request, Response, Result,
+    endpoint::{box_endpoint, BoxEndpoint},
+    error::MethodNotAllowedError,
+    http::{header, HeaderValue, Method, StatusCode},
+    route::router::MethodNotAllowedHandler,
+    Endpoint, IntoEndpoint, Request, Response, Result,
 };
 
 /// Routing object for HTTP methods
 ///
+/// The `HEAD` requests are handled by the `GET` endpoint if there is no `HEAD`
+/// endpoint, the body is discarded and the `Content-Length` header is kept,
+/// which can be disabled with [`RouteMethod::auto_head`]. The `OPTIONS`
+/// requests are answered with `204 No Content` and the `Allow` header if there
+/// is no `OPTIONS` endpoint, which can be disabled
+/// with [`RouteMethod::auto_options`]. The requests with the other methods are
+/// rejected with `405 Method Not Allowed` and the `Allow` header, or handled
+/// by the handler set with
+/// [`Route::method_not_allowed_handler`](crate::Route::method_not_allowed_handler).
+///
+///
 /// # Errors
 ///
 /// - [`MethodNotAllowedError`]
@@ -51,17 +65,76 @@ use crate::{
 ///     .get_response(Request::builder().method(Method::PUT).finish())
 ///     .await;
 /// assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+/// assert_eq!(resp.headers()["allow"], "GET, POST, HEAD, OPTIONS");
 /// # });
 /// ```
-#[derive(Default)]
 pub struct RouteMethod {
     methods: Vec<(Method, BoxEndpoint<'static>)>,
+    auto_head: bool,
+    auto_options: bool,
+}
+
+impl Default for RouteMethod {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RouteMethod {
     /// Create a `RouteMethod` object.
     pub fn new() -> Self {
-        Default::default()
+        Self {
+            methods: Vec::new(),
+            auto_head: true,
+            auto_options: true,
+        }
+    }
+
+    /// Sets whether to handle the `HEAD` requests with the `GET` endpoint if
+    /// there is no `HEAD` endpoint, otherwise they are rejected with `405
+    /// Method Not Allowed`.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn auto_head(self, enable: bool) -> Self {
+        Self {
+            auto_head: enable,
+            ..self
+        }
+    }
+
+    /// Sets whether to answer the `OPTIONS` requests with the allowed methods
+    /// if there is no `OPTIONS` endpoint, otherwise they are rejected with
+    /// `405 Method Not Allowed`.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn auto_options(self, enable: bool) -> Self {
+        Self {
+            auto_options: enable,
+            ..self
+        }
+    }
+
+    fn has_method(&self, method: &Method) -> bool {
+        self.methods.iter().any(|(m, _)| m == method)
+    }
+
+    /// Returns the value of the `Allow` header.
+    fn allow(&self) -> HeaderValue {
+        let mut methods: Vec<&str> = Vec::new();
+        for (method, _) in &self.methods {
+            if !methods.contains(&method.as_str()) {
+                methods.push(method.as_str());
+            }
+        }
+        if self.auto_head && self.has_method(&Method::GET) && !self.has_method(&Method::HEAD) {
+            methods.push(Method::HEAD.as_str());
+        }
+        if self.auto_options && !self.has_method(&Method::OPTIONS) {
+            methods.push(Method::OPTIONS.as_str());
+        }
+        HeaderValue::from_str(&methods.join(", ")).expect("valid header value")
     }
 
     /// Sets the endpoint for the specified `method`.
@@ -72,7 +145,7 @@ impl RouteMethod {
         E::Endpoint: 'static,
     {
         self.methods
-            .push((method, ep.into_endpoint().map_to_response().boxed()));
+            .push((method, box_endpoint(ep.into_endpoint())));
         self
     }
 
@@ -179,18 +252,46 @@ impl Endpoint for RouteMethod {
         {
             Some(ep) => Either::Left(ep.call(req)),
             None => {
-                if req.method() == Method::HEAD {
+                if req.method() == Method::HEAD && self.auto_head && self.has_method(&Method::GET) {
                     Either::Right(Either::Left(
                         async move {
                             req.set_method(Method::GET);
                             let mut resp = self.call(req).await?;
-                            resp.set_body(());
+                            let body = resp.take_body();
+                            if !resp.headers().contains_key(header::CONTENT_LENGTH) {
+                                if let Some(len) = hyper::body::Body::size_hint(&body.0).exact() {
+                                    resp.headers_mut()
+                                        .insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+                                }
+                            }
                             Ok(resp)
                         }
                         .boxed(),
                     ))
+                } else if req.method() == Method::OPTIONS && self.auto_options {
+                    let resp = Response::builder()
+                        .status(StatusCode::NO_CONTENT)
+                        .header(header::ALLOW, self.allow())
+                        .finish();
+                    Either::Right(Either::Right(Either::Left(async { Ok(resp) })))
                 } else {
-                    Either::Right(Either::Right(async { Err(MethodNotAllowedError.into()) }))
+                    let allow = self.allow();
+                    match req.data::<MethodNotAllowedHandler>().cloned() {
+                        Some(MethodNotAllowedHandler(handler)) => Either::Right(Either::Left(
+                            async move {
+                                let mut resp = handler.call(req).await?;
+                                if !resp.headers().contains_key(header::ALLOW) {
+                                    resp.headers_mut().insert(header::ALLOW, allow);
+                                }
+                                Ok(resp)
+                            }
+                            .boxed(),
+                        )),
+                        None => {
+                            let err = MethodNotAllowedError.with_allow(allow);
+                            Either::Right(Either::Right(Either::Right(async { Err(err) })))
+                        }
+                    }
                 }
             }
         }
@@ -281,12 +382,48 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{handler, http::StatusCode, test::TestClient};
+    use crate::{handler, http::StatusCode, test::TestClient, Body};
 
     #[tokio::test]
     async fn method_not_allowed() {
         let resp = TestClient::new(RouteMethod::new()).get("/").send().await;
         resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+
+        #[handler(internal)]
+        fn index() {}
+
+        let route = RouteMethod::new().get(index).post(index);
+        let resp = route
+            .call(Request::builder().method(Method::PUT).finish())
+            .await
+            .unwrap_err();
+        assert!(resp.is::<MethodNotAllowedError>());
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let resp = resp.into_response();
+        assert_eq!(resp.headers()["allow"], "GET, POST, HEAD, OPTIONS");
+    }
+
+    #[tokio::test]
+    async fn auto_options() {
+        #[handler(internal)]
+        fn index() -> &'static str {
+            "hello"
+        }
+
+        let cli = TestClient::new(RouteMethod::new().get(index).head(index).put(index));
+        let resp = cli.options("/").send().await;
+        resp.assert_status(StatusCode::NO_CONTENT);
+        resp.assert_header("allow", "GET, HEAD, PUT, OPTIONS");
+
+        let cli = TestClient::new(RouteMethod::new().options(index));
+        let resp = cli.options("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("hello").await;
+
+        let cli = TestClient::new(RouteMethod::new().post(index).auto_options(false));
+        let resp = cli.options("/").send().await;
+        resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+        resp.assert_header("allow", "POST");
     }
 
     #[tokio::test]
@@ -350,6 +487,37 @@ mod tests {
         let route = RouteMethod::new().get(index);
         let resp = TestClient::new(route).head("/").send().await;
         resp.assert_status_is_ok();
+        resp.assert_header("content-length", "5");
+        resp.assert_text("").await;
+
+        let route = RouteMethod::new().get(index).auto_head(false);
+        let resp = TestClient::new(route).head("/").send().await;
+        resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+        resp.assert_header("allow", "GET, OPTIONS");
+
+        let resp = TestClient::new(RouteMethod::new().post(index))
+            .head("/")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn head_method_streaming_body() {
+        #[handler(internal)]
+        fn index() -> Body {
+            Body::from_bytes_stream(futures_util::stream::iter(vec![
+                Ok::<_, std::io::Error>(bytes::Bytes::from_static(b"hello")),
+                Ok(bytes::Bytes::from_static(b" world")),
+            ]))
+        }
+
+        let resp = TestClient::new(RouteMethod::new().get(index))
+            .head("/")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist("content-length");
         resp.assert_text("").await;
     }
 }
@@ -1,8 +1,9 @@
 use http::uri::Scheme;
 
 use crate::{
-    endpoint::BoxEndpoint, error::NotFoundError, Endpoint, EndpointExt, IntoEndpoint, Request,
-    Response,
+    endpoint::{box_endpoint, BoxEndpoint},
+    error::NotFoundError,
+    Endpoint, IntoEndpoint, Request, Response,
 };
 
 /// Routing object for request scheme
@@ -30,7 +31,7 @@ impl RouteScheme {
         E::Endpoint: 'static,
     {
         self.schemes
-            .push((Scheme::HTTPS, ep.into_endpoint().map_to_response().boxed()));
+            .push((Scheme::HTTPS, box_endpoint(ep.into_endpoint())));
         self
     }
 
@@ -42,7 +43,7 @@ impl RouteScheme {
         E::Endpoint: 'static,
     {
         self.schemes
-            .push((Scheme::HTTP, ep.into_endpoint().map_to_response().boxed()));
+            .push((Scheme::HTTP, box_endpoint(ep.into_endpoint())));
         self
     }
 
@@ -54,7 +55,7 @@ impl RouteScheme {
         E::Endpoint: 'static,
     {
         self.schemes
-            .push((scheme, ep.into_endpoint().map_to_response().boxed()));
+            .push((scheme, box_endpoint(ep.into_endpoint())));
         self
     }
 
@@ -67,7 +68,7 @@ impl RouteScheme {
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        self.fallback = Some(ep.into_endpoint().map_to_response().boxed());
+        self.fallback = Some(box_endpoint(ep.into_endpoint()));
         self
     }
 }
@@ -0,0 +1,280 @@
+use crate::{
+    endpoint::{box_endpoint, BoxEndpoint},
+    hash::fnv1a,
+    http::{header, HeaderName, HeaderValue},
+    Endpoint, IntoEndpoint, Request, Response, Result,
+};
+
+/// The variant of the [`RouteSplit`] which handles the request.
+///
+/// It is inserted into the extensions of the request, so the handlers and the
+/// middlewares can tell which variant is served, for example to label the
+/// metrics.
+#[cfg_attr(docsrs, doc(cfg(feature = "route-split")))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SplitVariant {
+    /// The first endpoint, usually the current version.
+    A,
+    /// The second endpoint, usually the canary version.
+    B,
+}
+
+impl SplitVariant {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SplitVariant::A => "a",
+            SplitVariant::B => "b",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "a" => Some(SplitVariant::A),
+            "b" => Some(SplitVariant::B),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Sticky {
+    Cookie(String),
+    Header(HeaderName),
+}
+
+/// Routing object which splits the requests between two endpoints by weight,
+/// for the blue/green deployments and the canary releases of the handlers.
+///
+/// The `weight` is the percentage of the requests routed to the second
+/// endpoint, from `0.0` to `100.0`.
+///
+/// By default each request is assigned randomly. With
+/// [`RouteSplit::sticky_cookie`], the assignment is stored in a cookie, so
+/// the following requests of the client are routed to the same endpoint.
+/// With [`RouteSplit::sticky_header`], the assignment is derived from the
+/// value of a header, for example a user id, so increasing the weight only
+/// moves the clients from the first endpoint to the second one.
+///
+/// The [`SplitVariant`] of the assignment is inserted into the extensions of
+/// the request.
+///
+/// # Example
+///
+/// ```
+/// use poem::{get, handler, test::TestClient, Route};
+///
+/// #[handler]
+/// fn v1() -> &'static str {
+///     "v1"
+/// }
+///
+/// #[handler]
+/// fn v2() -> &'static str {
+///     "v2"
+/// }
+///
+/// let app = Route::new().at("/", get(Route::split(v1, v2, 10.0).sticky_cookie("canary")));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").header("cookie", "canary=b").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("v2").await;
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "route-split")))]
+pub struct RouteSplit {
+    a: BoxEndpoint<'static>,
+    b: BoxEndpoint<'static>,
+    weight: f64,
+    sticky: Option<Sticky>,
+}
+
+impl RouteSplit {
+    /// Create a `RouteSplit` object which routes `weight` percent of the
+    /// requests to `b`, and the others to `a`.
+    pub fn new<A, B>(a: A, b: B, weight: f64) -> Self
+    where
+        A: IntoEndpoint,
+        A::Endpoint: 'static,
+        B: IntoEndpoint,
+        B::Endpoint: 'static,
+    {
+        Self {
+            a: box_endpoint(a.into_endpoint()),
+            b: box_endpoint(b.into_endpoint()),
+            weight: weight.clamp(0.0, 100.0),
+            sticky: None,
+        }
+    }
+
+    /// Stores the assignment in the cookie with the specified name.
+    ///
+    /// The cookie is added to the response if the request does not have
+    /// one, and the clients already having the cookie are not reassigned
+    /// when the weight is changed.
+    #[must_use]
+    pub fn sticky_cookie(self, name: impl Into<String>) -> Self {
+        Self {
+            sticky: Some(Sticky::Cookie(name.into())),
+            ..self
+        }
+    }
+
+    /// Derives the assignment from the value of the specified header.
+    ///
+    /// The requests without the header are assigned randomly.
+    #[must_use]
+    pub fn sticky_header<K>(self, key: K) -> Self
+    where
+        K: TryInto<HeaderName>,
+    {
+        Self {
+            sticky: key.try_into().ok().map(Sticky::Header),
+            ..self
+        }
+    }
+
+    fn assign(&self, bucket: u64) -> SplitVariant {
+        if ((bucket % 10000) as f64) < self.weight * 100.0 {
+            SplitVariant::B
+        } else {
+            SplitVariant::A
+        }
+    }
+}
+
+impl Endpoint for RouteSplit {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let (variant, set_cookie) = match &self.sticky {
+            Some(Sticky::Cookie(name)) => {
+                match get_cookie(&req, name).and_then(SplitVariant::parse) {
+                    Some(variant) => (variant, None),
+                    None => (self.assign(rand::random::<u64>()), Some(name)),
+                }
+            }
+            Some(Sticky::Header(name)) => {
+                let bucket = match req.headers().get(name) {
+                    Some(value) => fnv1a(value.as_bytes()),
+                    None => rand::random::<u64>(),
+                };
+                (self.assign(bucket), None)
+            }
+            None => (self.assign(rand::random::<u64>()), None),
+        };
+
+        req.extensions_mut().insert(variant);
+        let ep = match variant {
+            SplitVariant::A => &self.a,
+            SplitVariant::B => &self.b,
+        };
+        let mut resp = ep.call(req).await?;
+
+        if let Some(name) = set_cookie {
+            if let Ok(value) = HeaderValue::from_str(&format!(
+                "{}={}; Path=/; HttpOnly; SameSite=Lax",
+                name,
+                variant.as_str()
+            )) {
+                resp.headers_mut().append(header::SET_COOKIE, value);
+            }
+        }
+        Ok(resp)
+    }
+}
+
+fn get_cookie<'a>(req: &'a Request, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{endpoint::make_sync, handler, test::TestClient, Route};
+
+    fn app(weight: f64) -> RouteSplit {
+        #[handler(internal)]
+        fn variant(req: &Request) -> &'static str {
+            req.extensions().get::<SplitVariant>().unwrap().as_str()
+        }
+
+        Route::split(variant, variant, weight)
+    }
+
+    #[tokio::test]
+    async fn split_by_weight() {
+        let cli = TestClient::new(app(0.0));
+        for _ in 0..10 {
+            cli.get("/").send().await.assert_text("a").await;
+        }
+
+        let cli = TestClient::new(app(100.0));
+        for _ in 0..10 {
+            cli.get("/").send().await.assert_text("b").await;
+        }
+
+        let ep = Route::split(make_sync(|_| "a"), make_sync(|_| "b"), 50.0);
+        let mut count = 0;
+        for _ in 0..200 {
+            let resp = ep.call(Request::default()).await.unwrap();
+            if resp.into_body().into_string().await.unwrap() == "b" {
+                count += 1;
+            }
+        }
+        assert!((50..150).contains(&count));
+    }
+
+    #[tokio::test]
+    async fn sticky_cookie() {
+        let cli = TestClient::new(app(100.0).sticky_cookie("canary"));
+
+        let resp = cli.get("/").send().await;
+        resp.assert_header("set-cookie", "canary=b; Path=/; HttpOnly; SameSite=Lax");
+        resp.assert_text("b").await;
+
+        let resp = cli
+            .get("/")
+            .header("cookie", "foo=1; canary=a")
+            .send()
+            .await;
+        resp.assert_header_is_not_exist("set-cookie");
+        resp.assert_text("a").await;
+
+        let resp = cli.get("/").header("cookie", "canary=c").send().await;
+        resp.assert_header("set-cookie", "canary=b; Path=/; HttpOnly; SameSite=Lax");
+        resp.assert_text("b").await;
+    }
+
+    #[tokio::test]
+    async fn sticky_header() {
+        async fn variants(weight: f64) -> Vec<String> {
+            let cli = TestClient::new(app(weight).sticky_header("x-user-id"));
+            let mut variants = Vec::new();
+            for id in 0..100 {
+                let resp = cli.get("/").header("x-user-id", id).send().await;
+                variants.push(resp.0.into_body().into_string().await.unwrap());
+            }
+            variants
+        }
+
+        let first = variants(30.0).await;
+        assert_eq!(first, variants(30.0).await);
+        assert!(first.contains(&"a".to_string()) && first.contains(&"b".to_string()));
+
+        let more = variants(60.0).await;
+        for (a, b) in first.iter().zip(&more) {
+            if a == "b" {
+                assert_eq!(b, "b");
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use futures_util::future::BoxFuture;
+use tokio::io::Result as IoResult;
+
+use crate::web::LocalAddr;
+
+/// The information of a running [`Server`](crate::Server), which is passed to
+/// the lifecycle hooks.
+///
+/// See [`Server::on_start`](crate::Server::on_start) and
+/// [`Server::on_stop`](crate::Server::on_stop).
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+#[derive(Debug, Clone)]
+pub struct ServerInfo {
+    pub(crate) name: Option<String>,
+    pub(crate) local_addrs: Vec<LocalAddr>,
+}
+
+impl ServerInfo {
+    /// Returns the name of the server.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the addresses the server is listening on.
+    pub fn local_addrs(&self) -> &[LocalAddr] {
+        &self.local_addrs
+    }
+}
+
+pub(crate) type Hook = Box<dyn FnOnce(ServerInfo) -> BoxFuture<'static, IoResult<()>> + Send>;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use crate::{
+        endpoint::make_sync,
+        listener::{Acceptor, Listener, TcpListener},
+        Server,
+    };
+
+    #[tokio::test]
+    async fn hooks() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor.local_addr().remove(0);
+
+        let server = {
+            let (e1, e2, e3) = (events.clone(), events.clone(), events.clone());
+            Server::new_with_acceptor(acceptor)
+                .name("test")
+                .on_start(move |info| async move {
+                    e1.lock().push(format!("start1 {:?}", info.name()));
+                    Ok(())
+                })
+                .on_start(move |info| async move {
+                    e2.lock().push(format!("start2 {}", info.local_addrs()[0]));
+                    Ok(())
+                })
+                .on_stop(move |_| async move {
+                    e3.lock().push("stop".to_string());
+                    Err(std::io::Error::other("failed"))
+                })
+        };
+        // the stop hooks are called when the shutdown is initiated, and their
+        // errors are only logged
+        server
+            .run_with_graceful_shutdown(make_sync(|_| ()), async {}, None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *events.lock(),
+            vec![
+                "start1 Some(\"test\")".to_string(),
+                format!("start2 {addr}"),
+                "stop".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn start_failed() {
+        let res = Server::new(TcpListener::bind("127.0.0.1:0"))
+            .on_start(|_| async { Err(std::io::Error::other("failed")) })
+            .run(make_sync(|_| ()))
+            .await;
+        assert_eq!(res.unwrap_err().to_string(), "failed");
+    }
+}
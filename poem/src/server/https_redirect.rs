@@ -0,0 +1,321 @@
+use futures_util::{future::BoxFuture, FutureExt, TryFutureExt};
+use http::{header, uri::Scheme, HeaderValue, StatusCode, Uri};
+use tokio::io::Result as IoResult;
+
+#[cfg(feature = "acme-base")]
+use crate::listener::acme::{AutoCert, Http01Endpoint};
+#[cfg(feature = "secure-headers")]
+use crate::middleware::Hsts;
+use crate::{
+    listener::{Acceptor, AcceptorExt, BoxAcceptor, Listener},
+    middleware::redirect_host,
+    web::Redirect,
+    Endpoint, IntoResponse, Request, Response, Result,
+};
+
+/// A plaintext companion listener which redirects all the requests to the
+/// HTTPS origin with `301 Moved Permanently`, preserving the path and the
+/// query.
+///
+/// It can also answer the ACME `HTTP-01` challenges, and add the
+/// `Strict-Transport-Security` header to the responses of the HTTPS
+/// connections of the server.
+///
+/// The requests received by this listener never reach the endpoint of the
+/// server.
+///
+/// # Example
+///
+/// ```no_run
+/// use poem::{listener::TcpListener, HttpsRedirect, Route, Server};
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// // the listener is usually wrapped with a TLS listener, such as
+/// // `TcpListener::bind("0.0.0.0:443").acme(auto_cert)`
+/// let listener = TcpListener::bind("0.0.0.0:8443");
+///
+/// Server::new(listener)
+///     .https_redirect(HttpsRedirect::new(TcpListener::bind("0.0.0.0:8080")).https_port(8443))
+///     .run(Route::new())
+///     .await
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+pub struct HttpsRedirect {
+    acceptor: BoxFuture<'static, IoResult<BoxAcceptor>>,
+    https_port: Option<u16>,
+    #[cfg(feature = "acme-base")]
+    http01: Option<Http01Endpoint>,
+    hsts: Option<HeaderValue>,
+}
+
+impl HttpsRedirect {
+    /// Create a `HttpsRedirect` with the plaintext listener.
+    pub fn new(listener: impl Listener + 'static) -> Self {
+        Self::with_acceptor(listener.into_acceptor().map_ok(AcceptorExt::boxed).boxed())
+    }
+
+    /// Create a `HttpsRedirect` with the plaintext acceptor.
+    pub fn new_with_acceptor(acceptor: impl Acceptor + 'static) -> Self {
+        Self::with_acceptor(futures_util::future::ready(Ok(acceptor.boxed())).boxed())
+    }
+
+    fn with_acceptor(acceptor: BoxFuture<'static, IoResult<BoxAcceptor>>) -> Self {
+        Self {
+            acceptor,
+            https_port: None,
+            #[cfg(feature = "acme-base")]
+            http01: None,
+            hsts: None,
+        }
+    }
+
+    /// Specify the port of the HTTPS origin.
+    ///
+    /// By default, the port of the `Host` header is removed.
+    #[must_use]
+    pub fn https_port(self, port: u16) -> Self {
+        Self {
+            https_port: Some(port),
+            ..self
+        }
+    }
+
+    /// Answers the `HTTP-01` challenges of the ACME configuration, which
+    /// are served under `/.well-known/acme-challenge/`.
+    ///
+    /// # Panics
+    ///
+    /// Panic if the challenge type of the configuration is not
+    /// [`ChallengeType::Http01`](crate::listener::acme::ChallengeType::Http01).
+    #[cfg(feature = "acme-base")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "acme-base")))]
+    #[must_use]
+    pub fn acme_challenge(self, auto_cert: &AutoCert) -> Self {
+        Self {
+            http01: Some(auto_cert.http_01_endpoint()),
+            ..self
+        }
+    }
+
+    /// Adds the `Strict-Transport-Security` header to the responses of the
+    /// HTTPS connections, unless the endpoint has set it.
+    #[cfg(feature = "secure-headers")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+    #[must_use]
+    pub fn hsts(self, hsts: Hsts) -> Self {
+        Self {
+            hsts: Some(HeaderValue::try_from(hsts.to_string()).expect("valid header value")),
+            ..self
+        }
+    }
+
+    /// Splits into the acceptor future, the redirect endpoint, and the value of
+    /// the `Strict-Transport-Security` header.
+    pub(crate) fn into_parts(
+        self,
+    ) -> (
+        BoxFuture<'static, IoResult<BoxAcceptor>>,
+        RedirectEndpoint,
+        Option<HeaderValue>,
+    ) {
+        let ep = RedirectEndpoint {
+            https_port: self.https_port,
+            #[cfg(feature = "acme-base")]
+            http01: self.http01,
+        };
+        (self.acceptor, ep, self.hsts)
+    }
+}
+
+pub(crate) struct RedirectEndpoint {
+    https_port: Option<u16>,
+    #[cfg(feature = "acme-base")]
+    http01: Option<Http01Endpoint>,
+}
+
+impl Endpoint for RedirectEndpoint {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        #[cfg(feature = "acme-base")]
+        if let Some(http01) = &self.http01 {
+            if req.uri().path().starts_with("/.well-known/acme-challenge/") {
+                return http01.call(req).await;
+            }
+        }
+
+        let host = req
+            .headers()
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .or_else(|| req.uri().authority().map(|authority| authority.as_str()));
+        let Some(host) = host else {
+            return Ok(StatusCode::BAD_REQUEST.into_response());
+        };
+
+        let mut builder = Uri::builder()
+            .scheme(Scheme::HTTPS)
+            .authority(&*redirect_host(host, self.https_port));
+        if let Some(path_and_query) = req.uri().path_and_query() {
+            builder = builder.path_and_query(path_and_query.clone());
+        }
+        match builder.build() {
+            Ok(uri) => Ok(Redirect::moved_permanent(uri).into_response()),
+            Err(_) => Ok(StatusCode::BAD_REQUEST.into_response()),
+        }
+    }
+}
+
+/// Adds the `Strict-Transport-Security` header to the responses of the HTTPS
+/// requests.
+pub(crate) struct HstsEndpoint<E> {
+    pub(crate) inner: E,
+    pub(crate) hsts: Option<HeaderValue>,
+}
+
+impl<E: Endpoint<Output = Response>> Endpoint for HstsEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let https = req.scheme() == &Scheme::HTTPS;
+        let mut resp = self.inner.call(req).await?;
+        if let Some(hsts) = self.hsts.as_ref().filter(|_| https) {
+            resp.headers_mut()
+                .entry(header::STRICT_TRANSPORT_SECURITY)
+                .or_insert_with(|| hsts.clone());
+        }
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::{handler, listener::TcpListener, Server};
+
+    #[handler(internal)]
+    fn index() -> &'static str {
+        "index"
+    }
+
+    async fn bind() -> (crate::listener::TcpAcceptor, SocketAddr) {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        (acceptor, addr)
+    }
+
+    async fn send(addr: SocketAddr, data: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(data.as_bytes()).await.unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        resp
+    }
+
+    #[tokio::test]
+    async fn redirect() {
+        let (acceptor, addr) = bind().await;
+        let (redirect_acceptor, redirect_addr) = bind().await;
+        tokio::spawn(
+            Server::new_with_acceptor(acceptor)
+                .https_redirect(
+                    HttpsRedirect::new_with_acceptor(redirect_acceptor).https_port(8443),
+                )
+                .run(index),
+        );
+
+        let resp = send(
+            redirect_addr,
+            "POST /a/b?c=1 HTTP/1.1\r\nhost: example.com:8080\r\nconnection: close\r\n\r\n",
+        )
+        .await;
+        assert!(resp.starts_with("HTTP/1.1 301 Moved Permanently\r\n"));
+        assert!(resp.contains("\r\nlocation: https://example.com:8443/a/b?c=1\r\n"));
+
+        let resp = send(redirect_addr, "GET / HTTP/1.0\r\n\r\n").await;
+        assert!(resp.starts_with("HTTP/1.0 400 Bad Request\r\n"));
+
+        // the server listener is served as usual
+        let resp = send(
+            addr,
+            "GET / HTTP/1.1\r\nhost: example.com\r\nconnection: close\r\n\r\n",
+        )
+        .await;
+        assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(!resp.contains("strict-transport-security"));
+        assert!(resp.ends_with("\r\n\r\nindex"));
+    }
+
+    #[cfg(feature = "acme-base")]
+    #[tokio::test]
+    async fn acme_challenge() {
+        use crate::listener::acme::Http01TokensMap;
+
+        let keys = Http01TokensMap::new();
+        keys.insert("token", "authorization");
+        let (_, ep, _) = HttpsRedirect::new(TcpListener::bind("127.0.0.1:0")).into_parts();
+        let ep = RedirectEndpoint {
+            http01: Some(Http01Endpoint { keys }),
+            ..ep
+        };
+
+        let req = Request::builder()
+            .uri(Uri::from_static("/.well-known/acme-challenge/token"))
+            .header(header::HOST, "example.com")
+            .finish();
+        let resp = ep.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.into_body().into_string().await.unwrap(),
+            "authorization"
+        );
+
+        let req = Request::builder()
+            .uri(Uri::from_static("/.well-known/acme-challenge/unknown"))
+            .header(header::HOST, "example.com")
+            .finish();
+        assert_eq!(ep.get_response(req).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[cfg(feature = "secure-headers")]
+    #[tokio::test]
+    async fn hsts() {
+        use crate::{
+            web::{LocalAddr, RemoteAddr},
+            EndpointExt,
+        };
+
+        let (_, _, hsts) = HttpsRedirect::new(TcpListener::bind("127.0.0.1:0"))
+            .hsts(Hsts::new(std::time::Duration::from_secs(60)))
+            .into_parts();
+        let ep = HstsEndpoint {
+            inner: index.map_to_response(),
+            hsts,
+        };
+
+        let request = |scheme| {
+            let req = http::Request::new(http_body_util::Empty::<bytes::Bytes>::new());
+            Request::from((req, LocalAddr::default(), RemoteAddr::default(), scheme))
+        };
+        let resp = ep.call(request(Scheme::HTTPS)).await.unwrap();
+        assert_eq!(
+            resp.headers()[header::STRICT_TRANSPORT_SECURITY],
+            "max-age=60"
+        );
+        let resp = ep.call(request(Scheme::HTTP)).await.unwrap();
+        assert!(!resp
+            .headers()
+            .contains_key(header::STRICT_TRANSPORT_SECURITY));
+    }
+}
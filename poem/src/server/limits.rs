@@ -0,0 +1,1118 @@
+use std::{
+    future::Future,
+    io,
+    io::IoSlice,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::Sleep,
+};
+
+/// The bytes of a head are buffered up to the default buffer size of the HTTP
+/// parser without the limits.
+const MAX_BUF_SIZE: usize = 8192 + 4096 * 100;
+
+const H2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Limits and checks applied to the heads of the HTTP/1 requests before they
+/// are passed to the HTTP parser.
+///
+/// The requests that violate them are rejected, and the connection is closed:
+///
+/// - `400 Bad Request` for the requests with both the `Content-Length` and the
+///   `Transfer-Encoding` headers, with conflicting `Content-Length` values, or
+///   with header values folded across multiple lines (obs-fold).
+/// - `431 Request Header Fields Too Large` for the requests with too many
+///   headers or an oversized head.
+///
+/// The rejections are counted in
+/// [`Server::request_rejections`](crate::Server::request_rejections).
+///
+/// # Example
+///
+/// ```no_run
+/// use poem::{listener::TcpListener, RequestLimits, Route, Server};
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let server = Server::new(TcpListener::bind("0.0.0.0:3000")).request_limits(
+///     RequestLimits::new()
+///         .max_headers(50)
+///         .max_header_bytes(16 * 1024),
+/// );
+/// let rejections = server.request_rejections();
+///
+/// tokio::spawn(async move {
+///     loop {
+///         tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+///         tracing::info!(total = rejections.total(), "rejected requests");
+///     }
+/// });
+///
+/// server.run(Route::new()).await
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+#[derive(Debug, Clone)]
+pub struct RequestLimits {
+    pub(crate) max_headers: usize,
+    pub(crate) max_header_bytes: usize,
+    reject_ambiguous_length: bool,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_headers: 100,
+            max_header_bytes: 64 * 1024,
+            reject_ambiguous_length: true,
+        }
+    }
+}
+
+impl RequestLimits {
+    /// Create a `RequestLimits` with the default limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of headers in a request.
+    ///
+    /// Default is `100`.
+    #[must_use]
+    pub fn max_headers(self, max: usize) -> Self {
+        Self {
+            max_headers: max,
+            ..self
+        }
+    }
+
+    /// Sets the maximum size in bytes of the request line and the headers.
+    ///
+    /// It also limits the size of the header list of the HTTP/2 requests.
+    ///
+    /// Default is `64KiB`.
+    #[must_use]
+    pub fn max_header_bytes(self, max: usize) -> Self {
+        Self {
+            max_header_bytes: max,
+            ..self
+        }
+    }
+
+    /// Rejects the requests that have both the `Content-Length` and the
+    /// `Transfer-Encoding` headers.
+    ///
+    /// If disabled, the `Content-Length` header of these requests is ignored.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn reject_ambiguous_length(self, reject: bool) -> Self {
+        Self {
+            reject_ambiguous_length: reject,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    ambiguous_length: AtomicU64,
+    obs_fold: AtomicU64,
+    too_many_headers: AtomicU64,
+    headers_too_large: AtomicU64,
+    header_timeouts: AtomicU64,
+    body_timeouts: AtomicU64,
+}
+
+/// The number of the requests rejected by the [`RequestLimits`] and the read
+/// timeouts of the [`Server`](crate::Server).
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+#[derive(Debug, Clone, Default)]
+pub struct RequestRejections(Arc<Counters>);
+
+impl RequestRejections {
+    /// Returns the number of the requests rejected because of the ambiguous
+    /// `Content-Length` and `Transfer-Encoding` headers.
+    pub fn ambiguous_length(&self) -> u64 {
+        self.0.ambiguous_length.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of the requests rejected because of the folded
+    /// header values.
+    pub fn obs_fold(&self) -> u64 {
+        self.0.obs_fold.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of the requests rejected because of too many
+    /// headers.
+    pub fn too_many_headers(&self) -> u64 {
+        self.0.too_many_headers.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of the requests rejected because of an oversized
+    /// head.
+    pub fn headers_too_large(&self) -> u64 {
+        self.0.headers_too_large.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of the requests whose headers were not received
+    /// within the
+    /// [`header_read_timeout`](crate::Server::header_read_timeout).
+    pub fn header_timeouts(&self) -> u64 {
+        self.0.header_timeouts.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of the requests whose body was not received within
+    /// the [`body_read_timeout`](crate::Server::body_read_timeout).
+    pub fn body_timeouts(&self) -> u64 {
+        self.0.body_timeouts.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total number of the rejected requests.
+    pub fn total(&self) -> u64 {
+        self.ambiguous_length()
+            + self.obs_fold()
+            + self.too_many_headers()
+            + self.headers_too_large()
+            + self.header_timeouts()
+            + self.body_timeouts()
+    }
+
+    fn record(&self, rejection: Rejection) {
+        let counter = match rejection {
+            Rejection::AmbiguousLength => &self.0.ambiguous_length,
+            Rejection::ObsFold => &self.0.obs_fold,
+            Rejection::TooManyHeaders => &self.0.too_many_headers,
+            Rejection::HeadersTooLarge => &self.0.headers_too_large,
+            Rejection::HeaderTimeout => &self.0.header_timeouts,
+            Rejection::BodyTimeout => &self.0.body_timeouts,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Rejection {
+    AmbiguousLength,
+    ObsFold,
+    TooManyHeaders,
+    HeadersTooLarge,
+    HeaderTimeout,
+    BodyTimeout,
+}
+
+impl Rejection {
+    fn reason(self) -> &'static str {
+        match self {
+            Rejection::AmbiguousLength => "ambiguous message length",
+            Rejection::ObsFold => "folded header value",
+            Rejection::TooManyHeaders => "too many headers",
+            Rejection::HeadersTooLarge => "headers too large",
+            Rejection::HeaderTimeout => "header read timeout",
+            Rejection::BodyTimeout => "body read timeout",
+        }
+    }
+
+    fn response(self) -> &'static [u8] {
+        match self {
+            Rejection::AmbiguousLength | Rejection::ObsFold => {
+                b"HTTP/1.1 400 Bad Request\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+            }
+            Rejection::TooManyHeaders | Rejection::HeadersTooLarge => {
+                b"HTTP/1.1 431 Request Header Fields Too Large\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+            }
+            Rejection::HeaderTimeout | Rejection::BodyTimeout => {
+                b"HTTP/1.1 408 Request Timeout\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum State {
+    Head,
+    Body(u64),
+    ChunkSize,
+    ChunkData(u64),
+    ChunkEnd,
+    Trailers,
+    Passthrough,
+    Rejected {
+        response: &'static [u8],
+        written: usize,
+    },
+    TimedOut {
+        written: usize,
+    },
+}
+
+impl State {
+    fn is_body(self) -> bool {
+        matches!(
+            self,
+            State::Body(_)
+                | State::ChunkSize
+                | State::ChunkData(_)
+                | State::ChunkEnd
+                | State::Trailers
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Timer {
+    None,
+    Header,
+    Body,
+}
+
+/// Checks the request heads read from the connection.
+///
+/// The bytes of a request head are held back until the whole head has been
+/// checked, the bodies are tracked to find the start of the next request, and
+/// the connection is passed through untouched after an HTTP/2 preface or once
+/// the protocol has been switched.
+///
+/// A request asking for an upgrade does not switch the protocol by itself, the
+/// service sets `switched` when it answers with `101 Switching Protocols` or
+/// accepts a `CONNECT` request, otherwise the next requests on the connection
+/// are checked as usual.
+///
+/// The timer of the header read timeout starts when the first byte of a head
+/// is received, and the timer of the body read timeout restarts whenever a
+/// part of the body is received.
+pub(crate) struct RequestGuard<T> {
+    inner: T,
+    limits: RequestLimits,
+    header_read_timeout: Option<Duration>,
+    body_read_timeout: Option<Duration>,
+    rejections: RequestRejections,
+    state: State,
+    buf: Vec<u8>,
+    checked: usize,
+    eof: bool,
+    timer: Timer,
+    sleep: Option<Pin<Box<Sleep>>>,
+    responding: bool,
+    head: HeadParser,
+    /// The number of bytes after the start of the current chunk line that are
+    /// known to contain no line end.
+    line_scanned: usize,
+    /// The connection may still start with the HTTP/2 preface.
+    preface: bool,
+    switched: Arc<AtomicBool>,
+}
+
+impl<T> RequestGuard<T> {
+    pub(crate) fn new(
+        inner: T,
+        limits: Option<RequestLimits>,
+        (header_read_timeout, body_read_timeout): (Option<Duration>, Option<Duration>),
+        rejections: RequestRejections,
+        switched: Arc<AtomicBool>,
+    ) -> Self {
+        let enabled =
+            limits.is_some() || header_read_timeout.is_some() || body_read_timeout.is_some();
+        Self {
+            inner,
+            state: if enabled {
+                State::Head
+            } else {
+                State::Passthrough
+            },
+            limits: limits.unwrap_or(RequestLimits {
+                max_headers: usize::MAX,
+                max_header_bytes: MAX_BUF_SIZE,
+                reject_ambiguous_length: false,
+            }),
+            header_read_timeout,
+            body_read_timeout,
+            rejections,
+            buf: Vec::new(),
+            checked: 0,
+            eof: false,
+            timer: Timer::None,
+            sleep: None,
+            responding: false,
+            head: HeadParser::default(),
+            line_scanned: 0,
+            preface: true,
+            switched,
+        }
+    }
+
+    fn set_timer(&mut self, timer: Timer) {
+        if self.timer != timer {
+            let timeout = match timer {
+                Timer::None => None,
+                Timer::Header => self.header_read_timeout,
+                Timer::Body => self.body_read_timeout,
+            };
+            self.timer = timer;
+            self.sleep = timeout.map(|timeout| Box::pin(tokio::time::sleep(timeout)));
+        }
+    }
+
+    /// Returns `true` if the current timer has expired.
+    fn poll_timeout(&mut self, cx: &mut Context<'_>) -> bool {
+        self.sleep
+            .as_mut()
+            .is_some_and(|sleep| sleep.as_mut().poll(cx).is_ready())
+    }
+
+    fn reject(&mut self, rejection: Rejection) {
+        tracing::debug!(reason = rejection.reason(), "reject request");
+        self.rejections.record(rejection);
+        self.buf.clear();
+        self.head = HeadParser::default();
+        self.line_scanned = 0;
+        self.set_timer(Timer::None);
+        self.state = match rejection {
+            // the response of the current request may have been started
+            Rejection::BodyTimeout if self.responding => State::TimedOut {
+                written: rejection.response().len(),
+            },
+            Rejection::BodyTimeout => State::TimedOut { written: 0 },
+            _ => State::Rejected {
+                response: rejection.response(),
+                written: 0,
+            },
+        };
+    }
+
+    fn advance(&mut self) -> Result<bool, Rejection> {
+        let data = &self.buf[self.checked..];
+        if data.is_empty() {
+            return Ok(false);
+        }
+
+        if self.preface {
+            let n = data.len().min(H2_PREFACE.len());
+            if data[..n] != H2_PREFACE[..n] {
+                self.preface = false;
+            } else if n < H2_PREFACE.len() {
+                return Ok(false);
+            } else {
+                self.state = State::Passthrough;
+                return Ok(true);
+            }
+        }
+
+        let scanned = &mut self.line_scanned;
+        let next = match self.state {
+            State::Head => self.head.parse(data, &self.limits)?,
+            State::Body(remaining) => Some(skip(data, remaining, State::Body, State::Head)),
+            State::ChunkData(remaining) => {
+                Some(skip(data, remaining, State::ChunkData, State::ChunkEnd))
+            }
+            State::ChunkSize => line(data, scanned).map(|n| {
+                let state = match parse_chunk_size(&data[..n]) {
+                    Some(0) => State::Trailers,
+                    Some(size) => State::ChunkData(size),
+                    None => State::Passthrough,
+                };
+                (n, state)
+            }),
+            State::ChunkEnd => line(data, scanned).map(|n| (n, State::ChunkSize)),
+            State::Trailers => line(data, scanned).map(|n| {
+                let state = if trim_eol(&data[..n]).is_empty() {
+                    State::Head
+                } else {
+                    State::Trailers
+                };
+                (n, state)
+            }),
+            State::Passthrough | State::Rejected { .. } | State::TimedOut { .. } => {
+                Some((data.len(), self.state))
+            }
+        };
+
+        match next {
+            Some((n, state)) => {
+                if matches!(self.state, State::Head) && state.is_body() {
+                    self.responding = false;
+                }
+                self.checked += n;
+                self.state = state;
+                Ok(true)
+            }
+            None if data.len() > self.limits.max_header_bytes => {
+                // a malformed chunk line, leave it to the HTTP parser
+                self.state = State::Passthrough;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Returns the length of the first line including the line end.
+///
+/// `scanned` is the number of bytes known to contain no line end from the
+/// previous calls, so that a line received in small pieces is scanned once.
+fn line(data: &[u8], scanned: &mut usize) -> Option<usize> {
+    match memchr::memchr(b'\n', &data[*scanned..]) {
+        Some(n) => {
+            let len = *scanned + n + 1;
+            *scanned = 0;
+            Some(len)
+        }
+        None => {
+            *scanned = data.len();
+            None
+        }
+    }
+}
+
+fn trim_eol(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+fn trim(value: &[u8]) -> &[u8] {
+    let start = value
+        .iter()
+        .position(|c| !c.is_ascii_whitespace())
+        .unwrap_or(value.len());
+    let end = value
+        .iter()
+        .rposition(|c| !c.is_ascii_whitespace())
+        .map_or(start, |n| n + 1);
+    &value[start..end]
+}
+
+fn skip(data: &[u8], remaining: u64, partial: fn(u64) -> State, done: State) -> (usize, State) {
+    let n = remaining.min(data.len() as u64);
+    let state = if n == remaining {
+        done
+    } else {
+        partial(remaining - n)
+    };
+    (n as usize, state)
+}
+
+fn parse_chunk_size(line: &[u8]) -> Option<u64> {
+    let line = trim_eol(line);
+    let size = line.split(|&c| c == b';').next().map(trim)?;
+    u64::from_str_radix(std::str::from_utf8(size).ok()?, 16).ok()
+}
+
+/// An incremental parser of the request heads.
+///
+/// The bytes of a head are held back until the whole head has been checked,
+/// so the parser keeps the offset of the next line and the headers seen so far
+/// across the reads instead of starting over from the first byte.
+#[derive(Default)]
+struct HeadParser {
+    /// The offset of the next line.
+    pos: usize,
+    /// The number of bytes of the next line that contain no line end.
+    scanned: usize,
+    request_line: bool,
+    headers: usize,
+    content_length: Option<u64>,
+    /// Whether the last transfer coding is `chunked`.
+    transfer_encoding: Option<bool>,
+}
+
+impl HeadParser {
+    /// Parses the lines of the head received since the last call, returns the
+    /// length of the head and the next state if it is complete.
+    ///
+    /// `data` starts with the head, and is the data of the last call with the
+    /// new bytes appended.
+    fn parse(
+        &mut self,
+        data: &[u8],
+        limits: &RequestLimits,
+    ) -> Result<Option<(usize, State)>, Rejection> {
+        loop {
+            let Some(n) = line(&data[self.pos..], &mut self.scanned) else {
+                if data.len() > limits.max_header_bytes {
+                    return Err(Rejection::HeadersTooLarge);
+                }
+                return Ok(None);
+            };
+            let line = trim_eol(&data[self.pos..self.pos + n]);
+            self.pos += n;
+            if self.pos > limits.max_header_bytes {
+                return Err(Rejection::HeadersTooLarge);
+            }
+
+            if !self.request_line {
+                // the empty lines before the request line are ignored
+                self.request_line = !line.is_empty();
+                continue;
+            }
+            if line.is_empty() {
+                let len = self.pos;
+                let state = self.finish(limits)?;
+                *self = HeadParser::default();
+                return Ok(Some((len, state)));
+            }
+
+            if line[0] == b' ' || line[0] == b'\t' {
+                return Err(Rejection::ObsFold);
+            }
+            self.headers += 1;
+            if self.headers > limits.max_headers {
+                return Err(Rejection::TooManyHeaders);
+            }
+
+            let Some(colon) = memchr::memchr(b':', line) else {
+                continue;
+            };
+            let name = &line[..colon];
+            let value = trim(&line[colon + 1..]);
+            if name.eq_ignore_ascii_case(b"content-length") {
+                for value in value.split(|&c| c == b',') {
+                    let len = std::str::from_utf8(trim(value))
+                        .ok()
+                        .and_then(|value| value.parse::<u64>().ok())
+                        .ok_or(Rejection::AmbiguousLength)?;
+                    if self.content_length.is_some_and(|prev| prev != len) {
+                        return Err(Rejection::AmbiguousLength);
+                    }
+                    self.content_length = Some(len);
+                }
+            } else if name.eq_ignore_ascii_case(b"transfer-encoding") {
+                self.transfer_encoding = Some(
+                    value
+                        .rsplit(|&c| c == b',')
+                        .next()
+                        .is_some_and(|coding| trim(coding).eq_ignore_ascii_case(b"chunked")),
+                );
+            }
+        }
+    }
+
+    /// Returns the state after the complete head.
+    fn finish(&self, limits: &RequestLimits) -> Result<State, Rejection> {
+        if self.transfer_encoding.is_some()
+            && self.content_length.is_some()
+            && limits.reject_ambiguous_length
+        {
+            return Err(Rejection::AmbiguousLength);
+        }
+
+        Ok(if let Some(chunked) = self.transfer_encoding {
+            if chunked {
+                State::ChunkSize
+            } else {
+                // rejected by the HTTP parser
+                State::Passthrough
+            }
+        } else {
+            match self.content_length {
+                Some(len) if len > 0 => State::Body(len),
+                _ => State::Head,
+            }
+        })
+    }
+}
+
+impl<T> AsyncRead for RequestGuard<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        loop {
+            if !matches!(
+                this.state,
+                State::Passthrough | State::Rejected { .. } | State::TimedOut { .. }
+            ) && this.switched.load(Ordering::Acquire)
+            {
+                // the bytes after the switch belong to the new protocol
+                this.state = State::Passthrough;
+                this.head = HeadParser::default();
+                this.line_scanned = 0;
+                this.set_timer(Timer::None);
+            }
+
+            if this.checked > 0 {
+                let n = this.checked.min(out.remaining());
+                out.put_slice(&this.buf[..n]);
+                this.buf.drain(..n);
+                this.checked -= n;
+                return Poll::Ready(Ok(()));
+            }
+
+            if matches!(this.state, State::Rejected { .. }) {
+                // the response is written when the connection is shut down, after the
+                // responses of the previous requests
+                return Poll::Ready(Ok(()));
+            }
+
+            if let State::TimedOut { written } = &mut this.state {
+                let response = Rejection::BodyTimeout.response();
+                while *written < response.len() {
+                    let n =
+                        ready!(Pin::new(&mut this.inner).poll_write(cx, &response[*written..]))?;
+                    if n == 0 {
+                        break;
+                    }
+                    *written += n;
+                }
+                ready!(Pin::new(&mut this.inner).poll_flush(cx))?;
+                return Poll::Ready(Err(io::ErrorKind::TimedOut.into()));
+            }
+
+            if this.buf.is_empty() && matches!(this.state, State::Passthrough) {
+                return Pin::new(&mut this.inner).poll_read(cx, out);
+            }
+
+            if this.eof {
+                // leave the incomplete request to the HTTP parser
+                if this.buf.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                this.checked = this.buf.len();
+                this.head = HeadParser::default();
+                this.line_scanned = 0;
+                continue;
+            }
+
+            if let State::Body(remaining) = this.state {
+                if this.buf.is_empty() {
+                    // the body is read into `out` directly, limited to its remaining length so
+                    // that the next request is still checked
+                    this.set_timer(Timer::Body);
+                    let len = remaining.min(out.remaining() as u64) as usize;
+                    let mut data = ReadBuf::new(out.initialize_unfilled_to(len));
+                    if Pin::new(&mut this.inner)
+                        .poll_read(cx, &mut data)?
+                        .is_pending()
+                    {
+                        if !this.poll_timeout(cx) {
+                            return Poll::Pending;
+                        }
+                        this.reject(Rejection::BodyTimeout);
+                        continue;
+                    }
+                    let n = data.filled().len();
+                    if n == 0 {
+                        this.eof = true;
+                        continue;
+                    }
+                    out.advance(n);
+                    this.set_timer(Timer::None);
+                    this.state = match remaining - n as u64 {
+                        0 => State::Head,
+                        remaining => State::Body(remaining),
+                    };
+                    return Poll::Ready(Ok(()));
+                }
+            }
+
+            match this.advance() {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(rejection) => {
+                    this.reject(rejection);
+                    continue;
+                }
+            }
+
+            let timer = match this.state {
+                State::Head if !this.buf.is_empty() => Timer::Header,
+                state if state.is_body() => Timer::Body,
+                _ => Timer::None,
+            };
+            this.set_timer(timer);
+
+            let mut data = [0; 8192];
+            let mut data = ReadBuf::new(&mut data);
+            if Pin::new(&mut this.inner)
+                .poll_read(cx, &mut data)?
+                .is_pending()
+            {
+                if !this.poll_timeout(cx) {
+                    return Poll::Pending;
+                }
+                this.reject(match this.timer {
+                    Timer::Header => Rejection::HeaderTimeout,
+                    _ => Rejection::BodyTimeout,
+                });
+                continue;
+            }
+
+            if data.filled().is_empty() {
+                this.eof = true;
+            } else {
+                if this.timer == Timer::Body {
+                    this.set_timer(Timer::None);
+                }
+                this.buf.extend_from_slice(data.filled());
+            }
+        }
+    }
+}
+
+impl<T> AsyncWrite for RequestGuard<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        if matches!(self.state, State::TimedOut { .. }) {
+            // discard the response of the handler after `408 Request Timeout`
+            return Poll::Ready(Ok(buf.len()));
+        }
+        self.responding = true;
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        let this = &mut *self;
+        if let State::Rejected { response, written } = &mut this.state {
+            while *written < response.len() {
+                let n = ready!(Pin::new(&mut this.inner).poll_write(cx, &response[*written..]))?;
+                if n == 0 {
+                    break;
+                }
+                *written += n;
+            }
+            ready!(Pin::new(&mut this.inner).poll_flush(cx))?;
+        }
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, io::Error>> {
+        if matches!(self.state, State::TimedOut { .. }) {
+            return Poll::Ready(Ok(bufs.iter().map(|buf| buf.len()).sum()));
+        }
+        self.responding = true;
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, net::SocketAddr};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::{
+        handler,
+        listener::{Acceptor, Listener, TcpAcceptor, TcpListener},
+        web::Upgrade,
+        IntoResponse, Route, Server,
+    };
+
+    #[handler(internal)]
+    fn echo(body: String) -> String {
+        format!("[{body}]")
+    }
+
+    #[handler(internal)]
+    fn upgrade(upgrade: Upgrade) -> impl IntoResponse {
+        upgrade.on_upgrade("echo", |io| async move {
+            let (mut reader, mut writer) = tokio::io::split(io);
+            let _ = tokio::io::copy(&mut reader, &mut writer).await;
+        })
+    }
+
+    async fn start(
+        f: impl FnOnce(Server<Infallible, TcpAcceptor>) -> Server<Infallible, TcpAcceptor>,
+    ) -> (SocketAddr, RequestRejections) {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor
+            .local_addr()
+            .remove(0)
+            .as_socket_addr()
+            .cloned()
+            .unwrap();
+        let server = f(Server::new_with_acceptor(acceptor));
+        let rejections = server.request_rejections();
+        tokio::spawn(server.run(Route::new().at("/", echo).at("/upgrade", upgrade)));
+        (addr, rejections)
+    }
+
+    async fn send(addr: SocketAddr, data: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(data.as_bytes()).await.unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        resp
+    }
+
+    #[tokio::test]
+    async fn ambiguous_length() {
+        let (addr, rejections) = start(|server| server.request_limits(RequestLimits::new())).await;
+
+        let resp = send(
+            addr,
+            "POST / HTTP/1.1\r\nhost: a\r\ncontent-length: 5\r\n\r\nhello\
+             POST / HTTP/1.1\r\nhost: a\r\ntransfer-encoding: chunked\r\n\r\n\
+             3;ext=1\r\nabc\r\n0\r\nx-trailer: 1\r\n\r\n\
+             POST / HTTP/1.1\r\nhost: a\r\ncontent-length: 4\r\ntransfer-encoding: chunked\r\n\r\n\
+             0\r\n\r\n",
+        )
+        .await;
+        assert!(resp.contains("[hello]"));
+        assert!(resp.contains("[abc]"));
+        assert!(resp.ends_with(
+            "HTTP/1.1 400 Bad Request\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+        ));
+
+        let resp = send(
+            addr,
+            "GET / HTTP/1.1\r\nhost: a\r\ncontent-length: 1\r\ncontent-length: 2\r\n\r\nab",
+        )
+        .await;
+        assert!(resp.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert_eq!(rejections.ambiguous_length(), 2);
+        assert_eq!(rejections.total(), 2);
+    }
+
+    #[tokio::test]
+    async fn allow_ambiguous_length() {
+        let (addr, rejections) = start(|server| {
+            server.request_limits(RequestLimits::new().reject_ambiguous_length(false))
+        })
+        .await;
+
+        let resp = send(
+            addr,
+            "POST / HTTP/1.1\r\nhost: a\r\ncontent-length: 100\r\ntransfer-encoding: chunked\r\n\r\n\
+             2\r\nab\r\n0\r\n\r\n",
+        )
+        .await;
+        assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(resp.ends_with("[ab]"));
+        assert_eq!(rejections.total(), 0);
+    }
+
+    #[tokio::test]
+    async fn obs_fold() {
+        let (addr, rejections) = start(|server| server.request_limits(RequestLimits::new())).await;
+
+        let resp = send(addr, "GET / HTTP/1.1\r\nhost: a\r\nx-a: 1\r\n 2\r\n\r\n").await;
+        assert!(resp.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert_eq!(rejections.obs_fold(), 1);
+    }
+
+    #[tokio::test]
+    async fn header_limits() {
+        let (addr, rejections) = start(|server| {
+            server.request_limits(RequestLimits::new().max_headers(3).max_header_bytes(100))
+        })
+        .await;
+
+        let resp = send(
+            addr,
+            "GET / HTTP/1.1\r\nhost: a\r\nx-a: 1\r\nconnection: close\r\n\r\n",
+        )
+        .await;
+        assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let resp = send(
+            addr,
+            "GET / HTTP/1.1\r\nhost: a\r\nx-a: 1\r\nx-b: 2\r\nx-c: 3\r\n\r\n",
+        )
+        .await;
+        assert!(resp.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+        assert_eq!(rejections.too_many_headers(), 1);
+
+        let resp = send(
+            addr,
+            &format!(
+                "GET / HTTP/1.1\r\nhost: a\r\nx-a: {}\r\n\r\n",
+                "a".repeat(100)
+            ),
+        )
+        .await;
+        assert!(resp.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+        assert_eq!(rejections.headers_too_large(), 1);
+    }
+
+    #[tokio::test]
+    async fn header_read_timeout() {
+        let (addr, rejections) =
+            start(|server| server.header_read_timeout(Duration::from_millis(100))).await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: a\r\n")
+            .await
+            .unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        assert!(resp.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+        assert_eq!(rejections.header_timeouts(), 1);
+
+        // idle connections are not affected
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: a\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[tokio::test]
+    async fn body_read_timeout() {
+        let (addr, rejections) =
+            start(|server| server.body_read_timeout(Duration::from_millis(100))).await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"POST / HTTP/1.1\r\nhost: a\r\nconnection: close\r\ncontent-length: 6\r\n\r\nabc",
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stream.write_all(b"de").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stream.write_all(b"f").await.unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        assert!(resp.ends_with("[abcdef]"));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"POST / HTTP/1.1\r\nhost: a\r\ntransfer-encoding: chunked\r\n\r\n3\r\nabc\r\n",
+            )
+            .await
+            .unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        assert_eq!(
+            resp,
+            "HTTP/1.1 408 Request Timeout\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+        );
+        assert_eq!(rejections.body_timeouts(), 1);
+    }
+
+    #[tokio::test]
+    async fn split_reads() {
+        let (addr, rejections) = start(|server| server.request_limits(RequestLimits::new())).await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.set_nodelay(true).unwrap();
+        for part in [
+            "POST / HTT",
+            "P/1.1\r\nhost: a\r",
+            "\ncontent-len",
+            "gth: 100000\r\n\r\n",
+        ] {
+            stream.write_all(part.as_bytes()).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let body = "a".repeat(100000);
+        stream.write_all(body.as_bytes()).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: a\r\nx-a: 1\r\n 2\r\n\r\n")
+            .await
+            .unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        assert!(resp.contains(&format!("[{body}]")));
+        assert!(resp.ends_with(
+            "HTTP/1.1 400 Bad Request\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+        ));
+        assert_eq!(rejections.obs_fold(), 1);
+    }
+
+    #[tokio::test]
+    async fn upgrade_without_switch() {
+        let (addr, rejections) = start(|server| server.request_limits(RequestLimits::new())).await;
+
+        let resp = send(
+            addr,
+            "GET / HTTP/1.1\r\nhost: a\r\nupgrade: foo\r\n\r\n\
+             GET / HTTP/1.1\r\nhost: a\r\nx-a: 1\r\n 2\r\n\r\n",
+        )
+        .await;
+        assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(resp.matches("HTTP/1.1 ").count(), 1);
+        assert_eq!(rejections.obs_fold(), 1);
+
+        let resp = send(
+            addr,
+            "CONNECT a:80 HTTP/1.1\r\nhost: a:80\r\n\r\n\
+             POST / HTTP/1.1\r\nhost: a\r\ncontent-length: 4\r\ntransfer-encoding: chunked\r\n\r\n\
+             0\r\n\r\n",
+        )
+        .await;
+        assert!(resp.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(!resp.contains("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(rejections.ambiguous_length(), 1);
+    }
+
+    #[tokio::test]
+    async fn switch_protocols() {
+        let (addr, rejections) = start(|server| {
+            server
+                .request_limits(RequestLimits::new())
+                .header_read_timeout(Duration::from_millis(100))
+        })
+        .await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"GET /upgrade HTTP/1.1\r\nhost: a\r\nconnection: upgrade\r\nupgrade: echo\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut resp = Vec::new();
+        while !resp.ends_with(b"\r\n\r\n") {
+            resp.push(stream.read_u8().await.unwrap());
+        }
+        assert!(resp.starts_with(b"HTTP/1.1 101 Switching Protocols\r\n"));
+
+        // neither checked as a request head nor timed out
+        stream.write_all(b"x-a: 1\r\n 2\r\n").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        stream.write_all(b"\r\n").await.unwrap();
+        let mut data = [0; 14];
+        stream.read_exact(&mut data).await.unwrap();
+        assert_eq!(&data, b"x-a: 1\r\n 2\r\n\r\n");
+        assert_eq!(rejections.total(), 0);
+    }
+}
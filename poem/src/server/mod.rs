@@ -0,0 +1,1167 @@
+use std::{
+    convert::Infallible,
+    future::Future,
+    io,
+    io::IoSlice,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use futures_util::FutureExt;
+use http::{uri::Scheme, Extensions};
+use hyper::body::Incoming;
+use hyper_util::server::conn::auto;
+use pin_project_lite::pin_project;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult},
+    runtime::Handle,
+    sync::{oneshot, Notify},
+    time::Duration,
+};
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "service-registry")]
+pub use self::registry::{ConsulRegistry, EtcdRegistry, ServiceRegistration, ServiceRegistry};
+pub(crate) use self::shutdown::InFlightRequest;
+use self::{
+    hooks::Hook, https_redirect::HstsEndpoint, limits::RequestGuard, shutdown::InFlightRequests,
+};
+pub use self::{
+    hooks::ServerInfo,
+    https_redirect::HttpsRedirect,
+    limits::{RequestLimits, RequestRejections},
+    readiness::Readiness,
+    sharded::{ShardAcceptor, ShardStream, ShardedServer},
+    shutdown::ShutdownReport,
+};
+use crate::{
+    di::{Container, Injector},
+    endpoint::{DynEndpoint, ToDynEndpoint},
+    listener::{Acceptor, AcceptorExt, BoxAcceptor, BoxIo, Listener},
+    task::{Task, TaskContext, Tasks},
+    web::{CancelToken, LocalAddr, RemoteAddr},
+    Endpoint, EndpointExt, IntoEndpoint, Request, Response,
+};
+
+mod hooks;
+mod https_redirect;
+mod limits;
+mod readiness;
+#[cfg(feature = "service-registry")]
+mod registry;
+mod sharded;
+mod shutdown;
+
+enum Either<L, A> {
+    Listener(L),
+    Acceptor(A),
+}
+
+/// An HTTP Server.
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+pub struct Server<L, A> {
+    listener: Either<L, A>,
+    name: Option<String>,
+    idle_timeout: Option<Duration>,
+    http2_max_concurrent_streams: Option<u32>,
+    http2_max_pending_accept_reset_streams: Option<u32>,
+    http1_writev: Option<bool>,
+    request_limits: Option<RequestLimits>,
+    request_rejections: RequestRejections,
+    connections: ServerConnections,
+    header_read_timeout: Option<Duration>,
+    body_read_timeout: Option<Duration>,
+    https_redirect: Option<HttpsRedirect>,
+    container: Container,
+    tasks: Tasks,
+    on_start: Vec<Hook>,
+    on_stop: Vec<Hook>,
+    readiness: Readiness,
+    sigterm_delay: Option<Duration>,
+    runtime: Option<Handle>,
+}
+
+/// The number of the connections of the [`Server`].
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+#[derive(Debug, Clone, Default)]
+pub struct ServerConnections(Arc<AtomicUsize>);
+
+impl ServerConnections {
+    /// Returns the number of the connections being served.
+    pub fn active(&self) -> usize {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+impl<L: Listener> Server<L, Infallible> {
+    /// Use the specified listener to create an HTTP server.
+    pub fn new(listener: L) -> Self {
+        Self {
+            listener: Either::Listener(listener),
+            name: None,
+            idle_timeout: None,
+            http2_max_concurrent_streams: None,
+            http2_max_pending_accept_reset_streams: Some(20),
+            http1_writev: None,
+            request_limits: None,
+            request_rejections: RequestRejections::default(),
+            connections: ServerConnections::default(),
+            header_read_timeout: None,
+            body_read_timeout: None,
+            https_redirect: None,
+            container: Container::new(),
+            tasks: Tasks::default(),
+            on_start: Vec::new(),
+            on_stop: Vec::new(),
+            readiness: Readiness::default(),
+            sigterm_delay: None,
+            runtime: None,
+        }
+    }
+}
+
+impl<A: Acceptor> Server<Infallible, A> {
+    /// Use the specified acceptor to create an HTTP server.
+    pub fn new_with_acceptor(acceptor: A) -> Self {
+        Self {
+            listener: Either::Acceptor(acceptor),
+            name: None,
+            idle_timeout: None,
+            http2_max_concurrent_streams: None,
+            http2_max_pending_accept_reset_streams: Some(20),
+            http1_writev: None,
+            request_limits: None,
+            request_rejections: RequestRejections::default(),
+            connections: ServerConnections::default(),
+            header_read_timeout: None,
+            body_read_timeout: None,
+            https_redirect: None,
+            container: Container::new(),
+            tasks: Tasks::default(),
+            on_start: Vec::new(),
+            on_stop: Vec::new(),
+            readiness: Readiness::default(),
+            sigterm_delay: None,
+            runtime: None,
+        }
+    }
+}
+
+impl<L, A> Server<L, A>
+where
+    L: Listener,
+    L::Acceptor: 'static,
+    A: Acceptor + 'static,
+{
+    /// Specify the name of the server, it is only used for logs.
+    #[must_use]
+    pub fn name(self, name: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..self
+        }
+    }
+
+    /// Specify connection idle timeout. Connections will be terminated if there
+    /// was no activity within this period of time
+    #[must_use]
+    pub fn idle_timeout(self, timeout: Duration) -> Self {
+        Self {
+            idle_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Sets the [`SETTINGS_MAX_CONCURRENT_STREAMS`][spec] option for HTTP2
+    /// connections.
+    ///
+    /// Default is 200. Passing `None` will remove any limit.
+    ///
+    /// [spec]: https://http2.github.io/http2-spec/#SETTINGS_MAX_CONCURRENT_STREAMS
+    pub fn http2_max_concurrent_streams(self, max: impl Into<Option<u32>>) -> Self {
+        Self {
+            http2_max_concurrent_streams: max.into(),
+            ..self
+        }
+    }
+
+    /// Configures the maximum number of pending reset streams allowed before a
+    /// GOAWAY will be sent.
+    ///
+    /// This will default to the default value set by the [`h2` crate](https://crates.io/crates/h2).
+    /// As of v0.4.0, it is 20.
+    pub fn http2_max_pending_accept_reset_streams(self, max: impl Into<Option<u32>>) -> Self {
+        Self {
+            http2_max_pending_accept_reset_streams: max.into(),
+            ..self
+        }
+    }
+
+    /// Sets whether to use vectored writes for HTTP/1 connections.
+    ///
+    /// By default, the vectored writes are used when the connection supports
+    /// them, so the head and the body of a response are written with a single
+    /// `writev` call without being copied. Passing `false` copies them into a
+    /// single buffer first, which can be faster for the tiny responses.
+    #[must_use]
+    pub fn http1_writev(self, enabled: bool) -> Self {
+        Self {
+            http1_writev: Some(enabled),
+            ..self
+        }
+    }
+
+    /// Rejects the ambiguous and oversized requests with the specified
+    /// limits.
+    ///
+    /// See also [`RequestLimits`].
+    #[must_use]
+    pub fn request_limits(self, limits: RequestLimits) -> Self {
+        Self {
+            request_limits: Some(limits),
+            ..self
+        }
+    }
+
+    /// Specify the timeout for reading the headers of an HTTP/1 request, which
+    /// starts when the first byte of the request is received.
+    ///
+    /// If the headers are not received in time, the server responds with
+    /// `408 Request Timeout` and closes the connection. It protects against the
+    /// clients that send the headers very slowly to hold the connections.
+    #[must_use]
+    pub fn header_read_timeout(self, timeout: Duration) -> Self {
+        Self {
+            header_read_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Specify the maximum gap between the chunks of the body of an HTTP/1
+    /// request.
+    ///
+    /// The timer only runs when the handler is waiting for the body. If it
+    /// expires, the server responds with `408 Request Timeout`, unless the
+    /// response has been started, and closes the connection.
+    #[must_use]
+    pub fn body_read_timeout(self, timeout: Duration) -> Self {
+        Self {
+            body_read_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Binds a plaintext companion listener which redirects the requests to
+    /// the HTTPS origin.
+    ///
+    /// See also [`HttpsRedirect`].
+    #[must_use]
+    pub fn https_redirect(self, redirect: HttpsRedirect) -> Self {
+        Self {
+            https_redirect: Some(redirect),
+            ..self
+        }
+    }
+
+    /// Returns the counters of the requests rejected by the
+    /// [`RequestLimits`] and the read timeouts.
+    pub fn request_rejections(&self) -> RequestRejections {
+        self.request_rejections.clone()
+    }
+
+    /// Returns the number of the connections being served.
+    pub fn connections(&self) -> ServerConnections {
+        self.connections.clone()
+    }
+
+    /// Registers a singleton dependency that can be extracted with
+    /// [`Dep<T>`](crate::di::Dep).
+    ///
+    /// See also [`Container::provide`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use poem::{di::Dep, handler, listener::TcpListener, Route, Server};
+    ///
+    /// struct Db {
+    ///     url: String,
+    /// }
+    ///
+    /// #[handler]
+    /// fn index(db: Dep<Db>) -> String {
+    ///     db.url.clone()
+    /// }
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// Server::new(TcpListener::bind("0.0.0.0:3000"))
+    ///     .provide::<Db, _>(|_| {
+    ///         Ok(Db {
+    ///             url: "postgres://localhost".to_string(),
+    ///         })
+    ///     })
+    ///     .run(Route::new().at("/", index))
+    ///     .await
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn provide<T, F>(self, factory: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&Injector) -> crate::Result<T> + Send + Sync + 'static,
+    {
+        Self {
+            container: self.container.provide(factory),
+            ..self
+        }
+    }
+
+    /// Registers a singleton dependency with an existing instance.
+    ///
+    /// See also [`Container::provide_instance`].
+    #[must_use]
+    pub fn provide_instance<T>(self, instance: T) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        Self {
+            container: self.container.provide_instance(instance),
+            ..self
+        }
+    }
+
+    /// Registers a dependency that is created at most once per request.
+    ///
+    /// See also [`Container::provide_scoped`].
+    #[must_use]
+    pub fn provide_scoped<T, F>(self, factory: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&Injector) -> crate::Result<T> + Send + Sync + 'static,
+    {
+        Self {
+            container: self.container.provide_scoped(factory),
+            ..self
+        }
+    }
+
+    /// Registers a background task, which is started when the server starts
+    /// and restarted when it panics.
+    ///
+    /// See also [`task`](crate::task).
+    #[must_use]
+    pub fn spawn_task<F, Fut>(self, name: impl Into<String>, f: F) -> Self
+    where
+        F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.task(Task::new(name, f))
+    }
+
+    /// Registers a background task with the specified options.
+    ///
+    /// See also [`task`](crate::task).
+    #[must_use]
+    pub fn task(self, task: impl Into<Task>) -> Self {
+        self.tasks.add(task.into());
+        self
+    }
+
+    /// Returns the registry of the background tasks, which can be used to
+    /// inspect their status.
+    pub fn tasks(&self) -> Tasks {
+        self.tasks.clone()
+    }
+
+    /// Returns the readiness of the server, which can be used as the endpoint
+    /// of the readiness probe.
+    pub fn readiness(&self) -> Readiness {
+        self.readiness.clone()
+    }
+
+    /// Initiates the graceful shutdown when the process receives `SIGTERM`
+    /// (Ctrl-C on the platforms without it), for running in Kubernetes.
+    ///
+    /// When the signal is received, the [`Readiness`] is set to `false`, and
+    /// the server keeps serving the requests during the delay, so the load
+    /// balancers can stop sending the requests to it, then the graceful
+    /// shutdown starts.
+    ///
+    /// See [`Readiness`] for an example.
+    #[must_use]
+    pub fn shutdown_on_sigterm(self, delay: Duration) -> Self {
+        Self {
+            sigterm_delay: Some(delay),
+            ..self
+        }
+    }
+
+    /// Spawns the connections and the background tasks on the specified
+    /// runtime, instead of the runtime which runs the server.
+    ///
+    /// The listener is still driven by the runtime which runs the server, so
+    /// the connections can be accepted on one runtime and served on another.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use poem::{listener::TcpListener, Route, Server};
+    ///
+    /// let workers = tokio::runtime::Runtime::new().unwrap();
+    /// let rt = tokio::runtime::Builder::new_current_thread()
+    ///     .enable_all()
+    ///     .build()
+    ///     .unwrap();
+    /// rt.block_on(
+    ///     Server::new(TcpListener::bind("0.0.0.0:3000"))
+    ///         .runtime(workers.handle().clone())
+    ///         .run(Route::new()),
+    /// )
+    /// .unwrap();
+    /// ```
+    #[must_use]
+    pub fn runtime(self, handle: Handle) -> Self {
+        Self {
+            runtime: Some(handle),
+            ..self
+        }
+    }
+
+    /// Registers a hook which is called after the listener is bound, before
+    /// the server starts accepting the connections.
+    ///
+    /// The hooks are called in the order they are registered, and the server
+    /// fails to start with the error returned by a hook.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use poem::{listener::TcpListener, Route, Server};
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// Server::new(TcpListener::bind("0.0.0.0:3000"))
+    ///     .on_start(|info| async move {
+    ///         println!("listening on {:?}", info.local_addrs());
+    ///         Ok(())
+    ///     })
+    ///     .on_stop(|_| async move {
+    ///         println!("stopping");
+    ///         Ok(())
+    ///     })
+    ///     .run(Route::new())
+    ///     .await
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn on_start<F, Fut>(mut self, f: F) -> Self
+    where
+        F: FnOnce(ServerInfo) -> Fut + Send + 'static,
+        Fut: Future<Output = IoResult<()>> + Send + 'static,
+    {
+        self.on_start.push(Box::new(move |info| f(info).boxed()));
+        self
+    }
+
+    /// Registers a hook which is called when the graceful shutdown is
+    /// initiated, before waiting for the connections to close.
+    ///
+    /// The hooks are called in the order they are registered, the errors
+    /// returned by the hooks are logged.
+    #[must_use]
+    pub fn on_stop<F, Fut>(mut self, f: F) -> Self
+    where
+        F: FnOnce(ServerInfo) -> Fut + Send + 'static,
+        Fut: Future<Output = IoResult<()>> + Send + 'static,
+    {
+        self.on_stop.push(Box::new(move |info| f(info).boxed()));
+        self
+    }
+
+    /// Run this server.
+    pub async fn run<E>(self, ep: E) -> IoResult<()>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.run_with_graceful_shutdown(ep, futures_util::future::pending(), None)
+            .await
+            .map(|_| ())
+    }
+
+    /// Run this server on a new current-thread runtime, blocking the current
+    /// thread until the server stops.
+    ///
+    /// It can be used to run a server per core, with the listeners bound with
+    /// [`TcpListener::reuse_port`](crate::listener::TcpListener::reuse_port).
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is called within an asynchronous execution context.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use poem::{listener::TcpListener, Route, Server};
+    ///
+    /// let threads = (0..4)
+    ///     .map(|_| {
+    ///         std::thread::spawn(|| {
+    ///             Server::new(TcpListener::bind("0.0.0.0:3000").reuse_port(true))
+    ///                 .run_current_thread(Route::new())
+    ///         })
+    ///     })
+    ///     .collect::<Vec<_>>();
+    /// for thread in threads {
+    ///     thread.join().unwrap().unwrap();
+    /// }
+    /// ```
+    pub fn run_current_thread<E>(self, ep: E) -> IoResult<()>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(self.run(ep))
+    }
+
+    /// Run this server and a signal to initiate graceful shutdown.
+    ///
+    /// When the `timeout` elapses after the signal, the remaining connections
+    /// are closed forcibly. The returned [`ShutdownReport`] describes the
+    /// connections and the in-flight requests that were aborted, and how long
+    /// the shutdown took.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use poem::{listener::TcpListener, Route, Server};
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let report = Server::new(TcpListener::bind("0.0.0.0:3000"))
+    ///     .run_with_graceful_shutdown(
+    ///         Route::new(),
+    ///         async {
+    ///             let _ = tokio::signal::ctrl_c().await;
+    ///         },
+    ///         Some(Duration::from_secs(5)),
+    ///     )
+    ///     .await?;
+    /// println!("{}", serde_json::to_string(&report).unwrap());
+    /// # Ok::<_, std::io::Error>(())
+    /// # });
+    /// ```
+    pub async fn run_with_graceful_shutdown<E>(
+        self,
+        ep: E,
+        signal: impl Future<Output = ()>,
+        timeout: Option<Duration>,
+    ) -> IoResult<ShutdownReport>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        let Server {
+            listener,
+            name,
+            idle_timeout,
+            http2_max_concurrent_streams,
+            http2_max_pending_accept_reset_streams,
+            http1_writev,
+            request_limits,
+            request_rejections,
+            connections,
+            header_read_timeout,
+            body_read_timeout,
+            https_redirect,
+            container,
+            tasks,
+            on_start,
+            on_stop,
+            readiness,
+            sigterm_delay,
+            runtime,
+        } = self;
+        let runtime = runtime.unwrap_or_else(Handle::current);
+        let (mut redirect_acceptor, redirect_ep, hsts) = match https_redirect {
+            Some(redirect) => {
+                let (acceptor, ep, hsts) = redirect.into_parts();
+                let ep: Arc<dyn DynEndpoint<Output = Response>> = Arc::new(ToDynEndpoint(ep));
+                (Some(acceptor.await?), Some(ep), hsts)
+            }
+            None => (None, None, None),
+        };
+        let ep: Arc<dyn DynEndpoint<Output = Response>> = Arc::new(ToDynEndpoint(HstsEndpoint {
+            inner: ep
+                .into_endpoint()
+                .with_if(!container.is_empty(), container)
+                .map_to_response(),
+            hsts,
+        }));
+        let name = name.as_deref();
+        let alive_connections = connections.0;
+        let notify = Arc::new(Notify::new());
+        let timeout_token = CancellationToken::new();
+        let server_graceful_shutdown_token = CancellationToken::new();
+        // the in-flight requests are only tracked to report the ones aborted by
+        // the shutdown timeout
+        let in_flight_requests = timeout.map(|_| Arc::new(InFlightRequests::default()));
+        let forced = Arc::new(parking_lot::Mutex::new(None));
+        let shutdown_started;
+
+        let mut acceptor = match listener {
+            Either::Listener(listener) => listener.into_acceptor().await?.boxed(),
+            Either::Acceptor(acceptor) => acceptor.boxed(),
+        };
+
+        let signal = {
+            let readiness = readiness.clone();
+            async move {
+                match sigterm_delay {
+                    Some(delay) => tokio::select! {
+                        _ = signal => {}
+                        _ = readiness::drain_after_sigterm(readiness, delay, name) => {}
+                    },
+                    None => signal.await,
+                }
+            }
+        };
+        tokio::pin!(signal);
+
+        for addr in acceptor.local_addr() {
+            tracing::info!(name = name, addr = %addr, "listening");
+        }
+        for addr in redirect_acceptor
+            .iter()
+            .flat_map(|acceptor| acceptor.local_addr())
+        {
+            tracing::info!(name = name, addr = %addr, "listening for https redirects");
+        }
+        let info = ServerInfo {
+            name: name.map(ToString::to_string),
+            local_addrs: acceptor.local_addr(),
+        };
+        for hook in on_start {
+            hook(info.clone()).await?;
+        }
+        readiness.set_ready(true);
+        tracing::info!(name = name, "server started");
+        let tasks_token = CancellationToken::new();
+        let task_handles = tasks.start(&runtime, tasks_token.clone());
+
+        loop {
+            tokio::select! {
+                _ = &mut signal => {
+                    shutdown_started = Instant::now();
+                    readiness.set_ready(false);
+                    server_graceful_shutdown_token.cancel();
+                    tasks_token.cancel();
+                    if let Some(timeout) = timeout {
+                        tracing::info!(
+                            name = name,
+                            timeout_in_seconds = timeout.as_secs_f32(),
+                            "initiate graceful shutdown",
+                        );
+
+                        let timeout_token = timeout_token.clone();
+                        let alive_connections = alive_connections.clone();
+                        let in_flight_requests = in_flight_requests.clone();
+                        let forced = forced.clone();
+                        crate::spawn::spawn_on(&runtime, "poem-shutdown-timeout", async move {
+                            tokio::time::sleep(timeout).await;
+                            // record what is still running before it is aborted
+                            *forced.lock() = Some((
+                                alive_connections.load(Ordering::Acquire),
+                                in_flight_requests
+                                    .as_deref()
+                                    .map(InFlightRequests::count_by_route)
+                                    .unwrap_or_default(),
+                            ));
+                            timeout_token.cancel();
+                        });
+                    } else {
+                        tracing::info!(name = name, "initiate graceful shutdown");
+                    }
+                    break;
+                },
+                (res, redirect) = accept(&mut acceptor, redirect_acceptor.as_mut()) => {
+                    if let Ok((socket, local_addr, remote_addr, scheme, extensions)) = res {
+                        alive_connections.fetch_add(1, Ordering::Release);
+
+                        let ep = match (&redirect_ep, redirect) {
+                            (Some(redirect_ep), true) => redirect_ep.clone(),
+                            _ => ep.clone(),
+                        };
+                        let alive_connections = alive_connections.clone();
+                        let notify = notify.clone();
+                        let timeout_token = timeout_token.clone();
+                        let server_graceful_shutdown_token = server_graceful_shutdown_token.clone();
+                        let server_graceful_shutdown_token_clone = server_graceful_shutdown_token.clone();
+                        let request_limits = request_limits.clone();
+                        let request_rejections = request_rejections.clone();
+                        let in_flight_requests = in_flight_requests.clone();
+
+                        let spawn_fut = AssertUnwindSafe(async move {
+                            let serve_connection = serve_connection(ConnectionOptions{
+                                socket,
+                                local_addr,
+                                remote_addr,
+                                scheme,
+                                extensions,
+                                ep,
+                                server_graceful_shutdown_token: server_graceful_shutdown_token.clone(),
+                                idle_connection_close_timeout: idle_timeout,
+                                http2_max_concurrent_streams,
+                                http2_max_pending_accept_reset_streams,
+                                http1_writev,
+                                request_limits,
+                                request_rejections,
+                                header_read_timeout,
+                                body_read_timeout,
+                                in_flight_requests,
+                            });
+
+                            if timeout.is_some() {
+                                tokio::select! {
+                                    _ = serve_connection => {}
+                                    _ = timeout_token.cancelled() => {}
+                                }
+                            } else {
+                               serve_connection.await;
+                            }
+                        });
+
+                        crate::spawn::spawn_on(&runtime, "poem-connection", async move {
+                            let result = spawn_fut.catch_unwind().await;
+
+                            if alive_connections.fetch_sub(1, Ordering::Acquire) == 1 {
+                                // notify only if shutdown is initiated, to prevent notification when server is active.
+                                // It's a valid state to have 0 alive connections when server is not shutting down.
+                                if server_graceful_shutdown_token_clone.is_cancelled() {
+                                    notify.notify_one();
+                                }
+                            }
+
+                            if let Err(err) = result {
+                                std::panic::resume_unwind(err);
+                            }
+                        });
+                    }
+                }
+            }
+        }
+
+        for hook in on_stop {
+            if let Err(err) = hook(info.clone()).await {
+                tracing::error!(name = name, error = %err, "failed to run the stop hook");
+            }
+        }
+
+        drop(acceptor);
+        drop(redirect_acceptor);
+        if alive_connections.load(Ordering::Acquire) > 0 {
+            tracing::info!(name = name, "wait for all connections to close.");
+            notify.notified().await;
+        }
+
+        if !task_handles.is_empty() {
+            tracing::info!(name = name, "wait for all background tasks to finish.");
+            let wait_tasks = futures_util::future::join_all(task_handles);
+            if timeout.is_some() {
+                tokio::select! {
+                    _ = wait_tasks => {}
+                    _ = timeout_token.cancelled() => {}
+                }
+            } else {
+                wait_tasks.await;
+            }
+        }
+
+        let (forced_connections, aborted_requests) = forced.lock().take().unwrap_or_default();
+        let report = ShutdownReport {
+            forced_connections,
+            aborted_requests,
+            drain_duration: shutdown_started.elapsed(),
+        };
+        tracing::info!(
+            name = name,
+            forced_connections = report.forced_connections,
+            aborted_requests = report.aborted_requests.values().sum::<usize>(),
+            drain_duration_in_seconds = report.drain_duration.as_secs_f32(),
+            "server stopped"
+        );
+        Ok(report)
+    }
+}
+
+/// Accepts a connection from the acceptor or the acceptor of the HTTPS
+/// redirects, and returns whether it is a redirect connection.
+async fn accept(
+    acceptor: &mut BoxAcceptor,
+    redirect_acceptor: Option<&mut BoxAcceptor>,
+) -> (
+    IoResult<(BoxIo, LocalAddr, RemoteAddr, Scheme, Extensions)>,
+    bool,
+) {
+    match redirect_acceptor {
+        Some(redirect_acceptor) => tokio::select! {
+            res = acceptor.accept_with_extensions() => (res, false),
+            res = redirect_acceptor.accept_with_extensions() => (res, true),
+        },
+        None => (acceptor.accept_with_extensions().await, false),
+    }
+}
+
+pin_project! {
+    struct ClosingInactiveConnection<T> {
+        #[pin]
+        inner: T,
+        #[pin]
+        alive: Arc<Notify>,
+        timeout: Duration,
+        stop_tx: oneshot::Sender<()>,
+    }
+}
+
+impl<T> AsyncRead for ClosingInactiveConnection<T>
+where
+    T: AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+
+        match this.inner.poll_read(cx, buf) {
+            Poll::Ready(Ok(())) => {
+                this.alive.notify_waiters();
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T> AsyncWrite for ClosingInactiveConnection<T>
+where
+    T: AsyncWrite,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = self.project();
+        this.alive.notify_waiters();
+        this.inner.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.project();
+        this.alive.notify_waiters();
+        this.inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.project();
+        this.alive.notify_waiters();
+        this.inner.poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = self.project();
+        this.alive.notify_waiters();
+        this.inner.poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+impl<T> ClosingInactiveConnection<T> {
+    fn new<F, Fut>(inner: T, timeout: Duration, mut f: F) -> ClosingInactiveConnection<T>
+    where
+        F: Send + FnMut() -> Fut + 'static,
+        Fut: Future + Send + 'static,
+    {
+        let alive = Arc::new(Notify::new());
+        let (stop_tx, stop_rx) = oneshot::channel();
+        crate::spawn::spawn("poem-idle-timeout", {
+            let alive = alive.clone();
+
+            async move {
+                let check_timeout = async {
+                    loop {
+                        match tokio::time::timeout(timeout, alive.notified()).await {
+                            Ok(()) => {}
+                            Err(_) => {
+                                f().await;
+                            }
+                        }
+                    }
+                };
+                tokio::select! {
+                    _ = stop_rx => {},
+                    _ = check_timeout => {}
+                }
+            }
+        });
+        Self {
+            inner,
+            alive,
+            timeout,
+            stop_tx,
+        }
+    }
+}
+
+struct ConnectionOptions<Io> {
+    socket: Io,
+    local_addr: LocalAddr,
+    remote_addr: RemoteAddr,
+    scheme: Scheme,
+    extensions: Extensions,
+    ep: Arc<dyn DynEndpoint<Output = Response>>,
+    server_graceful_shutdown_token: CancellationToken,
+    idle_connection_close_timeout: Option<Duration>,
+    http2_max_concurrent_streams: Option<u32>,
+    http2_max_pending_accept_reset_streams: Option<u32>,
+    http1_writev: Option<bool>,
+    request_limits: Option<RequestLimits>,
+    request_rejections: RequestRejections,
+    header_read_timeout: Option<Duration>,
+    body_read_timeout: Option<Duration>,
+    in_flight_requests: Option<Arc<InFlightRequests>>,
+}
+
+async fn serve_connection<Io>(
+    ConnectionOptions {
+        socket,
+        local_addr,
+        remote_addr,
+        scheme,
+        extensions,
+        ep,
+        server_graceful_shutdown_token,
+        idle_connection_close_timeout,
+        http2_max_concurrent_streams,
+        http2_max_pending_accept_reset_streams,
+        http1_writev,
+        request_limits,
+        request_rejections,
+        header_read_timeout,
+        body_read_timeout,
+        in_flight_requests,
+    }: ConnectionOptions<Io>,
+) where
+    Io: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let connection_shutdown_token = CancellationToken::new();
+    let switched = Arc::new(AtomicBool::new(false));
+
+    let service = hyper::service::service_fn({
+        let remote_addr = remote_addr.clone();
+        let server_graceful_shutdown_token = server_graceful_shutdown_token.clone();
+        let switched = switched.clone();
+
+        move |req: http::Request<Incoming>| {
+            let ep = ep.clone();
+            let local_addr = local_addr.clone();
+            let remote_addr = remote_addr.clone();
+            let scheme = scheme.clone();
+            let extensions = extensions.clone();
+            let switched = switched.clone();
+            let cancel_token = server_graceful_shutdown_token.child_token();
+            let in_flight = in_flight_requests
+                .as_ref()
+                .map(|requests| requests.register(req.uri().clone()));
+            async move {
+                let connect = req.method() == http::Method::CONNECT;
+                let mut req: Request = (req, local_addr, remote_addr, scheme).into();
+                req.extensions_mut().extend(extensions);
+                let _in_flight_guard = in_flight.map(|(in_flight, guard)| {
+                    req.extensions_mut().insert(in_flight);
+                    guard
+                });
+                req.extensions_mut()
+                    .insert(CancelToken::new(cancel_token.clone()));
+
+                // cancel the token if this future is dropped, which means the client has
+                // disconnected
+                let guard = cancel_token.drop_guard();
+                let resp = ep.get_response(req).await;
+                guard.disarm();
+                if resp.status() == http::StatusCode::SWITCHING_PROTOCOLS
+                    || (connect && resp.status().is_success())
+                {
+                    // the request guard passes the connection through after the response
+                    switched.store(true, Ordering::Release);
+                }
+                Ok::<http::Response<_>, Infallible>(resp.into())
+            }
+        }
+    });
+
+    let socket = RequestGuard::new(
+        socket,
+        request_limits.clone(),
+        (header_read_timeout, body_read_timeout),
+        request_rejections,
+        switched,
+    );
+    let socket = match idle_connection_close_timeout {
+        Some(timeout) => {
+            tokio_util::either::Either::Left(ClosingInactiveConnection::new(socket, timeout, {
+                let connection_shutdown_token = connection_shutdown_token.clone();
+
+                move || {
+                    let connection_shutdown_token = connection_shutdown_token.clone();
+                    async move {
+                        connection_shutdown_token.cancel();
+                    }
+                }
+            }))
+        }
+        None => tokio_util::either::Either::Right(socket),
+    };
+
+    let mut builder = auto::Builder::new(hyper_util::rt::TokioExecutor::new());
+    builder
+        .http2()
+        .max_concurrent_streams(http2_max_concurrent_streams)
+        .max_pending_accept_reset_streams(
+            http2_max_pending_accept_reset_streams.map(|x| x as usize),
+        );
+    if let Some(writev) = http1_writev {
+        builder.http1().writev(writev);
+    }
+    if let Some(limits) = &request_limits {
+        builder.http1().max_headers(limits.max_headers);
+        builder
+            .http2()
+            .max_header_list_size(limits.max_header_bytes.try_into().unwrap_or(u32::MAX));
+    }
+
+    let conn =
+        builder.serve_connection_with_upgrades(hyper_util::rt::TokioIo::new(socket), service);
+    futures_util::pin_mut!(conn);
+
+    tokio::select! {
+        _ = &mut conn => {
+            // Connection completed successfully.
+        },
+        _ = connection_shutdown_token.cancelled() => {
+            tracing::info!(remote_addr=%remote_addr, "closing connection due to inactivity");
+        }
+        _ = server_graceful_shutdown_token.cancelled() => {}
+    }
+
+    // Init graceful shutdown for connection
+    conn.as_mut().graceful_shutdown();
+    // Continue awaiting after graceful-shutdown is initiated to handle existed
+    // requests.
+    let _ = conn.await;
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::{handler, listener::TcpListener};
+
+    #[handler(internal)]
+    async fn thread_name() -> String {
+        std::thread::current()
+            .name()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    #[test]
+    fn runtime() {
+        let workers = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("poem-worker")
+            .enable_all()
+            .build()
+            .unwrap();
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let acceptor = TcpListener::bind("127.0.0.1:0")
+                .into_acceptor()
+                .await
+                .unwrap();
+            let addr = acceptor
+                .local_addr()
+                .remove(0)
+                .as_socket_addr()
+                .cloned()
+                .unwrap();
+            tokio::spawn(
+                Server::new_with_acceptor(acceptor)
+                    .runtime(workers.handle().clone())
+                    .run(thread_name),
+            );
+
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nhost: a\r\nconnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut resp = String::new();
+            stream.read_to_string(&mut resp).await.unwrap();
+            assert!(resp.ends_with("\r\n\r\npoem-worker"));
+        });
+    }
+
+    #[test]
+    fn run_current_thread() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        std::thread::spawn(move || {
+            Server::new(TcpListener::bind(addr)).run_current_thread(thread_name)
+        });
+
+        let mut resp = String::new();
+        for _ in 0..50 {
+            if let Ok(mut stream) = std::net::TcpStream::connect(addr) {
+                use std::io::{Read, Write};
+
+                stream
+                    .write_all(b"GET / HTTP/1.1\r\nhost: a\r\nconnection: close\r\n\r\n")
+                    .unwrap();
+                stream.read_to_string(&mut resp).unwrap();
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+}
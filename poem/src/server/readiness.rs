@@ -0,0 +1,167 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use http::StatusCode;
+
+use crate::{Endpoint, Request, Result};
+
+/// The readiness of the [`Server`](crate::Server), which is `true` after the
+/// server started and `false` once the shutdown is initiated.
+///
+/// It is an endpoint which responds `200 OK` if the server is ready and
+/// `503 Service Unavailable` otherwise, to be used as the readiness probe of
+/// Kubernetes. See
+/// [`Server::shutdown_on_sigterm`](crate::Server::shutdown_on_sigterm).
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use poem::{listener::TcpListener, Route, Server};
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let server =
+///     Server::new(TcpListener::bind("0.0.0.0:3000")).shutdown_on_sigterm(Duration::from_secs(5));
+/// let app = Route::new().at("/ready", server.readiness());
+/// server.run(app).await
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+#[derive(Debug, Clone, Default)]
+pub struct Readiness(Arc<AtomicBool>);
+
+impl Readiness {
+    /// Returns `true` if the server is ready.
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// Sets the readiness, for example to take the server out of the load
+    /// balancer while it is still running.
+    pub fn set_ready(&self, ready: bool) {
+        self.0.store(ready, Ordering::Release);
+    }
+}
+
+impl Endpoint for Readiness {
+    type Output = StatusCode;
+
+    async fn call(&self, _req: Request) -> Result<Self::Output> {
+        Ok(if self.is_ready() {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        })
+    }
+}
+
+/// Waits for `SIGTERM`, or Ctrl-C on the platforms without it.
+async fn sigterm() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "failed to listen for SIGTERM");
+                futures_util::future::pending::<()>().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    if let Err(err) = tokio::signal::ctrl_c().await {
+        tracing::error!(error = %err, "failed to listen for Ctrl-C");
+        futures_util::future::pending::<()>().await;
+    }
+}
+
+/// Waits for the signal, then marks the server as not ready and waits for the
+/// delay before the shutdown is initiated.
+pub(crate) async fn drain_after(
+    signal: impl Future<Output = ()>,
+    readiness: Readiness,
+    delay: Duration,
+    name: Option<&str>,
+) {
+    signal.await;
+    readiness.set_ready(false);
+    tracing::info!(
+        name = name,
+        delay_in_seconds = delay.as_secs_f32(),
+        "received the shutdown signal, wait for the load balancers",
+    );
+    tokio::time::sleep(delay).await;
+}
+
+pub(crate) async fn drain_after_sigterm(readiness: Readiness, delay: Duration, name: Option<&str>) {
+    drain_after(sigterm(), readiness, delay, name).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{listener::TcpListener, test::TestClient, Server};
+
+    #[tokio::test]
+    async fn readiness() {
+        let readiness = Readiness::default();
+        let cli = TestClient::new(readiness.clone());
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        readiness.set_ready(true);
+        cli.get("/").send().await.assert_status_is_ok();
+    }
+
+    #[tokio::test]
+    async fn drain() {
+        let readiness = Readiness::default();
+        readiness.set_ready(true);
+        let start = Instant::now();
+        drain_after(
+            async {},
+            readiness.clone(),
+            Duration::from_millis(100),
+            None,
+        )
+        .await;
+        assert!(!readiness.is_ready());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn server_readiness() {
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let server = Server::new(TcpListener::bind("127.0.0.1:0"))
+            .shutdown_on_sigterm(Duration::from_secs(1));
+        let readiness = server.readiness();
+        let handle = tokio::spawn(server.run_with_graceful_shutdown(
+            readiness.clone(),
+            async move {
+                let _ = rx.await;
+            },
+            None,
+        ));
+
+        while !readiness.is_ready() {
+            tokio::task::yield_now().await;
+        }
+        tx.send(()).unwrap();
+        handle.await.unwrap().unwrap();
+        assert!(!readiness.is_ready());
+    }
+}
@@ -0,0 +1,653 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures_util::{future::BoxFuture, FutureExt};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use tokio::{
+    io::{Error as IoError, Result as IoResult},
+    task::JoinHandle,
+};
+
+use super::ServerInfo;
+use crate::{
+    listener::{Acceptor, Listener},
+    Server,
+};
+
+/// A service registry, such as [`ConsulRegistry`] or [`EtcdRegistry`], where
+/// the server is registered when it starts and deregistered when it stops.
+///
+/// See [`Server::service_registry`].
+#[cfg_attr(docsrs, doc(cfg(feature = "service-registry")))]
+pub trait ServiceRegistry: Send + Sync + 'static {
+    /// Registers the service.
+    fn register<'a>(&'a self, registration: &'a ServiceRegistration)
+        -> BoxFuture<'a, IoResult<()>>;
+
+    /// Deregisters the service.
+    fn deregister<'a>(
+        &'a self,
+        registration: &'a ServiceRegistration,
+    ) -> BoxFuture<'a, IoResult<()>>;
+}
+
+/// A service instance to register in a [`ServiceRegistry`].
+///
+/// When it is registered by [`Server::service_registry`], the port defaults
+/// to the port of the first address the server is listening on, the address
+/// defaults to its IP unless it is unspecified (such as `0.0.0.0`), and the
+/// id defaults to `{name}-{address}-{port}`.
+#[cfg_attr(docsrs, doc(cfg(feature = "service-registry")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ServiceRegistration {
+    /// The id of the service instance.
+    pub id: Option<String>,
+    /// The name of the service.
+    pub name: String,
+    /// The address of the service instance.
+    pub address: Option<String>,
+    /// The port of the service instance.
+    pub port: Option<u16>,
+    /// The tags of the service instance.
+    pub tags: Vec<String>,
+    /// The path of the HTTP health check.
+    pub health_check: Option<String>,
+    /// The interval of the health check.
+    pub health_check_interval: Duration,
+}
+
+impl ServiceRegistration {
+    /// Create a `ServiceRegistration` with the name of the service.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            name: name.into(),
+            address: None,
+            port: None,
+            tags: Vec::new(),
+            health_check: None,
+            health_check_interval: Duration::from_secs(10),
+        }
+    }
+
+    /// Sets the id of the service instance.
+    #[must_use]
+    pub fn id(self, id: impl Into<String>) -> Self {
+        Self {
+            id: Some(id.into()),
+            ..self
+        }
+    }
+
+    /// Sets the address of the service instance, it should be set if the
+    /// server is listening on an unspecified address.
+    #[must_use]
+    pub fn address(self, address: impl Into<String>) -> Self {
+        Self {
+            address: Some(address.into()),
+            ..self
+        }
+    }
+
+    /// Sets the port of the service instance.
+    #[must_use]
+    pub fn port(self, port: u16) -> Self {
+        Self {
+            port: Some(port),
+            ..self
+        }
+    }
+
+    /// Appends a tag.
+    #[must_use]
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Sets the tags.
+    #[must_use]
+    pub fn tags(self, tags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            tags: tags.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Sets the path of the HTTP health check, such as `/health`.
+    #[must_use]
+    pub fn health_check(self, path: impl Into<String>) -> Self {
+        Self {
+            health_check: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// Sets the interval of the health check.
+    ///
+    /// Default is `10s`.
+    #[must_use]
+    pub fn health_check_interval(self, interval: Duration) -> Self {
+        Self {
+            health_check_interval: interval,
+            ..self
+        }
+    }
+
+    /// Fills the defaults with the addresses of the server.
+    fn resolve(&self, info: &ServerInfo) -> Self {
+        let mut registration = self.clone();
+        let addr = info
+            .local_addrs()
+            .iter()
+            .find_map(|addr| addr.as_socket_addr().copied());
+        if let Some(addr) = addr {
+            if registration.address.is_none() && !addr.ip().is_unspecified() {
+                registration.address = Some(addr.ip().to_string());
+            }
+            registration.port.get_or_insert(addr.port());
+        }
+        if registration.id.is_none() {
+            let mut id = registration.name.clone();
+            if let Some(address) = &registration.address {
+                id = format!("{id}-{address}");
+            }
+            if let Some(port) = registration.port {
+                id = format!("{id}-{port}");
+            }
+            registration.id = Some(id);
+        }
+        registration
+    }
+
+    fn service_id(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.name)
+    }
+
+    fn health_check_url(&self) -> Option<String> {
+        let path = self.health_check.as_deref()?;
+        let host = match self.address.as_deref() {
+            Some(address) if address.contains(':') => format!("[{address}]"),
+            Some(address) => address.to_string(),
+            None => "127.0.0.1".to_string(),
+        };
+        let port = self.port.map(|port| format!(":{port}")).unwrap_or_default();
+        let slash = if path.starts_with('/') { "" } else { "/" };
+        Some(format!("http://{host}{port}{slash}{path}"))
+    }
+}
+
+fn go_duration(duration: Duration) -> String {
+    format!("{}s", duration.as_secs().max(1))
+}
+
+async fn send(req: reqwest::RequestBuilder) -> IoResult<reqwest::Response> {
+    req.send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(IoError::other)
+}
+
+/// A [`ServiceRegistry`] which registers the service in the local
+/// [Consul](https://www.consul.io) agent.
+///
+/// The registered service has an HTTP check of the
+/// [`health_check`](ServiceRegistration::health_check) path, if it is
+/// specified, and the address of the check defaults to `127.0.0.1` if the
+/// address of the service is not specified.
+///
+/// # Example
+///
+/// ```no_run
+/// use poem::{listener::TcpListener, ConsulRegistry, Route, Server, ServiceRegistration};
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// Server::new(TcpListener::bind("0.0.0.0:3000"))
+///     .service_registry(
+///         ConsulRegistry::new("http://127.0.0.1:8500"),
+///         ServiceRegistration::new("users")
+///             .address("10.0.0.1")
+///             .health_check("/health")
+///             .tag("v1"),
+///     )
+///     .run(Route::new())
+///     .await
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "service-registry")))]
+pub struct ConsulRegistry {
+    client: reqwest::Client,
+    endpoint: String,
+    token: Option<String>,
+    deregister_critical_after: Option<Duration>,
+}
+
+impl ConsulRegistry {
+    /// Create a `ConsulRegistry` with the endpoint of the agent, such as
+    /// `http://127.0.0.1:8500`.
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            token: None,
+            deregister_critical_after: None,
+        }
+    }
+
+    /// Sets the ACL token.
+    #[must_use]
+    pub fn token(self, token: impl Into<String>) -> Self {
+        Self {
+            token: Some(token.into()),
+            ..self
+        }
+    }
+
+    /// Sets the time after which the service is deregistered by Consul if
+    /// its health check stays critical.
+    #[must_use]
+    pub fn deregister_critical_after(self, timeout: Duration) -> Self {
+        Self {
+            deregister_critical_after: Some(timeout),
+            ..self
+        }
+    }
+
+    fn put(&self, path: &str) -> reqwest::RequestBuilder {
+        let req = self.client.put(format!("{}{path}", self.endpoint));
+        match &self.token {
+            Some(token) => req.header("X-Consul-Token", token),
+            None => req,
+        }
+    }
+}
+
+impl ServiceRegistry for ConsulRegistry {
+    fn register<'a>(
+        &'a self,
+        registration: &'a ServiceRegistration,
+    ) -> BoxFuture<'a, IoResult<()>> {
+        async move {
+            let mut service = json!({
+                "ID": registration.service_id(),
+                "Name": registration.name,
+                "Tags": registration.tags,
+            });
+            if let Some(address) = &registration.address {
+                service["Address"] = json!(address);
+            }
+            if let Some(port) = registration.port {
+                service["Port"] = json!(port);
+            }
+            if let Some(url) = registration.health_check_url() {
+                let mut check = json!({
+                    "HTTP": url,
+                    "Interval": go_duration(registration.health_check_interval),
+                });
+                if let Some(timeout) = self.deregister_critical_after {
+                    check["DeregisterCriticalServiceAfter"] = json!(go_duration(timeout));
+                }
+                service["Check"] = check;
+            }
+            send(self.put("/v1/agent/service/register").json(&service)).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn deregister<'a>(
+        &'a self,
+        registration: &'a ServiceRegistration,
+    ) -> BoxFuture<'a, IoResult<()>> {
+        async move {
+            let path = format!("/v1/agent/service/deregister/{}", registration.service_id());
+            send(self.put(&path)).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// A [`ServiceRegistry`] which registers the service in
+/// [etcd](https://etcd.io) with its JSON gateway.
+///
+/// The service is stored as JSON at `{prefix}/{name}/{id}` with a lease
+/// which is kept alive while the server is running, so the key is removed
+/// if the server exits without deregistering.
+///
+/// # Example
+///
+/// ```no_run
+/// use poem::{listener::TcpListener, EtcdRegistry, Route, Server, ServiceRegistration};
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// Server::new(TcpListener::bind("0.0.0.0:3000"))
+///     .service_registry(
+///         EtcdRegistry::new("http://127.0.0.1:2379"),
+///         ServiceRegistration::new("users").address("10.0.0.1"),
+///     )
+///     .run(Route::new())
+///     .await
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "service-registry")))]
+pub struct EtcdRegistry {
+    client: reqwest::Client,
+    endpoint: String,
+    prefix: String,
+    ttl: Duration,
+    leases: Mutex<HashMap<String, (Value, JoinHandle<()>)>>,
+}
+
+impl EtcdRegistry {
+    /// Create an `EtcdRegistry` with the endpoint of etcd, such as
+    /// `http://127.0.0.1:2379`.
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            prefix: "/services".to_string(),
+            ttl: Duration::from_secs(10),
+            leases: Default::default(),
+        }
+    }
+
+    /// Sets the prefix of the keys.
+    ///
+    /// Default is `/services`.
+    #[must_use]
+    pub fn prefix(self, prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into().trim_end_matches('/').to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the TTL of the lease, it is kept alive every third of the TTL.
+    ///
+    /// Default is `10s`.
+    #[must_use]
+    pub fn ttl(self, ttl: Duration) -> Self {
+        Self {
+            ttl: ttl.max(Duration::from_secs(1)),
+            ..self
+        }
+    }
+
+    fn key(&self, registration: &ServiceRegistration) -> String {
+        format!(
+            "{}/{}/{}",
+            self.prefix,
+            registration.name,
+            registration.service_id()
+        )
+    }
+
+    async fn post(&self, path: &str, body: Value) -> IoResult<Value> {
+        send(
+            self.client
+                .post(format!("{}{path}", self.endpoint))
+                .json(&body),
+        )
+        .await?
+        .json()
+        .await
+        .map_err(IoError::other)
+    }
+}
+
+impl ServiceRegistry for EtcdRegistry {
+    fn register<'a>(
+        &'a self,
+        registration: &'a ServiceRegistration,
+    ) -> BoxFuture<'a, IoResult<()>> {
+        async move {
+            let grant = self
+                .post("/v3/lease/grant", json!({ "TTL": self.ttl.as_secs() }))
+                .await?;
+            let lease = grant
+                .get("ID")
+                .cloned()
+                .ok_or_else(|| IoError::other("etcd returned no lease id"))?;
+
+            let value = json!({
+                "id": registration.service_id(),
+                "name": registration.name,
+                "address": registration.address,
+                "port": registration.port,
+                "tags": registration.tags,
+                "health_check": registration.health_check_url(),
+            });
+            self.post(
+                "/v3/kv/put",
+                json!({
+                    "key": STANDARD.encode(self.key(registration)),
+                    "value": STANDARD.encode(value.to_string()),
+                    "lease": lease,
+                }),
+            )
+            .await?;
+
+            let client = self.client.clone();
+            let url = format!("{}/v3/lease/keepalive", self.endpoint);
+            let body = json!({ "ID": lease });
+            let interval = self.ttl / 3;
+            let keepalive = crate::spawn::spawn("poem-registry-keepalive", async move {
+                loop {
+                    tokio::time::sleep(interval).await;
+                    if let Err(err) = send(client.post(&url).json(&body)).await {
+                        tracing::warn!(error = %err, "failed to keep alive the etcd lease");
+                    }
+                }
+            });
+            if let Some((_, keepalive)) = self
+                .leases
+                .lock()
+                .insert(registration.service_id().to_string(), (lease, keepalive))
+            {
+                keepalive.abort();
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn deregister<'a>(
+        &'a self,
+        registration: &'a ServiceRegistration,
+    ) -> BoxFuture<'a, IoResult<()>> {
+        async move {
+            let Some((lease, keepalive)) = self.leases.lock().remove(registration.service_id())
+            else {
+                return Ok(());
+            };
+            keepalive.abort();
+            // the keys attached to the lease are deleted
+            self.post("/v3/lease/revoke", json!({ "ID": lease }))
+                .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+impl<L, A> Server<L, A>
+where
+    L: Listener,
+    L::Acceptor: 'static,
+    A: Acceptor + 'static,
+{
+    /// Registers the service in the registry when the server starts, and
+    /// deregisters it when the graceful shutdown is initiated.
+    ///
+    /// See [`ConsulRegistry`] and [`EtcdRegistry`] for examples.
+    #[cfg_attr(docsrs, doc(cfg(feature = "service-registry")))]
+    #[must_use]
+    pub fn service_registry(
+        self,
+        registry: impl ServiceRegistry,
+        registration: ServiceRegistration,
+    ) -> Self {
+        let registry = Arc::new(registry);
+        let registration = Arc::new(registration);
+        let (start_registry, start_registration) = (registry.clone(), registration.clone());
+        self.on_start(move |info| async move {
+            start_registry
+                .register(&start_registration.resolve(&info))
+                .await
+        })
+        .on_stop(move |info| async move { registry.deregister(&registration.resolve(&info)).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::{
+        handler,
+        http::Method,
+        listener::{Acceptor, Listener, TcpListener},
+        web::{Data, Json},
+        EndpointExt, Request,
+    };
+
+    type Captured = (Method, String, Value);
+
+    #[handler(internal)]
+    fn capture(
+        req: &Request,
+        body: String,
+        tx: Data<&mpsc::UnboundedSender<Captured>>,
+    ) -> Json<Value> {
+        let body = serde_json::from_str(&body).unwrap_or(Value::Null);
+        tx.send((req.method().clone(), req.uri().path().to_string(), body))
+            .unwrap();
+        Json(json!({ "ID": "7587" }))
+    }
+
+    async fn registry_server() -> (String, mpsc::UnboundedReceiver<Captured>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor.local_addr()[0].as_socket_addr().cloned().unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(capture.data(tx)));
+        (format!("http://{addr}"), rx)
+    }
+
+    fn server_info(addr: &str) -> ServerInfo {
+        ServerInfo {
+            name: None,
+            local_addrs: vec![crate::web::LocalAddr(
+                addr.parse::<std::net::SocketAddr>().unwrap().into(),
+            )],
+        }
+    }
+
+    #[test]
+    fn resolve() {
+        let registration = ServiceRegistration::new("users").resolve(&server_info("10.0.0.1:3000"));
+        assert_eq!(registration.id.as_deref(), Some("users-10.0.0.1-3000"));
+        assert_eq!(registration.address.as_deref(), Some("10.0.0.1"));
+        assert_eq!(registration.port, Some(3000));
+
+        let registration = ServiceRegistration::new("users")
+            .health_check("health")
+            .resolve(&server_info("0.0.0.0:3000"));
+        assert_eq!(registration.id.as_deref(), Some("users-3000"));
+        assert_eq!(registration.address, None);
+        assert_eq!(
+            registration.health_check_url().as_deref(),
+            Some("http://127.0.0.1:3000/health")
+        );
+
+        let registration = ServiceRegistration::new("users")
+            .id("a")
+            .address("::1")
+            .port(80)
+            .health_check("/health")
+            .resolve(&server_info("10.0.0.1:3000"));
+        assert_eq!(registration.id.as_deref(), Some("a"));
+        assert_eq!(registration.port, Some(80));
+        assert_eq!(
+            registration.health_check_url().as_deref(),
+            Some("http://[::1]:80/health")
+        );
+    }
+
+    #[tokio::test]
+    async fn consul() {
+        let (endpoint, mut rx) = registry_server().await;
+        let registry = ConsulRegistry::new(endpoint)
+            .token("secret")
+            .deregister_critical_after(Duration::from_secs(60));
+        let registration = ServiceRegistration::new("users")
+            .tags(["a", "b"])
+            .health_check("/health")
+            .resolve(&server_info("10.0.0.1:3000"));
+
+        registry.register(&registration).await.unwrap();
+        let (method, path, body) = rx.recv().await.unwrap();
+        assert_eq!(method, Method::PUT);
+        assert_eq!(path, "/v1/agent/service/register");
+        assert_eq!(
+            body,
+            json!({
+                "ID": "users-10.0.0.1-3000",
+                "Name": "users",
+                "Tags": ["a", "b"],
+                "Address": "10.0.0.1",
+                "Port": 3000,
+                "Check": {
+                    "HTTP": "http://10.0.0.1:3000/health",
+                    "Interval": "10s",
+                    "DeregisterCriticalServiceAfter": "60s",
+                },
+            })
+        );
+
+        registry.deregister(&registration).await.unwrap();
+        let (method, path, _) = rx.recv().await.unwrap();
+        assert_eq!(method, Method::PUT);
+        assert_eq!(path, "/v1/agent/service/deregister/users-10.0.0.1-3000");
+    }
+
+    #[tokio::test]
+    async fn etcd() {
+        let (endpoint, mut rx) = registry_server().await;
+        let registry = EtcdRegistry::new(endpoint).ttl(Duration::from_secs(3));
+        let registration = ServiceRegistration::new("users").resolve(&server_info("10.0.0.1:3000"));
+
+        registry.register(&registration).await.unwrap();
+        let (_, path, body) = rx.recv().await.unwrap();
+        assert_eq!(path, "/v3/lease/grant");
+        assert_eq!(body, json!({ "TTL": 3 }));
+
+        let (_, path, body) = rx.recv().await.unwrap();
+        assert_eq!(path, "/v3/kv/put");
+        assert_eq!(body["lease"], "7587");
+        let key = STANDARD.decode(body["key"].as_str().unwrap()).unwrap();
+        assert_eq!(key, b"/services/users/users-10.0.0.1-3000");
+        let value: Value =
+            serde_json::from_slice(&STANDARD.decode(body["value"].as_str().unwrap()).unwrap())
+                .unwrap();
+        assert_eq!(value["address"], "10.0.0.1");
+        assert_eq!(value["port"], 3000);
+
+        let (_, path, body) = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(path, "/v3/lease/keepalive");
+        assert_eq!(body, json!({ "ID": "7587" }));
+
+        registry.deregister(&registration).await.unwrap();
+        let (_, path, body) = rx.recv().await.unwrap();
+        assert_eq!(path, "/v3/lease/revoke");
+        assert_eq!(body, json!({ "ID": "7587" }));
+    }
+}
@@ -0,0 +1,591 @@
+use std::{
+    convert::Infallible,
+    future::Future,
+    io::Error as IoError,
+    net::SocketAddr,
+    num::NonZeroUsize,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc as std_mpsc, Arc,
+    },
+    task::{Context, Poll},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use futures_util::future::BoxFuture;
+use http::uri::Scheme;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult},
+    net::{TcpStream, ToSocketAddrs},
+    sync::mpsc,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    listener::{Acceptor, Listener, TcpAcceptor, TcpListener},
+    web::{LocalAddr, RemoteAddr},
+    IntoEndpoint, Server,
+};
+
+type Configure = Arc<
+    dyn Fn(Server<Infallible, ShardAcceptor>) -> Server<Infallible, ShardAcceptor> + Send + Sync,
+>;
+
+/// An HTTP server which runs a shard per core, each with its own
+/// current-thread runtime, accept loop and endpoint.
+///
+/// Every shard binds its own listening socket to the same address with
+/// `SO_REUSEPORT`, so the kernel distributes the connections between them
+/// and a connection is served by the shard that accepted it, without the
+/// contention between the cores of a shared runtime.
+///
+/// Because the kernel distributes the connections by hashing, some shards may
+/// receive much more connections than the others. When a shard serves more
+/// than [`max_imbalance`](ShardedServer::max_imbalance) connections above
+/// the least loaded shard, it hands off the new connections to that shard.
+///
+/// `SO_REUSEPORT` is not supported on Windows, where only one shard can be
+/// used.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use poem::{handler, listener::TcpListener, Route, ShardedServer};
+///
+/// #[handler]
+/// fn hello() -> &'static str {
+///     "hello"
+/// }
+///
+/// ShardedServer::new(TcpListener::bind("0.0.0.0:3000"))
+///     .shards(4)
+///     .configure(|server| server.idle_timeout(Duration::from_secs(60)))
+///     .run(|| Route::new().at("/", hello))
+///     .unwrap();
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+pub struct ShardedServer<T> {
+    listener: TcpListener<T>,
+    shards: usize,
+    max_imbalance: Option<usize>,
+    configure: Configure,
+}
+
+impl<T> ShardedServer<T>
+where
+    T: ToSocketAddrs + Send + 'static,
+{
+    /// Use the specified listener to create a sharded HTTP server.
+    pub fn new(listener: TcpListener<T>) -> Self {
+        Self {
+            listener,
+            shards: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            max_imbalance: Some(64),
+            configure: Arc::new(|server| server),
+        }
+    }
+
+    /// Sets the number of the shards.
+    ///
+    /// Default is the number of the available cores.
+    #[must_use]
+    pub fn shards(self, shards: usize) -> Self {
+        Self {
+            shards: shards.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of the connections a shard serves above the
+    /// least loaded shard, before it hands off the new connections to that
+    /// shard.
+    ///
+    /// Default is `64`. Passing `None` disables the handoff.
+    #[must_use]
+    pub fn max_imbalance(self, max: impl Into<Option<usize>>) -> Self {
+        Self {
+            max_imbalance: max.into(),
+            ..self
+        }
+    }
+
+    /// Configures the [`Server`] of each shard, such as the timeouts and the
+    /// hooks.
+    #[must_use]
+    pub fn configure<F>(self, f: F) -> Self
+    where
+        F: Fn(Server<Infallible, ShardAcceptor>) -> Server<Infallible, ShardAcceptor>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self {
+            configure: Arc::new(f),
+            ..self
+        }
+    }
+
+    /// Run this server, blocking the current thread until all the shards
+    /// stop.
+    ///
+    /// The endpoint of each shard is created by calling `f` on the thread of
+    /// the shard.
+    pub fn run<F, E>(self, f: F) -> IoResult<()>
+    where
+        F: Fn() -> E + Send + Sync + 'static,
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.run_with_graceful_shutdown(f, futures_util::future::pending(), None)
+    }
+
+    /// Run this server and a signal to initiate graceful shutdown of all the
+    /// shards.
+    ///
+    /// If a shard fails, the other shards are shut down, and the first error
+    /// is returned.
+    pub fn run_with_graceful_shutdown<F, E>(
+        self,
+        f: F,
+        signal: impl Future<Output = ()> + Send + 'static,
+        timeout: Option<Duration>,
+    ) -> IoResult<()>
+    where
+        F: Fn() -> E + Send + Sync + 'static,
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        let ShardedServer {
+            listener,
+            shards,
+            max_imbalance,
+            configure,
+        } = self;
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            (0..shards).map(|_| mpsc::unbounded_channel()).unzip();
+        let balancer = Arc::new(Balancer {
+            loads: (0..shards).map(|_| AtomicUsize::new(0)).collect(),
+            handoff: senders,
+            max_imbalance,
+        });
+        let token = CancellationToken::new();
+        let f = Arc::new(f);
+        let listener = listener.reuse_port(shards > 1);
+        let mut receivers = receivers.into_iter();
+        let mut shard = |index| Shard {
+            index,
+            balancer: balancer.clone(),
+            handoff: receivers.next().expect("a receiver per shard"),
+            token: token.clone(),
+            configure: configure.clone(),
+            f: f.clone(),
+            timeout,
+        };
+
+        // the first shard resolves the address, and the other shards bind to
+        // it, so they share the port even if it is assigned by the system
+        let (bound_tx, bound_rx) = std_mpsc::channel();
+        let template = listener.with_addr(());
+        let first = shard(0).spawn(listener, Some(bound_tx), Some(Box::pin(signal)))?;
+        let Ok(Some(addr)) = bound_rx.recv() else {
+            return join(first);
+        };
+
+        let mut handles = vec![first];
+        for index in 1..shards {
+            match shard(index).spawn(template.with_addr(addr), None, None) {
+                Ok(handle) => handles.push(handle),
+                Err(err) => {
+                    token.cancel();
+                    for handle in handles {
+                        let _ = join(handle);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        // wait for all the shards, and return the first error
+        let mut res = Ok(());
+        for handle in handles {
+            let shard_res = join(handle);
+            if res.is_ok() {
+                res = shard_res;
+            }
+        }
+        res
+    }
+}
+
+fn join(handle: JoinHandle<IoResult<()>>) -> IoResult<()> {
+    handle
+        .join()
+        .unwrap_or_else(|_| Err(IoError::other("shard panicked")))
+}
+
+struct Shard<F> {
+    index: usize,
+    balancer: Arc<Balancer>,
+    handoff: mpsc::UnboundedReceiver<Handoff>,
+    token: CancellationToken,
+    configure: Configure,
+    f: Arc<F>,
+    timeout: Option<Duration>,
+}
+
+impl<F, E> Shard<F>
+where
+    F: Fn() -> E + Send + Sync + 'static,
+    E: IntoEndpoint,
+    E::Endpoint: 'static,
+{
+    fn spawn<L>(
+        self,
+        listener: L,
+        bound: Option<std_mpsc::Sender<Option<SocketAddr>>>,
+        signal: Option<BoxFuture<'static, ()>>,
+    ) -> IoResult<JoinHandle<IoResult<()>>>
+    where
+        L: Listener<Acceptor = TcpAcceptor> + Send + 'static,
+    {
+        thread::Builder::new()
+            .name(format!("poem-shard-{}", self.index))
+            .spawn(move || {
+                // stops the other shards if this shard fails or panics
+                let _guard = self.token.clone().drop_guard();
+                let rt = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()?;
+                rt.block_on(self.run(listener, bound, signal))
+            })
+    }
+
+    async fn run<L>(
+        self,
+        listener: L,
+        bound: Option<std_mpsc::Sender<Option<SocketAddr>>>,
+        signal: Option<BoxFuture<'static, ()>>,
+    ) -> IoResult<()>
+    where
+        L: Listener<Acceptor = TcpAcceptor>,
+    {
+        let acceptor = listener.into_acceptor().await;
+        if let Some(bound) = bound {
+            let addr = acceptor.as_ref().ok().and_then(|acceptor| {
+                acceptor
+                    .local_addr()
+                    .first()
+                    .and_then(|addr| addr.as_socket_addr().copied())
+            });
+            let _ = bound.send(addr);
+        }
+        let acceptor = acceptor?;
+
+        if let Some(signal) = signal {
+            let token = self.token.clone();
+            crate::spawn::spawn("poem-shard-signal", async move {
+                signal.await;
+                token.cancel();
+            });
+        }
+
+        let local_addr = acceptor.local_addr().remove(0);
+        let acceptor = ShardAcceptor {
+            index: self.index,
+            inner: acceptor,
+            local_addr,
+            balancer: self.balancer,
+            handoff: self.handoff,
+        };
+        (self.configure)(Server::new_with_acceptor(acceptor))
+            .run_with_graceful_shutdown((self.f)(), self.token.cancelled(), self.timeout)
+            .await
+            .map(|_| ())
+    }
+}
+
+/// The loads of the shards, and the channels to hand off the connections.
+struct Balancer {
+    loads: Vec<AtomicUsize>,
+    handoff: Vec<mpsc::UnboundedSender<Handoff>>,
+    max_imbalance: Option<usize>,
+}
+
+impl Balancer {
+    fn acquire(self: &Arc<Self>, index: usize) -> LoadGuard {
+        self.loads[index].fetch_add(1, Ordering::Relaxed);
+        LoadGuard {
+            balancer: self.clone(),
+            index,
+        }
+    }
+
+    /// Returns the least loaded shard if the shard serves more than
+    /// `max_imbalance` connections above it.
+    fn target(&self, index: usize) -> Option<usize> {
+        let max_imbalance = self.max_imbalance?;
+        let load = self.loads[index].load(Ordering::Relaxed);
+        let (target, min) = self
+            .loads
+            .iter()
+            .map(|load| load.load(Ordering::Relaxed))
+            .enumerate()
+            .min_by_key(|(_, load)| *load)?;
+        (load > min + max_imbalance).then_some(target)
+    }
+}
+
+/// Decrements the load of the shard when the connection is closed.
+struct LoadGuard {
+    balancer: Arc<Balancer>,
+    index: usize,
+}
+
+impl Drop for LoadGuard {
+    fn drop(&mut self) {
+        self.balancer.loads[self.index].fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A connection handed off to another shard.
+struct Handoff {
+    stream: std::net::TcpStream,
+    remote_addr: RemoteAddr,
+    guard: LoadGuard,
+}
+
+/// An acceptor of a shard of the [`ShardedServer`], which also accepts the
+/// connections handed off by the other shards.
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+pub struct ShardAcceptor {
+    index: usize,
+    inner: TcpAcceptor,
+    local_addr: LocalAddr,
+    balancer: Arc<Balancer>,
+    handoff: mpsc::UnboundedReceiver<Handoff>,
+}
+
+impl ShardAcceptor {
+    /// Returns the index of the shard.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Hands off the connection to the target shard, and returns it if the
+    /// shard has been stopped.
+    fn hand_off(
+        &self,
+        target: usize,
+        stream: TcpStream,
+        remote_addr: RemoteAddr,
+    ) -> IoResult<Option<(TcpStream, RemoteAddr)>> {
+        let handoff = Handoff {
+            stream: stream.into_std()?,
+            remote_addr,
+            guard: self.balancer.acquire(target),
+        };
+        match self.balancer.handoff[target].send(handoff) {
+            Ok(()) => Ok(None),
+            Err(err) => Ok(Some((
+                TcpStream::from_std(err.0.stream)?,
+                err.0.remote_addr,
+            ))),
+        }
+    }
+}
+
+impl Acceptor for ShardAcceptor {
+    type Io = ShardStream;
+
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        vec![self.local_addr.clone()]
+    }
+
+    async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        loop {
+            let (stream, remote_addr, guard) = tokio::select! {
+                Some(handoff) = self.handoff.recv() => {
+                    (TcpStream::from_std(handoff.stream)?, handoff.remote_addr, handoff.guard)
+                }
+                res = self.inner.accept() => {
+                    let (mut stream, _, mut remote_addr, _) = res?;
+                    match self.balancer.target(self.index) {
+                        Some(target) if target != self.index => {
+                            match self.hand_off(target, stream, remote_addr)? {
+                                Some(res) => (stream, remote_addr) = res,
+                                None => continue,
+                            }
+                        }
+                        _ => {}
+                    }
+                    (stream, remote_addr, self.balancer.acquire(self.index))
+                }
+            };
+            let stream = ShardStream {
+                inner: stream,
+                _guard: guard,
+            };
+            return Ok((stream, self.local_addr.clone(), remote_addr, Scheme::HTTP));
+        }
+    }
+}
+
+/// A connection accepted by a [`ShardAcceptor`].
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+pub struct ShardStream {
+    inner: TcpStream,
+    _guard: LoadGuard,
+}
+
+impl AsyncRead for ShardStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ShardStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        io::{ErrorKind, Read, Write},
+    };
+
+    use super::*;
+    use crate::handler;
+
+    #[handler(internal)]
+    async fn thread_name() -> String {
+        thread::current().name().unwrap_or_default().to_string()
+    }
+
+    /// Sends a request on the keep-alive connection, and returns the body.
+    fn get(stream: &mut std::net::TcpStream) -> String {
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: a\r\n\r\n")
+            .unwrap();
+        let mut resp = Vec::new();
+        let mut buf = [0; 1024];
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            assert!(n > 0);
+            resp.extend_from_slice(&buf[..n]);
+            let resp = String::from_utf8_lossy(&resp);
+            if let Some((head, body)) = resp.split_once("\r\n\r\n") {
+                let len = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length: "))
+                    .and_then(|len| len.parse::<usize>().ok())
+                    .unwrap();
+                if body.len() == len {
+                    return body.to_string();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn balance() {
+        let (addr_tx, addr_rx) = std_mpsc::channel();
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = thread::spawn(move || {
+            ShardedServer::new(TcpListener::bind("127.0.0.1:0"))
+                .shards(2)
+                .max_imbalance(0)
+                .configure(move |server| {
+                    let addr_tx = addr_tx.clone();
+                    server.on_start(move |info| async move {
+                        let _ = addr_tx.send(info.local_addrs()[0].clone());
+                        Ok(())
+                    })
+                })
+                .run_with_graceful_shutdown(
+                    || thread_name,
+                    async move {
+                        let _ = stop_rx.await;
+                    },
+                    None,
+                )
+        });
+
+        let addr = addr_rx.recv().unwrap();
+        assert_eq!(addr_rx.recv().unwrap(), addr);
+        let addr = *addr.as_socket_addr().unwrap();
+
+        // the connections are kept alive, so each shard serves two of them
+        let mut streams = (0..4)
+            .map(|_| std::net::TcpStream::connect(addr).unwrap())
+            .collect::<Vec<_>>();
+        let mut shards = HashMap::<_, usize>::new();
+        for stream in &mut streams {
+            *shards.entry(get(stream)).or_default() += 1;
+        }
+        assert_eq!(
+            shards,
+            HashMap::from([
+                ("poem-shard-0".to_string(), 2),
+                ("poem-shard-1".to_string(), 2)
+            ])
+        );
+
+        // a handed off connection is still served by the same shard
+        for stream in &mut streams {
+            let shard = get(stream);
+            assert_eq!(get(stream), shard);
+        }
+
+        drop(streams);
+        stop_tx.send(()).unwrap();
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn bind_error() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // the port is in use without `SO_REUSEPORT`
+        let err = ShardedServer::new(TcpListener::bind(addr))
+            .shards(2)
+            .run(|| thread_name)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AddrInUse);
+    }
+}
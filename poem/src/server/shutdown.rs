@@ -0,0 +1,222 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use http::Uri;
+use parking_lot::Mutex;
+use serde::Serialize;
+
+/// The report of the graceful shutdown of the [`Server`](crate::Server).
+///
+/// See [`Server::run_with_graceful_shutdown`](crate::Server::run_with_graceful_shutdown).
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShutdownReport {
+    pub(crate) forced_connections: usize,
+    pub(crate) aborted_requests: BTreeMap<String, usize>,
+    pub(crate) drain_duration: Duration,
+}
+
+impl ShutdownReport {
+    /// Returns the number of the connections which were still open when the
+    /// shutdown timeout elapsed, and were closed forcibly.
+    pub fn forced_connections(&self) -> usize {
+        self.forced_connections
+    }
+
+    /// Returns the number of the in-flight requests aborted by the shutdown
+    /// timeout, keyed by the matched route pattern, or by the request path if
+    /// no route has been matched.
+    pub fn aborted_requests(&self) -> &BTreeMap<String, usize> {
+        &self.aborted_requests
+    }
+
+    /// Returns the time from initiating the graceful shutdown until all the
+    /// connections are closed and all the background tasks are finished.
+    pub fn drain_duration(&self) -> Duration {
+        self.drain_duration
+    }
+}
+
+/// The requests being handled by the server.
+#[derive(Default)]
+pub(crate) struct InFlightRequests {
+    next_id: AtomicU64,
+    requests: Mutex<HashMap<u64, InFlightRequest>>,
+}
+
+impl InFlightRequests {
+    /// Registers a request, it is removed when the returned guard is dropped.
+    pub(crate) fn register(self: &Arc<Self>, uri: Uri) -> (InFlightRequest, InFlightGuard) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = InFlightRequest(Arc::new(InFlightRequestInner {
+            uri,
+            route: Mutex::new(None),
+        }));
+        self.requests.lock().insert(id, request.clone());
+        (
+            request,
+            InFlightGuard {
+                requests: self.clone(),
+                id,
+            },
+        )
+    }
+
+    /// Returns the number of the requests per route.
+    pub(crate) fn count_by_route(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for request in self.requests.lock().values() {
+            let key = match &*request.0.route.lock() {
+                Some(route) => route.to_string(),
+                None => request.0.uri.path().to_string(),
+            };
+            *counts.entry(key).or_default() += 1;
+        }
+        counts
+    }
+}
+
+struct InFlightRequestInner {
+    uri: Uri,
+    route: Mutex<Option<Arc<str>>>,
+}
+
+/// A request being handled, stored in the request extensions so that the
+/// router can record the matched route.
+#[derive(Clone)]
+pub(crate) struct InFlightRequest(Arc<InFlightRequestInner>);
+
+impl InFlightRequest {
+    pub(crate) fn set_route(&self, route: Arc<str>) {
+        *self.0.route.lock() = Some(route);
+    }
+}
+
+pub(crate) struct InFlightGuard {
+    requests: Arc<InFlightRequests>,
+    id: u64,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.requests.requests.lock().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::oneshot,
+        task::JoinHandle,
+    };
+
+    use super::*;
+    use crate::{
+        endpoint::make_sync,
+        handler,
+        listener::{Acceptor, Listener, TcpListener},
+        IntoEndpoint, Route, Server,
+    };
+
+    async fn start<E>(
+        ep: E,
+        timeout: Duration,
+    ) -> (
+        SocketAddr,
+        oneshot::Sender<()>,
+        JoinHandle<std::io::Result<ShutdownReport>>,
+    )
+    where
+        E: IntoEndpoint + Send + 'static,
+        E::Endpoint: 'static,
+    {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor
+            .local_addr()
+            .remove(0)
+            .as_socket_addr()
+            .cloned()
+            .unwrap();
+        let (tx, rx) = oneshot::channel::<()>();
+        let handle = tokio::spawn(
+            Server::new_with_acceptor(acceptor).run_with_graceful_shutdown(
+                ep,
+                async move {
+                    let _ = rx.await;
+                },
+                Some(timeout),
+            ),
+        );
+        (addr, tx, handle)
+    }
+
+    async fn get(addr: SocketAddr, path: &'static str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                format!("GET {path} HTTP/1.1\r\nhost: a\r\nconnection: close\r\n\r\n").as_bytes(),
+            )
+            .await
+            .unwrap();
+        let mut resp = String::new();
+        let _ = stream.read_to_string(&mut resp).await;
+        resp
+    }
+
+    #[tokio::test]
+    async fn drained() {
+        let (addr, tx, handle) = start(
+            Route::new().at("/", make_sync(|_| "hello")),
+            Duration::from_secs(5),
+        )
+        .await;
+        assert!(get(addr, "/").await.ends_with("\r\n\r\nhello"));
+        tx.send(()).unwrap();
+
+        let report = handle.await.unwrap().unwrap();
+        assert_eq!(report.forced_connections(), 0);
+        assert!(report.aborted_requests().is_empty());
+        assert!(report.drain_duration() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn aborted() {
+        #[handler(internal)]
+        async fn slow() {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        }
+
+        let (addr, tx, handle) = start(
+            Route::new().at("/slow/:id", slow),
+            Duration::from_millis(200),
+        )
+        .await;
+        let requests = ["/slow/0", "/slow/1"].map(|path| tokio::spawn(get(addr, path)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        tx.send(()).unwrap();
+
+        let report = handle.await.unwrap().unwrap();
+        assert_eq!(report.forced_connections(), 2);
+        assert_eq!(
+            report.aborted_requests(),
+            &BTreeMap::from([("/slow/:id".to_string(), 2)])
+        );
+        assert!(report.drain_duration() >= Duration::from_millis(200));
+        for request in requests {
+            assert_eq!(request.await.unwrap(), "");
+        }
+    }
+}
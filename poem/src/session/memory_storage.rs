@@ -9,27 +9,28 @@ use parking_lot::Mutex;
 use priority_queue::PriorityQueue;
 use serde_json::Value;
 
-use crate::{session::SessionStorage, Result};
+use crate::{
+    clock::{Clock, SharedClock},
+    session::SessionStorage,
+    Result,
+};
 
 struct InnerStorage {
     sessions: HashMap<String, BTreeMap<String, Value>>,
     timeout_queue: PriorityQueue<String, Reverse<Instant>>,
+    clock: SharedClock,
 }
 
 impl InnerStorage {
     fn cleanup(&mut self) {
-        loop {
-            let now = Instant::now();
-            if let Some((_, expire_at)) = self.timeout_queue.peek() {
-                if expire_at.0 > now {
-                    break;
-                }
-                if let Some((session_id, _)) = self.timeout_queue.pop() {
-                    self.sessions.remove(&session_id);
-                }
-            } else {
+        let now = self.clock.now();
+        while let Some((_, expire_at)) = self.timeout_queue.peek() {
+            if expire_at.0 > now {
                 break;
             }
+            if let Some((session_id, _)) = self.timeout_queue.pop() {
+                self.sessions.remove(&session_id);
+            }
         }
     }
 }
@@ -44,8 +45,9 @@ impl Default for MemoryStorage {
         let inner = Arc::new(Mutex::new(InnerStorage {
             sessions: HashMap::new(),
             timeout_queue: PriorityQueue::new(),
+            clock: SharedClock::default(),
         }));
-        tokio::spawn({
+        crate::spawn::spawn("poem-memory-session-cleanup", {
             let inner = Arc::downgrade(&inner);
             async move {
                 loop {
@@ -66,6 +68,14 @@ impl MemoryStorage {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Sets the clock used to expire the sessions, defaults to the
+    /// [`SystemClock`](crate::clock::SystemClock).
+    #[must_use]
+    pub fn clock(self, clock: impl Clock) -> Self {
+        self.inner.lock().clock = SharedClock::new(clock);
+        self
+    }
 }
 
 impl SessionStorage for MemoryStorage {
@@ -73,7 +83,8 @@ impl SessionStorage for MemoryStorage {
         &'a self,
         session_id: &'a str,
     ) -> Result<Option<BTreeMap<String, Value>>> {
-        let inner = self.inner.lock();
+        let mut inner = self.inner.lock();
+        inner.cleanup();
         Ok(inner.sessions.get(session_id).cloned())
     }
 
@@ -89,9 +100,10 @@ impl SessionStorage for MemoryStorage {
             .sessions
             .insert(session_id.to_string(), entries.clone());
         if let Some(expires) = expires {
+            let expire_at = inner.clock.now() + expires;
             inner
                 .timeout_queue
-                .push(session_id.to_string(), Reverse(Instant::now() + expires));
+                .push(session_id.to_string(), Reverse(expire_at));
         }
         Ok(())
     }
@@ -108,6 +120,7 @@ impl SessionStorage for MemoryStorage {
 mod tests {
     use super::*;
     use crate::{
+        clock::MockClock,
         session::{
             test_harness::{index, TestClient},
             CookieConfig, ServerSession,
@@ -192,4 +205,31 @@ mod tests {
         assert_eq!(storage.load_session("b").await.unwrap(), None);
         assert_eq!(storage.load_session("c").await.unwrap(), None);
     }
+
+    #[tokio::test]
+    async fn timeout_with_mock_clock() {
+        let clock = MockClock::new();
+        let storage = MemoryStorage::new().clock(clock.clone());
+        let mut values = BTreeMap::new();
+        values.insert("value".to_string(), "1".into());
+
+        storage
+            .update_session("a", &values, Some(Duration::from_secs(60)))
+            .await
+            .unwrap();
+        storage.update_session("b", &values, None).await.unwrap();
+
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(
+            storage.load_session("a").await.unwrap(),
+            Some(values.clone())
+        );
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(storage.load_session("a").await.unwrap(), None);
+        assert_eq!(
+            storage.load_session("b").await.unwrap(),
+            Some(values.clone())
+        );
+    }
 }
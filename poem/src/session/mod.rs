@@ -9,8 +9,11 @@ mod server_session;
 #[allow(clippy::module_inception)]
 mod session;
 mod session_storage;
+#[cfg(any(feature = "postgres-session", feature = "sqlite-session"))]
+mod sql_storage;
 #[cfg(test)]
 pub(crate) mod test_harness;
+mod typed_session;
 
 pub use cookie_config::{CookieConfig, CookieSecurity};
 pub use cookie_session::{CookieSession, CookieSessionEndpoint};
@@ -20,3 +23,8 @@ pub use redis_storage::RedisStorage;
 pub use server_session::{ServerSession, ServerSessionEndpoint};
 pub use session::{Session, SessionStatus};
 pub use session_storage::SessionStorage;
+#[cfg(feature = "postgres-session")]
+pub use sql_storage::PostgresStorage;
+#[cfg(feature = "sqlite-session")]
+pub use sql_storage::SqliteStorage;
+pub use typed_session::{TypedSession, TypedSessionData};
@@ -1,18 +1,63 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::{
+    clock::SharedClock,
     middleware::{CookieJarManager, CookieJarManagerEndpoint},
     session::{session_storage::SessionStorage, CookieConfig, Session, SessionStatus},
     Endpoint, Middleware, Request, Result,
 };
 
+/// The key of the entry where the timestamps of the session are stored.
+const META_KEY: &str = "__poem_session";
+
 /// Middleware for server-side session.
+///
+/// The session id is rotated when the session is renewed by
+/// [`Session::renew`], or when an entry specified by
+/// [`ServerSession::rotate_on_change`] is changed, to prevent the session
+/// fixation attacks.
+///
+/// When [`ServerSession::idle_timeout`] or [`ServerSession::absolute_timeout`]
+/// is set, the timestamps of the session are stored with its entries, and the
+/// expired sessions are removed, as well as the sessions stored without the
+/// timestamps.
+///
+/// If the storage supports the versions of the sessions, such as
+/// `PostgresStorage` and `SqliteStorage`, the request fails with
+/// [`SessionConflictError`](crate::error::SessionConflictError) when it
+/// changes a session which was updated by a concurrent request. Refreshing the
+/// access time of an unchanged session never conflicts.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     session::{CookieConfig, MemoryStorage, ServerSession},
+///     EndpointExt, Route,
+/// };
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let app = Route::new().with(
+///     ServerSession::new(CookieConfig::default(), MemoryStorage::new())
+///         .rotate_on_change(["user_id", "role"])
+///         .idle_timeout(Duration::from_secs(30 * 60))
+///         .absolute_timeout(Duration::from_secs(12 * 60 * 60)),
+/// );
+/// # });
+/// ```
 pub struct ServerSession<T> {
     config: Arc<CookieConfig>,
     storage: Arc<T>,
+    rotate_keys: Arc<[String]>,
+    idle_timeout: Option<Duration>,
+    absolute_timeout: Option<Duration>,
 }
 
 impl<T> ServerSession<T> {
@@ -21,6 +66,41 @@ impl<T> ServerSession<T> {
         Self {
             config: Arc::new(config),
             storage: Arc::new(storage),
+            rotate_keys: Arc::new([]),
+            idle_timeout: None,
+            absolute_timeout: None,
+        }
+    }
+
+    /// Rotates the session id when any of the specified entries is changed,
+    /// such as the id or the role of the signed in user.
+    #[must_use]
+    pub fn rotate_on_change(self, keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            rotate_keys: keys.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Sets the time after which the session expires if there is no request
+    /// with it.
+    #[must_use]
+    pub fn idle_timeout(self, timeout: Duration) -> Self {
+        Self {
+            idle_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Sets the time after which the session expires since it was created,
+    /// regardless of the activity.
+    ///
+    /// The creation time is preserved when the session is renewed.
+    #[must_use]
+    pub fn absolute_timeout(self, timeout: Duration) -> Self {
+        Self {
+            absolute_timeout: Some(timeout),
+            ..self
         }
     }
 }
@@ -33,6 +113,9 @@ impl<T: SessionStorage, E: Endpoint> Middleware<E> for ServerSession<T> {
             inner: ep,
             config: self.config.clone(),
             storage: self.storage.clone(),
+            rotate_keys: self.rotate_keys.clone(),
+            idle_timeout: self.idle_timeout,
+            absolute_timeout: self.absolute_timeout,
         })
     }
 }
@@ -45,11 +128,65 @@ fn generate_session_id() -> String {
     URL_SAFE_NO_PAD.encode(random_bytes)
 }
 
+/// The timestamps of a session in seconds since the unix epoch.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct SessionMeta {
+    created_at: u64,
+    accessed_at: u64,
+}
+
 /// Endpoint for `ServerSession` middleware.
 pub struct ServerSessionEndpoint<T, E> {
     inner: E,
     config: Arc<CookieConfig>,
     storage: Arc<T>,
+    rotate_keys: Arc<[String]>,
+    idle_timeout: Option<Duration>,
+    absolute_timeout: Option<Duration>,
+}
+
+impl<T, E> ServerSessionEndpoint<T, E> {
+    fn has_timeouts(&self) -> bool {
+        self.idle_timeout.is_some() || self.absolute_timeout.is_some()
+    }
+
+    fn is_expired(&self, meta: Option<SessionMeta>, now: u64) -> bool {
+        if !self.has_timeouts() {
+            return false;
+        }
+        let Some(meta) = meta else {
+            return true;
+        };
+        let elapsed =
+            |since: u64, timeout: Duration| now.saturating_sub(since) >= timeout.as_secs();
+        self.idle_timeout
+            .is_some_and(|timeout| elapsed(meta.accessed_at, timeout))
+            || self
+                .absolute_timeout
+                .is_some_and(|timeout| elapsed(meta.created_at, timeout))
+    }
+
+    /// Returns the entries to store, with the timestamps if the timeouts are
+    /// enabled.
+    fn entries(
+        &self,
+        session: &Session,
+        meta: Option<SessionMeta>,
+        now: u64,
+    ) -> BTreeMap<String, Value> {
+        let mut entries = session.entries();
+        if self.has_timeouts() {
+            let meta = SessionMeta {
+                created_at: meta.map_or(now, |meta| meta.created_at),
+                accessed_at: now,
+            };
+            entries.insert(
+                META_KEY.to_string(),
+                serde_json::to_value(meta).unwrap_or_default(),
+            );
+        }
+        entries
+    }
 }
 
 impl<T, E> Endpoint for ServerSessionEndpoint<T, E>
@@ -61,10 +198,30 @@ where
 
     async fn call(&self, mut req: Request) -> Result<Self::Output> {
         let cookie_jar = req.cookie().clone();
+        let now = SharedClock::from_request(&req)
+            .elapsed_since_epoch()
+            .as_secs();
         let mut session_id = self.config.get_cookie_value(&cookie_jar);
+        let mut meta = None;
+        let mut expired = false;
+        let mut version = 0;
         let session = match &session_id {
-            Some(id) => match self.storage.load_session(id).await? {
-                Some(entries) => Session::new(entries),
+            Some(id) => match self.storage.load_versioned_session(id).await? {
+                Some((mut entries, loaded_version)) => {
+                    version = loaded_version;
+                    meta = entries
+                        .remove(META_KEY)
+                        .and_then(|value| serde_json::from_value(value).ok());
+                    if self.is_expired(meta, now) {
+                        self.storage.remove_session(id).await?;
+                        session_id = None;
+                        meta = None;
+                        expired = true;
+                        Session::default()
+                    } else {
+                        Session::new(entries)
+                    }
+                }
                 None => {
                     session_id = None;
                     Session::default()
@@ -72,22 +229,50 @@ where
             },
             None => Session::default(),
         };
+        let privileges = self
+            .rotate_keys
+            .iter()
+            .map(|key| session.get::<Value>(key))
+            .collect::<Vec<_>>();
 
         req.extensions_mut().insert(session.clone());
         let resp = self.inner.call(req).await?;
 
-        match session.status() {
+        let status = match session.status() {
+            SessionStatus::Changed
+                if session_id.is_some()
+                    && self
+                        .rotate_keys
+                        .iter()
+                        .zip(&privileges)
+                        .any(|(key, value)| session.get::<Value>(key) != *value) =>
+            {
+                SessionStatus::Renewed
+            }
+            status => status,
+        };
+
+        match status {
             SessionStatus::Changed => match session_id {
                 Some(session_id) => {
                     self.storage
-                        .update_session(&session_id, &session.entries(), self.config.ttl())
+                        .update_versioned_session(
+                            &session_id,
+                            &self.entries(&session, meta, now),
+                            self.config.ttl(),
+                            version,
+                        )
                         .await?;
                 }
                 None => {
                     let session_id = generate_session_id();
                     self.config.set_cookie_value(&cookie_jar, &session_id);
                     self.storage
-                        .update_session(&session_id, &session.entries(), self.config.ttl())
+                        .update_session(
+                            &session_id,
+                            &self.entries(&session, meta, now),
+                            self.config.ttl(),
+                        )
                         .await?;
                 }
             },
@@ -99,7 +284,11 @@ where
                 let session_id = generate_session_id();
                 self.config.set_cookie_value(&cookie_jar, &session_id);
                 self.storage
-                    .update_session(&session_id, &session.entries(), self.config.ttl())
+                    .update_session(
+                        &session_id,
+                        &self.entries(&session, meta, now),
+                        self.config.ttl(),
+                    )
                     .await?;
             }
             SessionStatus::Purged => {
@@ -108,9 +297,137 @@ where
                     self.config.remove_cookie(&cookie_jar);
                 }
             }
+            SessionStatus::Unchanged if expired => {
+                self.config.remove_cookie(&cookie_jar);
+            }
+            // refreshes the access time
+            SessionStatus::Unchanged if self.idle_timeout.is_some() => {
+                if let Some(session_id) = session_id {
+                    self.storage
+                        .touch_session(
+                            &session_id,
+                            &self.entries(&session, meta, now),
+                            self.config.ttl(),
+                            version,
+                        )
+                        .await?;
+                }
+            }
             SessionStatus::Unchanged => {}
         };
 
         Ok(resp)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::MockClock,
+        handler,
+        session::{test_harness::TestClient, MemoryStorage},
+        web::Path,
+        EndpointExt, Route,
+    };
+
+    #[handler(internal)]
+    fn login(Path(action): Path<i32>, session: &Session) {
+        match action {
+            1 => session.set("a", 1),
+            2 => session.set("user_id", 1),
+            3 => session.set("user_id", 2),
+            _ => {}
+        }
+    }
+
+    fn app(session: ServerSession<MemoryStorage>, clock: &MockClock) -> impl Endpoint {
+        Route::new()
+            .at("/:action", login)
+            .with(session)
+            .data(SharedClock::new(clock.clone()))
+    }
+
+    #[tokio::test]
+    async fn rotate_on_change() {
+        let clock = MockClock::new();
+        let app = app(
+            ServerSession::new(CookieConfig::default(), MemoryStorage::new())
+                .rotate_on_change(["user_id"]),
+            &clock,
+        );
+        let mut client = TestClient::default();
+
+        client.call(&app, 1).await;
+        let id = client.cookie("poem-session").unwrap().to_string();
+        client.call(&app, 1).await;
+        assert_eq!(client.cookie("poem-session"), Some(id.as_str()));
+
+        client.call(&app, 2).await;
+        let new_id = client.cookie("poem-session").unwrap().to_string();
+        assert_ne!(new_id, id);
+
+        // the value is not changed
+        client.call(&app, 2).await;
+        assert_eq!(client.cookie("poem-session"), Some(new_id.as_str()));
+
+        client.call(&app, 3).await;
+        assert_ne!(client.cookie("poem-session"), Some(new_id.as_str()));
+    }
+
+    #[tokio::test]
+    async fn idle_timeout() {
+        let clock = MockClock::new();
+        let app = app(
+            ServerSession::new(CookieConfig::default(), MemoryStorage::new())
+                .idle_timeout(Duration::from_secs(30)),
+            &clock,
+        );
+        let mut client = TestClient::default();
+
+        client.call(&app, 1).await;
+        let id = client.cookie("poem-session").unwrap().to_string();
+
+        // the access time is refreshed by the requests
+        clock.advance(Duration::from_secs(20));
+        client.call(&app, 0).await;
+        clock.advance(Duration::from_secs(20));
+        client.call(&app, 0).await;
+        assert_eq!(client.cookie("poem-session"), Some(id.as_str()));
+
+        clock.advance(Duration::from_secs(30));
+        client.call(&app, 0).await;
+        client.assert_cookies(vec![]);
+    }
+
+    #[tokio::test]
+    async fn absolute_timeout() {
+        let clock = MockClock::new();
+        let app = app(
+            ServerSession::new(CookieConfig::default(), MemoryStorage::new())
+                .absolute_timeout(Duration::from_secs(60)),
+            &clock,
+        );
+        let mut client = TestClient::default();
+
+        client.call(&app, 1).await;
+        let id = client.cookie("poem-session").unwrap().to_string();
+
+        clock.advance(Duration::from_secs(45));
+        client.call(&app, 1).await;
+        assert_eq!(client.cookie("poem-session"), Some(id.as_str()));
+
+        // a new session is created after the session expires
+        clock.advance(Duration::from_secs(15));
+        client.call(&app, 1).await;
+        let new_id = client.cookie("poem-session").unwrap().to_string();
+        assert_ne!(new_id, id);
+
+        clock.advance(Duration::from_secs(59));
+        client.call(&app, 0).await;
+        assert_eq!(client.cookie("poem-session"), Some(new_id.as_str()));
+        clock.advance(Duration::from_secs(1));
+        client.call(&app, 0).await;
+        client.assert_cookies(vec![]);
+    }
+}
@@ -124,6 +124,9 @@ impl Session {
     }
 
     /// Renews the session key, assigning existing session state to new key.
+    ///
+    /// It should be called when the privilege of the session changes, such as
+    /// after signing in, to prevent the session fixation attacks.
     pub fn renew(&self) {
         let mut inner = self.inner.write();
         if inner.status != SessionStatus::Purged {
@@ -25,4 +25,54 @@ pub trait SessionStorage: Send + Sync {
         &'a self,
         session_id: &'a str,
     ) -> impl Future<Output = Result<()>> + Send + 'a;
+
+    /// Load session entries with the version of the session, which is used
+    /// for the optimistic locking.
+    ///
+    /// The storages without the versions return `0`.
+    fn load_versioned_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> impl Future<Output = Result<Option<(BTreeMap<String, Value>, u64)>>> + Send + 'a {
+        async move {
+            Ok(self
+                .load_session(session_id)
+                .await?
+                .map(|entries| (entries, 0)))
+        }
+    }
+
+    /// Update a session if its version is still `version`, returns
+    /// [`SessionConflictError`](crate::error::SessionConflictError) if the
+    /// session was updated by another request after it was loaded.
+    ///
+    /// The storages without the versions update the session unconditionally.
+    fn update_versioned_session<'a>(
+        &'a self,
+        session_id: &'a str,
+        entries: &'a BTreeMap<String, Value>,
+        expires: Option<Duration>,
+        version: u64,
+    ) -> impl Future<Output = Result<()>> + Send + 'a {
+        let _ = version;
+        self.update_session(session_id, entries, expires)
+    }
+
+    /// Stores the entries of a session which is not changed by the request,
+    /// such as the refreshed access time, without bumping its version.
+    ///
+    /// Nothing is stored if the session was updated by another request after
+    /// it was loaded, so the concurrent changes are not overwritten.
+    ///
+    /// The storages without the versions update the session unconditionally.
+    fn touch_session<'a>(
+        &'a self,
+        session_id: &'a str,
+        entries: &'a BTreeMap<String, Value>,
+        expires: Option<Duration>,
+        version: u64,
+    ) -> impl Future<Output = Result<()>> + Send + 'a {
+        let _ = version;
+        self.update_session(session_id, entries, expires)
+    }
 }
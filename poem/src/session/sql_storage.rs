@@ -0,0 +1,510 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use serde_json::Value;
+
+use crate::{
+    clock::{Clock, SharedClock},
+    error::{SessionConflictError, SqlSessionError},
+    session::SessionStorage,
+    Result,
+};
+
+/// The default name of the sessions table.
+const DEFAULT_TABLE: &str = "poem_sessions";
+
+/// The queries of a SQL session storage, generated for the table name.
+struct Queries {
+    create_table: String,
+    create_index: String,
+    load: String,
+    upsert: String,
+    update_versioned: String,
+    touch: String,
+    remove: String,
+    cleanup: String,
+}
+
+impl Queries {
+    fn new(table: &str, placeholder: fn(usize) -> String) -> Self {
+        let p = placeholder;
+        Self {
+            create_table: format!(
+                "CREATE TABLE IF NOT EXISTS {table} (id TEXT PRIMARY KEY, entries TEXT NOT NULL, \
+                 expires_at BIGINT, version BIGINT NOT NULL DEFAULT 0)"
+            ),
+            create_index: format!(
+                "CREATE INDEX IF NOT EXISTS {table}_expires_at ON {table} (expires_at)"
+            ),
+            load: format!(
+                "SELECT entries, version FROM {table} WHERE id = {} AND (expires_at IS NULL OR \
+                 expires_at > {})",
+                p(1),
+                p(2)
+            ),
+            upsert: format!(
+                "INSERT INTO {table} (id, entries, expires_at, version) VALUES ({}, {}, {}, 0) ON \
+                 CONFLICT (id) DO UPDATE SET entries = excluded.entries, expires_at = \
+                 excluded.expires_at, version = {table}.version + 1",
+                p(1),
+                p(2),
+                p(3)
+            ),
+            update_versioned: format!(
+                "UPDATE {table} SET entries = {}, expires_at = {}, version = version + 1 WHERE id \
+                 = {} AND version = {}",
+                p(2),
+                p(3),
+                p(1),
+                p(4)
+            ),
+            touch: format!(
+                "UPDATE {table} SET entries = {}, expires_at = {} WHERE id = {} AND version = {}",
+                p(2),
+                p(3),
+                p(1),
+                p(4)
+            ),
+            remove: format!("DELETE FROM {table} WHERE id = {}", p(1)),
+            cleanup: format!(
+                "DELETE FROM {table} WHERE expires_at IS NOT NULL AND expires_at <= {}",
+                p(1)
+            ),
+        }
+    }
+}
+
+fn encode_entries(entries: &BTreeMap<String, Value>) -> String {
+    #[cfg(not(feature = "sonic-rs"))]
+    {
+        serde_json::to_string(entries).unwrap_or_default()
+    }
+    #[cfg(feature = "sonic-rs")]
+    {
+        sonic_rs::to_string(entries).unwrap_or_default()
+    }
+}
+
+fn decode_entries(value: &str) -> Option<BTreeMap<String, Value>> {
+    #[cfg(not(feature = "sonic-rs"))]
+    {
+        serde_json::from_str(value).ok()
+    }
+    #[cfg(feature = "sonic-rs")]
+    {
+        sonic_rs::from_str(value).ok()
+    }
+}
+
+fn now_millis(clock: &SharedClock) -> i64 {
+    clock.elapsed_since_epoch().as_millis() as i64
+}
+
+fn expires_at(clock: &SharedClock, expires: Option<Duration>) -> Option<i64> {
+    expires.map(|expires| now_millis(clock).saturating_add(expires.as_millis() as i64))
+}
+
+macro_rules! sql_storage {
+    (
+        $(#[$meta:meta])*
+        $name:ident, $feature:literal, $pool:ty, $placeholder:expr
+    ) => {
+        $(#[$meta])*
+        #[cfg_attr(docsrs, doc(cfg(feature = $feature)))]
+        #[derive(Clone)]
+        pub struct $name {
+            pool: $pool,
+            queries: Arc<Queries>,
+            clock: SharedClock,
+        }
+
+        impl $name {
+            /// Create a storage with the connection pool, the sessions are
+            /// stored in the `poem_sessions` table.
+            pub fn new(pool: $pool) -> Self {
+                Self {
+                    pool,
+                    queries: Arc::new(Queries::new(DEFAULT_TABLE, $placeholder)),
+                    clock: SharedClock::default(),
+                }
+            }
+
+            /// Sets the name of the sessions table, it is not escaped.
+            #[must_use]
+            pub fn table(self, table: &str) -> Self {
+                Self {
+                    queries: Arc::new(Queries::new(table, $placeholder)),
+                    ..self
+                }
+            }
+
+            /// Sets the clock used to expire the sessions, defaults to the
+            /// [`SystemClock`](crate::clock::SystemClock).
+            #[must_use]
+            pub fn clock(self, clock: impl Clock) -> Self {
+                Self {
+                    clock: SharedClock::new(clock),
+                    ..self
+                }
+            }
+
+            /// Creates the sessions table and its index if they do not exist.
+            pub async fn migrate(&self) -> Result<()> {
+                sqlx::query(&self.queries.create_table)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?;
+                sqlx::query(&self.queries.create_index)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?;
+                Ok(())
+            }
+
+            /// Removes the expired sessions, and returns the number of the
+            /// removed sessions.
+            ///
+            /// The expired sessions are never loaded, this only reclaims the
+            /// space of the table.
+            pub async fn cleanup_expired(&self) -> Result<u64> {
+                let res = sqlx::query(&self.queries.cleanup)
+                    .bind(now_millis(&self.clock))
+                    .execute(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?;
+                Ok(res.rows_affected())
+            }
+
+            /// Returns a background task which removes the expired sessions
+            /// periodically, see [`Server::task`](crate::Server::task).
+            #[cfg(feature = "server")]
+            #[cfg_attr(docsrs, doc(cfg(all(feature = $feature, feature = "server"))))]
+            pub fn cleanup_task(&self, interval: Duration) -> crate::task::Task {
+                let storage = self.clone();
+                crate::task::Task::new("session-cleanup", move |ctx| {
+                    let storage = storage.clone();
+                    async move {
+                        while ctx.sleep(interval).await {
+                            if let Err(err) = storage.cleanup_expired().await {
+                                tracing::warn!(error = %err, "failed to remove the expired sessions");
+                            }
+                        }
+                    }
+                })
+            }
+        }
+
+        impl SessionStorage for $name {
+            async fn load_session<'a>(
+                &'a self,
+                session_id: &'a str,
+            ) -> Result<Option<BTreeMap<String, Value>>> {
+                Ok(self
+                    .load_versioned_session(session_id)
+                    .await?
+                    .map(|(entries, _)| entries))
+            }
+
+            async fn update_session<'a>(
+                &'a self,
+                session_id: &'a str,
+                entries: &'a BTreeMap<String, Value>,
+                expires: Option<Duration>,
+            ) -> Result<()> {
+                sqlx::query(&self.queries.upsert)
+                    .bind(session_id)
+                    .bind(encode_entries(entries))
+                    .bind(expires_at(&self.clock, expires))
+                    .execute(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?;
+                Ok(())
+            }
+
+            async fn remove_session<'a>(&'a self, session_id: &'a str) -> Result<()> {
+                sqlx::query(&self.queries.remove)
+                    .bind(session_id)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?;
+                Ok(())
+            }
+
+            async fn load_versioned_session<'a>(
+                &'a self,
+                session_id: &'a str,
+            ) -> Result<Option<(BTreeMap<String, Value>, u64)>> {
+                let row: Option<(String, i64)> = sqlx::query_as(&self.queries.load)
+                    .bind(session_id)
+                    .bind(now_millis(&self.clock))
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?;
+                Ok(row.and_then(|(entries, version)| {
+                    Some((decode_entries(&entries)?, version as u64))
+                }))
+            }
+
+            async fn update_versioned_session<'a>(
+                &'a self,
+                session_id: &'a str,
+                entries: &'a BTreeMap<String, Value>,
+                expires: Option<Duration>,
+                version: u64,
+            ) -> Result<()> {
+                let res = sqlx::query(&self.queries.update_versioned)
+                    .bind(session_id)
+                    .bind(encode_entries(entries))
+                    .bind(expires_at(&self.clock, expires))
+                    .bind(version as i64)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?;
+                if res.rows_affected() == 0 {
+                    return Err(SessionConflictError.into());
+                }
+                Ok(())
+            }
+
+            async fn touch_session<'a>(
+                &'a self,
+                session_id: &'a str,
+                entries: &'a BTreeMap<String, Value>,
+                expires: Option<Duration>,
+                version: u64,
+            ) -> Result<()> {
+                sqlx::query(&self.queries.touch)
+                    .bind(session_id)
+                    .bind(encode_entries(entries))
+                    .bind(expires_at(&self.clock, expires))
+                    .bind(version as i64)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?;
+                Ok(())
+            }
+        }
+    };
+}
+
+#[cfg(feature = "postgres-session")]
+sql_storage!(
+    /// A session storage using PostgreSQL.
+    ///
+    /// The sessions have versions, and the updates of the
+    /// [`ServerSession`](crate::session::ServerSession) fail with
+    /// [`SessionConflictError`] if the session was updated by another request
+    /// after it was loaded.
+    ///
+    /// # Errors
+    ///
+    /// - [`SqlSessionError`]
+    /// - [`SessionConflictError`]
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use poem::{
+    ///     listener::TcpListener,
+    ///     session::{CookieConfig, PostgresStorage, ServerSession},
+    ///     EndpointExt, Route, Server,
+    /// };
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let pool = sqlx::PgPool::connect("postgres://localhost/app").await.unwrap();
+    /// let storage = PostgresStorage::new(pool);
+    /// storage.migrate().await.unwrap();
+    ///
+    /// let app = Route::new().with(ServerSession::new(CookieConfig::default(), storage.clone()));
+    /// Server::new(TcpListener::bind("0.0.0.0:3000"))
+    ///     .task(storage.cleanup_task(Duration::from_secs(60)))
+    ///     .run(app)
+    ///     .await
+    /// # });
+    /// ```
+    PostgresStorage,
+    "postgres-session",
+    sqlx::PgPool,
+    |n| format!("${n}")
+);
+
+#[cfg(feature = "sqlite-session")]
+sql_storage!(
+    /// A session storage using SQLite.
+    ///
+    /// The sessions have versions, and the updates of the
+    /// [`ServerSession`](crate::session::ServerSession) fail with
+    /// [`SessionConflictError`] if the session was updated by another request
+    /// after it was loaded.
+    ///
+    /// # Errors
+    ///
+    /// - [`SqlSessionError`]
+    /// - [`SessionConflictError`]
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use poem::{
+    ///     session::{CookieConfig, ServerSession, SqliteStorage},
+    ///     EndpointExt, Route,
+    /// };
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let pool = sqlx::SqlitePool::connect("sqlite://sessions.db").await.unwrap();
+    /// let storage = SqliteStorage::new(pool);
+    /// storage.migrate().await.unwrap();
+    ///
+    /// let app = Route::new().with(ServerSession::new(CookieConfig::default(), storage));
+    /// # });
+    /// ```
+    SqliteStorage,
+    "sqlite-session",
+    sqlx::SqlitePool,
+    |n| format!("?{n}")
+);
+
+#[cfg(all(test, feature = "sqlite-session"))]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::{
+        clock::MockClock,
+        session::{
+            test_harness::{index, TestClient},
+            CookieConfig, ServerSession, Session,
+        },
+        EndpointExt, Route,
+    };
+
+    async fn storage(clock: &MockClock) -> SqliteStorage {
+        // each connection of an in-memory database has its own database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let storage = SqliteStorage::new(pool).clock(clock.clone());
+        storage.migrate().await.unwrap();
+        storage
+    }
+
+    #[tokio::test]
+    async fn sqlite_session() {
+        let clock = MockClock::new();
+        let app = Route::new().at("/:action", index).with(ServerSession::new(
+            CookieConfig::default(),
+            storage(&clock).await,
+        ));
+        let mut client = TestClient::default();
+
+        client.call(&app, 0).await;
+        client.assert_cookies(vec![]);
+
+        client.call(&app, 1).await;
+        client.call(&app, 2).await;
+        client.call(&app, 7).await;
+        client.call(&app, 6).await;
+        client.call(&app, 3).await;
+        client.call(&app, 4).await;
+        client.call(&app, 5).await;
+        client.assert_cookies(vec![]);
+    }
+
+    #[tokio::test]
+    async fn expires() {
+        let clock = MockClock::new();
+        let storage = storage(&clock).await;
+        let mut entries = BTreeMap::new();
+        entries.insert("a".to_string(), Value::from(1));
+
+        storage
+            .update_session("a", &entries, Some(Duration::from_secs(10)))
+            .await
+            .unwrap();
+        storage.update_session("b", &entries, None).await.unwrap();
+        assert_eq!(
+            storage.load_session("a").await.unwrap(),
+            Some(entries.clone())
+        );
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(storage.load_session("a").await.unwrap(), None);
+        assert_eq!(storage.cleanup_expired().await.unwrap(), 1);
+        assert_eq!(storage.load_session("b").await.unwrap(), Some(entries));
+    }
+
+    #[tokio::test]
+    async fn optimistic_locking() {
+        let clock = MockClock::new();
+        let storage = storage(&clock).await;
+        let mut entries = BTreeMap::new();
+        entries.insert("a".to_string(), Value::from(1));
+
+        storage.update_session("a", &entries, None).await.unwrap();
+        let (_, version) = storage.load_versioned_session("a").await.unwrap().unwrap();
+        assert_eq!(version, 0);
+
+        storage
+            .update_versioned_session("a", &entries, None, version)
+            .await
+            .unwrap();
+        let err = storage
+            .update_versioned_session("a", &entries, None, version)
+            .await
+            .unwrap_err();
+        assert!(err.is::<SessionConflictError>());
+
+        let (_, version) = storage.load_versioned_session("a").await.unwrap().unwrap();
+        assert_eq!(version, 1);
+    }
+
+    #[tokio::test]
+    async fn concurrent_unchanged_requests() {
+        use std::sync::Arc;
+
+        use tokio::sync::Barrier;
+
+        use crate::{
+            handler,
+            http::{header, StatusCode},
+            web::{Data, Path},
+            Endpoint, IntoResponse, Request,
+        };
+
+        #[handler(internal)]
+        async fn wait(Path(action): Path<i32>, session: &Session, barrier: Data<&Arc<Barrier>>) {
+            if action == 1 {
+                session.set("a", 1);
+            } else {
+                // both requests load the session before any of them stores it
+                barrier.wait().await;
+            }
+        }
+
+        let clock = MockClock::new();
+        let app = Route::new()
+            .at("/:action", wait)
+            .with(
+                ServerSession::new(CookieConfig::default(), storage(&clock).await)
+                    .idle_timeout(Duration::from_secs(30)),
+            )
+            .data(Arc::new(Barrier::new(2)));
+        let mut client = TestClient::default();
+        client.call(&app, 1).await;
+        let cookie = format!("poem-session={}", client.cookie("poem-session").unwrap());
+
+        let call = || {
+            let req = Request::builder()
+                .uri("/0".parse().unwrap())
+                .header(header::COOKIE, &cookie)
+                .finish();
+            async { app.call(req).await.map(IntoResponse::into_response) }
+        };
+        let (a, b) = tokio::join!(call(), call());
+        assert_eq!(a.unwrap().status(), StatusCode::OK);
+        assert_eq!(b.unwrap().status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,291 @@
+use std::marker::PhantomData;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{session::Session, FromRequest, Request, RequestBody, Result};
+
+/// The data stored in a [`TypedSession`].
+///
+/// The data is stored with its [`VERSION`](TypedSessionData::VERSION), and
+/// the data stored with an older version is passed to
+/// [`migrate`](TypedSessionData::migrate) when it is loaded.
+///
+/// # Example
+///
+/// ```
+/// use poem::session::TypedSessionData;
+/// use serde::{Deserialize, Serialize};
+/// use serde_json::{json, Value};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct UserSession {
+///     user_id: i64,
+///     roles: Vec<String>,
+/// }
+///
+/// impl TypedSessionData for UserSession {
+///     const KEY: &'static str = "user";
+///     const VERSION: u32 = 2;
+///
+///     fn migrate(version: u32, mut value: Value) -> Option<Value> {
+///         match version {
+///             // the version 1 had a single role
+///             1 => {
+///                 let role = value.get("role")?.clone();
+///                 value["roles"] = json!([role]);
+///                 Some(value)
+///             }
+///             _ => None,
+///         }
+///     }
+/// }
+/// ```
+pub trait TypedSessionData: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// The key of the session entry where the data is stored.
+    const KEY: &'static str;
+
+    /// The version of the data.
+    ///
+    /// Default is `1`.
+    const VERSION: u32 = 1;
+
+    /// Migrates the data stored with an older version to the current
+    /// version, the data is discarded if it returns `None`.
+    ///
+    /// By default, the data of the older versions is discarded.
+    fn migrate(version: u32, value: Value) -> Option<Value> {
+        let _ = (version, value);
+        None
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Versioned<T> {
+    version: u32,
+    data: T,
+}
+
+/// A typed view of the [`Session`], which stores a single
+/// [`TypedSessionData`] in it.
+///
+/// It requires the [`CookieSession`](crate::session::CookieSession) or the
+/// [`ServerSession`](crate::session::ServerSession) middleware.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     session::{CookieConfig, CookieSession, TypedSession, TypedSessionData},
+///     test::TestClient,
+///     EndpointExt, Route,
+/// };
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Default, Serialize, Deserialize)]
+/// struct Counter {
+///     count: i32,
+/// }
+///
+/// impl TypedSessionData for Counter {
+///     const KEY: &'static str = "counter";
+/// }
+///
+/// #[handler]
+/// fn index(session: TypedSession<Counter>) -> String {
+///     let counter = session.update(|counter| counter.count += 1);
+///     format!("{}", counter.count)
+/// }
+///
+/// let app = Route::new()
+///     .at("/", index)
+///     .with(CookieSession::new(CookieConfig::default()));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_text("1").await;
+/// # });
+/// ```
+pub struct TypedSession<T> {
+    session: Session,
+    _mark: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for TypedSession<T> {
+    fn clone(&self) -> Self {
+        Self {
+            session: self.session.clone(),
+            _mark: PhantomData,
+        }
+    }
+}
+
+impl<T: TypedSessionData> TypedSession<T> {
+    /// Create a `TypedSession` of the session.
+    pub fn new(session: Session) -> Self {
+        Self {
+            session,
+            _mark: PhantomData,
+        }
+    }
+
+    /// Returns the underlying session.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Returns the data, the data stored with an older version is migrated
+    /// and stored back, and `None` is returned for the data stored with a
+    /// newer version.
+    pub fn get(&self) -> Option<T> {
+        let Versioned { version, data } = self.session.get::<Versioned<Value>>(T::KEY)?;
+        if version == T::VERSION {
+            return serde_json::from_value(data).ok();
+        }
+
+        // the data stored by a newer version is kept
+        if version > T::VERSION {
+            return None;
+        }
+
+        let data =
+            T::migrate(version, data).and_then(|data| serde_json::from_value::<T>(data).ok());
+        match &data {
+            Some(data) => self.set(data),
+            None => self.remove(),
+        }
+        data
+    }
+
+    /// Returns the data, or the default value if there is no data.
+    pub fn get_or_default(&self) -> T
+    where
+        T: Default,
+    {
+        self.get().unwrap_or_default()
+    }
+
+    /// Stores the data.
+    pub fn set(&self, data: &T) {
+        self.session.set(
+            T::KEY,
+            Versioned {
+                version: T::VERSION,
+                data,
+            },
+        );
+    }
+
+    /// Updates the data with the function, the default value is used if
+    /// there is no data, and returns the updated data.
+    pub fn update(&self, f: impl FnOnce(&mut T)) -> T
+    where
+        T: Default,
+    {
+        let mut data = self.get_or_default();
+        f(&mut data);
+        self.set(&data);
+        data
+    }
+
+    /// Removes the data.
+    pub fn remove(&self) {
+        self.session.remove(T::KEY);
+    }
+}
+
+impl<'a, T: TypedSessionData> FromRequest<'a> for TypedSession<T> {
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        let session = <&Session>::from_request(req, body).await?;
+        Ok(Self::new(session.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::session::SessionStatus;
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct User {
+        id: i32,
+        roles: Vec<String>,
+    }
+
+    impl TypedSessionData for User {
+        const KEY: &'static str = "user";
+        const VERSION: u32 = 3;
+
+        fn migrate(version: u32, mut value: Value) -> Option<Value> {
+            match version {
+                2 => {
+                    let role = value.get("role")?.clone();
+                    value["roles"] = json!([role]);
+                    Some(value)
+                }
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn typed_session() {
+        let session = TypedSession::<User>::new(Session::default());
+        assert_eq!(session.get(), None);
+
+        let user = session.update(|user| user.id = 1);
+        assert_eq!(
+            user,
+            User {
+                id: 1,
+                roles: vec![]
+            }
+        );
+        assert_eq!(session.get(), Some(user));
+        assert_eq!(
+            session.session().get::<Value>("user"),
+            Some(json!({ "version": 3, "data": { "id": 1, "roles": [] } }))
+        );
+
+        session.remove();
+        assert_eq!(session.get(), None);
+        assert_eq!(session.session().status(), SessionStatus::Changed);
+    }
+
+    #[test]
+    fn migrate() {
+        let session = TypedSession::<User>::new(Session::default());
+        session.session().set(
+            "user",
+            json!({ "version": 2, "data": { "id": 1, "role": "admin" } }),
+        );
+        assert_eq!(
+            session.get(),
+            Some(User {
+                id: 1,
+                roles: vec!["admin".to_string()]
+            })
+        );
+        assert_eq!(
+            session.session().get::<Value>("user").unwrap()["version"],
+            3
+        );
+
+        // the data is discarded if it cannot be migrated
+        session
+            .session()
+            .set("user", json!({ "version": 1, "data": { "id": 1 } }));
+        assert_eq!(session.get(), None);
+        assert_eq!(session.session().get::<Value>("user"), None);
+
+        session
+            .session()
+            .set("user", json!({ "version": 4, "data": { "id": 1 } }));
+        assert_eq!(session.get(), None);
+        assert!(session.session().get::<Value>("user").is_some());
+    }
+}
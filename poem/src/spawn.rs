@@ -0,0 +1,88 @@
+//! Spawning the internal tasks.
+//!
+//! With the `tokio-console` feature and the `tokio_unstable` cfg, the tasks are
+//! spawned with a name, which is shown by
+//! [`tokio-console`](https://github.com/tokio-rs/console).
+
+use std::future::Future;
+
+use tokio::{runtime::Handle, task::JoinHandle};
+
+/// Spawns a named task on the current runtime.
+#[track_caller]
+pub(crate) fn spawn<F>(name: &str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    spawn_on(&Handle::current(), name, future)
+}
+
+/// Spawns a named task on the specified runtime.
+#[track_caller]
+pub(crate) fn spawn_on<F>(handle: &Handle, name: &str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(all(tokio_unstable, feature = "tokio-console"))]
+    {
+        tokio::task::Builder::new()
+            .name(name)
+            .spawn_on(future, handle)
+            .expect("failed to spawn task")
+    }
+    #[cfg(not(all(tokio_unstable, feature = "tokio-console")))]
+    {
+        let _ = name;
+        handle.spawn(future)
+    }
+}
+
+/// Runs a named blocking function on the blocking thread pool of the current
+/// runtime.
+#[track_caller]
+pub(crate) fn spawn_blocking<F, R>(name: &str, f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    #[cfg(all(tokio_unstable, feature = "tokio-console"))]
+    {
+        tokio::task::Builder::new()
+            .name(name)
+            .spawn_blocking(f)
+            .expect("failed to spawn task")
+    }
+    #[cfg(not(all(tokio_unstable, feature = "tokio-console")))]
+    {
+        let _ = name;
+        tokio::task::spawn_blocking(f)
+    }
+}
+
+/// Spawns a named task into the [`JoinSet`].
+#[cfg(feature = "cron")]
+#[track_caller]
+pub(crate) fn spawn_into<F>(
+    set: &mut tokio::task::JoinSet<F::Output>,
+    name: &str,
+    future: F,
+) -> tokio::task::AbortHandle
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(all(tokio_unstable, feature = "tokio-console"))]
+    {
+        set.build_task()
+            .name(name)
+            .spawn(future)
+            .expect("failed to spawn task")
+    }
+    #[cfg(not(all(tokio_unstable, feature = "tokio-console")))]
+    {
+        let _ = name;
+        set.spawn(future)
+    }
+}
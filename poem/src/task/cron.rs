@@ -0,0 +1,449 @@
+use std::{future::Future, str::FromStr, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Datelike, TimeZone, Timelike, Utc};
+use futures_util::{future::BoxFuture, FutureExt};
+use rand::Rng;
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use crate::{
+    error::ParseCronError,
+    task::{RestartPolicy, Task, TaskContext},
+};
+
+/// A cron schedule.
+///
+/// The expression consists of 5 fields (minute, hour, day of month, month and
+/// day of week), or 6 fields with an additional leading field for the
+/// second. The times are in UTC.
+///
+/// Each field can be `*`, a value, a range `a-b`, a step `*/n` or `a-b/n`, or
+/// a comma separated list of them. The months and the days of week can also
+/// be specified by their English abbreviations, e.g. `JAN` or `MON`, and both
+/// `0` and `7` mean Sunday. If both the day of month and the day of week are
+/// restricted, the time matches when either matches.
+///
+/// The following macros are also supported: `@yearly`, `@annually`,
+/// `@monthly`, `@weekly`, `@daily`, `@midnight` and `@hourly`.
+///
+/// # Example
+///
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use poem::task::CronSchedule;
+///
+/// let schedule: CronSchedule = "30 2 * * MON-FRI".parse().unwrap();
+/// let next = schedule
+///     .next_after(Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap())
+///     .unwrap();
+/// assert_eq!(next, Utc.with_ymd_and_hms(2024, 6, 3, 2, 30, 0).unwrap());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CronSchedule {
+    seconds: u64,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    day_of_month_restricted: bool,
+    day_of_week_restricted: bool,
+}
+
+const MONTH_NAMES: &[&str] = &[
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const DAY_OF_WEEK_NAMES: &[&str] = &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+fn parse_value(s: &str, min: u32, names: &[&str]) -> Option<u32> {
+    if let Some(idx) = names.iter().position(|name| name.eq_ignore_ascii_case(s)) {
+        return Some(idx as u32 + min);
+    }
+    s.parse().ok()
+}
+
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, ParseCronError> {
+    let err = || ParseCronError(format!("invalid field `{field}`"));
+    let mut bits = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(err)?,
+            ),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (
+                    parse_value(start, min, names).ok_or_else(err)?,
+                    parse_value(end, min, names).ok_or_else(err)?,
+                ),
+                None => {
+                    let value = parse_value(range, min, names).ok_or_else(err)?;
+                    (value, if step > 1 { max } else { value })
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err(err());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+impl FromStr for CronSchedule {
+    type Err = ParseCronError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expr = match s.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            s => s,
+        };
+        let fields = expr.split_whitespace().collect::<Vec<_>>();
+        let fields = match fields.len() {
+            5 => [&["0"][..], &fields].concat(),
+            6 => fields,
+            _ => return Err(ParseCronError(format!("expect 5 or 6 fields, got `{s}`"))),
+        };
+
+        let mut days_of_week = parse_field(fields[5], 0, 7, DAY_OF_WEEK_NAMES)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            seconds: parse_field(fields[0], 0, 59, &[])?,
+            minutes: parse_field(fields[1], 0, 59, &[])?,
+            hours: parse_field(fields[2], 0, 23, &[])?,
+            days_of_month: parse_field(fields[3], 1, 31, &[])?,
+            months: parse_field(fields[4], 1, 12, MONTH_NAMES)?,
+            days_of_week,
+            day_of_month_restricted: fields[3] != "*",
+            day_of_week_restricted: fields[5] != "*",
+        })
+    }
+}
+
+impl CronSchedule {
+    fn matches_day(&self, time: &DateTime<Utc>) -> bool {
+        let dom = self.days_of_month & (1 << time.day()) != 0;
+        let dow = self.days_of_week & (1 << time.weekday().num_days_from_sunday()) != 0;
+        if self.day_of_month_restricted && self.day_of_week_restricted {
+            dom || dow
+        } else {
+            dom && dow
+        }
+    }
+
+    /// Returns the first time matching this schedule after `time`, or `None`
+    /// if there is no such time in the next five years.
+    pub fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut time = time.with_nanosecond(0)? + chrono::Duration::seconds(1);
+        let limit = time + chrono::Duration::days(366 * 5);
+
+        while time < limit {
+            if self.months & (1 << time.month()) == 0 {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    month => (time.year(), month + 1),
+                };
+                time = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+            } else if !self.matches_day(&time) {
+                let date = time.date_naive().succ_opt()?;
+                time = Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?);
+            } else if self.hours & (1 << time.hour()) == 0 {
+                time = time.with_minute(0)?.with_second(0)? + chrono::Duration::hours(1);
+            } else if self.minutes & (1 << time.minute()) == 0 {
+                time = time.with_second(0)? + chrono::Duration::minutes(1);
+            } else if self.seconds & (1 << time.second()) == 0 {
+                time += chrono::Duration::seconds(1);
+            } else {
+                return Some(time);
+            }
+        }
+
+        None
+    }
+}
+
+/// What to do when a cron task is due while its previous run is still
+/// running.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OverlapPolicy {
+    /// Skip this run.
+    Skip,
+    /// Run it after the previous runs have finished.
+    Queue,
+}
+
+type JobFn = Arc<dyn Fn(TaskContext) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// A background task that runs on a [`CronSchedule`].
+///
+/// It is converted into a [`Task`] when registered on the server.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use poem::{
+///     listener::TcpListener,
+///     task::{CronTask, OverlapPolicy},
+///     Route, Server,
+/// };
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// Server::new(TcpListener::bind("0.0.0.0:3000"))
+///     .task(
+///         CronTask::new("cleanup", "0 */5 * * * *".parse().unwrap(), |_ctx| async {
+///             // clean up the expired data...
+///         })
+///         .jitter(Duration::from_secs(10))
+///         .overlap(OverlapPolicy::Skip),
+///     )
+///     .run(Route::new())
+///     .await
+/// # });
+/// ```
+pub struct CronTask {
+    name: String,
+    schedule: CronSchedule,
+    f: JobFn,
+    jitter: Duration,
+    overlap: OverlapPolicy,
+}
+
+impl CronTask {
+    /// Create a cron task that runs `f` on the specified schedule.
+    pub fn new<F, Fut>(name: impl Into<String>, schedule: CronSchedule, f: F) -> Self
+    where
+        F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            schedule,
+            f: Arc::new(move |ctx| f(ctx).boxed()),
+            jitter: Duration::ZERO,
+            overlap: OverlapPolicy::Skip,
+        }
+    }
+
+    /// Delays each run by a random duration up to `jitter`, defaults to no
+    /// jitter.
+    #[must_use]
+    pub fn jitter(self, jitter: Duration) -> Self {
+        Self { jitter, ..self }
+    }
+
+    /// Sets the overlap policy, defaults to [`OverlapPolicy::Skip`].
+    #[must_use]
+    pub fn overlap(self, overlap: OverlapPolicy) -> Self {
+        Self { overlap, ..self }
+    }
+}
+
+impl From<CronTask> for Task {
+    fn from(cron: CronTask) -> Self {
+        let CronTask {
+            name,
+            schedule,
+            f,
+            jitter,
+            overlap,
+        } = cron;
+        let running = Arc::new(Semaphore::new(1));
+
+        Task::new(name, move |ctx| {
+            run_schedule(
+                ctx,
+                schedule.clone(),
+                f.clone(),
+                jitter,
+                overlap,
+                running.clone(),
+            )
+        })
+        .restart_policy(RestartPolicy::OnPanic)
+    }
+}
+
+async fn run_schedule(
+    ctx: TaskContext,
+    schedule: CronSchedule,
+    f: JobFn,
+    jitter: Duration,
+    overlap: OverlapPolicy,
+    running: Arc<Semaphore>,
+) {
+    let mut jobs = JoinSet::new();
+
+    loop {
+        while let Some(res) = jobs.try_join_next() {
+            if let Err(err) = res {
+                tracing::error!(task = ctx.name(), error = %err, "cron job failed");
+            }
+        }
+
+        let now = Utc::now();
+        let Some(next) = schedule.next_after(now) else {
+            break;
+        };
+        let mut delay = (next - now).to_std().unwrap_or_default();
+        if !jitter.is_zero() {
+            delay += rand::thread_rng().gen_range(Duration::ZERO..=jitter);
+        }
+        if !ctx.sleep(delay).await {
+            break;
+        }
+
+        let permit = match overlap {
+            OverlapPolicy::Skip => match running.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    tracing::warn!(
+                        task = ctx.name(),
+                        "skip cron job, the previous run is still running"
+                    );
+                    continue;
+                }
+            },
+            OverlapPolicy::Queue => None,
+        };
+
+        let f = f.clone();
+        let running = running.clone();
+        let job_ctx = ctx.clone();
+        crate::spawn::spawn_into(
+            &mut jobs,
+            &format!("poem-cron-{}", ctx.name()),
+            async move {
+                let _permit = match permit {
+                    Some(permit) => permit,
+                    None => match running.acquire_owned().await {
+                        Ok(permit) => permit,
+                        Err(_) => return,
+                    },
+                };
+                f(job_ctx).await;
+            },
+        );
+    }
+
+    while let Some(res) = jobs.join_next().await {
+        if let Err(err) = res {
+            tracing::error!(task = ctx.name(), error = %err, "cron job failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::runtime::Handle;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::task::Tasks;
+
+    fn time(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn next(expr: &str, after: &str) -> String {
+        expr.parse::<CronSchedule>()
+            .unwrap()
+            .next_after(time(after))
+            .unwrap()
+            .to_rfc3339()
+    }
+
+    #[test]
+    fn parse() {
+        assert!("* * * *".parse::<CronSchedule>().is_err());
+        assert!("60 * * * *".parse::<CronSchedule>().is_err());
+        assert!("* * 0 * *".parse::<CronSchedule>().is_err());
+        assert!("*/0 * * * *".parse::<CronSchedule>().is_err());
+        assert!("5-1 * * * *".parse::<CronSchedule>().is_err());
+        assert!("* * * FOO *".parse::<CronSchedule>().is_err());
+        assert_eq!(
+            "0 0 * * 7".parse::<CronSchedule>().unwrap(),
+            "0 0 * * SUN".parse::<CronSchedule>().unwrap()
+        );
+        assert_eq!(
+            "@daily".parse::<CronSchedule>().unwrap(),
+            "0 0 0 * * *".parse::<CronSchedule>().unwrap()
+        );
+    }
+
+    #[test]
+    fn next_after() {
+        let base = "2024-01-31T10:15:30+00:00";
+        assert_eq!(next("* * * * *", base), "2024-01-31T10:16:00+00:00");
+        assert_eq!(next("* * * * * *", base), "2024-01-31T10:15:31+00:00");
+        assert_eq!(next("*/20 * * * *", base), "2024-01-31T10:20:00+00:00");
+        assert_eq!(next("10/20 * * * *", base), "2024-01-31T10:30:00+00:00");
+        assert_eq!(next("0 9-17 * * *", base), "2024-01-31T11:00:00+00:00");
+        assert_eq!(next("0 0 29 2 *", base), "2024-02-29T00:00:00+00:00");
+        assert_eq!(next("0 0 31 * *", base), "2024-03-31T00:00:00+00:00");
+        assert_eq!(next("0 0 * * MON", base), "2024-02-05T00:00:00+00:00");
+        assert_eq!(next("0 0 1 * MON", base), "2024-02-01T00:00:00+00:00");
+        assert_eq!(next("@yearly", base), "2025-01-01T00:00:00+00:00");
+        assert_eq!(next("0,30 10 * * *", base), "2024-01-31T10:30:00+00:00");
+        assert!("0 0 30 2 *"
+            .parse::<CronSchedule>()
+            .unwrap()
+            .next_after(time(base))
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn run_cron_task() {
+        for overlap in [OverlapPolicy::Skip, OverlapPolicy::Queue] {
+            let runs = Arc::new(AtomicUsize::new(0));
+            let tasks = Tasks::default();
+            tasks.add(
+                CronTask::new("job", "* * * * * *".parse().unwrap(), {
+                    let runs = runs.clone();
+                    move |ctx| {
+                        let runs = runs.clone();
+                        async move {
+                            runs.fetch_add(1, Ordering::SeqCst);
+                            ctx.sleep(Duration::from_secs(10)).await;
+                        }
+                    }
+                })
+                .overlap(overlap)
+                .into(),
+            );
+
+            let token = CancellationToken::new();
+            let handles = tasks.start(&Handle::current(), token.clone());
+            tokio::time::sleep(Duration::from_millis(2500)).await;
+            token.cancel();
+            for handle in handles {
+                handle.await.unwrap();
+            }
+
+            match overlap {
+                OverlapPolicy::Skip => assert_eq!(runs.load(Ordering::SeqCst), 1),
+                OverlapPolicy::Queue => assert!(runs.load(Ordering::SeqCst) >= 2),
+            }
+        }
+    }
+}
@@ -0,0 +1,560 @@
+//! Background tasks tied to the server lifecycle.
+//!
+//! Background tasks are registered on the server with
+//! [`Server::spawn_task`](crate::Server::spawn_task) or
+//! [`Server::task`](crate::Server::task). They are started when the server
+//! starts, notified via [`TaskContext`] when the server begins graceful
+//! shutdown, and the server waits for them to finish before it stops.
+//!
+//! A task can be restarted with a backoff when it panics, or whenever it
+//! exits, according to its [`RestartPolicy`].
+//!
+//! With the `cron` feature, [`CronTask`] runs a job on a cron schedule.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use poem::{
+//!     handler,
+//!     listener::TcpListener,
+//!     task::{RestartPolicy, Task, TaskContext},
+//!     Route, Server,
+//! };
+//!
+//! #[handler]
+//! fn index() -> &'static str {
+//!     "hello"
+//! }
+//!
+//! async fn prune_sessions(ctx: TaskContext) {
+//!     while !ctx.is_shutdown() {
+//!         // prune the expired sessions...
+//!         ctx.sleep(Duration::from_secs(60)).await;
+//!     }
+//! }
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let server = Server::new(TcpListener::bind("0.0.0.0:3000"))
+//!     .task(Task::new("prune-sessions", prune_sessions).restart_policy(RestartPolicy::Always));
+//! let app = Route::new()
+//!     .at("/", index)
+//!     .at("/tasks", server.tasks().status_endpoint());
+//! server.run(app).await
+//! # });
+//! ```
+
+#[cfg(feature = "cron")]
+mod cron;
+
+use std::{
+    any::Any,
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use futures_util::{future::BoxFuture, FutureExt};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::{runtime::Handle, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "cron")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cron")))]
+pub use self::cron::{CronSchedule, CronTask, OverlapPolicy};
+use crate::{web::Json, Endpoint, IntoResponse, Request, Response, Result};
+
+/// The policy to restart a background task when it exits.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RestartPolicy {
+    /// Never restart the task.
+    Never,
+    /// Restart the task when it panics.
+    OnPanic,
+    /// Restart the task whenever it exits, unless the server is shutting
+    /// down.
+    Always,
+}
+
+/// The backoff between the restarts of a background task.
+///
+/// The delay starts at `initial` and is multiplied by `multiplier` after each
+/// restart, up to `max`. It is reset to `initial` when the task ran for at
+/// least `max` before it stopped.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    multiplier: f64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(30),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl Backoff {
+    /// Create a `Backoff`, defaults to start at 100ms, double after each
+    /// restart, up to 30 seconds.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the delay before the first restart.
+    #[must_use]
+    pub fn initial(self, initial: Duration) -> Self {
+        Self { initial, ..self }
+    }
+
+    /// Sets the maximum delay between the restarts.
+    #[must_use]
+    pub fn max(self, max: Duration) -> Self {
+        Self { max, ..self }
+    }
+
+    /// Sets the factor that the delay is multiplied by after each restart.
+    #[must_use]
+    pub fn multiplier(self, multiplier: f64) -> Self {
+        Self { multiplier, ..self }
+    }
+
+    fn next(&self, delay: Duration) -> Duration {
+        delay.mul_f64(self.multiplier).min(self.max)
+    }
+}
+
+/// The context passed to a background task.
+#[derive(Clone)]
+pub struct TaskContext {
+    name: Arc<str>,
+    restarts: usize,
+    token: CancellationToken,
+}
+
+impl TaskContext {
+    /// Returns the name of the task.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns how many times the task has been restarted.
+    #[inline]
+    pub fn restarts(&self) -> usize {
+        self.restarts
+    }
+
+    /// Returns `true` if the server has begun graceful shutdown.
+    #[inline]
+    pub fn is_shutdown(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Waits until the server begins graceful shutdown.
+    pub async fn shutdown(&self) {
+        self.token.cancelled().await
+    }
+
+    /// Sleeps for the specified duration, returns `false` without waiting
+    /// for the duration to elapse if the server begins graceful shutdown.
+    pub async fn sleep(&self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => true,
+            _ = self.token.cancelled() => false,
+        }
+    }
+
+    /// Returns the cancellation token that is cancelled when the server begins
+    /// graceful shutdown.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+type TaskFn = Box<dyn Fn(TaskContext) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// A background task.
+pub struct Task {
+    name: Arc<str>,
+    f: TaskFn,
+    restart_policy: RestartPolicy,
+    backoff: Backoff,
+    max_restarts: Option<usize>,
+}
+
+impl Task {
+    /// Create a background task with the specified name.
+    ///
+    /// The task is restarted when it panics by default.
+    pub fn new<F, Fut>(name: impl Into<String>, f: F) -> Self
+    where
+        F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self {
+            name: name.into().into(),
+            f: Box::new(move |ctx| f(ctx).boxed()),
+            restart_policy: RestartPolicy::OnPanic,
+            backoff: Backoff::default(),
+            max_restarts: None,
+        }
+    }
+
+    /// Sets the restart policy of the task, defaults to
+    /// [`RestartPolicy::OnPanic`].
+    #[must_use]
+    pub fn restart_policy(self, restart_policy: RestartPolicy) -> Self {
+        Self {
+            restart_policy,
+            ..self
+        }
+    }
+
+    /// Sets the backoff between the restarts.
+    #[must_use]
+    pub fn backoff(self, backoff: Backoff) -> Self {
+        Self { backoff, ..self }
+    }
+
+    /// Sets the maximum number of restarts, defaults to unlimited.
+    #[must_use]
+    pub fn max_restarts(self, max_restarts: usize) -> Self {
+        Self {
+            max_restarts: Some(max_restarts),
+            ..self
+        }
+    }
+}
+
+/// The state of a background task.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    /// The task has not been started.
+    Pending,
+    /// The task is running.
+    Running,
+    /// The task is waiting to be restarted.
+    Restarting,
+    /// The task has finished.
+    Finished,
+    /// The task has panicked and will not be restarted.
+    Failed,
+}
+
+/// The status of a background task.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStatus {
+    /// The name of the task.
+    pub name: String,
+    /// The state of the task.
+    pub state: TaskState,
+    /// How many times the task has been restarted.
+    pub restarts: usize,
+    /// The panic message of the last run, if it panicked.
+    pub last_error: Option<String>,
+    /// The time the task was last started, in seconds since the Unix epoch.
+    pub started_at: Option<u64>,
+}
+
+struct TaskEntry {
+    task: Task,
+    status: Mutex<TaskStatus>,
+}
+
+impl TaskEntry {
+    fn update(&self, f: impl FnOnce(&mut TaskStatus)) {
+        f(&mut self.status.lock());
+    }
+}
+
+/// A registry of the background tasks of a server.
+///
+/// It is a cheap cloneable handle, returned by
+/// [`Server::tasks`](crate::Server::tasks).
+#[derive(Clone, Default)]
+pub struct Tasks {
+    entries: Arc<Mutex<Vec<Arc<TaskEntry>>>>,
+}
+
+impl Tasks {
+    pub(crate) fn add(&self, task: Task) {
+        let status = TaskStatus {
+            name: task.name.to_string(),
+            state: TaskState::Pending,
+            restarts: 0,
+            last_error: None,
+            started_at: None,
+        };
+        self.entries.lock().push(Arc::new(TaskEntry {
+            task,
+            status: Mutex::new(status),
+        }));
+    }
+
+    /// Returns the status of all the tasks.
+    pub fn status(&self) -> Vec<TaskStatus> {
+        self.entries
+            .lock()
+            .iter()
+            .map(|entry| entry.status.lock().clone())
+            .collect()
+    }
+
+    /// Returns an endpoint that responds with the status of all the tasks as
+    /// JSON.
+    pub fn status_endpoint(&self) -> TaskStatusEndpoint {
+        TaskStatusEndpoint {
+            tasks: self.clone(),
+        }
+    }
+
+    /// Starts all the tasks on the runtime, they are notified to stop when
+    /// `token` is cancelled.
+    pub(crate) fn start(&self, runtime: &Handle, token: CancellationToken) -> Vec<JoinHandle<()>> {
+        self.entries
+            .lock()
+            .iter()
+            .map(|entry| {
+                crate::spawn::spawn_on(
+                    runtime,
+                    &format!("poem-task-{}", entry.task.name),
+                    supervise(entry.clone(), token.clone()),
+                )
+            })
+            .collect()
+    }
+}
+
+fn panic_message(err: Box<dyn Any + Send>) -> String {
+    if let Some(s) = err.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = err.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+async fn supervise(entry: Arc<TaskEntry>, token: CancellationToken) {
+    let task = &entry.task;
+    let mut restarts = 0;
+    let mut delay = task.backoff.initial;
+
+    loop {
+        entry.update(|status| {
+            status.state = TaskState::Running;
+            status.restarts = restarts;
+            status.started_at = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs());
+        });
+
+        let ctx = TaskContext {
+            name: task.name.clone(),
+            restarts,
+            token: token.clone(),
+        };
+        let started = Instant::now();
+        let res = AssertUnwindSafe((task.f)(ctx)).catch_unwind().await;
+        if started.elapsed() >= task.backoff.max {
+            delay = task.backoff.initial;
+        }
+
+        let restart = match res {
+            Ok(()) => {
+                entry.update(|status| status.last_error = None);
+                task.restart_policy == RestartPolicy::Always
+            }
+            Err(err) => {
+                let msg = panic_message(err);
+                tracing::error!(task = %task.name, error = %msg, "background task panicked");
+                entry.update(|status| status.last_error = Some(msg));
+                task.restart_policy != RestartPolicy::Never
+            }
+        };
+        let restart = restart
+            && !token.is_cancelled()
+            && task.max_restarts.map(|max| restarts < max).unwrap_or(true);
+
+        if !restart {
+            entry.update(|status| {
+                status.state = match status.last_error {
+                    Some(_) => TaskState::Failed,
+                    None => TaskState::Finished,
+                }
+            });
+            return;
+        }
+
+        entry.update(|status| status.state = TaskState::Restarting);
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = token.cancelled() => {
+                entry.update(|status| status.state = TaskState::Finished);
+                return;
+            }
+        }
+        tracing::info!(task = %task.name, delay = ?delay, "restart background task");
+        restarts += 1;
+        delay = task.backoff.next(delay);
+    }
+}
+
+/// An endpoint that responds with the status of the background tasks.
+///
+/// See also [`Tasks::status_endpoint`].
+pub struct TaskStatusEndpoint {
+    tasks: Tasks,
+}
+
+impl Endpoint for TaskStatusEndpoint {
+    type Output = Response;
+
+    async fn call(&self, _req: Request) -> Result<Self::Output> {
+        Ok(Json(self.tasks.status()).into_response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::test::TestClient;
+
+    #[tokio::test]
+    async fn restart_on_panic() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let tasks = Tasks::default();
+        tasks.add(
+            Task::new("panic", {
+                let runs = runs.clone();
+                move |ctx| {
+                    let runs = runs.clone();
+                    async move {
+                        runs.fetch_add(1, Ordering::SeqCst);
+                        if ctx.restarts() < 2 {
+                            panic!("failed {}", ctx.restarts());
+                        }
+                    }
+                }
+            })
+            .backoff(Backoff::new().initial(Duration::from_millis(1))),
+        );
+        tasks.add(
+            Task::new("never", |_| async { panic!("boom") }).restart_policy(RestartPolicy::Never),
+        );
+
+        for handle in tasks.start(&Handle::current(), CancellationToken::new()) {
+            handle.await.unwrap();
+        }
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        let status = tasks.status();
+        assert_eq!(status[0].state, TaskState::Finished);
+        assert_eq!(status[0].restarts, 2);
+        assert_eq!(status[0].last_error, None);
+        assert_eq!(status[1].state, TaskState::Failed);
+        assert_eq!(status[1].restarts, 0);
+        assert_eq!(status[1].last_error.as_deref(), Some("boom"));
+    }
+
+    #[tokio::test]
+    async fn max_restarts() {
+        let tasks = Tasks::default();
+        tasks.add(
+            Task::new("always", |_| async {})
+                .restart_policy(RestartPolicy::Always)
+                .backoff(Backoff::new().initial(Duration::from_millis(1)))
+                .max_restarts(3),
+        );
+        for handle in tasks.start(&Handle::current(), CancellationToken::new()) {
+            handle.await.unwrap();
+        }
+        assert_eq!(tasks.status()[0].restarts, 3);
+        assert_eq!(tasks.status()[0].state, TaskState::Finished);
+    }
+
+    #[tokio::test]
+    async fn reset_backoff() {
+        let starts = Arc::new(Mutex::new(Vec::new()));
+        let tasks = Tasks::default();
+        tasks.add(
+            Task::new("panic", {
+                let starts = starts.clone();
+                move |ctx| {
+                    let starts = starts.clone();
+                    async move {
+                        starts.lock().push(Instant::now());
+                        match ctx.restarts() {
+                            0 | 1 => panic!("failed"),
+                            2 => {
+                                tokio::time::sleep(Duration::from_millis(600)).await;
+                                panic!("failed after a healthy run");
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            })
+            .backoff(
+                Backoff::new()
+                    .initial(Duration::from_millis(50))
+                    .max(Duration::from_millis(500))
+                    .multiplier(10.0),
+            ),
+        );
+        for handle in tasks.start(&Handle::current(), CancellationToken::new()) {
+            handle.await.unwrap();
+        }
+
+        let starts = starts.lock();
+        assert_eq!(starts.len(), 4);
+        assert!(starts[2] - starts[1] >= Duration::from_millis(500));
+        assert!(starts[3] - starts[2] < Duration::from_millis(1000));
+        assert_eq!(tasks.status()[0].state, TaskState::Finished);
+    }
+
+    #[tokio::test]
+    async fn shutdown() {
+        let tasks = Tasks::default();
+        tasks.add(
+            Task::new("loop", |ctx| async move {
+                while ctx.sleep(Duration::from_secs(60)).await {}
+            })
+            .restart_policy(RestartPolicy::Always),
+        );
+        let token = CancellationToken::new();
+        let handles = tasks.start(&Handle::current(), token.clone());
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(tasks.status()[0].state, TaskState::Running);
+
+        token.cancel();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(tasks.status()[0].state, TaskState::Finished);
+
+        let cli = TestClient::new(tasks.status_endpoint());
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_json(serde_json::json!([{
+            "name": "loop",
+            "state": "finished",
+            "restarts": 0,
+            "last_error": null,
+            "started_at": tasks.status()[0].started_at,
+        }]))
+        .await;
+    }
+}
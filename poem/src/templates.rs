@@ -0,0 +1,354 @@
+//! Server-side template rendering.
+//!
+//! This module is independent of the template engine. Implement
+//! [`TemplateEngine`] for the engine you use (e.g. `tera` or `minijinja`),
+//! register the [`Templates`] as data, and return
+//! `Html<TemplateResult>` from the handlers.
+//!
+//! In debug builds, the templates are reloaded automatically when the files
+//! in the directories specified with [`Templates::watch`] are changed.
+//!
+//! When rendering fails, `Html<TemplateResult>` responds with
+//! `500 Internal Server Error`, using the page rendered from
+//! [`Templates::error_template`] if specified.
+//!
+//! # Example
+//!
+//! ```
+//! use poem::{
+//!     handler,
+//!     templates::{TemplateResult, Templates},
+//!     test::TestClient,
+//!     web::{Data, Html},
+//!     EndpointExt,
+//! };
+//! use serde_json::{json, Value};
+//!
+//! // A toy engine that replaces `{{ name }}` with the value in the context.
+//! let templates = Templates::new(|name: &str, ctx: &Value| {
+//!     let template = match name {
+//!         "hello.html" => "<h1>Hello {{ name }}</h1>",
+//!         _ => return Err(format!("template `{name}` not found").into()),
+//!     };
+//!     Ok(template.replace("{{ name }}", ctx["name"].as_str().unwrap_or_default()))
+//! });
+//!
+//! #[handler]
+//! fn index(templates: Data<&Templates>) -> Html<TemplateResult> {
+//!     Html(templates.render("hello.html", &json!({ "name": "poem" })))
+//! }
+//!
+//! let cli = TestClient::new(index.data(templates));
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let resp = cli.get("/").send().await;
+//! resp.assert_status_is_ok();
+//! resp.assert_text("<h1>Hello poem</h1>").await;
+//! # });
+//! ```
+
+use std::{
+    error::Error as StdError,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use http::StatusCode;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{error::TemplateError, web::Html, IntoResponse, Response};
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// Represents a template engine.
+///
+/// It is implemented for the functions with the signature
+/// `Fn(&str, &Value) -> Result<String, Box<dyn Error + Send + Sync>>`.
+pub trait TemplateEngine: Send + Sync + 'static {
+    /// Renders the template named `name` with the context.
+    fn render(&self, name: &str, context: &Value) -> Result<String, BoxError>;
+
+    /// Reloads the templates from the sources.
+    ///
+    /// In debug builds, it is called when the watched files are changed.
+    fn reload(&mut self) -> Result<(), BoxError> {
+        Ok(())
+    }
+}
+
+impl<F> TemplateEngine for F
+where
+    F: Fn(&str, &Value) -> Result<String, BoxError> + Send + Sync + 'static,
+{
+    fn render(&self, name: &str, context: &Value) -> Result<String, BoxError> {
+        (self)(name, context)
+    }
+}
+
+type Fingerprint = Vec<(PathBuf, Option<SystemTime>, u64)>;
+
+struct Watch {
+    paths: Vec<PathBuf>,
+    fingerprint: Fingerprint,
+}
+
+struct Inner {
+    engine: RwLock<Box<dyn TemplateEngine>>,
+    watch: Option<Mutex<Watch>>,
+    error_template: Option<String>,
+}
+
+/// A shared template engine.
+///
+/// See the [module level documentation](self) for more details.
+#[derive(Clone)]
+pub struct Templates {
+    inner: Arc<Inner>,
+}
+
+impl Templates {
+    /// Create `Templates` with the template engine.
+    pub fn new(engine: impl TemplateEngine) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                engine: RwLock::new(Box::new(engine)),
+                watch: None,
+                error_template: None,
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).expect("the templates can not be modified after it is used")
+    }
+
+    /// Watches the files in the directory, and reloads the templates when
+    /// they are changed.
+    ///
+    /// It only takes effect in debug builds.
+    #[must_use]
+    pub fn watch(mut self, path: impl Into<PathBuf>) -> Self {
+        if cfg!(debug_assertions) {
+            let watch = self.inner_mut().watch.get_or_insert_with(|| {
+                Mutex::new(Watch {
+                    paths: Vec::new(),
+                    fingerprint: Vec::new(),
+                })
+            });
+            let watch = watch.get_mut();
+            watch.paths.push(path.into());
+            watch.fingerprint = fingerprint(&watch.paths);
+        }
+        self
+    }
+
+    /// Sets the template used to render the `500 Internal Server Error` page
+    /// when rendering fails.
+    ///
+    /// The context of the template contains the `status` field, and the
+    /// `error` field with the error message in debug builds.
+    #[must_use]
+    pub fn error_template(mut self, name: impl Into<String>) -> Self {
+        self.inner_mut().error_template = Some(name.into());
+        self
+    }
+
+    /// Renders the template named `name` with the context.
+    pub fn render(&self, name: &str, context: &impl Serialize) -> TemplateResult {
+        match self.try_render(name, context) {
+            Ok(html) => TemplateResult {
+                result: Ok(html),
+                error_page: None,
+            },
+            Err(err) => TemplateResult {
+                error_page: self.render_error_page(&err),
+                result: Err(err),
+            },
+        }
+    }
+
+    fn try_render(&self, name: &str, context: &impl Serialize) -> Result<String, TemplateError> {
+        self.reload_if_changed()?;
+        let context = serde_json::to_value(context)?;
+        self.inner
+            .engine
+            .read()
+            .render(name, &context)
+            .map_err(|source| TemplateError::Render {
+                name: name.to_string(),
+                source,
+            })
+    }
+
+    fn reload_if_changed(&self) -> Result<(), TemplateError> {
+        let Some(watch) = &self.inner.watch else {
+            return Ok(());
+        };
+        let mut watch = watch.lock();
+        let fingerprint = fingerprint(&watch.paths);
+        if fingerprint != watch.fingerprint {
+            tracing::debug!("reload templates");
+            self.inner
+                .engine
+                .write()
+                .reload()
+                .map_err(TemplateError::Reload)?;
+            watch.fingerprint = fingerprint;
+        }
+        Ok(())
+    }
+
+    fn render_error_page(&self, err: &TemplateError) -> Option<String> {
+        let name = self.inner.error_template.as_deref()?;
+        let mut context = serde_json::json!({
+            "status": StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+        });
+        if cfg!(debug_assertions) {
+            context["error"] = err.to_string().into();
+        }
+        self.inner.engine.read().render(name, &context).ok()
+    }
+}
+
+fn fingerprint(paths: &[PathBuf]) -> Fingerprint {
+    fn visit(path: &Path, fingerprint: &mut Fingerprint) {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return;
+        };
+        if metadata.is_dir() {
+            if let Ok(entries) = std::fs::read_dir(path) {
+                for entry in entries.flatten() {
+                    visit(&entry.path(), fingerprint);
+                }
+            }
+        } else {
+            fingerprint.push((path.to_path_buf(), metadata.modified().ok(), metadata.len()));
+        }
+    }
+
+    let mut fingerprint = Vec::new();
+    for path in paths {
+        visit(path, &mut fingerprint);
+    }
+    fingerprint.sort();
+    fingerprint
+}
+
+/// The result of rendering a template.
+///
+/// Use `Html<TemplateResult>` as the response, or call
+/// [`TemplateResult::into_result`] to handle the error manually.
+pub struct TemplateResult {
+    result: Result<String, TemplateError>,
+    error_page: Option<String>,
+}
+
+impl TemplateResult {
+    /// Consumes this object and returns the rendered string or the error.
+    pub fn into_result(self) -> Result<String, TemplateError> {
+        self.result
+    }
+}
+
+impl IntoResponse for Html<TemplateResult> {
+    fn into_response(self) -> Response {
+        match self.0.result {
+            Ok(html) => Html(html).into_response(),
+            Err(err) => {
+                tracing::error!(error = %err, "failed to render template");
+                let page = self
+                    .0
+                    .error_page
+                    .unwrap_or_else(|| "<h1>500 Internal Server Error</h1>".to_string());
+                Html(page)
+                    .with_status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+
+    use super::*;
+
+    struct FileEngine {
+        dir: PathBuf,
+        reloads: Arc<AtomicUsize>,
+    }
+
+    impl TemplateEngine for FileEngine {
+        fn render(&self, name: &str, _context: &Value) -> Result<String, BoxError> {
+            Ok(std::fs::read_to_string(self.dir.join(name))?)
+        }
+
+        fn reload(&mut self) -> Result<(), BoxError> {
+            self.reloads.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn render_error() {
+        let templates = Templates::new(|name: &str, ctx: &Value| match name {
+            "error.html" => Ok(format!("error {}", ctx["status"])),
+            _ => Err("not found".into()),
+        });
+
+        let resp = Html(templates.render("a.html", &json!({}))).into_response();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            resp.into_body().into_string().await.unwrap(),
+            "<h1>500 Internal Server Error</h1>"
+        );
+
+        let templates = templates.error_template("error.html");
+        let resp = Html(templates.render("a.html", &json!({}))).into_response();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "error 500");
+
+        assert!(matches!(
+            templates.render("a.html", &()).into_result(),
+            Err(TemplateError::Render { name, .. }) if name == "a.html"
+        ));
+    }
+
+    #[test]
+    fn reload() {
+        let dir = std::env::temp_dir().join(format!("poem-templates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.html"), "a").unwrap();
+
+        let reloads = Arc::new(AtomicUsize::new(0));
+        let templates = Templates::new(FileEngine {
+            dir: dir.clone(),
+            reloads: reloads.clone(),
+        })
+        .watch(&dir);
+
+        assert_eq!(templates.render("a.html", &()).into_result().unwrap(), "a");
+        assert_eq!(reloads.load(Ordering::SeqCst), 0);
+
+        std::fs::write(dir.join("a.html"), "abc").unwrap();
+        assert_eq!(
+            templates.render("a.html", &()).into_result().unwrap(),
+            "abc"
+        );
+        assert_eq!(reloads.load(Ordering::SeqCst), 1);
+
+        assert_eq!(
+            templates.render("a.html", &()).into_result().unwrap(),
+            "abc"
+        );
+        assert_eq!(reloads.load(Ordering::SeqCst), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::{stream::BoxStream, StreamExt};
+
+use crate::Body;
+
+/// A stream of the response body chunks for testing.
+///
+/// This is returned by
+/// [`TestResponse::body_stream`](crate::test::TestResponse::body_stream).
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use futures_util::stream;
+/// use poem::{handler, test::TestClient, Body, Route};
+///
+/// #[handler]
+/// fn index() -> Body {
+///     Body::from_bytes_stream(stream::iter(vec![
+///         Ok::<_, std::io::Error>("hello"),
+///         Ok("world"),
+///     ]))
+/// }
+///
+/// let app = Route::new().at("/", index);
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status_is_ok();
+///
+/// let mut stream = resp.body_stream().timeout(Duration::from_secs(1));
+/// stream.assert_next("hello").await;
+/// stream.assert_next("world").await;
+/// stream.assert_end().await;
+/// # });
+/// ```
+pub struct TestBodyStream {
+    stream: BoxStream<'static, Bytes>,
+    timeout: Option<Duration>,
+}
+
+impl TestBodyStream {
+    pub(crate) fn new(body: Body) -> Self {
+        Self {
+            stream: body
+                .into_bytes_stream()
+                .map(|res| res.expect("valid body chunk"))
+                .boxed(),
+            timeout: None,
+        }
+    }
+
+    /// Sets the maximum time to wait for each chunk, panics if it is
+    /// exceeded.
+    #[must_use]
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Returns the next chunk of the body, returns `None` if the body has
+    /// ended.
+    pub async fn next(&mut self) -> Option<Bytes> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.stream.next())
+                .await
+                .unwrap_or_else(|_| panic!("no body chunk received within {timeout:?}")),
+            None => self.stream.next().await,
+        }
+    }
+
+    /// Asserts that the next chunk is equals to `bytes`.
+    pub async fn assert_next(&mut self, bytes: impl AsRef<[u8]>) {
+        assert_eq!(
+            self.next().await.expect("expect body chunk").as_ref(),
+            bytes.as_ref()
+        );
+    }
+
+    /// Asserts that the body has ended.
+    pub async fn assert_end(&mut self) {
+        if let Some(chunk) = self.next().await {
+            panic!("expect end of body, got `{chunk:?}`");
+        }
+    }
+
+    /// Reads all remaining chunks, and asserts that their concatenation is
+    /// equals to `bytes`.
+    pub async fn assert_remaining(&mut self, bytes: impl AsRef<[u8]>) {
+        let mut data = Vec::new();
+        while let Some(chunk) = self.next().await {
+            data.extend_from_slice(&chunk);
+        }
+        assert_eq!(data, bytes.as_ref());
+    }
+}
@@ -1,5 +1,7 @@
 use http::{header, header::HeaderName, HeaderMap, HeaderValue, Method};
 
+#[cfg(feature = "capture")]
+use crate::{middleware::CapturedRequest, test::TestResponse};
 use crate::{test::TestRequestBuilder, Endpoint, IntoEndpoint};
 
 macro_rules! impl_methods {
@@ -123,6 +125,27 @@ impl<E: Endpoint> TestClient<E> {
         self.default_header(header::CONTENT_TYPE, content_type.as_ref())
     }
 
+    /// Replays a request recorded by the
+    /// [`Capture`](crate::middleware::Capture) middleware.
+    ///
+    /// # Panics
+    ///
+    /// Panic when the file is not a valid captured request.
+    #[cfg(feature = "capture")]
+    pub async fn replay(&self, path: impl AsRef<std::path::Path>) -> TestResponse {
+        let path = path.as_ref();
+        let captured = CapturedRequest::load(path)
+            .unwrap_or_else(|err| panic!("invalid captured request `{}`: {err}", path.display()));
+        let method = captured.method.parse().expect("valid method");
+        let body = captured.body_bytes().expect("valid body");
+
+        let mut builder = self.request(method, captured.uri);
+        for (key, value) in captured.headers {
+            builder = builder.header(key, value);
+        }
+        builder.body(body).send().await
+    }
+
     /// Create a [`TestRequestBuilder`].
     pub fn request(&self, method: Method, uri: impl Into<String>) -> TestRequestBuilder<'_, E> {
         TestRequestBuilder::new(self, method, uri.into())
@@ -122,6 +122,24 @@ impl TestForm {
         self
     }
 
+    /// Adds a file field.
+    #[must_use]
+    pub fn file(
+        mut self,
+        name: impl Into<String>,
+        filename: impl Into<String>,
+        content_type: impl AsRef<str>,
+        value: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.fields.push(
+            TestFormField::bytes(value)
+                .name(name)
+                .filename(filename)
+                .content_type(content_type),
+        );
+        self
+    }
+
     #[inline]
     pub(crate) fn boundary(&self) -> &str {
         BOUNDARY_STRING
@@ -290,4 +308,25 @@ mod tests {
             .await;
         resp.assert_status_is_ok();
     }
+
+    #[tokio::test]
+    async fn multipart_file() {
+        #[handler(internal)]
+        async fn index(mut multipart: Multipart) {
+            let field = multipart.next_field().await.unwrap().unwrap();
+            assert_eq!(field.name(), Some("file"));
+            assert_eq!(field.file_name(), Some("a.json"));
+            assert_eq!(field.content_type(), Some("application/json"));
+            assert_eq!(field.text().await.unwrap(), "{}");
+            assert!(multipart.next_field().await.unwrap().is_none());
+        }
+
+        let cli = TestClient::new(index);
+        let resp = cli
+            .post("/")
+            .multipart(TestForm::new().file("file", "a.json", "application/json", "{}"))
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+    }
 }
@@ -109,14 +109,20 @@
 //! # });
 //! ```
 
+mod body_stream;
 mod client;
 mod form;
 mod json;
 mod request_builder;
 mod response;
+#[cfg(feature = "websocket")]
+mod websocket;
 
+pub use body_stream::TestBodyStream;
 pub use client::TestClient;
 pub use form::{TestForm, TestFormField};
 pub use json::{TestJson, TestJsonArray, TestJsonObject, TestJsonValue};
 pub use request_builder::TestRequestBuilder;
 pub use response::TestResponse;
+#[cfg(feature = "websocket")]
+pub use websocket::TestWebSocket;
@@ -206,6 +206,107 @@ impl<'a, E> TestRequestBuilder<'a, E> {
         self
     }
 
+    /// Send this request as an upgrade to the protocol to endpoint, and
+    /// returns the response and the upgraded connection, which is connected
+    /// to the endpoint in-process.
+    ///
+    /// Panics if the endpoint does not accept the upgrade.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     get, handler,
+    ///     test::TestClient,
+    ///     web::{BoxUpgradeResponse, Upgrade},
+    ///     Route,
+    /// };
+    /// use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    ///
+    /// #[handler]
+    /// fn index(upgrade: Upgrade) -> BoxUpgradeResponse {
+    ///     upgrade
+    ///         .on_upgrade("echo", |io| async move {
+    ///             let (mut reader, mut writer) = tokio::io::split(io);
+    ///             let _ = tokio::io::copy(&mut reader, &mut writer).await;
+    ///         })
+    ///         .boxed()
+    /// }
+    ///
+    /// let app = Route::new().at("/", get(index));
+    /// let cli = TestClient::new(app);
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let (resp, mut io) = cli.get("/").upgrade("echo").await;
+    /// resp.assert_header("upgrade", "echo");
+    ///
+    /// io.write_all(b"hello").await.unwrap();
+    /// let mut data = [0; 5];
+    /// io.read_exact(&mut data).await.unwrap();
+    /// assert_eq!(&data, b"hello");
+    /// # });
+    /// ```
+    pub async fn upgrade(self, protocol: &str) -> (TestResponse, crate::Upgraded)
+    where
+        E: Endpoint,
+    {
+        use crate::{OnUpgrade, Upgraded};
+
+        let ep = &self.cli.ep;
+        let mut req = self
+            .header(header::CONNECTION, "upgrade")
+            .header(header::UPGRADE, protocol)
+            .make_request();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        *req.state_mut().on_upgrade.get_mut() = Some(OnUpgrade::from_io(rx));
+
+        let resp = ep.get_response(req).await;
+        assert_eq!(
+            resp.status(),
+            http::StatusCode::SWITCHING_PROTOCOLS,
+            "expect {protocol} upgrade"
+        );
+
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let _ = tx.send(Box::new(server));
+        (TestResponse::new(resp), Upgraded::from_io(Box::new(client)))
+    }
+
+    /// Send this request as a WebSocket handshake to endpoint, and returns a
+    /// [`TestWebSocket`](crate::test::TestWebSocket) connected to the upgraded
+    /// connection in-process.
+    ///
+    /// Panics if the endpoint does not accept the upgrade.
+    #[cfg(feature = "websocket")]
+    pub async fn websocket(self) -> crate::test::TestWebSocket
+    where
+        E: Endpoint,
+    {
+        use tokio_tungstenite::tungstenite::protocol::Role;
+
+        use crate::{
+            test::TestWebSocket,
+            web::websocket::{sign, WebSocketStream},
+        };
+
+        const KEY: &str = "dGhlIHNhbXBsZSBub25jZQ==";
+
+        let (resp, upgraded) = self
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, KEY)
+            .upgrade("websocket")
+            .await;
+        assert_eq!(
+            resp.0.headers().get(header::SEC_WEBSOCKET_ACCEPT),
+            Some(&sign(KEY.as_bytes())),
+            "invalid `Sec-WebSocket-Accept` header"
+        );
+
+        let stream =
+            tokio_tungstenite::WebSocketStream::from_raw_socket(upgraded, Role::Client, None).await;
+        TestWebSocket::new(resp.0.headers().clone(), WebSocketStream::new(stream))
+    }
+
     /// Send this request to endpoint to get the response.
     pub async fn send(self) -> TestResponse
     where
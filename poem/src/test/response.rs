@@ -6,7 +6,11 @@ use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value;
 use tokio_util::compat::TokioAsyncReadCompatExt;
 
-use crate::{test::json::TestJson, web::sse::Event, Response};
+use crate::{
+    test::{json::TestJson, TestBodyStream},
+    web::sse::Event,
+    Response,
+};
 
 /// A response object for testing.
 pub struct TestResponse(pub Response);
@@ -176,6 +180,12 @@ impl TestResponse {
         );
     }
 
+    /// Consumes this object and return the [`TestBodyStream`] for asserting
+    /// the body chunk by chunk.
+    pub fn body_stream(self) -> TestBodyStream {
+        TestBodyStream::new(self.0.into_body())
+    }
+
     /// Consumes this object and return the [`TestJson`].
     pub async fn json(self) -> TestJson {
         self.0
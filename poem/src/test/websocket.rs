@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use http::HeaderMap;
+
+use crate::web::websocket::{Message, WebSocketStream};
+
+/// A WebSocket client for testing, which is connected to the endpoint
+/// in-process.
+///
+/// This is returned by
+/// [`TestRequestBuilder::websocket`](crate::test::TestRequestBuilder::websocket).
+///
+/// # Example
+///
+/// ```
+/// use futures_util::{SinkExt, StreamExt};
+/// use poem::{
+///     get, handler,
+///     test::TestClient,
+///     web::websocket::{Message, WebSocket},
+///     IntoResponse, Route,
+/// };
+///
+/// #[handler]
+/// async fn index(ws: WebSocket) -> impl IntoResponse {
+///     ws.on_upgrade(|mut socket| async move {
+///         while let Some(Ok(Message::Text(text))) = socket.next().await {
+///             let _ = socket.send(Message::Text(format!("echo: {}", text))).await;
+///         }
+///     })
+/// }
+///
+/// let app = Route::new().at("/", get(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let mut ws = cli.get("/").websocket().await;
+/// ws.send_text("hello").await;
+/// ws.assert_text("echo: hello").await;
+/// ws.close().await;
+/// # });
+/// ```
+pub struct TestWebSocket {
+    headers: HeaderMap,
+    stream: WebSocketStream,
+    timeout: Option<Duration>,
+}
+
+impl TestWebSocket {
+    pub(crate) fn new(headers: HeaderMap, stream: WebSocketStream) -> Self {
+        Self {
+            headers,
+            stream,
+            timeout: None,
+        }
+    }
+
+    /// Sets the maximum time to wait for each message, panics if it is
+    /// exceeded.
+    #[must_use]
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Returns the headers of the `101 Switching Protocols` response.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Sends a message to the endpoint.
+    pub async fn send(&mut self, msg: Message) {
+        self.stream.send(msg).await.expect("send websocket message");
+    }
+
+    /// Sends a text message to the endpoint.
+    pub async fn send_text(&mut self, text: impl Into<String>) {
+        self.send(Message::text(text)).await;
+    }
+
+    /// Sends a binary message to the endpoint.
+    pub async fn send_binary(&mut self, data: impl Into<Vec<u8>>) {
+        self.send(Message::binary(data)).await;
+    }
+
+    /// Receives the next message from the endpoint, returns `None` if the
+    /// connection has been closed.
+    pub async fn receive(&mut self) -> Option<Message> {
+        let next = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.stream.next())
+                .await
+                .unwrap_or_else(|_| panic!("no websocket message received within {timeout:?}")),
+            None => self.stream.next().await,
+        };
+        next.map(|res| res.expect("valid websocket message"))
+    }
+
+    /// Asserts that the next message is equals to `msg`.
+    pub async fn assert_message(&mut self, msg: Message) {
+        assert_eq!(self.receive().await.expect("expect message"), msg);
+    }
+
+    /// Asserts that the next message is a text message and it equals to
+    /// `text`.
+    pub async fn assert_text(&mut self, text: impl Into<String>) {
+        self.assert_message(Message::text(text)).await;
+    }
+
+    /// Asserts that the next message is a binary message and it equals to
+    /// `data`.
+    pub async fn assert_binary(&mut self, data: impl Into<Vec<u8>>) {
+        self.assert_message(Message::binary(data)).await;
+    }
+
+    /// Asserts that the connection has been closed by the endpoint.
+    pub async fn assert_closed(&mut self) {
+        match self.receive().await {
+            Some(Message::Close(_)) | None => {}
+            Some(msg) => panic!("expect websocket closed, got `{msg:?}`"),
+        }
+    }
+
+    /// Closes the connection.
+    pub async fn close(mut self) {
+        let _ = self.stream.close().await;
+    }
+
+    /// Consumes this object and return the inner [`WebSocketStream`].
+    pub fn into_inner(self) -> WebSocketStream {
+        self.stream
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get, handler, test::TestClient, web::websocket::WebSocket, IntoResponse, Route};
+
+    #[tokio::test]
+    async fn websocket() {
+        #[handler(internal)]
+        async fn index(ws: WebSocket) -> impl IntoResponse {
+            ws.protocols(["aaa"]).on_upgrade(|mut socket| async move {
+                while let Some(Ok(msg)) = socket.next().await {
+                    match msg {
+                        Message::Text(text) if text == "bye" => break,
+                        Message::Text(text) => {
+                            let _ = socket.send(Message::Text(text.to_uppercase())).await;
+                        }
+                        Message::Binary(data) => {
+                            let _ = socket.send(Message::Binary(data)).await;
+                        }
+                        _ => {}
+                    }
+                }
+                let _ = socket.close().await;
+            })
+        }
+
+        let cli = TestClient::new(Route::new().at("/", get(index)));
+        let mut ws = cli
+            .get("/")
+            .header(http::header::SEC_WEBSOCKET_PROTOCOL, "aaa")
+            .websocket()
+            .await
+            .timeout(Duration::from_secs(5));
+        assert_eq!(
+            ws.headers().get(http::header::SEC_WEBSOCKET_PROTOCOL),
+            Some(&http::HeaderValue::from_static("aaa"))
+        );
+
+        ws.send_text("hello").await;
+        ws.assert_text("HELLO").await;
+        ws.send_binary(vec![1, 2, 3]).await;
+        ws.assert_binary(vec![1, 2, 3]).await;
+        ws.send_text("bye").await;
+        ws.assert_closed().await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "expect websocket upgrade")]
+    async fn websocket_rejected() {
+        #[handler(internal)]
+        fn index() {}
+
+        let cli = TestClient::new(index);
+        cli.get("/").websocket().await;
+    }
+}
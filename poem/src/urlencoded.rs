@@ -0,0 +1,537 @@
+//! Fast percent-decoding and `application/x-www-form-urlencoded` parsing.
+//!
+//! The bytes which need no decoding are found with `memchr`, which uses the
+//! SIMD instructions when they are available, and are copied in chunks
+//! instead of one by one.
+
+use std::{borrow::Cow, str::Utf8Error};
+
+use serde::{
+    de::{self, value::MapDeserializer, Error as _, IntoDeserializer},
+    forward_to_deserialize_any,
+};
+pub(crate) use serde_urlencoded::de::Error;
+
+#[inline]
+fn hex(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Decodes the escape at the beginning of `input`, which starts with `%`.
+#[inline]
+fn escape(input: &[u8]) -> Option<u8> {
+    match input {
+        [_, h, l, ..] => Some(hex(*h)? << 4 | hex(*l)?),
+        _ => None,
+    }
+}
+
+/// Percent-decodes `input`, and replaces `+` with space if `plus` is `true`.
+///
+/// The invalid escapes are kept, and `input` is borrowed if nothing is
+/// decoded.
+fn decode(input: &[u8], plus: bool) -> Cow<'_, [u8]> {
+    let find = |input: &[u8]| {
+        if plus {
+            memchr::memchr2(b'%', b'+', input)
+        } else {
+            memchr::memchr(b'%', input)
+        }
+    };
+
+    let Some(mut pos) = find(input) else {
+        return Cow::Borrowed(input);
+    };
+    let mut output = Vec::new();
+    let mut start = 0;
+    loop {
+        let decoded = match input[pos] {
+            b'+' => Some((b' ', 1)),
+            _ => escape(&input[pos..]).map(|byte| (byte, 3)),
+        };
+        let skip = match decoded {
+            Some((byte, len)) => {
+                if output.capacity() == 0 {
+                    output.reserve(input.len());
+                }
+                output.extend_from_slice(&input[start..pos]);
+                output.push(byte);
+                start = pos + len;
+                len
+            }
+            None => 1,
+        };
+        match find(&input[pos + skip..]) {
+            Some(next) => pos += skip + next,
+            None => break,
+        }
+    }
+
+    if start == 0 {
+        return Cow::Borrowed(input);
+    }
+    output.extend_from_slice(&input[start..]);
+    Cow::Owned(output)
+}
+
+/// Percent-decodes `input` as UTF-8, like
+/// `percent_encoding::percent_decode(input).decode_utf8()`.
+pub(crate) fn percent_decode(input: &[u8]) -> Result<Cow<'_, str>, Utf8Error> {
+    match decode(input, false) {
+        Cow::Borrowed(bytes) => std::str::from_utf8(bytes).map(Cow::Borrowed),
+        Cow::Owned(bytes) => String::from_utf8(bytes)
+            .map(Cow::Owned)
+            .map_err(|err| err.utf8_error()),
+    }
+}
+
+/// Percent-decodes `input`, like
+/// `percent_encoding::percent_decode_str(input).decode_utf8()`.
+#[cfg_attr(not(feature = "static-files"), allow(dead_code))]
+pub(crate) fn percent_decode_str(input: &str) -> Result<Cow<'_, str>, Utf8Error> {
+    match decode(input.as_bytes(), false) {
+        Cow::Borrowed(_) => Ok(Cow::Borrowed(input)),
+        Cow::Owned(bytes) => String::from_utf8(bytes)
+            .map(Cow::Owned)
+            .map_err(|err| err.utf8_error()),
+    }
+}
+
+/// Decodes a name or a value of the form, replacing the invalid UTF-8.
+fn decode_part(input: &[u8]) -> Cow<'_, str> {
+    match decode(input, true) {
+        Cow::Borrowed(bytes) => String::from_utf8_lossy(bytes),
+        Cow::Owned(bytes) => match String::from_utf8(bytes) {
+            Ok(s) => Cow::Owned(s),
+            Err(err) => Cow::Owned(String::from_utf8_lossy(err.as_bytes()).into_owned()),
+        },
+    }
+}
+
+/// An iterator of the name and value pairs of a form, like
+/// `form_urlencoded::parse`.
+pub(crate) struct Parse<'a> {
+    input: &'a [u8],
+    /// The input if it is known to be valid UTF-8.
+    text: Option<&'a str>,
+    pos: usize,
+    /// The position of the next `%` or `+`, the parts before it are borrowed
+    /// without decoding.
+    next_escape: Option<usize>,
+}
+
+impl<'a> Parse<'a> {
+    fn new(input: &'a [u8], text: Option<&'a str>) -> Self {
+        Self {
+            input,
+            text,
+            pos: 0,
+            next_escape: memchr::memchr2(b'%', b'+', input),
+        }
+    }
+
+    fn part(&mut self, start: usize, end: usize) -> Cow<'a, str> {
+        if self.next_escape.is_some_and(|pos| pos < start) {
+            self.next_escape =
+                memchr::memchr2(b'%', b'+', &self.input[start..]).map(|pos| start + pos);
+        }
+        match (self.next_escape, self.text) {
+            (Some(pos), _) if pos < end => decode_part(&self.input[start..end]),
+            (_, Some(text)) => Cow::Borrowed(&text[start..end]),
+            (_, None) => String::from_utf8_lossy(&self.input[start..end]),
+        }
+    }
+}
+
+pub(crate) fn parse(input: &[u8]) -> Parse<'_> {
+    Parse::new(input, None)
+}
+
+pub(crate) fn parse_str(input: &str) -> Parse<'_> {
+    Parse::new(input.as_bytes(), Some(input))
+}
+
+impl<'a> Iterator for Parse<'a> {
+    type Item = (Cow<'a, str>, Cow<'a, str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.pos >= self.input.len() {
+                return None;
+            }
+            let start = self.pos;
+            let end = memchr::memchr(b'&', &self.input[start..])
+                .map_or(self.input.len(), |pos| start + pos);
+            self.pos = end + 1;
+            if start == end {
+                continue;
+            }
+            let (name, value) = match memchr::memchr(b'=', &self.input[start..end]) {
+                Some(pos) => (
+                    self.part(start, start + pos),
+                    self.part(start + pos + 1, end),
+                ),
+                None => (self.part(start, end), Cow::Borrowed("")),
+            };
+            return Some((name, value));
+        }
+    }
+}
+
+/// Deserializes a form from the bytes, like `serde_urlencoded::from_bytes`.
+pub(crate) fn from_bytes<'de, T>(input: &'de [u8]) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    T::deserialize(Deserializer {
+        inner: MapDeserializer::new(PartIterator(parse(input))),
+    })
+}
+
+/// Deserializes a form from the string, like `serde_urlencoded::from_str`.
+pub(crate) fn from_str<'de, T>(input: &'de str) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    T::deserialize(Deserializer {
+        inner: MapDeserializer::new(PartIterator(parse_str(input))),
+    })
+}
+
+/// The deserializer of the forms, which behaves the same as
+/// `serde_urlencoded::Deserializer`.
+struct Deserializer<'de> {
+    inner: MapDeserializer<'de, PartIterator<'de>, Error>,
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_map(self.inner)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_seq(self.inner)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.inner.end()?;
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string option bytes
+        byte_buf unit_struct newtype_struct tuple_struct struct identifier tuple
+        enum ignored_any
+    }
+}
+
+struct PartIterator<'de>(Parse<'de>);
+
+impl<'de> Iterator for PartIterator<'de> {
+    type Item = (Part<'de>, Part<'de>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (Part(k), Part(v)))
+    }
+}
+
+struct Part<'de>(Cow<'de, str>);
+
+impl<'de> IntoDeserializer<'de> for Part<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! forward_parsed_value {
+    ($($ty:ident => $method:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                match self.0.parse::<$ty>() {
+                    Ok(val) => val.into_deserializer().$method(visitor),
+                    Err(err) => Err(Error::custom(err)),
+                }
+            }
+        )*
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Part<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
+            Cow::Owned(value) => visitor.visit_string(value),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_enum(ValueEnumAccess(self.0))
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        char str string unit bytes byte_buf unit_struct tuple_struct struct
+        identifier tuple ignored_any seq map
+    }
+
+    forward_parsed_value! {
+        bool => deserialize_bool,
+        u8 => deserialize_u8,
+        u16 => deserialize_u16,
+        u32 => deserialize_u32,
+        u64 => deserialize_u64,
+        i8 => deserialize_i8,
+        i16 => deserialize_i16,
+        i32 => deserialize_i32,
+        i64 => deserialize_i64,
+        f32 => deserialize_f32,
+        f64 => deserialize_f64,
+    }
+}
+
+struct ValueEnumAccess<'de>(Cow<'de, str>);
+
+impl<'de> de::EnumAccess<'de> for ValueEnumAccess<'de> {
+    type Error = Error;
+    type Variant = UnitOnlyVariantAccess;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(self.0.into_deserializer())?;
+        Ok((variant, UnitOnlyVariantAccess))
+    }
+}
+
+struct UnitOnlyVariantAccess;
+
+impl<'de> de::VariantAccess<'de> for UnitOnlyVariantAccess {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, _seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        Err(Error::custom("expected unit variant"))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::custom("expected unit variant"))
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::custom("expected unit variant"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    const INPUTS: &[&str] = &[
+        "",
+        "abc",
+        "a%20b",
+        "a+b",
+        "%",
+        "%2",
+        "%zz",
+        "100%",
+        "%41%42%43",
+        "%e4%B8%ad%E6%96%87",
+        "%ff",
+        "%c3",
+        "a%2Bb+c%",
+        "++",
+        "%%41",
+        "中文%20",
+    ];
+
+    #[test]
+    fn decode_path() {
+        for input in INPUTS {
+            assert_eq!(
+                percent_decode_str(input),
+                percent_encoding::percent_decode_str(input).decode_utf8(),
+                "{input}"
+            );
+            assert_eq!(
+                percent_decode(input.as_bytes()),
+                percent_encoding::percent_decode(input.as_bytes()).decode_utf8(),
+                "{input}"
+            );
+        }
+        assert!(matches!(
+            percent_decode_str("a+b"),
+            Ok(Cow::Borrowed("a+b"))
+        ));
+        assert!(matches!(percent_decode_str("100%"), Ok(Cow::Borrowed(_))));
+    }
+
+    #[test]
+    fn parse_form() {
+        let mut inputs = INPUTS.iter().map(ToString::to_string).collect::<Vec<_>>();
+        for a in INPUTS {
+            for b in INPUTS {
+                inputs.push(format!("{a}={b}&&{b}={a}&{a}"));
+            }
+        }
+        let expected =
+            |input: &[u8]| serde_urlencoded::from_bytes::<Vec<(String, String)>>(input).unwrap();
+        let actual = |input: &[u8]| {
+            parse(input)
+                .map(|(name, value)| (name.into_owned(), value.into_owned()))
+                .collect::<Vec<_>>()
+        };
+        for input in &inputs {
+            assert_eq!(
+                actual(input.as_bytes()),
+                expected(input.as_bytes()),
+                "{input}"
+            );
+            assert_eq!(
+                parse_str(input).collect::<Vec<_>>(),
+                parse(input.as_bytes()).collect::<Vec<_>>(),
+                "{input}"
+            );
+        }
+
+        let invalid = b"a=%ff\xfe&b\xc3=1";
+        assert_eq!(actual(invalid), expected(invalid));
+        assert!(matches!(
+            parse_str("a=b&c=%20&d=e").collect::<Vec<_>>().as_slice(),
+            [
+                (Cow::Borrowed("a"), Cow::Borrowed("b")),
+                (Cow::Borrowed("c"), Cow::Owned(_)),
+                (Cow::Borrowed("d"), Cow::Borrowed("e")),
+            ]
+        ));
+    }
+
+    #[test]
+    fn deserialize() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Order {
+            Asc,
+            Desc,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Params<'a> {
+            name: String,
+            #[serde(borrow)]
+            tag: Cow<'a, str>,
+            page: u32,
+            ratio: f64,
+            enabled: bool,
+            order: Order,
+            limit: Option<i64>,
+        }
+
+        let input = "name=a+b%21&tag=rust&page=2&ratio=0.5&enabled=true&order=desc&limit=-1";
+        let params = from_str::<Params>(input).unwrap();
+        assert_eq!(params, serde_urlencoded::from_str::<Params>(input).unwrap());
+        assert!(matches!(params.tag, Cow::Borrowed("rust")));
+
+        assert_eq!(
+            from_bytes::<Vec<(String, String)>>(b"a=1&b=2&a=3").unwrap(),
+            vec![
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "2".to_string()),
+                ("a".to_string(), "3".to_string())
+            ]
+        );
+        assert_eq!(
+            from_str::<HashMap<String, u8>>("x=1").unwrap(),
+            HashMap::from([("x".to_string(), 1)])
+        );
+        from_str::<()>("").unwrap();
+
+        for input in ["page=a", "order=up", "page=1"] {
+            assert_eq!(
+                from_str::<Params>(input).unwrap_err().to_string(),
+                serde_urlencoded::from_str::<Params>(input)
+                    .unwrap_err()
+                    .to_string()
+            );
+        }
+    }
+}
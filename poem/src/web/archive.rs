@@ -0,0 +1,753 @@
+use std::{
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use futures_util::{stream::BoxStream, Stream, StreamExt, TryStreamExt};
+use http::{header, HeaderValue};
+use hyper::body::Body as _;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+use crate::{Body, IntoResponse, Response};
+
+/// An entry of an archive streamed by [`ZipStream`] or [`TarStream`].
+pub struct ArchiveEntry {
+    path: String,
+    body: Body,
+    size: Option<u64>,
+    modified: Option<SystemTime>,
+    mode: u32,
+}
+
+impl ArchiveEntry {
+    /// Create an archive entry with the path and the content.
+    ///
+    /// The size of the content is required by [`TarStream`], it is taken
+    /// from the body if it is known, such as a body created from bytes,
+    /// otherwise it must be specified with [`ArchiveEntry::size`].
+    pub fn new(path: impl Into<String>, body: impl Into<Body>) -> Self {
+        let body = body.into();
+        Self {
+            path: path.into(),
+            size: body.0.size_hint().exact(),
+            body,
+            modified: None,
+            mode: 0o644,
+        }
+    }
+
+    /// Create an archive entry with the path and the content of the file.
+    ///
+    /// The file is read as the archive is streamed, and the size, the
+    /// modification time and the permissions are taken from its metadata.
+    pub async fn from_file(path: impl Into<String>, file: impl AsRef<Path>) -> IoResult<Self> {
+        let file = tokio::fs::File::open(file).await?;
+        let metadata = file.metadata().await?;
+        if !metadata.is_file() {
+            return Err(IoError::new(ErrorKind::InvalidInput, "not a file"));
+        }
+
+        #[cfg(unix)]
+        let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o777;
+        #[cfg(not(unix))]
+        let mode = if metadata.permissions().readonly() {
+            0o444
+        } else {
+            0o644
+        };
+
+        Ok(Self {
+            path: path.into(),
+            body: Body::from_async_read(file),
+            size: Some(metadata.len()),
+            modified: metadata.modified().ok(),
+            mode,
+        })
+    }
+
+    /// Specify the size of the content.
+    ///
+    /// The stream fails if the length of the content is different.
+    #[must_use]
+    pub fn size(self, size: u64) -> Self {
+        Self {
+            size: Some(size),
+            ..self
+        }
+    }
+
+    /// Specify the modification time.
+    #[must_use]
+    pub fn modified(self, modified: SystemTime) -> Self {
+        Self {
+            modified: Some(modified),
+            ..self
+        }
+    }
+
+    /// Specify the Unix permissions, defaults to `0o644`.
+    #[must_use]
+    pub fn mode(self, mode: u32) -> Self {
+        Self {
+            mode: mode & 0o7777,
+            ..self
+        }
+    }
+}
+
+/// A format of archive which is written as the entries are streamed.
+trait ArchiveFormat: Send + 'static {
+    /// Returns the data written before the content of the entry.
+    fn begin_entry(&mut self, entry: &ArchiveEntry) -> IoResult<Bytes>;
+
+    /// Called for each data chunk of the content of the current entry.
+    fn entry_data(&mut self, data: &[u8]) -> IoResult<()>;
+
+    /// Returns the data written after the content of the current entry.
+    fn end_entry(&mut self) -> IoResult<Bytes>;
+
+    /// Returns the data written after all the entries.
+    fn finish(&mut self) -> IoResult<Bytes>;
+}
+
+struct ArchiveState<F> {
+    entries: BoxStream<'static, IoResult<ArchiveEntry>>,
+    current: Option<BoxStream<'static, IoResult<Bytes>>>,
+    format: F,
+    finished: bool,
+}
+
+/// Creates the body of the archive, the entries are pulled from the stream
+/// only when the previous entry has been written, so nothing is read after
+/// the body is dropped.
+fn archive_body<F: ArchiveFormat>(
+    entries: BoxStream<'static, IoResult<ArchiveEntry>>,
+    format: F,
+) -> Body {
+    let state = ArchiveState {
+        entries,
+        current: None,
+        format,
+        finished: false,
+    };
+    Body::from_bytes_stream(futures_util::stream::try_unfold(
+        state,
+        |mut state| async move {
+            loop {
+                if let Some(content) = &mut state.current {
+                    match content.try_next().await? {
+                        Some(data) => {
+                            state.format.entry_data(&data)?;
+                            if !data.is_empty() {
+                                return Ok(Some((data, state)));
+                            }
+                        }
+                        None => {
+                            state.current = None;
+                            let data = state.format.end_entry()?;
+                            if !data.is_empty() {
+                                return Ok(Some((data, state)));
+                            }
+                        }
+                    }
+                    continue;
+                }
+
+                if state.finished {
+                    return Ok(None);
+                }
+
+                match state.entries.try_next().await? {
+                    Some(entry) => {
+                        let data = state.format.begin_entry(&entry)?;
+                        state.current = Some(entry.body.into_bytes_stream().boxed());
+                        return Ok(Some((data, state)));
+                    }
+                    None => {
+                        state.finished = true;
+                        return Ok::<_, IoError>(Some((state.format.finish()?, state)));
+                    }
+                }
+            }
+        },
+    ))
+}
+
+fn archive_response(body: Body, content_type: &'static str, filename: Option<String>) -> Response {
+    let mut resp = Response::builder().content_type(content_type).body(body);
+    if let Some(value) = filename.and_then(|filename| content_disposition(&filename)) {
+        resp.headers_mut()
+            .insert(header::CONTENT_DISPOSITION, value);
+    }
+    resp
+}
+
+fn content_disposition(filename: &str) -> Option<HeaderValue> {
+    let value = if filename.is_ascii() && !filename.chars().any(|c| c.is_ascii_control()) {
+        format!(
+            "attachment; filename=\"{}\"",
+            filename.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+    } else {
+        format!(
+            "attachment; filename*=UTF-8''{}",
+            utf8_percent_encode(filename, NON_ALPHANUMERIC)
+        )
+    };
+    HeaderValue::try_from(value).ok()
+}
+
+/// A response which streams a zip archive built from a stream of entries,
+/// without buffering the entries.
+///
+/// The entries are stored without compression, and they are pulled from the
+/// stream only as the response body is sent, so the stream is dropped as
+/// soon as the client disconnects.
+///
+/// ZIP64 is not supported, the stream fails if an entry or the archive is
+/// larger than 4 GiB, or if there are more than 65535 entries.
+///
+/// # Example
+///
+/// ```
+/// use futures_util::stream;
+/// use poem::{
+///     handler,
+///     web::{ArchiveEntry, ZipStream},
+/// };
+///
+/// #[handler]
+/// fn download() -> ZipStream {
+///     ZipStream::new(stream::iter([
+///         Ok(ArchiveEntry::new("a.txt", "hello")),
+///         Ok(ArchiveEntry::new("dir/b.txt", "world")),
+///     ]))
+///     .attachment_filename("archive.zip")
+/// }
+/// ```
+pub struct ZipStream {
+    entries: BoxStream<'static, IoResult<ArchiveEntry>>,
+    filename: Option<String>,
+}
+
+impl ZipStream {
+    /// Create a `ZipStream` from a stream of entries.
+    pub fn new(entries: impl Stream<Item = IoResult<ArchiveEntry>> + Send + 'static) -> Self {
+        Self {
+            entries: entries.boxed(),
+            filename: None,
+        }
+    }
+
+    /// Sets the `Content-Disposition` header to download the archive as an
+    /// attachment with the filename.
+    #[must_use]
+    pub fn attachment_filename(self, filename: impl Into<String>) -> Self {
+        Self {
+            filename: Some(filename.into()),
+            ..self
+        }
+    }
+}
+
+impl IntoResponse for ZipStream {
+    fn into_response(self) -> Response {
+        archive_response(
+            archive_body(self.entries, ZipFormat::default()),
+            "application/zip",
+            self.filename,
+        )
+    }
+}
+
+/// A response which streams a tar archive built from a stream of entries,
+/// without buffering the entries.
+///
+/// The size of each entry must be known, see [`ArchiveEntry::new`]. The
+/// entries are pulled from the stream only as the response body is sent, so
+/// the stream is dropped as soon as the client disconnects.
+///
+/// # Example
+///
+/// ```
+/// use futures_util::{stream, StreamExt};
+/// use poem::{
+///     handler,
+///     web::{ArchiveEntry, TarStream},
+/// };
+///
+/// #[handler]
+/// fn download() -> TarStream {
+///     let entries = stream::iter(["Cargo.toml", "README.md"])
+///         .then(|path| ArchiveEntry::from_file(path, path));
+///     TarStream::new(entries).attachment_filename("archive.tar")
+/// }
+/// ```
+pub struct TarStream {
+    entries: BoxStream<'static, IoResult<ArchiveEntry>>,
+    filename: Option<String>,
+}
+
+impl TarStream {
+    /// Create a `TarStream` from a stream of entries.
+    pub fn new(entries: impl Stream<Item = IoResult<ArchiveEntry>> + Send + 'static) -> Self {
+        Self {
+            entries: entries.boxed(),
+            filename: None,
+        }
+    }
+
+    /// Sets the `Content-Disposition` header to download the archive as an
+    /// attachment with the filename.
+    #[must_use]
+    pub fn attachment_filename(self, filename: impl Into<String>) -> Self {
+        Self {
+            filename: Some(filename.into()),
+            ..self
+        }
+    }
+}
+
+impl IntoResponse for TarStream {
+    fn into_response(self) -> Response {
+        archive_response(
+            archive_body(self.entries, TarFormat::default()),
+            "application/x-tar",
+            self.filename,
+        )
+    }
+}
+
+fn unix_time(time: Option<SystemTime>) -> u64 {
+    time.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn too_large(msg: &'static str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, msg)
+}
+
+const TAR_BLOCK_SIZE: usize = 512;
+
+#[derive(Default)]
+struct TarFormat {
+    remaining: u64,
+    size: u64,
+}
+
+/// Writes the number to the field as an octal string terminated by NUL, or
+/// with the base-256 encoding of GNU tar if it is too large.
+fn tar_number(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    if digits >= 22 || value < 1 << (3 * digits) {
+        let s = format!("{value:0digits$o}");
+        field[..digits].copy_from_slice(s.as_bytes());
+        field[digits] = 0;
+    } else {
+        field.fill(0);
+        let len = field.len();
+        field[len - 8..].copy_from_slice(&value.to_be_bytes());
+        field[0] = 0x80;
+    }
+}
+
+fn tar_header(name: &[u8], size: u64, mode: u32, mtime: u64, typeflag: u8) -> [u8; 512] {
+    let mut header = [0u8; TAR_BLOCK_SIZE];
+    let len = name.len().min(100);
+    header[..len].copy_from_slice(&name[..len]);
+    tar_number(&mut header[100..108], mode as u64);
+    tar_number(&mut header[108..116], 0);
+    tar_number(&mut header[116..124], 0);
+    tar_number(&mut header[124..136], size);
+    tar_number(&mut header[136..148], mtime);
+    header[156] = typeflag;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // the checksum is computed with the checksum field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|b| *b as u32).sum();
+    let s = format!("{checksum:06o}\0 ");
+    header[148..156].copy_from_slice(s.as_bytes());
+    header
+}
+
+fn tar_padding(size: u64) -> usize {
+    (TAR_BLOCK_SIZE - (size % TAR_BLOCK_SIZE as u64) as usize) % TAR_BLOCK_SIZE
+}
+
+impl ArchiveFormat for TarFormat {
+    fn begin_entry(&mut self, entry: &ArchiveEntry) -> IoResult<Bytes> {
+        let size = entry.size.ok_or_else(|| {
+            IoError::new(
+                ErrorKind::InvalidInput,
+                "the size of the tar entry is unknown",
+            )
+        })?;
+        let name = entry.path.trim_start_matches('/').as_bytes();
+        let mtime = unix_time(entry.modified);
+        let mut data = BytesMut::new();
+
+        // the names longer than 100 bytes are written in a GNU long name entry
+        if name.len() > 100 {
+            let len = name.len() as u64 + 1;
+            data.put_slice(&tar_header(b"././@LongLink", len, 0o644, 0, b'L'));
+            data.put_slice(name);
+            data.put_bytes(0, 1 + tar_padding(len));
+        }
+        data.put_slice(&tar_header(name, size, entry.mode, mtime, b'0'));
+
+        self.remaining = size;
+        self.size = size;
+        Ok(data.freeze())
+    }
+
+    fn entry_data(&mut self, data: &[u8]) -> IoResult<()> {
+        self.remaining = self
+            .remaining
+            .checked_sub(data.len() as u64)
+            .ok_or_else(|| too_large("the tar entry is larger than its size"))?;
+        Ok(())
+    }
+
+    fn end_entry(&mut self) -> IoResult<Bytes> {
+        if self.remaining > 0 {
+            return Err(IoError::new(
+                ErrorKind::UnexpectedEof,
+                "the tar entry is smaller than its size",
+            ));
+        }
+        Ok(Bytes::from(vec![0; tar_padding(self.size)]))
+    }
+
+    fn finish(&mut self) -> IoResult<Bytes> {
+        Ok(Bytes::from(vec![0; TAR_BLOCK_SIZE * 2]))
+    }
+}
+
+/// The general purpose flags: the sizes are in the data descriptor, and the
+/// names are UTF-8.
+const ZIP_FLAGS: u16 = 0x0808;
+const ZIP_VERSION: u16 = 20;
+
+struct ZipCentralEntry {
+    name: String,
+    crc32: u32,
+    size: u32,
+    offset: u32,
+    time: u16,
+    date: u16,
+    mode: u32,
+}
+
+#[derive(Default)]
+struct ZipFormat {
+    offset: u64,
+    hasher: crc32fast::Hasher,
+    size: u64,
+    entries: Vec<ZipCentralEntry>,
+}
+
+impl ZipFormat {
+    fn advance(&mut self, len: usize) -> IoResult<()> {
+        self.offset += len as u64;
+        if self.offset > u32::MAX as u64 {
+            return Err(too_large("the zip archive is larger than 4 GiB"));
+        }
+        Ok(())
+    }
+}
+
+/// Converts the time to the MS-DOS time and date, in UTC.
+fn dos_datetime(time: Option<SystemTime>) -> (u16, u16) {
+    let secs = unix_time(time);
+    let days = (secs / 86400) as i64;
+    let secs = secs % 86400;
+
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    if !(1980..=2107).contains(&year) {
+        return (0, (1 << 5) | 1);
+    }
+    let time = ((secs / 3600) << 11) | (((secs % 3600) / 60) << 5) | ((secs % 60) / 2);
+    let date = (((year - 1980) as u16) << 9) | ((month as u16) << 5) | day as u16;
+    (time as u16, date)
+}
+
+impl ArchiveFormat for ZipFormat {
+    fn begin_entry(&mut self, entry: &ArchiveEntry) -> IoResult<Bytes> {
+        if self.entries.len() >= u16::MAX as usize {
+            return Err(too_large("the zip archive has more than 65535 entries"));
+        }
+        let name = entry.path.trim_start_matches('/').to_string();
+        let name_len =
+            u16::try_from(name.len()).map_err(|_| too_large("the zip entry name is too long"))?;
+        let (time, date) = dos_datetime(entry.modified);
+
+        let mut data = BytesMut::with_capacity(30 + name.len());
+        data.put_u32_le(0x04034b50);
+        data.put_u16_le(ZIP_VERSION);
+        data.put_u16_le(ZIP_FLAGS);
+        data.put_u16_le(0); // stored
+        data.put_u16_le(time);
+        data.put_u16_le(date);
+        data.put_u32_le(0); // crc-32
+        data.put_u32_le(0); // compressed size
+        data.put_u32_le(0); // uncompressed size
+        data.put_u16_le(name_len);
+        data.put_u16_le(0); // extra field length
+        data.put_slice(name.as_bytes());
+
+        self.entries.push(ZipCentralEntry {
+            name,
+            crc32: 0,
+            size: 0,
+            offset: self.offset as u32,
+            time,
+            date,
+            mode: entry.mode,
+        });
+        self.hasher = crc32fast::Hasher::new();
+        self.size = 0;
+        self.advance(data.len())?;
+        Ok(data.freeze())
+    }
+
+    fn entry_data(&mut self, data: &[u8]) -> IoResult<()> {
+        self.hasher.update(data);
+        self.size += data.len() as u64;
+        if self.size > u32::MAX as u64 {
+            return Err(too_large("the zip entry is larger than 4 GiB"));
+        }
+        self.advance(data.len())
+    }
+
+    fn end_entry(&mut self) -> IoResult<Bytes> {
+        let crc32 = std::mem::take(&mut self.hasher).finalize();
+        let entry = self.entries.last_mut().expect("current entry");
+        entry.crc32 = crc32;
+        entry.size = self.size as u32;
+
+        let mut data = BytesMut::with_capacity(16);
+        data.put_u32_le(0x08074b50);
+        data.put_u32_le(entry.crc32);
+        data.put_u32_le(entry.size);
+        data.put_u32_le(entry.size);
+        self.advance(data.len())?;
+        Ok(data.freeze())
+    }
+
+    fn finish(&mut self) -> IoResult<Bytes> {
+        let offset = self.offset as u32;
+        let mut data = BytesMut::new();
+        for entry in &self.entries {
+            data.put_u32_le(0x02014b50);
+            data.put_u16_le((3 << 8) | ZIP_VERSION); // made by unix
+            data.put_u16_le(ZIP_VERSION);
+            data.put_u16_le(ZIP_FLAGS);
+            data.put_u16_le(0); // stored
+            data.put_u16_le(entry.time);
+            data.put_u16_le(entry.date);
+            data.put_u32_le(entry.crc32);
+            data.put_u32_le(entry.size);
+            data.put_u32_le(entry.size);
+            data.put_u16_le(entry.name.len() as u16);
+            data.put_u16_le(0); // extra field length
+            data.put_u16_le(0); // comment length
+            data.put_u16_le(0); // disk number
+            data.put_u16_le(0); // internal attributes
+            data.put_u32_le((0o100000 | entry.mode) << 16); // regular file
+            data.put_u32_le(entry.offset);
+            data.put_slice(entry.name.as_bytes());
+        }
+        let size = data.len();
+        self.advance(size)?;
+
+        data.put_u32_le(0x06054b50);
+        data.put_u16_le(0); // disk number
+        data.put_u16_le(0); // disk with the central directory
+        data.put_u16_le(self.entries.len() as u16);
+        data.put_u16_le(self.entries.len() as u16);
+        data.put_u32_le(size as u32);
+        data.put_u32_le(offset);
+        data.put_u16_le(0); // comment length
+        Ok(data.freeze())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::stream;
+
+    use super::*;
+
+    fn entries() -> impl Stream<Item = IoResult<ArchiveEntry>> + Send + 'static {
+        stream::iter([
+            Ok(ArchiveEntry::new("a.txt", "hello")
+                .modified(UNIX_EPOCH + Duration::from_secs(1_700_000_000))),
+            Ok(ArchiveEntry::new(
+                "dir/b.txt",
+                Body::from_bytes_stream(stream::iter([Ok::<_, IoError>("wor"), Ok("ld")])),
+            )
+            .size(5)
+            .mode(0o755)),
+        ])
+    }
+
+    fn u16_at(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[tokio::test]
+    async fn zip() {
+        let resp = ZipStream::new(entries())
+            .attachment_filename("archive.zip")
+            .into_response();
+        assert_eq!(resp.content_type(), Some("application/zip"));
+        assert_eq!(
+            resp.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"archive.zip\""
+        );
+        let data = resp.into_body().into_vec().await.unwrap();
+
+        // end of central directory record
+        let eocd = &data[data.len() - 22..];
+        assert_eq!(u32_at(eocd, 0), 0x06054b50);
+        assert_eq!(u16_at(eocd, 10), 2);
+        let cd_size = u32_at(eocd, 12) as usize;
+        let cd_offset = u32_at(eocd, 16) as usize;
+        assert_eq!(cd_offset + cd_size, data.len() - 22);
+
+        let mut offset = cd_offset;
+        let mut names = Vec::new();
+        for content in [&b"hello"[..], b"world"] {
+            let cd = &data[offset..];
+            assert_eq!(u32_at(cd, 0), 0x02014b50);
+            assert_eq!(u32_at(cd, 16), crc32fast::hash(content));
+            assert_eq!(u32_at(cd, 20), 5);
+            let name_len = u16_at(cd, 28) as usize;
+            names.push(String::from_utf8(cd[46..46 + name_len].to_vec()).unwrap());
+
+            let local = &data[u32_at(cd, 42) as usize..];
+            assert_eq!(u32_at(local, 0), 0x04034b50);
+            assert_eq!(u16_at(local, 6), ZIP_FLAGS);
+            let data_offset = 30 + u16_at(local, 26) as usize;
+            assert_eq!(&local[data_offset..data_offset + 5], content);
+            assert_eq!(u32_at(local, data_offset + 5), 0x08074b50);
+            assert_eq!(u32_at(local, data_offset + 9), crc32fast::hash(content));
+
+            offset += 46 + name_len;
+        }
+        assert_eq!(names, ["a.txt", "dir/b.txt"]);
+    }
+
+    #[tokio::test]
+    async fn tar() {
+        let long_name = "d/".repeat(60) + "c.txt";
+        let entries = entries().chain(stream::iter([Ok(ArchiveEntry::new(
+            long_name.clone(),
+            "!",
+        ))]));
+        let resp = TarStream::new(entries).into_response();
+        assert_eq!(resp.content_type(), Some("application/x-tar"));
+        let data = resp.into_body().into_vec().await.unwrap();
+        assert_eq!(data.len() % TAR_BLOCK_SIZE, 0);
+
+        let header = &data[..512];
+        assert_eq!(&header[..6], b"a.txt\0");
+        assert_eq!(&header[124..136], b"00000000005\0");
+        assert_eq!(&header[136..148], b"14524770400\0");
+        assert_eq!(&header[257..263], b"ustar\0");
+        let checksum: u32 = header
+            .iter()
+            .enumerate()
+            .map(|(i, b)| if (148..156).contains(&i) { b' ' } else { *b } as u32)
+            .sum();
+        assert_eq!(&header[148..156], format!("{checksum:06o}\0 ").as_bytes());
+        assert_eq!(&data[512..517], b"hello");
+
+        let header = &data[1024..1536];
+        assert_eq!(&header[..10], b"dir/b.txt\0");
+        assert_eq!(&header[100..108], b"0000755\0");
+        assert_eq!(&data[1536..1541], b"world");
+
+        let header = &data[2048..2560];
+        assert_eq!(&header[..14], b"././@LongLink\0");
+        assert_eq!(header[156], b'L');
+        assert_eq!(&data[2560..2560 + long_name.len()], long_name.as_bytes());
+        assert_eq!(&data[3072..3172], &long_name.as_bytes()[..100]);
+        assert_eq!(data[3584], b'!');
+
+        assert_eq!(data.len(), 4096 + 1024);
+        assert!(data[4096..].iter().all(|b| *b == 0));
+    }
+
+    #[tokio::test]
+    async fn tar_size_mismatch() {
+        let entries = stream::iter([Ok(ArchiveEntry::new(
+            "a.txt",
+            Body::from_bytes_stream(stream::iter([Ok::<_, IoError>("hello")])),
+        ))]);
+        assert!(TarStream::new(entries)
+            .into_response()
+            .into_body()
+            .into_vec()
+            .await
+            .is_err());
+
+        let entries = stream::iter([Ok(ArchiveEntry::new("a.txt", "hello").size(4))]);
+        assert!(TarStream::new(entries)
+            .into_response()
+            .into_body()
+            .into_vec()
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn tar_number_base256() {
+        let mut field = [0u8; 12];
+        tar_number(&mut field, 1 << 40);
+        assert_eq!(field[0], 0x80);
+        assert_eq!(&field[4..], &(1u64 << 40).to_be_bytes());
+    }
+
+    #[test]
+    fn dos_time() {
+        // 2023-11-14 22:13:20 UTC
+        let (time, date) = dos_datetime(Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+        assert_eq!(date, ((2023 - 1980) << 9) | (11 << 5) | 14);
+        assert_eq!(time, (22 << 11) | (13 << 5) | 10);
+        assert_eq!(dos_datetime(None), (0, (1 << 5) | 1));
+    }
+
+    #[test]
+    fn content_disposition_filename() {
+        assert_eq!(
+            content_disposition("a \"b\".zip").unwrap(),
+            "attachment; filename=\"a \\\"b\\\".zip\""
+        );
+        assert_eq!(
+            content_disposition("文件.zip").unwrap(),
+            "attachment; filename*=UTF-8''%E6%96%87%E4%BB%B6%2Ezip"
+        );
+    }
+}
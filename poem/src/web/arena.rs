@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use bumpalo::Bump;
+use parking_lot::Mutex;
+
+use crate::{FromRequest, Request, RequestBody, Result};
+
+/// A bump arena for the allocations of the current request, which is reset
+/// and reused for another request after the response is returned.
+///
+/// It is used for the short-lived allocations, such as the strings built to
+/// parse or format the headers, which are freed all at once instead of one by
+/// one.
+///
+/// See also [`Arena`](crate::middleware::Arena)
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     http::HeaderMap,
+///     web::{bumpalo::collections::String, RequestArena},
+/// };
+///
+/// #[handler]
+/// fn index(arena: &RequestArena, headers: &HeaderMap) -> std::string::String {
+///     arena.with(|bump| {
+///         let mut names = String::new_in(bump);
+///         for name in headers.keys() {
+///             names.push_str(name.as_str());
+///             names.push(',');
+///         }
+///         names.trim_end_matches(',').to_uppercase()
+///     })
+/// }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "arena")))]
+#[derive(Debug, Clone)]
+pub struct RequestArena(Arc<Mutex<Bump>>);
+
+impl RequestArena {
+    pub(crate) fn new(bump: Bump) -> Self {
+        Self(Arc::new(Mutex::new(bump)))
+    }
+
+    /// Returns the arena if it is not used by the others.
+    pub(crate) fn into_inner(self) -> Option<Bump> {
+        Arc::try_unwrap(self.0).ok().map(Mutex::into_inner)
+    }
+
+    /// Calls `f` with the arena, the allocations can not outlive the call.
+    pub fn with<R>(&self, f: impl FnOnce(&Bump) -> R) -> R {
+        f(&self.0.lock())
+    }
+
+    /// Returns the number of the bytes allocated by the arena, including the
+    /// unused capacity.
+    pub fn allocated_bytes(&self) -> usize {
+        self.0.lock().allocated_bytes()
+    }
+}
+
+impl<'a> FromRequest<'a> for &'a RequestArena {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<RequestArena>()
+            .expect("To use the `RequestArena` extractor, the `Arena` middleware is required."))
+    }
+}
@@ -0,0 +1,77 @@
+use tokio_util::sync::CancellationToken;
+
+use crate::{FromRequest, Request, RequestBody, Result};
+
+/// An extractor that returns a token which is cancelled when the client
+/// disconnects, the server begins graceful shutdown, or the request exceeds
+/// the [`Timeout`](crate::middleware::Timeout) middleware.
+///
+/// Handlers doing long work can check it, or select over
+/// [`cancelled`](CancelToken::cancelled), to abort early. Since the handler
+/// future itself is dropped when the client disconnects, this is mostly
+/// useful for the work spawned by the handler.
+///
+/// If the request is not served by [`Server`](crate::Server), the token is
+/// only cancelled by the `Timeout` middleware.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{handler, web::CancelToken};
+///
+/// #[handler]
+/// async fn index(token: CancelToken) -> String {
+///     let job = tokio::spawn({
+///         let token = token.clone();
+///         async move {
+///             tokio::select! {
+///                 _ = tokio::time::sleep(Duration::from_secs(1)) => "done",
+///                 _ = token.cancelled() => "aborted",
+///             }
+///         }
+///     });
+///     job.await.unwrap().to_string()
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(CancellationToken);
+
+impl CancelToken {
+    pub(crate) fn new(token: CancellationToken) -> Self {
+        Self(token)
+    }
+
+    /// Returns `true` if the token has been cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.is_cancelled()
+    }
+
+    /// Waits until the token is cancelled.
+    pub async fn cancelled(&self) {
+        self.0.cancelled().await
+    }
+
+    /// Creates a child token, which is cancelled when this token is
+    /// cancelled, and can be cancelled independently.
+    pub fn child_token(&self) -> CancellationToken {
+        self.0.child_token()
+    }
+
+    /// Consumes this token and returns the inner [`CancellationToken`].
+    pub fn into_inner(self) -> CancellationToken {
+        self.0
+    }
+}
+
+impl<'a> FromRequest<'a> for CancelToken {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<CancelToken>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
@@ -0,0 +1,214 @@
+use headers::{ETag, HeaderMap, HeaderMapExt, IfMatch, IfNoneMatch};
+use serde::Serialize;
+
+use crate::{
+    error::PreconditionError,
+    hash::fnv1a,
+    http::{header, StatusCode},
+    FromRequest, IntoResponse, Request, RequestBody, Response, Result,
+};
+
+/// Returns a strong entity tag computed from the content.
+pub fn etag_for(content: &[u8]) -> ETag {
+    format!("\"{:016x}\"", fnv1a(content))
+        .parse()
+        .expect("valid etag")
+}
+
+/// Returns a strong entity tag computed from the JSON serialization of the
+/// value.
+pub fn json_etag(value: &impl Serialize) -> Result<ETag, serde_json::Error> {
+    Ok(etag_for(&serde_json::to_vec(value)?))
+}
+
+/// An extractor for the `If-Match` and `If-None-Match` preconditions of the
+/// request.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     error::InternalServerError,
+///     handler,
+///     web::{json_etag, Json, Preconditions},
+///     Result,
+/// };
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// # fn load_user() -> User { User { name: "a".to_string() } }
+/// #[handler]
+/// fn update_user(preconditions: Preconditions, Json(user): Json<User>) -> Result<()> {
+///     let current = load_user();
+///     // rejects with `412 Precondition Failed` if the user has been modified
+///     preconditions.check(Some(&json_etag(&current).map_err(InternalServerError)?))?;
+///     // save the user...
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Preconditions {
+    if_match: Option<IfMatch>,
+    if_none_match: Option<IfNoneMatch>,
+}
+
+impl Preconditions {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Self {
+        Self {
+            if_match: headers.typed_get(),
+            if_none_match: headers.typed_get(),
+        }
+    }
+
+    /// Returns `true` if the request has the `If-Match` header.
+    pub fn has_if_match(&self) -> bool {
+        self.if_match.is_some()
+    }
+
+    /// Returns `true` if the request has neither the `If-Match` nor the
+    /// `If-None-Match` header.
+    pub fn is_empty(&self) -> bool {
+        self.if_match.is_none() && self.if_none_match.is_none()
+    }
+
+    /// Checks the preconditions against the entity tag of the current
+    /// representation, which is `None` if the resource does not exist.
+    ///
+    /// Use it for the state-changing methods, such as `PUT`, `PATCH` and
+    /// `DELETE`.
+    pub fn check(&self, current: Option<&ETag>) -> Result<(), PreconditionError> {
+        let passes = match current {
+            Some(etag) => {
+                self.if_match
+                    .as_ref()
+                    .map_or(true, |if_match| if_match.precondition_passes(etag))
+                    && self.if_none_match.as_ref().map_or(true, |if_none_match| {
+                        if_none_match.precondition_passes(etag)
+                    })
+            }
+            None => self.if_match.is_none(),
+        };
+        if passes {
+            Ok(())
+        } else {
+            Err(PreconditionError::Failed)
+        }
+    }
+
+    /// Returns `true` if the `If-None-Match` header matches the entity tag, so
+    /// the response to a `GET` or `HEAD` request should be `304 Not
+    /// Modified`.
+    pub fn is_not_modified(&self, current: &ETag) -> bool {
+        self.if_none_match
+            .as_ref()
+            .is_some_and(|if_none_match| !if_none_match.precondition_passes(current))
+    }
+}
+
+impl<'a> FromRequest<'a> for Preconditions {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(Self::from_headers(req.headers()))
+    }
+}
+
+/// A JSON response with the `ETag` header computed from the body.
+///
+/// Use it with the
+/// [`ConditionalRequest`](crate::middleware::ConditionalRequest) middleware to
+/// respond `304 Not Modified` to the conditional `GET` requests.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     test::TestClient,
+///     web::{headers::{ETag, HeaderMapExt}, json_etag, TaggedJson},
+/// };
+/// use serde_json::json;
+///
+/// #[handler]
+/// fn index() -> TaggedJson<serde_json::Value> {
+///     TaggedJson(json!({ "name": "a" }))
+/// }
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = TestClient::new(get(index)).get("/").send().await;
+/// resp.assert_status_is_ok();
+/// assert_eq!(
+///     resp.0.headers().typed_get::<ETag>(),
+///     Some(json_etag(&json!({ "name": "a" })).unwrap())
+/// );
+/// # });
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct TaggedJson<T>(pub T);
+
+impl<T: Serialize + Send> IntoResponse for TaggedJson<T> {
+    fn into_response(self) -> Response {
+        let data = match serde_json::to_vec(&self.0) {
+            Ok(data) => data,
+            Err(err) => {
+                return Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(err.to_string())
+            }
+        };
+        let mut resp = Response::builder()
+            .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+            .finish();
+        resp.headers_mut().typed_insert(etag_for(&data));
+        resp.set_body(data);
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preconditions(if_match: Option<&str>, if_none_match: Option<&str>) -> Preconditions {
+        let mut req = Request::builder();
+        if let Some(value) = if_match {
+            req = req.header(header::IF_MATCH, value);
+        }
+        if let Some(value) = if_none_match {
+            req = req.header(header::IF_NONE_MATCH, value);
+        }
+        Preconditions::from_headers(req.finish().headers())
+    }
+
+    #[test]
+    fn check() {
+        let etag = json_etag(&serde_json::json!({ "a": 1 })).unwrap();
+        let tag = format!("\"{:016x}\"", fnv1a(br#"{"a":1}"#));
+        assert_eq!(etag, json_etag(&serde_json::json!({ "a": 1 })).unwrap());
+        assert_ne!(etag, json_etag(&serde_json::json!({ "a": 2 })).unwrap());
+
+        assert!(preconditions(None, None).check(Some(&etag)).is_ok());
+        assert!(preconditions(None, None).check(None).is_ok());
+        assert!(preconditions(Some(&tag), None).check(Some(&etag)).is_ok());
+        assert!(preconditions(Some("*"), None).check(Some(&etag)).is_ok());
+        assert_eq!(
+            preconditions(Some("\"other\""), None).check(Some(&etag)),
+            Err(PreconditionError::Failed)
+        );
+        assert_eq!(
+            preconditions(Some("*"), None).check(None),
+            Err(PreconditionError::Failed)
+        );
+        assert!(preconditions(None, Some("*")).check(None).is_ok());
+        assert_eq!(
+            preconditions(None, Some("*")).check(Some(&etag)),
+            Err(PreconditionError::Failed)
+        );
+
+        assert!(preconditions(None, Some(&tag)).is_not_modified(&etag));
+        assert!(!preconditions(None, Some("\"other\"")).is_not_modified(&etag));
+        assert!(!preconditions(None, None).is_not_modified(&etag));
+    }
+}
@@ -14,7 +14,7 @@ use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    error::ParseCookieError,
+    error::{InvalidCookieError, ParseCookieError},
     http::{header, HeaderMap},
     FromRequest, Request, RequestBody, Result,
 };
@@ -22,6 +22,30 @@ use crate::{
 /// The `SameSite` cookie attribute.
 pub type SameSite = libcookie::SameSite;
 
+/// The prefixes of the cookie names which require the browsers to enforce
+/// the attributes of the cookies.
+///
+/// Reference: <https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Set-Cookie#cookie_prefixes>
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CookiePrefix {
+    /// The `__Secure-` prefix, the cookie must be secure.
+    Secure,
+
+    /// The `__Host-` prefix, the cookie must be secure, must not have a domain
+    /// and its path must be `/`.
+    Host,
+}
+
+impl CookiePrefix {
+    /// Returns the prefix as a string slice.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CookiePrefix::Secure => "__Secure-",
+            CookiePrefix::Host => "__Host-",
+        }
+    }
+}
+
 /// HTTP cookie extractor.
 ///
 /// # Errors
@@ -83,6 +107,27 @@ impl Cookie {
         ))
     }
 
+    /// Creates a [`CookieBuilder`] with the modern defaults.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use poem::web::cookie::{Cookie, CookiePrefix, SameSite};
+    ///
+    /// let cookie = Cookie::builder("id", "1")
+    ///     .prefix(CookiePrefix::Host)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(cookie.name(), "__Host-id");
+    /// assert!(cookie.secure());
+    /// assert!(cookie.http_only());
+    /// assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+    /// assert_eq!(cookie.path(), Some("/"));
+    /// ```
+    pub fn builder(name: impl Into<String>, value: impl Into<String>) -> CookieBuilder {
+        CookieBuilder::new(name, value)
+    }
+
     /// Returns the Domain of the cookie if one was specified.
     ///
     /// # Example
@@ -219,6 +264,67 @@ impl Cookie {
         self.0.same_site()
     }
 
+    /// Returns whether this cookie was marked `Partitioned` or not.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use poem::web::cookie::Cookie;
+    ///
+    /// let cookie = Cookie::parse("foo=bar; Secure; Partitioned").unwrap();
+    /// assert!(cookie.partitioned());
+    /// ```
+    pub fn partitioned(&self) -> bool {
+        self.0.partitioned().unwrap_or_default()
+    }
+
+    /// Returns the prefix of the name of this cookie if it has one.
+    ///
+    /// The prefixes are case-sensitive.
+    pub fn prefix(&self) -> Option<CookiePrefix> {
+        [CookiePrefix::Host, CookiePrefix::Secure]
+            .into_iter()
+            .find(|prefix| self.name().starts_with(prefix.as_str()))
+    }
+
+    /// Checks that the attributes of this cookie satisfy the requirements of
+    /// its [`prefix`](Cookie::prefix) and the `Partitioned` attribute,
+    /// otherwise the browsers reject it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use poem::{error::InvalidCookieError, web::cookie::Cookie};
+    ///
+    /// let cookie = Cookie::parse("__Host-id=1; Secure; Path=/").unwrap();
+    /// assert!(cookie.validate().is_ok());
+    ///
+    /// let cookie = Cookie::parse("__Host-id=1; Secure; Path=/; Domain=example.com").unwrap();
+    /// assert_eq!(
+    ///     cookie.validate(),
+    ///     Err(InvalidCookieError::HostPrefixWithDomain)
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), InvalidCookieError> {
+        if let Some(prefix) = self.prefix() {
+            if !self.secure() {
+                return Err(InvalidCookieError::PrefixRequiresSecure(prefix.as_str()));
+            }
+            if prefix == CookiePrefix::Host {
+                if self.domain().is_some() {
+                    return Err(InvalidCookieError::HostPrefixWithDomain);
+                }
+                if self.path() != Some("/") {
+                    return Err(InvalidCookieError::HostPrefixRequiresRootPath);
+                }
+            }
+        }
+        if self.partitioned() && !self.secure() {
+            return Err(InvalidCookieError::PartitionedRequiresSecure);
+        }
+        Ok(())
+    }
+
     /// Returns whether this cookie was marked `Secure` or not.
     ///
     /// # Example
@@ -273,6 +379,11 @@ impl Cookie {
         self.0.set_same_site(value);
     }
 
+    /// Sets the value of `Partitioned` in `self` to `value`.
+    pub fn set_partitioned(&mut self, value: impl Into<Option<bool>>) {
+        self.0.set_partitioned(value);
+    }
+
     /// Sets the value of `Secure` in `self` to `value`.
     pub fn set_secure(&mut self, value: impl Into<Option<bool>>) {
         self.0.set_secure(value);
@@ -328,6 +439,118 @@ impl<'a> FromRequest<'a> for Cookie {
     }
 }
 
+/// A builder of [`Cookie`] which enforces the modern defaults, created by
+/// [`Cookie::builder`].
+///
+/// The cookies are `Secure`, `HttpOnly`, `SameSite=Lax` and have the path
+/// `/` by default, and [`CookieBuilder::build`] validates the cookie with
+/// [`Cookie::validate`].
+#[derive(Debug, Clone)]
+pub struct CookieBuilder {
+    cookie: Cookie,
+    prefix: Option<CookiePrefix>,
+}
+
+impl CookieBuilder {
+    /// Creates a `CookieBuilder` with the name and the value.
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        let mut cookie = Cookie::new_with_str(name, value);
+        cookie.set_secure(true);
+        cookie.set_http_only(true);
+        cookie.set_same_site(SameSite::Lax);
+        cookie.set_path("/");
+        Self {
+            cookie,
+            prefix: None,
+        }
+    }
+
+    /// Prepends the prefix to the name of the cookie.
+    ///
+    /// The domain of the cookie is removed for the `__Host-` prefix.
+    #[must_use]
+    pub fn prefix(self, prefix: CookiePrefix) -> Self {
+        Self {
+            prefix: Some(prefix),
+            ..self
+        }
+    }
+
+    /// Sets the `Domain` of the cookie.
+    #[must_use]
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.cookie.set_domain(domain);
+        self
+    }
+
+    /// Sets the `Path` of the cookie. Default is `/`.
+    #[must_use]
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.cookie.set_path(path);
+        self
+    }
+
+    /// Sets the `Secure` of the cookie. Default is `true`.
+    #[must_use]
+    pub fn secure(mut self, value: bool) -> Self {
+        self.cookie.set_secure(value);
+        self
+    }
+
+    /// Sets the `HttpOnly` of the cookie. Default is `true`.
+    #[must_use]
+    pub fn http_only(mut self, value: bool) -> Self {
+        self.cookie.set_http_only(value);
+        self
+    }
+
+    /// Sets the `SameSite` of the cookie. Default is `Lax`.
+    #[must_use]
+    pub fn same_site(mut self, value: impl Into<Option<SameSite>>) -> Self {
+        self.cookie.set_same_site(value);
+        self
+    }
+
+    /// Sets the `Max-Age` of the cookie.
+    #[must_use]
+    pub fn max_age(mut self, value: Duration) -> Self {
+        self.cookie.set_max_age(value);
+        self
+    }
+
+    /// Sets the `Expires` of the cookie.
+    #[must_use]
+    pub fn expires(mut self, time: DateTime<impl TimeZone>) -> Self {
+        self.cookie.set_expires(time);
+        self
+    }
+
+    /// Sets the `Partitioned` of the cookie, to store the cookie separately
+    /// for each top-level site ([CHIPS](https://developer.mozilla.org/en-US/docs/Web/Privacy/Privacy_sandbox/Partitioned_cookies)).
+    #[must_use]
+    pub fn partitioned(mut self, value: bool) -> Self {
+        self.cookie.set_partitioned(value);
+        self
+    }
+
+    /// Builds the cookie, returns an error if the cookie violates the
+    /// requirements of its prefix or attributes.
+    pub fn build(self) -> Result<Cookie, InvalidCookieError> {
+        let Self { mut cookie, prefix } = self;
+        if let Some(prefix) = prefix {
+            if !cookie.name().starts_with(prefix.as_str()) {
+                let name = format!("{}{}", prefix.as_str(), cookie.name());
+                cookie.set_name(name);
+            }
+            if prefix == CookiePrefix::Host {
+                cookie.0.unset_domain();
+            }
+        }
+        cookie.validate()?;
+        Ok(cookie)
+    }
+}
+
 /// A collection of cookies that tracks its modifications.
 ///
 /// # Example
@@ -752,4 +975,80 @@ mod tests {
             vec![String::from("a"), String::from("b"), String::from("c")]
         );
     }
+
+    #[test]
+    fn prefixes() {
+        let cookie = Cookie::parse("__Secure-a=1").unwrap();
+        assert_eq!(cookie.prefix(), Some(CookiePrefix::Secure));
+        assert_eq!(
+            cookie.validate(),
+            Err(InvalidCookieError::PrefixRequiresSecure("__Secure-"))
+        );
+        assert!(Cookie::parse("__Secure-a=1; Secure; Domain=a.com")
+            .unwrap()
+            .validate()
+            .is_ok());
+
+        let cookie = Cookie::parse("__Host-a=1; Secure").unwrap();
+        assert_eq!(cookie.prefix(), Some(CookiePrefix::Host));
+        assert_eq!(
+            cookie.validate(),
+            Err(InvalidCookieError::HostPrefixRequiresRootPath)
+        );
+        assert_eq!(
+            Cookie::parse("__Host-a=1; Path=/").unwrap().validate(),
+            Err(InvalidCookieError::PrefixRequiresSecure("__Host-"))
+        );
+
+        let cookie = Cookie::parse("__host-a=1").unwrap();
+        assert_eq!(cookie.prefix(), None);
+        assert!(cookie.validate().is_ok());
+
+        assert_eq!(
+            Cookie::parse("a=1; Partitioned").unwrap().validate(),
+            Err(InvalidCookieError::PartitionedRequiresSecure)
+        );
+    }
+
+    #[test]
+    fn builder() {
+        let cookie = Cookie::builder("a", "1")
+            .domain("a.com")
+            .prefix(CookiePrefix::Host)
+            .partitioned(true)
+            .max_age(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        assert_eq!(
+            cookie.to_string(),
+            "__Host-a=1; HttpOnly; SameSite=Lax; Partitioned; Secure; Path=/; Max-Age=60"
+        );
+
+        let cookie = Cookie::builder("__Secure-a", "1")
+            .prefix(CookiePrefix::Secure)
+            .same_site(SameSite::Strict)
+            .http_only(false)
+            .build()
+            .unwrap();
+        assert_eq!(cookie.name(), "__Secure-a");
+        assert!(!cookie.http_only());
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+
+        assert_eq!(
+            Cookie::builder("a", "1")
+                .prefix(CookiePrefix::Secure)
+                .secure(false)
+                .build()
+                .unwrap_err(),
+            InvalidCookieError::PrefixRequiresSecure("__Secure-")
+        );
+        assert_eq!(
+            Cookie::builder("a", "1")
+                .prefix(CookiePrefix::Host)
+                .path("/api")
+                .build()
+                .unwrap_err(),
+            InvalidCookieError::HostPrefixRequiresRootPath
+        );
+    }
 }
@@ -0,0 +1,30 @@
+use std::ops::Deref;
+
+use crate::{FromRequest, Request, RequestBody, Result};
+
+/// The nonce of the `Content-Security-Policy` for the current response.
+///
+/// Use it in the `nonce` attribute of the inline `<script>` and `<style>`
+/// elements.
+///
+/// See also [`SecureHeaders`](crate::middleware::SecureHeaders)
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CspNonce(pub String);
+
+impl Deref for CspNonce {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> FromRequest<'a> for &'a CspNonce {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req.extensions().get::<CspNonce>().expect(
+            "To use the `CspNonce` extractor, the `SecureHeaders` middleware with a nonce source \
+             in the content security policy is required.",
+        ))
+    }
+}
@@ -0,0 +1,423 @@
+use std::mem;
+
+use serde::{
+    de::{
+        value::{MapDeserializer, SeqDeserializer},
+        IntoDeserializer, Visitor,
+    },
+    forward_to_deserialize_any, Deserializer,
+};
+
+use super::ser::CsvError;
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum State {
+    StartField,
+    Unquoted,
+    Quoted,
+    /// A quote in a quoted field, which is either escaped or closes the field.
+    QuotedQuote,
+}
+
+/// An incremental parser of the records described in RFC 4180.
+///
+/// The records may be terminated by CRLF, LF or CR, and the empty lines are
+/// skipped.
+pub(crate) struct CsvParser {
+    state: State,
+    skip_lf: bool,
+    quoted: bool,
+    field: Vec<u8>,
+    record: Vec<String>,
+}
+
+impl Default for CsvParser {
+    fn default() -> Self {
+        Self {
+            state: State::StartField,
+            skip_lf: false,
+            quoted: false,
+            field: Vec::new(),
+            record: Vec::new(),
+        }
+    }
+}
+
+impl CsvParser {
+    /// Parses the data and appends the completed records.
+    pub(crate) fn feed(
+        &mut self,
+        data: &[u8],
+        records: &mut Vec<Vec<String>>,
+    ) -> Result<(), CsvError> {
+        for &b in data {
+            if mem::take(&mut self.skip_lf) && b == b'\n' {
+                continue;
+            }
+
+            match (self.state, b) {
+                (State::StartField, b'"') => {
+                    self.state = State::Quoted;
+                    self.quoted = true;
+                }
+                (State::StartField | State::Unquoted | State::QuotedQuote, b',') => {
+                    self.end_field()?;
+                    self.state = State::StartField;
+                }
+                (State::StartField | State::Unquoted | State::QuotedQuote, b'\r' | b'\n') => {
+                    self.skip_lf = b == b'\r';
+                    self.end_record(records)?;
+                    self.state = State::StartField;
+                }
+                (State::StartField | State::Unquoted, _) => {
+                    self.field.push(b);
+                    self.state = State::Unquoted;
+                }
+                (State::Quoted, b'"') => self.state = State::QuotedQuote,
+                (State::Quoted, _) => self.field.push(b),
+                (State::QuotedQuote, b'"') => {
+                    self.field.push(b'"');
+                    self.state = State::Quoted;
+                }
+                (State::QuotedQuote, _) => {
+                    return Err(CsvError(
+                        "unexpected character after a closing quote".to_string(),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Completes the last record at the end of the data.
+    pub(crate) fn finish(&mut self, records: &mut Vec<Vec<String>>) -> Result<(), CsvError> {
+        match self.state {
+            State::Quoted => Err(CsvError("unterminated quoted field".to_string())),
+            State::StartField if self.record.is_empty() => Ok(()),
+            _ => self.end_record(records),
+        }
+    }
+
+    fn end_field(&mut self) -> Result<(), CsvError> {
+        let field = String::from_utf8(mem::take(&mut self.field))
+            .map_err(|_| CsvError("invalid utf-8 field".to_string()))?;
+        self.record.push(field);
+        Ok(())
+    }
+
+    fn end_record(&mut self, records: &mut Vec<Vec<String>>) -> Result<(), CsvError> {
+        let empty_line =
+            self.record.is_empty() && self.field.is_empty() && !mem::take(&mut self.quoted);
+        self.quoted = false;
+        if empty_line {
+            return Ok(());
+        }
+        self.end_field()?;
+        records.push(mem::take(&mut self.record));
+        Ok(())
+    }
+}
+
+/// Deserializes a record as a map from the names in the header to the
+/// fields, or as a sequence of the fields.
+pub(crate) struct RecordDeserializer<'de> {
+    pub(crate) header: &'de [String],
+    pub(crate) fields: &'de [String],
+}
+
+impl<'de> Deserializer<'de> for RecordDeserializer<'de> {
+    type Error = CsvError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.header.len() != self.fields.len() {
+            return Err(CsvError(format!(
+                "wrong number of fields: {} expected {}",
+                self.fields.len(),
+                self.header.len()
+            )));
+        }
+        let mut map = MapDeserializer::new(
+            self.header
+                .iter()
+                .map(String::as_str)
+                .zip(self.fields.iter().map(|field| FieldDeserializer(field))),
+        );
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let mut seq =
+            SeqDeserializer::new(self.fields.iter().map(|field| FieldDeserializer(field)));
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct enum identifier ignored_any
+    }
+}
+
+/// Deserializes a field, the empty field is `None` for the optional values.
+struct FieldDeserializer<'de>(&'de str);
+
+impl<'de> IntoDeserializer<'de, CsvError> for FieldDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! parse_field {
+    ($($method:ident => $visit:ident),*) => {
+        $(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            match self.0.trim().parse() {
+                Ok(value) => visitor.$visit(value),
+                Err(_) => Err(CsvError(format!(
+                    "can not parse `{}` to a `{}`",
+                    self.0,
+                    stringify!($method).trim_start_matches("deserialize_")
+                ))),
+            }
+        }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for FieldDeserializer<'de> {
+    type Error = CsvError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    parse_field!(
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char
+    );
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_bytes(self.0.as_bytes())
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.0.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    fn parse(chunks: &[&str]) -> Result<Vec<Vec<String>>, CsvError> {
+        let mut parser = CsvParser::default();
+        let mut records = Vec::new();
+        for chunk in chunks {
+            parser.feed(chunk.as_bytes(), &mut records)?;
+        }
+        parser.finish(&mut records)?;
+        Ok(records)
+    }
+
+    #[test]
+    fn parse_records() {
+        assert_eq!(
+            parse(&[
+                "a,b\r\n1,\"x,",
+                "\"\"y\"\"\"\r",
+                "\n\n2,\"line\r\nbreak\"\r3,"
+            ])
+            .unwrap(),
+            vec![
+                vec!["a", "b"],
+                vec!["1", "x,\"y\""],
+                vec!["2", "line\r\nbreak"],
+                vec!["3", ""],
+            ]
+        );
+        assert_eq!(parse(&["\"\"\n"]).unwrap(), vec![vec![""]]);
+        assert_eq!(parse(&["a\n"]).unwrap(), vec![vec!["a"]]);
+        assert!(parse(&[""]).unwrap().is_empty());
+
+        assert_eq!(
+            parse(&["\"a\"b"]).unwrap_err(),
+            CsvError("unexpected character after a closing quote".to_string())
+        );
+        assert_eq!(
+            parse(&["\"a"]).unwrap_err(),
+            CsvError("unterminated quoted field".to_string())
+        );
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Kind {
+        Admin,
+        User,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct User {
+        name: String,
+        age: Option<u8>,
+        kind: Kind,
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn deserialize_record() {
+        let header = strings(&["kind", "name", "age", "other"]);
+
+        let fields = strings(&["Admin", "a", "", "x"]);
+        assert_eq!(
+            User::deserialize(RecordDeserializer {
+                header: &header,
+                fields: &fields,
+            })
+            .unwrap(),
+            User {
+                name: "a".to_string(),
+                age: None,
+                kind: Kind::Admin,
+            }
+        );
+
+        let fields = strings(&["User", "b", "18", "x"]);
+        assert_eq!(
+            User::deserialize(RecordDeserializer {
+                header: &header,
+                fields: &fields,
+            })
+            .unwrap()
+            .age,
+            Some(18)
+        );
+
+        let fields = strings(&["User", "b", "abc", "x"]);
+        assert_eq!(
+            User::deserialize(RecordDeserializer {
+                header: &header,
+                fields: &fields,
+            })
+            .unwrap_err(),
+            CsvError("can not parse `abc` to a `u8`".to_string())
+        );
+
+        let fields = strings(&["User", "b"]);
+        assert!(User::deserialize(RecordDeserializer {
+            header: &header,
+            fields: &fields,
+        })
+        .is_err());
+
+        let fields = strings(&["1", "true"]);
+        assert_eq!(
+            <(i32, bool)>::deserialize(RecordDeserializer {
+                header: &header,
+                fields: &fields,
+            })
+            .unwrap(),
+            (1, true)
+        );
+    }
+}
@@ -0,0 +1,374 @@
+mod de;
+mod ser;
+
+use std::{
+    collections::VecDeque,
+    io::{Error as IoError, ErrorKind},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures_util::{stream::BoxStream, Stream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+
+use self::{
+    de::{CsvParser, RecordDeserializer},
+    ser::{to_record, write_record},
+};
+use crate::{
+    error::ParseCsvError, http::header, Body, FromRequest, IntoResponse, Request, RequestBody,
+    Response, Result,
+};
+
+/// A response which streams the rows as CSV.
+///
+/// The rows are written as records described in
+/// [RFC 4180](https://www.rfc-editor.org/rfc/rfc4180), and the fields are
+/// quoted only if needed. If the rows are structs or maps, a header row with
+/// the names of the fields of the first row is written first.
+///
+/// Nested sequences, maps and structs are not supported, the body fails when
+/// such a row is written.
+///
+/// # Example
+///
+/// ```
+/// use futures_util::stream;
+/// use poem::{get, handler, test::TestClient, web::Csv, Route};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     name: String,
+///     age: Option<u8>,
+/// }
+///
+/// #[handler]
+/// fn index() -> Csv<impl futures_util::Stream<Item = User> + Send + 'static> {
+///     Csv(stream::iter([
+///         User {
+///             name: "Alice, Jr.".to_string(),
+///             age: Some(20),
+///         },
+///         User {
+///             name: "Bob".to_string(),
+///             age: None,
+///         },
+///     ]))
+/// }
+///
+/// let app = Route::new().at("/", get(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_content_type("text/csv; charset=utf-8");
+/// resp.assert_text("name,age\r\n\"Alice, Jr.\",20\r\nBob,\r\n")
+///     .await;
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "csv")))]
+pub struct Csv<S>(pub S);
+
+impl<S> Deref for Csv<S> {
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<S> DerefMut for Csv<S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<S, T> IntoResponse for Csv<S>
+where
+    S: Stream<Item = T> + Send + 'static,
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        let mut write_header = true;
+        let body = Body::from_bytes_stream(self.0.map(move |row| {
+            let record = to_record(&row)
+                .map_err(|err| IoError::new(ErrorKind::InvalidData, err.to_string()))?;
+            let mut buf = String::new();
+            if let Some(header) = record.header.filter(|_| write_header) {
+                write_record(&mut buf, header.iter().map(String::as_str));
+            }
+            write_header = false;
+            write_record(&mut buf, record.fields.iter().map(String::as_str));
+            Ok::<_, IoError>(buf)
+        }));
+
+        Response::builder()
+            .content_type("text/csv; charset=utf-8")
+            .body(body)
+    }
+}
+
+/// An extractor which parses the CSV request body to a stream of records.
+///
+/// The first record is the header, and each following record is deserialized
+/// as a map from the names in the header to the fields, so the columns can be
+/// in any order, and the unknown columns are ignored. The empty fields are
+/// `None` for the optional values.
+///
+/// The body is parsed as the stream is polled, an error of a record is
+/// returned as an item of the stream.
+///
+/// # Errors
+///
+/// - [`ParseCsvError`]
+///
+/// # Example
+///
+/// ```
+/// use futures_util::TryStreamExt;
+/// use poem::{handler, http::header, post, test::TestClient, web::CsvStream, Result, Route};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct User {
+///     name: String,
+///     age: Option<u8>,
+/// }
+///
+/// #[handler]
+/// async fn index(mut users: CsvStream<User>) -> Result<String> {
+///     let mut total = 0;
+///     while let Some(user) = users.try_next().await? {
+///         total += user.age.unwrap_or_default() as u32;
+///     }
+///     Ok(total.to_string())
+/// }
+///
+/// let app = Route::new().at("/", post(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .post("/")
+///     .header(header::CONTENT_TYPE, "text/csv")
+///     .body("age,name\r\n20,Alice\r\n,Bob\r\n30,Carol\r\n")
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("50").await;
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "csv")))]
+pub struct CsvStream<T> {
+    body: BoxStream<'static, Result<Bytes, IoError>>,
+    parser: CsvParser,
+    parsed: Vec<Vec<String>>,
+    records: VecDeque<Vec<String>>,
+    header: Option<Vec<String>>,
+    num_records: usize,
+    error: Option<ParseCsvError>,
+    eof: bool,
+    _mark: PhantomData<fn() -> T>,
+}
+
+impl<T> CsvStream<T> {
+    /// Create a `CsvStream` from the body.
+    pub fn new(body: Body) -> Self {
+        Self {
+            body: body.into_bytes_stream().boxed(),
+            parser: CsvParser::default(),
+            parsed: Vec::new(),
+            records: VecDeque::new(),
+            header: None,
+            num_records: 0,
+            error: None,
+            eof: false,
+            _mark: PhantomData,
+        }
+    }
+
+    /// Returns the header, if it has been parsed.
+    pub fn header(&self) -> Option<&[String]> {
+        self.header.as_deref()
+    }
+}
+
+impl<T: DeserializeOwned> Stream for CsvStream<T> {
+    type Item = Result<T, ParseCsvError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(fields) = this.records.pop_front() {
+                this.num_records += 1;
+                let Some(header) = &this.header else {
+                    this.header = Some(fields);
+                    continue;
+                };
+                let res = T::deserialize(RecordDeserializer {
+                    header,
+                    fields: &fields,
+                });
+                return Poll::Ready(Some(res.map_err(|err| ParseCsvError::Parse {
+                    record: this.num_records,
+                    message: err.0,
+                })));
+            }
+
+            if let Some(err) = this.error.take() {
+                return Poll::Ready(Some(Err(err)));
+            }
+            if this.eof {
+                return Poll::Ready(None);
+            }
+
+            let res = match this.body.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(data))) => this.parser.feed(&data, &mut this.parsed),
+                Poll::Ready(Some(Err(err))) => {
+                    this.eof = true;
+                    return Poll::Ready(Some(Err(ParseCsvError::Io(err))));
+                }
+                Poll::Ready(None) => {
+                    this.eof = true;
+                    this.parser.finish(&mut this.parsed)
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+            this.records.extend(this.parsed.drain(..));
+            if let Err(err) = res {
+                // the records before the error are returned first
+                this.eof = true;
+                this.error = Some(ParseCsvError::Parse {
+                    record: this.num_records + this.records.len() + 1,
+                    message: err.0,
+                });
+            }
+        }
+    }
+}
+
+impl<'a, T: DeserializeOwned> FromRequest<'a> for CsvStream<T> {
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .ok_or(ParseCsvError::ContentTypeRequired)?;
+        if !is_csv_content_type(content_type) {
+            return Err(ParseCsvError::InvalidContentType(content_type.into()).into());
+        }
+        Ok(Self::new(body.take()?))
+    }
+}
+
+fn is_csv_content_type(content_type: &str) -> bool {
+    matches!(content_type.parse::<mime::Mime>(),
+        Ok(content_type) if content_type.type_() == "text" && content_type.subtype() == "csv")
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{stream, TryStreamExt};
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Row {
+        id: i32,
+        note: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn csv_response() {
+        let resp = Csv(stream::iter([
+            Row {
+                id: 1,
+                note: Some("a \"b\"".to_string()),
+            },
+            Row { id: 2, note: None },
+        ]))
+        .into_response();
+        assert_eq!(resp.content_type(), Some("text/csv; charset=utf-8"));
+        assert_eq!(
+            resp.into_body().into_string().await.unwrap(),
+            "id,note\r\n1,\"a \"\"b\"\"\"\r\n2,\r\n"
+        );
+
+        let resp = Csv(stream::iter([(1, "a"), (2, "b")])).into_response();
+        assert_eq!(
+            resp.into_body().into_string().await.unwrap(),
+            "1,a\r\n2,b\r\n"
+        );
+
+        let resp = Csv(stream::iter([vec![vec![1]]])).into_response();
+        assert!(resp.into_body().into_string().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn csv_stream() {
+        let body = Body::from_bytes_stream(stream::iter([
+            Ok::<_, IoError>("note,id\r\n\"x,"),
+            Ok("y\",1\r\n,2\r\n"),
+            Ok(",abc\r\n,3\r\n\"a\"b"),
+        ]));
+        let mut rows = CsvStream::<Row>::new(body);
+        assert_eq!(
+            rows.try_next().await.unwrap(),
+            Some(Row {
+                id: 1,
+                note: Some("x,y".to_string())
+            })
+        );
+        assert_eq!(rows.header().unwrap(), ["note", "id"]);
+        assert_eq!(
+            rows.try_next().await.unwrap(),
+            Some(Row { id: 2, note: None })
+        );
+        assert!(matches!(
+            rows.try_next().await,
+            Err(ParseCsvError::Parse { record: 4, .. })
+        ));
+        assert_eq!(
+            rows.try_next().await.unwrap(),
+            Some(Row { id: 3, note: None })
+        );
+        assert!(matches!(
+            rows.try_next().await,
+            Err(ParseCsvError::Parse { record: 6, .. })
+        ));
+        assert!(rows.try_next().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn csv_extractor() {
+        #[handler(internal)]
+        async fn index(rows: CsvStream<Row>) -> Result<Response> {
+            let rows: Vec<_> = rows.try_collect().await?;
+            Ok(Csv(stream::iter(rows)).into_response())
+        }
+
+        let cli = TestClient::new(index.map_to_response());
+        let resp = cli
+            .post("/")
+            .content_type("text/csv; charset=utf-8")
+            .body("id,note\n1,a\n")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("id,note\r\n1,a\r\n").await;
+
+        cli.post("/")
+            .content_type("application/json")
+            .body("id\n1\n")
+            .send()
+            .await
+            .assert_status(http::StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
@@ -0,0 +1,500 @@
+use std::fmt::{self, Display};
+
+use serde::{
+    ser::{
+        self, Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeTuple,
+        SerializeTupleStruct,
+    },
+    Serialize, Serializer,
+};
+
+/// This type represents errors that can occur when serializing or
+/// deserializing a record.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct CsvError(pub(crate) String);
+
+impl ser::Error for CsvError {
+    fn custom<T: Display>(msg: T) -> Self {
+        CsvError(msg.to_string())
+    }
+}
+
+impl serde::de::Error for CsvError {
+    fn custom<T: Display>(msg: T) -> Self {
+        CsvError(msg.to_string())
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+impl Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn unsupported(name: &str) -> CsvError {
+    CsvError(format!("unsupported type: {name}"))
+}
+
+/// The names and the values of the fields of a record.
+#[derive(Default)]
+pub(crate) struct Record {
+    /// The names of the fields, if the record is a struct or a map.
+    pub(crate) header: Option<Vec<String>>,
+    pub(crate) fields: Vec<String>,
+}
+
+pub(crate) fn to_record<T: Serialize + ?Sized>(value: &T) -> Result<Record, CsvError> {
+    let mut record = Record::default();
+    value.serialize(&mut RecordSerializer(&mut record))?;
+    Ok(record)
+}
+
+/// Writes the record, terminated by CRLF, and quotes the fields as described
+/// in RFC 4180.
+pub(crate) fn write_record<'a>(buf: &mut String, fields: impl ExactSizeIterator<Item = &'a str>) {
+    let len = fields.len();
+    for (idx, field) in fields.enumerate() {
+        if idx > 0 {
+            buf.push(',');
+        }
+        if field.contains([',', '"', '\r', '\n']) || (len == 1 && field.is_empty()) {
+            buf.push('"');
+            buf.push_str(&field.replace('"', "\"\""));
+            buf.push('"');
+        } else {
+            buf.push_str(field);
+        }
+    }
+    buf.push_str("\r\n");
+}
+
+struct RecordSerializer<'a>(&'a mut Record);
+
+impl RecordSerializer<'_> {
+    fn push_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CsvError> {
+        self.0.fields.push(value.serialize(FieldSerializer)?);
+        Ok(())
+    }
+
+    fn push_name(&mut self, name: String) {
+        self.0.header.get_or_insert_with(Vec::new).push(name);
+    }
+}
+
+macro_rules! serialize_single_field {
+    ($($method:ident($ty:ty)),*) => {
+        $(
+        fn $method(self, v: $ty) -> Result<Self::Ok, Self::Error> {
+            self.push_field(&v)
+        }
+        )*
+    };
+}
+
+impl<'a, 'b> Serializer for &'a mut RecordSerializer<'b> {
+    type Ok = ();
+    type Error = CsvError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), CsvError>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), CsvError>;
+
+    serialize_single_field!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8])
+    );
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.push_field(&())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.push_field(&())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.push_field(&())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.push_field(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(unsupported("newtype variant"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(unsupported("tuple variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(unsupported("struct variant"))
+    }
+}
+
+impl SerializeSeq for &mut RecordSerializer<'_> {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push_field(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
+
+impl SerializeTuple for &mut RecordSerializer<'_> {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push_field(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
+
+impl SerializeTupleStruct for &mut RecordSerializer<'_> {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push_field(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
+
+impl SerializeMap for &mut RecordSerializer<'_> {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        let name = key.serialize(FieldSerializer)?;
+        self.push_name(name);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push_field(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
+
+impl SerializeStruct for &mut RecordSerializer<'_> {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.push_name(key.to_string());
+        self.push_field(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
+
+/// Serializes a field to a string.
+struct FieldSerializer;
+
+macro_rules! serialize_display {
+    ($($method:ident($ty:ty)),*) => {
+        $(
+        fn $method(self, v: $ty) -> Result<Self::Ok, Self::Error> {
+            Ok(v.to_string())
+        }
+        )*
+    };
+}
+
+impl Serializer for FieldSerializer {
+    type Ok = String;
+    type Error = CsvError;
+    type SerializeSeq = Impossible<String, CsvError>;
+    type SerializeTuple = Impossible<String, CsvError>;
+    type SerializeTupleStruct = Impossible<String, CsvError>;
+    type SerializeTupleVariant = Impossible<String, CsvError>;
+    type SerializeMap = Impossible<String, CsvError>;
+    type SerializeStruct = Impossible<String, CsvError>;
+    type SerializeStructVariant = Impossible<String, CsvError>;
+
+    serialize_display!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str)
+    );
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        String::from_utf8(v.to_vec()).map_err(|_| CsvError("invalid utf-8 bytes".to_string()))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(String::new())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(unsupported("newtype variant"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(unsupported("nested sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(unsupported("nested tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(unsupported("nested tuple struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(unsupported("tuple variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(unsupported("nested map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Err(unsupported("nested struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(unsupported("struct variant"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    enum Kind {
+        Admin,
+    }
+
+    #[derive(Serialize)]
+    struct User {
+        name: &'static str,
+        age: Option<u8>,
+        kind: Kind,
+        score: f64,
+    }
+
+    #[test]
+    fn struct_record() {
+        let record = to_record(&User {
+            name: "a",
+            age: None,
+            kind: Kind::Admin,
+            score: 1.5,
+        })
+        .unwrap();
+        assert_eq!(record.header.unwrap(), vec!["name", "age", "kind", "score"]);
+        assert_eq!(record.fields, vec!["a", "", "Admin", "1.5"]);
+    }
+
+    #[test]
+    fn map_and_tuple_record() {
+        let record = to_record(&BTreeMap::from([("a", 1), ("b", 2)])).unwrap();
+        assert_eq!(record.header.unwrap(), vec!["a", "b"]);
+        assert_eq!(record.fields, vec!["1", "2"]);
+
+        let record = to_record(&(1, "a", true)).unwrap();
+        assert!(record.header.is_none());
+        assert_eq!(record.fields, vec!["1", "a", "true"]);
+    }
+
+    #[test]
+    fn nested_is_unsupported() {
+        #[derive(Serialize)]
+        struct Nested {
+            values: Vec<i32>,
+        }
+
+        assert_eq!(
+            to_record(&Nested { values: vec![1] }).err().unwrap(),
+            CsvError("unsupported type: nested sequence".to_string())
+        );
+    }
+
+    #[test]
+    fn quoting() {
+        let mut buf = String::new();
+        write_record(&mut buf, ["a", "b,c", "d\"e", "f\r\ng", ""].into_iter());
+        assert_eq!(buf, "a,\"b,c\",\"d\"\"e\",\"f\r\ng\",\r\n");
+
+        let mut buf = String::new();
+        write_record(&mut buf, [""].into_iter());
+        assert_eq!(buf, "\"\"\r\n");
+    }
+}
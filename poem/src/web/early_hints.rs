@@ -0,0 +1,173 @@
+use std::{fmt::Debug, sync::Arc};
+
+use parking_lot::Mutex;
+
+use crate::{
+    http::{header, HeaderValue, StatusCode},
+    FromRequest, Request, RequestBody, Result,
+};
+
+/// A sender of the informational (`1xx`) responses which are sent before the
+/// final response.
+///
+/// It is inserted into the request extensions by the servers which can send
+/// the informational responses on the connection, and is used by the
+/// [`EarlyHints`] extractor. The built-in [`Server`](crate::Server) does not
+/// provide it yet, because the underlying HTTP implementation does not
+/// support sending the informational responses.
+#[derive(Clone)]
+pub struct InformationalSender(Arc<dyn Fn(http::Response<()>) -> bool + Send + Sync>);
+
+impl Debug for InformationalSender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InformationalSender").finish()
+    }
+}
+
+impl InformationalSender {
+    /// Create an `InformationalSender` with a function which sends the
+    /// response, and returns `false` if the response cannot be sent.
+    pub fn new(f: impl Fn(http::Response<()>) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// Sends an informational response.
+    pub fn send(&self, resp: http::Response<()>) -> bool {
+        (self.0)(resp)
+    }
+}
+
+#[derive(Default)]
+struct Links {
+    pending: Vec<HeaderValue>,
+    sent: Vec<HeaderValue>,
+}
+
+/// An extractor for sending the `103 Early Hints` responses, so that the
+/// clients can start preloading the resources while the final response is
+/// being prepared.
+///
+/// The hints are sent only if the connection is HTTP/2 or later, and an
+/// [`InformationalSender`] is provided by the server. All the hints are also
+/// added to the final response as the `Link` headers, which are used by the
+/// clients that do not receive the early hints, and can be converted to the
+/// early hints by the CDNs.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler, middleware::EarlyHintsManager, test::TestClient, web::EarlyHints,
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// async fn index(hints: &EarlyHints) -> &'static str {
+///     hints.preload("/app.js", "script").send();
+///     // render the page...
+///     "hello"
+/// }
+///
+/// let app = Route::new()
+///     .at("/", get(index))
+///     .with(EarlyHintsManager::new().preload("/style.css", "style"));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_header_all(
+///     "link",
+///     [
+///         "</style.css>; rel=preload; as=style",
+///         "</app.js>; rel=preload; as=script",
+///     ],
+/// );
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct EarlyHints {
+    links: Arc<Mutex<Links>>,
+    sender: Option<InformationalSender>,
+}
+
+impl<'a> FromRequest<'a> for &'a EarlyHints {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req.extensions().get::<EarlyHints>().expect(
+            "To use the `EarlyHints` extractor, the `EarlyHintsManager` middleware is required.",
+        ))
+    }
+}
+
+impl EarlyHints {
+    pub(crate) fn new(sender: Option<InformationalSender>) -> Self {
+        Self {
+            links: Default::default(),
+            sender,
+        }
+    }
+
+    /// Adds a `Link` header value to the hints, such as
+    /// `</style.css>; rel=preload; as=style`.
+    pub fn link(&self, link: HeaderValue) -> &Self {
+        self.links.lock().pending.push(link);
+        self
+    }
+
+    /// Adds a hint to preload the resource with the destination, such as
+    /// `style`, `script` or `font`.
+    ///
+    /// The hint is ignored if it is not a valid header value.
+    pub fn preload(&self, url: &str, destination: &str) -> &Self {
+        match HeaderValue::try_from(format!("<{url}>; rel=preload; as={destination}")) {
+            Ok(link) => self.link(link),
+            Err(_) => self,
+        }
+    }
+
+    /// Adds a hint to connect to the origin.
+    ///
+    /// The hint is ignored if it is not a valid header value.
+    pub fn preconnect(&self, origin: &str) -> &Self {
+        match HeaderValue::try_from(format!("<{origin}>; rel=preconnect")) {
+            Ok(link) => self.link(link),
+            Err(_) => self,
+        }
+    }
+
+    /// Returns `true` if the early hints can be sent on the connection.
+    pub fn is_supported(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    /// Sends the hints which are added since the last call as a
+    /// `103 Early Hints` response.
+    ///
+    /// Returns `false` if there are no new hints, or the early hints cannot
+    /// be sent on the connection.
+    pub fn send(&self) -> bool {
+        let mut links = self.links.lock();
+        if links.pending.is_empty() {
+            return false;
+        }
+
+        let pending = std::mem::take(&mut links.pending);
+        let sent = self.sender.as_ref().is_some_and(|sender| {
+            let mut resp = http::Response::new(());
+            *resp.status_mut() = StatusCode::EARLY_HINTS;
+            for link in &pending {
+                resp.headers_mut().append(header::LINK, link.clone());
+            }
+            sender.send(resp)
+        });
+        links.sent.extend(pending);
+        sent
+    }
+
+    /// Returns all the hints, including the hints which have been sent.
+    pub(crate) fn take_links(&self) -> Vec<HeaderValue> {
+        let mut links = self.links.lock();
+        let mut all = std::mem::take(&mut links.sent);
+        all.append(&mut links.pending);
+        all
+    }
+}
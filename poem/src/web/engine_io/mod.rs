@@ -0,0 +1,528 @@
+//! Engine.io transport for serving the socket.io clients.
+//!
+//! [`EngineIo`] implements the version 4 of the engine.io protocol, with the
+//! long-polling transport and the WebSocket transport, including the upgrade
+//! from polling to WebSocket. Each session is passed to the handler as a
+//! [`Socket`], which exchanges the text and binary messages with the client.
+//! The socket.io packets are carried in these messages.
+//!
+//! # Example
+//!
+//! ```
+//! use poem::{
+//!     web::engine_io::{EngineIo, Socket},
+//!     Route,
+//! };
+//!
+//! async fn echo(mut socket: Socket) {
+//!     while let Some(msg) = socket.recv().await {
+//!         socket.send(msg);
+//!     }
+//! }
+//!
+//! let app = Route::new().at("/engine.io/", EngineIo::new(echo));
+//! ```
+
+mod packet;
+mod socket;
+
+use std::{
+    future::Future,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use base64::engine::{general_purpose::URL_SAFE_NO_PAD, Engine};
+use futures_util::{SinkExt, StreamExt};
+use rand::RngCore;
+use serde::Deserialize;
+
+pub use self::socket::{Message, Socket, SocketSender};
+use self::{
+    packet::{decode_payload, encode_payload, Packet},
+    socket::{Session, Sessions},
+};
+use crate::{
+    error::EngineIoError,
+    http::Method,
+    web::websocket::{Message as WsMessage, WebSocket, WebSocketStream},
+    Endpoint, FromRequest, IntoResponse, Request, Response, Result,
+};
+
+#[derive(Deserialize)]
+struct Params {
+    #[serde(rename = "EIO")]
+    eio: Option<String>,
+    transport: Option<String>,
+    sid: Option<String>,
+}
+
+/// An endpoint which accepts the engine.io connections, and calls the
+/// handler with a [`Socket`] for each session.
+///
+/// # Errors
+///
+/// - [`EngineIoError`]
+pub struct EngineIo<F> {
+    handler: F,
+    sessions: Sessions,
+    ping_interval: Duration,
+    ping_timeout: Duration,
+    max_payload: usize,
+}
+
+impl<F, Fut> EngineIo<F>
+where
+    F: Fn(Socket) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    /// Create an `EngineIo` endpoint with the session handler.
+    pub fn new(handler: F) -> Self {
+        Self {
+            handler,
+            sessions: Default::default(),
+            ping_interval: Duration::from_secs(25),
+            ping_timeout: Duration::from_secs(20),
+            max_payload: 1_000_000,
+        }
+    }
+
+    /// Sets the interval of the pings sent to the clients.
+    ///
+    /// Default is `25s`.
+    #[must_use]
+    pub fn ping_interval(self, ping_interval: Duration) -> Self {
+        Self {
+            ping_interval,
+            ..self
+        }
+    }
+
+    /// Sets the time to wait for the pongs, and the session is closed if it
+    /// is exceeded.
+    ///
+    /// Default is `20s`.
+    #[must_use]
+    pub fn ping_timeout(self, ping_timeout: Duration) -> Self {
+        Self {
+            ping_timeout,
+            ..self
+        }
+    }
+
+    /// Sets the maximum size of the payloads posted by the polling clients.
+    ///
+    /// Default is `1000000`.
+    #[must_use]
+    pub fn max_payload(self, max_payload: usize) -> Self {
+        Self {
+            max_payload,
+            ..self
+        }
+    }
+
+    /// Creates a session, and returns it with the open packet.
+    fn open(&self, req: &Request, upgrades: bool) -> (Arc<Session>, Packet) {
+        let mut id = [0; 15];
+        rand::thread_rng().fill_bytes(&mut id);
+        let id = URL_SAFE_NO_PAD.encode(id);
+
+        let (session, incoming) = Session::new(id.clone());
+        self.sessions.lock().insert(id.clone(), session.clone());
+        crate::spawn::spawn(
+            "poem-engine-io-heartbeat",
+            session
+                .clone()
+                .heartbeat(self.sessions.clone(), self.ping_interval, self.ping_timeout),
+        );
+        crate::spawn::spawn(
+            "poem-engine-io-socket",
+            (self.handler)(Socket::new(
+                session.clone(),
+                incoming,
+                req.headers().clone(),
+            )),
+        );
+
+        let upgrades: &[&str] = if upgrades { &["websocket"] } else { &[] };
+        let open = serde_json::json!({
+            "sid": id,
+            "upgrades": upgrades,
+            "pingInterval": self.ping_interval.as_millis() as u64,
+            "pingTimeout": self.ping_timeout.as_millis() as u64,
+            "maxPayload": self.max_payload,
+        });
+        (session, Packet::Open(open.to_string()))
+    }
+
+    async fn post(&self, session: &Session, req: Request) -> Result<Response> {
+        let body = req.into_body().into_bytes_limit(self.max_payload).await?;
+        let packets = std::str::from_utf8(&body)
+            .ok()
+            .and_then(decode_payload)
+            .ok_or(EngineIoError::BadRequest)?;
+        for packet in packets {
+            session.receive(packet);
+        }
+        Ok(polling_response("ok".to_string()))
+    }
+}
+
+impl<F, Fut> Endpoint for EngineIo<F>
+where
+    F: Fn(Socket) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let params = crate::urlencoded::from_str::<Params>(req.uri().query().unwrap_or_default())
+            .map_err(|_| EngineIoError::BadRequest)?;
+        if params.eio.as_deref() != Some("4") {
+            return Err(EngineIoError::UnsupportedProtocolVersion.into());
+        }
+        let websocket = match params.transport.as_deref() {
+            Some("polling") => false,
+            Some("websocket") => true,
+            _ => return Err(EngineIoError::UnknownTransport.into()),
+        };
+        let session = match &params.sid {
+            Some(sid) => Some(
+                self.sessions
+                    .lock()
+                    .get(sid)
+                    .cloned()
+                    .ok_or(EngineIoError::UnknownSession)?,
+            ),
+            None => None,
+        };
+
+        match (websocket, session) {
+            (false, None) => {
+                if req.method() != Method::GET {
+                    return Err(EngineIoError::BadHandshakeMethod.into());
+                }
+                let (_, open) = self.open(&req, true);
+                Ok(polling_response(open.encode()))
+            }
+            (false, Some(session)) => {
+                if session.upgraded.load(Ordering::Acquire) {
+                    return Err(EngineIoError::BadRequest.into());
+                }
+                match *req.method() {
+                    Method::GET => poll(&session).await,
+                    Method::POST => self.post(&session, req).await,
+                    _ => Err(EngineIoError::BadRequest.into()),
+                }
+            }
+            (true, None) => {
+                let ws = WebSocket::from_request_without_body(&req).await?;
+                let (session, open) = self.open(&req, false);
+                Ok(ws
+                    .on_upgrade(move |mut socket| async move {
+                        if socket.send(WsMessage::Text(open.encode())).await.is_ok() {
+                            run_websocket(&session, &mut socket).await;
+                        }
+                        session.close();
+                    })
+                    .into_response())
+            }
+            (true, Some(session)) => {
+                if session.upgraded.load(Ordering::Acquire) {
+                    return Err(EngineIoError::BadRequest.into());
+                }
+                let ws = WebSocket::from_request_without_body(&req).await?;
+                Ok(ws
+                    .on_upgrade(move |socket| upgrade(session, socket))
+                    .into_response())
+            }
+        }
+    }
+}
+
+fn polling_response(payload: String) -> Response {
+    Response::builder()
+        .content_type("text/plain; charset=UTF-8")
+        .body(payload)
+}
+
+/// Responds the queued packets to a polling request, or waits until a packet
+/// is queued.
+async fn poll(session: &Session) -> Result<Response> {
+    // only one polling request is allowed at a time
+    let Ok(mut transport) = session.transport.try_lock() else {
+        session.close();
+        return Err(EngineIoError::BadRequest.into());
+    };
+
+    let mut packets = Vec::new();
+    packets.extend(transport.recv().await);
+    while packets.last() != Some(&Packet::Close) {
+        match transport.try_recv() {
+            Ok(packet) => packets.push(packet),
+            Err(_) => break,
+        }
+    }
+    if packets.last() == Some(&Packet::Close) {
+        session.flushed.cancel();
+    }
+    Ok(polling_response(encode_payload(&packets)))
+}
+
+/// Returns the next text message, skipping the control messages.
+async fn next_text(socket: &mut WebSocketStream) -> Option<String> {
+    loop {
+        match socket.next().await? {
+            Ok(WsMessage::Text(text)) => return Some(text),
+            Ok(WsMessage::Ping(_) | WsMessage::Pong(_)) => {}
+            _ => return None,
+        }
+    }
+}
+
+/// Upgrades a polling session to the WebSocket transport.
+async fn upgrade(session: Arc<Session>, mut socket: WebSocketStream) {
+    if next_text(&mut socket).await.as_deref() != Some("2probe")
+        || socket.send(WsMessage::text("3probe")).await.is_err()
+    {
+        return;
+    }
+
+    // completes the pending polling request
+    session.push(Packet::Noop);
+
+    if next_text(&mut socket).await.as_deref() != Some("5")
+        || session.upgraded.swap(true, Ordering::AcqRel)
+    {
+        return;
+    }
+    run_websocket(&session, &mut socket).await;
+    session.close();
+}
+
+/// Exchanges the packets over the WebSocket connection until either side is
+/// closed.
+async fn run_websocket(session: &Session, socket: &mut WebSocketStream) {
+    let mut transport = session.transport.lock().await;
+
+    loop {
+        tokio::select! {
+            msg = socket.next() => match msg {
+                Some(Ok(WsMessage::Text(text))) => match Packet::decode(&text) {
+                    Some(packet) => session.receive(packet),
+                    None => break,
+                },
+                Some(Ok(WsMessage::Binary(data))) => {
+                    session.receive(Packet::Message(Message::Binary(data)));
+                }
+                Some(Ok(WsMessage::Ping(_) | WsMessage::Pong(_))) => {}
+                _ => break,
+            },
+            packet = transport.recv() => {
+                let Some(packet) = packet else {
+                    break;
+                };
+                let is_close = packet == Packet::Close;
+                let msg = match packet {
+                    Packet::Message(Message::Binary(data)) => WsMessage::Binary(data),
+                    packet => WsMessage::Text(packet.encode()),
+                };
+                if socket.send(msg).await.is_err() || is_close {
+                    break;
+                }
+            }
+        }
+    }
+
+    session.flushed.cancel();
+    let _ = socket.close().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::{header, StatusCode},
+        test::TestClient,
+    };
+
+    async fn echo(mut socket: Socket) {
+        while let Some(msg) = socket.recv().await {
+            socket.send(msg);
+        }
+    }
+
+    async fn handshake<E: Endpoint>(cli: &TestClient<E>) -> (String, serde_json::Value) {
+        let resp = cli.get("/?EIO=4&transport=polling").send().await;
+        resp.assert_status_is_ok();
+        let payload = resp.0.into_body().into_string().await.unwrap();
+        let open: serde_json::Value =
+            serde_json::from_str(payload.strip_prefix('0').unwrap()).unwrap();
+        (open["sid"].as_str().unwrap().to_string(), open)
+    }
+
+    #[tokio::test]
+    async fn polling() {
+        let cli = TestClient::new(EngineIo::new(echo));
+        let (sid, open) = handshake(&cli).await;
+        assert_eq!(open["upgrades"], serde_json::json!(["websocket"]));
+        assert_eq!(open["pingInterval"], 25000);
+        assert_eq!(open["pingTimeout"], 20000);
+        assert_eq!(open["maxPayload"], 1_000_000);
+
+        let uri = format!("/?EIO=4&transport=polling&sid={sid}");
+        cli.post(&uri)
+            .body("4hello\x1ebAQIDBA==")
+            .send()
+            .await
+            .assert_text("ok")
+            .await;
+        let resp = cli.get(&uri).send().await;
+        resp.assert_content_type("text/plain; charset=UTF-8");
+        resp.assert_text("4hello\x1ebAQIDBA==").await;
+
+        cli.post(&uri)
+            .body("1")
+            .send()
+            .await
+            .assert_text("ok")
+            .await;
+        cli.get(&uri).send().await.assert_text("1").await;
+    }
+
+    #[tokio::test]
+    async fn errors() {
+        let cli = TestClient::new(EngineIo::new(echo));
+
+        for (uri, code) in [
+            ("/?EIO=4&transport=polling&sid=abc", 1),
+            ("/?EIO=4&transport=udp", 0),
+            ("/?EIO=3&transport=polling", 5),
+        ] {
+            let resp = cli.get(uri).send().await;
+            resp.assert_status(StatusCode::BAD_REQUEST);
+            resp.assert_json(serde_json::json!({
+                "code": code,
+                "message": match code {
+                    0 => "Transport unknown",
+                    1 => "Session ID unknown",
+                    _ => "Unsupported protocol version",
+                }
+            }))
+            .await;
+        }
+
+        let resp = cli.post("/?EIO=4&transport=polling").send().await;
+        resp.assert_status(StatusCode::BAD_REQUEST);
+        resp.assert_json(serde_json::json!({ "code": 2, "message": "Bad handshake method" }))
+            .await;
+
+        let (sid, _) = handshake(&cli).await;
+        cli.post(format!("/?EIO=4&transport=polling&sid={sid}"))
+            .body("9")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn websocket() {
+        let cli = TestClient::new(EngineIo::new(echo));
+        let mut ws = cli
+            .get("/?EIO=4&transport=websocket")
+            .websocket()
+            .await
+            .timeout(Duration::from_secs(5));
+
+        let WsMessage::Text(open) = ws.receive().await.unwrap() else {
+            panic!("expect open packet");
+        };
+        let open: serde_json::Value =
+            serde_json::from_str(open.strip_prefix('0').unwrap()).unwrap();
+        assert_eq!(open["upgrades"], serde_json::json!([]));
+
+        ws.send_text("4hello").await;
+        ws.assert_text("4hello").await;
+        ws.send_binary(vec![1, 2, 3]).await;
+        ws.assert_binary(vec![1, 2, 3]).await;
+        ws.send_text("1").await;
+        ws.assert_text("1").await;
+        ws.assert_closed().await;
+    }
+
+    #[tokio::test]
+    async fn upgrade_from_polling() {
+        let cli = TestClient::new(EngineIo::new(echo));
+        let (sid, _) = handshake(&cli).await;
+        let uri = format!("/?EIO=4&transport=polling&sid={sid}");
+
+        let pending = cli.get(&uri).send();
+        let upgrade = async {
+            let mut ws = cli
+                .get(format!("/?EIO=4&transport=websocket&sid={sid}"))
+                .websocket()
+                .await
+                .timeout(Duration::from_secs(5));
+            ws.send_text("2probe").await;
+            ws.assert_text("3probe").await;
+            ws
+        };
+        let (resp, mut ws) = tokio::join!(pending, upgrade);
+        resp.assert_text("6").await;
+
+        ws.send_text("5").await;
+        ws.send_text("4hello").await;
+        ws.assert_text("4hello").await;
+
+        cli.get(&uri)
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn heartbeat() {
+        let cli = TestClient::new(
+            EngineIo::new(echo)
+                .ping_interval(Duration::from_millis(20))
+                .ping_timeout(Duration::from_millis(50)),
+        );
+        let (sid, _) = handshake(&cli).await;
+        let uri = format!("/?EIO=4&transport=polling&sid={sid}");
+
+        cli.get(&uri).send().await.assert_text("2").await;
+        cli.post(&uri)
+            .body("3")
+            .send()
+            .await
+            .assert_text("ok")
+            .await;
+        cli.get(&uri).send().await.assert_text("2").await;
+
+        // no pong
+        cli.get(&uri).send().await.assert_text("1").await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let resp = cli.get(&uri).send().await;
+        resp.assert_status(StatusCode::BAD_REQUEST);
+        resp.assert_header(header::CONTENT_TYPE, "application/json; charset=utf-8");
+    }
+
+    #[tokio::test]
+    async fn socket_closed() {
+        let cli = TestClient::new(EngineIo::new(|socket: Socket| async move {
+            assert!(socket.send("bye"));
+            let sender = socket.sender();
+            assert_eq!(sender.id(), socket.id());
+            drop(socket);
+            assert!(sender.is_closed());
+            assert!(!sender.send("hello"));
+        }));
+        let (sid, _) = handshake(&cli).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cli.get(format!("/?EIO=4&transport=polling&sid={sid}"))
+            .send()
+            .await
+            .assert_text("4bye\x1e1")
+            .await;
+    }
+}
@@ -0,0 +1,113 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use super::Message;
+
+/// The separator of the packets in a polling payload.
+const SEPARATOR: char = '\x1e';
+
+/// An engine.io packet.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum Packet {
+    Open(String),
+    Close,
+    Ping(String),
+    Pong(String),
+    Message(Message),
+    Upgrade,
+    Noop,
+}
+
+impl Packet {
+    /// Encodes the packet for the polling transport, or a text WebSocket
+    /// message.
+    ///
+    /// Binary messages are encoded in base64 with the `b` prefix.
+    pub(crate) fn encode(&self) -> String {
+        match self {
+            Packet::Open(data) => format!("0{data}"),
+            Packet::Close => "1".to_string(),
+            Packet::Ping(data) => format!("2{data}"),
+            Packet::Pong(data) => format!("3{data}"),
+            Packet::Message(Message::Text(text)) => format!("4{text}"),
+            Packet::Message(Message::Binary(data)) => format!("b{}", STANDARD.encode(data)),
+            Packet::Upgrade => "5".to_string(),
+            Packet::Noop => "6".to_string(),
+        }
+    }
+
+    /// Decodes a packet from the polling transport, or a text WebSocket
+    /// message.
+    pub(crate) fn decode(s: &str) -> Option<Self> {
+        let mut chars = s.chars();
+        let ty = chars.next()?;
+        let data = chars.as_str();
+        Some(match ty {
+            '0' => Packet::Open(data.to_string()),
+            '1' => Packet::Close,
+            '2' => Packet::Ping(data.to_string()),
+            '3' => Packet::Pong(data.to_string()),
+            '4' => Packet::Message(Message::Text(data.to_string())),
+            '5' => Packet::Upgrade,
+            '6' => Packet::Noop,
+            'b' => Packet::Message(Message::Binary(STANDARD.decode(data).ok()?)),
+            _ => return None,
+        })
+    }
+}
+
+/// Encodes the packets as a polling payload.
+pub(crate) fn encode_payload(packets: &[Packet]) -> String {
+    let mut payload = String::new();
+    for (idx, packet) in packets.iter().enumerate() {
+        if idx > 0 {
+            payload.push(SEPARATOR);
+        }
+        payload.push_str(&packet.encode());
+    }
+    payload
+}
+
+/// Decodes the packets from a polling payload.
+pub(crate) fn decode_payload(payload: &str) -> Option<Vec<Packet>> {
+    payload.split(SEPARATOR).map(Packet::decode).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets() {
+        let packets = [
+            (Packet::Open("{}".to_string()), "0{}"),
+            (Packet::Close, "1"),
+            (Packet::Ping("probe".to_string()), "2probe"),
+            (Packet::Pong(String::new()), "3"),
+            (Packet::Message(Message::text("hello €")), "4hello €"),
+            (Packet::Message(Message::binary([1, 2, 3, 4])), "bAQIDBA=="),
+            (Packet::Upgrade, "5"),
+            (Packet::Noop, "6"),
+        ];
+        for (packet, encoded) in packets {
+            assert_eq!(packet.encode(), encoded);
+            assert_eq!(Packet::decode(encoded), Some(packet));
+        }
+
+        assert_eq!(Packet::decode(""), None);
+        assert_eq!(Packet::decode("7"), None);
+        assert_eq!(Packet::decode("b!"), None);
+    }
+
+    #[test]
+    fn payload() {
+        let packets = vec![
+            Packet::Message(Message::text("hello")),
+            Packet::Message(Message::binary([1, 2, 3, 4])),
+            Packet::Ping(String::new()),
+        ];
+        let payload = encode_payload(&packets);
+        assert_eq!(payload, "4hello\x1ebAQIDBA==\x1e2");
+        assert_eq!(decode_payload(&payload), Some(packets));
+        assert_eq!(decode_payload("4a\x1e"), None);
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use tokio::sync::{mpsc, Notify};
+use tokio_util::sync::CancellationToken;
+
+use super::packet::Packet;
+use crate::http::HeaderMap;
+
+/// An engine.io message.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Message {
+    /// A text message
+    Text(String),
+
+    /// A binary message
+    Binary(Vec<u8>),
+}
+
+impl Message {
+    /// Construct a new text message.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    /// Construct a new binary message.
+    pub fn binary(data: impl Into<Vec<u8>>) -> Self {
+        Self::Binary(data.into())
+    }
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Message {
+    fn from(data: Vec<u8>) -> Self {
+        Self::Binary(data)
+    }
+}
+
+pub(crate) type Sessions = Arc<Mutex<HashMap<String, Arc<Session>>>>;
+
+/// The state of an engine.io session, which is shared by the transports and
+/// the [`Socket`].
+pub(crate) struct Session {
+    pub(crate) id: String,
+    outgoing: mpsc::UnboundedSender<Packet>,
+    /// The outgoing packets, which are locked by the active transport.
+    pub(crate) transport: tokio::sync::Mutex<mpsc::UnboundedReceiver<Packet>>,
+    incoming: Mutex<Option<mpsc::UnboundedSender<Message>>>,
+    pong: Notify,
+    pub(crate) upgraded: AtomicBool,
+    pub(crate) closed: CancellationToken,
+    /// Cancelled when the close packet has been sent by the transport.
+    pub(crate) flushed: CancellationToken,
+}
+
+impl Session {
+    pub(crate) fn new(id: String) -> (Arc<Self>, mpsc::UnboundedReceiver<Message>) {
+        let (outgoing, transport) = mpsc::unbounded_channel();
+        let (incoming, rx) = mpsc::unbounded_channel();
+        let session = Arc::new(Self {
+            id,
+            outgoing,
+            transport: tokio::sync::Mutex::new(transport),
+            incoming: Mutex::new(Some(incoming)),
+            pong: Notify::new(),
+            upgraded: AtomicBool::new(false),
+            closed: CancellationToken::new(),
+            flushed: CancellationToken::new(),
+        });
+        (session, rx)
+    }
+
+    /// Queues a packet to be sent by the transport.
+    pub(crate) fn push(&self, packet: Packet) -> bool {
+        !self.closed.is_cancelled() && self.outgoing.send(packet).is_ok()
+    }
+
+    /// Handles a packet received by the transport.
+    pub(crate) fn receive(&self, packet: Packet) {
+        match packet {
+            Packet::Message(msg) => {
+                if let Some(incoming) = &*self.incoming.lock() {
+                    let _ = incoming.send(msg);
+                }
+            }
+            Packet::Pong(_) => self.pong.notify_one(),
+            Packet::Close => self.close(),
+            _ => {}
+        }
+    }
+
+    pub(crate) fn close(&self) {
+        let mut incoming = self.incoming.lock();
+        if !self.closed.is_cancelled() {
+            let _ = self.outgoing.send(Packet::Close);
+            self.closed.cancel();
+            incoming.take();
+        }
+    }
+
+    /// Sends the pings until the session is closed, or a pong is not received
+    /// in time, and removes the session after the close packet is sent.
+    pub(crate) async fn heartbeat(
+        self: Arc<Self>,
+        sessions: Sessions,
+        interval: Duration,
+        timeout: Duration,
+    ) {
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = self.closed.cancelled() => break,
+            }
+
+            self.push(Packet::Ping(String::new()));
+            tokio::select! {
+                res = tokio::time::timeout(timeout, self.pong.notified()) => {
+                    if res.is_err() {
+                        self.close();
+                        break;
+                    }
+                }
+                _ = self.closed.cancelled() => break,
+            }
+        }
+
+        let _ = tokio::time::timeout(timeout, self.flushed.cancelled()).await;
+        sessions.lock().remove(&self.id);
+    }
+}
+
+/// A connection of an engine.io client, which is passed to the handler of
+/// [`EngineIo`](super::EngineIo).
+///
+/// The session is closed when the socket is dropped.
+pub struct Socket {
+    session: Arc<Session>,
+    incoming: mpsc::UnboundedReceiver<Message>,
+    headers: HeaderMap,
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        self.session.close();
+    }
+}
+
+impl Socket {
+    pub(crate) fn new(
+        session: Arc<Session>,
+        incoming: mpsc::UnboundedReceiver<Message>,
+        headers: HeaderMap,
+    ) -> Self {
+        Self {
+            session,
+            incoming,
+            headers,
+        }
+    }
+
+    /// Returns the session id.
+    pub fn id(&self) -> &str {
+        &self.session.id
+    }
+
+    /// Returns the headers of the handshake request.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Receives the next message from the client, returns `None` if the
+    /// session has been closed.
+    pub async fn recv(&mut self) -> Option<Message> {
+        self.incoming.recv().await
+    }
+
+    /// Sends a message to the client, returns `false` if the session has
+    /// been closed.
+    pub fn send(&self, msg: impl Into<Message>) -> bool {
+        self.session.push(Packet::Message(msg.into()))
+    }
+
+    /// Returns a sender which can send messages to the client from other
+    /// tasks.
+    pub fn sender(&self) -> SocketSender {
+        SocketSender(self.session.clone())
+    }
+
+    /// Returns `true` if the session has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.session.closed.is_cancelled()
+    }
+
+    /// Closes the session.
+    pub fn close(&self) {
+        self.session.close();
+    }
+}
+
+/// A sender of the messages to an engine.io client, returned by
+/// [`Socket::sender`].
+#[derive(Clone)]
+pub struct SocketSender(Arc<Session>);
+
+impl SocketSender {
+    /// Returns the session id.
+    pub fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    /// Sends a message to the client, returns `false` if the session has
+    /// been closed.
+    pub fn send(&self, msg: impl Into<Message>) -> bool {
+        self.0.push(Packet::Message(msg.into()))
+    }
+
+    /// Returns `true` if the session has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.0.closed.is_cancelled()
+    }
+
+    /// Closes the session.
+    pub fn close(&self) {
+        self.0.close();
+    }
+}
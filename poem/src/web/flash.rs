@@ -0,0 +1,148 @@
+use std::{ops::Deref, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{FromRequest, IntoResponse, Request, RequestBody, Response, Result};
+
+/// The level of a [`FlashMessage`].
+#[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlashLevel {
+    /// Debug
+    Debug,
+    /// Info
+    Info,
+    /// Success
+    Success,
+    /// Warning
+    Warning,
+    /// Error
+    Error,
+}
+
+/// A message which is displayed in the next request.
+#[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FlashMessage {
+    /// The level of the message.
+    pub level: FlashLevel,
+    /// The message.
+    pub message: String,
+}
+
+/// An extractor for the flash messages set by the previous request.
+///
+/// The messages are removed after this request, whether they are extracted
+/// or not.
+///
+/// See also [`FlashMessages`](crate::middleware::FlashMessages).
+#[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Flash(pub(crate) Arc<[FlashMessage]>);
+
+impl Flash {
+    /// Returns the messages.
+    pub fn messages(&self) -> &[FlashMessage] {
+        &self.0
+    }
+
+    /// Returns the messages with the specified level.
+    pub fn with_level(&self, level: FlashLevel) -> impl Iterator<Item = &FlashMessage> {
+        self.0.iter().filter(move |message| message.level == level)
+    }
+}
+
+impl Deref for Flash {
+    type Target = [FlashMessage];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> FromRequest<'a> for &'a Flash {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<Flash>()
+            .expect("To use the `Flash` extractor, the `FlashMessages` middleware is required."))
+    }
+}
+
+/// The flash messages set by a response.
+#[derive(Clone, Default)]
+pub(crate) struct OutgoingFlash(pub(crate) Vec<FlashMessage>);
+
+/// A response which sets the flash messages for the next request, usually a
+/// redirect after a form is submitted.
+///
+/// See also [`FlashMessages`](crate::middleware::FlashMessages).
+#[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
+pub struct FlashSetter<T> {
+    inner: T,
+    messages: Vec<FlashMessage>,
+}
+
+impl<T: IntoResponse> FlashSetter<T> {
+    /// Create a `FlashSetter` with the response.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            messages: Vec::new(),
+        }
+    }
+
+    /// Appends a message with the level.
+    #[must_use]
+    pub fn message(mut self, level: FlashLevel, message: impl Into<String>) -> Self {
+        self.messages.push(FlashMessage {
+            level,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Appends a debug message.
+    #[must_use]
+    pub fn debug(self, message: impl Into<String>) -> Self {
+        self.message(FlashLevel::Debug, message)
+    }
+
+    /// Appends an info message.
+    #[must_use]
+    pub fn info(self, message: impl Into<String>) -> Self {
+        self.message(FlashLevel::Info, message)
+    }
+
+    /// Appends a success message.
+    #[must_use]
+    pub fn success(self, message: impl Into<String>) -> Self {
+        self.message(FlashLevel::Success, message)
+    }
+
+    /// Appends a warning message.
+    #[must_use]
+    pub fn warning(self, message: impl Into<String>) -> Self {
+        self.message(FlashLevel::Warning, message)
+    }
+
+    /// Appends an error message.
+    #[must_use]
+    pub fn error(self, message: impl Into<String>) -> Self {
+        self.message(FlashLevel::Error, message)
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for FlashSetter<T> {
+    fn into_response(self) -> Response {
+        let mut resp = self.inner.into_response();
+        match resp.extensions_mut().get_mut::<OutgoingFlash>() {
+            Some(outgoing) => outgoing.0.extend(self.messages),
+            None => {
+                resp.extensions_mut().insert(OutgoingFlash(self.messages));
+            }
+        }
+        resp
+    }
+}
@@ -90,7 +90,7 @@ impl<'a, T: DeserializeOwned> FromRequest<'a> for Form<T> {
     async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
         if req.method() == Method::GET {
             Ok(
-                serde_urlencoded::from_str(req.uri().query().unwrap_or_default())
+                crate::urlencoded::from_str(req.uri().query().unwrap_or_default())
                     .map_err(ParseFormError::UrlDecode)
                     .map(Self)?,
             )
@@ -105,7 +105,7 @@ impl<'a, T: DeserializeOwned> FromRequest<'a> for Form<T> {
             }
 
             Ok(Self(
-                serde_urlencoded::from_bytes(&body.take()?.into_vec().await?)
+                crate::urlencoded::from_bytes(&body.take()?.into_vec().await?)
                     .map_err(ParseFormError::UrlDecode)?,
             ))
         }
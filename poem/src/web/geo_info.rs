@@ -0,0 +1,24 @@
+use crate::{FromRequest, Request, RequestBody, Result};
+
+/// An extractor for the geographic information of the client IP address,
+/// which is looked up by the [`IpFilter`](crate::middleware::IpFilter)
+/// middleware.
+///
+/// The fields are `None` if the address is not found in the database.
+#[cfg_attr(docsrs, doc(cfg(feature = "ip-filter")))]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct GeoInfo {
+    /// The ISO 3166-1 alpha-2 code of the country, such as `US`.
+    pub country: Option<String>,
+    /// The code of the continent, such as `NA`.
+    pub continent: Option<String>,
+}
+
+impl<'a> FromRequest<'a> for &'a GeoInfo {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req.extensions().get::<GeoInfo>().expect(
+            "To use the `GeoInfo` extractor, the `IpFilter` middleware with a GeoIP lookup is \
+             required.",
+        ))
+    }
+}
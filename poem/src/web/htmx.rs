@@ -0,0 +1,322 @@
+//! Helpers for [htmx](https://htmx.org) requests and responses.
+//!
+//! # Example
+//!
+//! ```
+//! use poem::{
+//!     handler,
+//!     test::TestClient,
+//!     web::htmx::{HxRequest, HxResponse, HxTarget},
+//!     IntoResponse, Response,
+//! };
+//!
+//! #[handler]
+//! fn index(HxRequest(is_htmx): HxRequest, HxTarget(target): HxTarget) -> Response {
+//!     if !is_htmx {
+//!         return "<html>...</html>".into_response();
+//!     }
+//!     HxResponse::new(format!("<p>target: {}</p>", target.unwrap_or_default()))
+//!         .trigger("loaded")
+//!         .oob(r#"<span id="count" hx-swap-oob="true">1</span>"#)
+//!         .into_response()
+//! }
+//!
+//! let cli = TestClient::new(index);
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let resp = cli
+//!     .get("/")
+//!     .header("HX-Request", "true")
+//!     .header("HX-Target", "content")
+//!     .send()
+//!     .await;
+//! resp.assert_status_is_ok();
+//! resp.assert_header("HX-Trigger", "loaded");
+//! resp.assert_text(r#"<p>target: content</p><span id="count" hx-swap-oob="true">1</span>"#)
+//!     .await;
+//! # });
+//! ```
+
+use http::{header::HeaderName, HeaderValue};
+
+use crate::{FromRequest, IntoResponse, Request, RequestBody, Response, Result};
+
+const HX_REQUEST: HeaderName = HeaderName::from_static("hx-request");
+const HX_BOOSTED: HeaderName = HeaderName::from_static("hx-boosted");
+const HX_CURRENT_URL: HeaderName = HeaderName::from_static("hx-current-url");
+const HX_TARGET: HeaderName = HeaderName::from_static("hx-target");
+const HX_TRIGGER: HeaderName = HeaderName::from_static("hx-trigger");
+const HX_TRIGGER_NAME: HeaderName = HeaderName::from_static("hx-trigger-name");
+const HX_REDIRECT: HeaderName = HeaderName::from_static("hx-redirect");
+const HX_REFRESH: HeaderName = HeaderName::from_static("hx-refresh");
+const HX_PUSH_URL: HeaderName = HeaderName::from_static("hx-push-url");
+const HX_REPLACE_URL: HeaderName = HeaderName::from_static("hx-replace-url");
+const HX_RESWAP: HeaderName = HeaderName::from_static("hx-reswap");
+const HX_RETARGET: HeaderName = HeaderName::from_static("hx-retarget");
+const HX_RESELECT: HeaderName = HeaderName::from_static("hx-reselect");
+
+fn header_str<'a>(req: &'a Request, name: &HeaderName) -> Option<&'a str> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+macro_rules! define_bool_extractor {
+    ($(#[$docs:meta])* $name:ident, $header:ident) => {
+        $(#[$docs])*
+        #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+        pub struct $name(pub bool);
+
+        impl<'a> FromRequest<'a> for $name {
+            async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+                Ok(Self(header_str(req, &$header) == Some("true")))
+            }
+        }
+    };
+}
+
+macro_rules! define_string_extractor {
+    ($(#[$docs:meta])* $name:ident, $header:ident) => {
+        $(#[$docs])*
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct $name(pub Option<String>);
+
+        impl<'a> FromRequest<'a> for $name {
+            async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+                Ok(Self(header_str(req, &$header).map(ToString::to_string)))
+            }
+        }
+    };
+}
+
+define_bool_extractor!(
+    /// An extractor that checks whether the request is issued by htmx, from
+    /// the `HX-Request` header.
+    HxRequest,
+    HX_REQUEST
+);
+
+define_bool_extractor!(
+    /// An extractor that checks whether the request is issued by an element
+    /// using `hx-boost`, from the `HX-Boosted` header.
+    HxBoosted,
+    HX_BOOSTED
+);
+
+define_string_extractor!(
+    /// An extractor for the current URL of the browser, from the
+    /// `HX-Current-URL` header.
+    HxCurrentUrl,
+    HX_CURRENT_URL
+);
+
+define_string_extractor!(
+    /// An extractor for the `id` of the target element, from the `HX-Target`
+    /// header.
+    HxTarget,
+    HX_TARGET
+);
+
+define_string_extractor!(
+    /// An extractor for the `id` of the triggered element, from the
+    /// `HX-Trigger` header.
+    HxTrigger,
+    HX_TRIGGER
+);
+
+define_string_extractor!(
+    /// An extractor for the `name` of the triggered element, from the
+    /// `HX-Trigger-Name` header.
+    HxTriggerName,
+    HX_TRIGGER_NAME
+);
+
+/// A response that makes htmx do a client-side redirect to the URL, with the
+/// `HX-Redirect` header.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HxRedirect(pub String);
+
+impl IntoResponse for HxRedirect {
+    fn into_response(self) -> Response {
+        HxResponse::new(String::new())
+            .header(HX_REDIRECT, self.0)
+            .into_response()
+    }
+}
+
+/// An HTML fragment response with the htmx response headers and
+/// out-of-band swaps.
+///
+/// See the [module level documentation](self) for an example.
+#[derive(Debug, Clone, Default)]
+pub struct HxResponse {
+    body: String,
+    headers: Vec<(HeaderName, String)>,
+    triggers: Vec<String>,
+    oob: Vec<String>,
+}
+
+impl HxResponse {
+    /// Create an `HxResponse` with the HTML fragment.
+    pub fn new(body: impl Into<String>) -> Self {
+        Self {
+            body: body.into(),
+            ..Default::default()
+        }
+    }
+
+    fn header(mut self, name: HeaderName, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Triggers a client-side event with the `HX-Trigger` header, can be
+    /// called multiple times.
+    #[must_use]
+    pub fn trigger(mut self, event: impl Into<String>) -> Self {
+        self.triggers.push(event.into());
+        self
+    }
+
+    /// Pushes the URL into the history stack with the `HX-Push-Url` header.
+    #[must_use]
+    pub fn push_url(self, url: impl Into<String>) -> Self {
+        self.header(HX_PUSH_URL, url)
+    }
+
+    /// Replaces the current URL in the location bar with the `HX-Replace-Url`
+    /// header.
+    #[must_use]
+    pub fn replace_url(self, url: impl Into<String>) -> Self {
+        self.header(HX_REPLACE_URL, url)
+    }
+
+    /// Makes the client do a full refresh of the page with the `HX-Refresh`
+    /// header.
+    #[must_use]
+    pub fn refresh(self) -> Self {
+        self.header(HX_REFRESH, "true")
+    }
+
+    /// Specifies how the response will be swapped with the `HX-Reswap`
+    /// header, e.g. `outerHTML`.
+    #[must_use]
+    pub fn reswap(self, swap: impl Into<String>) -> Self {
+        self.header(HX_RESWAP, swap)
+    }
+
+    /// Updates the target of the content update to a different element with
+    /// the `HX-Retarget` header.
+    #[must_use]
+    pub fn retarget(self, selector: impl Into<String>) -> Self {
+        self.header(HX_RETARGET, selector)
+    }
+
+    /// Chooses which part of the response is used to be swapped in with the
+    /// `HX-Reselect` header.
+    #[must_use]
+    pub fn reselect(self, selector: impl Into<String>) -> Self {
+        self.header(HX_RESELECT, selector)
+    }
+
+    /// Appends an out-of-band fragment to the response.
+    ///
+    /// The fragment should have the `hx-swap-oob` attribute, e.g.
+    /// `<div id="alerts" hx-swap-oob="true">Saved!</div>`.
+    #[must_use]
+    pub fn oob(mut self, fragment: impl Into<String>) -> Self {
+        self.oob.push(fragment.into());
+        self
+    }
+}
+
+impl IntoResponse for HxResponse {
+    fn into_response(self) -> Response {
+        let mut body = self.body;
+        for fragment in self.oob {
+            body.push_str(&fragment);
+        }
+
+        let mut resp = Response::builder()
+            .content_type("text/html; charset=utf-8")
+            .body(body);
+        let triggers = (!self.triggers.is_empty()).then(|| (HX_TRIGGER, self.triggers.join(", ")));
+        for (name, value) in self.headers.into_iter().chain(triggers) {
+            if let Ok(value) = HeaderValue::try_from(value) {
+                resp.headers_mut().insert(name, value);
+            }
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, test::TestClient};
+
+    #[tokio::test]
+    async fn extractors() {
+        #[handler(internal)]
+        fn index(
+            HxRequest(request): HxRequest,
+            HxBoosted(boosted): HxBoosted,
+            HxTarget(target): HxTarget,
+            HxTrigger(trigger): HxTrigger,
+            HxTriggerName(trigger_name): HxTriggerName,
+            HxCurrentUrl(url): HxCurrentUrl,
+        ) -> String {
+            format!("{request} {boosted} {target:?} {trigger:?} {trigger_name:?} {url:?}")
+        }
+
+        let cli = TestClient::new(index);
+        cli.get("/")
+            .send()
+            .await
+            .assert_text("false false None None None None")
+            .await;
+
+        cli.get("/")
+            .header("HX-Request", "true")
+            .header("HX-Boosted", "true")
+            .header("HX-Target", "a")
+            .header("HX-Trigger", "b")
+            .header("HX-Trigger-Name", "c")
+            .header("HX-Current-URL", "http://localhost/")
+            .send()
+            .await
+            .assert_text(r#"true true Some("a") Some("b") Some("c") Some("http://localhost/")"#)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn responses() {
+        let resp = HxResponse::new("<p>a</p>")
+            .retarget("#list")
+            .reswap("outerHTML")
+            .reselect(".item")
+            .push_url("/a")
+            .replace_url("/b")
+            .refresh()
+            .trigger("a")
+            .trigger("b")
+            .oob(r#"<div id="c" hx-swap-oob="true">c</div>"#)
+            .into_response();
+        let headers = resp.headers();
+        assert_eq!(headers["HX-Retarget"], "#list");
+        assert_eq!(headers["HX-Reswap"], "outerHTML");
+        assert_eq!(headers["HX-Reselect"], ".item");
+        assert_eq!(headers["HX-Push-Url"], "/a");
+        assert_eq!(headers["HX-Replace-Url"], "/b");
+        assert_eq!(headers["HX-Refresh"], "true");
+        assert_eq!(headers["HX-Trigger"], "a, b");
+        assert_eq!(resp.content_type(), Some("text/html; charset=utf-8"));
+        assert_eq!(
+            resp.into_body().into_string().await.unwrap(),
+            r#"<p>a</p><div id="c" hx-swap-oob="true">c</div>"#
+        );
+
+        let resp = HxRedirect("/login".to_string()).into_response();
+        assert_eq!(resp.headers()["HX-Redirect"], "/login");
+    }
+}
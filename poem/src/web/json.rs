@@ -1,11 +1,17 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    io::{BufReader, Error as IoError, Read},
+    ops::{Deref, DerefMut},
+};
 
 use http::StatusCode;
 use serde::{de::DeserializeOwned, Serialize};
+use tokio_util::io::SyncIoBridge;
 
 use crate::{
-    error::ParseJsonError, http::header, web::RequestBody, FromRequest, IntoResponse, Request,
-    Response, Result,
+    error::{ParseJsonError, ReadBodyError},
+    http::header,
+    web::RequestBody,
+    FromRequest, IntoResponse, Request, Response, Result,
 };
 
 /// JSON extractor and response.
@@ -131,7 +137,185 @@ impl<'a, T: DeserializeOwned> FromRequest<'a> for Json<T> {
     }
 }
 
-fn is_json_content_type(content_type: &str) -> bool {
+/// Configuration for the [`StreamingJson`] extractor.
+///
+/// Add it to the endpoint with
+/// [`EndpointExt::data`](crate::EndpointExt::data), otherwise the default
+/// configuration is used.
+#[derive(Debug, Clone)]
+pub struct StreamingJsonConfig {
+    limit: usize,
+}
+
+impl Default for StreamingJsonConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamingJsonConfig {
+    /// Create a `StreamingJsonConfig`.
+    pub fn new() -> Self {
+        Self {
+            limit: 16 * 1024 * 1024,
+        }
+    }
+
+    /// Sets the maximum size of the body in bytes, defaults to `16MiB`.
+    #[must_use]
+    pub fn limit(self, limit: usize) -> Self {
+        Self { limit }
+    }
+}
+
+/// JSON extractor that deserializes the body while it is being received.
+///
+/// Unlike [`Json`], the body is not collected into a buffer before parsing,
+/// it is read from the connection and deserialized on a blocking thread with
+/// [`serde_json::from_reader`], so a multi-MB payload is never kept in memory
+/// next to the deserialized value. The parsing is slower than [`Json`] for the
+/// small bodies, prefer it for the large ones.
+///
+/// The size of the body is limited by [`StreamingJsonConfig::limit`].
+///
+/// # Errors
+///
+/// - [`ReadBodyError`]
+/// - [`ParseJsonError`]
+///
+/// ```
+/// use poem::{
+///     handler,
+///     http::{header, StatusCode},
+///     post,
+///     test::TestClient,
+///     web::{StreamingJson, StreamingJsonConfig},
+///     EndpointExt, Route,
+/// };
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Batch {
+///     items: Vec<u64>,
+/// }
+///
+/// #[handler]
+/// async fn index(StreamingJson(batch): StreamingJson<Batch>) -> String {
+///     batch.items.iter().sum::<u64>().to_string()
+/// }
+///
+/// let app = Route::new()
+///     .at("/", post(index))
+///     .data(StreamingJsonConfig::new().limit(1024));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .post("/")
+///     .header(header::CONTENT_TYPE, "application/json")
+///     .body(r#"{"items": [1, 2, 3]}"#)
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("6").await;
+///
+/// let resp = cli
+///     .post("/")
+///     .header(header::CONTENT_TYPE, "application/json")
+///     .body(format!(r#"{{"items": [{}1]}}"#, "1,".repeat(1024)))
+///     .send()
+///     .await;
+/// resp.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+/// # });
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct StreamingJson<T>(pub T);
+
+impl<T> Deref for StreamingJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for StreamingJson<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'a, T: DeserializeOwned + Send + 'static> FromRequest<'a> for StreamingJson<T> {
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .ok_or(ParseJsonError::ContentTypeRequired)?;
+        if !is_json_content_type(content_type) {
+            return Err(ParseJsonError::InvalidContentType(content_type.into()).into());
+        }
+
+        let limit = req
+            .data::<StreamingJsonConfig>()
+            .map(|config| config.limit)
+            .unwrap_or_else(|| StreamingJsonConfig::new().limit);
+        let content_length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+        if content_length.is_some_and(|len| len > limit) {
+            return Err(ReadBodyError::PayloadTooLarge.into());
+        }
+
+        let reader = SyncIoBridge::new(body.take()?.into_async_read());
+        let res = crate::spawn::spawn_blocking("poem-streaming-json", move || {
+            let mut reader = LimitedReader {
+                inner: BufReader::new(reader),
+                remaining: limit,
+                exceeded: false,
+            };
+            let res = serde_json::from_reader::<_, T>(&mut reader);
+            (res, reader.exceeded)
+        })
+        .await;
+
+        match res {
+            Ok((Ok(value), _)) => Ok(Self(value)),
+            Ok((Err(_), true)) => Err(ReadBodyError::PayloadTooLarge.into()),
+            Ok((Err(err), false)) if err.is_io() => Err(ReadBodyError::Io(err.into()).into()),
+            #[cfg(not(feature = "sonic-rs"))]
+            Ok((Err(err), false)) => Err(ParseJsonError::Parse(err).into()),
+            #[cfg(feature = "sonic-rs")]
+            Ok((Err(err), false)) => {
+                Err(ParseJsonError::Parse(serde::de::Error::custom(err)).into())
+            }
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+}
+
+/// A reader that fails after reading more than `remaining` bytes.
+struct LimitedReader<R> {
+    inner: R,
+    remaining: usize,
+    exceeded: bool,
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > self.remaining {
+            self.exceeded = true;
+            return Err(IoError::other("payload too large"));
+        }
+        self.remaining -= n;
+        Ok(n)
+    }
+}
+
+pub(crate) fn is_json_content_type(content_type: &str) -> bool {
     matches!(content_type.parse::<mime::Mime>(), 
         Ok(content_type) if content_type.type_() == "application" 
         && (content_type.subtype() == "json"
@@ -170,7 +354,7 @@ mod tests {
     use sonic_rs::{json, to_string};
 
     use super::*;
-    use crate::{handler, test::TestClient};
+    use crate::{handler, test::TestClient, Body, EndpointExt};
 
     #[derive(Deserialize, Serialize, Debug, Eq, PartialEq)]
     struct CreateResource {
@@ -213,6 +397,82 @@ mod tests {
             .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
     }
 
+    #[tokio::test]
+    async fn test_streaming_json_extractor() {
+        #[handler(internal)]
+        async fn index(StreamingJson(items): StreamingJson<Vec<CreateResource>>) -> String {
+            items.iter().map(|item| item.value).sum::<i32>().to_string()
+        }
+
+        let items = (0..10000)
+            .map(|value| CreateResource {
+                name: "abc".to_string(),
+                value,
+            })
+            .collect::<Vec<_>>();
+        let cli = TestClient::new(index);
+        let resp = cli.post("/").body_json(&items).send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text((0..10000).sum::<i32>().to_string()).await;
+
+        cli.post("/")
+            .content_type("application/json")
+            .body(r#"[{"name": "abc"}]"#)
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+
+        cli.post("/")
+            .body(r#"[]"#)
+            .send()
+            .await
+            .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_json_limit() {
+        #[handler(internal)]
+        async fn index(_items: StreamingJson<Vec<CreateResource>>) {}
+
+        let items = |n| {
+            (0..n)
+                .map(|value| CreateResource {
+                    name: "abc".to_string(),
+                    value,
+                })
+                .collect::<Vec<_>>()
+        };
+        let body = to_string(&items(100)).unwrap();
+        let cli = TestClient::new(index.data(StreamingJsonConfig::new().limit(body.len())));
+        cli.post("/")
+            .content_type("application/json")
+            .body(body.clone())
+            .send()
+            .await
+            .assert_status_is_ok();
+
+        let body = to_string(&items(101)).unwrap();
+        cli.post("/")
+            .content_type("application/json")
+            .body(body.clone())
+            .send()
+            .await
+            .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+
+        // without `Content-Length`
+        cli.post("/")
+            .content_type("application/json")
+            .body(Body::from_bytes_stream(futures_util::stream::iter(
+                body.into_bytes()
+                    .chunks(64)
+                    .map(|chunk| Ok::<_, std::io::Error>(bytes::Bytes::copy_from_slice(chunk)))
+                    .collect::<Vec<_>>(),
+            )))
+            .send()
+            .await
+            .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
     #[tokio::test]
     async fn test_json_response() {
         #[handler(internal)]
@@ -0,0 +1,598 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    error::{JsonPatchError, ParseJsonError},
+    http::header,
+    web::{json::is_json_content_type, RequestBody},
+    FromRequest, Request, Result,
+};
+
+/// A single operation of a [`JsonPatch`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    /// Adds a value to an object or inserts it into an array.
+    Add {
+        /// The JSON pointer of the target location.
+        path: String,
+        /// The value to add.
+        value: Value,
+    },
+    /// Removes the value at the target location.
+    Remove {
+        /// The JSON pointer of the target location.
+        path: String,
+    },
+    /// Replaces the value at the target location.
+    Replace {
+        /// The JSON pointer of the target location.
+        path: String,
+        /// The new value.
+        value: Value,
+    },
+    /// Removes the value at the `from` location and adds it to the target
+    /// location.
+    Move {
+        /// The JSON pointer of the source location.
+        from: String,
+        /// The JSON pointer of the target location.
+        path: String,
+    },
+    /// Copies the value at the `from` location to the target location.
+    Copy {
+        /// The JSON pointer of the source location.
+        from: String,
+        /// The JSON pointer of the target location.
+        path: String,
+    },
+    /// Tests that the value at the target location is equal to the value.
+    Test {
+        /// The JSON pointer of the target location.
+        path: String,
+        /// The expected value.
+        value: Value,
+    },
+}
+
+/// JSON Patch extractor, see [RFC 6902](https://datatracker.ietf.org/doc/html/rfc6902).
+///
+/// The operations are applied in order, and the document is left unchanged
+/// if any of them fails.
+///
+/// # Errors
+///
+/// - [`ReadBodyError`](crate::error::ReadBodyError)
+/// - [`ParseJsonError`]
+/// - [`JsonPatchError`] when applying the patch
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     http::{header, StatusCode},
+///     patch,
+///     test::TestClient,
+///     web::{Json, JsonPatch},
+///     Result,
+/// };
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Serialize, Deserialize, PartialEq)]
+/// struct User {
+///     name: String,
+///     age: u32,
+/// }
+///
+/// #[handler]
+/// fn update_user(patch: JsonPatch) -> Result<Json<User>> {
+///     let user = User {
+///         name: "a".to_string(),
+///         age: 10,
+///     };
+///     Ok(Json(patch.apply_to(&user)?))
+/// }
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = TestClient::new(patch(update_user))
+///     .patch("/")
+///     .header(header::CONTENT_TYPE, "application/json-patch+json")
+///     .body(r#"[{"op": "replace", "path": "/age", "value": 11}]"#)
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_json(&User {
+///     name: "a".to_string(),
+///     age: 11,
+/// })
+/// .await;
+/// # });
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JsonPatch(pub Vec<PatchOperation>);
+
+impl Deref for JsonPatch {
+    type Target = Vec<PatchOperation>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for JsonPatch {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl JsonPatch {
+    /// Applies the patch to the document.
+    pub fn apply(&self, doc: &mut Value) -> Result<(), JsonPatchError> {
+        let mut patched = doc.clone();
+        for operation in &self.0 {
+            apply_operation(&mut patched, operation)?;
+        }
+        *doc = patched;
+        Ok(())
+    }
+
+    /// Applies the patch to the JSON representation of the value, and returns
+    /// the patched value.
+    pub fn apply_to<T: Serialize + DeserializeOwned>(
+        &self,
+        value: &T,
+    ) -> Result<T, JsonPatchError> {
+        let mut doc = serde_json::to_value(value)?;
+        self.apply(&mut doc)?;
+        Ok(serde_json::from_value(doc)?)
+    }
+}
+
+impl<'a> FromRequest<'a> for JsonPatch {
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        Ok(Self(parse_json_body(req, body).await?))
+    }
+}
+
+/// JSON Merge Patch extractor, see [RFC 7386](https://datatracker.ietf.org/doc/html/rfc7386).
+///
+/// The type parameter is the type of the resource the patch is applied to.
+///
+/// # Errors
+///
+/// - [`ReadBodyError`](crate::error::ReadBodyError)
+/// - [`ParseJsonError`]
+/// - [`JsonPatchError`] when applying the patch
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     http::header,
+///     patch,
+///     test::TestClient,
+///     web::{Json, MergePatch},
+///     Result,
+/// };
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Serialize, Deserialize, PartialEq)]
+/// struct User {
+///     name: String,
+///     email: Option<String>,
+/// }
+///
+/// #[handler]
+/// fn update_user(patch: MergePatch<User>) -> Result<Json<User>> {
+///     let user = User {
+///         name: "a".to_string(),
+///         email: Some("a@example.com".to_string()),
+///     };
+///     Ok(Json(patch.apply(&user)?))
+/// }
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = TestClient::new(patch(update_user))
+///     .patch("/")
+///     .header(header::CONTENT_TYPE, "application/merge-patch+json")
+///     .body(r#"{"name": "b", "email": null}"#)
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_json(&User {
+///     name: "b".to_string(),
+///     email: None,
+/// })
+/// .await;
+/// # });
+/// ```
+pub struct MergePatch<T = Value> {
+    patch: Value,
+    _mark: PhantomData<fn() -> T>,
+}
+
+impl<T> Debug for MergePatch<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MergePatch").field(&self.patch).finish()
+    }
+}
+
+impl<T> Clone for MergePatch<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.patch.clone())
+    }
+}
+
+impl<T> PartialEq for MergePatch<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.patch == other.patch
+    }
+}
+
+impl<T> MergePatch<T> {
+    /// Create a merge patch from the patch document.
+    pub fn new(patch: Value) -> Self {
+        Self {
+            patch,
+            _mark: PhantomData,
+        }
+    }
+
+    /// Returns the patch document.
+    #[inline]
+    pub fn patch(&self) -> &Value {
+        &self.patch
+    }
+
+    /// Consumes this object and returns the patch document.
+    #[inline]
+    pub fn into_inner(self) -> Value {
+        self.patch
+    }
+
+    /// Applies the patch to the document.
+    pub fn apply_to_value(&self, doc: &mut Value) {
+        merge(doc, &self.patch);
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> MergePatch<T> {
+    /// Applies the patch to the JSON representation of the value, and returns
+    /// the patched value.
+    pub fn apply(&self, value: &T) -> Result<T, JsonPatchError> {
+        let mut doc = serde_json::to_value(value)?;
+        self.apply_to_value(&mut doc);
+        Ok(serde_json::from_value(doc)?)
+    }
+}
+
+impl<'a, T> FromRequest<'a> for MergePatch<T> {
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        Ok(Self::new(parse_json_body(req, body).await?))
+    }
+}
+
+async fn parse_json_body<T: DeserializeOwned>(req: &Request, body: &mut RequestBody) -> Result<T> {
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .ok_or(ParseJsonError::ContentTypeRequired)?;
+    if !is_json_content_type(content_type) {
+        return Err(ParseJsonError::InvalidContentType(content_type.into()).into());
+    }
+
+    Ok(serde_json::from_slice(&body.take()?.into_bytes().await?)
+        .map_err(|err| JsonPatchError::Parse(err.to_string()))?)
+}
+
+fn merge(doc: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *doc = patch.clone();
+        return;
+    };
+    if !doc.is_object() {
+        *doc = Value::Object(Map::new());
+    }
+    let doc = doc.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            doc.remove(key);
+        } else {
+            merge(doc.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Splits a JSON pointer into the unescaped reference tokens, see [RFC 6901](https://datatracker.ietf.org/doc/html/rfc6901).
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, JsonPatchError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(tokens) = pointer.strip_prefix('/') else {
+        return Err(JsonPatchError::InvalidPointer(pointer.to_string()));
+    };
+    Ok(tokens
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+fn parse_index(token: &str, len: usize, path: &str) -> Result<usize, JsonPatchError> {
+    if token.starts_with('+') || (token.starts_with('0') && token.len() > 1) {
+        return Err(JsonPatchError::InvalidPointer(path.to_string()));
+    }
+    match token.parse::<usize>() {
+        Ok(index) if index < len => Ok(index),
+        _ => Err(JsonPatchError::PathNotFound(path.to_string())),
+    }
+}
+
+fn get_mut<'a>(
+    doc: &'a mut Value,
+    tokens: &[String],
+    path: &str,
+) -> Result<&'a mut Value, JsonPatchError> {
+    tokens.iter().try_fold(doc, |value, token| match value {
+        Value::Object(map) => map
+            .get_mut(token)
+            .ok_or_else(|| JsonPatchError::PathNotFound(path.to_string())),
+        Value::Array(array) => {
+            let index = parse_index(token, array.len(), path)?;
+            Ok(&mut array[index])
+        }
+        _ => Err(JsonPatchError::PathNotFound(path.to_string())),
+    })
+}
+
+fn add(doc: &mut Value, path: &str, value: Value) -> Result<(), JsonPatchError> {
+    let tokens = parse_pointer(path)?;
+    let Some((last, parent)) = tokens.split_last() else {
+        *doc = value;
+        return Ok(());
+    };
+    match get_mut(doc, parent, path)? {
+        Value::Object(map) => {
+            map.insert(last.clone(), value);
+        }
+        Value::Array(array) if last == "-" => array.push(value),
+        Value::Array(array) => {
+            let index = parse_index(last, array.len() + 1, path)?;
+            array.insert(index, value);
+        }
+        _ => return Err(JsonPatchError::PathNotFound(path.to_string())),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut Value, path: &str) -> Result<Value, JsonPatchError> {
+    let tokens = parse_pointer(path)?;
+    let Some((last, parent)) = tokens.split_last() else {
+        return Ok(std::mem::take(doc));
+    };
+    match get_mut(doc, parent, path)? {
+        Value::Object(map) => map
+            .remove(last)
+            .ok_or_else(|| JsonPatchError::PathNotFound(path.to_string())),
+        Value::Array(array) => {
+            let index = parse_index(last, array.len(), path)?;
+            Ok(array.remove(index))
+        }
+        _ => Err(JsonPatchError::PathNotFound(path.to_string())),
+    }
+}
+
+fn apply_operation(doc: &mut Value, operation: &PatchOperation) -> Result<(), JsonPatchError> {
+    match operation {
+        PatchOperation::Add { path, value } => add(doc, path, value.clone()),
+        PatchOperation::Remove { path } => remove(doc, path).map(|_| ()),
+        PatchOperation::Replace { path, value } => {
+            *get_mut(doc, &parse_pointer(path)?, path)? = value.clone();
+            Ok(())
+        }
+        PatchOperation::Move { from, path } => {
+            if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                return Err(JsonPatchError::InvalidPointer(path.clone()));
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        PatchOperation::Copy { from, path } => {
+            let value = get_mut(doc, &parse_pointer(from)?, from)?.clone();
+            add(doc, path, value)
+        }
+        PatchOperation::Test { path, value } => {
+            if get_mut(doc, &parse_pointer(path)?, path)? == value {
+                Ok(())
+            } else {
+                Err(JsonPatchError::TestFailed(path.clone()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{handler, http::StatusCode, test::TestClient, web::Json};
+
+    fn json_patch(patch: Value) -> JsonPatch {
+        serde_json::from_value(patch).unwrap()
+    }
+
+    #[test]
+    fn apply_json_patch() {
+        let mut doc = json!({
+            "a": { "b": [1, 2, 3] },
+            "c/d": "e",
+            "f~g": "h",
+        });
+        json_patch(json!([
+            { "op": "add", "path": "/a/b/1", "value": 4 },
+            { "op": "add", "path": "/a/b/-", "value": 5 },
+            { "op": "remove", "path": "/a/b/0" },
+            { "op": "replace", "path": "/c~1d", "value": "x" },
+            { "op": "move", "from": "/f~0g", "path": "/a/g" },
+            { "op": "copy", "from": "/a/b", "path": "/i" },
+            { "op": "test", "path": "/i/3", "value": 5 },
+        ]))
+        .apply(&mut doc)
+        .unwrap();
+        assert_eq!(
+            doc,
+            json!({
+                "a": { "b": [4, 2, 3, 5], "g": "h" },
+                "c/d": "x",
+                "i": [4, 2, 3, 5],
+            })
+        );
+
+        let original = doc.clone();
+        assert_eq!(
+            json_patch(json!([
+                { "op": "remove", "path": "/a" },
+                { "op": "test", "path": "/c~1d", "value": "y" },
+            ]))
+            .apply(&mut doc),
+            Err(JsonPatchError::TestFailed("/c~1d".to_string()))
+        );
+        assert_eq!(doc, original);
+
+        for (patch, err) in [
+            (
+                json!([{ "op": "remove", "path": "/x" }]),
+                JsonPatchError::PathNotFound("/x".to_string()),
+            ),
+            (
+                json!([{ "op": "add", "path": "/a/b/9", "value": 1 }]),
+                JsonPatchError::PathNotFound("/a/b/9".to_string()),
+            ),
+            (
+                json!([{ "op": "replace", "path": "a", "value": 1 }]),
+                JsonPatchError::InvalidPointer("a".to_string()),
+            ),
+            (
+                json!([{ "op": "move", "from": "/a", "path": "/a/b" }]),
+                JsonPatchError::InvalidPointer("/a/b".to_string()),
+            ),
+        ] {
+            assert_eq!(json_patch(patch).apply(&mut doc), Err(err));
+        }
+    }
+
+    #[test]
+    fn apply_merge_patch() {
+        let mut doc = json!({
+            "title": "Goodbye!",
+            "author": { "givenName": "John", "familyName": "Doe" },
+            "tags": ["example", "sample"],
+            "content": "This will be unchanged",
+        });
+        MergePatch::<Value>::new(json!({
+            "title": "Hello!",
+            "phoneNumber": "+01-123-456-7890",
+            "author": { "familyName": null },
+            "tags": ["example"],
+        }))
+        .apply_to_value(&mut doc);
+        assert_eq!(
+            doc,
+            json!({
+                "title": "Hello!",
+                "author": { "givenName": "John" },
+                "tags": ["example"],
+                "content": "This will be unchanged",
+                "phoneNumber": "+01-123-456-7890",
+            })
+        );
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Item {
+        name: String,
+        count: u32,
+    }
+
+    #[tokio::test]
+    async fn extractors() {
+        #[handler(internal)]
+        fn json_patch(patch: JsonPatch) -> Result<Json<Item>, JsonPatchError> {
+            let item = Item {
+                name: "a".to_string(),
+                count: 1,
+            };
+            Ok(Json(patch.apply_to(&item)?))
+        }
+
+        #[handler(internal)]
+        fn merge_patch(patch: MergePatch<Item>) -> Result<Json<Item>, JsonPatchError> {
+            let item = Item {
+                name: "a".to_string(),
+                count: 1,
+            };
+            Ok(Json(patch.apply(&item)?))
+        }
+
+        let cli = TestClient::new(json_patch);
+        let resp = cli
+            .patch("/")
+            .header(header::CONTENT_TYPE, "application/json-patch+json")
+            .body(r#"[{"op": "replace", "path": "/count", "value": 2}]"#)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_json(&Item {
+            name: "a".to_string(),
+            count: 2,
+        })
+        .await;
+
+        cli.patch("/")
+            .header(header::CONTENT_TYPE, "application/json-patch+json")
+            .body(r#"[{"op": "replace", "path": "/count", "value": "x"}]"#)
+            .send()
+            .await
+            .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        cli.patch("/")
+            .header(header::CONTENT_TYPE, "application/json-patch+json")
+            .body(r#"[{"op": "test", "path": "/count", "value": 2}]"#)
+            .send()
+            .await
+            .assert_status(StatusCode::CONFLICT);
+        cli.patch("/")
+            .header(header::CONTENT_TYPE, "application/json-patch+json")
+            .body(r#"[{"op": "unknown"}]"#)
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.patch("/")
+            .body(r#"[]"#)
+            .send()
+            .await
+            .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let resp = TestClient::new(merge_patch)
+            .patch("/")
+            .header(header::CONTENT_TYPE, "application/merge-patch+json")
+            .body(r#"{"name": "b"}"#)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_json(&Item {
+            name: "b".to_string(),
+            count: 1,
+        })
+        .await;
+    }
+}
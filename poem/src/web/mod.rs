@@ -2,20 +2,46 @@
 
 mod accept;
 mod addr;
+#[cfg(feature = "archive")]
+mod archive;
+#[cfg(feature = "arena")]
+mod arena;
+mod cancel_token;
 #[cfg(feature = "compression")]
 mod compress;
+mod conditional;
 #[cfg(feature = "cookie")]
 #[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
 pub mod cookie;
+#[cfg(feature = "secure-headers")]
+mod csp_nonce;
+#[cfg(feature = "csv")]
+mod csv;
 mod data;
+mod early_hints;
+#[cfg(feature = "engine-io")]
+#[cfg_attr(docsrs, doc(cfg(feature = "engine-io")))]
+pub mod engine_io;
+#[cfg(feature = "cookie")]
+mod flash;
 mod form;
+#[cfg(feature = "ip-filter")]
+mod geo_info;
+pub mod htmx;
 mod json;
+mod json_patch;
+mod mount;
 #[cfg(feature = "multipart")]
 mod multipart;
+#[cfg(feature = "pagination")]
+mod pagination;
 mod path;
+mod problem_details;
 mod query;
 mod real_ip;
 mod redirect;
+#[cfg(feature = "soap")]
+mod soap;
 #[cfg(feature = "sse")]
 #[cfg_attr(docsrs, doc(cfg(feature = "sse")))]
 pub mod sse;
@@ -23,6 +49,8 @@ pub mod sse;
 mod static_file;
 #[cfg(feature = "tempfile")]
 mod tempfile;
+#[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
+mod tls_info;
 #[cfg(feature = "xml")]
 mod xml;
 #[cfg(feature = "yaml")]
@@ -32,6 +60,8 @@ pub use headers;
 #[cfg(feature = "csrf")]
 mod csrf;
 mod typed_header;
+#[cfg(feature = "server")]
+mod upgrade;
 #[cfg(feature = "websocket")]
 #[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
 pub mod websocket;
@@ -40,21 +70,49 @@ use std::{convert::Infallible, fmt::Debug, future::Future};
 
 #[cfg(feature = "compression")]
 pub use async_compression::Level as CompressionLevel;
+#[cfg(feature = "arena")]
+pub use bumpalo;
 use bytes::Bytes;
 use futures_util::FutureExt;
 use http::header;
 
+#[cfg(feature = "archive")]
+pub use self::archive::{ArchiveEntry, TarStream, ZipStream};
+#[cfg(feature = "arena")]
+pub use self::arena::RequestArena;
 #[cfg(feature = "compression")]
 pub use self::compress::{Compress, CompressionAlgo};
+#[cfg(feature = "secure-headers")]
+pub use self::csp_nonce::CspNonce;
 #[cfg(feature = "csrf")]
 pub use self::csrf::{CsrfToken, CsrfVerifier};
+#[cfg(feature = "csv")]
+pub use self::csv::{Csv, CsvStream};
+#[cfg(feature = "cookie")]
+pub(crate) use self::flash::OutgoingFlash;
+#[cfg(feature = "cookie")]
+pub use self::flash::{Flash, FlashLevel, FlashMessage, FlashSetter};
+#[cfg(feature = "ip-filter")]
+pub use self::geo_info::GeoInfo;
 #[cfg(feature = "multipart")]
 pub use self::multipart::{Field, Multipart};
+#[cfg(feature = "pagination")]
+pub use self::pagination::{Paginated, Pagination, PaginationConfig, Sort, SortField, SortOrder};
 pub(crate) use self::path::PathDeserializer;
+#[cfg(feature = "soap")]
+pub(crate) use self::soap::{parse_envelope, request_version_and_action};
+#[cfg(feature = "soap")]
+pub use self::soap::{Soap, SoapFault, SoapFaultCode, SoapVersion};
 #[cfg(feature = "static-files")]
 pub use self::static_file::{StaticFileRequest, StaticFileResponse};
 #[cfg(feature = "tempfile")]
 pub use self::tempfile::TempFile;
+#[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
+pub(crate) use self::tls_info::{with_tls_info, TlsInfoSlot};
+#[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
+pub use self::tls_info::{TlsInfo, TlsVersion};
+#[cfg(feature = "server")]
+pub use self::upgrade::{BoxUpgradeResponse, Upgrade, UpgradeResponse};
 #[cfg(feature = "xml")]
 pub use self::xml::Xml;
 #[cfg(feature = "yaml")]
@@ -62,10 +120,16 @@ pub use self::yaml::Yaml;
 pub use self::{
     accept::Accept,
     addr::{LocalAddr, RemoteAddr},
+    cancel_token::CancelToken,
+    conditional::{etag_for, json_etag, Preconditions, TaggedJson},
     data::Data,
+    early_hints::{EarlyHints, InformationalSender},
     form::Form,
-    json::Json,
+    json::{Json, StreamingJson, StreamingJsonConfig},
+    json_patch::{JsonPatch, MergePatch, PatchOperation},
+    mount::{MountPrefix, TailPath},
     path::Path,
+    problem_details::ProblemDetails,
     query::Query,
     real_ip::RealIp,
     redirect::Redirect,
@@ -135,6 +199,12 @@ impl RequestBody {
 ///
 ///    Extracts the remote peer's real ip address from request.
 ///
+/// - **&TlsInfo**
+///
+///    Extracts the TLS handshake details of the connection from request.
+///
+///    _Requires a TLS listener._
+///
 /// - **Method**
 ///
 ///    Extracts the [`Method`] from the incoming request.
@@ -212,6 +282,24 @@ impl RequestBody {
 ///
 ///    _Requires `CookieSession` or `RedisSession` middleware._
 ///
+/// - **&Flash**
+///
+///    Extracts the [`Flash`] messages set by the previous request.
+///
+///    _Requires `FlashMessages` middleware._
+///
+/// - **&GeoInfo**
+///
+///    Extracts the [`GeoInfo`] of the client IP address.
+///
+///    _Requires `IpFilter` middleware with a GeoIP lookup._
+///
+/// - **&EarlyHints**
+///
+///    Extracts the [`EarlyHints`] to send the `103 Early Hints` responses.
+///
+///    _Requires `EarlyHintsManager` middleware._
+///
 /// - **Body**
 ///
 ///    Extracts the [`Body`] from the incoming request.
@@ -248,6 +336,11 @@ impl RequestBody {
 ///    Ready to accept a websocket [`WebSocket`](websocket::WebSocket)
 ///    connection.
 ///
+/// - **Upgrade**
+///
+///    Ready to upgrade the connection to an arbitrary protocol
+///    [`Upgrade`].
+///
 /// - **Locale**
 ///
 ///    Extracts the [`Locale`](crate::i18n::Locale) from the incoming request.
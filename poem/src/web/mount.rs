@@ -0,0 +1,149 @@
+use std::{ops::Deref, sync::Arc};
+
+use crate::{FromRequest, Request, RequestBody, Result};
+
+/// An extractor for the prefix stripped from the path by
+/// [`Route::nest`](crate::Route::nest), which is the path where the endpoint is
+/// mounted.
+///
+/// The prefixes of the nested routes are joined, and it is empty if the
+/// endpoint is not nested, or nested by
+/// [`Route::nest_no_strip`](crate::Route::nest_no_strip). Use
+/// [`MountPrefix::join`] to generate the absolute links in the nested
+/// endpoints.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     test::TestClient,
+///     web::{MountPrefix, Redirect, TailPath},
+///     Route,
+/// };
+///
+/// #[handler]
+/// fn login(prefix: MountPrefix) -> Redirect {
+///     Redirect::see_other(prefix.join("/home"))
+/// }
+///
+/// #[handler]
+/// fn tail(prefix: MountPrefix, tail: TailPath) -> String {
+///     format!("{} {}", prefix.as_str(), tail.as_str())
+/// }
+///
+/// let app = Route::new().nest(
+///     "/admin",
+///     Route::new().at("/login", login).at("/users/:id", tail),
+/// );
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/admin/login").send().await;
+/// resp.assert_header("location", "/admin/home");
+///
+/// let resp = cli.get("/admin/users/1").send().await;
+/// resp.assert_text("/admin /users/1").await;
+/// # });
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct MountPrefix(pub Arc<str>);
+
+impl MountPrefix {
+    /// Returns the prefix as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Joins the prefix with a path relative to the mount point.
+    pub fn join(&self, path: &str) -> String {
+        match path {
+            "" | "/" if !self.0.is_empty() => self.0.to_string(),
+            _ if path.starts_with('/') => format!("{}{path}", self.0),
+            _ => format!("{}/{path}", self.0),
+        }
+    }
+}
+
+impl Deref for MountPrefix {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> FromRequest<'a> for MountPrefix {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req.data::<MountPrefix>().cloned().unwrap_or_default())
+    }
+}
+
+/// An extractor for the path relative to the [`MountPrefix`], which is the
+/// path after the prefixes are stripped by
+/// [`Route::nest`](crate::Route::nest).
+///
+/// See [`MountPrefix`] for an example.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TailPath(pub String);
+
+impl TailPath {
+    /// Returns the path as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for TailPath {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> FromRequest<'a> for TailPath {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(TailPath(req.uri().path().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, test::TestClient, Route};
+
+    #[handler(internal)]
+    fn paths(prefix: MountPrefix, tail: TailPath) -> String {
+        format!("{}|{}", prefix.as_str(), tail.as_str())
+    }
+
+    #[tokio::test]
+    async fn nested() {
+        let app = Route::new()
+            .at("/a", paths)
+            .nest(
+                "/b",
+                Route::new()
+                    .at("/", paths)
+                    .nest("/c", Route::new().at("/d", paths)),
+            )
+            .nest_no_strip("/e", Route::new().at("/e/f", paths));
+        let cli = TestClient::new(app);
+
+        cli.get("/a").send().await.assert_text("|/a").await;
+        cli.get("/b").send().await.assert_text("/b|/").await;
+        cli.get("/b/c/d").send().await.assert_text("/b/c|/d").await;
+        cli.get("/e/f").send().await.assert_text("|/e/f").await;
+    }
+
+    #[test]
+    fn join() {
+        let prefix = MountPrefix("/api".into());
+        assert_eq!(prefix.join("/users"), "/api/users");
+        assert_eq!(prefix.join("users"), "/api/users");
+        assert_eq!(prefix.join("/"), "/api");
+        assert_eq!(MountPrefix::default().join("/users"), "/users");
+        assert_eq!(MountPrefix::default().join(""), "/");
+    }
+}
@@ -0,0 +1,600 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    ops::{Deref, DerefMut},
+    sync::{Arc, OnceLock},
+};
+
+use base64::engine::{general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use rand::{thread_rng, RngCore};
+use sha2::Sha256;
+
+use crate::{
+    error::PaginationError,
+    http::{header, HeaderValue, Uri},
+    FromRequest, IntoResponse, Request, RequestBody, Response, Result,
+};
+
+/// The configuration of the [`Pagination`] extractor.
+///
+/// Add it to the endpoint with
+/// [`EndpointExt::data`](crate::EndpointExt::data), otherwise the default
+/// configuration is used, which signs the cursors with a random key generated
+/// when the process starts.
+#[derive(Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "pagination")))]
+pub struct PaginationConfig {
+    default_size: u64,
+    max_size: u64,
+    key: Arc<[u8]>,
+}
+
+impl Debug for PaginationConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PaginationConfig")
+            .field("default_size", &self.default_size)
+            .field("max_size", &self.max_size)
+            .finish()
+    }
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        static KEY: OnceLock<Arc<[u8]>> = OnceLock::new();
+
+        Self {
+            default_size: 20,
+            max_size: 100,
+            key: KEY
+                .get_or_init(|| {
+                    let mut key = [0; 32];
+                    thread_rng().fill_bytes(&mut key);
+                    key.into()
+                })
+                .clone(),
+        }
+    }
+}
+
+impl PaginationConfig {
+    /// Create a `PaginationConfig`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the page size used when the `size` parameter is missing, defaults
+    /// to `20`.
+    #[must_use]
+    pub fn default_size(self, size: u64) -> Self {
+        Self {
+            default_size: size,
+            ..self
+        }
+    }
+
+    /// Sets the maximum page size, defaults to `100`.
+    #[must_use]
+    pub fn max_size(self, size: u64) -> Self {
+        Self {
+            max_size: size,
+            ..self
+        }
+    }
+
+    /// Sets the key used to sign the cursors.
+    #[must_use]
+    pub fn key(self, key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            key: key.into().into(),
+            ..self
+        }
+    }
+
+    fn mac(&self, value: &[u8]) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC can take key of any size");
+        mac.update(value);
+        mac
+    }
+
+    /// Returns a signed cursor for the value.
+    pub fn encode_cursor(&self, value: &str) -> String {
+        let signature = self.mac(value.as_bytes()).finalize().into_bytes();
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(value),
+            URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    /// Verifies the signed cursor and returns the value.
+    pub fn decode_cursor(&self, cursor: &str) -> Result<String, PaginationError> {
+        let (value, signature) = cursor
+            .split_once('.')
+            .ok_or(PaginationError::InvalidCursor)?;
+        let value = URL_SAFE_NO_PAD
+            .decode(value)
+            .map_err(|_| PaginationError::InvalidCursor)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| PaginationError::InvalidCursor)?;
+        self.mac(&value)
+            .verify_slice(&signature)
+            .map_err(|_| PaginationError::InvalidCursor)?;
+        String::from_utf8(value).map_err(|_| PaginationError::InvalidCursor)
+    }
+}
+
+fn query_pairs(uri: &Uri) -> Vec<(String, String)> {
+    uri.query()
+        .and_then(|query| crate::urlencoded::from_str(query).ok())
+        .unwrap_or_default()
+}
+
+/// An extractor for the pagination parameters of the request.
+///
+/// It supports both the page based pagination with the `page` (starting from
+/// `1`) and `size` query parameters, and the cursor based pagination with the
+/// `cursor` and `size` query parameters. The cursors are signed with the key
+/// of the [`PaginationConfig`], so the clients can't forge them.
+///
+/// # Errors
+///
+/// - [`PaginationError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     http::header,
+///     test::TestClient,
+///     web::{Json, Paginated, Pagination},
+///     Request,
+/// };
+///
+/// #[handler]
+/// fn list(req: &Request, pagination: Pagination) -> Paginated<Json<Vec<u64>>> {
+///     let total = 95;
+///     let items = (pagination.offset()..total)
+///         .take(pagination.size() as usize)
+///         .collect();
+///     Paginated::new(Json(items)).offset_links(req.original_uri(), &pagination, total)
+/// }
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = TestClient::new(get(list))
+///     .get("/items")
+///     .query("page", &2)
+///     .query("size", &10)
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_header("x-total-count", "95");
+/// resp.assert_header(
+///     header::LINK,
+///     r#"</items?page=1&size=10>; rel="first", </items?page=1&size=10>; rel="prev", </items?page=3&size=10>; rel="next", </items?page=10&size=10>; rel="last""#,
+/// );
+/// resp.assert_json(&(10..20).collect::<Vec<u64>>()).await;
+/// # });
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "pagination")))]
+pub struct Pagination {
+    page: u64,
+    size: u64,
+    cursor: Option<String>,
+    config: PaginationConfig,
+}
+
+impl Pagination {
+    /// Returns the page number, starting from `1`.
+    #[inline]
+    pub fn page(&self) -> u64 {
+        self.page
+    }
+
+    /// Returns the page size.
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the number of items to skip for the page based pagination.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        (self.page - 1) * self.size
+    }
+
+    /// Returns the verified value of the `cursor` parameter.
+    #[inline]
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    /// Returns a signed cursor for the value, which is used as the `cursor`
+    /// parameter of the next page.
+    pub fn encode_cursor(&self, value: &str) -> String {
+        self.config.encode_cursor(value)
+    }
+
+    fn link(&self, uri: &Uri, param: (&str, &str)) -> String {
+        let mut pairs = query_pairs(uri);
+        pairs.retain(|(name, _)| !matches!(name.as_str(), "page" | "cursor" | "size"));
+        pairs.push((param.0.to_string(), param.1.to_string()));
+        pairs.push(("size".to_string(), self.size.to_string()));
+        format!(
+            "{}?{}",
+            uri.path(),
+            serde_urlencoded::to_string(pairs).unwrap_or_default()
+        )
+    }
+}
+
+impl<'a> FromRequest<'a> for Pagination {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        let config = req.data::<PaginationConfig>().cloned().unwrap_or_default();
+        let mut pagination = Self {
+            page: 1,
+            size: config.default_size,
+            cursor: None,
+            config,
+        };
+
+        for (name, value) in query_pairs(req.uri()) {
+            match name.as_str() {
+                "page" => {
+                    pagination.page = value
+                        .parse()
+                        .ok()
+                        .filter(|page| *page > 0)
+                        .ok_or(PaginationError::InvalidPage)?;
+                }
+                "size" => {
+                    let max = pagination.config.max_size;
+                    pagination.size = value
+                        .parse()
+                        .ok()
+                        .filter(|size| (1..=max).contains(size))
+                        .ok_or(PaginationError::InvalidSize { max })?;
+                }
+                "cursor" => pagination.cursor = Some(pagination.config.decode_cursor(&value)?),
+                _ => {}
+            }
+        }
+
+        Ok(pagination)
+    }
+}
+
+/// Represents the fields that a [`Sort`] accepts.
+///
+/// # Example
+///
+/// ```
+/// use poem::web::SortField;
+///
+/// enum UserField {
+///     Name,
+///     CreatedAt,
+/// }
+///
+/// impl SortField for UserField {
+///     const FIELDS: &'static [&'static str] = &["name", "created_at"];
+///
+///     fn from_name(name: &str) -> Option<Self> {
+///         match name {
+///             "name" => Some(UserField::Name),
+///             "created_at" => Some(UserField::CreatedAt),
+///             _ => None,
+///         }
+///     }
+/// }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "pagination")))]
+pub trait SortField: Sized + Send {
+    /// The names of the fields.
+    const FIELDS: &'static [&'static str];
+
+    /// Returns the field with the name, or `None` if it is not allowed.
+    fn from_name(name: &str) -> Option<Self>;
+}
+
+/// The order of a sort field.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(docsrs, doc(cfg(feature = "pagination")))]
+pub enum SortOrder {
+    /// Ascending order.
+    Asc,
+    /// Descending order.
+    Desc,
+}
+
+/// An extractor for the `sort` query parameter.
+///
+/// The parameter is a comma separated list of field names, each optionally
+/// prefixed with `-` for descending order, such as `sort=-created_at,name`.
+/// Only the fields accepted by the [`SortField`] are allowed.
+///
+/// # Errors
+///
+/// - [`PaginationError`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(docsrs, doc(cfg(feature = "pagination")))]
+pub struct Sort<T>(pub Vec<(T, SortOrder)>);
+
+impl<T> Default for Sort<T> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<T> Deref for Sort<T> {
+    type Target = Vec<(T, SortOrder)>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Sort<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: SortField> Sort<T> {
+    /// Parses the value of the `sort` parameter.
+    pub fn parse(value: &str) -> Result<Self, PaginationError> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let (name, order) = match name.strip_prefix('-') {
+                    Some(name) => (name, SortOrder::Desc),
+                    None => (name.strip_prefix('+').unwrap_or(name), SortOrder::Asc),
+                };
+                T::from_name(name)
+                    .map(|field| (field, order))
+                    .ok_or_else(|| PaginationError::InvalidSortField(name.to_string()))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl<'a, T: SortField> FromRequest<'a> for Sort<T> {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        let mut sort = Self::default();
+        for (_, value) in query_pairs(req.uri())
+            .into_iter()
+            .filter(|(name, _)| name == "sort")
+        {
+            sort.0.extend(Self::parse(&value)?.0);
+        }
+        Ok(sort)
+    }
+}
+
+/// A paginated response, with the `Link` header and the `X-Total-Count`
+/// header.
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "pagination")))]
+pub struct Paginated<T> {
+    body: T,
+    total: Option<u64>,
+    links: Vec<(String, String)>,
+}
+
+impl<T> Paginated<T> {
+    /// Create a paginated response with the body.
+    pub fn new(body: T) -> Self {
+        Self {
+            body,
+            total: None,
+            links: Vec::new(),
+        }
+    }
+
+    /// Sets the total number of items, which is sent in the `X-Total-Count`
+    /// header.
+    #[must_use]
+    pub fn total(self, total: u64) -> Self {
+        Self {
+            total: Some(total),
+            ..self
+        }
+    }
+
+    /// Appends a link with the relation type to the `Link` header.
+    #[must_use]
+    pub fn link(mut self, rel: impl Into<String>, uri: impl Into<String>) -> Self {
+        self.links.push((rel.into(), uri.into()));
+        self
+    }
+
+    /// Sets the total number of items and appends the `first`, `prev`, `next`
+    /// and `last` links of the page based pagination.
+    #[must_use]
+    pub fn offset_links(self, uri: &Uri, pagination: &Pagination, total: u64) -> Self {
+        let page = pagination.page();
+        let last = total.div_ceil(pagination.size()).max(1);
+        let link = |page: u64| pagination.link(uri, ("page", &page.to_string()));
+
+        let mut resp = self.total(total).link("first", link(1));
+        if page > 1 {
+            resp = resp.link("prev", link((page - 1).min(last)));
+        }
+        if page < last {
+            resp = resp.link("next", link(page + 1));
+        }
+        resp.link("last", link(last))
+    }
+
+    /// Appends the `next` link of the cursor based pagination, if there is a
+    /// next page.
+    #[must_use]
+    pub fn cursor_links(self, uri: &Uri, pagination: &Pagination, next: Option<&str>) -> Self {
+        match next {
+            Some(next) => {
+                let cursor = pagination.encode_cursor(next);
+                self.link("next", pagination.link(uri, ("cursor", &cursor)))
+            }
+            None => self,
+        }
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for Paginated<T> {
+    fn into_response(self) -> Response {
+        let mut resp = self.body.into_response();
+        if !self.links.is_empty() {
+            let link = self
+                .links
+                .iter()
+                .map(|(rel, uri)| format!("<{uri}>; rel=\"{rel}\""))
+                .collect::<Vec<_>>()
+                .join(", ");
+            if let Ok(link) = HeaderValue::from_str(&link) {
+                resp.headers_mut().insert(header::LINK, link);
+            }
+        }
+        if let Some(total) = self.total {
+            resp.headers_mut()
+                .insert("x-total-count", HeaderValue::from(total));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, http::StatusCode, test::TestClient, web::Json, EndpointExt};
+
+    #[derive(Debug, Eq, PartialEq)]
+    enum Field {
+        Name,
+        CreatedAt,
+    }
+
+    impl SortField for Field {
+        const FIELDS: &'static [&'static str] = &["name", "created_at"];
+
+        fn from_name(name: &str) -> Option<Self> {
+            match name {
+                "name" => Some(Field::Name),
+                "created_at" => Some(Field::CreatedAt),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn sort() {
+        assert_eq!(
+            Sort::<Field>::parse("-created_at, +name,").unwrap().0,
+            vec![
+                (Field::CreatedAt, SortOrder::Desc),
+                (Field::Name, SortOrder::Asc)
+            ]
+        );
+        assert!(Sort::<Field>::parse("").unwrap().is_empty());
+        assert_eq!(
+            Sort::<Field>::parse("name,password"),
+            Err(PaginationError::InvalidSortField("password".to_string()))
+        );
+    }
+
+    #[test]
+    fn cursor() {
+        let config = PaginationConfig::new().key("key");
+        let cursor = config.encode_cursor("42");
+        assert_eq!(config.decode_cursor(&cursor).unwrap(), "42");
+        assert_eq!(
+            PaginationConfig::new().key("other").decode_cursor(&cursor),
+            Err(PaginationError::InvalidCursor)
+        );
+        let (_, signature) = cursor.split_once('.').unwrap();
+        assert_eq!(
+            config.decode_cursor(&format!("{}.{signature}", URL_SAFE_NO_PAD.encode("43"))),
+            Err(PaginationError::InvalidCursor)
+        );
+    }
+
+    #[tokio::test]
+    async fn pagination() {
+        #[handler(internal)]
+        fn list(req: &Request, pagination: Pagination, sort: Sort<Field>) -> impl IntoResponse {
+            let start = pagination
+                .cursor()
+                .map(|cursor| cursor.parse().unwrap())
+                .unwrap_or_default();
+            let items: Vec<u64> = (start..10).take(pagination.size() as usize).collect();
+            let next = items.last().filter(|last| **last < 9).map(|last| last + 1);
+            Paginated::new(Json((items, sort.len()))).cursor_links(
+                req.original_uri(),
+                &pagination,
+                next.map(|next| next.to_string()).as_deref(),
+            )
+        }
+
+        let cli = TestClient::new(list.data(PaginationConfig::new().default_size(4).max_size(5)));
+
+        let resp = cli.get("/").query("sort", &"-name").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_json(&((0..4).collect::<Vec<u64>>(), 1)).await;
+
+        let resp = cli.get("/").query("sort", &"-name").send().await;
+        let link = resp.0.headers()[header::LINK].to_str().unwrap();
+        let uri: Uri = link[1..link.find('>').unwrap()].parse().unwrap();
+        assert!(link.ends_with("; rel=\"next\""));
+        assert!(uri.query().unwrap().starts_with("sort=-name&cursor="));
+        assert!(uri.query().unwrap().ends_with("&size=4"));
+
+        let resp = cli.get(uri.to_string()).send().await;
+        resp.assert_status_is_ok();
+        resp.assert_json(&((4..8).collect::<Vec<u64>>(), 1)).await;
+
+        cli.get("/")
+            .query("cursor", &"NDI.invalid")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.get("/")
+            .query("size", &6)
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.get("/")
+            .query("page", &0)
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.get("/")
+            .query("sort", &"age")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn offset_links() {
+        let pagination = Pagination {
+            page: 3,
+            size: 10,
+            cursor: None,
+            config: PaginationConfig::default(),
+        };
+        let uri: Uri = "/items?q=a&page=3".parse().unwrap();
+        let resp = Paginated::new(())
+            .offset_links(&uri, &pagination, 30)
+            .into_response();
+        assert_eq!(resp.headers()["x-total-count"], "30");
+        assert_eq!(
+            resp.headers()[header::LINK],
+            r#"</items?q=a&page=1&size=10>; rel="first", </items?q=a&page=2&size=10>; rel="prev", </items?q=a&page=3&size=10>; rel="last""#
+        );
+    }
+}
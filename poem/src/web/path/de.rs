@@ -1,4 +1,7 @@
-use std::fmt::{self, Display};
+use std::{
+    fmt::{self, Display},
+    sync::Arc,
+};
 
 use serde::{
     de::{self, DeserializeSeed, EnumAccess, Error, MapAccess, SeqAccess, VariantAccess, Visitor},
@@ -75,12 +78,12 @@ macro_rules! parse_single_value {
 }
 
 pub(crate) struct PathDeserializer<'de> {
-    url_params: &'de [(String, String)],
+    url_params: &'de [(Arc<str>, String)],
 }
 
 impl<'de> PathDeserializer<'de> {
     #[inline]
-    pub(crate) fn new(url_params: &'de [(String, String)]) -> Self {
+    pub(crate) fn new(url_params: &'de [(Arc<str>, String)]) -> Self {
         PathDeserializer { url_params }
     }
 }
@@ -248,7 +251,7 @@ impl<'de> Deserializer<'de> for PathDeserializer<'de> {
 }
 
 struct MapDeserializer<'de> {
-    params: &'de [(String, String)],
+    params: &'de [(Arc<str>, String)],
     value: Option<&'de str>,
 }
 
@@ -518,7 +521,7 @@ impl<'de> VariantAccess<'de> for UnitVariant {
 }
 
 struct SeqDeserializer<'de> {
-    params: &'de [(String, String)],
+    params: &'de [(Arc<str>, String)],
 }
 
 impl<'de> SeqAccess<'de> for SeqDeserializer<'de> {
@@ -566,7 +569,7 @@ mod tests {
     fn create_url_params<I, K, V>(values: I) -> PathParams
     where
         I: IntoIterator<Item = (K, V)>,
-        K: Into<String>,
+        K: Into<Arc<str>>,
         V: Into<String>,
     {
         values
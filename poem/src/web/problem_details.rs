@@ -0,0 +1,290 @@
+use std::fmt::{self, Display, Formatter};
+
+use http::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{error::ResponseError, Error, IntoResponse, Response};
+
+/// The content type of the problem details.
+pub(crate) const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Problem details for HTTP APIs as defined in
+/// [RFC 9457](https://www.rfc-editor.org/rfc/rfc9457).
+///
+/// It can be used as a response, or as an error. The response body is a JSON
+/// object with the `application/problem+json` content type.
+///
+/// See also [`ProblemJson`](crate::middleware::ProblemJson), which converts
+/// all the errors into problem details.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler, http::StatusCode, test::TestClient, web::ProblemDetails, Result,
+/// };
+/// use serde_json::json;
+///
+/// #[handler]
+/// fn index() -> Result<()> {
+///     Err(ProblemDetails::new(StatusCode::FORBIDDEN)
+///         .ty("https://example.com/probs/out-of-credit")
+///         .title("You do not have enough credit.")
+///         .detail("Your current balance is 30, but that costs 50.")
+///         .instance("/account/12345/msgs/abc")
+///         .extension("balance", 30)
+///         .into())
+/// }
+///
+/// let cli = TestClient::new(index);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status(StatusCode::FORBIDDEN);
+/// resp.assert_content_type("application/problem+json");
+/// resp.assert_json(json!({
+///     "type": "https://example.com/probs/out-of-credit",
+///     "title": "You do not have enough credit.",
+///     "status": 403,
+///     "detail": "Your current balance is 30, but that costs 50.",
+///     "instance": "/account/12345/msgs/abc",
+///     "balance": 30,
+/// }))
+/// .await;
+/// # });
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    ty: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    instance: Option<String>,
+    #[serde(flatten)]
+    extensions: Map<String, Value>,
+}
+
+impl ProblemDetails {
+    /// Create a `ProblemDetails` with the status code, the title is
+    /// initialized to the canonical reason of the status code.
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            ty: None,
+            title: status.canonical_reason().map(ToString::to_string),
+            status: status.as_u16(),
+            detail: None,
+            instance: None,
+            extensions: Map::new(),
+        }
+    }
+
+    /// Create a `ProblemDetails` from an [`Error`].
+    ///
+    /// If the error was created from a `ProblemDetails`, it is returned as it
+    /// is. Otherwise, the status code is taken from the error, and the error
+    /// message is used as the detail.
+    pub fn from_error(err: &Error) -> Self {
+        if let Some(problem) = err.downcast_ref::<ProblemDetails>() {
+            return problem.clone();
+        }
+
+        let status = err.status();
+        let problem = Self::new(status);
+        let msg = err.to_string();
+        if msg.is_empty() || msg == status.to_string() {
+            problem
+        } else {
+            problem.detail(msg)
+        }
+    }
+
+    /// Sets the URI reference that identifies the problem type.
+    #[must_use]
+    pub fn ty(self, ty: impl Into<String>) -> Self {
+        Self {
+            ty: Some(ty.into()),
+            ..self
+        }
+    }
+
+    /// Sets the short, human-readable summary of the problem type.
+    #[must_use]
+    pub fn title(self, title: impl Into<String>) -> Self {
+        Self {
+            title: Some(title.into()),
+            ..self
+        }
+    }
+
+    /// Sets the human-readable explanation specific to this occurrence of the
+    /// problem.
+    #[must_use]
+    pub fn detail(self, detail: impl Into<String>) -> Self {
+        Self {
+            detail: Some(detail.into()),
+            ..self
+        }
+    }
+
+    /// Sets the URI reference that identifies the specific occurrence of the
+    /// problem.
+    #[must_use]
+    pub fn instance(self, instance: impl Into<String>) -> Self {
+        Self {
+            instance: Some(instance.into()),
+            ..self
+        }
+    }
+
+    /// Adds an extension member.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value cannot be serialized to JSON.
+    #[must_use]
+    pub fn extension(mut self, name: impl Into<String>, value: impl Serialize) -> Self {
+        self.extensions.insert(
+            name.into(),
+            serde_json::to_value(value).expect("valid extension value"),
+        );
+        self
+    }
+
+    /// Returns the URI reference that identifies the problem type.
+    pub fn get_type(&self) -> Option<&str> {
+        self.ty.as_deref()
+    }
+
+    /// Returns the summary of the problem type.
+    pub fn get_title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Returns the status code.
+    pub fn get_status(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    /// Returns the explanation of this occurrence of the problem.
+    pub fn get_detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+
+    /// Returns the URI reference that identifies this occurrence of the
+    /// problem.
+    pub fn get_instance(&self) -> Option<&str> {
+        self.instance.as_deref()
+    }
+
+    /// Returns the extension members.
+    pub fn extensions(&self) -> &Map<String, Value> {
+        &self.extensions
+    }
+
+    pub(crate) fn has_instance(&self) -> bool {
+        self.instance.is_some()
+    }
+}
+
+impl Display for ProblemDetails {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match (&self.title, &self.detail) {
+            (Some(title), Some(detail)) => write!(f, "{title}: {detail}"),
+            (Some(msg), None) | (None, Some(msg)) => write!(f, "{msg}"),
+            (None, None) => write!(f, "{}", self.get_status()),
+        }
+    }
+}
+
+impl std::error::Error for ProblemDetails {}
+
+impl ResponseError for ProblemDetails {
+    fn status(&self) -> StatusCode {
+        self.get_status()
+    }
+
+    fn as_response(&self) -> Response {
+        self.clone().into_response()
+    }
+}
+
+impl IntoResponse for ProblemDetails {
+    fn into_response(self) -> Response {
+        let data = match serde_json::to_vec(&self) {
+            Ok(data) => data,
+            Err(err) => {
+                return Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(err.to_string())
+            }
+        };
+        Response::builder()
+            .status(self.get_status())
+            .header(header::CONTENT_TYPE, PROBLEM_JSON)
+            .body(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::error::{NotFoundError, ParseJsonError};
+
+    #[test]
+    fn from_error() {
+        assert_eq!(
+            ProblemDetails::from_error(&NotFoundError.into()),
+            ProblemDetails::new(StatusCode::NOT_FOUND).detail("not found")
+        );
+        assert_eq!(
+            ProblemDetails::from_error(&Error::from_status(StatusCode::BAD_GATEWAY)),
+            ProblemDetails::new(StatusCode::BAD_GATEWAY)
+        );
+        assert_eq!(
+            ProblemDetails::from_error(
+                &ParseJsonError::InvalidContentType("text/plain".into()).into()
+            ),
+            ProblemDetails::new(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+                .detail("invalid content type `text/plain`, expect: `application/json`")
+        );
+
+        let problem = ProblemDetails::new(StatusCode::CONFLICT).extension("a", 1);
+        assert_eq!(ProblemDetails::from_error(&problem.clone().into()), problem);
+    }
+
+    #[tokio::test]
+    async fn into_response() {
+        let resp = ProblemDetails::new(StatusCode::BAD_REQUEST)
+            .detail("abc")
+            .extension("errors", ["a", "b"])
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(resp.content_type(), Some(PROBLEM_JSON));
+        assert_eq!(
+            resp.into_body().into_json::<Value>().await.unwrap(),
+            json!({
+                "title": "Bad Request",
+                "status": 400,
+                "detail": "abc",
+                "errors": ["a", "b"],
+            })
+        );
+
+        let problem: ProblemDetails = serde_json::from_value(json!({
+            "type": "about:blank",
+            "status": 404,
+            "a": 1,
+        }))
+        .unwrap();
+        assert_eq!(problem.get_type(), Some("about:blank"));
+        assert_eq!(problem.get_status(), StatusCode::NOT_FOUND);
+        assert_eq!(problem.extensions().get("a"), Some(&json!(1)));
+    }
+}
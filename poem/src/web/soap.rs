@@ -0,0 +1,526 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::{Deref, DerefMut},
+};
+
+use http::StatusCode;
+use quick_xml::{escape::escape, events::Event, name::ResolveResult, NsReader};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    error::{ParseSoapError, ResponseError},
+    http::header,
+    web::RequestBody,
+    FromRequest, IntoResponse, Request, Response, Result,
+};
+
+/// The version of the SOAP protocol.
+#[cfg_attr(docsrs, doc(cfg(feature = "soap")))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum SoapVersion {
+    /// SOAP 1.1, uses the `text/xml` content type.
+    #[default]
+    V11,
+    /// SOAP 1.2, uses the `application/soap+xml` content type.
+    V12,
+}
+
+impl SoapVersion {
+    /// Returns the namespace of the envelope.
+    pub fn namespace(&self) -> &'static str {
+        match self {
+            SoapVersion::V11 => "http://schemas.xmlsoap.org/soap/envelope/",
+            SoapVersion::V12 => "http://www.w3.org/2003/05/soap-envelope",
+        }
+    }
+
+    /// Returns the content type of the messages.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SoapVersion::V11 => "text/xml; charset=utf-8",
+            SoapVersion::V12 => "application/soap+xml; charset=utf-8",
+        }
+    }
+
+    pub(crate) fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.parse::<mime::Mime>().ok()?;
+        match mime.essence_str() {
+            "text/xml" => Some(SoapVersion::V11),
+            "application/soap+xml" => Some(SoapVersion::V12),
+            _ => None,
+        }
+    }
+
+    fn from_namespace(namespace: &[u8]) -> Option<Self> {
+        [SoapVersion::V11, SoapVersion::V12]
+            .into_iter()
+            .find(|version| version.namespace().as_bytes() == namespace)
+    }
+
+    fn envelope(&self, header: Option<&str>, body: &str) -> String {
+        let namespace = self.namespace();
+        let header = header
+            .map(|header| format!("<soap:Header>{header}</soap:Header>"))
+            .unwrap_or_default();
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?><soap:Envelope xmlns:soap="{namespace}">{header}<soap:Body>{body}</soap:Body></soap:Envelope>"#
+        )
+    }
+}
+
+/// Returns the version and the SOAP action of the request.
+///
+/// SOAP 1.1 puts the action in the `SOAPAction` header, and SOAP 1.2 puts it
+/// in the `action` parameter of the content type.
+pub(crate) fn request_version_and_action(
+    req: &Request,
+) -> Result<(SoapVersion, Option<String>), ParseSoapError> {
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .ok_or(ParseSoapError::ContentTypeRequired)?;
+    let version = SoapVersion::from_content_type(content_type)
+        .ok_or_else(|| ParseSoapError::InvalidContentType(content_type.into()))?;
+
+    let action = match version {
+        SoapVersion::V11 => req
+            .headers()
+            .get("soapaction")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().trim_matches('"').to_string()),
+        SoapVersion::V12 => content_type
+            .parse::<mime::Mime>()
+            .ok()
+            .and_then(|mime| mime.get_param("action").map(|value| value.to_string())),
+    };
+    Ok((version, action.filter(|action| !action.is_empty())))
+}
+
+/// The parsed SOAP envelope.
+pub(crate) struct Envelope<'a> {
+    pub(crate) header: Option<&'a str>,
+    /// The first element in the body.
+    pub(crate) body: &'a str,
+    /// The local name of the first element in the body.
+    pub(crate) operation: Option<String>,
+}
+
+pub(crate) fn parse_envelope(
+    version: SoapVersion,
+    data: &str,
+) -> Result<Envelope<'_>, ParseSoapError> {
+    let invalid = |reason: String| ParseSoapError::InvalidEnvelope { version, reason };
+    let mut reader = NsReader::from_str(data);
+    let mut header = None;
+    let mut in_body = false;
+    let mut in_envelope = false;
+
+    loop {
+        let start = reader.buffer_position() as usize;
+        let (namespace, event) = reader
+            .read_resolved_event()
+            .map_err(|err| invalid(err.to_string()))?;
+        let is_soap = matches!(&namespace, ResolveResult::Bound(ns) if ns.as_ref() == version.namespace().as_bytes());
+
+        match event {
+            Event::Start(_) | Event::Empty(_) if in_body => {
+                let operation = match &event {
+                    Event::Start(tag) => {
+                        reader
+                            .read_to_end(tag.name())
+                            .map_err(|err| invalid(err.to_string()))?;
+                        tag.local_name()
+                    }
+                    Event::Empty(tag) => tag.local_name(),
+                    _ => unreachable!(),
+                };
+                return Ok(Envelope {
+                    header,
+                    body: &data[start..reader.buffer_position() as usize],
+                    operation: Some(String::from_utf8_lossy(operation.as_ref()).into_owned()),
+                });
+            }
+            Event::Start(tag) if !in_envelope && tag.local_name().as_ref() == b"Envelope" => {
+                if !is_soap {
+                    return Err(match namespace {
+                        ResolveResult::Bound(ns)
+                            if SoapVersion::from_namespace(ns.as_ref()).is_some() =>
+                        {
+                            ParseSoapError::VersionMismatch { version }
+                        }
+                        _ => invalid("unknown envelope namespace".to_string()),
+                    });
+                }
+                in_envelope = true;
+            }
+            Event::Start(tag)
+                if in_envelope
+                    && is_soap
+                    && header.is_none()
+                    && tag.local_name().as_ref() == b"Header" =>
+            {
+                let span = reader
+                    .read_to_end(tag.name())
+                    .map_err(|err| invalid(err.to_string()))?;
+                header = Some(data[span.start as usize..span.end as usize].trim());
+            }
+            Event::Start(tag) if in_envelope && is_soap && tag.local_name().as_ref() == b"Body" => {
+                in_body = true;
+            }
+            Event::Empty(tag) if in_envelope && is_soap && tag.local_name().as_ref() == b"Body" => {
+                return Ok(Envelope {
+                    header,
+                    body: "",
+                    operation: None,
+                });
+            }
+            Event::End(_) if in_body => {
+                return Ok(Envelope {
+                    header,
+                    body: "",
+                    operation: None,
+                });
+            }
+            Event::Text(text) if text.iter().all(u8::is_ascii_whitespace) => {}
+            Event::Decl(_) | Event::Comment(_) | Event::PI(_) => {}
+            Event::DocType(_) => return Err(invalid("DTD is not allowed".to_string())),
+            Event::Eof => return Err(invalid("missing body".to_string())),
+            _ => return Err(invalid("unexpected content".to_string())),
+        }
+    }
+}
+
+/// The code of a [`SoapFault`].
+#[cfg_attr(docsrs, doc(cfg(feature = "soap")))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SoapFaultCode {
+    /// The envelope has an invalid namespace.
+    VersionMismatch,
+    /// A mandatory header entry is not understood.
+    MustUnderstand,
+    /// The message is incorrectly formed, `Sender` in SOAP 1.2.
+    Client,
+    /// The message could not be processed, `Receiver` in SOAP 1.2.
+    Server,
+}
+
+impl SoapFaultCode {
+    fn name(&self, version: SoapVersion) -> &'static str {
+        match (self, version) {
+            (SoapFaultCode::VersionMismatch, _) => "VersionMismatch",
+            (SoapFaultCode::MustUnderstand, _) => "MustUnderstand",
+            (SoapFaultCode::Client, SoapVersion::V11) => "Client",
+            (SoapFaultCode::Client, SoapVersion::V12) => "Sender",
+            (SoapFaultCode::Server, SoapVersion::V11) => "Server",
+            (SoapFaultCode::Server, SoapVersion::V12) => "Receiver",
+        }
+    }
+}
+
+/// A SOAP fault, which can be used as a response or an error.
+///
+/// The faults are sent with `500 Internal Server Error`, except the `Sender`
+/// faults of SOAP 1.2, which are sent with `400 Bad Request`.
+///
+/// # Example
+///
+/// ```
+/// use poem::web::{Soap, SoapFault};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize)]
+/// struct GetUser {
+///     id: i64,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct GetUserResponse {
+///     name: String,
+/// }
+///
+/// #[poem::handler]
+/// async fn get_user(req: Soap<GetUser>) -> Result<Soap<GetUserResponse>, SoapFault> {
+///     if req.id != 1 {
+///         return Err(SoapFault::client("user not found").with_version(req.version));
+///     }
+///     Ok(req.reply(GetUserResponse {
+///         name: "sunli".to_string(),
+///     }))
+/// }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "soap")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SoapFault {
+    version: SoapVersion,
+    code: SoapFaultCode,
+    reason: String,
+    detail: Option<String>,
+}
+
+impl SoapFault {
+    /// Create a SOAP 1.1 fault with the code and reason.
+    pub fn new(code: SoapFaultCode, reason: impl Into<String>) -> Self {
+        Self {
+            version: SoapVersion::V11,
+            code,
+            reason: reason.into(),
+            detail: None,
+        }
+    }
+
+    /// Create a fault with the [`SoapFaultCode::Client`] code.
+    pub fn client(reason: impl Into<String>) -> Self {
+        Self::new(SoapFaultCode::Client, reason)
+    }
+
+    /// Create a fault with the [`SoapFaultCode::Server`] code.
+    pub fn server(reason: impl Into<String>) -> Self {
+        Self::new(SoapFaultCode::Server, reason)
+    }
+
+    /// Sets the SOAP version of the fault.
+    #[must_use]
+    pub fn with_version(self, version: SoapVersion) -> Self {
+        Self { version, ..self }
+    }
+
+    /// Sets the detail of the fault, which is an XML fragment.
+    #[must_use]
+    pub fn with_detail(self, detail: impl Into<String>) -> Self {
+        Self {
+            detail: Some(detail.into()),
+            ..self
+        }
+    }
+
+    /// Returns the code of the fault.
+    pub fn code(&self) -> SoapFaultCode {
+        self.code
+    }
+
+    /// Returns the reason of the fault.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    fn to_xml(&self) -> String {
+        let code = self.code.name(self.version);
+        let reason = escape(&self.reason);
+        let fault = match self.version {
+            SoapVersion::V11 => {
+                let detail = self
+                    .detail
+                    .as_ref()
+                    .map(|detail| format!("<detail>{detail}</detail>"))
+                    .unwrap_or_default();
+                format!(
+                    "<soap:Fault><faultcode>soap:{code}</faultcode><faultstring>{reason}</faultstring>{detail}</soap:Fault>"
+                )
+            }
+            SoapVersion::V12 => {
+                let detail = self
+                    .detail
+                    .as_ref()
+                    .map(|detail| format!("<soap:Detail>{detail}</soap:Detail>"))
+                    .unwrap_or_default();
+                format!(
+                    r#"<soap:Fault><soap:Code><soap:Value>soap:{code}</soap:Value></soap:Code><soap:Reason><soap:Text xml:lang="en">{reason}</soap:Text></soap:Reason>{detail}</soap:Fault>"#
+                )
+            }
+        };
+        self.version.envelope(None, &fault)
+    }
+}
+
+impl Display for SoapFault {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code.name(self.version), self.reason)
+    }
+}
+
+impl std::error::Error for SoapFault {}
+
+impl ResponseError for SoapFault {
+    fn status(&self) -> StatusCode {
+        match (self.version, self.code) {
+            (SoapVersion::V12, SoapFaultCode::Client) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn as_response(&self) -> Response {
+        Response::builder()
+            .status(self.status())
+            .content_type(self.version.content_type())
+            .body(self.to_xml())
+    }
+}
+
+impl IntoResponse for SoapFault {
+    fn into_response(self) -> Response {
+        self.as_response()
+    }
+}
+
+/// SOAP extractor and response.
+///
+/// To extract the content of the body from the envelope, `T` must implement
+/// [`serde::Deserialize`], and it is deserialized with
+/// [`quick-xml`](https://crates.io/crates/quick-xml) from the first element in
+/// the body, the namespace prefixes of the element names are ignored.
+///
+/// The header entries are not processed, the raw XML of the header is
+/// available as [`Soap::header`].
+///
+/// # Errors
+///
+/// - [`ReadBodyError`](crate::error::ReadBodyError)
+/// - [`ParseSoapError`]
+///
+/// # Response
+///
+/// To serialize the content of the body, `T` must implement
+/// [`serde::Serialize`]. The envelope uses the version of the [`Soap::version`]
+/// field, use [`Soap::reply`] to respond with the version of the request.
+///
+/// ```
+/// use poem::{
+///     handler, http::header, post, test::TestClient, web::Soap, Endpoint, Request, Route,
+/// };
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize)]
+/// struct Add {
+///     a: i32,
+///     b: i32,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct AddResponse {
+///     result: i32,
+/// }
+///
+/// #[handler]
+/// async fn add(req: Soap<Add>) -> Soap<AddResponse> {
+///     req.reply(AddResponse {
+///         result: req.a + req.b,
+///     })
+/// }
+///
+/// let app = Route::new().at("/", post(add));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .post("/")
+///     .header(header::CONTENT_TYPE, "text/xml")
+///     .body(
+///         r#"<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+///             <soap:Body><m:Add xmlns:m="urn:calc"><a>1</a><b>2</b></m:Add></soap:Body>
+///         </soap:Envelope>"#,
+///     )
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text(
+///     r#"<?xml version="1.0" encoding="utf-8"?><soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/"><soap:Body><AddResponse><result>3</result></AddResponse></soap:Body></soap:Envelope>"#,
+/// )
+/// .await;
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "soap")))]
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct Soap<T> {
+    /// The SOAP version.
+    pub version: SoapVersion,
+    /// The SOAP action of the request.
+    pub action: Option<String>,
+    /// The raw XML of the header entries.
+    pub header: Option<String>,
+    /// The content of the body.
+    pub body: T,
+}
+
+impl<T> Soap<T> {
+    /// Create a SOAP 1.1 message with the content of the body.
+    pub fn new(body: T) -> Self {
+        Self {
+            version: SoapVersion::V11,
+            action: None,
+            header: None,
+            body,
+        }
+    }
+
+    /// Sets the SOAP version.
+    #[must_use]
+    pub fn with_version(self, version: SoapVersion) -> Self {
+        Self { version, ..self }
+    }
+
+    /// Sets the raw XML of the header entries.
+    #[must_use]
+    pub fn with_header(self, header: impl Into<String>) -> Self {
+        Self {
+            header: Some(header.into()),
+            ..self
+        }
+    }
+
+    /// Create a response message with the same SOAP version as this message.
+    pub fn reply<R>(&self, body: R) -> Soap<R> {
+        Soap::new(body).with_version(self.version)
+    }
+
+    /// Consumes this message to the content of the body.
+    pub fn into_inner(self) -> T {
+        self.body
+    }
+}
+
+impl<T> Deref for Soap<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.body
+    }
+}
+
+impl<T> DerefMut for Soap<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.body
+    }
+}
+
+impl<'a, T: DeserializeOwned> FromRequest<'a> for Soap<T> {
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        let (version, action) = request_version_and_action(req)?;
+        let data = String::from_request(req, body).await?;
+        let envelope = parse_envelope(version, &data)?;
+        let body = quick_xml::de::from_str(envelope.body)
+            .map_err(|err| ParseSoapError::Parse { version, err })?;
+        Ok(Self {
+            version,
+            action,
+            header: envelope.header.map(ToString::to_string),
+            body,
+        })
+    }
+}
+
+impl<T: Serialize + Send> IntoResponse for Soap<T> {
+    fn into_response(self) -> Response {
+        let body = match quick_xml::se::to_string(&self.body) {
+            Ok(body) => body,
+            Err(err) => {
+                return SoapFault::server(err.to_string())
+                    .with_version(self.version)
+                    .into_response()
+            }
+        };
+        Response::builder()
+            .content_type(self.version.content_type())
+            .body(self.version.envelope(self.header.as_deref(), &body))
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    future::Future,
+    io::Result as IoResult,
+    sync::{Arc, OnceLock},
+};
+
+use http::Extensions;
+
+use crate::{error::NotTlsConnectionError, FromRequest, Request, RequestBody, Result};
+
+/// A version of the TLS protocol.
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls")))
+)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum TlsVersion {
+    /// SSL 3.0
+    Ssl3,
+    /// TLS 1.0
+    Tls10,
+    /// TLS 1.1
+    Tls11,
+    /// TLS 1.2
+    Tls12,
+    /// TLS 1.3
+    Tls13,
+}
+
+impl Display for TlsVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TlsVersion::Ssl3 => "SSLv3",
+            TlsVersion::Tls10 => "TLSv1.0",
+            TlsVersion::Tls11 => "TLSv1.1",
+            TlsVersion::Tls12 => "TLSv1.2",
+            TlsVersion::Tls13 => "TLSv1.3",
+        })
+    }
+}
+
+/// The details of the TLS handshake of the connection which received the
+/// request.
+///
+/// It is available for the requests received by the TLS listeners, such as
+/// [`RustlsListener`](crate::listener::RustlsListener), and the extractor
+/// returns [`NotTlsConnectionError`] for the other requests.
+///
+/// The details which are not exposed by the TLS backend are `None`.
+/// [`NativeTlsListener`](crate::listener::NativeTlsListener) reports none of
+/// them, because `native-tls` exposes neither the version, the cipher suite
+/// nor the server name of a connection, and the listener does not negotiate
+/// ALPN.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     http::StatusCode,
+///     web::{TlsInfo, TlsVersion},
+///     Error, Result,
+/// };
+///
+/// #[handler]
+/// fn index(tls_info: &TlsInfo) -> Result<String> {
+///     if matches!(tls_info.version(), Some(version) if version < TlsVersion::Tls12) {
+///         return Err(Error::from_string(
+///             "TLS 1.2 or later is required",
+///             StatusCode::UPGRADE_REQUIRED,
+///         ));
+///     }
+///     Ok(format!("{:?}", tls_info.server_name()))
+/// }
+/// ```
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls")))
+)]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TlsInfo {
+    pub(crate) version: Option<TlsVersion>,
+    pub(crate) cipher_suite: Option<String>,
+    pub(crate) alpn_protocol: Option<Vec<u8>>,
+    pub(crate) server_name: Option<String>,
+}
+
+impl TlsInfo {
+    /// Returns the negotiated version of the TLS protocol.
+    #[inline]
+    pub fn version(&self) -> Option<TlsVersion> {
+        self.version
+    }
+
+    /// Returns the name of the negotiated cipher suite, as reported by the
+    /// TLS backend.
+    #[inline]
+    pub fn cipher_suite(&self) -> Option<&str> {
+        self.cipher_suite.as_deref()
+    }
+
+    /// Returns the protocol negotiated with ALPN, such as `h2`.
+    #[inline]
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
+    }
+
+    /// Returns the server name sent by the client with SNI.
+    #[inline]
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+}
+
+impl<'a> FromRequest<'a> for &'a TlsInfo {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req.tls_info().ok_or(NotTlsConnectionError)?)
+    }
+}
+
+/// The [`TlsInfo`] of a connection, which is added to the extensions of the
+/// connection when it is accepted and filled after the handshake.
+#[derive(Clone)]
+pub(crate) struct TlsInfoSlot(Arc<OnceLock<TlsInfo>>);
+
+impl TlsInfoSlot {
+    #[inline]
+    pub(crate) fn get(&self) -> Option<&TlsInfo> {
+        self.0.get()
+    }
+}
+
+/// Adds a [`TlsInfoSlot`] to the extensions, and returns the handshake future
+/// which fills it.
+pub(crate) fn with_tls_info<F, S>(
+    extensions: &mut Extensions,
+    handshake: F,
+    tls_info: fn(&S) -> TlsInfo,
+) -> impl Future<Output = IoResult<S>> + Send + 'static
+where
+    F: Future<Output = IoResult<S>> + Send + 'static,
+    S: Send + 'static,
+{
+    let slot = Arc::new(OnceLock::new());
+    extensions.insert(TlsInfoSlot(slot.clone()));
+    async move {
+        let stream = handshake.await?;
+        let _ = slot.set(tls_info(&stream));
+        Ok(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tls_info() {
+        let mut extensions = Extensions::new();
+        let fut = with_tls_info(
+            &mut extensions,
+            async { Ok(TlsVersion::Tls13) },
+            |version| TlsInfo {
+                version: Some(*version),
+                ..Default::default()
+            },
+        );
+        let slot = extensions.get::<TlsInfoSlot>().unwrap().clone();
+        assert!(slot.get().is_none());
+        fut.await.unwrap();
+
+        let mut req = Request::default();
+        req.extensions_mut().insert(slot);
+        let tls_info = <&TlsInfo>::from_request_without_body(&req).await.unwrap();
+        assert_eq!(tls_info.version(), Some(TlsVersion::Tls13));
+        assert_eq!(tls_info.server_name(), None);
+
+        assert!(<&TlsInfo>::from_request_without_body(&Request::default())
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn tls_version() {
+        assert!(TlsVersion::Tls11 < TlsVersion::Tls12);
+        assert_eq!(TlsVersion::Tls12.to_string(), "TLSv1.2");
+    }
+}
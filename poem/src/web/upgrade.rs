@@ -0,0 +1,272 @@
+use std::future::Future;
+
+use futures_util::{future::BoxFuture, FutureExt};
+use headers::HeaderMapExt;
+
+use crate::{
+    error::UpgradeError,
+    http::{
+        header::{self, HeaderName},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    Body, FromRequest, IntoResponse, OnUpgrade, Request, RequestBody, Response, Result, Upgraded,
+};
+
+/// An extractor that can upgrade the connection to an arbitrary protocol,
+/// such as a raw TCP tunnel.
+///
+/// The request must contain the `Connection: upgrade` header and the
+/// `Upgrade` header with the protocols offered by the client.
+///
+/// # Errors
+///
+/// - [`UpgradeError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{get, handler, http::StatusCode, web::Upgrade, Error, IntoResponse, Result, Route};
+///
+/// #[handler]
+/// fn index(upgrade: Upgrade) -> Result<impl IntoResponse> {
+///     if !upgrade.has_protocol("echo") {
+///         return Err(Error::from_status(StatusCode::BAD_REQUEST));
+///     }
+///     Ok(upgrade.on_upgrade("echo", |io| async move {
+///         let (mut reader, mut writer) = tokio::io::split(io);
+///         let _ = tokio::io::copy(&mut reader, &mut writer).await;
+///     }))
+/// }
+///
+/// let app = Route::new().at("/", get(index));
+/// ```
+pub struct Upgrade {
+    protocols: Vec<String>,
+    on_upgrade: OnUpgrade,
+}
+
+impl Upgrade {
+    fn internal_from_request(req: &Request) -> Result<Self, UpgradeError> {
+        let is_upgrade = req
+            .headers()
+            .typed_get::<headers::Connection>()
+            .is_some_and(|connection| connection.contains(header::UPGRADE));
+        let protocols = req
+            .headers()
+            .get_all(header::UPGRADE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|protocol| !protocol.is_empty())
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        if !is_upgrade || protocols.is_empty() {
+            return Err(UpgradeError::NotUpgradeRequest);
+        }
+
+        Ok(Self {
+            protocols,
+            on_upgrade: req.take_upgrade()?,
+        })
+    }
+}
+
+impl<'a> FromRequest<'a> for Upgrade {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Self::internal_from_request(req).map_err(Into::into)
+    }
+}
+
+impl Upgrade {
+    /// Returns the protocols offered by the client, in order of preference.
+    pub fn protocols(&self) -> impl Iterator<Item = &str> {
+        self.protocols.iter().map(String::as_str)
+    }
+
+    /// Returns `true` if the client offers the protocol, such as `tunnel` or
+    /// `h2c/1.0`.
+    pub fn has_protocol(&self, protocol: &str) -> bool {
+        self.protocols
+            .iter()
+            .any(|p| p.eq_ignore_ascii_case(protocol))
+    }
+
+    /// Finalize upgrading the connection to the protocol, and call the
+    /// provided `callback` with the upgraded connection after the
+    /// `101 Switching Protocols` response is sent.
+    ///
+    /// Note that the return value of this function must be returned from the
+    /// handler.
+    #[must_use]
+    pub fn on_upgrade<F, Fut>(self, protocol: impl Into<String>, callback: F) -> UpgradeResponse<F>
+    where
+        F: FnOnce(Upgraded) -> Fut + Send + Sync + 'static,
+        Fut: Future + Send + 'static,
+    {
+        UpgradeResponse {
+            on_upgrade: self.on_upgrade,
+            protocol: protocol.into(),
+            headers: HeaderMap::new(),
+            callback,
+        }
+    }
+}
+
+/// A response returned from `Upgrade::on_upgrade`.
+pub struct UpgradeResponse<F> {
+    on_upgrade: OnUpgrade,
+    protocol: String,
+    headers: HeaderMap,
+    callback: F,
+}
+
+type BoxUpgradeHandler =
+    Box<dyn FnOnce(Upgraded) -> BoxFuture<'static, ()> + Send + Sync + 'static>;
+
+/// An owned dynamically typed UpgradeResponse for use in cases where you
+/// can’t statically type your result or need to add some indirection.
+pub type BoxUpgradeResponse = UpgradeResponse<BoxUpgradeHandler>;
+
+impl<F, Fut> UpgradeResponse<F>
+where
+    F: FnOnce(Upgraded) -> Fut + Send + Sync + 'static,
+    Fut: Future + Send + 'static,
+{
+    /// Appends a header to the `101 Switching Protocols` response.
+    #[must_use]
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: TryInto<HeaderName>,
+        V: TryInto<HeaderValue>,
+    {
+        if let (Ok(key), Ok(value)) = (key.try_into(), value.try_into()) {
+            self.headers.append(key, value);
+        }
+        self
+    }
+
+    /// Create an owned dynamically typed UpgradeResponse
+    pub fn boxed(self) -> BoxUpgradeResponse {
+        UpgradeResponse {
+            on_upgrade: self.on_upgrade,
+            protocol: self.protocol,
+            headers: self.headers,
+            callback: Box::new(|upgraded| (self.callback)(upgraded).map(|_| ()).boxed()),
+        }
+    }
+}
+
+impl<F, Fut> IntoResponse for UpgradeResponse<F>
+where
+    F: FnOnce(Upgraded) -> Fut + Send + Sync + 'static,
+    Fut: Future + Send + 'static,
+{
+    fn into_response(self) -> Response {
+        let Ok(protocol) = HeaderValue::try_from(self.protocol) else {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        };
+
+        let mut resp = Response::builder()
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .header(header::CONNECTION, "upgrade")
+            .header(header::UPGRADE, protocol)
+            .body(Body::empty());
+        resp.headers_mut().extend(self.headers);
+
+        crate::spawn::spawn("poem-upgrade", async move {
+            if let Ok(upgraded) = self.on_upgrade.await {
+                (self.callback)(upgraded).await;
+            }
+        });
+
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::{
+        handler,
+        listener::{Acceptor, Listener, TcpListener},
+        test::TestClient,
+        Server,
+    };
+
+    #[handler(internal)]
+    async fn echo(upgrade: Upgrade) -> Result<BoxUpgradeResponse> {
+        if !upgrade.has_protocol("echo") {
+            return Err(UpgradeError::Other("unsupported protocol".to_string()).into());
+        }
+        Ok(upgrade
+            .on_upgrade("echo", |io| async move {
+                let (mut reader, mut writer) = tokio::io::split(io);
+                let _ = tokio::io::copy(&mut reader, &mut writer).await;
+            })
+            .header("x-echo", "1")
+            .boxed())
+    }
+
+    #[tokio::test]
+    async fn upgrade() {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor
+            .local_addr()
+            .remove(0)
+            .as_socket_addr()
+            .cloned()
+            .unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(echo));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"GET / HTTP/1.1\r\nhost: a\r\nconnection: upgrade\r\nupgrade: foo, echo\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut buf = [0; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        let resp = String::from_utf8_lossy(&buf[..n]);
+        assert!(resp.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(resp.contains("upgrade: echo\r\n"));
+        assert!(resp.contains("x-echo: 1\r\n"));
+        assert!(resp.ends_with("\r\n\r\n"));
+
+        stream.write_all(b"hello").await.unwrap();
+        let mut data = [0; 5];
+        stream.read_exact(&mut data).await.unwrap();
+        assert_eq!(&data, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_client() {
+        let cli = TestClient::new(echo);
+        let (resp, mut io) = cli.get("/").upgrade("echo").await;
+        resp.assert_header("x-echo", "1");
+        io.write_all(b"hello").await.unwrap();
+        let mut data = [0; 5];
+        io.read_exact(&mut data).await.unwrap();
+        assert_eq!(&data, b"hello");
+
+        cli.get("/")
+            .header(header::CONNECTION, "upgrade")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+}
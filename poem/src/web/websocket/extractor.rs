@@ -202,7 +202,7 @@ where
 
         let resp = builder.body(Body::empty());
 
-        tokio::spawn(async move {
+        crate::spawn::spawn("poem-websocket", async move {
             let upgraded = match self.websocket.on_upgrade.await {
                 Ok(upgraded) => upgraded,
                 Err(_) => return,
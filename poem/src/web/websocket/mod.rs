@@ -24,13 +24,17 @@
 
 mod extractor;
 mod message;
+mod mqtt;
 mod stream;
 mod utils;
 
 pub use extractor::{BoxWebSocketUpgraded, WebSocket, WebSocketUpgraded};
 pub use message::{CloseCode, Message};
+pub use mqtt::MqttBridge;
 pub use stream::WebSocketStream;
 pub use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+#[cfg(feature = "test")]
+pub(crate) use utils::sign;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,287 @@
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+
+use bytes::{Buf, BytesMut};
+use futures_util::{SinkExt, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::{CloseCode, Message, WebSocket, WebSocketStream};
+use crate::{
+    error::WebSocketError, http::header, Endpoint, FromRequest, IntoResponse, Request, Response,
+    Result,
+};
+
+/// The WebSocket subprotocol of MQTT.
+const MQTT_PROTOCOL: &str = "mqtt";
+
+/// A bridge between the MQTT over WebSocket clients and a MQTT broker, for
+/// the IoT dashboards in the browsers.
+///
+/// Every connection which negotiates the `mqtt` subprotocol is connected to
+/// the broker over TCP. The binary messages from the client are forwarded to
+/// the broker as they are, because the MQTT packets may span several
+/// messages. The bytes from the broker are split at the packet boundaries,
+/// and each MQTT packet is sent as one binary message.
+///
+/// It can be used as an endpoint, or with the [`WebSocket`] extractor by
+/// calling [`MqttBridge::bridge`].
+///
+/// # Example
+///
+/// ```
+/// use poem::{get, web::websocket::MqttBridge, Route};
+///
+/// let app = Route::new().at("/mqtt", get(MqttBridge::new("127.0.0.1:1883")));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MqttBridge {
+    addr: String,
+    max_packet_size: usize,
+}
+
+impl MqttBridge {
+    /// Create a `MqttBridge` to the broker address, such as
+    /// `127.0.0.1:1883`.
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            max_packet_size: 1024 * 1024,
+        }
+    }
+
+    /// Sets the maximum size of the MQTT packets from the broker, and the
+    /// connection is closed if it is exceeded.
+    ///
+    /// Default is `1MiB`.
+    #[must_use]
+    pub fn max_packet_size(self, max_packet_size: usize) -> Self {
+        Self {
+            max_packet_size,
+            ..self
+        }
+    }
+
+    /// Connects to the broker, and bridges the WebSocket connection to it
+    /// until either side is closed.
+    pub async fn bridge(&self, socket: WebSocketStream) -> IoResult<()> {
+        let broker = TcpStream::connect(&self.addr).await?;
+        broker.set_nodelay(true)?;
+        self.bridge_with(socket, broker).await
+    }
+
+    /// Bridges the WebSocket connection to a connected broker until either
+    /// side is closed.
+    pub async fn bridge_with<T>(&self, mut socket: WebSocketStream, broker: T) -> IoResult<()>
+    where
+        T: AsyncRead + AsyncWrite + Send + Unpin,
+    {
+        let (mut reader, mut writer) = tokio::io::split(broker);
+        let mut buf = BytesMut::new();
+
+        loop {
+            tokio::select! {
+                msg = socket.next() => match msg.transpose()? {
+                    Some(Message::Binary(data)) => writer.write_all(&data).await?,
+                    Some(Message::Text(_)) => {
+                        let _ = socket
+                            .send(Message::close_with(
+                                CloseCode::Unsupported,
+                                "MQTT packets must be sent in binary messages",
+                            ))
+                            .await;
+                        return Ok(());
+                    }
+                    Some(Message::Close(_)) | None => return writer.shutdown().await,
+                    Some(Message::Ping(_) | Message::Pong(_)) => {}
+                },
+                res = reader.read_buf(&mut buf) => {
+                    if res? == 0 {
+                        let _ = socket.close().await;
+                        return Ok(());
+                    }
+                    loop {
+                        match packet_len(&buf) {
+                            Ok(Some(len)) if len > self.max_packet_size => {
+                                let _ = socket
+                                    .send(Message::close_with(CloseCode::Size, "packet too large"))
+                                    .await;
+                                return Err(IoError::new(
+                                    ErrorKind::InvalidData,
+                                    "MQTT packet too large",
+                                ));
+                            }
+                            Ok(Some(len)) if len <= buf.len() => {
+                                socket.feed(Message::binary(buf.split_to(len).to_vec())).await?;
+                            }
+                            Ok(_) => break,
+                            Err(err) => {
+                                let _ = socket.close().await;
+                                return Err(err);
+                            }
+                        }
+                    }
+                    socket.flush().await?;
+                }
+            }
+        }
+    }
+}
+
+/// Returns the length of the MQTT packet at the beginning of the buffer, or
+/// `None` if the fixed header is incomplete.
+fn packet_len(mut buf: &[u8]) -> IoResult<Option<usize>> {
+    if buf.is_empty() {
+        return Ok(None);
+    }
+    buf.advance(1);
+
+    // the remaining length is encoded in at most four bytes
+    let mut remaining = 0;
+    for i in 0..4 {
+        let Some(&byte) = buf.get(i) else {
+            return Ok(None);
+        };
+        remaining |= usize::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(Some(1 + (i + 1) + remaining));
+        }
+    }
+    Err(IoError::new(
+        ErrorKind::InvalidData,
+        "invalid MQTT remaining length",
+    ))
+}
+
+impl Endpoint for MqttBridge {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let offers_mqtt = req
+            .headers()
+            .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|protocol| protocol.trim() == MQTT_PROTOCOL);
+        if !offers_mqtt {
+            return Err(WebSocketError::InvalidProtocol.into());
+        }
+
+        let ws = WebSocket::from_request_without_body(&req).await?;
+        let bridge = self.clone();
+        Ok(ws
+            .protocols([MQTT_PROTOCOL])
+            .on_upgrade(move |socket| async move {
+                if let Err(err) = bridge.bridge(socket).await {
+                    tracing::debug!(error = %err, "mqtt bridge closed");
+                }
+            })
+            .into_response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        get,
+        http::{HeaderValue, StatusCode},
+        test::TestClient,
+        Route,
+    };
+
+    #[test]
+    fn packet_length() {
+        assert_eq!(packet_len(&[]).unwrap(), None);
+        assert_eq!(packet_len(&[0xc0]).unwrap(), None);
+        assert_eq!(packet_len(&[0xc0, 0x00]).unwrap(), Some(2));
+        assert_eq!(packet_len(&[0x30, 0x80]).unwrap(), None);
+        assert_eq!(packet_len(&[0x30, 0x80, 0x01]).unwrap(), Some(3 + 128));
+        assert_eq!(
+            packet_len(&[0x30, 0xff, 0xff, 0xff, 0x7f]).unwrap(),
+            Some(5 + 268_435_455)
+        );
+        assert!(packet_len(&[0x30, 0xff, 0xff, 0xff, 0xff]).is_err());
+    }
+
+    /// Starts a broker which echoes the bytes it receives.
+    async fn echo_broker() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let (mut reader, mut writer) = tokio::io::split(stream);
+                    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                });
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn bridge() {
+        let addr = echo_broker().await;
+        let cli = TestClient::new(Route::new().at("/mqtt", get(MqttBridge::new(addr))));
+
+        let mut ws = cli
+            .get("/mqtt")
+            .header(header::SEC_WEBSOCKET_PROTOCOL, "mqttv3.1, mqtt")
+            .websocket()
+            .await
+            .timeout(Duration::from_secs(5));
+        assert_eq!(
+            ws.headers().get(header::SEC_WEBSOCKET_PROTOCOL),
+            Some(&HeaderValue::from_static("mqtt"))
+        );
+
+        // two packets in one message
+        ws.send_binary(vec![0xc0, 0x00, 0x30, 0x03, 0x00, 0x01, b'a'])
+            .await;
+        ws.assert_binary(vec![0xc0, 0x00]).await;
+        ws.assert_binary(vec![0x30, 0x03, 0x00, 0x01, b'a']).await;
+
+        // one packet in two messages
+        ws.send_binary(vec![0x30, 0x03]).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        ws.send_binary(vec![0x00, 0x01, b'b']).await;
+        ws.assert_binary(vec![0x30, 0x03, 0x00, 0x01, b'b']).await;
+
+        ws.send_text("hello").await;
+        ws.assert_closed().await;
+    }
+
+    #[tokio::test]
+    async fn max_packet_size() {
+        let addr = echo_broker().await;
+        let cli = TestClient::new(MqttBridge::new(addr).max_packet_size(4));
+
+        let mut ws = cli
+            .get("/")
+            .header(header::SEC_WEBSOCKET_PROTOCOL, "mqtt")
+            .websocket()
+            .await
+            .timeout(Duration::from_secs(5));
+        ws.send_binary(vec![0x30, 0x03, 0x00, 0x01, b'a']).await;
+        ws.assert_message(Message::close_with(CloseCode::Size, "packet too large"))
+            .await;
+    }
+
+    #[tokio::test]
+    async fn requires_mqtt_protocol() {
+        let cli = TestClient::new(MqttBridge::new("127.0.0.1:1883"));
+        cli.get("/")
+            .header(header::CONNECTION, "upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==")
+            .header(header::SEC_WEBSOCKET_PROTOCOL, "graphql-ws")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+}